@@ -0,0 +1,168 @@
+use rand::Rng;
+
+use crate::create_random_rgb;
+
+/// Generations a cell has to survive to reach the oldest color of a ramp
+pub const AGE_RAMP_LENGTH: u16 = 32;
+
+// Color of every live cell in the monochrome scheme
+const MONOCHROME_R_G_B: [u8; 3] = [0, 0, 0];
+
+/// How live cells are colored. Ramps fade from a bright color for newborn cells to a dark
+/// one for cells alive `AGE_RAMP_LENGTH` generations or more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    /// A fresh random color per cell every frame
+    Random,
+    #[default]
+    Fire,
+    Ocean,
+    Grey,
+    /// A hue picked when the cell is born and kept until it dies
+    Rainbow,
+    Monochrome,
+}
+
+impl ColorScheme {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ColorScheme::Random),
+            1 => Some(ColorScheme::Fire),
+            2 => Some(ColorScheme::Ocean),
+            3 => Some(ColorScheme::Grey),
+            4 => Some(ColorScheme::Rainbow),
+            5 => Some(ColorScheme::Monochrome),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            ColorScheme::Random => 0,
+            ColorScheme::Fire => 1,
+            ColorScheme::Ocean => 2,
+            ColorScheme::Grey => 3,
+            ColorScheme::Rainbow => 4,
+            ColorScheme::Monochrome => 5,
+        }
+    }
+
+    /// Color of the live cell at (x, y) that has survived `age` generations by
+    /// `generation`
+    pub fn live_cell_rgb<R: Rng + ?Sized>(
+        &self,
+        (x, y): (u16, u16),
+        age: u16,
+        generation: u64,
+        rng: &mut R,
+    ) -> [u8; 3] {
+        match self {
+            ColorScheme::Random => create_random_rgb(rng),
+            ColorScheme::Fire => ramp_rgb([255, 220, 60], [120, 10, 10], age),
+            ColorScheme::Ocean => ramp_rgb([120, 240, 255], [10, 30, 110], age),
+            ColorScheme::Grey => ramp_rgb([190, 190, 190], [0, 0, 0], age),
+            // Where and when the cell was born stay the same for its whole life, so its hue
+            // does too without being stored
+            ColorScheme::Rainbow => {
+                let born = generation.wrapping_sub(age as u64);
+                let hash = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                    ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                    ^ born.wrapping_mul(0x1656_67B1_9E37_79F9);
+                hue_rgb((hash >> 32) as u16 % 360)
+            }
+            ColorScheme::Monochrome => MONOCHROME_R_G_B,
+        }
+    }
+
+    /// Whether a cell alive `age` generations is drawn differently than a generation
+    /// earlier
+    pub fn recolors_at(&self, age: u16) -> bool {
+        match self {
+            ColorScheme::Random => true,
+            ColorScheme::Fire | ColorScheme::Ocean | ColorScheme::Grey => age <= AGE_RAMP_LENGTH,
+            ColorScheme::Rainbow | ColorScheme::Monochrome => false,
+        }
+    }
+}
+
+// Blend from the newborn color to the oldest one as the cell ages
+fn ramp_rgb(young: [u8; 3], old: [u8; 3], age: u16) -> [u8; 3] {
+    let t = age.min(AGE_RAMP_LENGTH) as u32;
+    let span = AGE_RAMP_LENGTH as u32;
+    core::array::from_fn(|i| {
+        let (young, old) = (young[i] as u32, old[i] as u32);
+        ((young * (span - t) + old * t) / span) as u8
+    })
+}
+
+/// Fully saturated color of a hue in degrees
+pub fn hue_rgb(hue: u16) -> [u8; 3] {
+    let rising = ((hue % 60) as u32 * 255 / 60) as u8;
+    let falling = 255 - rising;
+    match hue / 60 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameOfLifeVecs;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn ramps_darken_with_age() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut grey = |age| ColorScheme::Grey.live_cell_rgb((0, 0), age, 100, &mut rng);
+        assert_eq!(grey(0), [190, 190, 190]);
+        assert_eq!(grey(AGE_RAMP_LENGTH / 2), [95, 95, 95]);
+        assert_eq!(grey(AGE_RAMP_LENGTH), [0, 0, 0]);
+        assert_eq!(grey(u16::MAX), [0, 0, 0]);
+
+        for id in 0..6 {
+            assert_eq!(ColorScheme::from_id(id).unwrap().id(), id);
+        }
+        assert_eq!(ColorScheme::from_id(6), None);
+    }
+
+    #[test]
+    fn rainbow_colors_last_a_lifetime() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let rainbow = |cell, age, generation, rng: &mut StdRng| {
+            ColorScheme::Rainbow.live_cell_rgb(cell, age, generation, rng)
+        };
+        let born = rainbow((3, 4), 0, 10, &mut rng);
+        assert_eq!(rainbow((3, 4), 5, 15, &mut rng), born);
+        assert_ne!(rainbow((4, 3), 0, 10, &mut rng), born);
+        assert_eq!(hue_rgb(0), [255, 0, 0]);
+        assert_eq!(hue_rgb(120), [0, 255, 0]);
+    }
+
+    #[test]
+    fn survivors_age_and_newborns_start_young() {
+        let mut game = GameOfLifeVecs::new(5, 5, &mut StdRng::seed_from_u64(1));
+        game.initialize_blinker();
+        game.step();
+        game.step();
+        // The blinker's center never dies, its ends are reborn every generation
+        assert_eq!(game.ages[2][2], 2);
+        assert_eq!(game.ages[2][1], 0);
+        assert_eq!(game.ages[1][2], 0);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let frame = game.to_rgb_data(ColorScheme::Grey, &mut rng);
+        let center = (2 * 5 + 2) * 3;
+        assert_eq!(
+            frame[center..center + 3],
+            ColorScheme::Grey.live_cell_rgb((2, 2), 2, 2, &mut rng)
+        );
+
+        game.kill_cell_in(2, 2);
+        assert_eq!(game.ages[2][2], 0);
+    }
+}
@@ -0,0 +1,104 @@
+//! zlib-wrapped DEFLATE (RFC 1950/1951) for message payloads, the format browsers read
+//! with `DecompressionStream("deflate")`. Both directions go through `miniz_oxide`, and
+//! inflating stops at a caller's limit since the input comes from the network.
+
+use alloc::vec::Vec;
+
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::protocol::ProtocolError;
+
+/// Codecs a client can decode, advertised as a bitmask in HELLO
+pub const CODEC_DEFLATE: u8 = 1 << 0;
+/// Not a compression: the client verifies payload checksums, see `FLAG_CRC32`
+pub const CODEC_CRC32: u8 = 1 << 1;
+/// Every codec the server can encode with
+pub const SUPPORTED_CODECS: u8 = CODEC_DEFLATE | CODEC_CRC32;
+/// Header flags bit set when the payload is compressed with `deflate`
+pub const FLAG_DEFLATE: u8 = 1 << 1;
+
+// Frames are compressed once per tick, the fastest level already folds their runs of dead
+// cells and repeated colors
+const COMPRESSION_LEVEL: u8 = 1;
+
+/// Compresses `data` into a zlib stream
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(data, COMPRESSION_LEVEL)
+}
+
+/// Decompresses a zlib stream, refusing one that inflates past `max_output` bytes
+pub fn inflate(data: &[u8], max_output: usize) -> Result<Vec<u8>, ProtocolError> {
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, max_output).map_err(|error| {
+        let reason = match error.status {
+            TINFLStatus::HasMoreOutput => {
+                return ProtocolError::InflatedTooLarge {
+                    max_length: max_output,
+                };
+            }
+            TINFLStatus::Adler32Mismatch => "checksum mismatch",
+            TINFLStatus::FailedCannotMakeProgress | TINFLStatus::NeedsMoreInput => {
+                "stream ends early"
+            }
+            _ => "malformed stream",
+        };
+        ProtocolError::InvalidDeflate { reason }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn deflate_round_trips() {
+        let mut frame = vec![0, 100, 0, 100];
+        for i in 0..100 * 100 {
+            let rgb = if i % 37 == 0 {
+                [i as u8, 20, 200]
+            } else {
+                [255; 3]
+            };
+            frame.extend_from_slice(&rgb);
+        }
+        let compressed = deflate(&frame);
+        assert!(compressed.len() < frame.len() / 10);
+        assert_eq!(inflate(&compressed, frame.len()).unwrap(), frame);
+
+        for data in [&b""[..], b"a", b"abcabcabcabcabcabcabd", &[7; 1000]] {
+            assert_eq!(inflate(&deflate(data), data.len()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn inflate_reads_stored_blocks_and_rejects_corruption() {
+        // zlib.compress(b"hi", level=0)
+        let stored = [
+            0x78, 0x01, 0x01, 0x02, 0x00, 0xFD, 0xFF, b'h', b'i', 0x01, 0x3B, 0x00, 0xD2,
+        ];
+        assert_eq!(inflate(&stored, 2).unwrap(), b"hi");
+
+        let mut corrupted = deflate(b"hello hello hello");
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(inflate(&corrupted, 100).is_err());
+        assert!(inflate(&[0x78], 100).is_err());
+    }
+
+    #[test]
+    fn inflate_stops_at_the_output_limit() {
+        // Well under the wire limit, but it would inflate to 16MB
+        let bomb = deflate(&vec![0; 16 * 1024 * 1024]);
+        assert!(bomb.len() < 128 * 1024);
+        assert_eq!(
+            inflate(&bomb, 1024 * 1024),
+            Err(ProtocolError::InflatedTooLarge {
+                max_length: 1024 * 1024
+            })
+        );
+        assert_eq!(
+            inflate(&deflate(&[7; 1000]), 999).unwrap_err().to_string(),
+            "Deflated payload inflates past 999 bytes"
+        );
+    }
+}
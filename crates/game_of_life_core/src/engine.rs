@@ -0,0 +1,569 @@
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+use rand::RngCore;
+use tracing::debug;
+
+use crate::{
+    ColorScheme, DEAD_CELL_R_G_B, GameOfLifeBits, GameOfLifeSparse, GameOfLifeVecs,
+    region::{Region, include_cell},
+};
+
+// Densities the adaptive engine moves between the sparse and dense engines at, apart so a
+// board hovering around one of them doesn't migrate back and forth every generation
+const SPARSE_BELOW_DENSITY: f64 = 0.01;
+const DENSE_ABOVE_DENSITY: f64 = 0.04;
+
+/// A Game of Life board and the way it steps. Every engine steps the same rule on the same
+/// bounded grid, they only differ in how fast they get there and in how much they keep:
+/// only `GameOfLifeVecs` tracks the ages cells are colored by, the others draw every live
+/// cell as newborn.
+pub trait GolEngine: Send + Sync {
+    fn kind(&self) -> EngineKind;
+    fn dimensions(&self) -> (u16, u16);
+    fn generation_count(&self) -> u64;
+    /// Carries the generation number over to a board loaded from elsewhere
+    fn set_generation_count(&mut self, generation: u64);
+    fn step(&mut self);
+    fn is_alive(&self, x: u16, y: u16) -> bool;
+    fn set_cell(&mut self, x: u16, y: u16, alive: bool);
+    fn population(&self) -> usize;
+    /// Row-major live/dead bits, most significant bit first, last byte zero padded
+    fn to_packed_bits(&self) -> Vec<u8>;
+    /// Replaces the board with bits packed as by `to_packed_bits`, every cell starting over
+    /// as newborn
+    fn load_packed_bits(&mut self, bits: &[u8]);
+    fn boxed_clone(&self) -> Box<dyn GolEngine>;
+
+    /// Coordinates of every live cell, row by row
+    fn live_cells(&self) -> Vec<(u16, u16)> {
+        let (width, height) = self.dimensions();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_alive(x, y))
+            .collect()
+    }
+
+    /// Cells the last step brought to life or killed, row by row. Only meaningful right
+    /// after a step.
+    fn changed_cells(&self) -> Vec<(u16, u16)>;
+
+    /// Bounding box of the cells drawn differently by `scheme` than before the last step,
+    /// None if nothing changed. Only meaningful right after a step.
+    fn changed_region(&self, scheme: ColorScheme) -> Option<Region> {
+        let mut region = None;
+        for (x, y) in self.changed_cells() {
+            include_cell(&mut region, x, y);
+        }
+        redrawn_survivors(self, region, scheme)
+    }
+
+    /// Color of the cell at (x, y), live cells colored by `scheme` as newborn
+    fn cell_rgb(&self, scheme: ColorScheme, x: u16, y: u16, rng: &mut dyn RngCore) -> [u8; 3] {
+        if !self.is_alive(x, y) {
+            return DEAD_CELL_R_G_B;
+        }
+        // Born at generation 0, so a rainbow hue only depends on where the cell is and
+        // doesn't move every generation
+        scheme.live_cell_rgb((x, y), 0, 0, rng)
+    }
+
+    /// Every cell colored as by `cell_rgb`, row by row
+    fn to_rgb_data(&self, scheme: ColorScheme, rng: &mut dyn RngCore) -> Vec<u8> {
+        let width = self.dimensions().0 as usize;
+        let mut frame_data = DEAD_CELL_R_G_B.repeat(width * self.dimensions().1 as usize);
+        for (x, y) in self.live_cells() {
+            let i = (y as usize * width + x as usize) * 3;
+            frame_data[i..i + 3].copy_from_slice(&self.cell_rgb(scheme, x, y, rng));
+        }
+        frame_data
+    }
+}
+
+impl Clone for Box<dyn GolEngine> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+// Grows the region of the cells a step flipped over every live cell when `scheme` draws
+// newborn cells anew each frame, survivors are redrawn then too
+fn redrawn_survivors<E: GolEngine + ?Sized>(
+    engine: &E,
+    mut region: Option<Region>,
+    scheme: ColorScheme,
+) -> Option<Region> {
+    if scheme == ColorScheme::Random {
+        for (x, y) in engine.live_cells() {
+            include_cell(&mut region, x, y);
+        }
+    }
+    region
+}
+
+/// Engines a board can be stepped with. There's no hashlife engine: it gets its speed from
+/// jumping many generations at once across an unbounded plane, while rooms step a bounded
+/// grid one broadcast generation at a time. `Sparse` covers the huge, mostly empty boards
+/// it would otherwise suit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EngineKind {
+    // `GameOfLifeVecs`, a bool per cell, rows stepped on the rayon thread pool
+    #[default]
+    Vecs,
+    // `GameOfLifeBits`, 64 cells per word, stepped a word at a time
+    Bits,
+    // `GameOfLifeBits` on NEON or AVX2 when the CPU has them, Bits otherwise
+    Simd,
+    // `GameOfLifeBits` with rows on the rayon thread pool, Bits without `parallel`
+    BitsParallel,
+    // `GameOfLifeSparse`, only the live cells, for huge boards that are mostly empty
+    Sparse,
+    // Sparse while the board is mostly empty, Simd once it fills up, and back
+    Adaptive,
+}
+
+impl EngineKind {
+    pub const ALL: [EngineKind; 6] = [
+        EngineKind::Vecs,
+        EngineKind::Bits,
+        EngineKind::Simd,
+        EngineKind::BitsParallel,
+        EngineKind::Sparse,
+        EngineKind::Adaptive,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            EngineKind::Vecs => 0,
+            EngineKind::Bits => 1,
+            EngineKind::Simd => 2,
+            EngineKind::BitsParallel => 3,
+            EngineKind::Sparse => 4,
+            EngineKind::Adaptive => 5,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EngineKind::Vecs => "vecs",
+            EngineKind::Bits => "bits",
+            EngineKind::Simd => "simd",
+            EngineKind::BitsParallel => "bits-parallel",
+            EngineKind::Sparse => "sparse",
+            EngineKind::Adaptive => "auto",
+        }
+    }
+
+    /// An engine of this kind with every cell dead
+    pub fn create(&self, width: u16, height: u16) -> Box<dyn GolEngine> {
+        match self {
+            EngineKind::Vecs => Box::new(GameOfLifeVecs::empty(width, height)),
+            EngineKind::Sparse => Box::new(GameOfLifeSparse::empty(width, height)),
+            EngineKind::Adaptive => Box::new(AdaptiveEngine {
+                inner: Box::new(GameOfLifeSparse::empty(width, height)),
+                generation_count: 0,
+                migrated_from: None,
+            }),
+            kind => Box::new(BitsEngine {
+                game: GameOfLifeBits::empty(width, height),
+                kind: *kind,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for EngineKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EngineKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown engine {:?}, expected vecs, bits, simd, bits-parallel, sparse or auto",
+                    name
+                )
+            })
+    }
+}
+
+impl GolEngine for GameOfLifeVecs {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Vecs
+    }
+
+    fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn generation_count(&self) -> u64 {
+        self.generation_count
+    }
+
+    fn set_generation_count(&mut self, generation: u64) {
+        self.generation_count = generation;
+    }
+
+    fn step(&mut self) {
+        GameOfLifeVecs::step(self);
+    }
+
+    fn is_alive(&self, x: u16, y: u16) -> bool {
+        self.current_generation[y as usize][x as usize]
+    }
+
+    fn set_cell(&mut self, x: u16, y: u16, alive: bool) {
+        if alive {
+            self.awaken_cell_in(x, y);
+        } else {
+            self.kill_cell_in(x, y);
+        }
+    }
+
+    fn population(&self) -> usize {
+        GameOfLifeVecs::population(self)
+    }
+
+    fn to_packed_bits(&self) -> Vec<u8> {
+        GameOfLifeVecs::to_packed_bits(self)
+    }
+
+    fn load_packed_bits(&mut self, bits: &[u8]) {
+        GameOfLifeVecs::load_packed_bits(self, bits);
+    }
+
+    fn boxed_clone(&self) -> Box<dyn GolEngine> {
+        Box::new(self.clone())
+    }
+
+    fn changed_cells(&self) -> Vec<(u16, u16)> {
+        GameOfLifeVecs::changed_cells(self)
+    }
+
+    fn changed_region(&self, scheme: ColorScheme) -> Option<Region> {
+        GameOfLifeVecs::changed_region(self, scheme)
+    }
+
+    fn cell_rgb(&self, scheme: ColorScheme, x: u16, y: u16, rng: &mut dyn RngCore) -> [u8; 3] {
+        GameOfLifeVecs::cell_rgb(self, scheme, x, y, rng)
+    }
+
+    fn to_rgb_data(&self, scheme: ColorScheme, rng: &mut dyn RngCore) -> Vec<u8> {
+        GameOfLifeVecs::to_rgb_data(self, scheme, rng)
+    }
+}
+
+/// `GameOfLifeBits` stepped the way its kind asks for
+#[derive(Clone)]
+struct BitsEngine {
+    game: GameOfLifeBits,
+    kind: EngineKind,
+}
+
+impl GolEngine for BitsEngine {
+    fn kind(&self) -> EngineKind {
+        self.kind
+    }
+
+    fn dimensions(&self) -> (u16, u16) {
+        (self.game.width, self.game.height)
+    }
+
+    fn generation_count(&self) -> u64 {
+        self.game.generation_count
+    }
+
+    fn set_generation_count(&mut self, generation: u64) {
+        self.game.generation_count = generation;
+    }
+
+    fn step(&mut self) {
+        match self.kind {
+            EngineKind::Simd => self.game.step(),
+            #[cfg(feature = "parallel")]
+            EngineKind::BitsParallel => self.game.step_parallel(),
+            _ => {
+                self.game.step_fallback();
+                self.game.generation_count += 1;
+            }
+        }
+    }
+
+    fn is_alive(&self, x: u16, y: u16) -> bool {
+        self.game.get_cell(x as usize, y as usize)
+    }
+
+    fn set_cell(&mut self, x: u16, y: u16, alive: bool) {
+        self.game.set_cell(x as usize, y as usize, alive);
+    }
+
+    fn population(&self) -> usize {
+        self.game.population_count() as usize
+    }
+
+    fn to_packed_bits(&self) -> Vec<u8> {
+        self.game.to_packed_bits()
+    }
+
+    fn load_packed_bits(&mut self, bits: &[u8]) {
+        self.game.load_packed_bits(bits);
+    }
+
+    fn boxed_clone(&self) -> Box<dyn GolEngine> {
+        Box::new(self.clone())
+    }
+
+    fn live_cells(&self) -> Vec<(u16, u16)> {
+        self.game.live_cells()
+    }
+
+    fn changed_cells(&self) -> Vec<(u16, u16)> {
+        self.game.changed_cells()
+    }
+
+    fn changed_region(&self, scheme: ColorScheme) -> Option<Region> {
+        redrawn_survivors(self, self.game.changed_region(), scheme)
+    }
+}
+
+impl GolEngine for GameOfLifeSparse {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Sparse
+    }
+
+    fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn generation_count(&self) -> u64 {
+        self.generation_count
+    }
+
+    fn set_generation_count(&mut self, generation: u64) {
+        self.generation_count = generation;
+    }
+
+    fn step(&mut self) {
+        GameOfLifeSparse::step(self);
+    }
+
+    fn is_alive(&self, x: u16, y: u16) -> bool {
+        GameOfLifeSparse::is_alive(self, x, y)
+    }
+
+    fn set_cell(&mut self, x: u16, y: u16, alive: bool) {
+        GameOfLifeSparse::set_cell(self, x, y, alive);
+    }
+
+    fn population(&self) -> usize {
+        GameOfLifeSparse::population(self)
+    }
+
+    fn to_packed_bits(&self) -> Vec<u8> {
+        GameOfLifeSparse::to_packed_bits(self)
+    }
+
+    fn load_packed_bits(&mut self, bits: &[u8]) {
+        GameOfLifeSparse::load_packed_bits(self, bits);
+    }
+
+    fn boxed_clone(&self) -> Box<dyn GolEngine> {
+        Box::new(self.clone())
+    }
+
+    fn live_cells(&self) -> Vec<(u16, u16)> {
+        self.live.iter().map(|&(y, x)| (x, y)).collect()
+    }
+
+    fn changed_cells(&self) -> Vec<(u16, u16)> {
+        GameOfLifeSparse::changed_cells(self)
+    }
+}
+
+/// The sparse engine while the board is mostly empty, the SIMD bits engine once it fills
+/// up. Density is checked after every step and load.
+#[derive(Clone)]
+struct AdaptiveEngine {
+    inner: Box<dyn GolEngine>,
+    // Kept here, a migrated board starts the engine it moves to over at generation 0
+    generation_count: u64,
+    // Engine the board just moved away from, which still knows what the step before changed
+    migrated_from: Option<Box<dyn GolEngine>>,
+}
+
+impl AdaptiveEngine {
+    fn migrate(&mut self) {
+        let (width, height) = self.inner.dimensions();
+        let density = self.inner.population() as f64 / (width as f64 * height as f64);
+        let target = match self.inner.kind() {
+            EngineKind::Sparse if density > DENSE_ABOVE_DENSITY => EngineKind::Simd,
+            EngineKind::Simd if density < SPARSE_BELOW_DENSITY => EngineKind::Sparse,
+            _ => return,
+        };
+        let mut migrated = target.create(width, height);
+        migrated.load_packed_bits(&self.inner.to_packed_bits());
+        self.migrated_from = Some(core::mem::replace(&mut self.inner, migrated));
+        debug!(
+            "Board at density {:.3} moved to the {} engine",
+            density, target
+        );
+    }
+}
+
+impl GolEngine for AdaptiveEngine {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Adaptive
+    }
+
+    fn dimensions(&self) -> (u16, u16) {
+        self.inner.dimensions()
+    }
+
+    fn generation_count(&self) -> u64 {
+        self.generation_count
+    }
+
+    fn set_generation_count(&mut self, generation: u64) {
+        self.generation_count = generation;
+    }
+
+    fn step(&mut self) {
+        self.migrated_from = None;
+        self.inner.step();
+        self.generation_count += 1;
+        self.migrate();
+    }
+
+    fn is_alive(&self, x: u16, y: u16) -> bool {
+        self.inner.is_alive(x, y)
+    }
+
+    fn set_cell(&mut self, x: u16, y: u16, alive: bool) {
+        self.inner.set_cell(x, y, alive);
+    }
+
+    fn population(&self) -> usize {
+        self.inner.population()
+    }
+
+    fn to_packed_bits(&self) -> Vec<u8> {
+        self.inner.to_packed_bits()
+    }
+
+    fn load_packed_bits(&mut self, bits: &[u8]) {
+        self.inner.load_packed_bits(bits);
+        self.migrate();
+    }
+
+    fn boxed_clone(&self) -> Box<dyn GolEngine> {
+        Box::new(self.clone())
+    }
+
+    fn live_cells(&self) -> Vec<(u16, u16)> {
+        self.inner.live_cells()
+    }
+
+    fn changed_cells(&self) -> Vec<(u16, u16)> {
+        self.migrated_from
+            .as_ref()
+            .unwrap_or(&self.inner)
+            .changed_cells()
+    }
+
+    fn changed_region(&self, scheme: ColorScheme) -> Option<Region> {
+        self.migrated_from
+            .as_ref()
+            .unwrap_or(&self.inner)
+            .changed_region(scheme)
+    }
+
+    fn cell_rgb(&self, scheme: ColorScheme, x: u16, y: u16, rng: &mut dyn RngCore) -> [u8; 3] {
+        self.inner.cell_rgb(scheme, x, y, rng)
+    }
+
+    fn to_rgb_data(&self, scheme: ColorScheme, rng: &mut dyn RngCore) -> Vec<u8> {
+        self.inner.to_rgb_data(scheme, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn every_engine_steps_the_same_board() {
+        let board = GameOfLifeVecs::new(130, 40, &mut StdRng::seed_from_u64(7)).to_packed_bits();
+        let mut engines: Vec<_> = EngineKind::ALL
+            .iter()
+            .map(|kind| {
+                let mut engine = kind.create(130, 40);
+                engine.load_packed_bits(&board);
+                // A glider in the corner
+                for (x, y) in [(128, 37), (129, 38), (127, 39), (128, 39), (129, 39)] {
+                    engine.set_cell(x, y, true);
+                }
+                engine
+            })
+            .collect();
+        assert!(engines.iter().all(|engine| engine.is_alive(127, 39)));
+
+        for _ in 0..6 {
+            for engine in &mut engines {
+                engine.step();
+            }
+            let expected = engines[0].to_packed_bits();
+            for engine in &engines {
+                assert_eq!(engine.to_packed_bits(), expected, "{}", engine.kind());
+                assert_eq!(engine.population(), engines[0].population());
+                assert_eq!(engine.live_cells(), engines[0].live_cells());
+                assert_eq!(engine.changed_cells(), engines[0].changed_cells());
+            }
+        }
+        assert!(engines.iter().all(|engine| engine.generation_count() == 6));
+
+        assert_eq!("bits-parallel".parse(), Ok(EngineKind::BitsParallel));
+        assert!("fastest".parse::<EngineKind>().is_err());
+        for kind in EngineKind::ALL {
+            assert_eq!(EngineKind::from_id(kind.id()), Some(kind));
+        }
+        assert_eq!(EngineKind::from_id(6), None);
+    }
+
+    #[test]
+    fn adaptive_engine_follows_the_density() {
+        let soup = GameOfLifeVecs::new(200, 100, &mut StdRng::seed_from_u64(7));
+        let mut glider = GameOfLifeVecs::empty(200, 100);
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            glider.awaken_cell_in(x, y);
+        }
+        let mut adaptive = AdaptiveEngine {
+            inner: EngineKind::Sparse.create(200, 100),
+            generation_count: 0,
+            migrated_from: None,
+        };
+
+        for (mut reference, kind) in [(soup, EngineKind::Simd), (glider, EngineKind::Sparse)] {
+            adaptive.load_packed_bits(&reference.to_packed_bits());
+            assert_eq!(adaptive.inner.kind(), kind);
+            for _ in 0..4 {
+                adaptive.step();
+                reference.step();
+                assert_eq!(adaptive.to_packed_bits(), reference.to_packed_bits());
+                // Across a migration too
+                assert_eq!(adaptive.changed_cells(), reference.changed_cells());
+            }
+        }
+        assert_eq!(adaptive.generation_count(), 8);
+    }
+}
@@ -0,0 +1,970 @@
+use alloc::{vec, vec::Vec};
+#[cfg(target_arch = "aarch64")]
+use core::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use rand::Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use tracing::debug;
+
+use crate::{
+    DEAD_CELL_R_G_B, create_random_rgb,
+    region::{Region, include_cell},
+};
+
+const BIT_LENGTH: usize = 64;
+
+/// Vector instruction set used for bulk operations, detected at runtime with `std` and
+/// taken from the compile-time target features without it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdPath {
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    Scalar,
+}
+
+impl SimdPath {
+    pub fn detect() -> Self {
+        #[cfg(all(target_arch = "aarch64", feature = "std"))]
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return SimdPath::Neon;
+        }
+        #[cfg(all(target_arch = "aarch64", not(feature = "std")))]
+        if cfg!(target_feature = "neon") {
+            return SimdPath::Neon;
+        }
+        #[cfg(all(target_arch = "x86_64", feature = "std"))]
+        if std::arch::is_x86_feature_detected!("avx2") {
+            return SimdPath::Avx2;
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+        if cfg!(target_feature = "avx2") {
+            return SimdPath::Avx2;
+        }
+        SimdPath::Scalar
+    }
+}
+
+#[derive(Clone)]
+pub struct GameOfLifeBits {
+    pub width: u16,
+    pub height: u16,
+    // Store as bits: each u64 holds 64 cells
+    pub current_generation: Vec<u64>,
+    pub next_generation: Vec<u64>,
+    pub generation_count: u64,
+    // Width in u64 chunks (rounded up)
+    width_chunks: usize,
+}
+
+impl GameOfLifeBits {
+    pub fn new<R: Rng + ?Sized>(width: u16, height: u16, rng: &mut R) -> Self {
+        let mut game = Self::empty(width, height);
+        game.initialize_random(rng);
+        game
+    }
+
+    /// A grid with every cell dead
+    pub fn empty(width: u16, height: u16) -> Self {
+        let width_chunks = (width as usize).div_ceil(BIT_LENGTH); // Round up to nearest 64
+        let total_chunks = width_chunks * height as usize;
+
+        Self {
+            width,
+            height,
+            current_generation: vec![0u64; total_chunks],
+            next_generation: vec![0u64; total_chunks],
+            generation_count: 0,
+            width_chunks,
+        }
+    }
+
+    #[inline]
+    fn get_chunk_index(&self, x: usize, y: usize) -> (usize, usize) {
+        let chunk_x = x / 64;
+        let bit_x = x % 64;
+        let chunk_index = y * self.width_chunks + chunk_x;
+        (chunk_index, bit_x)
+    }
+
+    #[inline]
+    pub(crate) fn get_cell(&self, x: usize, y: usize) -> bool {
+        if x >= self.width as usize || y >= self.height as usize {
+            return false;
+        }
+        let (chunk_index, bit_x) = self.get_chunk_index(x, y);
+        if chunk_index >= self.current_generation.len() {
+            return false;
+        }
+        (self.current_generation[chunk_index] >> bit_x) & 1 == 1
+    }
+
+    #[inline]
+    pub(crate) fn set_cell(&mut self, x: usize, y: usize, alive: bool) {
+        if x >= self.width as usize || y >= self.height as usize {
+            return;
+        }
+        let (chunk_index, bit_x) = self.get_chunk_index(x, y);
+        if chunk_index >= self.current_generation.len() {
+            return;
+        }
+
+        if alive {
+            self.current_generation[chunk_index] |= 1u64 << bit_x;
+        } else {
+            self.current_generation[chunk_index] &= !(1u64 << bit_x);
+        }
+    }
+
+    #[inline]
+    fn set_next_cell(&mut self, x: usize, y: usize, alive: bool) {
+        if x >= self.width as usize || y >= self.height as usize {
+            return;
+        }
+        let (chunk_index, bit_x) = self.get_chunk_index(x, y);
+        if chunk_index >= self.next_generation.len() {
+            return;
+        }
+
+        if alive {
+            self.next_generation[chunk_index] |= 1u64 << bit_x;
+        }
+    }
+
+    pub fn initialize_random<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        for chunk in &mut self.current_generation {
+            *chunk = 0;
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if rng.random::<f32>() < 0.3 {
+                    self.set_cell(x as usize, y as usize, true);
+                }
+            }
+        }
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with random pattern");
+    }
+
+    pub fn initialize_glider(&mut self) {
+        // Clear the grid
+        for chunk in &mut self.current_generation {
+            *chunk = 0;
+        }
+
+        // Create a glider pattern in the top-left
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for (x, y) in glider.iter() {
+            self.set_cell(*x, *y, true);
+        }
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with glider pattern");
+    }
+
+    pub fn initialize_blinker(&mut self) {
+        // Clear the grid
+        for chunk in &mut self.current_generation {
+            *chunk = 0;
+        }
+
+        // Create a blinker pattern in the center
+        let center_x = (self.width / 2) as usize;
+        let center_y = (self.height / 2) as usize;
+
+        if center_x > 0 && center_y > 0 && center_x < (self.width - 1) as usize {
+            self.set_cell(center_x - 1, center_y, true);
+            self.set_cell(center_x, center_y, true);
+            self.set_cell(center_x + 1, center_y, true);
+        }
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with blinker pattern");
+    }
+
+    #[inline]
+    fn count_neighbors_optimized(&self, x: usize, y: usize) -> u8 {
+        let mut count = 0;
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        // Unrolled neighbor checking for better performance
+        // Top row
+        if y > 0 {
+            if x > 0 && self.get_cell(x - 1, y - 1) {
+                count += 1;
+            }
+            if self.get_cell(x, y - 1) {
+                count += 1;
+            }
+            if x < width - 1 && self.get_cell(x + 1, y - 1) {
+                count += 1;
+            }
+        }
+
+        // Middle row (left and right)
+        if x > 0 && self.get_cell(x - 1, y) {
+            count += 1;
+        }
+        if x < width - 1 && self.get_cell(x + 1, y) {
+            count += 1;
+        }
+
+        // Bottom row
+        if y < height - 1 {
+            if x > 0 && self.get_cell(x - 1, y + 1) {
+                count += 1;
+            }
+            if self.get_cell(x, y + 1) {
+                count += 1;
+            }
+            if x < width - 1 && self.get_cell(x + 1, y + 1) {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    // Vectorized step on NEON or AVX2, scalar elsewhere
+    pub fn step(&mut self) {
+        match SimdPath::detect() {
+            #[cfg(target_arch = "aarch64")]
+            SimdPath::Neon => unsafe { self.step_neon() },
+            #[cfg(target_arch = "x86_64")]
+            SimdPath::Avx2 => unsafe { self.step_avx2() },
+            SimdPath::Scalar => self.step_fallback(),
+        }
+        self.generation_count += 1;
+        debug!("Advanced to generation {}", self.generation_count);
+    }
+
+    // Shared by the sequential step paths, see `compute_row`
+    fn compute_next_generation(&mut self) {
+        let height = self.height as usize;
+        let width_chunks = self.width_chunks;
+        let last_chunk_mask = self.last_chunk_mask();
+
+        for (y, next_row) in self.next_generation.chunks_mut(width_chunks).enumerate() {
+            compute_row(
+                &self.current_generation,
+                y,
+                height,
+                width_chunks,
+                last_chunk_mask,
+                next_row,
+            );
+        }
+    }
+
+    // Valid cell bits of the last chunk in each row
+    fn last_chunk_mask(&self) -> u64 {
+        match self.width as usize % BIT_LENGTH {
+            0 => u64::MAX,
+            bits => (1u64 << bits) - 1,
+        }
+    }
+
+    // Per-cell reference implementation, kept to verify and benchmark the bitwise step
+    pub fn step_per_cell(&mut self) {
+        for chunk in &mut self.next_generation {
+            *chunk = 0;
+        }
+
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                let neighbors = self.count_neighbors_optimized(x, y);
+                let current_alive = self.get_cell(x, y);
+
+                let next_alive = match neighbors {
+                    2 => current_alive,
+                    3 => true,
+                    _ => false,
+                };
+
+                if next_alive {
+                    self.set_next_cell(x, y, true);
+                }
+            }
+        }
+
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.generation_count += 1;
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn step_neon(&mut self) {
+        // Clear next generation using NEON
+        let chunks = self.next_generation.len();
+        let mut i = 0;
+
+        unsafe {
+            // Process 2 u64s at a time with NEON (128-bit registers)
+            while i + 1 < chunks {
+                let zeros = vdupq_n_u64(0);
+                vst1q_u64(self.next_generation.as_mut_ptr().add(i), zeros);
+                i += 2;
+            }
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            self.next_generation[i] = 0;
+            i += 1;
+        }
+
+        // Process each cell with optimized neighbor counting
+        self.compute_next_generation();
+
+        // Swap generations using NEON for bulk copy
+        self.swap_generations_neon();
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn swap_generations_neon(&mut self) {
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+
+        unsafe {
+            // Process 2 u64s at a time
+            while i + 1 < chunks {
+                let current = vld1q_u64(self.current_generation.as_ptr().add(i));
+                let next = vld1q_u64(self.next_generation.as_ptr().add(i));
+
+                vst1q_u64(self.current_generation.as_mut_ptr().add(i), next);
+                vst1q_u64(self.next_generation.as_mut_ptr().add(i), current);
+
+                i += 2;
+            }
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            core::mem::swap(
+                &mut self.current_generation[i],
+                &mut self.next_generation[i],
+            );
+            i += 1;
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn step_avx2(&mut self) {
+        // Clear next generation using AVX2
+        let chunks = self.next_generation.len();
+        let mut i = 0;
+
+        unsafe {
+            // Process 4 u64s at a time with AVX2 (256-bit registers)
+            let zeros = _mm256_setzero_si256();
+            while i + 3 < chunks {
+                _mm256_storeu_si256(self.next_generation.as_mut_ptr().add(i).cast(), zeros);
+                i += 4;
+            }
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            self.next_generation[i] = 0;
+            i += 1;
+        }
+
+        self.compute_next_generation();
+
+        // Swap generations using AVX2 for bulk copy
+        unsafe { self.swap_generations_avx2() };
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn swap_generations_avx2(&mut self) {
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+
+        unsafe {
+            // Process 4 u64s at a time
+            while i + 3 < chunks {
+                let current_ptr = self
+                    .current_generation
+                    .as_mut_ptr()
+                    .add(i)
+                    .cast::<__m256i>();
+                let next_ptr = self.next_generation.as_mut_ptr().add(i).cast::<__m256i>();
+                let current = _mm256_loadu_si256(current_ptr);
+                let next = _mm256_loadu_si256(next_ptr);
+
+                _mm256_storeu_si256(current_ptr, next);
+                _mm256_storeu_si256(next_ptr, current);
+
+                i += 4;
+            }
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            core::mem::swap(
+                &mut self.current_generation[i],
+                &mut self.next_generation[i],
+            );
+            i += 1;
+        }
+    }
+
+    pub(crate) fn step_fallback(&mut self) {
+        // Clear next generation
+        for chunk in &mut self.next_generation {
+            *chunk = 0;
+        }
+
+        // Process each cell
+        self.compute_next_generation();
+
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+    }
+
+    /// Bounding box of the cells born or killed by the last step, None if it changed
+    /// nothing. Only meaningful right after a step, which leaves the previous generation in
+    /// `next_generation`.
+    pub fn changed_region(&self) -> Option<Region> {
+        let mut region = None;
+        let rows = self
+            .current_generation
+            .chunks(self.width_chunks)
+            .zip(self.next_generation.chunks(self.width_chunks));
+        for (y, (row, previous)) in rows.enumerate() {
+            for (c, (&word, &was)) in row.iter().zip(previous).enumerate() {
+                let changed = word ^ was;
+                if changed != 0 {
+                    // Bit i is cell x = c * 64 + i
+                    let first = c * BIT_LENGTH + changed.trailing_zeros() as usize;
+                    let last = c * BIT_LENGTH + (BIT_LENGTH - 1) - changed.leading_zeros() as usize;
+                    include_cell(&mut region, first as u16, y as u16);
+                    include_cell(&mut region, last as u16, y as u16);
+                }
+            }
+        }
+        region
+    }
+
+    /// Coordinates of every live cell, row by row
+    pub fn live_cells(&self) -> Vec<(u16, u16)> {
+        self.cells_where(|word, _| word)
+    }
+
+    /// Cells the last step brought to life or killed, row by row. Only meaningful right
+    /// after a step, which leaves the previous generation in `next_generation`.
+    pub fn changed_cells(&self) -> Vec<(u16, u16)> {
+        self.cells_where(|word, was| word ^ was)
+    }
+
+    // Cells whose bit is set in what `select` makes of their word now and before the last step
+    fn cells_where(&self, select: impl Fn(u64, u64) -> u64) -> Vec<(u16, u16)> {
+        let mut cells = Vec::new();
+        let rows = self
+            .current_generation
+            .chunks(self.width_chunks)
+            .zip(self.next_generation.chunks(self.width_chunks));
+        for (y, (row, previous)) in rows.enumerate() {
+            for (c, (&word, &was)) in row.iter().zip(previous).enumerate() {
+                let mut bits = select(word, was);
+                while bits != 0 {
+                    // Bit i is cell x = c * 64 + i
+                    let x = c * BIT_LENGTH + bits.trailing_zeros() as usize;
+                    if x < self.width as usize {
+                        cells.push((x as u16, y as u16));
+                    }
+                    bits &= bits - 1;
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn to_rgb_data<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<u8> {
+        let mut frame_data = Vec::with_capacity(self.width as usize * self.height as usize * 3);
+
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                if self.get_cell(x, y) {
+                    frame_data.extend(create_random_rgb(rng));
+                } else {
+                    frame_data.extend_from_slice(&DEAD_CELL_R_G_B);
+                }
+            }
+        }
+
+        frame_data
+    }
+
+    /// Row-major live/dead bits, most significant bit first, as `GameOfLifeVecs::to_packed_bits`
+    pub fn to_packed_bits(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let mut bits = vec![0u8; (width * self.height as usize).div_ceil(8)];
+
+        for y in 0..self.height as usize {
+            for x in 0..width {
+                if self.get_cell(x, y) {
+                    let i = y * width + x;
+                    bits[i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+        }
+
+        bits
+    }
+
+    /// Replaces the current generation with bits packed as by `to_packed_bits`, cells
+    /// missing from a short buffer are dead
+    pub fn load_packed_bits(&mut self, bits: &[u8]) {
+        let width = self.width as usize;
+        for y in 0..self.height as usize {
+            for x in 0..width {
+                let i = y * width + x;
+                let alive = bits
+                    .get(i / 8)
+                    .is_some_and(|byte| byte & (0x80 >> (i % 8)) != 0);
+                self.set_cell(x, y, alive);
+            }
+        }
+    }
+
+    pub fn awaken_random_cell<R: Rng + ?Sized>(&mut self, rng: &mut R) -> (u16, u16) {
+        let x = rng.random_range(0..self.width as usize);
+        let y = rng.random_range(0..self.height as usize);
+
+        self.set_cell(x, y, true);
+        (x as u16, y as u16)
+    }
+
+    pub fn kill_random_cell<R: Rng + ?Sized>(&mut self, rng: &mut R) -> (u16, u16) {
+        let x = rng.random_range(0..self.width as usize);
+        let y = rng.random_range(0..self.height as usize);
+
+        self.set_cell(x, y, false);
+        (x as u16, y as u16)
+    }
+
+    // Utility functions using bit manipulation
+    pub fn population_count(&self) -> u32 {
+        match SimdPath::detect() {
+            #[cfg(target_arch = "aarch64")]
+            SimdPath::Neon => unsafe { self.population_count_neon() },
+            #[cfg(target_arch = "x86_64")]
+            SimdPath::Avx2 => unsafe { self.population_count_avx2() },
+            SimdPath::Scalar => self
+                .current_generation
+                .iter()
+                .map(|chunk| chunk.count_ones())
+                .sum(),
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn population_count_neon(&self) -> u32 {
+        let mut total = 0u32;
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+
+        // Process 2 u64s at a time
+        while i + 1 < chunks {
+            let data = vld1q_u64(self.current_generation.as_ptr().add(i));
+            // Use ARM's population count instruction
+            let count = vcntq_u8(vreinterpretq_u8_u64(data));
+            let sum = vaddvq_u8(count);
+            total += sum as u32;
+            i += 2;
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            total += self.current_generation[i].count_ones();
+            i += 1;
+        }
+
+        total
+    }
+
+    // AVX2 has no popcount, so count nibbles with a shuffle lookup table and sum bytes with SAD
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn population_count_avx2(&self) -> u32 {
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+
+        let mut total = unsafe {
+            let lookup = _mm256_setr_epi8(
+                0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, 0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3,
+                2, 3, 3, 4,
+            );
+            let low_mask = _mm256_set1_epi8(0x0f);
+            let mut sums = _mm256_setzero_si256();
+
+            // Process 4 u64s at a time
+            while i + 3 < chunks {
+                let data = _mm256_loadu_si256(self.current_generation.as_ptr().add(i).cast());
+                let low = _mm256_and_si256(data, low_mask);
+                let high = _mm256_and_si256(_mm256_srli_epi16(data, 4), low_mask);
+                let counts = _mm256_add_epi8(
+                    _mm256_shuffle_epi8(lookup, low),
+                    _mm256_shuffle_epi8(lookup, high),
+                );
+                sums = _mm256_add_epi64(sums, _mm256_sad_epu8(counts, _mm256_setzero_si256()));
+                i += 4;
+            }
+
+            let mut lanes = [0u64; 4];
+            _mm256_storeu_si256(lanes.as_mut_ptr().cast(), sums);
+            lanes.iter().sum::<u64>() as u32
+        };
+
+        // Handle remaining chunks
+        while i < chunks {
+            total += self.current_generation[i].count_ones();
+            i += 1;
+        }
+
+        total
+    }
+
+    pub fn clear(&mut self) {
+        match SimdPath::detect() {
+            #[cfg(target_arch = "aarch64")]
+            SimdPath::Neon => unsafe { self.clear_neon() },
+            #[cfg(target_arch = "x86_64")]
+            SimdPath::Avx2 => unsafe { self.clear_avx2() },
+            SimdPath::Scalar => {
+                for chunk in &mut self.current_generation {
+                    *chunk = 0;
+                }
+            }
+        }
+        self.generation_count = 0;
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn clear_neon(&mut self) {
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+        let zeros = vdupq_n_u64(0);
+
+        // Process 2 u64s at a time
+        while i + 1 < chunks {
+            vst1q_u64(self.current_generation.as_mut_ptr().add(i), zeros);
+            i += 2;
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            self.current_generation[i] = 0;
+            i += 1;
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn clear_avx2(&mut self) {
+        let chunks = self.current_generation.len();
+        let mut i = 0;
+
+        unsafe {
+            let zeros = _mm256_setzero_si256();
+
+            // Process 4 u64s at a time
+            while i + 3 < chunks {
+                _mm256_storeu_si256(self.current_generation.as_mut_ptr().add(i).cast(), zeros);
+                i += 4;
+            }
+        }
+
+        // Handle remaining chunks
+        while i < chunks {
+            self.current_generation[i] = 0;
+            i += 1;
+        }
+    }
+
+    pub fn invert(&mut self) {
+        for (i, chunk) in self.current_generation.iter_mut().enumerate() {
+            *chunk = !*chunk;
+
+            // Mask out bits beyond the actual width for the last chunk in each row
+            if (i + 1) % self.width_chunks == 0 {
+                let bits_in_last_chunk = self.width as usize % 64;
+                if bits_in_last_chunk > 0 {
+                    *chunk &= (1u64 << bits_in_last_chunk) - 1;
+                }
+            }
+        }
+    }
+
+    // Parallel processing on the rayon thread pool (good for Apple Silicon's many cores).
+    // Rows borrow the current generation and are written straight into the next generation
+    // buffer, so a step allocates nothing.
+    #[cfg(feature = "parallel")]
+    pub fn step_parallel(&mut self) {
+        let height = self.height as usize;
+        let width_chunks = self.width_chunks;
+        let last_chunk_mask = self.last_chunk_mask();
+        let current_gen = &self.current_generation;
+
+        self.next_generation
+            .par_chunks_mut(width_chunks)
+            .enumerate()
+            .for_each(|(y, next_row)| {
+                compute_row(
+                    current_gen,
+                    y,
+                    height,
+                    width_chunks,
+                    last_chunk_mask,
+                    next_row,
+                );
+            });
+
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.generation_count += 1;
+    }
+}
+
+#[inline]
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+#[inline]
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, (a & b) | (partial & c))
+}
+
+// Applies the Life rules to 64 cells given the 8 neighbor words aligned to them
+#[inline]
+fn next_word(alive: u64, neighbors: &[u64; 8]) -> u64 {
+    let [n0, n1, n2, n3, n4, n5, n6, n7] = *neighbors;
+
+    let (sum_a, carry_a) = full_add(n0, n1, n2);
+    let (sum_b, carry_b) = full_add(n3, n4, n5);
+    let (sum_c, carry_c) = half_add(n6, n7);
+    let (ones, carry_d) = full_add(sum_a, sum_b, sum_c);
+
+    let (sum_e, carry_e) = full_add(carry_a, carry_b, carry_c);
+    let (twos, carry_f) = half_add(sum_e, carry_d);
+    let (fours, eights) = half_add(carry_e, carry_f);
+
+    // Exactly 2 keeps a live cell, exactly 3 makes one
+    twos & !fours & !eights & (ones | alive)
+}
+
+// Computes all 64 cells of a word at once by adding the eight shifted neighbor words with
+// half/full adders. Cells outside the grid count as dead.
+fn compute_row(
+    current: &[u64],
+    y: usize,
+    height: usize,
+    width_chunks: usize,
+    last_chunk_mask: u64,
+    next_row: &mut [u64],
+) {
+    let word = |y: Option<usize>, c: usize| match y {
+        Some(y) if y < height => current[y * width_chunks + c],
+        _ => 0,
+    };
+
+    for (c, next) in next_row.iter_mut().enumerate() {
+        let mut neighbors = [0u64; 8];
+        let mut n = 0;
+
+        for (dy, row) in [y.checked_sub(1), Some(y), Some(y + 1)]
+            .into_iter()
+            .enumerate()
+        {
+            let center = word(row, c);
+            // Bit i is cell x = c * 64 + i, so west neighbors shift up and east shift down
+            let prev = if c > 0 { word(row, c - 1) } else { 0 };
+            let next = if c + 1 < width_chunks {
+                word(row, c + 1)
+            } else {
+                0
+            };
+            neighbors[n] = (center << 1) | (prev >> 63);
+            neighbors[n + 1] = (center >> 1) | (next << 63);
+            n += 2;
+            if dy != 1 {
+                neighbors[n] = center;
+                n += 1;
+            }
+        }
+
+        *next = next_word(word(Some(y), c), &neighbors);
+        if c + 1 == width_chunks {
+            *next &= last_chunk_mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorScheme, GameOfLifeVecs, color::AGE_RAMP_LENGTH};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn step_matches_scalar_fallback() {
+        // 130 columns -> 3 chunks per row, exercising both the vector and remainder loops
+        let mut simd = GameOfLifeBits::new(130, 40, &mut StdRng::seed_from_u64(7));
+        let mut scalar = simd.clone();
+
+        for _ in 0..5 {
+            simd.step();
+            scalar.step_fallback();
+            assert_eq!(simd.current_generation, scalar.current_generation);
+        }
+        assert_eq!(simd.generation_count, 5);
+    }
+
+    #[test]
+    fn packed_bits_match_vecs_engine() {
+        let mut vecs = GameOfLifeVecs::new(70, 9, &mut StdRng::seed_from_u64(7));
+        let mut bits = GameOfLifeBits::empty(70, 9);
+        bits.load_packed_bits(&vecs.to_packed_bits());
+
+        for _ in 0..4 {
+            assert_eq!(bits.to_packed_bits(), vecs.to_packed_bits());
+            bits.step();
+            vecs.step();
+        }
+    }
+
+    #[test]
+    fn changed_regions_bound_what_a_step_redraws() {
+        let mut vecs = GameOfLifeVecs::new(130, 12, &mut StdRng::seed_from_u64(7));
+        vecs.kill_all_cells();
+        // Blinker straddling the first chunk boundary, block in the far corner
+        for (x, y) in [
+            (63, 5),
+            (64, 5),
+            (65, 5),
+            (120, 10),
+            (121, 10),
+            (120, 11),
+            (121, 11),
+        ] {
+            vecs.awaken_cell_in(x, y);
+        }
+        let mut bits = GameOfLifeBits::empty(130, 12);
+        bits.load_packed_bits(&vecs.to_packed_bits());
+
+        bits.step();
+        vecs.step();
+        let blinker = Region {
+            x: 63,
+            y: 4,
+            width: 3,
+            height: 3,
+        };
+        assert_eq!(bits.changed_region(), Some(blinker));
+        assert_eq!(vecs.changed_region(ColorScheme::Monochrome), Some(blinker));
+        // The block darkens as it ages until it reaches the end of the ramp
+        let with_block = vecs.changed_region(ColorScheme::Fire).unwrap();
+        assert_eq!((with_block.width, with_block.height), (59, 8));
+        for _ in 0..AGE_RAMP_LENGTH {
+            vecs.step();
+        }
+        assert_eq!(vecs.changed_region(ColorScheme::Fire), Some(blinker));
+    }
+
+    #[test]
+    fn bitwise_step_matches_per_cell() {
+        for (width, height) in [(130, 40), (64, 64), (7, 5)] {
+            let mut bitwise = GameOfLifeBits::new(width, height, &mut StdRng::seed_from_u64(7));
+            let mut per_cell = bitwise.clone();
+
+            for _ in 0..8 {
+                bitwise.step_fallback();
+                per_cell.step_per_cell();
+                assert_eq!(bitwise.current_generation, per_cell.current_generation);
+            }
+        }
+    }
+
+    // cargo test --release bench_bitwise_step -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    #[cfg(feature = "std")]
+    fn bench_bitwise_step() {
+        use std::time::Instant;
+
+        const GENERATIONS: u32 = 200;
+        for size in [100, 500, 1000] {
+            let mut bitwise = GameOfLifeBits::new(size, size, &mut StdRng::seed_from_u64(7));
+            let mut per_cell = bitwise.clone();
+
+            let started = Instant::now();
+            for _ in 0..GENERATIONS {
+                per_cell.step_per_cell();
+            }
+            let per_cell_time = started.elapsed();
+
+            let started = Instant::now();
+            for _ in 0..GENERATIONS {
+                bitwise.step();
+            }
+            let bitwise_time = started.elapsed();
+
+            println!(
+                "{size}x{size}: per-cell {:.0} gen/s, bitwise {:.0} gen/s ({:.1}x)",
+                GENERATIONS as f64 / per_cell_time.as_secs_f64(),
+                GENERATIONS as f64 / bitwise_time.as_secs_f64(),
+                per_cell_time.as_secs_f64() / bitwise_time.as_secs_f64()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn step_parallel_matches_scalar_fallback() {
+        let mut parallel = GameOfLifeBits::new(130, 40, &mut StdRng::seed_from_u64(7));
+        let mut scalar = parallel.clone();
+        let buffers = (
+            parallel.current_generation.as_ptr(),
+            parallel.next_generation.as_ptr(),
+        );
+
+        for _ in 0..4 {
+            parallel.step_parallel();
+            scalar.step_fallback();
+            assert_eq!(parallel.current_generation, scalar.current_generation);
+        }
+
+        // Even number of steps: back on the original buffers, nothing reallocated
+        assert_eq!(
+            (
+                parallel.current_generation.as_ptr(),
+                parallel.next_generation.as_ptr()
+            ),
+            buffers
+        );
+    }
+
+    #[test]
+    fn population_count_and_clear() {
+        let mut game = GameOfLifeBits::new(130, 40, &mut StdRng::seed_from_u64(7));
+        let expected: u32 = game
+            .current_generation
+            .iter()
+            .map(|chunk| chunk.count_ones())
+            .sum();
+        assert_eq!(game.population_count(), expected);
+
+        game.clear();
+        assert_eq!(game.population_count(), 0);
+        assert!(game.current_generation.iter().all(|chunk| *chunk == 0));
+    }
+}
@@ -0,0 +1,105 @@
+use alloc::{collections::BTreeMap, collections::BTreeSet, vec, vec::Vec};
+use tracing::debug;
+
+/// A board that only stores its live cells, for huge grids that are mostly empty. A step
+/// costs time in the number of live cells rather than the area. Cells are kept as (y, x) in
+/// ordered sets, which work without `std` and list the cells row by row.
+#[derive(Clone)]
+pub struct GameOfLifeSparse {
+    pub width: u16,
+    pub height: u16,
+    pub live: BTreeSet<(u16, u16)>,
+    // Live cells before the last step
+    pub previous: BTreeSet<(u16, u16)>,
+    pub generation_count: u64,
+}
+
+impl GameOfLifeSparse {
+    /// A grid with every cell dead
+    pub fn empty(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            live: BTreeSet::new(),
+            previous: BTreeSet::new(),
+            generation_count: 0,
+        }
+    }
+
+    pub fn is_alive(&self, x: u16, y: u16) -> bool {
+        self.live.contains(&(y, x))
+    }
+
+    pub fn set_cell(&mut self, x: u16, y: u16, alive: bool) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        if alive {
+            self.live.insert((y, x));
+        } else {
+            self.live.remove(&(y, x));
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn step(&mut self) {
+        let mut neighbors: BTreeMap<(u16, u16), u8> = BTreeMap::new();
+        for &(y, x) in &self.live {
+            for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                    if (ny, nx) != (y, x) {
+                        *neighbors.entry((ny, nx)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let next = neighbors
+            .into_iter()
+            .filter(|(cell, count)| *count == 3 || (*count == 2 && self.live.contains(cell)))
+            .map(|(cell, _)| cell)
+            .collect();
+        self.previous = core::mem::replace(&mut self.live, next);
+        self.generation_count += 1;
+        debug!("Advanced to generation {} (sparse)", self.generation_count);
+    }
+
+    /// Cells the last step brought to life or killed, row by row. Only meaningful right
+    /// after a step.
+    pub fn changed_cells(&self) -> Vec<(u16, u16)> {
+        self.live
+            .symmetric_difference(&self.previous)
+            .map(|&(y, x)| (x, y))
+            .collect()
+    }
+
+    /// Row-major live/dead bits, most significant bit first, as `GameOfLifeVecs::to_packed_bits`
+    pub fn to_packed_bits(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let mut bits = vec![0u8; (width * self.height as usize).div_ceil(8)];
+        for &(y, x) in &self.live {
+            let i = y as usize * width + x as usize;
+            bits[i / 8] |= 0x80 >> (i % 8);
+        }
+        bits
+    }
+
+    /// Replaces the board with bits packed as by `to_packed_bits`, cells missing from a short
+    /// buffer are dead
+    pub fn load_packed_bits(&mut self, bits: &[u8]) {
+        let width = self.width as usize;
+        let cells = width * self.height as usize;
+        self.live.clear();
+        for (byte_index, &byte) in bits.iter().enumerate().filter(|(_, byte)| **byte != 0) {
+            for bit in 0..8 {
+                let i = byte_index * 8 + bit;
+                if i < cells && byte & (0x80 >> bit) != 0 {
+                    self.live.insert(((i / width) as u16, (i % width) as u16));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use alloc::{vec, vec::Vec};
+use rand::Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use tracing::debug;
+
+use crate::{
+    ColorScheme, DEAD_CELL_R_G_B,
+    region::{Region, include_cell},
+};
+
+#[derive(Clone)]
+pub struct GameOfLifeVecs {
+    pub width: u16,
+    pub height: u16,
+    pub current_generation: Vec<Vec<bool>>,
+    pub next_generation: Vec<Vec<bool>>,
+    // Generations each live cell has survived, 0 for newborn and dead cells
+    pub ages: Vec<Vec<u16>>,
+    pub generation_count: u64,
+}
+
+impl GameOfLifeVecs {
+    pub fn new<R: Rng + ?Sized>(width: u16, height: u16, rng: &mut R) -> Self {
+        let mut game = Self::empty(width, height);
+        game.initialize_random(rng);
+        game
+    }
+
+    /// A grid with every cell dead
+    pub fn empty(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            current_generation: vec![vec![false; width as usize]; height as usize],
+            next_generation: vec![vec![false; width as usize]; height as usize],
+            ages: vec![vec![0; width as usize]; height as usize],
+            generation_count: 0,
+        }
+    }
+
+    pub fn initialize_random<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                // 30% chance of a cell being alive initially
+                self.current_generation[y as usize][x as usize] = rng.random::<f32>() < 0.3;
+            }
+        }
+        self.reset_ages();
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with random pattern");
+    }
+
+    #[allow(dead_code)]
+    pub fn initialize_glider(&mut self) {
+        // Clear the grid
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.current_generation[y as usize][x as usize] = false;
+            }
+        }
+
+        // Create a glider pattern in the top-left
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for (dx, dy) in glider.iter() {
+            if *dx < self.width && *dy < self.height {
+                self.current_generation[*dy as usize][*dx as usize] = true;
+            }
+        }
+        self.reset_ages();
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with glider pattern");
+    }
+
+    #[allow(dead_code)]
+    pub fn initialize_blinker(&mut self) {
+        // Clear the grid
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.current_generation[y as usize][x as usize] = false;
+            }
+        }
+
+        // Create a blinker pattern in the center
+        let center_x = self.width / 2;
+        let center_y = self.height / 2;
+        if center_x > 0 && center_y > 0 && center_x < self.width - 1 {
+            self.current_generation[center_y as usize][(center_x - 1) as usize] = true;
+            self.current_generation[center_y as usize][center_x as usize] = true;
+            self.current_generation[center_y as usize][(center_x + 1) as usize] = true;
+        }
+        self.reset_ages();
+        self.generation_count = 0;
+        debug!("Initialized Game of Life with blinker pattern");
+    }
+
+    fn reset_ages(&mut self) {
+        for row in &mut self.ages {
+            row.fill(0);
+        }
+    }
+
+    // Called after the buffers were swapped, the next generation buffer holds the previous one
+    fn update_ages(&mut self) {
+        for ((ages, row), previous) in self
+            .ages
+            .iter_mut()
+            .zip(&self.current_generation)
+            .zip(&self.next_generation)
+        {
+            for ((age, &alive), &was_alive) in ages.iter_mut().zip(row).zip(previous) {
+                *age = if alive && was_alive {
+                    age.saturating_add(1)
+                } else {
+                    0
+                };
+            }
+        }
+    }
+
+    fn count_live_neighbors(&self, x: u16, y: u16) -> u8 {
+        let mut count = 0;
+        let x = x as usize;
+        let y = y as usize;
+
+        // Use saturating arithmetic to avoid bounds checking in loop
+        let start_y = y.saturating_sub(1);
+        let end_y = (y + 1).min(self.height as usize - 1);
+        let start_x = x.saturating_sub(1);
+        let end_x = (x + 1).min(self.width as usize - 1);
+
+        for ny in start_y..=end_y {
+            for nx in start_x..=end_x {
+                if nx == x && ny == y {
+                    continue; // Skip the cell itself
+                }
+                if self.current_generation[ny][nx] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    pub fn step_fallback(&mut self) {
+        // Calculate next generation
+        for y in 0..self.height {
+            let current_row = &self.current_generation[y as usize];
+
+            for x in 0..self.width {
+                let neighbors = self.count_live_neighbors(x, y);
+                let current_alive = current_row[x as usize];
+
+                // Conway's Game of Life rules - more explicit and readable
+                self.next_generation[y as usize][x as usize] = match neighbors {
+                    2 => current_alive, // Stays the same (live stays live, dead stays dead)
+                    3 => true,          // Birth or survival
+                    _ => false,         // Death or stays dead
+                };
+            }
+        }
+
+        // Swap generations
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.update_ages();
+        self.generation_count += 1;
+        debug!("Advanced to generation {}", self.generation_count);
+    }
+
+    /// Parallel processing on the rayon thread pool, one row per task, writing straight into
+    /// the next generation buffer. Rows are stepped in order without the `parallel` feature.
+    pub fn step(&mut self) {
+        let height = self.height as usize;
+        let width = self.width as usize;
+        let current_gen = &self.current_generation;
+
+        #[cfg(feature = "parallel")]
+        let rows = self.next_generation.par_iter_mut();
+        #[cfg(not(feature = "parallel"))]
+        let rows = self.next_generation.iter_mut();
+
+        rows.enumerate().for_each(|(y, next_row)| {
+            for (x, next_cell) in next_row.iter_mut().enumerate() {
+                let neighbors = count_neighbors_parallel(current_gen, x, y, width, height);
+
+                *next_cell = match neighbors {
+                    2 => current_gen[y][x],
+                    3 => true,
+                    _ => false,
+                };
+            }
+        });
+
+        // Swap generations
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.update_ages();
+        self.generation_count += 1;
+        debug!(
+            "Advanced to generation {} (parallel)",
+            self.generation_count
+        );
+    }
+
+    pub fn population(&self) -> usize {
+        self.current_generation
+            .iter()
+            .map(|row| row.iter().filter(|alive| **alive).count())
+            .sum()
+    }
+
+    /// Row-major live/dead bits, most significant bit first, last byte zero padded
+    pub fn to_packed_bits(&self) -> Vec<u8> {
+        let mut bits = vec![0u8; (self.width as usize * self.height as usize).div_ceil(8)];
+
+        for (i, alive) in self.current_generation.iter().flatten().enumerate() {
+            if *alive {
+                bits[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+
+        bits
+    }
+
+    /// Color of the cell at (x, y), live cells colored by `scheme`
+    pub fn cell_rgb<R: Rng + ?Sized>(
+        &self,
+        scheme: ColorScheme,
+        x: u16,
+        y: u16,
+        rng: &mut R,
+    ) -> [u8; 3] {
+        if !self.current_generation[y as usize][x as usize] {
+            return DEAD_CELL_R_G_B;
+        }
+        let age = self.ages[y as usize][x as usize];
+        scheme.live_cell_rgb((x, y), age, self.generation_count, rng)
+    }
+
+    /// Cells the last step brought to life or killed, row by row. Only meaningful right
+    /// after a step, which leaves the previous generation in `next_generation`.
+    pub fn changed_cells(&self) -> Vec<(u16, u16)> {
+        let mut cells = Vec::new();
+        for (y, (row, previous)) in self
+            .current_generation
+            .iter()
+            .zip(&self.next_generation)
+            .enumerate()
+        {
+            for (x, (&alive, &was_alive)) in row.iter().zip(previous).enumerate() {
+                if alive != was_alive {
+                    cells.push((x as u16, y as u16));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Bounding box of the cells drawn differently by `scheme` than before the last step:
+    /// births, deaths and survivors whose color moves with their age. None if nothing
+    /// changed. Only meaningful right after a step, which leaves the previous generation
+    /// in `next_generation`.
+    pub fn changed_region(&self, scheme: ColorScheme) -> Option<Region> {
+        let mut region = None;
+        for (y, ((row, previous), ages)) in self
+            .current_generation
+            .iter()
+            .zip(&self.next_generation)
+            .zip(&self.ages)
+            .enumerate()
+        {
+            for (x, ((&alive, &was_alive), &age)) in row.iter().zip(previous).zip(ages).enumerate()
+            {
+                if alive != was_alive || (alive && scheme.recolors_at(age)) {
+                    include_cell(&mut region, x as u16, y as u16);
+                }
+            }
+        }
+        region
+    }
+
+    /// Live cells colored by `scheme` according to their age
+    pub fn to_rgb_data<R: Rng + ?Sized>(&self, scheme: ColorScheme, rng: &mut R) -> Vec<u8> {
+        let mut frame_data = Vec::with_capacity(self.width as usize * self.height as usize * 3);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                frame_data.extend(self.cell_rgb(scheme, x, y, rng));
+            }
+        }
+
+        frame_data
+    }
+
+    pub fn awaken_random_cell<R: Rng + ?Sized>(&mut self, rng: &mut R) -> (u16, u16) {
+        let x: u16 = rng.random_range(0u16..self.width);
+        let y: u16 = rng.random_range(0u16..self.height);
+
+        self.current_generation[y as usize][x as usize] = true;
+        (x, y)
+    }
+
+    pub fn awaken_cell_in(&mut self, x: u16, y: u16) -> (u16, u16) {
+        self.current_generation[y as usize][x as usize] = true;
+        (x, y)
+    }
+
+    pub fn kill_cell_in(&mut self, x: u16, y: u16) -> (u16, u16) {
+        self.current_generation[y as usize][x as usize] = false;
+        self.ages[y as usize][x as usize] = 0;
+        (x, y)
+    }
+
+    pub fn kill_random_cell<R: Rng + ?Sized>(&mut self, rng: &mut R) -> (u16, u16) {
+        let x: u16 = rng.random_range(0u16..self.width);
+        let y: u16 = rng.random_range(0u16..self.height);
+
+        self.kill_cell_in(x, y)
+    }
+
+    /// Sets the given cell offsets alive relative to (x, y), clipping anything off the grid.
+    /// Returns the number of cells that landed on the grid.
+    pub fn stamp_cells(&mut self, x: u16, y: u16, cells: &[(usize, usize)]) -> usize {
+        let mut stamped = 0;
+        for &(dx, dy) in cells {
+            // Offsets past usize are off the grid too
+            let (Some(cx), Some(cy)) = ((x as usize).checked_add(dx), (y as usize).checked_add(dy))
+            else {
+                continue;
+            };
+            if cx < self.width as usize && cy < self.height as usize {
+                self.current_generation[cy][cx] = true;
+                stamped += 1;
+            }
+        }
+        stamped
+    }
+
+    /// Replaces the current generation with bits packed as by `to_packed_bits`, cells
+    /// missing from a short buffer are dead. Every cell starts over as newborn.
+    pub fn load_packed_bits(&mut self, bits: &[u8]) {
+        unpack_bits(bits, &mut self.current_generation);
+        self.reset_ages();
+    }
+
+    pub fn load_cells(&mut self, cells: Vec<Vec<bool>>) {
+        if cells.len() != self.height as usize
+            || cells.iter().any(|row| row.len() != self.width as usize)
+        {
+            panic!(
+                "Cell grid size mismatch: expected {}x{}",
+                self.width, self.height
+            );
+        }
+
+        self.current_generation = cells;
+        self.reset_ages();
+        self.generation_count = 0;
+        debug!("Loaded Game of Life from external cell grid");
+    }
+
+    pub fn kill_all_cells(&mut self) {
+        self.next_generation = vec![vec![false; self.width as usize]; self.height as usize];
+        core::mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.reset_ages();
+        self.generation_count = 0
+    }
+}
+
+fn unpack_bits(bits: &[u8], rows: &mut [Vec<bool>]) {
+    for (i, alive) in rows.iter_mut().flatten().enumerate() {
+        *alive = bits
+            .get(i / 8)
+            .is_some_and(|byte| byte & (0x80 >> (i % 8)) != 0);
+    }
+}
+
+// Helper function for parallel neighbor counting
+fn count_neighbors_parallel(
+    current_gen: &[Vec<bool>],
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> u8 {
+    let mut count = 0;
+
+    // Use saturating arithmetic to avoid bounds checking
+    let start_y = y.saturating_sub(1);
+    let end_y = (y + 1).min(height - 1);
+    let start_x = x.saturating_sub(1);
+    let end_x = (x + 1).min(width - 1);
+
+    for (ny, row) in current_gen.iter().enumerate().take(end_y + 1).skip(start_y) {
+        for (nx, &alive) in row.iter().enumerate().take(end_x + 1).skip(start_x) {
+            if nx == x && ny == y {
+                continue; // Skip the cell itself
+            }
+            if alive {
+                count += 1;
+            }
+        }
+    }
+    count
+}
@@ -0,0 +1,42 @@
+//! Game of Life engines and the binary protocol codec of the game-of-life server, without
+//! any server dependencies so they can be embedded in other apps and in wasm clients.
+//!
+//! `no_std` (with `alloc`) when built with `default-features = false`. The `parallel`
+//! feature steps rows on the rayon thread pool, `std` enables runtime SIMD detection and
+//! `wasm` exports the codec and `GameOfLifeBits` to JavaScript. `GolEngine` steps a board
+//! with whichever engine suits the platform.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod color;
+pub mod compression;
+pub mod engine;
+pub mod gol_simd;
+pub mod gol_sparse;
+pub mod gol_threads;
+pub mod protocol;
+pub mod region;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use rand::Rng;
+
+pub use color::ColorScheme;
+pub use engine::{EngineKind, GolEngine};
+pub use gol_simd::GameOfLifeBits;
+pub use gol_sparse::GameOfLifeSparse;
+pub use gol_threads::GameOfLifeVecs;
+pub use region::Region;
+
+pub const DEAD_CELL_R_G_B: [u8; 3] = [255, 255, 255];
+
+/// creates a random rgb value
+pub fn create_random_rgb<R: Rng + ?Sized>(rng: &mut R) -> [u8; 3] {
+    let r = rng.random_range(0..255);
+    let g = rng.random_range(0..255);
+    let b = rng.random_range(0..255);
+
+    [r, g, b]
+}
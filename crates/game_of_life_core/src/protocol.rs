@@ -0,0 +1,657 @@
+use alloc::vec::Vec;
+use core::fmt;
+use tracing::debug;
+
+pub const PROTOCOL_VERSION: u8 = 1;
+pub const HEADER_LENGTH: u8 = 7;
+/// Header flags bit set when the payload starts with the topic byte and u32 (big-endian)
+/// sequence number of a room broadcast. Rooms number each topic on its own.
+pub const FLAG_SEQUENCED: u8 = 1 << 2;
+/// Bytes of the topic and sequence number leading a sequenced payload
+pub const SEQUENCE_PREFIX_LENGTH: usize = 5;
+/// Header flags bit set when the payload is followed by its big-endian `crc32`. The
+/// header's payload length counts the checksum.
+pub const FLAG_CRC32: u8 = 1 << 3;
+/// Bytes of the checksum trailing a payload with `FLAG_CRC32`
+pub const CHECKSUM_LENGTH: usize = 4;
+/// Header flags bits of a message split into fragments: the first carries BEGIN, the last
+/// END and any in between CONTINUATION, all with the message's type. The BEGIN fragment
+/// holds the message's other flags. Messages sent whole carry none of them.
+pub const FLAG_FRAGMENT_BEGIN: u8 = 1 << 4;
+pub const FLAG_FRAGMENT_CONTINUATION: u8 = 1 << 5;
+pub const FLAG_FRAGMENT_END: u8 = 1 << 6;
+pub const FRAGMENT_FLAGS: u8 = FLAG_FRAGMENT_BEGIN | FLAG_FRAGMENT_CONTINUATION | FLAG_FRAGMENT_END;
+// Hash algorithm ids advertised in PREDICTION_PARAMS
+pub const HASH_FNV1A_32: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    TooShort {
+        length: usize,
+    },
+    UnsupportedVersion {
+        version: u8,
+    },
+    LengthMismatch {
+        length: usize,
+        payload_length: usize,
+    },
+    PayloadTooLarge {
+        payload_length: usize,
+        max_payload_length: usize,
+    },
+    CellLength {
+        length: usize,
+    },
+    CellPrefixLength {
+        length: usize,
+    },
+    CellListLength {
+        length: usize,
+    },
+    CellOutOfBounds {
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    },
+    InvalidDeflate {
+        reason: &'static str,
+    },
+    InflatedTooLarge {
+        max_length: usize,
+    },
+    MissingChecksum {
+        payload_length: usize,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        computed: u32,
+    },
+    UnexpectedFragment {
+        msg_type: u8,
+        flags: u8,
+    },
+    FragmentedTooLarge {
+        length: usize,
+        max_length: usize,
+    },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ProtocolError::TooShort { length } => write!(
+                f,
+                "Message too short: {} bytes (minimum {} required for header)",
+                length, HEADER_LENGTH
+            ),
+            ProtocolError::UnsupportedVersion { version } => write!(
+                f,
+                "Unsupported protocol version: {} (expected {})",
+                version, PROTOCOL_VERSION
+            ),
+            ProtocolError::LengthMismatch {
+                length,
+                payload_length,
+            } => write!(
+                f,
+                "Message length mismatch: got {} bytes, expected {} bytes (header: {}, payload: {})",
+                length,
+                HEADER_LENGTH as usize + payload_length,
+                HEADER_LENGTH,
+                payload_length
+            ),
+            ProtocolError::CellLength { length } => write!(
+                f,
+                "Cell payload of {} bytes (expected {} or {})",
+                length,
+                CellPayload::COORDS_LENGTH,
+                CellPayload::COLORED_LENGTH
+            ),
+            ProtocolError::CellPrefixLength { length } => write!(
+                f,
+                "Cell payload of {} bytes (expected at least {})",
+                length,
+                CellPayload::COORDS_LENGTH
+            ),
+            ProtocolError::CellListLength { length } => write!(
+                f,
+                "Cell list of {} bytes isn't a multiple of {}",
+                length,
+                CellPayload::COORDS_LENGTH
+            ),
+            ProtocolError::CellOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "Cell ({}, {}) outside of the {}x{} grid",
+                x, y, width, height
+            ),
+            ProtocolError::InvalidDeflate { reason } => {
+                write!(f, "Invalid deflate stream: {}", reason)
+            }
+            ProtocolError::InflatedTooLarge { max_length } => {
+                write!(f, "Deflated payload inflates past {} bytes", max_length)
+            }
+            ProtocolError::PayloadTooLarge {
+                payload_length,
+                max_payload_length,
+            } => write!(
+                f,
+                "Payload of {} bytes exceeds the {}-byte limit",
+                payload_length, max_payload_length
+            ),
+            ProtocolError::MissingChecksum { payload_length } => write!(
+                f,
+                "Payload of {} bytes too short for its {}-byte checksum",
+                payload_length, CHECKSUM_LENGTH
+            ),
+            ProtocolError::ChecksumMismatch { expected, computed } => write!(
+                f,
+                "Payload checksum mismatch: sent {:#010x}, computed {:#010x}",
+                expected, computed
+            ),
+            ProtocolError::UnexpectedFragment { msg_type, flags } => write!(
+                f,
+                "Fragment of message type {} with flags {:#010b} out of order",
+                msg_type, flags
+            ),
+            ProtocolError::FragmentedTooLarge { length, max_length } => write!(
+                f,
+                "Fragmented message of {} bytes so far exceeds {} bytes",
+                length, max_length
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ProtocolError {}
+
+/// FNV-1a (32-bit) of a generation's packed bits, sent so predicting clients can
+/// check their locally stepped grid against the server's
+pub fn generation_hash(bits: &[u8]) -> u32 {
+    bits.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+// Lookup table of the reflected IEEE polynomial, one entry per byte value
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE, as in zlib and PNG) of a payload, trailing it when `FLAG_CRC32` is set
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Cell target of client messages: x and y as big-endian u16, optionally followed by r, g, b
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellPayload {
+    pub x: u16,
+    pub y: u16,
+    pub rgb: Option<[u8; 3]>,
+}
+
+impl CellPayload {
+    pub const COORDS_LENGTH: usize = 4;
+    pub const COLORED_LENGTH: usize = 7;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::COLORED_LENGTH);
+        buf.extend(self.x.to_be_bytes());
+        buf.extend(self.y.to_be_bytes());
+        if let Some(rgb) = self.rgb {
+            buf.extend(rgb);
+        }
+        buf
+    }
+
+    /// Decodes a whole payload of coordinates, with or without a color
+    pub fn decode(data: &[u8]) -> Result<Self, ProtocolError> {
+        let rgb = match data.len() {
+            Self::COORDS_LENGTH => None,
+            Self::COLORED_LENGTH => Some([data[4], data[5], data[6]]),
+            length => return Err(ProtocolError::CellLength { length }),
+        };
+
+        Ok(Self {
+            x: u16::from_be_bytes([data[0], data[1]]),
+            y: u16::from_be_bytes([data[2], data[3]]),
+            rgb,
+        })
+    }
+
+    /// Decodes uncolored coordinates leading a payload, returning them and the rest
+    pub fn decode_prefix(data: &[u8]) -> Result<(Self, &[u8]), ProtocolError> {
+        if data.len() < Self::COORDS_LENGTH {
+            return Err(ProtocolError::CellPrefixLength { length: data.len() });
+        }
+        let (coords, rest) = data.split_at(Self::COORDS_LENGTH);
+        Ok((Self::decode(coords)?, rest))
+    }
+
+    /// Decodes a list of uncolored coordinates
+    pub fn decode_list(data: &[u8]) -> Result<Vec<Self>, ProtocolError> {
+        if !data.len().is_multiple_of(Self::COORDS_LENGTH) {
+            return Err(ProtocolError::CellListLength { length: data.len() });
+        }
+        data.chunks_exact(Self::COORDS_LENGTH)
+            .map(Self::decode)
+            .collect()
+    }
+
+    pub fn validate(&self, width: u16, height: u16) -> Result<(), ProtocolError> {
+        if self.x >= width || self.y >= height {
+            return Err(ProtocolError::CellOutOfBounds {
+                x: self.x,
+                y: self.y,
+                width,
+                height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A protocol message, owning its payload by default. Parsing borrows the payload from the
+/// received bytes instead, see `WsMessage::parse`.
+#[derive(Debug)]
+pub struct WsMessage<P = Vec<u8>> {
+    pub version: u8,
+    pub msg_type: u8,
+    pub flags: u8,
+    pub payload: P,
+}
+
+impl<P: AsRef<[u8]>> WsMessage<P> {
+    /// Header (version, type, flags, big-endian u32 payload length) followed by the payload,
+    /// and its checksum with `FLAG_CRC32`
+    pub fn encode(&self) -> Vec<u8> {
+        let payload = self.payload.as_ref();
+        let checksum_length = if self.flags & FLAG_CRC32 != 0 {
+            CHECKSUM_LENGTH
+        } else {
+            0
+        };
+        let payload_length = payload.len() + checksum_length;
+        let total_size = HEADER_LENGTH as usize + payload_length;
+        let mut buf = Vec::with_capacity(total_size);
+
+        buf.push(self.version);
+        buf.push(self.msg_type);
+        buf.push(self.flags);
+        buf.extend(&(payload_length as u32).to_be_bytes());
+        buf.extend(payload);
+        if checksum_length != 0 {
+            buf.extend(&crc32(payload).to_be_bytes());
+        }
+
+        debug!(
+            "Encoded message: version={}, type={}, flags={}, total_size={}",
+            self.version, self.msg_type, self.flags, total_size
+        );
+
+        buf
+    }
+
+    /// The same message with its payload converted, e.g. copied out of a parsed view
+    pub fn map_payload<Q>(self, f: impl FnOnce(P) -> Q) -> WsMessage<Q> {
+        WsMessage {
+            version: self.version,
+            msg_type: self.msg_type,
+            flags: self.flags,
+            payload: f(self.payload),
+        }
+    }
+}
+
+impl<'a> WsMessage<&'a [u8]> {
+    /// Parses a message without copying its payload. A header claiming more than
+    /// `max_payload_length` bytes is refused before anything else is looked at.
+    pub fn parse(data: &'a [u8], max_payload_length: usize) -> Result<Self, ProtocolError> {
+        let data_len = data.len();
+        debug!("Decoding WebSocket message of {} bytes", data_len);
+
+        if data_len < HEADER_LENGTH as usize {
+            return Err(ProtocolError::TooShort { length: data_len });
+        }
+
+        let version = data[0];
+        if version != PROTOCOL_VERSION {
+            return Err(ProtocolError::UnsupportedVersion { version });
+        }
+
+        let msg_type = data[1];
+        let flags = data[2];
+        let payload_length = u32::from_be_bytes([data[3], data[4], data[5], data[6]]) as usize;
+        if payload_length > max_payload_length {
+            return Err(ProtocolError::PayloadTooLarge {
+                payload_length,
+                max_payload_length,
+            });
+        }
+
+        if data_len != HEADER_LENGTH as usize + payload_length {
+            return Err(ProtocolError::LengthMismatch {
+                length: data_len,
+                payload_length,
+            });
+        }
+
+        // The checksum is verified and dropped, the flag stays so encoding adds it back
+        let mut payload = &data[HEADER_LENGTH as usize..];
+        if flags & FLAG_CRC32 != 0 {
+            let Some(body_length) = payload_length.checked_sub(CHECKSUM_LENGTH) else {
+                return Err(ProtocolError::MissingChecksum { payload_length });
+            };
+            let (body, checksum) = payload.split_at(body_length);
+            let expected = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+            let computed = crc32(body);
+            if computed != expected {
+                return Err(ProtocolError::ChecksumMismatch { expected, computed });
+            }
+            payload = body;
+        }
+
+        debug!(
+            "Successfully decoded message: version={}, type={}, flags={}, payload_len={}",
+            version,
+            msg_type,
+            flags,
+            payload.len()
+        );
+
+        Ok(Self {
+            version,
+            msg_type,
+            flags,
+            payload,
+        })
+    }
+}
+
+impl WsMessage {
+    /// Parses a message into one owning a copy of its payload
+    pub fn decode(data: &[u8]) -> Result<Self, ProtocolError> {
+        Ok(WsMessage::parse(data, usize::MAX)?.map_payload(<[u8]>::to_vec))
+    }
+
+    /// Splits the message into fragments of up to `max_payload` bytes, or keeps it whole
+    /// when it fits
+    pub fn fragment(self, max_payload: usize) -> Vec<WsMessage> {
+        if self.payload.len() <= max_payload || max_payload == 0 {
+            return alloc::vec![self];
+        }
+        let chunks = self.payload.chunks(max_payload);
+        let last = chunks.len() - 1;
+        chunks
+            .enumerate()
+            .map(|(i, chunk)| WsMessage {
+                version: self.version,
+                msg_type: self.msg_type,
+                flags: match i {
+                    0 => self.flags | FLAG_FRAGMENT_BEGIN,
+                    i if i == last => FLAG_FRAGMENT_END,
+                    _ => FLAG_FRAGMENT_CONTINUATION,
+                },
+                payload: chunk.to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Puts fragmented messages back together, refusing any that grow past `max_length`
+/// payload bytes. One message is reassembled at a time, whole messages may come in between.
+#[derive(Debug)]
+pub struct Reassembly {
+    max_length: usize,
+    pending: Option<WsMessage>,
+}
+
+impl Reassembly {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            pending: None,
+        }
+    }
+
+    /// Takes the next message received, returning it once it's complete. A fragment out of
+    /// order or past the size limit drops the message being reassembled.
+    pub fn push(&mut self, msg: WsMessage) -> Result<Option<WsMessage>, ProtocolError> {
+        let fragment = msg.flags & FRAGMENT_FLAGS;
+        if fragment == 0 {
+            return Ok(Some(msg));
+        }
+        let unexpected = ProtocolError::UnexpectedFragment {
+            msg_type: msg.msg_type,
+            flags: msg.flags,
+        };
+
+        if fragment == FLAG_FRAGMENT_BEGIN {
+            if self.pending.take().is_some() {
+                return Err(unexpected);
+            }
+            self.check_length(msg.payload.len())?;
+            self.pending = Some(WsMessage {
+                flags: msg.flags & !FRAGMENT_FLAGS,
+                ..msg
+            });
+            return Ok(None);
+        }
+        let Some(pending) = self.pending.as_mut().filter(|pending| {
+            pending.msg_type == msg.msg_type
+                && (fragment == FLAG_FRAGMENT_CONTINUATION || fragment == FLAG_FRAGMENT_END)
+        }) else {
+            self.pending = None;
+            return Err(unexpected);
+        };
+        let length = pending.payload.len() + msg.payload.len();
+        pending.payload.extend_from_slice(&msg.payload);
+        if let Err(e) = self.check_length(length) {
+            self.pending = None;
+            return Err(e);
+        }
+
+        if fragment == FLAG_FRAGMENT_END {
+            return Ok(self.pending.take());
+        }
+        Ok(None)
+    }
+
+    fn check_length(&self, length: usize) -> Result<(), ProtocolError> {
+        if length > self.max_length {
+            return Err(ProtocolError::FragmentedTooLarge {
+                length,
+                max_length: self.max_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn cell(x: u16, y: u16) -> CellPayload {
+        CellPayload { x, y, rgb: None }
+    }
+
+    #[test]
+    fn cell_payload_roundtrip() {
+        let plain = cell(300, 2);
+        assert_eq!(plain.encode(), vec![1, 44, 0, 2]);
+        assert_eq!(CellPayload::decode(&plain.encode()).unwrap(), plain);
+
+        let colored = CellPayload {
+            rgb: Some([1, 2, 3]),
+            ..plain
+        };
+        assert_eq!(CellPayload::decode(&colored.encode()).unwrap(), colored);
+
+        assert!(CellPayload::decode(&[0, 1]).is_err());
+        assert!(CellPayload::decode(&[0, 1, 0, 2, 9]).is_err());
+
+        let (coords, rest) = CellPayload::decode_prefix(&[0, 1, 0, 2, b'!']).unwrap();
+        assert_eq!((coords, rest), (cell(1, 2), &b"!"[..]));
+        assert!(CellPayload::decode_prefix(&[0, 1]).is_err());
+
+        let list = CellPayload::decode_list(&[0, 1, 0, 2, 0, 3, 0, 4]).unwrap();
+        assert_eq!(list, vec![cell(1, 2), cell(3, 4)]);
+        assert!(CellPayload::decode_list(&[0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn cell_payload_validation() {
+        assert!(cell(99, 99).validate(100, 100).is_ok());
+        assert!(cell(100, 0).validate(100, 100).is_err());
+        assert!(cell(0, 100).validate(100, 100).is_err());
+    }
+
+    #[test]
+    fn checksummed_payloads_roundtrip_and_catch_corruption() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let msg = WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: 42,
+            flags: FLAG_CRC32,
+            payload: b"cells".to_vec(),
+        };
+        let mut encoded = msg.encode();
+        assert_eq!(encoded.len(), HEADER_LENGTH as usize + 5 + CHECKSUM_LENGTH);
+        let decoded = WsMessage::decode(&encoded).unwrap();
+        assert_eq!(decoded.payload, b"cells");
+        assert_eq!(decoded.flags, FLAG_CRC32);
+
+        encoded[HEADER_LENGTH as usize] ^= 0x01;
+        assert!(matches!(
+            WsMessage::decode(&encoded),
+            Err(ProtocolError::ChecksumMismatch { .. })
+        ));
+        let truncated = [PROTOCOL_VERSION, 42, FLAG_CRC32, 0, 0, 0, 2, 0, 0];
+        assert_eq!(
+            WsMessage::decode(&truncated).unwrap_err(),
+            ProtocolError::MissingChecksum { payload_length: 2 }
+        );
+    }
+
+    #[test]
+    fn parsing_borrows_the_payload_and_caps_its_length() {
+        let encoded = WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: 42,
+            flags: 0,
+            payload: b"cells".to_vec(),
+        }
+        .encode();
+        let parsed = WsMessage::parse(&encoded, 5).unwrap();
+        assert!(core::ptr::eq(
+            parsed.payload,
+            &encoded[HEADER_LENGTH as usize..]
+        ));
+        assert_eq!(
+            WsMessage::parse(&encoded, 4).unwrap_err(),
+            ProtocolError::PayloadTooLarge {
+                payload_length: 5,
+                max_payload_length: 4
+            }
+        );
+        // The claim is refused before the missing bytes are noticed
+        let oversized = [PROTOCOL_VERSION, 42, 0, 0xff, 0xff, 0xff, 0xff];
+        assert!(matches!(
+            WsMessage::parse(&oversized, 1024),
+            Err(ProtocolError::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn fragments_reassemble_into_the_message() {
+        let msg = |flags, payload: &[u8]| WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: 42,
+            flags,
+            payload: payload.to_vec(),
+        };
+        let fragments = msg(FLAG_SEQUENCED, b"0123456789").fragment(4);
+        let flags: Vec<u8> = fragments.iter().map(|fragment| fragment.flags).collect();
+        assert_eq!(
+            flags,
+            [
+                FLAG_SEQUENCED | FLAG_FRAGMENT_BEGIN,
+                FLAG_FRAGMENT_CONTINUATION,
+                FLAG_FRAGMENT_END
+            ]
+        );
+        assert_eq!(msg(0, b"0123").fragment(4).len(), 1);
+
+        let mut reassembly = Reassembly::new(16);
+        let mut fragments = fragments.into_iter();
+        assert!(
+            reassembly
+                .push(fragments.next().unwrap())
+                .unwrap()
+                .is_none()
+        );
+        // Whole messages pass while a fragmented one is pending
+        let whole = reassembly.push(msg(0, b"hi")).unwrap().unwrap();
+        assert_eq!(whole.payload, b"hi");
+        assert!(
+            reassembly
+                .push(fragments.next().unwrap())
+                .unwrap()
+                .is_none()
+        );
+        let reassembled = reassembly.push(fragments.next().unwrap()).unwrap().unwrap();
+        assert_eq!(reassembled.payload, b"0123456789");
+        assert_eq!(reassembled.flags, FLAG_SEQUENCED);
+
+        assert!(reassembly.push(msg(FLAG_FRAGMENT_END, b"x")).is_err());
+        let mut too_large = msg(0, &[0; 20]).fragment(8).into_iter();
+        assert!(reassembly.push(too_large.next().unwrap()).is_ok());
+        assert!(reassembly.push(too_large.next().unwrap()).is_ok());
+        assert_eq!(
+            reassembly.push(too_large.next().unwrap()).unwrap_err(),
+            ProtocolError::FragmentedTooLarge {
+                length: 20,
+                max_length: 16
+            }
+        );
+        // The refused message is dropped, the next one starts over
+        let fresh = reassembly.push(msg(FLAG_FRAGMENT_BEGIN, b"a")).unwrap();
+        assert!(fresh.is_none());
+    }
+
+    #[test]
+    fn generation_hash_is_fnv1a() {
+        assert_eq!(generation_hash(b""), 0x811c_9dc5);
+        assert_eq!(generation_hash(b"a"), 0xe40c_292c);
+        assert_eq!(generation_hash(b"foobar"), 0xbf9c_f968);
+    }
+}
@@ -0,0 +1,42 @@
+/// Rectangle of cells, e.g. the bounding box of the cells a step changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Region {
+    /// The region covering just the cell at (x, y)
+    pub fn cell(x: u16, y: u16) -> Self {
+        Self {
+            x,
+            y,
+            width: 1,
+            height: 1,
+        }
+    }
+
+    /// Grows the region to cover the cell at (x, y)
+    pub fn include(&mut self, x: u16, y: u16) {
+        let right = (self.x + self.width).max(x + 1);
+        let bottom = (self.y + self.height).max(y + 1);
+        self.x = self.x.min(x);
+        self.y = self.y.min(y);
+        self.width = right - self.x;
+        self.height = bottom - self.y;
+    }
+
+    pub fn area(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// Grows `region` to cover the cell at (x, y), starting it when there's none yet
+pub(crate) fn include_cell(region: &mut Option<Region>, x: u16, y: u16) {
+    match region {
+        Some(region) => region.include(x, y),
+        None => *region = Some(Region::cell(x, y)),
+    }
+}
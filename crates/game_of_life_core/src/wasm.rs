@@ -0,0 +1,131 @@
+//! JavaScript bindings, so the browser client frames messages, hashes generations and
+//! steps grids with the same code as the server
+
+use alloc::{string::ToString, vec::Vec};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    GameOfLifeBits,
+    protocol::{self, PROTOCOL_VERSION, WsMessage},
+};
+
+/// Header and payload of a received message
+#[wasm_bindgen]
+pub struct DecodedMessage {
+    version: u8,
+    msg_type: u8,
+    flags: u8,
+    payload: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl DecodedMessage {
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    #[wasm_bindgen(getter, js_name = msgType)]
+    pub fn msg_type(&self) -> u8 {
+        self.msg_type
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+}
+
+#[wasm_bindgen(js_name = encodeMessage)]
+pub fn encode_message(msg_type: u8, flags: u8, payload: &[u8]) -> Vec<u8> {
+    WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type,
+        flags,
+        payload: payload.to_vec(),
+    }
+    .encode()
+}
+
+/// Throws on a malformed header, with the message the server would log
+#[wasm_bindgen(js_name = decodeMessage)]
+pub fn decode_message(data: &[u8]) -> Result<DecodedMessage, JsError> {
+    let message = WsMessage::decode(data).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(DecodedMessage {
+        version: message.version,
+        msg_type: message.msg_type,
+        flags: message.flags,
+        payload: message.payload,
+    })
+}
+
+#[wasm_bindgen(js_name = generationHash)]
+pub fn generation_hash(bits: &[u8]) -> u32 {
+    protocol::generation_hash(bits)
+}
+
+/// `GameOfLifeBits` exchanging grids as packed bits, the layout of keyframes and
+/// generation hashes
+#[wasm_bindgen]
+pub struct Life {
+    game: GameOfLifeBits,
+}
+
+#[wasm_bindgen]
+impl Life {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u16, height: u16) -> Life {
+        Life {
+            game: GameOfLifeBits::empty(width, height),
+        }
+    }
+
+    pub fn load(&mut self, bits: &[u8]) {
+        self.game.load_packed_bits(bits);
+    }
+
+    pub fn bits(&self) -> Vec<u8> {
+        self.game.to_packed_bits()
+    }
+
+    pub fn step(&mut self) {
+        self.game.step();
+    }
+
+    pub fn population(&self) -> u32 {
+        self.game.population_count()
+    }
+
+    pub fn hash(&self) -> u32 {
+        protocol::generation_hash(&self.game.to_packed_bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_match_the_codec() {
+        let encoded = encode_message(42, 5, b"cells");
+        let decoded = decode_message(&encoded).unwrap();
+        assert_eq!(
+            (decoded.version(), decoded.msg_type(), decoded.flags()),
+            (PROTOCOL_VERSION, 42, 5)
+        );
+        assert_eq!(decoded.payload(), b"cells");
+
+        // Blinker, vertical then horizontal
+        let mut life = Life::new(3, 3);
+        life.load(&[0b0100_1001, 0b0000_0000]);
+        life.step();
+        assert_eq!(life.bits(), [0b0001_1100, 0]);
+        assert_eq!(life.population(), 3);
+        assert_eq!(life.hash(), generation_hash(&life.bits()));
+    }
+}
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use gol_htmx_rust::constants::DEFAULT_MAX_PAYLOAD_LENGTH;
+use gol_htmx_rust::protocol::{WsMessage, decode_ws_message};
+
+// Any bytes a client sends in a binary frame
+fuzz_target!(|data: &[u8]| {
+    let Ok(parsed) = decode_ws_message(data.to_vec().into(), DEFAULT_MAX_PAYLOAD_LENGTH) else {
+        return;
+    };
+    // What decodes encodes back to a message that decodes the same
+    let encoded = parsed.encode();
+    let reparsed =
+        WsMessage::parse(&encoded, DEFAULT_MAX_PAYLOAD_LENGTH).expect("Encoded message decodes");
+    assert_eq!(reparsed.msg_type, parsed.msg_type);
+    assert_eq!(reparsed.flags, parsed.flags);
+    assert_eq!(reparsed.payload, &parsed.payload[..]);
+});
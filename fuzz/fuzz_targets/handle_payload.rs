@@ -0,0 +1,48 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use std::sync::{Arc, LazyLock};
+
+use gol_htmx_rust::constants::message_types;
+use gol_htmx_rust::payload::{WsPayload, is_handled};
+use gol_htmx_rust::protocol::{PROTOCOL_VERSION, WsMessage};
+use gol_htmx_rust::room::{RoomAccess, RoomSettings, RoomState};
+
+// A small board keeps every run fast, the room lives across runs like a real one
+static ROOM: LazyLock<Arc<RoomState>> = LazyLock::new(|| {
+    let settings = RoomSettings {
+        canvas_width: 24,
+        canvas_height: 16,
+        ..RoomSettings::default()
+    };
+    RoomState::new("fuzz".into(), &settings, RoomAccess::default())
+});
+
+// Messages that spawn searches or touch the disk rather than only the room
+const SKIPPED: [u8; 3] = [
+    message_types::START_SOUP_SEARCH,
+    message_types::SAVE_STATE,
+    message_types::LOAD_STATE,
+];
+
+// The first byte picks the message type, the rest is its payload
+fuzz_target!(|data: &[u8]| {
+    let Some((&msg_type, payload)) = data.split_first() else {
+        return;
+    };
+    if !is_handled(msg_type) || SKIPPED.contains(&msg_type) {
+        return;
+    }
+    let payload = WsPayload {
+        parsed: WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type,
+            flags: 0,
+            payload: Bytes::copy_from_slice(payload),
+        },
+        room: ROOM.clone(),
+        connection_id: "fuzz".to_string(),
+    };
+    let _ = payload.handle_payload();
+});
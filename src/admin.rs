@@ -0,0 +1,93 @@
+use crate::constants::message_types;
+
+/// Who may send privileged messages. Without a configured token every client may, like
+/// before the admin surface existed; with one only connections that presented it.
+#[derive(Debug, Default)]
+pub struct AdminAccess {
+    token: Option<String>,
+}
+
+impl AdminAccess {
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+
+    /// Whether privileged messages are open to every client
+    pub fn is_open(&self) -> bool {
+        self.token.is_none()
+    }
+
+    /// Whether `token` is the admin token, compared in constant time
+    pub fn verify(&self, token: &str) -> bool {
+        self.token
+            .as_deref()
+            .is_some_and(|expected| constant_time_eq(expected, token))
+    }
+
+    /// Whether a connection that presented `token` when joining starts out as an admin
+    pub fn admits(&self, token: Option<&str>) -> bool {
+        self.is_open() || token.is_some_and(|token| self.verify(token))
+    }
+}
+
+/// Whether `given` is `expected`, taking as long wherever they differ so a secret can't be
+/// guessed a byte at a time. Only the length leaks.
+pub fn constant_time_eq(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Messages that wipe, pause or take over a room for everyone, create rooms, keep cores
+/// busy, or write to the server's disk. Only admins may send them.
+pub fn is_privileged(msg_type: u8) -> bool {
+    matches!(
+        msg_type,
+        message_types::KILL_ALL_GOL_CELLS
+            | message_types::SET_SIMULATION_SPEED
+            | message_types::PAUSE_SIMULATION
+            | message_types::RESUME_SIMULATION
+            | message_types::SET_SEED
+            | message_types::SET_AUTOPLAY
+            | message_types::KICK_CLIENT
+            | message_types::SAVE_STATE
+            | message_types::LOAD_STATE
+            | message_types::RESTORE_CHECKPOINT
+            | message_types::SELECT_ENGINE
+            | message_types::START_BOT
+            | message_types::STOP_BOT
+            | message_types::START_SOUP_SEARCH
+            | message_types::STOP_SOUP_SEARCH
+            | message_types::ADD_SCHEDULE_ENTRY
+            | message_types::REMOVE_SCHEDULE_ENTRY
+            | message_types::LIST_SCHEDULE
+            | message_types::CREATE_ROOM
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_token_gates_privileged_messages() {
+        let open = AdminAccess::default();
+        assert!(open.admits(None));
+        assert!(!open.verify(""));
+
+        let access = AdminAccess::new(Some("s3cret".to_string()));
+        assert!(!access.admits(None));
+        assert!(!access.admits(Some("s3cre")));
+        assert!(!access.admits(Some("s3creT")));
+        assert!(access.admits(Some("s3cret")));
+
+        assert!(is_privileged(message_types::KILL_ALL_GOL_CELLS));
+        assert!(is_privileged(message_types::CREATE_ROOM));
+        assert!(is_privileged(message_types::START_SOUP_SEARCH));
+        assert!(!is_privileged(message_types::LIST_ROOMS));
+        assert!(!is_privileged(message_types::AWAKEN_RANDOM_GOL_CELL));
+    }
+}
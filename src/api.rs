@@ -0,0 +1,507 @@
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::{
+    constants::{MAX_CELL_BATCH, STATS_REFRESH_INTERVAL_MS, message_types},
+    metrics::METRICS,
+    payload::WsPayload,
+    protocol::{CellPayload, PROTOCOL_VERSION, WsMessage},
+    room::{DEFAULT_ROOM, JoinCredentials, RoomError, RoomState},
+    snapshots::list_snapshots,
+    state::AppState,
+    watchdog::RoomHealth,
+};
+
+// Connection id the room and its journal see for commands sent over HTTP
+const API_CONNECTION_ID: &str = "http-api";
+
+/// GET /api/stats/live
+pub async fn live_stats(State(state): State<Arc<AppState>>) -> Response {
+    room_stats_response(&state, DEFAULT_ROOM)
+}
+
+/// GET /api/stats/live/{room}
+pub async fn live_room_stats(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+) -> Response {
+    room_stats_response(&state, &room)
+}
+
+fn room_stats_response(state: &AppState, room: &str) -> Response {
+    let Some(stats) = state
+        .live_stats
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(room)
+        .cloned()
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No stats for room {:?}", room),
+        )
+            .into_response();
+    };
+    let cache_control = format!(
+        "public, max-age={}",
+        STATS_REFRESH_INTERVAL_MS.div_ceil(1000)
+    );
+
+    ([(header::CACHE_CONTROL, cache_control)], Json(stats)).into_response()
+}
+
+/// GET /api/rooms, the public rooms with their mode, clients and canvas size for a lobby
+pub async fn rooms(State(state): State<Arc<AppState>>) -> Response {
+    (
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(state.rooms.summaries()),
+    )
+        .into_response()
+}
+
+/// What /healthz and /readyz answer with
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub shutting_down: bool,
+    pub free_connection_slots: usize,
+    pub rooms: Vec<RoomHealth>,
+}
+
+/// GET /healthz, 503 unless every room's simulation loop, channel and engine work
+pub async fn healthz(State(state): State<Arc<AppState>>) -> Response {
+    health_response(&state, false)
+}
+
+/// GET /readyz, 503 when unhealthy, and while shutting down or out of connection slots
+pub async fn readyz(State(state): State<Arc<AppState>>) -> Response {
+    health_response(&state, true)
+}
+
+fn health_response(state: &AppState, readiness: bool) -> Response {
+    let mut rooms: Vec<RoomHealth> = state
+        .rooms()
+        .iter()
+        .map(|room| RoomHealth::check(room))
+        .collect();
+    rooms.sort_by(|a, b| a.room.cmp(&b.room));
+    let report = HealthReport {
+        healthy: rooms.iter().all(RoomHealth::is_healthy),
+        shutting_down: state.shutdown.is_cancelled(),
+        free_connection_slots: state.free_connection_slots(),
+        rooms,
+    };
+
+    let accepting = !report.shutting_down && report.free_connection_slots > 0;
+    let ok = report.healthy && (accepting || !readiness);
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        warn!("Health check failed: {:?}", report);
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// GET /metrics, message handling and tick timings for Prometheus
+pub async fn metrics() -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(),
+    )
+        .into_response()
+}
+
+/// GET /api/gol/grid.bin
+pub async fn gol_grid(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    grid_response(&state, DEFAULT_ROOM, &credentials)
+}
+
+/// GET /api/rooms/{room}/gol/grid.bin?password=...
+pub async fn room_gol_grid(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    grid_response(&state, &room, &credentials)
+}
+
+fn grid_response(state: &AppState, room: &str, credentials: &JoinCredentials) -> Response {
+    let Some(room) = state.room(room) else {
+        return (StatusCode::NOT_FOUND, format!("No room {:?}", room)).into_response();
+    };
+    // Reading the grid doesn't spend an invite, only the password opens a private room here
+    if !room.access.has_password(credentials.password.as_deref()) {
+        return RoomError::AccessDenied(room.id.clone()).into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/octet-stream"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        room.gol.grid_dump(),
+    )
+        .into_response()
+}
+
+/// Game of Life board as JSON, what the GOL endpoints answer with
+#[derive(Serialize)]
+struct GolState {
+    room: String,
+    generation: u64,
+    population: usize,
+    width: u16,
+    height: u16,
+    // Live cells as [x, y]
+    cells: Vec<[u16; 2]>,
+}
+
+impl GolState {
+    fn of(room: &RoomState) -> Self {
+        let (generation, population) = room.gol.generation_stats();
+        let (width, height) = room.gol.dimensions();
+        let cells = room
+            .gol
+            .generation_cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, alive)| **alive)
+                    .map(move |(x, _)| [x as u16, y as u16])
+            })
+            .collect();
+        Self {
+            room: room.id.clone(),
+            generation,
+            population,
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CellsRequest {
+    // Cells to awaken as [x, y], out-of-bounds ones are skipped
+    cells: Vec<[u16; 2]>,
+}
+
+/// GET /api/gol/state
+pub async fn gol_state(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    gol_command_response(&state, DEFAULT_ROOM, &credentials, None)
+}
+
+/// GET /api/rooms/{room}/gol/state?password=...
+pub async fn room_gol_state(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    gol_command_response(&state, &room, &credentials, None)
+}
+
+/// POST /api/gol/step
+pub async fn gol_step(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let command = (message_types::ADVANCE_GOL_GENERATION, Vec::new());
+    gol_command_response(&state, DEFAULT_ROOM, &credentials, Some(command))
+}
+
+/// POST /api/rooms/{room}/gol/step?password=...
+pub async fn room_gol_step(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let command = (message_types::ADVANCE_GOL_GENERATION, Vec::new());
+    gol_command_response(&state, &room, &credentials, Some(command))
+}
+
+/// POST /api/gol/reset
+pub async fn gol_reset(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let command = (message_types::CREATE_NEW_GOL_GENERATION, Vec::new());
+    gol_command_response(&state, DEFAULT_ROOM, &credentials, Some(command))
+}
+
+/// POST /api/rooms/{room}/gol/reset?password=...
+pub async fn room_gol_reset(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let command = (message_types::CREATE_NEW_GOL_GENERATION, Vec::new());
+    gol_command_response(&state, &room, &credentials, Some(command))
+}
+
+/// POST /api/gol/cells with `{"cells": [[x, y], ...]}`
+pub async fn gol_cells(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+    Json(request): Json<CellsRequest>,
+) -> Response {
+    cells_response(&state, DEFAULT_ROOM, &credentials, &request)
+}
+
+/// POST /api/rooms/{room}/gol/cells?password=... with `{"cells": [[x, y], ...]}`
+pub async fn room_gol_cells(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+    Json(request): Json<CellsRequest>,
+) -> Response {
+    cells_response(&state, &room, &credentials, &request)
+}
+
+fn cells_response(
+    state: &AppState,
+    room: &str,
+    credentials: &JoinCredentials,
+    request: &CellsRequest,
+) -> Response {
+    if request.cells.is_empty() || request.cells.len() > MAX_CELL_BATCH {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Between 1 and {} cells per request", MAX_CELL_BATCH),
+        )
+            .into_response();
+    }
+    let payload = request
+        .cells
+        .iter()
+        .flat_map(|&[x, y]| CellPayload { x, y, rgb: None }.encode())
+        .collect();
+    let command = (message_types::AWAKEN_CELLS_BATCH, payload);
+    gol_command_response(state, room, credentials, Some(command))
+}
+
+/// Runs `command`, a message type and its payload, on the room like a WebSocket client
+/// sending it would, then answers with the board
+fn gol_command_response(
+    state: &AppState,
+    room: &str,
+    credentials: &JoinCredentials,
+    command: Option<(u8, Vec<u8>)>,
+) -> Response {
+    let Some(room) = state.room(room) else {
+        return (StatusCode::NOT_FOUND, format!("No room {:?}", room)).into_response();
+    };
+    if !room.access.has_password(credentials.password.as_deref()) {
+        return RoomError::AccessDenied(room.id.clone()).into_response();
+    }
+
+    if let Some((msg_type, payload)) = command {
+        // Another pattern is on the canvas until a client switches back to GOL
+        if let Err(e) = room.modes.admit(msg_type) {
+            return (StatusCode::CONFLICT, e.to_string()).into_response();
+        }
+        if !run_command(&room, msg_type, payload) {
+            return (StatusCode::BAD_REQUEST, "Command rejected").into_response();
+        }
+    }
+    (
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(GolState::of(&room)),
+    )
+        .into_response()
+}
+
+// Handles, journals and broadcasts the command, returning whether the room accepted it
+fn run_command(room: &Arc<RoomState>, msg_type: u8, payload: Vec<u8>) -> bool {
+    let payload = WsPayload {
+        parsed: WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type,
+            flags: 0,
+            payload: Bytes::from(payload),
+        },
+        room: room.clone(),
+        connection_id: API_CONNECTION_ID.to_string(),
+    };
+    let response = match &room.journal {
+        Some(journal) => journal.record(API_CONNECTION_ID, &payload),
+        None => payload.handle_payload(),
+    };
+    match response {
+        Ok(Some(response)) => {
+            info!(
+                "Ran {} on room {:?} over HTTP",
+                message_types::name(msg_type).unwrap_or_default(),
+                room.id
+            );
+            // No subscribers is fine, the board changed either way
+            let _ = room.channel.send(response);
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            warn!(
+                "Ran {} on room {:?} over HTTP, but couldn't draw it: {}",
+                message_types::name(msg_type).unwrap_or_default(),
+                room.id,
+                e
+            );
+            false
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RoomInvite {
+    room: String,
+    token: String,
+}
+
+/// POST /api/rooms/{room}/invites?password=...
+///
+/// Mints a single-use invite token for a private room; only the password holder may do so.
+pub async fn create_room_invite(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let Some(room) = state.room(&room) else {
+        return (StatusCode::NOT_FOUND, format!("No room {:?}", room)).into_response();
+    };
+    if !room.access.is_private() {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Room {:?} is public, no invite needed", room.id),
+        )
+            .into_response();
+    }
+    if !room.access.has_password(credentials.password.as_deref()) {
+        return RoomError::AccessDenied(room.id.clone()).into_response();
+    }
+
+    match room.access.create_invite() {
+        Some(token) => {
+            info!("Created invite for room {:?}", room.id);
+            (
+                StatusCode::CREATED,
+                Json(RoomInvite {
+                    room: room.id.clone(),
+                    token,
+                }),
+            )
+                .into_response()
+        }
+        None => RoomError::TooManyInvites(room.id.clone()).into_response(),
+    }
+}
+
+/// GET /api/rooms/{room}/journal?password=...
+///
+/// Commands the room accepted since its last journal snapshot, for moderators to audit;
+/// private rooms require the password.
+pub async fn room_journal(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    let Some(room) = state.room(&room) else {
+        return (StatusCode::NOT_FOUND, format!("No room {:?}", room)).into_response();
+    };
+    if !room.access.has_password(credentials.password.as_deref()) {
+        return RoomError::AccessDenied(room.id.clone()).into_response();
+    }
+    let Some(journal) = &room.journal else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Room {:?} isn't journaled", room.id),
+        )
+            .into_response();
+    };
+
+    match journal.entries() {
+        Ok(entries) => Json(entries).into_response(),
+        Err(e) => {
+            error!("Failed to read the journal of room {:?}: {:#}", room.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read journal").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotQuery {
+    room: Option<String>,
+    tag: Option<String>,
+}
+
+/// GET /api/snapshots?room=...&tag=...
+///
+/// Saved snapshots with their detected properties, filtered by room and/or tag.
+pub async fn snapshots(Query(query): Query<SnapshotQuery>) -> Response {
+    match list_snapshots(query.room.as_deref(), query.tag.as_deref()).await {
+        Ok(snapshots) => Json(snapshots).into_response(),
+        Err(e) => {
+            error!("Failed to list snapshots: {:#}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to list snapshots",
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_commands_drive_the_board_like_messages() {
+        let room = RoomState::for_test();
+        let mut frames = room.channel.subscribe();
+        room.gol.kill_all_cells().unwrap();
+
+        // A blinker flips between a row and a column
+        let blinker = [(1, 2), (2, 2), (3, 2)]
+            .iter()
+            .flat_map(|&(x, y)| CellPayload { x, y, rgb: None }.encode())
+            .collect();
+        assert!(run_command(
+            &room,
+            message_types::AWAKEN_CELLS_BATCH,
+            blinker
+        ));
+        assert_eq!(GolState::of(&room).cells, [[1, 2], [2, 2], [3, 2]]);
+        assert!(run_command(
+            &room,
+            message_types::ADVANCE_GOL_GENERATION,
+            Vec::new()
+        ));
+        let state = GolState::of(&room);
+        assert_eq!(state.cells, [[2, 1], [2, 2], [2, 3]]);
+        assert_eq!(state.population, 3);
+
+        assert!(!run_command(
+            &room,
+            message_types::AWAKEN_CELLS_BATCH,
+            vec![0; 3]
+        ));
+        assert!(frames.try_recv().is_ok());
+        assert!(frames.try_recv().is_ok());
+        assert!(frames.try_recv().is_err());
+    }
+}
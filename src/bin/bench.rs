@@ -0,0 +1,178 @@
+use clap::Parser;
+use game_of_life_core::gol_simd::SimdPath;
+use game_of_life_core::{ColorScheme, GameOfLifeBits, GameOfLifeSparse, GameOfLifeVecs};
+use rand::{SeedableRng, rngs::StdRng};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gol_htmx_rust::protocol::compress_ws_message;
+use gol_htmx_rust::utils::create_frame_message;
+
+/// Steps every Game of Life engine on random boards and reports generations per second, and
+/// how fast frames of those boards encode. Build with --release, debug numbers mean little.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct BenchArgs {
+    /// Board sizes to run, WIDTHxHEIGHT separated by commas
+    #[arg(long, value_delimiter = ',', value_parser = parse_size,
+        default_value = "100x100,256x256,1024x1024")]
+    sizes: Vec<(u16, u16)>,
+    /// Generations each engine is timed for at every size
+    #[arg(long, default_value_t = 200)]
+    generations: u32,
+    /// Frames encoded at every size
+    #[arg(long, default_value_t = 50)]
+    frames: u32,
+    /// Seed of the random boards, the same seed benchmarks the same boards
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+fn parse_size(size: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("{:?} isn't WIDTHxHEIGHT", size);
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width: u16 = width.trim().parse().map_err(|_| invalid())?;
+    let height: u16 = height.trim().parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(format!("{:?} has no cells", size));
+    }
+    Ok((width, height))
+}
+
+/// Times `generations` steps after a few untimed warm-up steps, in generations per second
+fn generations_per_sec(generations: u32, mut step: impl FnMut()) -> f64 {
+    for _ in 0..generations.min(5) {
+        step();
+    }
+    let started = Instant::now();
+    for _ in 0..generations {
+        step();
+    }
+    generations as f64 / started.elapsed().as_secs_f64()
+}
+
+fn report(engine: &str, (width, height): (u16, u16), gens_per_sec: f64) {
+    let cells_per_sec = gens_per_sec * width as f64 * height as f64;
+    println!(
+        "{:<22} {:>5}x{:<5} {:>12.1} gen/s {:>10.1} Mcells/s",
+        engine,
+        width,
+        height,
+        gens_per_sec,
+        cells_per_sec / 1e6
+    );
+}
+
+fn bench_engines(args: &BenchArgs, size: (u16, u16)) {
+    let (width, height) = size;
+    // Every engine starts from the same board
+    let vecs = || GameOfLifeVecs::new(width, height, &mut StdRng::seed_from_u64(args.seed));
+    let board = vecs().to_packed_bits();
+
+    let mut sequential = vecs();
+    report(
+        "vecs sequential",
+        size,
+        generations_per_sec(args.generations, || sequential.step_fallback()),
+    );
+    let mut parallel = vecs();
+    report(
+        "vecs parallel",
+        size,
+        generations_per_sec(args.generations, || parallel.step()),
+    );
+    assert_eq!(sequential.population(), parallel.population());
+
+    let bits = || {
+        let mut bits = GameOfLifeBits::empty(width, height);
+        bits.load_packed_bits(&board);
+        bits
+    };
+    let mut per_cell = bits();
+    report(
+        "bits per cell",
+        size,
+        generations_per_sec(args.generations, || per_cell.step_per_cell()),
+    );
+    let mut simd = bits();
+    report(
+        &format!("bits {:?}", SimdPath::detect()).to_lowercase(),
+        size,
+        generations_per_sec(args.generations, || simd.step()),
+    );
+    let mut parallel = bits();
+    report(
+        "bits parallel",
+        size,
+        generations_per_sec(args.generations, || parallel.step_parallel()),
+    );
+    let mut sparse = GameOfLifeSparse::empty(width, height);
+    sparse.load_packed_bits(&board);
+    report(
+        "sparse",
+        size,
+        generations_per_sec(args.generations, || sparse.step()),
+    );
+    // Every path steps the same rule, so the boards agree
+    assert_eq!(per_cell.to_packed_bits(), simd.to_packed_bits());
+    assert_eq!(simd.to_packed_bits(), parallel.to_packed_bits());
+    assert_eq!(parallel.to_packed_bits(), sparse.to_packed_bits());
+}
+
+/// Builds and deflates frames of a stepped board the way rooms broadcast them
+fn bench_frames(args: &BenchArgs, (width, height): (u16, u16)) {
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut game = GameOfLifeVecs::new(width, height, &mut rng);
+    for _ in 0..10 {
+        game.step();
+    }
+    let frame_bytes = width as usize * height as usize * 3;
+    let throughput = |elapsed: Duration| {
+        let frames = args.frames as f64 / elapsed.as_secs_f64();
+        (frames, frames * frame_bytes as f64 / 1e6)
+    };
+
+    let started = Instant::now();
+    let mut frame = None;
+    for _ in 0..args.frames {
+        let rgb_data = game.to_rgb_data(ColorScheme::default(), &mut rng);
+        frame =
+            Some(create_frame_message(width, height, rgb_data).expect("Frame of the board's size"));
+    }
+    let (frames_per_sec, mb_per_sec) = throughput(started.elapsed());
+    println!(
+        "{:<22} {:>5}x{:<5} {:>12.1} frames/s {:>7.1} MB/s",
+        "frame encode", width, height, frames_per_sec, mb_per_sec
+    );
+
+    let Some(frame) = frame else { return };
+    let started = Instant::now();
+    let mut compressed_length = frame.as_payload().len();
+    for _ in 0..args.frames {
+        if let Some(compressed) = compress_ws_message(black_box(&frame)) {
+            compressed_length = compressed.as_payload().len();
+        }
+    }
+    let (frames_per_sec, mb_per_sec) = throughput(started.elapsed());
+    println!(
+        "{:<22} {:>5}x{:<5} {:>12.1} frames/s {:>7.1} MB/s, {:.0}% of the frame",
+        "frame deflate",
+        width,
+        height,
+        frames_per_sec,
+        mb_per_sec,
+        compressed_length as f64 * 100.0 / frame.as_payload().len() as f64
+    );
+}
+
+fn main() {
+    let args = BenchArgs::parse();
+    if cfg!(debug_assertions) {
+        eprintln!("Debug build, run with --release for numbers worth comparing");
+    }
+    for &size in &args.sizes {
+        bench_engines(&args, size);
+        bench_frames(&args, size);
+        println!();
+    }
+}
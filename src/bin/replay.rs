@@ -0,0 +1,83 @@
+use anyhow::{Result, bail};
+use clap::Parser;
+use std::path::PathBuf;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use gol_htmx_rust::recording::{read_recording, replay_commands};
+use gol_htmx_rust::room::{RoomAccess, RoomSettings, RoomState};
+use gol_htmx_rust::saves::write_saved_state;
+
+/// Feeds a room's recorded client messages into a fresh room, at the pace they were sent
+#[derive(Debug, Parser)]
+#[command(version)]
+struct ReplayArgs {
+    /// Recording to replay, a `<room>.rec` file from the server's record_dir
+    file: PathBuf,
+    /// How many times faster than recorded to replay, 0 replays without waiting
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+    /// Session of the file to replay, counting from 0 [default: the last one]
+    #[arg(long)]
+    session: Option<usize>,
+    /// Saves the board the replay ends with here, LOAD_STATE can load it into a server
+    #[arg(long)]
+    save: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = ReplayArgs::parse();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+    if args.speed < 0.0 {
+        bail!("Speed {} is negative", args.speed);
+    }
+
+    let mut sessions = read_recording(&args.file)?;
+    let index = args.session.unwrap_or(sessions.len().saturating_sub(1));
+    if index >= sessions.len() {
+        bail!(
+            "{} has {} sessions, there is no session {}",
+            args.file.display(),
+            sessions.len(),
+            index
+        );
+    }
+    let (session, commands) = sessions.swap_remove(index);
+    info!(
+        "Replaying session {} of room {:?} started {}, {} commands",
+        index,
+        session.room,
+        session.started,
+        commands.len()
+    );
+
+    let settings = RoomSettings {
+        canvas_width: session.canvas_width,
+        canvas_height: session.canvas_height,
+        ..RoomSettings::default()
+    };
+    let room = RoomState::new(session.room.clone(), &settings, RoomAccess::default());
+    room.reseed(session.seed)?;
+    let applied = replay_commands(&room, &commands, args.speed).await;
+
+    let (generation, population) = room.gol.generation_stats();
+    info!(
+        "Applied {} of {} commands, ended at generation {} with {} live cells",
+        applied,
+        commands.len(),
+        generation,
+        population
+    );
+    if let Some(path) = &args.save {
+        let generation = write_saved_state(path, &room)?;
+        info!("Saved generation {} to {}", generation, path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,245 @@
+//! Scripted agents a room can host.
+//!
+//! Admins start and stop them with START_BOT and STOP_BOT. Each bot is a tokio task that
+//! wakes on its own interval and sends a command through the same dispatch, journal and
+//! broadcast path as a client's, under a connection id of its own, so the room can't tell
+//! it from a person clicking. Bots stop with the room.
+
+use bytes::Bytes;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    client::ClientMessage,
+    constants::{CHAOS_BOT_CELLS, CHAOS_BOT_INTERVAL_MS, GARDENER_BOT_INTERVAL_MS},
+    patterns::library::LibraryPattern,
+    payload::{ReplyRoute, WsPayload, reply_route},
+    protocol::{PROTOCOL_VERSION, WsMessage},
+    room::RoomState,
+    utils::FrameError,
+};
+
+/// Scripted behaviours a room can run, at most one of each
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BotKind {
+    /// Awakens a handful of random cells every turn
+    Chaos,
+    /// Stamps a glider somewhere every turn
+    Gardener,
+}
+
+impl BotKind {
+    pub const ALL: [BotKind; 2] = [BotKind::Chaos, BotKind::Gardener];
+
+    /// Bot for a wire id, in the order of `ALL`
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BotKind::Chaos => "chaos",
+            BotKind::Gardener => "gardener",
+        }
+    }
+
+    // Connection id the bot's commands are journaled and attributed under
+    fn connection_id(self) -> String {
+        format!("bot:{}", self.name())
+    }
+
+    fn interval(self) -> Duration {
+        Duration::from_millis(match self {
+            BotKind::Chaos => CHAOS_BOT_INTERVAL_MS,
+            BotKind::Gardener => GARDENER_BOT_INTERVAL_MS,
+        })
+    }
+
+    /// What the bot sends on a board of `width` x `height` cells
+    pub fn next_command(self, (width, height): (u16, u16), rng: &mut impl Rng) -> ClientMessage {
+        match self {
+            BotKind::Chaos => {
+                let cells: Vec<(u16, u16)> = (0..CHAOS_BOT_CELLS)
+                    .map(|_| (rng.random_range(0..width), rng.random_range(0..height)))
+                    .collect();
+                ClientMessage::awaken_cells(&cells)
+            }
+            BotKind::Gardener => ClientMessage::stamp_pattern(
+                LibraryPattern::Glider.id(),
+                rng.random_range(0..width),
+                rng.random_range(0..height),
+            ),
+        }
+    }
+}
+
+/// Bots running in a room, each stopped through its token
+#[derive(Debug, Default)]
+pub struct RoomBots {
+    running: Mutex<HashMap<BotKind, CancellationToken>>,
+}
+
+impl RoomBots {
+    /// Running bots in the order of `BotKind::ALL`
+    pub fn running(&self) -> Vec<BotKind> {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let mut kinds: Vec<BotKind> = running.keys().copied().collect();
+        kinds.sort();
+        kinds
+    }
+
+    /// Stops a bot before its next turn. Returns whether it was running.
+    pub fn stop(&self, kind: BotKind) -> bool {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        match running.remove(&kind) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops every bot, returning how many were running
+    pub fn stop_all(&self) -> usize {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        for token in running.values() {
+            token.cancel();
+        }
+        let stopped = running.len();
+        running.clear();
+        stopped
+    }
+}
+
+/// Runs `kind` in the room until it's stopped or the room is gone. Returns false if it was
+/// already running, or there's no runtime to run it on, as when replaying a recording.
+pub fn start_bot(room: &Arc<RoomState>, kind: BotKind) -> bool {
+    let Ok(runtime) = Handle::try_current() else {
+        warn!("No runtime to run the {} bot on", kind.name());
+        return false;
+    };
+    let token = CancellationToken::new();
+    {
+        let mut running = room.bots.running.lock().unwrap_or_else(|e| e.into_inner());
+        if running.contains_key(&kind) {
+            return false;
+        }
+        running.insert(kind, token.clone());
+    }
+
+    runtime.spawn(run_bot(Arc::downgrade(room), kind, token));
+    info!("Started the {} bot in room {:?}", kind.name(), room.id);
+    true
+}
+
+async fn run_bot(room: Weak<RoomState>, kind: BotKind, token: CancellationToken) {
+    let mut rng = StdRng::seed_from_u64(rand::random());
+    let mut turns = 0u64;
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => {
+                info!("The {} bot stopped after {} turns", kind.name(), turns);
+                return;
+            }
+            _ = tokio::time::sleep(kind.interval()) => {}
+        }
+        let Some(room) = room.upgrade() else {
+            debug!("Room gone, the {} bot stopped", kind.name());
+            return;
+        };
+        let command = kind.next_command(room.gol.dimensions(), &mut rng);
+        if let Err(e) = send_command(&room, kind, command) {
+            warn!("The {} bot's command failed: {}", kind.name(), e);
+        }
+        turns += 1;
+    }
+}
+
+// Handles a command like one from a client and broadcasts what it changed
+fn send_command(
+    room: &Arc<RoomState>,
+    kind: BotKind,
+    command: ClientMessage,
+) -> Result<(), FrameError> {
+    let msg_type = command.msg_type;
+    // Gardening a board nobody is shown would only journal dropped commands
+    if let Err(e) = room.modes.admit(msg_type) {
+        debug!("The {} bot waits: {}", kind.name(), e);
+        return Ok(());
+    }
+    let connection_id = kind.connection_id();
+    let payload = WsPayload {
+        parsed: WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type,
+            flags: 0,
+            payload: Bytes::from(command.payload),
+        },
+        room: room.clone(),
+        connection_id: connection_id.clone(),
+    };
+    let response = match &room.journal {
+        Some(journal) => journal.record(&connection_id, &payload),
+        None => payload.handle_payload(),
+    }?;
+
+    // Replies meant for a sender have nobody to go to, and nobody watching is fine
+    if let Some(message) = response
+        && reply_route(msg_type) == ReplyRoute::Room
+    {
+        let _ = room.channel.send(message);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::message_types;
+    use crate::room::RoomSettings;
+
+    #[tokio::test]
+    async fn bots_play_through_the_room_until_stopped() {
+        let settings = RoomSettings {
+            canvas_width: 32,
+            canvas_height: 24,
+            ..RoomSettings::default()
+        };
+        let room = RoomState::for_test_with(&settings);
+        room.gol.kill_all_cells().unwrap();
+        let mut receiver = room.channel.subscribe();
+
+        let command = BotKind::Gardener.next_command((32, 24), &mut StdRng::seed_from_u64(1));
+        assert_eq!(command.msg_type, message_types::STAMP_GOL_PATTERN);
+        assert_eq!(command.payload[0], LibraryPattern::Glider.id());
+
+        assert!(start_bot(&room, BotKind::Chaos));
+        assert!(!start_bot(&room, BotKind::Chaos));
+        assert_eq!(room.bots.running(), [BotKind::Chaos]);
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("The chaos bot's cells within 5s")
+            .unwrap();
+        assert!(room.gol.generation_stats().1 > 0);
+
+        assert!(room.bots.stop(BotKind::Chaos));
+        assert!(!room.bots.stop(BotKind::Chaos));
+        let population = room.gol.generation_stats().1;
+        tokio::time::sleep(BotKind::Chaos.interval() * 3).await;
+        assert_eq!(room.gol.generation_stats().1, population);
+        assert_eq!(
+            BotKind::from_id(BotKind::Gardener.id()),
+            Some(BotKind::Gardener)
+        );
+    }
+}
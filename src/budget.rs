@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::{
+    constants::{BUDGET_REBALANCE_INTERVAL_MS, COMPUTE_BUDGET_CELLS_PER_SEC, SCHEDULER_RUN},
+    room::BroadcastMessage,
+    state::AppState,
+    utils::create_simulation_status_message,
+};
+
+/// Max-min fair split of `budget` between `demands`: rooms asking for less than an
+/// equal share get all of it, the rest divide what is left equally
+pub fn fair_shares(demands: &[f64], budget: f64) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..demands.len()).collect();
+    order.sort_by(|&a, &b| demands[a].total_cmp(&demands[b]));
+
+    let mut shares = vec![0.0; demands.len()];
+    let mut remaining = budget;
+    for (served, &i) in order.iter().enumerate() {
+        let equal_share = remaining / (order.len() - served) as f64;
+        shares[i] = demands[i].min(equal_share);
+        remaining -= shares[i];
+    }
+    shares
+}
+
+/// Periodically divides the compute budget between running rooms and stretches the
+/// tick interval of rooms asking for more than their share
+pub fn spawn_budget_balancer(state: Arc<AppState>) {
+    if !SCHEDULER_RUN {
+        debug!("Simulation loops disabled, budget balancer not started");
+        return;
+    }
+
+    tokio::spawn(async move {
+        info!(
+            "Starting compute budget balancer ({} cells/s)",
+            COMPUTE_BUDGET_CELLS_PER_SEC
+        );
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(BUDGET_REBALANCE_INTERVAL_MS));
+
+        loop {
+            interval.tick().await;
+            let rooms = state.rooms();
+
+            // Only rooms that advance generations draw from the budget
+            let (running, idle): (Vec<_>, Vec<_>) = rooms.iter().partition(|room| {
+                room.simulation.is_autoplaying()
+                    && !room.simulation.is_paused()
+                    && room.channel.receiver_count() > 0
+            });
+            for room in idle {
+                room.simulation.set_throttled_interval_ms(0);
+            }
+
+            let cells: Vec<f64> = running
+                .iter()
+                .map(|room| room.gol.cell_count() as f64)
+                .collect();
+            let demands: Vec<f64> = running
+                .iter()
+                .zip(&cells)
+                .map(|(room, cells)| cells * 1000.0 / room.simulation.tick_interval_ms() as f64)
+                .collect();
+            let shares = fair_shares(&demands, COMPUTE_BUDGET_CELLS_PER_SEC as f64);
+
+            for (i, room) in running.iter().enumerate() {
+                let throttled_interval_ms = if shares[i] < demands[i] {
+                    (cells[i] * 1000.0 / shares[i]).ceil() as u64
+                } else {
+                    0
+                };
+                if !room
+                    .simulation
+                    .set_throttled_interval_ms(throttled_interval_ms)
+                {
+                    continue;
+                }
+
+                info!(
+                    "Room {:?} now ticks every {}ms (requested {}ms)",
+                    room.id,
+                    room.simulation.applied_tick_interval_ms(),
+                    room.simulation.tick_interval_ms()
+                );
+                let message = create_simulation_status_message(&room.simulation);
+                // Viewers may have left since, nothing to do then
+                let _ = room.channel.send(BroadcastMessage::system(message));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fair_shares_serve_small_demands_first() {
+        // Everyone fits, everyone gets what they asked for
+        assert_eq!(fair_shares(&[10.0, 20.0], 100.0), vec![10.0, 20.0]);
+
+        // The small room is served fully, the two large ones split the rest
+        assert_eq!(
+            fair_shares(&[100.0, 10.0, 100.0], 110.0),
+            vec![50.0, 10.0, 50.0]
+        );
+
+        assert!(fair_shares(&[], 100.0).is_empty());
+    }
+}
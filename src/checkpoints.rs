@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    constants::CHECKPOINT_DIR,
+    room::RoomState,
+    saves::{SavedState, read_saved_state, write_saved_state},
+};
+
+/// Boards a room saves on its own every `every` generations, so a crashed server can
+/// restart from the last one. Only the newest `keep` are kept, as
+/// `<data dir>/checkpoints/<room>/<generation>.gol` in the save file format.
+#[derive(Debug)]
+pub struct Checkpoints {
+    dir: PathBuf,
+    every: u64,
+    keep: usize,
+    // Generation of the last checkpoint taken or restored, taking one holds the lock so
+    // two ticks can't write the same checkpoint
+    last: Mutex<u64>,
+}
+
+impl Checkpoints {
+    pub fn new(data_dir: &Path, room: &str, every: u64, keep: usize) -> Self {
+        Self {
+            dir: data_dir.join(CHECKPOINT_DIR).join(room),
+            every,
+            keep,
+            last: Mutex::new(0),
+        }
+    }
+
+    fn path(&self, generation: u64) -> PathBuf {
+        // Zero-padded so file names sort by generation
+        self.dir.join(format!("{:020}.gol", generation))
+    }
+
+    /// Generations with a checkpoint, newest first
+    pub fn list(&self) -> Result<Vec<u64>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to list {}", self.dir.display()));
+            }
+        };
+
+        let mut generations: Vec<u64> = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "gol") {
+                continue;
+            }
+            if let Some(generation) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                generations.push(generation);
+            }
+        }
+        generations.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(generations)
+    }
+
+    /// Checkpoints the room once its generation passed the next multiple of `every`.
+    /// Returns the checkpointed generation, if one was taken.
+    pub fn take_if_due(&self, room: &RoomState) -> Result<Option<u64>> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (generation, _) = room.gol.generation_stats();
+        if generation < *last {
+            // A fresh or restored board counts from its own generation
+            *last = generation;
+            return Ok(None);
+        }
+        if generation / self.every == *last / self.every {
+            return Ok(None);
+        }
+
+        let generation = write_saved_state(&self.path(generation), room)?;
+        *last = generation;
+        debug!(
+            "Checkpointed room {:?} at generation {}",
+            room.id, generation
+        );
+        self.prune()?;
+        Ok(Some(generation))
+    }
+
+    // Deletes all but the newest `keep` checkpoints
+    fn prune(&self) -> Result<()> {
+        for generation in self.list()?.into_iter().skip(self.keep) {
+            let path = self.path(generation);
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&self, generation: u64) -> Result<SavedState> {
+        read_saved_state(&self.path(generation))
+    }
+
+    /// Replaces the room's board with the checkpoint of `generation`, returning the restored
+    /// generation frame
+    pub fn restore(&self, room: &RoomState, generation: u64) -> Result<axum_tws::Message> {
+        let frame = room.load_saved(&self.load(generation)?)?;
+        *self.last.lock().unwrap_or_else(|e| e.into_inner()) = generation;
+        info!(
+            "Restored room {:?} to its checkpoint of generation {}",
+            room.id, generation
+        );
+        Ok(frame)
+    }
+
+    /// Restores the room's newest readable checkpoint, returning its generation
+    pub fn restore_latest(&self, room: &RoomState) -> Result<Option<u64>> {
+        for generation in self.list()? {
+            match self.restore(room, generation) {
+                Ok(_) => return Ok(Some(generation)),
+                Err(e) => warn!(
+                    "Skipping checkpoint {} of room {:?}: {:#}",
+                    generation, room.id, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_every_n_generations_and_keeps_the_newest() {
+        let dir = std::env::temp_dir().join(format!("gol-checkpoints-{}", uuid::Uuid::new_v4()));
+        let room = RoomState::for_test();
+        let checkpoints = Checkpoints::new(&dir, &room.id, 4, 2);
+
+        let mut taken = Vec::new();
+        for _ in 0..13 {
+            room.gol.advance_generation().unwrap();
+            taken.extend(checkpoints.take_if_due(&room).unwrap());
+        }
+        assert_eq!(taken, [4, 8, 12]);
+        assert_eq!(checkpoints.list().unwrap(), [12, 8]);
+        let expected = checkpoints.load(12).unwrap();
+
+        let restarted = RoomState::for_test();
+        assert_eq!(checkpoints.restore_latest(&restarted).unwrap(), Some(12));
+        assert_eq!(restarted.gol.grid_dump(), expected.grid_dump);
+        assert!(checkpoints.restore(&restarted, 4).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
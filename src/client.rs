@@ -0,0 +1,449 @@
+//! Typed messages for Rust clients of the server, such as bots and tests.
+//!
+//! `ClientMessage` builds what a client sends without laying out payloads by hand, and
+//! `FrameDecoder` reads the drawing messages the server sends back. Sequence prefixes,
+//! checksums and deflate are undone before decoding, so a client may advertise every codec.
+
+use axum_tws::Message;
+use game_of_life_core::Region;
+use game_of_life_core::compression::inflate;
+
+use crate::{
+    constants::{
+        DEFAULT_MAX_PAYLOAD_LENGTH, HELLO_PAYLOAD, MAX_REASSEMBLED_PAYLOAD, message_types,
+    },
+    lobby::RoomParams,
+    protocol::{
+        CellPayload, FLAG_DEFLATE, FLAG_SEQUENCED, PROTOCOL_VERSION, ProtocolError,
+        SEQUENCE_PREFIX_LENGTH, WsMessage, encode_ws_message,
+    },
+    state::ActivePattern,
+};
+
+/// A message for the server, encoded with `encode` or `to_message`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientMessage {
+    pub msg_type: u8,
+    pub payload: Vec<u8>,
+}
+
+impl ClientMessage {
+    pub fn new(msg_type: u8, payload: Vec<u8>) -> Self {
+        Self { msg_type, payload }
+    }
+
+    fn empty(msg_type: u8) -> Self {
+        Self::new(msg_type, Vec::new())
+    }
+
+    fn cell(msg_type: u8, x: u16, y: u16) -> Self {
+        Self::new(msg_type, CellPayload { x, y, rgb: None }.encode())
+    }
+
+    fn rect(msg_type: u8, x: u16, y: u16, width: u16, height: u16) -> Self {
+        let payload = [x, y, width, height]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        Self::new(msg_type, payload)
+    }
+
+    /// HELLO of a client that speaks this protocol version and keeps the default codecs
+    /// and topics
+    pub fn hello() -> Self {
+        Self::new(message_types::HELLO, HELLO_PAYLOAD.to_vec())
+    }
+
+    /// HELLO asking for `codecs` and starting with the `topics` bitmask
+    pub fn hello_with(codecs: u8, topics: u8) -> Self {
+        let mut payload = HELLO_PAYLOAD.to_vec();
+        payload.extend_from_slice(&[codecs, 1, PROTOCOL_VERSION, topics]);
+        Self::new(message_types::HELLO, payload)
+    }
+
+    pub fn create_new_generation() -> Self {
+        Self::empty(message_types::CREATE_NEW_GOL_GENERATION)
+    }
+
+    pub fn advance_generation() -> Self {
+        Self::empty(message_types::ADVANCE_GOL_GENERATION)
+    }
+
+    pub fn awaken_random_cell() -> Self {
+        Self::empty(message_types::AWAKEN_RANDOM_GOL_CELL)
+    }
+
+    pub fn kill_random_cell() -> Self {
+        Self::empty(message_types::KILL_RANDOM_GOL_CELL)
+    }
+
+    pub fn kill_all_cells() -> Self {
+        Self::empty(message_types::KILL_ALL_GOL_CELLS)
+    }
+
+    pub fn toggle_cell(x: u16, y: u16) -> Self {
+        Self::cell(message_types::TOGGLE_CELL, x, y)
+    }
+
+    pub fn awaken_cells(cells: &[(u16, u16)]) -> Self {
+        let payload = cells
+            .iter()
+            .flat_map(|&(x, y)| CellPayload { x, y, rgb: None }.encode())
+            .collect();
+        Self::new(message_types::AWAKEN_CELLS_BATCH, payload)
+    }
+
+    pub fn fill_rect(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self::rect(message_types::FILL_RECT, x, y, width, height)
+    }
+
+    pub fn clear_rect(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self::rect(message_types::CLEAR_RECT, x, y, width, height)
+    }
+
+    /// Stamps library pattern `pattern_id` with its top left cell at (`x`, `y`)
+    pub fn stamp_pattern(pattern_id: u8, x: u16, y: u16) -> Self {
+        let mut payload = vec![pattern_id];
+        payload.extend(CellPayload { x, y, rgb: None }.encode());
+        Self::new(message_types::STAMP_GOL_PATTERN, payload)
+    }
+
+    /// Asks for a pixel at (`x`, `y`), in `rgb` or a random color without one
+    pub fn request_pixel(x: u16, y: u16, rgb: Option<[u8; 3]>) -> Self {
+        Self::new(
+            message_types::REQUEST_RANDOM_COLORED_PIXEL,
+            CellPayload { x, y, rgb }.encode(),
+        )
+    }
+
+    /// Shows the other clients in the room this one's cursor over (`x`, `y`)
+    pub fn move_cursor(x: u16, y: u16) -> Self {
+        Self::new(
+            message_types::CURSOR_MOVE,
+            CellPayload { x, y, rgb: None }.encode(),
+        )
+    }
+
+    pub fn set_simulation_speed(tick_interval_ms: u32) -> Self {
+        Self::new(
+            message_types::SET_SIMULATION_SPEED,
+            tick_interval_ms.to_be_bytes().to_vec(),
+        )
+    }
+
+    pub fn set_autoplay(autoplay: bool) -> Self {
+        Self::new(message_types::SET_AUTOPLAY, vec![autoplay as u8])
+    }
+
+    pub fn pause_simulation() -> Self {
+        Self::empty(message_types::PAUSE_SIMULATION)
+    }
+
+    pub fn resume_simulation() -> Self {
+        Self::empty(message_types::RESUME_SIMULATION)
+    }
+
+    /// Has the room show `pattern`, commands for other patterns are rejected
+    pub fn switch_mode(pattern: ActivePattern) -> Self {
+        Self::new(message_types::SWITCH_MODE, vec![pattern.id()])
+    }
+
+    pub fn subscribe(topics: u8) -> Self {
+        Self::new(message_types::SUBSCRIBE, vec![topics])
+    }
+
+    pub fn unsubscribe(topics: u8) -> Self {
+        Self::new(message_types::UNSUBSCRIBE, vec![topics])
+    }
+
+    pub fn resync_request() -> Self {
+        Self::empty(message_types::RESYNC_REQUEST)
+    }
+
+    pub fn set_nickname(nickname: &str) -> Self {
+        Self::new(message_types::SET_NICKNAME, nickname.as_bytes().to_vec())
+    }
+
+    pub fn chat(text: &str) -> Self {
+        Self::new(message_types::CHAT, text.as_bytes().to_vec())
+    }
+
+    pub fn get_server_stats() -> Self {
+        Self::empty(message_types::GET_SERVER_STATS)
+    }
+
+    /// A line in the schedule file's syntax, see `scheduler`
+    pub fn add_schedule_entry(line: &str) -> Self {
+        Self::new(message_types::ADD_SCHEDULE_ENTRY, line.as_bytes().to_vec())
+    }
+
+    pub fn remove_schedule_entry(index: u16) -> Self {
+        Self::new(
+            message_types::REMOVE_SCHEDULE_ENTRY,
+            index.to_be_bytes().to_vec(),
+        )
+    }
+
+    pub fn list_schedule() -> Self {
+        Self::empty(message_types::LIST_SCHEDULE)
+    }
+
+    pub fn list_rooms() -> Self {
+        Self::empty(message_types::LIST_ROOMS)
+    }
+
+    pub fn create_room(params: &RoomParams) -> Self {
+        let mut payload = vec![params.id.len() as u8];
+        payload.extend_from_slice(params.id.as_bytes());
+        payload.extend_from_slice(&params.canvas_width.unwrap_or(0).to_be_bytes());
+        payload.extend_from_slice(&params.canvas_height.unwrap_or(0).to_be_bytes());
+        let tick_interval_ms = params.tick_interval_ms.unwrap_or(0) as u32;
+        payload.extend_from_slice(&tick_interval_ms.to_be_bytes());
+        payload.push(params.autoplay as u8);
+        payload.push(params.mode.id());
+        payload.extend_from_slice(params.password.as_deref().unwrap_or_default().as_bytes());
+        Self::new(message_types::CREATE_ROOM, payload)
+    }
+
+    fn ws_message(&self) -> WsMessage<&[u8]> {
+        WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: self.msg_type,
+            flags: 0,
+            payload: &self.payload,
+        }
+    }
+
+    /// Header and payload as sent in a binary WebSocket frame
+    pub fn encode(&self) -> Vec<u8> {
+        self.ws_message().encode()
+    }
+
+    pub fn to_message(&self) -> Message {
+        encode_ws_message(&self.ws_message())
+    }
+}
+
+/// Why a server message couldn't be decoded
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error("{name} payload of {length} bytes doesn't match its layout")]
+    Malformed { name: &'static str, length: usize },
+}
+
+/// The whole canvas, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u16,
+    pub height: u16,
+    pub rgb_data: Vec<u8>,
+}
+
+impl Frame {
+    /// Color of the pixel at (`x`, `y`), None off the canvas
+    pub fn pixel(&self, x: u16, y: u16) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        Some([self.rgb_data[i], self.rgb_data[i + 1], self.rgb_data[i + 2]])
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        if x < self.width as usize && y < self.height as usize {
+            let i = (y * self.width as usize + x) * 3;
+            self.rgb_data[i..i + 3].copy_from_slice(&rgb);
+        }
+    }
+
+    /// Draws a decoded message onto the canvas the way the browser client does, a frame
+    /// replacing it
+    pub fn apply(&mut self, draw: &Draw) {
+        match draw {
+            Draw::Frame(frame) => *self = frame.clone(),
+            Draw::Pixel(pixel) => self.set_pixel(pixel.x as usize, pixel.y as usize, pixel.rgb),
+            Draw::Region { region, rgb_data } => {
+                for (i, rgb) in rgb_data.chunks_exact(3).enumerate() {
+                    let x = region.x as usize + i % region.width as usize;
+                    let y = region.y as usize + i / region.width as usize;
+                    self.set_pixel(x, y, [rgb[0], rgb[1], rgb[2]]);
+                }
+            }
+        }
+    }
+}
+
+/// One colored pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pixel {
+    pub x: u16,
+    pub y: u16,
+    pub rgb: [u8; 3],
+}
+
+/// What a drawing message asks the client to draw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Draw {
+    Frame(Frame),
+    Pixel(Pixel),
+    // Part of the canvas, row by row
+    Region { region: Region, rgb_data: Vec<u8> },
+}
+
+/// Decodes DRAW_FRAME, DRAW_REGION and DRAW_PIXEL messages from the server
+#[derive(Debug, Clone, Copy)]
+pub struct FrameDecoder {
+    max_payload_length: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PAYLOAD_LENGTH)
+    }
+}
+
+impl FrameDecoder {
+    /// Refuses messages claiming more than `max_payload_length` bytes
+    pub fn new(max_payload_length: usize) -> Self {
+        Self { max_payload_length }
+    }
+
+    /// Type and payload of a server message, without its sequence prefix or compression
+    pub fn unwrap_message(&self, data: &[u8]) -> Result<(u8, Vec<u8>), DecodeError> {
+        let message = WsMessage::parse(data, self.max_payload_length)?;
+        let mut payload = message.payload;
+        if message.flags & FLAG_SEQUENCED != 0 {
+            payload = payload
+                .get(SEQUENCE_PREFIX_LENGTH..)
+                .ok_or(DecodeError::Malformed {
+                    name: "Sequenced",
+                    length: payload.len(),
+                })?;
+        }
+        let payload = if message.flags & FLAG_DEFLATE != 0 {
+            inflate(payload, MAX_REASSEMBLED_PAYLOAD)?
+        } else {
+            payload.to_vec()
+        };
+        Ok((message.msg_type, payload))
+    }
+
+    /// The drawing in an encoded message, None for messages that don't draw
+    pub fn decode(&self, data: &[u8]) -> Result<Option<Draw>, DecodeError> {
+        let (msg_type, payload) = self.unwrap_message(data)?;
+        let malformed = |name| DecodeError::Malformed {
+            name,
+            length: payload.len(),
+        };
+        let field = |i: usize| u16::from_be_bytes([payload[i], payload[i + 1]]);
+
+        let draw = match msg_type {
+            message_types::DRAW_FRAME => {
+                if payload.len() < 4 {
+                    return Err(malformed("DRAW_FRAME"));
+                }
+                let (width, height) = (field(0), field(2));
+                if payload.len() != 4 + width as usize * height as usize * 3 {
+                    return Err(malformed("DRAW_FRAME"));
+                }
+                Draw::Frame(Frame {
+                    width,
+                    height,
+                    rgb_data: payload[4..].to_vec(),
+                })
+            }
+            message_types::DRAW_REGION => {
+                if payload.len() < 8 {
+                    return Err(malformed("DRAW_REGION"));
+                }
+                let region = Region {
+                    x: field(0),
+                    y: field(2),
+                    width: field(4),
+                    height: field(6),
+                };
+                if payload.len() != 8 + region.area() * 3 {
+                    return Err(malformed("DRAW_REGION"));
+                }
+                Draw::Region {
+                    region,
+                    rgb_data: payload[8..].to_vec(),
+                }
+            }
+            message_types::DRAW_PIXEL => {
+                let cell = CellPayload::decode(&payload)?;
+                let rgb = cell.rgb.ok_or_else(|| malformed("DRAW_PIXEL"))?;
+                Draw::Pixel(Pixel {
+                    x: cell.x,
+                    y: cell.y,
+                    rgb,
+                })
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(draw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{compress_ws_message, sequence_ws_message};
+    use crate::utils::{create_frame_message, create_pixel_message, create_region_message};
+
+    #[test]
+    fn decoded_messages_draw_what_the_server_sent() {
+        let hello = ClientMessage::hello_with(1, 3);
+        assert_eq!(
+            hello.encode()[..3],
+            [PROTOCOL_VERSION, message_types::HELLO, 0]
+        );
+        assert_eq!(hello.payload, b"hello\x01\x01\x01\x03");
+        assert_eq!(
+            ClientMessage::awaken_cells(&[(1, 2), (258, 0)]).payload,
+            [0, 1, 0, 2, 1, 2, 0, 0]
+        );
+
+        let decoder = FrameDecoder::default();
+        // A 3x2 frame compresses, and arrives sequenced like room broadcasts do
+        let frame = create_frame_message(3, 2, vec![255; 18]).unwrap();
+        let frame = compress_ws_message(&sequence_ws_message(&frame, 1, 7)).unwrap();
+        let Some(Draw::Frame(mut canvas)) = decoder.decode(frame.as_payload()).unwrap() else {
+            panic!("DRAW_FRAME decodes to a frame");
+        };
+        assert_eq!((canvas.width, canvas.height), (3, 2));
+
+        let pixel = create_pixel_message(2, 1, 10, 20, 30).unwrap();
+        let region = create_region_message(
+            Region {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 2,
+            },
+            vec![1, 1, 1, 2, 2, 2],
+        );
+        for message in [pixel, region] {
+            canvas.apply(&decoder.decode(message.as_payload()).unwrap().unwrap());
+        }
+        assert_eq!(canvas.pixel(2, 1), Some([10, 20, 30]));
+        assert_eq!(canvas.pixel(0, 1), Some([2, 2, 2]));
+        assert_eq!(canvas.pixel(1, 1), Some([255; 3]));
+        assert_eq!(canvas.pixel(3, 0), None);
+
+        let hello = ClientMessage::hello().encode();
+        assert_eq!(decoder.decode(&hello).unwrap(), None);
+        let short = WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: message_types::DRAW_FRAME,
+            flags: 0,
+            payload: vec![0, 3, 0, 2, 255],
+        };
+        assert!(matches!(
+            decoder.decode(&short.encode()),
+            Err(DecodeError::Malformed { .. })
+        ));
+    }
+}
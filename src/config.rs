@@ -0,0 +1,391 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use game_of_life_core::EngineKind;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    constants::{
+        DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH, DEFAULT_CHANNEL_CAPACITY,
+        DEFAULT_CHECKPOINT_KEEP, DEFAULT_DATA_DIR, DEFAULT_LOG_FILTER, DEFAULT_MAX_CONNECTIONS,
+        DEFAULT_MAX_PAYLOAD_LENGTH, DEFAULT_PORT, DEFAULT_STATIC_DIR, DEFAULT_TICK_INTERVAL_MS,
+        MAX_REASSEMBLED_PAYLOAD, MAX_TICK_INTERVAL_MS, MIN_MAX_PAYLOAD_LENGTH,
+        MIN_TICK_INTERVAL_MS,
+    },
+    patterns::gol::max_canvas_side,
+    proxy::ProxyArgs,
+    room::{DeadlineMode, RoomSettings},
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Game of Life and Mona Lisa painting over WebSockets")]
+pub struct Cli {
+    /// TOML file with server options, options given on the command line take precedence
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub server: ServerOptions,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Log, record or replay protocol sessions between clients and a server
+    Proxy(ProxyArgs),
+}
+
+/// Server options, each one settable from the command line or the config file. Unset
+/// options fall back to the built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Args, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerOptions {
+    /// Address to listen on [default: 0.0.0.0]
+    #[arg(long)]
+    pub bind: Option<IpAddr>,
+    /// Port to listen on [default: 8080]
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Grid width in cells [default: 100]
+    #[arg(long)]
+    pub canvas_width: Option<u16>,
+    /// Grid height in cells [default: 100]
+    #[arg(long)]
+    pub canvas_height: Option<u16>,
+    /// Initial simulation tick interval of new rooms [default: 100]
+    #[arg(long)]
+    pub tick_interval_ms: Option<u64>,
+    /// Whether new rooms advance generations on their own, clients can toggle it per room
+    /// [default: false]
+    #[arg(long)]
+    pub autoplay: Option<bool>,
+    /// Whether autoplay starts a fresh random generation once the board settles into a
+    /// still life or short oscillator [default: false]
+    #[arg(long)]
+    pub reseed_when_stable: Option<bool>,
+    /// Messages a room buffers for slow clients before they start lagging [default: 100]
+    #[arg(long)]
+    pub channel_capacity: Option<usize>,
+    /// Open WebSocket connections across all rooms, more are refused [default: 1000]
+    #[arg(long)]
+    pub max_connections: Option<usize>,
+    /// Payload bytes a single client message may claim, larger messages are refused before
+    /// they're read [default: 1048576]
+    #[arg(long)]
+    pub max_payload_bytes: Option<usize>,
+    /// Directory the web client is served from [default: static]
+    #[arg(long)]
+    pub static_dir: Option<PathBuf>,
+    /// What to do when a tick takes longer than its interval [default: off]
+    #[arg(long, value_enum)]
+    pub tick_deadline: Option<DeadlineMode>,
+    /// Directory rooms journal their commands to and recover from after a restart
+    /// [default: no journal]
+    #[arg(long)]
+    pub journal_dir: Option<PathBuf>,
+    /// Engine Game of Life grids step with: vecs, bits, simd, bits-parallel, sparse or auto
+    /// (sparse while mostly empty, simd otherwise). They step the same rule, pick the
+    /// fastest on this machine with the bench binary [default: vecs]
+    #[arg(long)]
+    pub gol_engine: Option<String>,
+    /// Directory rooms record every client message to, for the replay binary
+    /// [default: no recording]
+    #[arg(long)]
+    pub record_dir: Option<PathBuf>,
+    /// Directory boards are saved to with SAVE_STATE and loaded from with LOAD_STATE
+    /// [default: data]
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+    /// Generations between automatic checkpoints of every room, a restarted server resumes
+    /// rooms without a journal from their newest one [default: no checkpoints]
+    #[arg(long)]
+    pub checkpoint_every: Option<u64>,
+    /// Checkpoints kept per room, older ones are deleted [default: 5]
+    #[arg(long)]
+    pub checkpoint_keep: Option<usize>,
+    /// Token clients present, with ?admin_token= or an AUTH message, to kill all cells,
+    /// change speeds, kick clients and save or load boards. Prefer the config file, command
+    /// lines are visible to other users [default: none, every client may]
+    #[arg(long)]
+    pub admin_token: Option<String>,
+    /// Tracing filter, e.g. "info,gol_htmx_rust=debug" [default: RUST_LOG, then
+    /// "info,websocket_server=debug"]
+    #[arg(long)]
+    pub log_filter: Option<String>,
+    /// How log lines are written, json for one object per line with the fields of the
+    /// spans they're in, such as connection_id and room [default: text]
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+}
+
+/// How the server writes its log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl ServerOptions {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Options set here win, the rest are taken from `fallback`
+    pub fn or(self, fallback: ServerOptions) -> Self {
+        Self {
+            bind: self.bind.or(fallback.bind),
+            port: self.port.or(fallback.port),
+            canvas_width: self.canvas_width.or(fallback.canvas_width),
+            canvas_height: self.canvas_height.or(fallback.canvas_height),
+            tick_interval_ms: self.tick_interval_ms.or(fallback.tick_interval_ms),
+            autoplay: self.autoplay.or(fallback.autoplay),
+            reseed_when_stable: self.reseed_when_stable.or(fallback.reseed_when_stable),
+            channel_capacity: self.channel_capacity.or(fallback.channel_capacity),
+            max_connections: self.max_connections.or(fallback.max_connections),
+            max_payload_bytes: self.max_payload_bytes.or(fallback.max_payload_bytes),
+            static_dir: self.static_dir.or(fallback.static_dir),
+            tick_deadline: self.tick_deadline.or(fallback.tick_deadline),
+            journal_dir: self.journal_dir.or(fallback.journal_dir),
+            gol_engine: self.gol_engine.or(fallback.gol_engine),
+            record_dir: self.record_dir.or(fallback.record_dir),
+            data_dir: self.data_dir.or(fallback.data_dir),
+            checkpoint_every: self.checkpoint_every.or(fallback.checkpoint_every),
+            checkpoint_keep: self.checkpoint_keep.or(fallback.checkpoint_keep),
+            admin_token: self.admin_token.or(fallback.admin_token),
+            log_filter: self.log_filter.or(fallback.log_filter),
+            log_format: self.log_format.or(fallback.log_format),
+        }
+    }
+}
+
+/// Resolved server settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    pub static_dir: PathBuf,
+    // None leaves the filter to RUST_LOG
+    pub log_filter: Option<String>,
+    pub log_format: LogFormat,
+    // None leaves privileged messages open to every client
+    pub admin_token: Option<String>,
+    pub max_connections: usize,
+    pub room: RoomSettings,
+}
+
+impl ServerConfig {
+    /// Merges the command line over the config file, if any, and validates the result
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let file = match &cli.config {
+            Some(path) => ServerOptions::from_file(path)?,
+            None => ServerOptions::default(),
+        };
+        Self::resolve(cli.server.clone().or(file))
+    }
+
+    fn resolve(options: ServerOptions) -> Result<Self> {
+        let gol_engine = match options.gol_engine.as_deref() {
+            Some(name) => name.parse().map_err(anyhow::Error::msg)?,
+            None => EngineKind::default(),
+        };
+        let canvas_width = options.canvas_width.unwrap_or(DEFAULT_CANVAS_WIDTH);
+        let canvas_height = options.canvas_height.unwrap_or(DEFAULT_CANVAS_HEIGHT);
+        let max_side = max_canvas_side(gol_engine);
+        for side in [canvas_width, canvas_height] {
+            if !(1..=max_side).contains(&side) {
+                bail!(
+                    "Canvas sides must be 1 to {} cells with the {} engine, got {}",
+                    max_side,
+                    gol_engine,
+                    side
+                );
+            }
+        }
+
+        let tick_interval_ms = options.tick_interval_ms.unwrap_or(DEFAULT_TICK_INTERVAL_MS);
+        if !(MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS).contains(&tick_interval_ms) {
+            bail!(
+                "Tick interval must be {} to {} ms, got {}",
+                MIN_TICK_INTERVAL_MS,
+                MAX_TICK_INTERVAL_MS,
+                tick_interval_ms
+            );
+        }
+
+        let channel_capacity = options.channel_capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY);
+        if channel_capacity == 0 {
+            bail!("Channel capacity must be at least 1");
+        }
+        let max_connections = options.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS);
+        if max_connections == 0 {
+            bail!("Connection limit must be at least 1");
+        }
+        let max_payload_length = options
+            .max_payload_bytes
+            .unwrap_or(DEFAULT_MAX_PAYLOAD_LENGTH);
+        if !(MIN_MAX_PAYLOAD_LENGTH..=MAX_REASSEMBLED_PAYLOAD).contains(&max_payload_length) {
+            bail!(
+                "Payload limit must be {} to {} bytes, got {}",
+                MIN_MAX_PAYLOAD_LENGTH,
+                MAX_REASSEMBLED_PAYLOAD,
+                max_payload_length
+            );
+        }
+        if options.checkpoint_every == Some(0) {
+            bail!("Checkpoints must be at least 1 generation apart");
+        }
+        let checkpoint_keep = options.checkpoint_keep.unwrap_or(DEFAULT_CHECKPOINT_KEEP);
+        if checkpoint_keep == 0 {
+            bail!("At least 1 checkpoint must be kept");
+        }
+        if options.admin_token.as_deref() == Some("") {
+            bail!("Admin token can't be empty");
+        }
+
+        Ok(Self {
+            addr: SocketAddr::new(
+                options.bind.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+                options.port.unwrap_or(DEFAULT_PORT),
+            ),
+            static_dir: options
+                .static_dir
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_STATIC_DIR)),
+            log_filter: options.log_filter,
+            log_format: options.log_format.unwrap_or_default(),
+            admin_token: options.admin_token,
+            max_connections,
+            room: RoomSettings {
+                channel_cap: channel_capacity,
+                canvas_width,
+                canvas_height,
+                tick_interval_ms,
+                autoplay: options.autoplay.unwrap_or_default(),
+                reseed_when_stable: options.reseed_when_stable.unwrap_or_default(),
+                deadline_mode: options.tick_deadline.unwrap_or_default(),
+                journal_dir: options.journal_dir,
+                record_dir: options.record_dir,
+                data_dir: options
+                    .data_dir
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR)),
+                checkpoint_every: options.checkpoint_every,
+                checkpoint_keep,
+                max_payload_length,
+                gol_engine,
+            },
+        })
+    }
+
+    pub fn log_filter(&self) -> String {
+        self.log_filter
+            .clone()
+            .or_else(|| std::env::var("RUST_LOG").ok())
+            .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MAX_CANVAS_SIDE;
+
+    #[test]
+    fn command_line_overrides_file() {
+        let file: ServerOptions = toml::from_str(
+            r#"
+            port = 9000
+            canvas_width = 200
+            static_dir = "public"
+            tick_deadline = "degrade"
+            autoplay = true
+            checkpoint_every = 500
+            max_payload_bytes = 65536
+            gol_engine = "simd"
+            log_format = "json"
+            "#,
+        )
+        .unwrap();
+        let cli = Cli::parse_from(["gol", "--port", "9100", "--tick-interval-ms", "50"]);
+
+        let config = ServerConfig::resolve(cli.server.or(file)).unwrap();
+        assert_eq!(config.addr, SocketAddr::from(([0, 0, 0, 0], 9100)));
+        assert_eq!(config.static_dir, PathBuf::from("public"));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(
+            config.room,
+            RoomSettings {
+                channel_cap: DEFAULT_CHANNEL_CAPACITY,
+                canvas_width: 200,
+                canvas_height: DEFAULT_CANVAS_HEIGHT,
+                tick_interval_ms: 50,
+                autoplay: true,
+                reseed_when_stable: false,
+                deadline_mode: DeadlineMode::Degrade,
+                journal_dir: None,
+                record_dir: None,
+                data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+                checkpoint_every: Some(500),
+                checkpoint_keep: DEFAULT_CHECKPOINT_KEEP,
+                max_payload_length: 65536,
+                gol_engine: EngineKind::Simd,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(toml::from_str::<ServerOptions>("prot = 1").is_err());
+        let invalid = |options: ServerOptions| ServerConfig::resolve(options).is_err();
+        assert!(invalid(ServerOptions {
+            canvas_width: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            canvas_width: Some(MAX_CANVAS_SIDE + 1),
+            ..Default::default()
+        }));
+        // Sparse boards may be larger
+        assert!(!invalid(ServerOptions {
+            canvas_width: Some(MAX_CANVAS_SIDE + 1),
+            gol_engine: Some("sparse".to_string()),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            tick_interval_ms: Some(1),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            channel_capacity: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            max_connections: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            max_payload_bytes: Some(100),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            checkpoint_every: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            checkpoint_keep: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            gol_engine: Some("fastest".to_string()),
+            ..Default::default()
+        }));
+        assert!(invalid(ServerOptions {
+            admin_token: Some(String::new()),
+            ..Default::default()
+        }));
+    }
+}
@@ -1,25 +1,495 @@
-pub const CANVAS_WIDTH: u16 = 100;
-pub const CANVAS_HEIGHT: u16 = 100;
+// Canvas size and tick interval when neither the command line nor the config file set them
+pub const DEFAULT_CANVAS_WIDTH: u16 = 100;
+pub const DEFAULT_CANVAS_HEIGHT: u16 = 100;
 pub const PIXEL_PAYLOAD_SIZE: usize = 7;
+// Cells a single AWAKEN_CELLS_BATCH message may carry
+pub const MAX_CELL_BATCH: usize = 4096;
+pub const MAX_BRUSH_RADIUS: u8 = 32;
 pub const HELLO_PAYLOAD: &[u8] = b"hello";
-#[allow(dead_code)]
+pub const DEFAULT_TICK_INTERVAL_MS: u64 = 100;
+pub const MIN_TICK_INTERVAL_MS: u64 = 10;
+pub const MAX_TICK_INTERVAL_MS: u64 = 5000;
+pub const MAX_CANVAS_SIDE: u16 = 1000;
+// Canvas side of a room stepped by the sparse or adaptive engine. Full frames of it are
+// large, such boards are meant to be watched through a viewport.
+pub const MAX_SPARSE_CANVAS_SIDE: u16 = 4096;
+// Cells a viewport pixel may cover along each side, zoomed further out frames cost too much
+// to render per connection
+pub const MAX_VIEWPORT_ZOOM: u8 = 16;
+// Pixels along each side of a viewport tile
+pub const TILE_SIDE: u16 = 32;
+// Payload a single message may claim when the config doesn't say, larger ones are refused
+// before they're read
+pub const DEFAULT_MAX_PAYLOAD_LENGTH: usize = 1024 * 1024;
+pub const MIN_MAX_PAYLOAD_LENGTH: usize = 1024;
+// Payload a message sent in fragments may add up to, a full frame of the largest canvas fits
+pub const MAX_REASSEMBLED_PAYLOAD: usize = 4 * 1024 * 1024;
+pub const DEFAULT_PORT: u16 = 8080;
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 100;
+// Open WebSocket connections across all rooms, further upgrades are refused
+pub const DEFAULT_MAX_CONNECTIONS: usize = 1000;
+// A client whose room channel backlog stays at or above this share of the channel capacity,
+// in percent, for longer than the grace period is dropped as too slow
+pub const SLOW_CLIENT_BACKLOG_PERCENT: usize = 75;
+pub const SLOW_CLIENT_GRACE_MS: u64 = 5000;
+pub const DEFAULT_STATIC_DIR: &str = "static";
+// Where SAVE_STATE writes boards unless the config says otherwise
+pub const DEFAULT_DATA_DIR: &str = "data";
+// Checkpoints of a room kept when the config doesn't say, older ones are deleted
+pub const DEFAULT_CHECKPOINT_KEEP: usize = 5;
+pub const DEFAULT_LOG_FILTER: &str = "info,websocket_server=debug";
+pub const STATS_REFRESH_INTERVAL_MS: u64 = 1000;
+// Upper bounds of the timing histograms served at /metrics, in microseconds
+pub const TIMING_BUCKETS_US: [u64; 12] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+];
+// Messages queued for a single connection only, such as its errors
+pub const DIRECT_CHANNEL_CAPACITY: usize = 16;
+// How long a closing connection gets to flush its queued errors
+pub const ERROR_FLUSH_TIMEOUT_MS: u64 = 1000;
+// Every connection is pinged this often, anything it sends back counts as an answer
+pub const KEEPALIVE_INTERVAL_MS: u64 = 15_000;
+// Pings in a row a connection may leave unanswered before it's dropped as dead
+pub const KEEPALIVE_MAX_MISSED: u8 = 3;
+// A connection counts as editing for this long after its last canvas change
+pub const EDITOR_ACTIVITY_WINDOW_MS: u64 = 30_000;
+// Seed of every new room's random stream, random per room when unset
+pub const SIMULATION_SEED: Option<u64> = None;
+// Whether each room runs its own periodic generation broadcaster, which only advances
+// generations while the room's autoplay is on
+pub const SCHEDULER_RUN: bool = true;
+pub const MAX_ROOMS: usize = 64;
+// Rooms nobody has been connected to for this long are removed, except the default room
+pub const EMPTY_ROOM_EVICT_MS: u64 = 300_000;
+pub const MAX_ROOM_ID_LENGTH: usize = 32;
+// Characters of a nickname set with SET_NICKNAME
+pub const MAX_NICKNAME_LENGTH: usize = 32;
+// Cursor moves closer together than this are dropped, about 20 per second per connection
+pub const CURSOR_MIN_INTERVAL_MS: u64 = 50;
+// Characters of a single chat message
+pub const MAX_CHAT_LENGTH: usize = 500;
+// Outstanding single-use invite tokens per room
+pub const MAX_ROOM_INVITES: usize = 32;
+pub const INVITE_TOKEN_LENGTH: usize = 24;
+// Cell updates per second shared by all running rooms (generations/sec x grid area)
+pub const COMPUTE_BUDGET_CELLS_PER_SEC: u64 = 2_000_000;
+pub const BUDGET_REBALANCE_INTERVAL_MS: u64 = 1000;
+pub const WATCHDOG_CHECK_INTERVAL_MS: u64 = 1000;
+// Grace period on top of the tick interval before a simulation loop counts as stuck
+pub const WATCHDOG_DEADLINE_MS: u64 = 5000;
+pub const WATCHDOG_SNAPSHOT_EVERY_TICKS: u64 = 10;
+// On-time ticks a degraded room needs before it goes back to full frames
+pub const DEADLINE_RECOVERY_TICKS: u32 = 50;
+// Cron-like automation entries loaded at startup, if the file exists
+pub const SCHEDULE_FILE: &str = "schedule.cron";
+// Entries admins may add with ADD_SCHEDULE_ENTRY on top of the file's
+pub const MAX_SCHEDULE_ENTRIES: usize = 64;
+pub const SNAPSHOT_DIR: &str = "snapshots";
+// Journal entries after which a room's grid is snapshotted and its log starts over
+pub const JOURNAL_COMPACT_EVERY: u64 = 1000;
+// Generations a snapshot is run ahead to find its oscillation period
+pub const SNAPSHOT_MAX_PERIOD: u32 = 64;
+// Header of the bit-packed grid download: magic, format version
+pub const GRID_DUMP_MAGIC: &[u8; 4] = b"GOLB";
+pub const GRID_DUMP_VERSION: u8 = 1;
+// Header of boards saved with SAVE_STATE: magic, format version
+pub const SAVE_FILE_MAGIC: &[u8; 4] = b"GOLS";
+pub const SAVE_FILE_VERSION: u8 = 1;
+pub const MAX_SAVE_NAME_LENGTH: usize = 32;
+// Directory under the data directory rooms write their automatic checkpoints to
+pub const CHECKPOINT_DIR: &str = "checkpoints";
+// Longest oscillator period the broadcaster recognizes as a stable board, 1 is a still life
+pub const MAX_STABLE_PERIOD: u8 = 16;
+// Largest share of the grid, in percent, the broadcaster sends as a DRAW_REGION. Busier
+// generations go out as whole frames.
+pub const REGION_MAX_AREA_PERCENT: usize = 50;
+// Regions sent between full frames, so clients that missed one catch up
+pub const REGION_KEYFRAME_EVERY: u32 = 60;
+// Generations of population curve kept per room for clients joining mid-run
+pub const POPULATION_HISTORY_LEN: usize = 120;
+// User edits per room that UNDO can revert, the oldest are forgotten first
+pub const MAX_UNDO_EDITS: usize = 100;
+// Birth/survival rule of the Game of Life engines, reported to clients
+pub const GOL_RULE: &str = "B3/S23";
+// Edge handling clients must reproduce to predict generations: cells beyond the grid are dead
+pub const EDGE_MODE_DEAD: u8 = 0;
 pub const LIVE_CELL_R_G_B: [u8; 3] = [0, 0, 0];
-pub const DEAD_CELL_R_G_B: [u8; 3] = [255, 255, 255];
+pub use game_of_life_core::DEAD_CELL_R_G_B;
+pub const BRAIN_DYING_R_G_B: [u8; 3] = [70, 130, 180];
+// Chance of each cell starting alive in a new Brian's Brain grid
+pub const BRAIN_SEED_DENSITY: f64 = 0.2;
+pub const SAND_R_G_B: [u8; 3] = [194, 178, 128];
+pub const WATER_R_G_B: [u8; 3] = [64, 120, 220];
+pub const WALL_R_G_B: [u8; 3] = [90, 90, 90];
+pub const FIRE_R_G_B: [u8; 3] = [230, 90, 30];
+// Chance per tick of a fire cell burning out
+pub const FIRE_BURNOUT_CHANCE: f64 = 0.1;
+// Gray-Scott rates new rooms start with, a coral-like growth
+pub const DEFAULT_REACTION_FEED: f32 = 0.055;
+pub const DEFAULT_REACTION_KILL: f32 = 0.062;
+// Feed and kill rates accepted from clients, patterns die out or blow up beyond it
+pub const MAX_REACTION_RATE: f32 = 0.1;
+pub const REACTION_SEED_SPOTS: usize = 10;
+pub const REACTION_STEPS_PER_ADVANCE: usize = 20;
+// Grids this large step their rows across the rayon pool
+pub const REACTION_PARALLEL_MIN_CELLS: usize = 64 * 64;
+pub const MAX_BOIDS: usize = 500;
+// Boids a single spawn message may add
+pub const MAX_BOID_SPAWN: u8 = 50;
+// Steering weights accepted from clients
+pub const MAX_BOID_WEIGHT: f32 = 10.0;
+// In cells per tick
+pub const BOID_MAX_SPEED: f32 = 1.0;
+// In cells, how far a boid sees its flockmates and how close it lets them get
+pub const BOID_VIEW_RADIUS: f32 = 8.0;
+pub const BOID_SEPARATION_RADIUS: f32 = 2.5;
+pub const PONG_PADDLE_HEIGHT: u16 = 8;
+// In cells per tick
+pub const PONG_BALL_SPEED: f32 = 0.7;
+pub const PONG_BALL_R_G_B: [u8; 3] = [220, 40, 40];
+// Snakes a room's game holds at once
+pub const MAX_SNAKES: usize = 16;
+pub const SNAKE_START_LENGTH: usize = 4;
+// Food kept on the grid, eaten food is replaced on the next tick
+pub const SNAKE_FOOD_COUNT: usize = 5;
+pub const SNAKE_FOOD_R_G_B: [u8; 3] = [240, 200, 40];
+// Chance of each cell starting alive in a new Immigration Game grid
+pub const IMMIGRATION_SEED_DENSITY: f64 = 0.25;
+// Cell colors of the red and blue Immigration Game teams, by team id
+pub const IMMIGRATION_TEAM_R_G_B: [[u8; 3]; 2] = [[220, 60, 60], [60, 110, 220]];
+// Soup search: squares of random cells, each alive by chance, run in the middle of an
+// empty field with dead edges
+pub const SOUP_SIZE: usize = 16;
+pub const SOUP_DENSITY: f64 = 0.5;
+pub const SOUP_FIELD_SIZE: u16 = 128;
+// Soups still changing after this many generations are reported with it as their lifespan
+pub const SOUP_MAX_GENERATIONS: u64 = 4000;
+// Generations a soup has to take to settle to be reported as a methuselah
+pub const SOUP_METHUSELAH_LIFESPAN: u64 = 1000;
+// Soups run between checks for a stop request
+pub const SOUP_BATCH_SIZE: usize = 64;
+// Odd so consecutive checks see spaceships in different phases
+pub const SOUP_SPACESHIP_CHECK_EVERY: u64 = 25;
+pub const SOUP_MAX_SPACESHIP_PERIOD: u32 = 8;
+// Turns of the chaos bot, and the random cells it awakens on each
+pub const CHAOS_BOT_INTERVAL_MS: u64 = 250;
+pub const CHAOS_BOT_CELLS: usize = 6;
+// Turns of the gardener bot, which stamps a glider on each
+pub const GARDENER_BOT_INTERVAL_MS: u64 = 3000;
+// Painting pixels darker than this become live cells when seeding GOL from MLP
+pub const CROSSOVER_LUMINANCE_THRESHOLD: u8 = 100;
+// Uploaded images wider or taller than this are refused before they're decoded
+pub const MAX_UPLOAD_IMAGE_SIDE: u32 = 4096;
+// Block sizes of coarse-to-fine painting, coarsest first down to single pixels. Uploaded
+// images are painted in blocks of these sizes and coarse-to-fine reveals interlace by them.
+pub const COARSE_TO_FINE_BLOCKS: [usize; 4] = [8, 4, 2, 1];
+// Side of the square regions a painting revealed region by region is split into
+pub const MLP_REGION_SIDE: usize = 16;
+// Paintings a room keeps side by side, the shown one included
+pub const MAX_MLP_PAINTINGS: usize = 16;
 
+/// Broadcast topics a connection can subscribe to, as a bitmask
+pub mod topics {
+    pub const GOL_FRAMES: u8 = 1 << 0;
+    pub const MLP_FRAMES: u8 = 1 << 1;
+    pub const PIXEL_EVENTS: u8 = 1 << 2;
+    pub const BRAIN_FRAMES: u8 = 1 << 3;
+    pub const SAND_FRAMES: u8 = 1 << 4;
+    pub const REACTION_FRAMES: u8 = 1 << 5;
+    pub const BOID_FRAMES: u8 = 1 << 6;
+    // Status and notices, delivered regardless of subscriptions
+    pub const SYSTEM: u8 = 1 << 7;
+
+    pub const ALL: u8 = GOL_FRAMES
+        | MLP_FRAMES
+        | PIXEL_EVENTS
+        | BRAIN_FRAMES
+        | SAND_FRAMES
+        | REACTION_FRAMES
+        | BOID_FRAMES
+        | SYSTEM;
+}
+
+/// Codes of ERROR messages, which only go to the client that caused them
+pub mod error_codes {
+    pub const MALFORMED_MESSAGE: u16 = 1;
+    pub const UNSUPPORTED_VERSION: u16 = 2;
+    pub const UNKNOWN_MESSAGE_TYPE: u16 = 3;
+    pub const INVALID_TEXT_MESSAGE: u16 = 4;
+    pub const RECEIVE_FAILED: u16 = 5;
+    pub const SEND_FAILED: u16 = 6;
+    pub const BROADCAST_FAILED: u16 = 7;
+    pub const TIMEOUT: u16 = 8;
+    pub const CONNECTION_CLOSED: u16 = 9;
+    pub const UNAUTHORIZED: u16 = 10;
+    pub const KICKED: u16 = 11;
+    pub const SLOW_CONSUMER: u16 = 12;
+    pub const CHECKSUM_MISMATCH: u16 = 13;
+    pub const HANDLER_FAILED: u16 = 14;
+    pub const FRAME_ENCODE_FAILED: u16 = 15;
+    pub const WRONG_MODE: u16 = 16;
+    pub const ROOM_NOT_CREATED: u16 = 17;
+}
+
+// Numbered by offset into the ranges registered in `registry`
 pub mod message_types {
-    pub const HELLO: u8 = 1;
+    use crate::registry::{
+        BOIDS, BOTS, BRIANS_BRAIN, CHECKPOINTS, CLIENT_INPUT, GOL, HANDSHAKE, IMMIGRATION, MLP,
+        MODES, PONG, REACTION, SAND, SERVER, SIMULATION, SNAKE, SUBSCRIPTIONS,
+    };
+
+    pub const HELLO: u8 = HANDSHAKE.at(0);
+    pub const SET_TEXT_PREFERENCES: u8 = HANDSHAKE.at(1);
+    pub const SET_NICKNAME: u8 = HANDSHAKE.at(2);
+    pub const AUTH: u8 = HANDSHAKE.at(3);
+    pub const KICK_CLIENT: u8 = HANDSHAKE.at(4);
+    pub const GET_SERVER_STATS: u8 = HANDSHAKE.at(5);
+    pub const ADD_SCHEDULE_ENTRY: u8 = HANDSHAKE.at(6);
+    pub const REMOVE_SCHEDULE_ENTRY: u8 = HANDSHAKE.at(7);
+    pub const LIST_SCHEDULE: u8 = HANDSHAKE.at(8);
+    pub const LIST_ROOMS: u8 = HANDSHAKE.at(9);
+    pub const CREATE_ROOM: u8 = HANDSHAKE.at(10);
+
+    pub const CREATE_NEW_GOL_GENERATION: u8 = GOL.at(0);
+    pub const AWAKEN_RANDOM_GOL_CELL: u8 = GOL.at(1);
+    pub const KILL_RANDOM_GOL_CELL: u8 = GOL.at(2);
+    pub const ADVANCE_GOL_GENERATION: u8 = GOL.at(3);
+    pub const KILL_ALL_GOL_CELLS: u8 = GOL.at(5);
+    pub const SEED_GOL_FROM_MLP_PAINTING: u8 = GOL.at(6);
+    pub const LOAD_GOL_PATTERN: u8 = GOL.at(7);
+    pub const STAMP_GOL_PATTERN: u8 = GOL.at(8);
+    pub const AWAKEN_CELLS_BATCH: u8 = GOL.at(9);
+    pub const BRUSH_STROKE: u8 = GOL.at(10);
+    pub const SET_COLOR_SCHEME: u8 = GOL.at(11);
+    pub const SET_GOL_HYBRID: u8 = GOL.at(12);
+    pub const SEED_GOL_FROM_IMAGE: u8 = GOL.at(13);
+    pub const TOGGLE_CELL: u8 = GOL.at(14);
+    pub const FILL_RECT: u8 = GOL.at(15);
+    pub const CLEAR_RECT: u8 = GOL.at(16);
+    pub const UNDO: u8 = GOL.at(17);
+    pub const REDO: u8 = GOL.at(18);
+
+    pub const SET_SIMULATION_SPEED: u8 = SIMULATION.at(0);
+    pub const PAUSE_SIMULATION: u8 = SIMULATION.at(1);
+    pub const RESUME_SIMULATION: u8 = SIMULATION.at(2);
+    pub const SET_SEED: u8 = SIMULATION.at(3);
+    pub const SET_AUTOPLAY: u8 = SIMULATION.at(4);
+    pub const START_SOUP_SEARCH: u8 = SIMULATION.at(5);
+    pub const STOP_SOUP_SEARCH: u8 = SIMULATION.at(6);
+    pub const SAVE_STATE: u8 = SIMULATION.at(7);
+    pub const LOAD_STATE: u8 = SIMULATION.at(8);
+    pub const SELECT_ENGINE: u8 = SIMULATION.at(9);
+
+    pub const START_BOT: u8 = BOTS.at(0);
+    pub const STOP_BOT: u8 = BOTS.at(1);
+
+    pub const SUBSCRIBE: u8 = SUBSCRIPTIONS.at(0);
+    pub const UNSUBSCRIBE: u8 = SUBSCRIPTIONS.at(1);
+    pub const RESYNC_REQUEST: u8 = SUBSCRIPTIONS.at(2);
+    pub const SET_VIEWPORT: u8 = SUBSCRIPTIONS.at(3);
+
+    pub const CREATE_NEW_MLP_PAINTING: u8 = MLP.at(0);
+    pub const ADVANCE_MLP_PAINTING: u8 = MLP.at(1);
+    pub const PAINT_MLP_FROM_GOL_GENERATION: u8 = MLP.at(2);
+    pub const UPLOAD_MLP_IMAGE: u8 = MLP.at(3);
+    pub const ADD_MLP_PAINTING: u8 = MLP.at(4);
+    pub const SELECT_MLP_PAINTING: u8 = MLP.at(5);
+    pub const DELETE_MLP_PAINTING: u8 = MLP.at(6);
+    pub const LIST_MLP_PAINTINGS: u8 = MLP.at(7);
+    pub const ADVANCE_MLP_PAINTING_TO: u8 = MLP.at(8);
+    pub const SET_MLP_STYLE: u8 = MLP.at(9);
+
+    pub const CREATE_NEW_BRAIN_GENERATION: u8 = BRIANS_BRAIN.at(0);
+    pub const ADVANCE_BRAIN_GENERATION: u8 = BRIANS_BRAIN.at(1);
+
+    pub const SWITCH_MODE: u8 = MODES.at(0);
+
+    pub const SPAWN_MATERIAL: u8 = SAND.at(0);
+
+    pub const CREATE_NEW_REACTION: u8 = REACTION.at(0);
+    pub const ADVANCE_REACTION: u8 = REACTION.at(1);
+    pub const SET_REACTION_RATES: u8 = REACTION.at(2);
+
+    pub const SPAWN_BOIDS: u8 = BOIDS.at(0);
+    pub const SET_BOID_WEIGHTS: u8 = BOIDS.at(1);
+    pub const RESET_BOIDS: u8 = BOIDS.at(2);
+
+    pub const REQUEST_RANDOM_COLORED_PIXEL: u8 = CLIENT_INPUT.at(0);
+    pub const INPUT_EVENT: u8 = CLIENT_INPUT.at(1);
+    pub const CURSOR_MOVE: u8 = CLIENT_INPUT.at(2);
+    pub const CHAT: u8 = CLIENT_INPUT.at(3);
+
+    pub const CLAIM_PADDLE: u8 = PONG.at(0);
+    pub const MOVE_PADDLE: u8 = PONG.at(1);
+
+    pub const JOIN_SNAKE: u8 = SNAKE.at(0);
+    pub const TURN_SNAKE: u8 = SNAKE.at(1);
+
+    pub const CREATE_NEW_IMMIGRATION_GENERATION: u8 = IMMIGRATION.at(0);
+    pub const ADVANCE_IMMIGRATION_GENERATION: u8 = IMMIGRATION.at(1);
+    pub const AWAKEN_TEAM_CELL: u8 = IMMIGRATION.at(2);
 
-    pub const CREATE_NEW_GOL_GENERATION: u8 = 40;
-    pub const AWAKEN_RANDOM_GOL_CELL: u8 = 41;
-    pub const KILL_RANDOM_GOL_CELL: u8 = 42;
-    pub const ADVANCE_GOL_GENERATION: u8 = 43;
-    pub const KILL_ALL_GOL_CELLS: u8 = 45;
+    pub const LIST_CHECKPOINTS: u8 = CHECKPOINTS.at(0);
+    pub const RESTORE_CHECKPOINT: u8 = CHECKPOINTS.at(1);
 
-    pub const CREATE_NEW_MLP_PAINTING: u8 = 20;
-    pub const ADVANCE_MLP_PAINTING: u8 = 21;
+    pub const DRAW_PIXEL: u8 = SERVER.at(0);
+    pub const DRAW_FRAME: u8 = SERVER.at(1);
+    pub const SIMULATION_STATUS: u8 = SERVER.at(2);
+    pub const SIMULATION_RESET: u8 = SERVER.at(3);
+    pub const CAPABILITIES: u8 = SERVER.at(4);
+    pub const ROOM_OCCUPANCY: u8 = SERVER.at(5);
+    pub const JOIN_SUMMARY: u8 = SERVER.at(6);
+    pub const DRAW_PIXELS_BATCH: u8 = SERVER.at(7);
+    pub const PREDICTION_PARAMS: u8 = SERVER.at(8);
+    pub const GENERATION_HASH: u8 = SERVER.at(9);
+    pub const ERROR: u8 = SERVER.at(10);
+    pub const PONG_SCORE: u8 = SERVER.at(11);
+    pub const PADDLE_ASSIGNED: u8 = SERVER.at(12);
+    pub const TEAM_ASSIGNED: u8 = SERVER.at(13);
+    pub const SIMULATION_STABLE: u8 = SERVER.at(14);
+    pub const SOUP_FOUND: u8 = SERVER.at(15);
+    pub const DRAW_REGION: u8 = SERVER.at(16);
+    pub const CLIENT_IDENTITY: u8 = SERVER.at(17);
+    pub const CLIENT_JOINED: u8 = SERVER.at(18);
+    pub const CLIENT_LEFT: u8 = SERVER.at(19);
+    pub const PRESENCE_LIST: u8 = SERVER.at(20);
+    pub const CURSOR_MOVED: u8 = SERVER.at(21);
+    pub const CHAT_MESSAGE: u8 = SERVER.at(22);
+    pub const ADMIN_STATUS: u8 = SERVER.at(23);
+    pub const STATE_SAVED: u8 = SERVER.at(24);
+    pub const CHECKPOINT_LIST: u8 = SERVER.at(25);
+    pub const HANDSHAKE_ACCEPTED: u8 = SERVER.at(26);
+    pub const MLP_PAINTING_LIST: u8 = SERVER.at(27);
+    pub const DRAW_TILES: u8 = SERVER.at(28);
+    pub const SERVER_STATS: u8 = SERVER.at(29);
+    pub const BOT_STATUS: u8 = SERVER.at(30);
+    pub const SCHEDULED_EVENT: u8 = SERVER.at(31);
+    pub const SCHEDULE_LIST: u8 = SERVER.at(32);
+    pub const MODE_CHANGED: u8 = SERVER.at(33);
+    pub const ROOM_LIST: u8 = SERVER.at(34);
 
-    pub const REQUEST_RANDOM_COLORED_PIXEL: u8 = 200;
+    /// Message type with the constant name `constant`, the reverse of `name`
+    pub fn from_name(constant: &str) -> Option<u8> {
+        (0..=u8::MAX).find(|&msg_type| name(msg_type) == Some(constant))
+    }
 
-    pub const DRAW_PIXEL: u8 = 100;
-    pub const DRAW_FRAME: u8 = 101;
+    /// Constant name of a message type, for logs and debugging tools
+    pub fn name(msg_type: u8) -> Option<&'static str> {
+        Some(match msg_type {
+            HELLO => "HELLO",
+            SET_TEXT_PREFERENCES => "SET_TEXT_PREFERENCES",
+            SET_NICKNAME => "SET_NICKNAME",
+            AUTH => "AUTH",
+            KICK_CLIENT => "KICK_CLIENT",
+            GET_SERVER_STATS => "GET_SERVER_STATS",
+            ADD_SCHEDULE_ENTRY => "ADD_SCHEDULE_ENTRY",
+            REMOVE_SCHEDULE_ENTRY => "REMOVE_SCHEDULE_ENTRY",
+            LIST_SCHEDULE => "LIST_SCHEDULE",
+            LIST_ROOMS => "LIST_ROOMS",
+            CREATE_ROOM => "CREATE_ROOM",
+            CREATE_NEW_GOL_GENERATION => "CREATE_NEW_GOL_GENERATION",
+            AWAKEN_RANDOM_GOL_CELL => "AWAKEN_RANDOM_GOL_CELL",
+            KILL_RANDOM_GOL_CELL => "KILL_RANDOM_GOL_CELL",
+            ADVANCE_GOL_GENERATION => "ADVANCE_GOL_GENERATION",
+            KILL_ALL_GOL_CELLS => "KILL_ALL_GOL_CELLS",
+            SEED_GOL_FROM_MLP_PAINTING => "SEED_GOL_FROM_MLP_PAINTING",
+            LOAD_GOL_PATTERN => "LOAD_GOL_PATTERN",
+            STAMP_GOL_PATTERN => "STAMP_GOL_PATTERN",
+            AWAKEN_CELLS_BATCH => "AWAKEN_CELLS_BATCH",
+            BRUSH_STROKE => "BRUSH_STROKE",
+            SET_COLOR_SCHEME => "SET_COLOR_SCHEME",
+            SET_GOL_HYBRID => "SET_GOL_HYBRID",
+            SEED_GOL_FROM_IMAGE => "SEED_GOL_FROM_IMAGE",
+            TOGGLE_CELL => "TOGGLE_CELL",
+            FILL_RECT => "FILL_RECT",
+            CLEAR_RECT => "CLEAR_RECT",
+            UNDO => "UNDO",
+            REDO => "REDO",
+            SET_SIMULATION_SPEED => "SET_SIMULATION_SPEED",
+            PAUSE_SIMULATION => "PAUSE_SIMULATION",
+            RESUME_SIMULATION => "RESUME_SIMULATION",
+            SET_SEED => "SET_SEED",
+            SET_AUTOPLAY => "SET_AUTOPLAY",
+            START_SOUP_SEARCH => "START_SOUP_SEARCH",
+            STOP_SOUP_SEARCH => "STOP_SOUP_SEARCH",
+            SAVE_STATE => "SAVE_STATE",
+            LOAD_STATE => "LOAD_STATE",
+            SELECT_ENGINE => "SELECT_ENGINE",
+            START_BOT => "START_BOT",
+            STOP_BOT => "STOP_BOT",
+            SUBSCRIBE => "SUBSCRIBE",
+            UNSUBSCRIBE => "UNSUBSCRIBE",
+            RESYNC_REQUEST => "RESYNC_REQUEST",
+            SET_VIEWPORT => "SET_VIEWPORT",
+            CREATE_NEW_MLP_PAINTING => "CREATE_NEW_MLP_PAINTING",
+            ADVANCE_MLP_PAINTING => "ADVANCE_MLP_PAINTING",
+            PAINT_MLP_FROM_GOL_GENERATION => "PAINT_MLP_FROM_GOL_GENERATION",
+            UPLOAD_MLP_IMAGE => "UPLOAD_MLP_IMAGE",
+            ADD_MLP_PAINTING => "ADD_MLP_PAINTING",
+            SELECT_MLP_PAINTING => "SELECT_MLP_PAINTING",
+            DELETE_MLP_PAINTING => "DELETE_MLP_PAINTING",
+            LIST_MLP_PAINTINGS => "LIST_MLP_PAINTINGS",
+            ADVANCE_MLP_PAINTING_TO => "ADVANCE_MLP_PAINTING_TO",
+            SET_MLP_STYLE => "SET_MLP_STYLE",
+            CREATE_NEW_BRAIN_GENERATION => "CREATE_NEW_BRAIN_GENERATION",
+            ADVANCE_BRAIN_GENERATION => "ADVANCE_BRAIN_GENERATION",
+            SWITCH_MODE => "SWITCH_MODE",
+            SPAWN_MATERIAL => "SPAWN_MATERIAL",
+            CREATE_NEW_REACTION => "CREATE_NEW_REACTION",
+            ADVANCE_REACTION => "ADVANCE_REACTION",
+            SET_REACTION_RATES => "SET_REACTION_RATES",
+            SPAWN_BOIDS => "SPAWN_BOIDS",
+            SET_BOID_WEIGHTS => "SET_BOID_WEIGHTS",
+            RESET_BOIDS => "RESET_BOIDS",
+            REQUEST_RANDOM_COLORED_PIXEL => "REQUEST_RANDOM_COLORED_PIXEL",
+            INPUT_EVENT => "INPUT_EVENT",
+            CURSOR_MOVE => "CURSOR_MOVE",
+            CHAT => "CHAT",
+            CLAIM_PADDLE => "CLAIM_PADDLE",
+            MOVE_PADDLE => "MOVE_PADDLE",
+            JOIN_SNAKE => "JOIN_SNAKE",
+            TURN_SNAKE => "TURN_SNAKE",
+            CREATE_NEW_IMMIGRATION_GENERATION => "CREATE_NEW_IMMIGRATION_GENERATION",
+            ADVANCE_IMMIGRATION_GENERATION => "ADVANCE_IMMIGRATION_GENERATION",
+            AWAKEN_TEAM_CELL => "AWAKEN_TEAM_CELL",
+            LIST_CHECKPOINTS => "LIST_CHECKPOINTS",
+            RESTORE_CHECKPOINT => "RESTORE_CHECKPOINT",
+            DRAW_PIXEL => "DRAW_PIXEL",
+            DRAW_FRAME => "DRAW_FRAME",
+            SIMULATION_STATUS => "SIMULATION_STATUS",
+            SIMULATION_RESET => "SIMULATION_RESET",
+            CAPABILITIES => "CAPABILITIES",
+            ROOM_OCCUPANCY => "ROOM_OCCUPANCY",
+            JOIN_SUMMARY => "JOIN_SUMMARY",
+            DRAW_PIXELS_BATCH => "DRAW_PIXELS_BATCH",
+            PREDICTION_PARAMS => "PREDICTION_PARAMS",
+            GENERATION_HASH => "GENERATION_HASH",
+            ERROR => "ERROR",
+            PONG_SCORE => "PONG_SCORE",
+            PADDLE_ASSIGNED => "PADDLE_ASSIGNED",
+            TEAM_ASSIGNED => "TEAM_ASSIGNED",
+            SIMULATION_STABLE => "SIMULATION_STABLE",
+            SOUP_FOUND => "SOUP_FOUND",
+            DRAW_REGION => "DRAW_REGION",
+            CLIENT_IDENTITY => "CLIENT_IDENTITY",
+            CLIENT_JOINED => "CLIENT_JOINED",
+            CLIENT_LEFT => "CLIENT_LEFT",
+            PRESENCE_LIST => "PRESENCE_LIST",
+            CURSOR_MOVED => "CURSOR_MOVED",
+            CHAT_MESSAGE => "CHAT_MESSAGE",
+            ADMIN_STATUS => "ADMIN_STATUS",
+            STATE_SAVED => "STATE_SAVED",
+            CHECKPOINT_LIST => "CHECKPOINT_LIST",
+            HANDSHAKE_ACCEPTED => "HANDSHAKE_ACCEPTED",
+            MLP_PAINTING_LIST => "MLP_PAINTING_LIST",
+            DRAW_TILES => "DRAW_TILES",
+            SERVER_STATS => "SERVER_STATS",
+            BOT_STATUS => "BOT_STATUS",
+            SCHEDULED_EVENT => "SCHEDULED_EVENT",
+            SCHEDULE_LIST => "SCHEDULE_LIST",
+            MODE_CHANGED => "MODE_CHANGED",
+            ROOM_LIST => "ROOM_LIST",
+            _ => return None,
+        })
+    }
 }
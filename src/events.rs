@@ -0,0 +1,130 @@
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum_tws::Message;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::OwnedSemaphorePermit;
+use tracing::{debug, info, warn};
+
+use crate::{
+    constants::topics,
+    fanout::{Received, RoomSubscription},
+    i18n::TextPreferences,
+    room::{DEFAULT_ROOM, JoinCredentials, RoomState},
+    state::AppState,
+};
+
+/// GET /events
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    events_response(&state, DEFAULT_ROOM, &credentials)
+}
+
+/// GET /events/{room}
+pub async fn room_events(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    events_response(&state, &room, &credentials)
+}
+
+/// Streams the room's frames as Server-Sent Events, for clients behind proxies that break
+/// WebSockets. Receive only, each event's data is one binary frame in base64.
+fn events_response(state: &AppState, room_id: &str, credentials: &JoinCredentials) -> Response {
+    info!("New event stream for room {:?}", room_id);
+
+    match state
+        .reserve_connection()
+        .and_then(|slot| Ok((slot, state.join_room(room_id, credentials)?)))
+    {
+        Ok((slot, room)) => {
+            let stream =
+                event_stream(room, slot).take_until(state.shutdown.clone().cancelled_owned());
+            Sse::new(stream)
+                .keep_alive(KeepAlive::default())
+                .into_response()
+        }
+        Err(e) => {
+            warn!("Rejected event stream: {}", e);
+            e.into_response()
+        }
+    }
+}
+
+fn frame_event(message: &Message) -> Result<Event, Infallible> {
+    Ok(Event::default().data(STANDARD.encode(&message.as_payload()[..])))
+}
+
+// Starts with a keyframe, then follows the room. The slot is held until the stream is dropped.
+fn event_stream(
+    room: Arc<RoomState>,
+    slot: OwnedSemaphorePermit,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let subscription = RoomSubscription::new(&room.channel, room.channel_capacity, None);
+    let keyframe = match room.keyframe() {
+        Ok(keyframe) => Some(frame_event(&keyframe)),
+        Err(e) => {
+            warn!(
+                "No keyframe for the event stream of room {:?}: {}",
+                room.id, e
+            );
+            None
+        }
+    };
+    let updates = futures::stream::unfold(
+        (room, subscription, slot),
+        |(room, mut subscription, slot)| async move {
+            let message = match subscription.recv(topics::ALL).await {
+                Ok(Received::Message(broadcast)) => {
+                    broadcast.render(&TextPreferences::default(), 0)
+                }
+                // An event stream can't ask for a resync, so it gets a keyframe right away
+                Ok(Received::Lagged(skipped)) => {
+                    debug!("Event stream lagged by {} messages", skipped);
+                    match room.keyframe() {
+                        Ok(keyframe) => keyframe,
+                        Err(e) => {
+                            warn!("Ending event stream for room {:?}: {}", room.id, e);
+                            return None;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Ending event stream for room {:?}: {}", room.id, e);
+                    return None;
+                }
+            };
+            Some((frame_event(&message), (room, subscription, slot)))
+        },
+    );
+    futures::stream::iter(keyframe).chain(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::BroadcastMessage;
+    use futures::FutureExt;
+    use tokio::sync::Semaphore;
+
+    #[test]
+    fn event_streams_start_with_a_keyframe_and_follow_the_room() {
+        let room = RoomState::for_test();
+        let slot = Arc::new(Semaphore::new(1)).try_acquire_owned().unwrap();
+        let mut stream = Box::pin(event_stream(room.clone(), slot));
+        assert!(stream.next().now_or_never().flatten().is_some());
+        assert!(stream.next().now_or_never().is_none());
+
+        let frame = Message::binary(vec![1, 100, 0]);
+        room.channel
+            .send(BroadcastMessage::new(topics::SYSTEM, frame))
+            .unwrap();
+        assert!(stream.next().now_or_never().flatten().is_some());
+    }
+}
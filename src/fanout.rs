@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::{
+    constants::{SLOW_CLIENT_BACKLOG_PERCENT, SLOW_CLIENT_GRACE_MS, topics},
+    room::{BroadcastMessage, RoomChannel},
+};
+
+/// Why a subscriber stops getting the room's messages
+#[derive(Debug, thiserror::Error)]
+pub enum SubscriptionError {
+    #[error("Too slow to keep up with the room, {backlog} messages behind")]
+    SlowConsumer { backlog: usize },
+    #[error("Room channel closed")]
+    Closed,
+}
+
+/// What a subscriber gets from the room next
+#[derive(Debug)]
+pub enum Received {
+    Message(BroadcastMessage),
+    // Messages the subscriber fell too far behind to get, it needs a keyframe to catch up
+    Lagged(u64),
+}
+
+/// One subscriber's view of a room's broadcasts, whichever transport delivers them. Skips
+/// messages outside its topics and those it sent itself, and gives up on subscribers that
+/// stay too far behind.
+pub struct RoomSubscription {
+    receiver: broadcast::Receiver<BroadcastMessage>,
+    // Connection whose own messages are skipped, None for receive-only subscribers
+    connection_id: Option<String>,
+    // Room messages the subscriber may have queued up before it counts as falling behind
+    max_backlog: usize,
+    // When the subscriber last fell behind, None while it keeps up
+    backlogged_since: Option<Instant>,
+}
+
+impl RoomSubscription {
+    pub fn new(channel: &RoomChannel, capacity: usize, connection_id: Option<String>) -> Self {
+        Self {
+            receiver: channel.subscribe(),
+            connection_id,
+            max_backlog: capacity * SLOW_CLIENT_BACKLOG_PERCENT / 100,
+            backlogged_since: None,
+        }
+    }
+
+    /// Waits for the next message in `subscriptions`, a bitmask of `topics`. Cancel safe.
+    pub async fn recv(&mut self, subscriptions: u8) -> Result<Received, SubscriptionError> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(broadcast) => {
+                    self.check_backlog(self.receiver.len())?;
+                    let subscribed =
+                        broadcast.topic == topics::SYSTEM || subscriptions & broadcast.topic != 0;
+                    let own = self
+                        .connection_id
+                        .as_deref()
+                        .is_some_and(|id| broadcast.is_from(id));
+                    if subscribed && !own {
+                        return Ok(Received::Message(broadcast));
+                    }
+                }
+                // Subscribers catch up with a keyframe, so lagging alone doesn't count toward
+                // dropping them. Staying behind afterwards does.
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    return Ok(Received::Lagged(skipped));
+                }
+                Err(broadcast::error::RecvError::Closed) => return Err(SubscriptionError::Closed),
+            }
+        }
+    }
+
+    /// Room messages waiting to be received
+    pub fn queued(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Tracks how many room messages the subscriber has queued up, giving up on it once it
+    /// has been behind for `SLOW_CLIENT_GRACE_MS`. Short bursts are fine, a subscriber that
+    /// can't keep up would otherwise lag the channel over and over.
+    fn check_backlog(&mut self, backlog: usize) -> Result<(), SubscriptionError> {
+        if backlog < self.max_backlog {
+            self.backlogged_since = None;
+            return Ok(());
+        }
+        let since = *self.backlogged_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= Duration::from_millis(SLOW_CLIENT_GRACE_MS) {
+            return Err(SubscriptionError::SlowConsumer { backlog });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum_tws::Message;
+    use futures::FutureExt;
+
+    #[test]
+    fn subscribers_behind_past_the_grace_period_are_dropped() {
+        let channel = RoomChannel::new(100);
+        let mut subscription = RoomSubscription::new(&channel, 100, None);
+        assert!(subscription.check_backlog(74).is_ok());
+        assert!(subscription.check_backlog(80).is_ok());
+        assert!(subscription.backlogged_since.is_some());
+        // Catching up resets the clock
+        assert!(subscription.check_backlog(10).is_ok());
+        assert!(subscription.backlogged_since.is_none());
+
+        subscription.backlogged_since =
+            Some(Instant::now() - Duration::from_millis(SLOW_CLIENT_GRACE_MS));
+        assert!(matches!(
+            subscription.check_backlog(90),
+            Err(SubscriptionError::SlowConsumer { backlog: 90 })
+        ));
+    }
+
+    #[test]
+    fn subscribers_skip_other_topics_and_their_own_messages() {
+        let channel = RoomChannel::new(8);
+        let mut subscription = RoomSubscription::new(&channel, 8, Some("a".to_string()));
+        let message = |topic| BroadcastMessage::new(topic, Message::binary(vec![1, 100, 0]));
+        channel.send(message(topics::GOL_FRAMES)).unwrap();
+        channel.send(message(topics::SYSTEM).sent_by("a")).unwrap();
+        channel.send(message(topics::PIXEL_EVENTS)).unwrap();
+
+        let received = subscription
+            .recv(topics::PIXEL_EVENTS)
+            .now_or_never()
+            .unwrap();
+        let Ok(Received::Message(received)) = received else {
+            panic!("expected a message");
+        };
+        assert_eq!(received.topic, topics::PIXEL_EVENTS);
+    }
+
+    #[test]
+    fn lagging_subscribers_catch_up_instead_of_being_dropped() {
+        let channel = RoomChannel::new(4);
+        let mut subscription = RoomSubscription::new(&channel, 4, None);
+        for _ in 0..10 {
+            let frame = BroadcastMessage::new(topics::GOL_FRAMES, Message::binary(vec![1, 101]));
+            channel.send(frame).unwrap();
+        }
+        assert_eq!(channel.last_sequence(topics::GOL_FRAMES), Some(10));
+
+        let received = subscription.recv(topics::ALL).now_or_never().unwrap();
+        assert!(matches!(received, Ok(Received::Lagged(6))));
+        assert!(subscription.backlogged_since.is_none());
+        // The oldest messages the channel still holds follow
+        let Ok(Received::Message(received)) =
+            subscription.recv(topics::ALL).now_or_never().unwrap()
+        else {
+            panic!("expected a message");
+        };
+        assert_eq!(received.sequence(), Some(7));
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Result, bail};
+
+use crate::{
+    constants::HELLO_PAYLOAD,
+    protocol::{PROTOCOL_VERSION, ProtocolError},
+};
+
+/// What a client offers in its HELLO. Older clients send only the magic, or the magic and
+/// their codecs, and keep the defaults for the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHello {
+    pub codecs: u8,
+    pub versions: Vec<u8>,
+    // Topics the client wants to start with, None keeps its subscriptions
+    pub topics: Option<u8>,
+}
+
+impl ClientHello {
+    // HELLO payload format:
+    // - 5 bytes: "hello"
+    // - 1 byte: codecs the client decodes (bit 0: deflate), optional
+    // - 1 byte: number of protocol versions, N bytes: the versions it speaks, optional
+    // - 1 byte: topics it wants as a bitmask, optional
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        let Some(rest) = payload.strip_prefix(HELLO_PAYLOAD) else {
+            bail!(
+                "HELLO without the {:?} magic",
+                String::from_utf8_lossy(HELLO_PAYLOAD)
+            );
+        };
+        let (codecs, rest) = match rest.split_first() {
+            Some((&codecs, rest)) => (codecs, rest),
+            None => (0, rest),
+        };
+        let (versions, rest) = match rest.split_first() {
+            Some((&count, rest)) => {
+                if rest.len() < count as usize {
+                    bail!("HELLO lists {} versions in {} bytes", count, rest.len());
+                }
+                let (versions, rest) = rest.split_at(count as usize);
+                (versions.to_vec(), rest)
+            }
+            None => (vec![PROTOCOL_VERSION], rest),
+        };
+        let topics = match rest {
+            [] => None,
+            [topics] => Some(*topics),
+            _ => bail!("HELLO with {} trailing bytes", rest.len() - 1),
+        };
+
+        Ok(Self {
+            codecs,
+            versions,
+            topics,
+        })
+    }
+
+    /// Newest protocol version both sides speak
+    pub fn negotiate_version(&self) -> Result<u8> {
+        if self.versions.contains(&PROTOCOL_VERSION) {
+            return Ok(PROTOCOL_VERSION);
+        }
+        match self.versions.iter().max() {
+            Some(&version) => Err(ProtocolError::UnsupportedVersion { version }.into()),
+            None => bail!("HELLO lists no protocol versions"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hello_negotiates_what_both_sides_support() {
+        let legacy = ClientHello::decode(b"hello").unwrap();
+        assert_eq!(legacy.codecs, 0);
+        assert_eq!(legacy.topics, None);
+        assert_eq!(legacy.negotiate_version().unwrap(), PROTOCOL_VERSION);
+        assert_eq!(ClientHello::decode(b"hello\x01").unwrap().codecs, 1);
+
+        let hello = ClientHello::decode(b"hello\x01\x02\x01\x07\x05").unwrap();
+        assert_eq!(hello.versions, [1, 7]);
+        assert_eq!(hello.topics, Some(5));
+        assert_eq!(hello.negotiate_version().unwrap(), PROTOCOL_VERSION);
+
+        let newer = ClientHello::decode(b"hello\x00\x01\x07").unwrap();
+        let e = newer.negotiate_version().unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<ProtocolError>(),
+            Some(ProtocolError::UnsupportedVersion { version: 7 })
+        ));
+        assert!(
+            ClientHello::decode(b"hello\x00\x00")
+                .unwrap()
+                .negotiate_version()
+                .is_err()
+        );
+        assert!(ClientHello::decode(b"hello\x00\x03\x01").is_err());
+        assert!(ClientHello::decode(b"hello\x00\x01\x01\x05\x05").is_err());
+        assert!(ClientHello::decode(b"howdy").is_err());
+    }
+}
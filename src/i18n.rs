@@ -0,0 +1,173 @@
+use anyhow::{Result, bail};
+use std::str::FromStr;
+
+/// Server-generated strings, rendered per connection in its preferred locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    SimulationRestored,
+    SimulationRestarted,
+    InvalidTextMessage,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    /// Accepts a BCP 47 tag and matches on its language, so "de-AT" is German
+    fn from_str(s: &str) -> Result<Self> {
+        let language = s.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "es" => Ok(Locale::Es),
+            "fr" => Ok(Locale::Fr),
+            _ => bail!("Unsupported locale {:?}", s),
+        }
+    }
+}
+
+impl Notice {
+    pub fn text(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Notice::SimulationRestored, Locale::En) => {
+                "Simulation stalled and was restored from the last good snapshot"
+            }
+            (Notice::SimulationRestored, Locale::De) => {
+                "Die Simulation hing und wurde aus dem letzten guten Stand wiederhergestellt"
+            }
+            (Notice::SimulationRestored, Locale::Es) => {
+                "La simulación se detuvo y se restauró desde la última instantánea válida"
+            }
+            (Notice::SimulationRestored, Locale::Fr) => {
+                "La simulation s'est bloquée et a été restaurée depuis le dernier instantané valide"
+            }
+            (Notice::SimulationRestarted, Locale::En) => "Simulation stalled and was restarted",
+            (Notice::SimulationRestarted, Locale::De) => {
+                "Die Simulation hing und wurde neu gestartet"
+            }
+            (Notice::SimulationRestarted, Locale::Es) => "La simulación se detuvo y se reinició",
+            (Notice::SimulationRestarted, Locale::Fr) => {
+                "La simulation s'est bloquée et a été redémarrée"
+            }
+            (Notice::InvalidTextMessage, Locale::En) => {
+                "Text messages must be JSON like {\"type\": \"HELLO\", \"payload\": [...]}"
+            }
+            (Notice::InvalidTextMessage, Locale::De) => {
+                "Textnachrichten müssen JSON sein, etwa {\"type\": \"HELLO\", \"payload\": [...]}"
+            }
+            (Notice::InvalidTextMessage, Locale::Es) => {
+                "Los mensajes de texto deben ser JSON, como {\"type\": \"HELLO\", \"payload\": [...]}"
+            }
+            (Notice::InvalidTextMessage, Locale::Fr) => {
+                "Les messages texte doivent être du JSON, comme {\"type\": \"HELLO\", \"payload\": [...]}"
+            }
+        }
+    }
+}
+
+/// How a connection wants server text: language, length limit and whether emoji may appear
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPreferences {
+    pub locale: Locale,
+    pub max_text_length: Option<usize>,
+    pub emoji_allowed: bool,
+}
+
+impl Default for TextPreferences {
+    fn default() -> Self {
+        Self {
+            locale: Locale::default(),
+            max_text_length: None,
+            emoji_allowed: true,
+        }
+    }
+}
+
+impl TextPreferences {
+    // Preferences payload format:
+    // - 1 byte: flags, bit 0 set when emoji are allowed
+    // - 2 bytes: max text length in characters, 0 for unlimited (big-endian)
+    // - N bytes: UTF-8 locale tag (e.g. "de-DE"), empty or unsupported falls back to English
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        if payload.len() < 3 {
+            bail!("Preferences payload of {} bytes", payload.len());
+        }
+        let max_text_length = u16::from_be_bytes([payload[1], payload[2]]) as usize;
+        let locale = std::str::from_utf8(&payload[3..])?
+            .parse()
+            .unwrap_or_default();
+
+        Ok(Self {
+            locale,
+            max_text_length: (max_text_length > 0).then_some(max_text_length),
+            emoji_allowed: payload[0] & 1 != 0,
+        })
+    }
+
+    pub fn render(&self, notice: Notice) -> String {
+        self.apply(notice.text(self.locale))
+    }
+
+    /// Drops emoji if unwanted and truncates to the length limit, marking the cut with '…'
+    pub fn apply(&self, text: &str) -> String {
+        let mut chars: Vec<char> = text
+            .chars()
+            .filter(|&c| self.emoji_allowed || !is_emoji(c))
+            .collect();
+        if let Some(max) = self.max_text_length
+            && chars.len() > max
+        {
+            chars.truncate(max.saturating_sub(1));
+            chars.push('…');
+        }
+        chars.into_iter().collect()
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F | 0x200D
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_match_on_language() {
+        assert_eq!("de-AT".parse::<Locale>().unwrap(), Locale::De);
+        assert_eq!("FR".parse::<Locale>().unwrap(), Locale::Fr);
+        assert_eq!("es_MX".parse::<Locale>().unwrap(), Locale::Es);
+        assert!("xx".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn preferences_shape_text() {
+        let preferences = TextPreferences::decode(&[0, 0, 12, b'd', b'e']).unwrap();
+        assert_eq!(preferences.locale, Locale::De);
+        assert_eq!(preferences.max_text_length, Some(12));
+        assert!(!preferences.emoji_allowed);
+
+        assert_eq!(
+            preferences.render(Notice::InvalidTextMessage),
+            "Textnachric…"
+        );
+        assert_eq!(preferences.apply("hi 🌍!"), "hi !");
+
+        let defaults = TextPreferences::decode(&[1, 0, 0]).unwrap();
+        assert_eq!(defaults, TextPreferences::default());
+        let unsupported = TextPreferences::decode(&[1, 0, 0, b'j', b'a']).unwrap();
+        assert_eq!(unsupported.locale, Locale::En);
+        assert!(TextPreferences::decode(&[1, 0]).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{Result, bail};
+use tracing::debug;
+
+pub const INPUT_EVENT_PAYLOAD_SIZE: usize = 4;
+
+/// Pattern an input event is addressed to
+pub mod input_targets {
+    pub const GOL: u8 = 0;
+    pub const MLP: u8 = 1;
+}
+
+/// Logical key/button identifiers, shared by keyboards and gamepads
+pub mod input_keys {
+    pub const UP: u16 = 1;
+    pub const DOWN: u16 = 2;
+    pub const LEFT: u16 = 3;
+    pub const RIGHT: u16 = 4;
+    pub const PRIMARY: u16 = 5; // space / gamepad A
+    pub const SECONDARY: u16 = 6; // enter / gamepad B
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Released,
+    Pressed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub target: u8,
+    pub key: u16,
+    pub state: KeyState,
+}
+
+impl InputEvent {
+    pub fn is_pressed(&self) -> bool {
+        self.state == KeyState::Pressed
+    }
+}
+
+// Input event payload format:
+// - 1 byte: target pattern (see `input_targets`)
+// - 2 bytes: key identifier (big-endian, see `input_keys`)
+// - 1 byte: state (0 = released, 1 = pressed)
+pub fn decode_input_event(payload: &[u8]) -> Result<InputEvent> {
+    if payload.len() != INPUT_EVENT_PAYLOAD_SIZE {
+        bail!(
+            "Invalid input event size: {} bytes (expected {})",
+            payload.len(),
+            INPUT_EVENT_PAYLOAD_SIZE
+        );
+    }
+
+    let target = payload[0];
+    let key = u16::from_be_bytes([payload[1], payload[2]]);
+    let state = match payload[3] {
+        0 => KeyState::Released,
+        1 => KeyState::Pressed,
+        other => bail!("Invalid key state: {}", other),
+    };
+
+    debug!(
+        "Decoded input event: target={}, key={}, state={:?}",
+        target, key, state
+    );
+
+    Ok(InputEvent { target, key, state })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_pressed_event() {
+        let event = decode_input_event(&[input_targets::MLP, 0, 5, 1]).unwrap();
+
+        assert_eq!(event.target, input_targets::MLP);
+        assert_eq!(event.key, input_keys::PRIMARY);
+        assert!(event.is_pressed());
+    }
+
+    #[test]
+    fn decode_invalid_size() {
+        let result = decode_input_event(&[0, 0, 1]);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid input event size")
+        );
+    }
+
+    #[test]
+    fn decode_invalid_state() {
+        let result = decode_input_event(&[0, 0, 1, 7]);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid key state")
+        );
+    }
+}
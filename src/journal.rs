@@ -0,0 +1,403 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::{debug, info, warn};
+
+use crate::{
+    constants::message_types,
+    patterns::gol::GolState,
+    payload::WsPayload,
+    protocol::{PROTOCOL_VERSION, WsMessage},
+    registry::{
+        BOIDS, BRIANS_BRAIN, CHECKPOINTS, CLIENT_INPUT, GOL, MLP, MODES, REACTION, SAND, SIMULATION,
+    },
+    room::{BroadcastMessage, RoomState},
+    utils::FrameError,
+};
+
+/// A command that changed a room, as appended to its journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub at: String,
+    pub connection: String,
+    pub command: String,
+    pub msg_type: u8,
+    pub flags: u8,
+    // Generation the command was applied to, replay steps the grid there first
+    pub generation: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Client commands that change room state; handshakes, subscriptions, echoed unknown
+/// types and Pong, Snake and Immigration Game input, which only mean something to live
+/// connections, aren't journaled. Neither are soup search, saving and listing checkpoints,
+/// they leave the grid alone.
+fn is_journaled(msg_type: u8) -> bool {
+    message_types::name(msg_type).is_some()
+        && !matches!(
+            msg_type,
+            message_types::START_SOUP_SEARCH
+                | message_types::STOP_SOUP_SEARCH
+                | message_types::SAVE_STATE
+                | message_types::LIST_CHECKPOINTS
+                | message_types::LIST_MLP_PAINTINGS
+                // Whole image files would bloat the log
+                | message_types::UPLOAD_MLP_IMAGE
+                | message_types::SEED_GOL_FROM_IMAGE
+        )
+        && [
+            GOL,
+            MLP,
+            BRIANS_BRAIN,
+            MODES,
+            SIMULATION,
+            SAND,
+            REACTION,
+            BOIDS,
+            CLIENT_INPUT,
+            CHECKPOINTS,
+        ]
+        .iter()
+        .any(|range| range.contains(msg_type))
+}
+
+/// Append-only log of the commands a room accepted, so a restarted server can rebuild the
+/// room and moderators can audit who changed what. Every `compact_every` entries the grid
+/// is snapshotted and the log starts over. Files in the journal directory:
+/// - `<room>.log`: JSON lines of the commands since the last snapshot
+/// - `<room>.snapshot`: [next seq u64][grid dump], big-endian
+/// - `<room>.<first seq>.log`: compacted logs, kept for audits
+pub struct CommandJournal {
+    dir: PathBuf,
+    room: String,
+    compact_every: u64,
+    writer: Mutex<JournalWriter>,
+}
+
+struct JournalWriter {
+    file: File,
+    next_seq: u64,
+    // Seq of the first entry in the current log
+    first_seq: u64,
+}
+
+impl CommandJournal {
+    pub fn open(dir: &Path, room: &str, compact_every: u64) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            room: room.to_string(),
+            compact_every,
+            writer: Mutex::new(JournalWriter {
+                file: open_log(&dir.join(format!("{}.log", room)))?,
+                next_seq: 0,
+                first_seq: 0,
+            }),
+        })
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.log", self.room))
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.snapshot", self.room))
+    }
+
+    fn writer(&self) -> MutexGuard<'_, JournalWriter> {
+        self.writer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Rebuilds the room from the last snapshot and the commands logged after it, returning
+    /// how many were replayed. Call before the room takes commands.
+    pub fn recover(&self, room: &Arc<RoomState>) -> Result<usize> {
+        let mut writer = self.writer();
+
+        let snapshot_seq = match std::fs::read(self.snapshot_path()) {
+            Ok(snapshot) if snapshot.len() >= 8 => {
+                room.gol.load_grid_dump(&snapshot[8..])?;
+                u64::from_be_bytes(snapshot[..8].try_into()?)
+            }
+            Ok(_) => {
+                warn!(
+                    "Ignoring truncated journal snapshot of room {:?}",
+                    self.room
+                );
+                0
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e).context("Failed to read journal snapshot"),
+        };
+
+        let (entries, valid_length) = read_log(&self.log_path())?;
+        if writer.file.metadata()?.len() > valid_length {
+            warn!(
+                "Dropping torn tail of the journal of room {:?} at byte {}",
+                self.room, valid_length
+            );
+            writer.file.set_len(valid_length)?;
+        }
+
+        let mut replayed = 0;
+        // Entries before the snapshot are left over from a compaction cut short
+        for entry in entries.iter().filter(|entry| entry.seq >= snapshot_seq) {
+            room.gol.fast_forward(entry.generation);
+            let payload = WsPayload {
+                parsed: WsMessage {
+                    version: PROTOCOL_VERSION,
+                    msg_type: entry.msg_type,
+                    flags: entry.flags,
+                    payload: Bytes::from(entry.payload.clone()),
+                },
+                room: room.clone(),
+                connection_id: entry.connection.clone(),
+            };
+            // Replaying only needs the board changed, not the frame drawn
+            if let Err(e) = payload.handle_payload() {
+                warn!(
+                    "Replayed journal entry {} without its frame: {}",
+                    entry.seq, e
+                );
+            }
+            replayed += 1;
+        }
+
+        writer.first_seq = entries.first().map_or(snapshot_seq, |entry| entry.seq);
+        writer.next_seq = entries
+            .last()
+            .map_or(snapshot_seq, |entry| entry.seq + 1)
+            .max(snapshot_seq);
+        Ok(replayed)
+    }
+
+    /// Handles the payload and journals it if it was accepted and changes the room.
+    /// Commands are handled and logged one at a time so the log order is the apply order.
+    pub fn record(
+        &self,
+        connection: &str,
+        payload: &WsPayload,
+    ) -> Result<Option<BroadcastMessage>, FrameError> {
+        let mut writer = self.writer();
+        let (generation, _) = payload.room.gol.generation_stats();
+        let Some(response) = payload.handle_payload()? else {
+            return Ok(None);
+        };
+
+        if is_journaled(payload.parsed.msg_type)
+            && let Err(e) = self.append(&mut writer, connection, &payload.parsed, generation)
+        {
+            warn!("Failed to journal command of room {:?}: {:#}", self.room, e);
+        }
+        if writer.next_seq - writer.first_seq >= self.compact_every
+            && let Err(e) = self.compact(&mut writer, &payload.room.gol)
+        {
+            warn!("Failed to compact journal of room {:?}: {:#}", self.room, e);
+        }
+        Ok(Some(response))
+    }
+
+    fn append(
+        &self,
+        writer: &mut JournalWriter,
+        connection: &str,
+        parsed: &WsMessage<Bytes>,
+        generation: u64,
+    ) -> Result<()> {
+        let entry = JournalEntry {
+            seq: writer.next_seq,
+            at: Local::now().to_rfc3339(),
+            connection: connection.to_string(),
+            command: message_types::name(parsed.msg_type)
+                .unwrap_or_default()
+                .to_string(),
+            msg_type: parsed.msg_type,
+            flags: parsed.flags,
+            generation,
+            payload: parsed.payload.to_vec(),
+        };
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        writer.file.write_all(&line)?;
+        writer.file.flush()?;
+        writer.next_seq += 1;
+        debug!("Journaled {} as #{}", entry.command, entry.seq);
+        Ok(())
+    }
+
+    // Snapshots the grid, then moves the log aside. A crash in between leaves entries older
+    // than the snapshot in the log, which recovery skips.
+    fn compact(&self, writer: &mut JournalWriter, gol: &GolState) -> Result<()> {
+        let snapshot_path = self.snapshot_path();
+        let partial_path = snapshot_path.with_extension("snapshot.partial");
+        let mut snapshot = writer.next_seq.to_be_bytes().to_vec();
+        snapshot.extend(gol.grid_dump());
+        std::fs::write(&partial_path, snapshot)
+            .with_context(|| format!("Failed to write {}", partial_path.display()))?;
+        std::fs::rename(&partial_path, &snapshot_path)?;
+
+        let log_path = self.log_path();
+        let archive_path = self
+            .dir
+            .join(format!("{}.{}.log", self.room, writer.first_seq));
+        std::fs::rename(&log_path, &archive_path)?;
+        writer.file = open_log(&log_path)?;
+        writer.first_seq = writer.next_seq;
+
+        info!(
+            "Compacted journal of room {:?} into a snapshot at #{}, log archived to {}",
+            self.room,
+            writer.next_seq,
+            archive_path.display()
+        );
+        Ok(())
+    }
+
+    /// Commands logged since the last snapshot, oldest first
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        let _writer = self.writer();
+        Ok(read_log(&self.log_path())?.0)
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+// Parses entries up to the first incomplete or unreadable line, as a crash mid-append
+// leaves behind. Returns them with the length of the log they were read from.
+fn read_log(path: &Path) -> Result<(Vec<JournalEntry>, u64)> {
+    let text = match std::fs::read(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let mut entries = Vec::new();
+    let mut valid_length = 0;
+    for line in text.split_inclusive(|&byte| byte == b'\n') {
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        match serde_json::from_slice(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => break,
+        }
+        valid_length += line.len() as u64;
+    }
+    Ok((entries, valid_length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{protocol::CellPayload, room::RoomSettings};
+
+    fn settings(journal_dir: &Path) -> RoomSettings {
+        RoomSettings {
+            journal_dir: Some(journal_dir.to_path_buf()),
+            ..RoomSettings::default()
+        }
+    }
+
+    fn command(journal: &CommandJournal, room: &Arc<RoomState>, msg_type: u8, payload: Vec<u8>) {
+        let payload = WsPayload {
+            parsed: WsMessage {
+                version: PROTOCOL_VERSION,
+                msg_type,
+                flags: 0,
+                payload: Bytes::from(payload),
+            },
+            room: room.clone(),
+            connection_id: "client-1".to_string(),
+        };
+        journal.record("client-1", &payload).unwrap();
+    }
+
+    fn cells(cells: &[(u16, u16)]) -> Vec<u8> {
+        cells
+            .iter()
+            .flat_map(|&(x, y)| CellPayload { x, y, rgb: None }.encode())
+            .collect()
+    }
+
+    #[test]
+    fn recovers_room_from_journal() {
+        let dir = std::env::temp_dir().join(format!("gol-journal-{}", uuid::Uuid::new_v4()));
+        let room = RoomState::for_test_with(&settings(&dir));
+        let journal = room.journal.as_ref().unwrap();
+
+        command(journal, &room, message_types::KILL_ALL_GOL_CELLS, vec![]);
+        let glider = cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        command(journal, &room, message_types::AWAKEN_CELLS_BATCH, glider);
+        command(
+            journal,
+            &room,
+            message_types::ADVANCE_GOL_GENERATION,
+            vec![],
+        );
+        // Answered, but not a command, so left out of the journal
+        command(journal, &room, message_types::HELLO, vec![]);
+        let expected = room.gol.generation_hash();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].command, "AWAKEN_CELLS_BATCH");
+        assert_eq!(entries[1].connection, "client-1");
+        drop(room);
+
+        let recovered = RoomState::for_test_with(&settings(&dir));
+        assert_eq!(recovered.gol.generation_hash(), expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn compaction_snapshots_and_restarts_log() {
+        let dir = std::env::temp_dir().join(format!("gol-journal-{}", uuid::Uuid::new_v4()));
+        let room = RoomState::for_test();
+        let journal = CommandJournal::open(&dir, &room.id, 3).unwrap();
+        journal.recover(&room).unwrap();
+
+        // Random picks can't be replayed, so they only survive through the snapshot
+        command(&journal, &room, message_types::KILL_ALL_GOL_CELLS, vec![]);
+        command(
+            &journal,
+            &room,
+            message_types::AWAKEN_RANDOM_GOL_CELL,
+            vec![],
+        );
+        command(
+            &journal,
+            &room,
+            message_types::AWAKEN_RANDOM_GOL_CELL,
+            vec![],
+        );
+        command(
+            &journal,
+            &room,
+            message_types::AWAKEN_CELLS_BATCH,
+            cells(&[(5, 5)]),
+        );
+
+        assert_eq!(journal.entries().unwrap().len(), 1);
+        assert!(dir.join("test.snapshot").exists());
+        assert!(dir.join("test.0.log").exists());
+
+        let recovered = RoomState::for_test_with(&settings(&dir));
+        assert_eq!(recovered.gol.generation_hash(), room.gol.generation_hash());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
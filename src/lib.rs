@@ -0,0 +1,38 @@
+pub mod admin;
+pub mod api;
+pub mod bots;
+pub mod budget;
+pub mod checkpoints;
+pub mod client;
+pub mod config;
+pub mod constants;
+pub mod events;
+pub mod fanout;
+pub mod handshake;
+pub mod i18n;
+pub mod input;
+pub mod journal;
+pub mod lobby;
+pub mod message;
+pub mod metrics;
+pub mod modes;
+pub mod patterns;
+pub mod payload;
+pub mod presence;
+pub mod protocol;
+pub mod proxy;
+pub mod recording;
+pub mod registry;
+pub mod room;
+pub mod saves;
+pub mod scheduler;
+pub mod server;
+pub mod snapshots;
+pub mod socket;
+pub mod soup;
+pub mod state;
+pub mod stats;
+pub mod text_protocol;
+pub mod utils;
+pub mod viewport;
+pub mod watchdog;
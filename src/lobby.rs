@@ -0,0 +1,485 @@
+//! The server's rooms, as a lobby lists and creates them.
+//!
+//! Clients list public rooms with LIST_ROOMS or GET /api/rooms, and create one with
+//! CREATE_ROOM instead of only by joining an id nobody used yet. Private rooms are left out
+//! of the listing like they are from server stats. Rooms left empty for a while are removed,
+//! whichever way they were created.
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    constants::{
+        MAX_ROOMS, MAX_SPARSE_CANVAS_SIDE, MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS,
+        SCHEDULER_RUN,
+    },
+    patterns::gol::max_canvas_side,
+    room::{
+        DEFAULT_ROOM, JoinCredentials, RoomAccess, RoomError, RoomId, RoomSettings, RoomState,
+        spawn_simulation_loop, validate_room_id,
+    },
+    state::ActivePattern,
+};
+
+/// What the lobby shows of a room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoomSummary {
+    pub id: RoomId,
+    pub mode: ActivePattern,
+    // Connections receiving the room's messages
+    pub clients: usize,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl RoomSummary {
+    pub fn of(room: &RoomState) -> Self {
+        let (width, height) = room.gol.dimensions();
+        Self {
+            id: room.id.clone(),
+            mode: room.active_pattern(),
+            clients: room.channel.receiver_count(),
+            width,
+            height,
+        }
+    }
+}
+
+/// How CREATE_ROOM sets a room up, the server's room settings fill in the rest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomParams {
+    pub id: RoomId,
+    pub canvas_width: Option<u16>,
+    pub canvas_height: Option<u16>,
+    pub tick_interval_ms: Option<u64>,
+    pub autoplay: bool,
+    pub mode: ActivePattern,
+    // Makes the room private
+    pub password: Option<String>,
+}
+
+impl RoomParams {
+    // Create room payload format:
+    // - 1 byte: id length, N bytes: UTF-8 id
+    // - 2 bytes: canvas width, 2 bytes: canvas height (big-endian), 0 for the server's
+    // - 4 bytes: tick interval in ms (big-endian), 0 for the server's
+    // - 1 byte: flags, bit 0 set for autoplay
+    // - 1 byte: id of the mode the room starts in, see `ActivePattern::ALL`
+    // - N bytes: UTF-8 password, optional, which makes the room private
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        let Some((&id_length, rest)) = payload.split_first() else {
+            bail!("Empty create room payload");
+        };
+        let Some((id, rest)) = rest.split_at_checked(id_length as usize) else {
+            bail!("Create room payload of {} bytes", payload.len());
+        };
+        let [w0, w1, h0, h1, t0, t1, t2, t3, flags, mode, password @ ..] = rest else {
+            bail!("Create room payload of {} bytes", payload.len());
+        };
+        let id = std::str::from_utf8(id)?.to_string();
+        let side = |side: u16| (side != 0).then_some(side);
+        let canvas_width = side(u16::from_be_bytes([*w0, *w1]));
+        let canvas_height = side(u16::from_be_bytes([*h0, *h1]));
+        for side in [canvas_width, canvas_height].into_iter().flatten() {
+            if side > MAX_SPARSE_CANVAS_SIDE {
+                bail!(
+                    "Canvas side of {} cells above {}",
+                    side,
+                    MAX_SPARSE_CANVAS_SIDE
+                );
+            }
+        }
+        let tick_interval_ms = match u32::from_be_bytes([*t0, *t1, *t2, *t3]) as u64 {
+            0 => None,
+            ms if (MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS).contains(&ms) => Some(ms),
+            ms => bail!(
+                "Tick interval of {} ms outside {}-{} ms",
+                ms,
+                MIN_TICK_INTERVAL_MS,
+                MAX_TICK_INTERVAL_MS
+            ),
+        };
+        let Some(mode) = ActivePattern::from_id(*mode) else {
+            bail!("Unknown mode {}", mode);
+        };
+        let password = std::str::from_utf8(password)?;
+
+        Ok(Self {
+            id,
+            canvas_width,
+            canvas_height,
+            tick_interval_ms,
+            autoplay: flags & 1 != 0,
+            mode,
+            password: (!password.is_empty()).then(|| password.to_string()),
+        })
+    }
+
+    fn settings(&self, defaults: &RoomSettings) -> RoomSettings {
+        RoomSettings {
+            canvas_width: self.canvas_width.unwrap_or(defaults.canvas_width),
+            canvas_height: self.canvas_height.unwrap_or(defaults.canvas_height),
+            tick_interval_ms: self.tick_interval_ms.unwrap_or(defaults.tick_interval_ms),
+            autoplay: self.autoplay,
+            ..defaults.clone()
+        }
+    }
+}
+
+/// Every room of the server by id, and the settings new ones start with
+pub struct RoomRegistry {
+    rooms: RwLock<HashMap<RoomId, Arc<RoomState>>>,
+    settings: RoomSettings,
+    // Stops the simulation loops of the rooms
+    shutdown: CancellationToken,
+    // When each room was first seen without connections, cleared once someone is back
+    empty_since: Mutex<HashMap<RoomId, Instant>>,
+}
+
+impl RoomRegistry {
+    pub fn new(settings: RoomSettings, shutdown: CancellationToken) -> Self {
+        Self {
+            rooms: RwLock::new(HashMap::new()),
+            settings,
+            shutdown,
+            empty_since: Mutex::default(),
+        }
+    }
+
+    /// Returns the room with the given id if the credentials admit the client, creating it on
+    /// first join. A password given when creating a room makes it private.
+    pub fn join(
+        &self,
+        id: &str,
+        credentials: &JoinCredentials,
+    ) -> Result<Arc<RoomState>, RoomError> {
+        validate_room_id(id)?;
+
+        let existing = self
+            .rooms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned();
+        let room = match existing {
+            Some(room) => room,
+            None => {
+                let mut rooms = self.rooms.write().unwrap_or_else(|e| e.into_inner());
+                match rooms.get(id) {
+                    Some(room) => room.clone(),
+                    None => {
+                        let access = RoomAccess::with_password(credentials.password.clone());
+                        return self.insert(&mut rooms, id, &self.settings, access);
+                    }
+                }
+            }
+        };
+
+        if room.access.admit(credentials) {
+            Ok(room)
+        } else {
+            Err(RoomError::AccessDenied(room.id.clone()))
+        }
+    }
+
+    /// Creates a room set up by `params`, failing if the id is taken
+    pub fn create(&self, params: &RoomParams) -> Result<Arc<RoomState>, RoomError> {
+        validate_room_id(&params.id)?;
+
+        let mut rooms = self.rooms.write().unwrap_or_else(|e| e.into_inner());
+        if rooms.contains_key(&params.id) {
+            return Err(RoomError::AlreadyExists(params.id.clone()));
+        }
+        let settings = params.settings(&self.settings);
+        let (width, height) = (settings.canvas_width, settings.canvas_height);
+        if width.max(height) > max_canvas_side(settings.gol_engine) {
+            return Err(RoomError::CanvasTooLarge(
+                width,
+                height,
+                settings.gol_engine,
+            ));
+        }
+        let access = RoomAccess::with_password(params.password.clone());
+        let room = self.insert(&mut rooms, &params.id, &settings, access)?;
+        room.set_active_pattern(params.mode);
+        info!(
+            "Created room {:?} from the lobby, {}x{} cells showing {}",
+            room.id,
+            room.gol.dimensions().0,
+            room.gol.dimensions().1,
+            params.mode.name()
+        );
+        Ok(room)
+    }
+
+    fn insert(
+        &self,
+        rooms: &mut HashMap<RoomId, Arc<RoomState>>,
+        id: &str,
+        settings: &RoomSettings,
+        access: RoomAccess,
+    ) -> Result<Arc<RoomState>, RoomError> {
+        if rooms.len() >= MAX_ROOMS {
+            return Err(RoomError::TooManyRooms(rooms.len()));
+        }
+
+        let room = RoomState::new(id.to_string(), settings, access);
+        if SCHEDULER_RUN {
+            room.health.beat();
+            spawn_simulation_loop(
+                Arc::downgrade(&room),
+                room.health.epoch(),
+                self.shutdown.clone(),
+            );
+        }
+        rooms.insert(id.to_string(), room.clone());
+        Ok(room)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<RoomState>> {
+        self.rooms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    pub fn all(&self) -> Vec<Arc<RoomState>> {
+        self.rooms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the rooms that have been empty for at least `after`, returning their ids. The
+    /// default room is kept. Dropping a room stops its simulation loop.
+    pub fn evict_empty(&self, after: Duration) -> Vec<RoomId> {
+        let now = Instant::now();
+        let mut rooms = self.rooms.write().unwrap_or_else(|e| e.into_inner());
+        let mut empty_since = self.empty_since.lock().unwrap_or_else(|e| e.into_inner());
+        // Connections and requests hold the room while they use it, only the registry holds
+        // an empty one
+        let is_empty = |room: &Arc<RoomState>| {
+            Arc::strong_count(room) == 1 && room.channel.receiver_count() == 0
+        };
+        empty_since.retain(|id, _| rooms.get(id).is_some_and(is_empty));
+
+        let mut evicted = Vec::new();
+        for (id, room) in rooms.iter() {
+            if id == DEFAULT_ROOM || !is_empty(room) {
+                continue;
+            }
+            let since = *empty_since.entry(id.clone()).or_insert(now);
+            if now.duration_since(since) >= after {
+                evicted.push(id.clone());
+            }
+        }
+        for id in &evicted {
+            rooms.remove(id);
+            empty_since.remove(id);
+            info!("Removed room {:?}, empty for {:?}", id, after);
+        }
+        evicted
+    }
+
+    /// The public rooms by id, for the lobby
+    pub fn summaries(&self) -> Vec<RoomSummary> {
+        let mut summaries: Vec<RoomSummary> = self
+            .all()
+            .iter()
+            .filter(|room| !room.access.is_private())
+            .map(|room| RoomSummary::of(room))
+            .collect();
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+        summaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::ClientMessage, constants::MAX_CANVAS_SIDE};
+    use game_of_life_core::EngineKind;
+
+    fn registry() -> RoomRegistry {
+        let settings = RoomSettings {
+            canvas_width: 16,
+            canvas_height: 16,
+            ..RoomSettings::default()
+        };
+        RoomRegistry::new(settings, CancellationToken::new())
+    }
+
+    fn arena() -> RoomParams {
+        RoomParams {
+            id: "arena".to_string(),
+            canvas_width: Some(40),
+            canvas_height: None,
+            tick_interval_ms: Some(50),
+            autoplay: true,
+            mode: ActivePattern::Sand,
+            password: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn lobby_lists_created_public_rooms() {
+        let registry = registry();
+        registry.create(&arena()).unwrap();
+        let vault = RoomParams {
+            id: "vault".to_string(),
+            password: Some("secret".to_string()),
+            ..arena()
+        };
+        registry.create(&vault).unwrap();
+        registry.join("hall", &JoinCredentials::default()).unwrap();
+
+        let summaries = registry.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(
+            summaries[0],
+            RoomSummary {
+                id: "arena".to_string(),
+                mode: ActivePattern::Sand,
+                clients: 0,
+                width: 40,
+                height: 16,
+            }
+        );
+        assert_eq!(summaries[1].id, "hall");
+    }
+
+    #[test]
+    fn create_room_payloads_decode_or_fail() {
+        let payload = ClientMessage::create_room(&arena()).payload;
+        assert_eq!(RoomParams::decode(&payload).unwrap(), arena());
+
+        let vault = RoomParams {
+            password: Some("secret".to_string()),
+            ..arena()
+        };
+        let decoded = RoomParams::decode(&ClientMessage::create_room(&vault).payload);
+        assert_eq!(decoded.unwrap(), vault);
+
+        assert!(RoomParams::decode(&[]).is_err());
+        assert!(RoomParams::decode(&payload[..8]).is_err());
+        // An id longer than the payload
+        assert!(RoomParams::decode(&[200, b'a']).is_err());
+
+        let too_fast = RoomParams {
+            tick_interval_ms: Some(MIN_TICK_INTERVAL_MS - 1),
+            ..arena()
+        };
+        assert!(RoomParams::decode(&ClientMessage::create_room(&too_fast).payload).is_err());
+        let too_wide = RoomParams {
+            canvas_width: Some(MAX_SPARSE_CANVAS_SIDE + 1),
+            ..arena()
+        };
+        assert!(RoomParams::decode(&ClientMessage::create_room(&too_wide).payload).is_err());
+
+        let mut unknown_mode = payload.clone();
+        let mode = 1 + "arena".len() + 9;
+        unknown_mode[mode] = u8::MAX;
+        assert!(RoomParams::decode(&unknown_mode).is_err());
+        let mut bad_id = payload;
+        bad_id[1] = 0xff;
+        assert!(RoomParams::decode(&bad_id).is_err());
+    }
+
+    #[tokio::test]
+    async fn creating_a_taken_room_fails() {
+        let registry = registry();
+        registry.create(&arena()).unwrap();
+        assert!(matches!(
+            registry.create(&arena()),
+            Err(RoomError::AlreadyExists(id)) if id == "arena"
+        ));
+
+        registry.join("hall", &JoinCredentials::default()).unwrap();
+        let hall = RoomParams {
+            id: "hall".to_string(),
+            ..arena()
+        };
+        assert!(matches!(
+            registry.create(&hall),
+            Err(RoomError::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_sparse_rooms_grow_past_the_dense_canvas_side() {
+        let wide = RoomParams {
+            canvas_width: Some(MAX_CANVAS_SIDE + 1),
+            ..arena()
+        };
+        assert!(matches!(
+            registry().create(&wide),
+            Err(RoomError::CanvasTooLarge(_, _, EngineKind::Vecs))
+        ));
+
+        let sparse = RoomRegistry::new(
+            RoomSettings {
+                canvas_height: 16,
+                gol_engine: EngineKind::Sparse,
+                ..RoomSettings::default()
+            },
+            CancellationToken::new(),
+        );
+        let room = sparse.create(&wide).unwrap();
+        assert_eq!(room.gol.dimensions(), (MAX_CANVAS_SIDE + 1, 16));
+        // The board stays too large for a dense engine
+        assert!(!room.gol.select_engine(EngineKind::Vecs));
+        assert_eq!(room.gol.engine(), EngineKind::Sparse);
+    }
+
+    #[tokio::test]
+    async fn creating_past_the_room_limit_fails() {
+        let registry = registry();
+        for room in 0..MAX_ROOMS {
+            registry
+                .join(&format!("room-{}", room), &JoinCredentials::default())
+                .unwrap();
+        }
+        assert!(matches!(
+            registry.create(&arena()),
+            Err(RoomError::TooManyRooms(MAX_ROOMS))
+        ));
+        assert!(registry.get("arena").is_none());
+    }
+
+    #[tokio::test]
+    async fn empty_rooms_are_evicted() {
+        let registry = registry();
+        registry
+            .join(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        registry.create(&arena()).unwrap();
+        let busy = registry.join("busy", &JoinCredentials::default()).unwrap();
+        let watched = registry
+            .join("watched", &JoinCredentials::default())
+            .unwrap();
+        let _receiver = watched.channel.subscribe();
+        drop(watched);
+
+        assert!(registry.evict_empty(Duration::from_secs(60)).is_empty());
+        assert_eq!(
+            registry.evict_empty(Duration::ZERO),
+            vec!["arena".to_string()]
+        );
+        assert!(registry.get("arena").is_none());
+        assert!(registry.get(DEFAULT_ROOM).is_some());
+
+        // Empty from when the last client left, not from when the room was created
+        drop(busy);
+        assert!(registry.evict_empty(Duration::from_secs(60)).is_empty());
+        assert!(registry.get("busy").is_some());
+        assert_eq!(registry.all().len(), 3);
+    }
+}
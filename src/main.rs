@@ -1,121 +1,71 @@
-mod constants;
-mod message;
-mod patterns;
-mod payload;
-mod protocol;
-mod socket;
-mod state;
-mod utils;
-
-use axum::extract::State;
-use axum::response::IntoResponse;
-use axum::{Router, routing::get};
-use axum_tws::WebSocketUpgrade;
-use chrono::{Duration, Utc};
-use std::net::SocketAddr;
+use clap::Parser;
 use std::sync::Arc;
-use std::thread;
-use tracing::{debug, error, info, trace, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::patterns::gol::advance_generation;
-use crate::socket::handle_socket;
-use crate::state::AppState;
-
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    info!("New WebSocket connection attempt");
-    ws.on_upgrade(|socket| handle_socket(socket, state))
-}
-
-const SCHEDULER_RUN: bool = false;
+use gol_htmx_rust::budget::spawn_budget_balancer;
+use gol_htmx_rust::config::{Cli, Command, LogFormat, ServerConfig};
+use gol_htmx_rust::scheduler::spawn_scheduler;
+use gol_htmx_rust::server::router;
+use gol_htmx_rust::state::AppState;
+use gol_htmx_rust::stats::spawn_stats_refresher;
+use gol_htmx_rust::watchdog::spawn_watchdog;
+use gol_htmx_rust::{proxy, registry};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let config = ServerConfig::load(&cli)?;
+
     // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or("info,websocket_server=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let registry = tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::try_new(config.log_filter())?);
+    match config.log_format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        // Events carry the fields of every span they're in, like connection_id and room
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().json().flatten_event(true))
+            .init(),
+    }
+
+    if let Some(Command::Proxy(args)) = cli.command {
+        proxy::run(args.into()).await?;
+        return Ok(());
+    }
 
     info!("Starting WebSocket server");
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+    registry::validate_message_ranges(registry::MESSAGE_RANGES)?;
+
+    let addr = config.addr;
     let listener = tokio::net::TcpListener::bind(&addr).await.map_err(|e| {
         error!("Failed to bind to address {}: {}", addr, e);
         e
     })?;
 
-    let app_state = Arc::new(AppState::new(100));
+    if config.admin_token.is_none() {
+        warn!("No admin token set, every client may send privileged messages");
+    }
+    let app_state = Arc::new(AppState::new(
+        config.room,
+        config.admin_token,
+        config.max_connections,
+    ));
     info!("Application state initialized");
 
-    let channel = app_state.channel.clone();
-
-    let app = Router::new()
-        .route("/ws", get(ws_handler))
-        .with_state(app_state)
-        .fallback_service(axum_static::static_router("static"));
+    spawn_stats_refresher(app_state.clone());
+    spawn_watchdog(app_state.clone());
+    spawn_budget_balancer(app_state.clone());
+    spawn_scheduler(app_state.clone());
+    let shutdown = app_state.shutdown.clone();
 
-    if SCHEDULER_RUN {
-        // Spawn background task for periodic message generation
-        thread::spawn(move || {
-            info!("Starting periodic message broadcaster");
-            let mut target_dt = Utc::now();
-            let mut consecutive_errors = 0;
-            const MAX_CONSECUTIVE_ERRORS: u32 = 10;
-
-            loop {
-                target_dt = target_dt
-                    .checked_add_signed(Duration::milliseconds(100))
-                    .unwrap();
-
-                let diff = match (target_dt - Utc::now()).to_std() {
-                    Ok(duration) => duration,
-                    Err(e) => {
-                        warn!("Time calculation error: {}, using 100ms default", e);
-                        std::time::Duration::from_millis(100)
-                    }
-                };
-
-                thread::sleep(diff);
-
-                if channel.receiver_count() > 0 {
-                    match channel.send(advance_generation()) {
-                        Ok(_) => {
-                            consecutive_errors = 0;
-                            debug!(
-                                "Broadcasted message to {} receivers",
-                                channel.receiver_count()
-                            );
-                        }
-                        Err(e) => {
-                            consecutive_errors += 1;
-                            error!(
-                                "Failed to broadcast message (attempt {}): {}",
-                                consecutive_errors, e
-                            );
-
-                            if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
-                                error!(
-                                    "Too many consecutive broadcast errors, shutting down broadcaster"
-                                );
-                                break;
-                            }
-                        }
-                    }
-                } else {
-                    trace!("No active receivers, skipping broadcast");
-                }
-            }
-
-            warn!("Periodic message broadcaster shutting down");
-        });
-    }
+    let app = router(app_state, config.static_dir);
 
     info!("Server running at {}", addr);
-    let server_result = axum::serve(listener, app).await;
+    let server_result = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(shutdown))
+        .await;
 
     // Cleanup
     warn!("Server shutting down");
@@ -125,3 +75,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         e.into()
     })
 }
+
+// Resolves on Ctrl+C, cancelling `shutdown` so background tasks stop with the server
+async fn shutdown_signal(shutdown: CancellationToken) {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Failed to listen for Ctrl+C: {}", e);
+        return std::future::pending().await;
+    }
+    info!("Received Ctrl+C, shutting down");
+    shutdown.cancel();
+}
@@ -1,18 +1,53 @@
-use anyhow::{Context, Result};
-use axum_tws::{Message, WebSocket};
+use anyhow::{Result, anyhow, bail};
+use axum_tws::{CloseCode, Message, WebSocket};
+use bytes::Bytes;
 use futures::{
     SinkExt, StreamExt,
     stream::{SplitSink, SplitStream},
 };
-use std::sync::Arc;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, Instant};
-use tokio::sync::broadcast;
-use tracing::{debug, error, info, instrument, warn};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::MissedTickBehavior;
+use tracing::{Instrument, debug, error, info, instrument, trace, warn};
 
 use crate::{
-    constants::message_types, patterns::gol::current_generation, payload::WsPayload,
-    protocol::decode_ws_message, state::AppState,
+    admin::{AdminAccess, is_privileged},
+    constants::{
+        CURSOR_MIN_INTERVAL_MS, DIRECT_CHANNEL_CAPACITY, ERROR_FLUSH_TIMEOUT_MS,
+        KEEPALIVE_INTERVAL_MS, KEEPALIVE_MAX_MISSED, MAX_CHAT_LENGTH, MAX_REASSEMBLED_PAYLOAD,
+        error_codes, message_types, topics,
+    },
+    fanout::{Received, RoomSubscription, SubscriptionError},
+    handshake::ClientHello,
+    i18n::{Notice, TextPreferences},
+    lobby::{RoomParams, RoomRegistry},
+    modes::WrongMode,
+    payload::{ReplyRoute, WsPayload, is_handled, reply_route},
+    presence::ConnectionRegistry,
+    protocol::{
+        CellPayload, FRAGMENT_FLAGS, ProtocolError, Reassembly, SUPPORTED_CODECS,
+        decode_ws_message, encode_ws_message,
+    },
+    room::{BroadcastMessage, RoomChannel, RoomError, RoomState},
+    scheduler::{ScheduleEntry, Scheduler},
+    state::{ActivePattern, AppState},
+    stats::ServerStats,
+    text_protocol::{decode_json_message, encode_json_message},
+    utils::{
+        FrameError, create_admin_status_message, create_capabilities_message, create_chat_message,
+        create_client_identity_message, create_client_joined_message, create_client_left_message,
+        create_cursor_moved_message, create_error_message, create_handshake_message,
+        create_invalid_text_error, create_join_summary_message, create_mode_changed_message,
+        create_prediction_params_message, create_presence_list_message, create_room_list_message,
+        create_schedule_list_message, create_server_stats_message, create_team_assigned_message,
+        create_tiles_message,
+    },
+    viewport::{TileCache, Viewport},
 };
+use game_of_life_core::color::hue_rgb;
 
 /// Custom error types for better error handling
 #[derive(Debug, thiserror::Error)]
@@ -24,69 +59,262 @@ pub enum SocketError {
     #[error("Protocol decode error: {0}")]
     DecodeError(#[from] anyhow::Error),
     #[error("Broadcast channel error: {0}")]
-    BroadcastError(#[from] broadcast::error::SendError<Message>),
+    BroadcastError(#[from] broadcast::error::SendError<BroadcastMessage>),
     #[error("Connection timeout after {duration:?}")]
     Timeout { duration: Duration },
     #[error("Connection closed by client")]
     ConnectionClosed,
+    #[error("Unknown message type: {0}")]
+    UnknownMessageType(u8),
+    #[error("Message type {0} needs admin rights")]
+    Unauthorized(u8),
+    #[error("Admin token rejected")]
+    AuthFailed,
+    #[error("Too slow to keep up with the room, {backlog} messages behind")]
+    SlowConsumer { backlog: usize },
+    #[error("Handling message type {0} panicked")]
+    HandlerFailed(u8),
+    #[error("Frame encode error: {0}")]
+    FrameError(#[from] FrameError),
+    #[error("{0}, switch modes first")]
+    WrongMode(#[from] WrongMode),
+    #[error("Room not created: {0}")]
+    RoomNotCreated(#[from] RoomError),
+}
+
+impl SocketError {
+    /// Code the error is reported to the client with, see `error_codes`
+    pub fn error_code(&self) -> u16 {
+        match self {
+            SocketError::SendError(_) => error_codes::SEND_FAILED,
+            SocketError::ReceiveError(_) => error_codes::RECEIVE_FAILED,
+            SocketError::DecodeError(e) => match e.downcast_ref::<ProtocolError>() {
+                Some(ProtocolError::UnsupportedVersion { .. }) => error_codes::UNSUPPORTED_VERSION,
+                Some(
+                    ProtocolError::MissingChecksum { .. } | ProtocolError::ChecksumMismatch { .. },
+                ) => error_codes::CHECKSUM_MISMATCH,
+                _ => error_codes::MALFORMED_MESSAGE,
+            },
+            SocketError::BroadcastError(_) => error_codes::BROADCAST_FAILED,
+            SocketError::Timeout { .. } => error_codes::TIMEOUT,
+            SocketError::ConnectionClosed => error_codes::CONNECTION_CLOSED,
+            SocketError::UnknownMessageType(_) => error_codes::UNKNOWN_MESSAGE_TYPE,
+            SocketError::Unauthorized(_) | SocketError::AuthFailed => error_codes::UNAUTHORIZED,
+            SocketError::SlowConsumer { .. } => error_codes::SLOW_CONSUMER,
+            SocketError::HandlerFailed(_) => error_codes::HANDLER_FAILED,
+            SocketError::FrameError(_) => error_codes::FRAME_ENCODE_FAILED,
+            SocketError::WrongMode(_) => error_codes::WRONG_MODE,
+            SocketError::RoomNotCreated(_) => error_codes::ROOM_NOT_CREATED,
+        }
+    }
+
+    /// Whether the connection keeps going once the client has been told
+    fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            SocketError::DecodeError(_)
+                | SocketError::UnknownMessageType(_)
+                | SocketError::Unauthorized(_)
+                | SocketError::HandlerFailed(_)
+                | SocketError::FrameError(_)
+                | SocketError::WrongMode(_)
+                | SocketError::RoomNotCreated(_)
+        )
+    }
 }
 
-#[derive(Debug)]
 pub struct SocketHandler {
-    state: Arc<AppState>,
+    room: Arc<RoomState>,
     connection_id: String,
+    connections: Arc<ConnectionRegistry>,
+    admin_access: Arc<AdminAccess>,
+    server_stats: Arc<ServerStats>,
+    scheduler: Arc<Scheduler>,
+    rooms: Arc<RoomRegistry>,
+    // Whether the connection joined with the admin token
+    admin: bool,
 }
 
 impl SocketHandler {
-    pub fn new(state: Arc<AppState>, connection_id: String) -> Self {
+    /// Handler of a connection to `room`, sharing the server-wide parts of `state`
+    pub fn new(room: Arc<RoomState>, connection_id: String, state: &AppState, admin: bool) -> Self {
         Self {
-            state,
+            room,
             connection_id,
+            connections: state.connections.clone(),
+            admin_access: state.admin.clone(),
+            server_stats: state.server_stats.clone(),
+            scheduler: state.scheduler.clone(),
+            rooms: state.rooms.clone(),
+            admin,
         }
     }
 
-    #[instrument(skip(self, sink), fields(connection_id = %self.connection_id, start_time))]
-    pub async fn send_current_generation(
+    /// Tells the client the id the room knows it by, e.g. in presence messages
+    pub async fn send_identity(
+        &self,
+        sink: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        let message = create_client_identity_message(&self.connection_id);
+        sink.send(message).await.map_err(|e| {
+            SocketError::SendError(format!(
+                "Failed to send identity: connection_id: {},  {}",
+                self.connection_id, e
+            ))
+        })
+    }
+
+    /// Handshake: tells the client which message type ranges the server speaks
+    #[instrument(skip(self, sink), fields(connection_id = %self.connection_id))]
+    pub async fn send_capabilities(
+        &self,
+        sink: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        sink.send(create_capabilities_message()).await.map_err(|e| {
+            SocketError::SendError(format!(
+                "Failed to send capabilities: connection_id: {},  {}",
+                self.connection_id, e
+            ))
+        })
+    }
+
+    /// Rule, grid size, edge mode and hash algorithm for clients predicting generations locally
+    pub async fn send_prediction_params(
         &self,
         sink: &mut SplitSink<WebSocket, Message>,
     ) -> Result<(), SocketError> {
-        sink.send(current_generation()).await.map_err(|e| {
+        let message =
+            create_prediction_params_message(self.room.seed(), self.room.gol.dimensions());
+        sink.send(message).await.map_err(|e| {
             SocketError::SendError(format!(
-                "Failed to send current generation: connection_id: {},  {}",
+                "Failed to send prediction params: connection_id: {},  {}",
                 self.connection_id, e
             ))
+        })
+    }
+
+    /// Seed, rule, generation, recent population curve and keyframe, so a client joining
+    /// mid-run has context before the next frame arrives
+    pub async fn send_join_summary(
+        &self,
+        sink: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        let message = create_join_summary_message(self.room.seed(), &self.room.gol.join_summary());
+        sink.send(message).await.map_err(|e| {
+            SocketError::SendError(format!(
+                "Failed to send join summary: connection_id: {},  {}",
+                self.connection_id, e
+            ))
+        })
+    }
+
+    /// Puts the connection on an Immigration Game team and tells it which
+    pub async fn send_team_assignment(
+        &self,
+        sink: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        let team = self.room.immigration.assign_team(&self.connection_id);
+        let message = create_team_assigned_message(team.id(), team.rgb());
+        sink.send(message).await.map_err(|e| {
+            SocketError::SendError(format!(
+                "Failed to send team assignment: connection_id: {},  {}",
+                self.connection_id, e
+            ))
+        })
+    }
+
+    /// Which pattern the room shows and a full frame of it, so the canvas isn't blank or
+    /// stale until the pattern next draws all of it
+    #[instrument(skip(self, sink), fields(connection_id = %self.connection_id, start_time))]
+    pub async fn send_keyframe(
+        &self,
+        sink: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        let pattern = self.room.active_pattern();
+        let keyframe = self.room.keyframe()?;
+        sink.send(create_mode_changed_message(pattern))
+            .await
+            .map_err(|e| {
+                SocketError::SendError(format!(
+                    "Failed to send mode: connection_id: {},  {}",
+                    self.connection_id, e
+                ))
+            })?;
+        sink.send(keyframe).await.map_err(|e| {
+            SocketError::SendError(format!(
+                "Failed to send {} keyframe: connection_id: {},  {}",
+                pattern.name(),
+                self.connection_id,
+                e
+            ))
         })?;
 
         debug!(
-            "Successfully sent current generation to client: connection_id: {}",
+            "Successfully sent {} keyframe to client: connection_id: {}",
+            pattern.name(),
             self.connection_id
         );
 
         Ok(())
     }
 
-    #[instrument(skip(self, stream, sink), fields(connection_id = %self.connection_id))]
+    #[instrument(skip(self, stream, sink), fields(connection_id = %self.connection_id, room = %self.room.id))]
     pub async fn run(self, stream: SplitStream<WebSocket>, sink: SplitSink<WebSocket, Message>) {
-        let channel = self.state.channel.clone();
-        let channel_rx = channel.subscribe();
+        let channel = self.room.channel.clone();
+        let subscription = RoomSubscription::new(
+            &channel,
+            self.room.channel_capacity,
+            Some(self.connection_id.clone()),
+        );
+
+        let shared = Arc::new(ConnectionShared::default());
+        shared.admin.store(self.admin, Ordering::Relaxed);
+        // Messages for this connection only, e.g. errors it caused
+        let (direct_tx, direct_rx) = mpsc::channel(DIRECT_CHANNEL_CAPACITY);
+
+        // Announced once subscribed, so the list and the joins and leaves after it line up
+        let (presence, kicked) = self
+            .connections
+            .register(&self.connection_id, &self.room.id);
+        let members = self.connections.room_members(&self.room.id);
+        let _ = direct_tx.try_send(BroadcastMessage::system(create_presence_list_message(
+            &members,
+        )));
+        let _ = direct_tx.try_send(BroadcastMessage::system(create_admin_status_message(
+            self.admin,
+        )));
+        let _ = channel.send(BroadcastMessage::system(create_client_joined_message(
+            &presence,
+        )));
 
         info!("Starting WebSocket message handlers");
 
         // Spawn receiver task (from channel to socket)
-        let recv_handler = ChannelReceiver::new(self.connection_id.clone());
-        let mut recv_task = tokio::spawn(async move {
-            if let Err(e) = recv_handler.run(channel_rx, sink).await {
-                error!("Channel receiver error: {}", e);
+        let recv_handler = ChannelReceiver::new(
+            self.connection_id.clone(),
+            self.room.clone(),
+            shared.clone(),
+            self.server_stats.clone(),
+        );
+        let mut recv_task = tokio::spawn(
+            async move {
+                if let Err(e) = recv_handler.run(subscription, direct_rx, sink).await {
+                    error!("Channel receiver error: {}", e);
+                }
             }
-        });
+            .in_current_span(),
+        );
 
         // Spawn sender task (from socket to channel)
-        let send_handler = ChannelSender::new(self.connection_id.clone());
-        let mut send_task = tokio::spawn(async move {
-            if let Err(e) = send_handler.run(stream, channel).await {
-                error!("Socket sender error: {}", e);
+        let send_handler = ChannelSender::new(&self, shared, direct_tx.clone());
+        let mut send_task = tokio::spawn(
+            async move {
+                if let Err(e) = send_handler.run(stream, channel).await {
+                    error!("Socket sender error: {}", e);
+                }
             }
-        });
+            .in_current_span(),
+        );
 
         // Wait for either task to complete and cleanup
         tokio::select! {
@@ -102,69 +330,406 @@ impl SocketHandler {
                     Ok(_) => debug!("Socket sender task completed normally"),
                     Err(e) => error!("Socket sender task panicked: {}", e),
                 }
-                recv_task.abort();
+                // Lets the receiver deliver errors queued for this client before closing
+                drop(direct_tx);
+                let flush_timeout = Duration::from_millis(ERROR_FLUSH_TIMEOUT_MS);
+                if tokio::time::timeout(flush_timeout, &mut recv_task).await.is_err() {
+                    recv_task.abort();
+                }
+            }
+            _ = kicked.notified() => {
+                info!("Connection kicked by an admin");
+                send_task.abort();
+                let message = create_error_message(error_codes::KICKED, "Kicked by an admin");
+                let _ = direct_tx.try_send(BroadcastMessage::system(message));
+                drop(direct_tx);
+                let flush_timeout = Duration::from_millis(ERROR_FLUSH_TIMEOUT_MS);
+                if tokio::time::timeout(flush_timeout, &mut recv_task).await.is_err() {
+                    recv_task.abort();
+                }
             }
         }
 
+        self.room.occupancy.forget(&self.connection_id);
+        self.room.pong.release(&self.connection_id);
+        self.room.snake.leave(&self.connection_id);
+        self.room.immigration.forget(&self.connection_id);
+        if self.connections.unregister(&self.connection_id) {
+            let left = create_client_left_message(&self.connection_id);
+            let _ = self.room.channel.send(BroadcastMessage::system(left));
+        }
         info!("WebSocket handler tasks terminated");
     }
 }
 
+/// What the client set up for its connection, shared by both halves
+struct ConnectionShared {
+    // Topics this connection wants. New connections get everything.
+    subscriptions: AtomicU8,
+    // How server notices are worded for this connection
+    preferences: RwLock<TextPreferences>,
+    // Protocol version agreed in the HELLO handshake, 0 until then
+    version: AtomicU8,
+    // Compression the client decodes, advertised in its HELLO. None until then.
+    codecs: AtomicU8,
+    // Pings sent since the client last sent anything
+    unanswered_pings: AtomicU8,
+    // Whether the client may send privileged messages, see `is_privileged`
+    admin: AtomicBool,
+    // Whether the client speaks JSON text frames, set by the first one it sends
+    json: AtomicBool,
+    // Part of the GOL board the client watches, None for the whole board
+    viewport: RwLock<Option<Viewport>>,
+    // Viewport tiles the client has, when it takes tiles
+    tiles: Mutex<TileCache>,
+}
+
+impl Default for ConnectionShared {
+    fn default() -> Self {
+        Self {
+            subscriptions: AtomicU8::new(topics::ALL),
+            preferences: RwLock::default(),
+            version: AtomicU8::new(0),
+            codecs: AtomicU8::new(0),
+            unanswered_pings: AtomicU8::new(0),
+            admin: AtomicBool::new(false),
+            json: AtomicBool::new(false),
+            viewport: RwLock::new(None),
+            tiles: Mutex::default(),
+        }
+    }
+}
+
+impl ConnectionShared {
+    /// Counts a ping about to be sent, failing once the client has left
+    /// `KEEPALIVE_MAX_MISSED` pings in a row unanswered
+    fn count_ping(&self) -> Result<(), SocketError> {
+        let missed = self.unanswered_pings.fetch_add(1, Ordering::Relaxed);
+        if missed >= KEEPALIVE_MAX_MISSED {
+            warn!("Client left {} pings unanswered, dropping it", missed);
+            return Err(SocketError::Timeout {
+                duration: Duration::from_millis(KEEPALIVE_INTERVAL_MS * missed as u64),
+            });
+        }
+        Ok(())
+    }
+
+    /// Anything the client sends answers the pings so far
+    fn heard_from_client(&self) {
+        self.unanswered_pings.store(0, Ordering::Relaxed);
+    }
+
+    fn preferences(&self) -> RwLockReadGuard<'_, TextPreferences> {
+        self.preferences.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn preferences_mut(&self) -> RwLockWriteGuard<'_, TextPreferences> {
+        self.preferences.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn viewport(&self) -> Option<Viewport> {
+        *self.viewport.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn viewport_mut(&self) -> RwLockWriteGuard<'_, Option<Viewport>> {
+        self.viewport.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// What the client's viewport shows now: a frame, or the tiles it doesn't have yet
+    fn render_viewport(
+        &self,
+        room: &RoomState,
+        viewport: &Viewport,
+    ) -> Result<Message, FrameError> {
+        if !viewport.tiles {
+            return room.gol.viewport_frame(viewport);
+        }
+        let tiles = room.gol.viewport_tiles(viewport);
+        let mut cache = self.tiles.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(create_tiles_message(&cache.unsent(viewport.zoom, tiles)))
+    }
+
+    /// Full frame of the active pattern, or the client's viewport while it watches GOL
+    fn keyframe(&self, room: &RoomState) -> Result<Message, FrameError> {
+        let viewport = self.viewport();
+        match viewport {
+            Some(viewport) if room.active_pattern() == ActivePattern::Gol => {
+                self.render_viewport(room, &viewport)
+            }
+            _ => room.keyframe(),
+        }
+    }
+
+    /// Keyframe for a client that missed messages, which may have been tiles it was sent
+    fn resync_keyframe(&self, room: &RoomState) -> Result<Message, FrameError> {
+        self.tiles.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        self.keyframe(room)
+    }
+}
+
 /// Handles receiving messages from the broadcast channel and sending to socket
 struct ChannelReceiver {
     connection_id: String,
+    room: Arc<RoomState>,
+    shared: Arc<ConnectionShared>,
+    server_stats: Arc<ServerStats>,
     message_count: u64,
+    // Moving average of how long a send to the socket takes
+    send_duration: Duration,
+    // Newest frame of the active pattern, held back while newer messages are queued
+    held_frame: Option<BroadcastMessage>,
+    // Frames dropped since the client last got one, the next goes out as a keyframe
+    skipped_frames: u32,
+    // Topics and last numbers of the messages a resync keyframe covered, older queued
+    // messages of those topics are dropped
+    superseded: Vec<(u8, u32)>,
 }
 
 impl ChannelReceiver {
-    fn new(connection_id: String) -> Self {
+    fn new(
+        connection_id: String,
+        room: Arc<RoomState>,
+        shared: Arc<ConnectionShared>,
+        server_stats: Arc<ServerStats>,
+    ) -> Self {
         Self {
             connection_id,
+            room,
+            shared,
+            server_stats,
             message_count: 0,
+            send_duration: Duration::ZERO,
+            held_frame: None,
+            skipped_frames: 0,
+            superseded: Vec::new(),
+        }
+    }
+
+    /// Catches up a client that fell a whole channel behind with a keyframe, rather than
+    /// having it ask for one. What's still queued of the frames and pixel events it covers
+    /// is dropped.
+    async fn resync(
+        &mut self,
+        socket_sender: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        let channel = &self.room.channel;
+        self.superseded = [
+            self.room.active_pattern().frame_topic(),
+            topics::PIXEL_EVENTS,
+        ]
+        .into_iter()
+        .filter_map(|topic| Some((topic, channel.last_sequence(topic)?)))
+        .collect();
+        self.held_frame = None;
+        self.skipped_frames = 0;
+        let keyframe = self.shared.resync_keyframe(&self.room)?;
+        self.send(BroadcastMessage::system(keyframe), socket_sender)
+            .await
+    }
+
+    // Whether the last resync keyframe already covers the message
+    fn is_superseded(&mut self, broadcast: &BroadcastMessage) -> bool {
+        let Some(sequence) = broadcast.sequence() else {
+            return false;
+        };
+        let Some(i) = self
+            .superseded
+            .iter()
+            .position(|&(topic, _)| topic == broadcast.topic)
+        else {
+            return false;
+        };
+        // Numbers wrap around
+        if sequence.wrapping_sub(self.superseded[i].1) as i32 <= 0 {
+            return true;
         }
+        self.superseded.swap_remove(i);
+        false
     }
 
-    #[instrument(skip(self, channel_receiver, socket_sender), fields(connection_id = %self.connection_id))]
+    // Frames of the active pattern supersede each other, unlike pixel events
+    fn is_active_frame(&self, broadcast: &BroadcastMessage) -> bool {
+        broadcast.topic != topics::PIXEL_EVENTS
+            && broadcast.topic == self.room.active_pattern().frame_topic()
+    }
+
+    // Whether sends take longer than the room takes to make a new frame
+    fn is_lagging(&self) -> bool {
+        let tick = Duration::from_millis(self.room.simulation.applied_tick_interval_ms());
+        self.send_duration >= tick
+    }
+
+    /// The broadcast as this client gets it. GOL frames and pixel events become a render of
+    /// the client's viewport, if it set one, as pixel events are in board coordinates. A
+    /// frame following skipped ones becomes a keyframe.
+    fn prepare(&mut self, broadcast: BroadcastMessage) -> BroadcastMessage {
+        let is_frame = self.is_active_frame(&broadcast);
+        let viewport = self.shared.viewport();
+        if let Some(viewport) = viewport
+            && matches!(broadcast.topic, topics::GOL_FRAMES | topics::PIXEL_EVENTS)
+            && self.room.active_pattern() == ActivePattern::Gol
+        {
+            // Viewport renders show all the client is missing
+            if is_frame {
+                self.skipped_frames = 0;
+            }
+            return match self.shared.render_viewport(&self.room, &viewport) {
+                Ok(render) => broadcast.replaced_with(render),
+                Err(e) => {
+                    warn!(
+                        "Sending the whole board, the viewport failed to render: {}",
+                        e
+                    );
+                    broadcast
+                }
+            };
+        }
+        if is_frame && self.skipped_frames > 0 {
+            debug!("Skipped {} frames, sending a keyframe", self.skipped_frames);
+            self.skipped_frames = 0;
+            match self.room.keyframe() {
+                Ok(keyframe) => return broadcast.replaced_with(keyframe),
+                Err(e) => warn!("Sending the frame as is, no keyframe: {}", e),
+            }
+        }
+        broadcast
+    }
+
+    /// What goes out for a message taken off the channels with `queued` room messages
+    /// behind it. A client that can't keep up gets the newest frame rather than every one,
+    /// so it doesn't fall a whole channel behind.
+    fn pace(
+        &mut self,
+        broadcast: BroadcastMessage,
+        from_room: bool,
+        queued: usize,
+    ) -> impl Iterator<Item = BroadcastMessage> + use<> {
+        let is_frame = self.is_active_frame(&broadcast);
+        if from_room && is_frame && queued > 0 && self.is_lagging() {
+            if self.held_frame.replace(broadcast).is_some() {
+                self.skipped_frames += 1;
+            }
+            return [None, None].into_iter().flatten();
+        }
+        // Held frames still go out before anything sent after them
+        let held = self.held_frame.take().filter(|held| {
+            let replaced = is_frame && held.topic == broadcast.topic;
+            if replaced {
+                self.skipped_frames += 1;
+            }
+            !replaced
+        });
+        [held, Some(broadcast)].into_iter().flatten()
+    }
+
+    async fn send(
+        &mut self,
+        broadcast: BroadcastMessage,
+        socket_sender: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        self.message_count += 1;
+
+        let broadcast = self.prepare(broadcast);
+        let mut message = broadcast.render(
+            &self.shared.preferences(),
+            self.shared.codecs.load(Ordering::Relaxed),
+        );
+        if self.shared.json.load(Ordering::Relaxed) {
+            message = encode_json_message(&message);
+        }
+        let started = Instant::now();
+        if let Err(e) = socket_sender.send(message).await {
+            warn!("Failed to send message to client: {}", e);
+            return Err(SocketError::SendError(e.to_string()));
+        }
+        self.send_duration = (self.send_duration * 7 + started.elapsed()) / 8;
+        self.server_stats.record_frame_sent();
+        debug!("Sent message #{} to client", self.message_count);
+        Ok(())
+    }
+
+    /// Closes the socket, telling the client why it's dropped
+    async fn close(socket_sender: &mut SplitSink<WebSocket, Message>, error: &SocketError) {
+        let close = Message::close(Some(CloseCode::POLICY_VIOLATION), &error.to_string());
+        if let Err(e) = socket_sender.send(close).await {
+            debug!("Failed to send close frame: {}", e);
+        }
+    }
+
+    /// Pings the client, giving up on it once it has left `KEEPALIVE_MAX_MISSED` pings in a
+    /// row unanswered
+    async fn ping(
+        &self,
+        socket_sender: &mut SplitSink<WebSocket, Message>,
+    ) -> Result<(), SocketError> {
+        self.shared.count_ping()?;
+        socket_sender
+            .send(Message::ping(Vec::new()))
+            .await
+            .map_err(|e| SocketError::SendError(e.to_string()))
+    }
+
+    #[instrument(skip(self, subscription, direct_receiver, socket_sender), fields(connection_id = %self.connection_id))]
     async fn run(
         mut self,
-        mut channel_receiver: broadcast::Receiver<Message>,
+        mut subscription: RoomSubscription,
+        mut direct_receiver: mpsc::Receiver<BroadcastMessage>,
         mut socket_sender: SplitSink<WebSocket, Message>,
     ) -> Result<(), SocketError> {
         debug!("Channel receiver started");
-        let mut consecutive_errors = 0;
-        const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+        let mut keepalive = tokio::time::interval(Duration::from_millis(KEEPALIVE_INTERVAL_MS));
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick is immediate, the client has only just connected
+        keepalive.tick().await;
 
         loop {
-            match channel_receiver.recv().await {
-                Ok(msg) => {
-                    consecutive_errors = 0;
-                    self.message_count += 1;
-
-                    match socket_sender.send(msg).await {
-                        Ok(_) => {
-                            debug!("Sent message #{} to client", self.message_count);
+            // Only room messages can pile up, direct ones are bounded by their channel
+            let (broadcast, from_room) = tokio::select! {
+                biased;
+                direct = direct_receiver.recv() => match direct {
+                    Some(message) => (message, false),
+                    // The socket reader is gone and everything queued for this client is sent
+                    None => return Err(SocketError::ConnectionClosed),
+                },
+                received = subscription.recv(self.shared.subscriptions.load(Ordering::Relaxed)) => {
+                    match received {
+                        Ok(Received::Message(message)) => (message, true),
+                        Ok(Received::Lagged(skipped)) => {
+                            warn!("Channel receiver lagging, skipped {} messages", skipped);
+                            self.server_stats.record_lag(skipped);
+                            self.resync(&mut socket_sender).await?;
+                            continue;
+                        }
+                        Err(SubscriptionError::SlowConsumer { backlog }) => {
+                            let e = SocketError::SlowConsumer { backlog };
+                            warn!("Dropping slow client: {}", e);
+                            Self::close(&mut socket_sender, &e).await;
+                            return Err(e);
                         }
-                        Err(e) => {
-                            warn!("Failed to send message to client: {}", e);
-                            return Err(SocketError::SendError(e.to_string()));
+                        Err(SubscriptionError::Closed) => {
+                            info!("Broadcast channel closed, terminating receiver");
+                            return Err(SocketError::ConnectionClosed);
                         }
                     }
                 }
-                Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                    consecutive_errors += 1;
-                    warn!("Channel receiver lagging, skipped {} messages", skipped);
-
-                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
-                        return Err(SocketError::ReceiveError(format!(
-                            "Too many consecutive lag events: {}",
-                            consecutive_errors
-                        )));
+                // What was queued behind the held frame wasn't for this client after all
+                _ = std::future::ready(()), if self.held_frame.is_some() => {
+                    if let Some(held) = self.held_frame.take() {
+                        self.send(held, &mut socket_sender).await?;
                     }
+                    continue;
                 }
-                Err(broadcast::error::RecvError::Closed) => {
-                    info!("Broadcast channel closed, terminating receiver");
-                    return Err(SocketError::ConnectionClosed);
+                _ = keepalive.tick() => {
+                    self.ping(&mut socket_sender).await?;
+                    continue;
                 }
+            };
+            if from_room && self.is_superseded(&broadcast) {
+                continue;
+            }
+
+            for broadcast in self.pace(broadcast, from_room, subscription.queued()) {
+                self.send(broadcast, &mut socket_sender).await?;
             }
         }
     }
@@ -173,16 +738,42 @@ impl ChannelReceiver {
 /// Handles receiving messages from socket and sending to broadcast channel
 struct ChannelSender {
     connection_id: String,
+    room: Arc<RoomState>,
+    shared: Arc<ConnectionShared>,
+    connections: Arc<ConnectionRegistry>,
+    admin_access: Arc<AdminAccess>,
+    server_stats: Arc<ServerStats>,
+    scheduler: Arc<Scheduler>,
+    rooms: Arc<RoomRegistry>,
+    direct: mpsc::Sender<BroadcastMessage>,
     message_count: u64,
-    last_activity: Instant,
+    // Color other clients draw this connection's cursor in
+    cursor_rgb: [u8; 3],
+    last_cursor_move: Mutex<Option<Instant>>,
+    // Fragments of the message the client is sending in parts
+    reassembly: Mutex<Reassembly>,
 }
 
 impl ChannelSender {
-    fn new(connection_id: String) -> Self {
+    fn new(
+        handler: &SocketHandler,
+        shared: Arc<ConnectionShared>,
+        direct: mpsc::Sender<BroadcastMessage>,
+    ) -> Self {
         Self {
-            connection_id,
+            connection_id: handler.connection_id.clone(),
+            room: handler.room.clone(),
+            shared,
+            connections: handler.connections.clone(),
+            admin_access: handler.admin_access.clone(),
+            server_stats: handler.server_stats.clone(),
+            scheduler: handler.scheduler.clone(),
+            rooms: handler.rooms.clone(),
+            direct,
             message_count: 0,
-            last_activity: Instant::now(),
+            cursor_rgb: hue_rgb(rand::random_range(0..360)),
+            last_cursor_move: Mutex::new(None),
+            reassembly: Mutex::new(Reassembly::new(MAX_REASSEMBLED_PAYLOAD)),
         }
     }
 
@@ -190,38 +781,42 @@ impl ChannelSender {
     async fn run(
         mut self,
         mut socket_receiver: SplitStream<WebSocket>,
-        channel_sender: broadcast::Sender<Message>,
+        channel_sender: RoomChannel,
     ) -> Result<(), SocketError> {
         debug!("Socket sender started");
-        const ACTIVITY_TIMEOUT: Duration = Duration::from_secs(300); // 5 minutes
 
+        // Silent clients are fine as long as they answer pings, see `ChannelReceiver::ping`
         loop {
-            // Check for timeout
-            if self.last_activity.elapsed() > ACTIVITY_TIMEOUT {
-                warn!("Connection inactive for {:?}, timing out", ACTIVITY_TIMEOUT);
-                return Err(SocketError::Timeout {
-                    duration: ACTIVITY_TIMEOUT,
-                });
-            }
-
             match socket_receiver.next().await {
                 Some(Ok(msg)) => {
-                    self.last_activity = Instant::now();
+                    self.shared.heard_from_client();
                     self.message_count += 1;
 
                     debug!("Received message #{} from client", self.message_count);
 
-                    if msg.is_binary() {
-                        self.handle_binary_message(msg, &channel_sender).await?;
+                    let handled = if msg.is_binary() {
+                        self.handle_binary_message(msg, &channel_sender).await
                     } else if msg.is_text() {
-                        self.handle_text_message(msg, &channel_sender).await?;
+                        self.handle_text_message(msg, &channel_sender).await
+                    } else if msg.is_pong() {
+                        trace!("Client answered ping");
+                        Ok(())
                     } else {
-                        debug!("Received non-text/binary message (ping/pong/close)");
+                        debug!("Received non-text/binary message (ping/close)");
+                        Ok(())
+                    };
+                    if let Err(e) = handled {
+                        self.report(&e);
+                        if !e.is_recoverable() {
+                            return Err(e);
+                        }
                     }
                 }
                 Some(Err(e)) => {
                     error!("WebSocket receive error: {}", e);
-                    return Err(SocketError::ReceiveError(e.to_string()));
+                    let error = SocketError::ReceiveError(e.to_string());
+                    self.report(&error);
+                    return Err(error);
                 }
                 None => {
                     info!("WebSocket stream ended (client disconnected)");
@@ -235,13 +830,25 @@ impl ChannelSender {
     async fn handle_binary_message(
         &self,
         msg: Message,
-        channel_sender: &broadcast::Sender<Message>,
+        channel_sender: &RoomChannel,
     ) -> Result<(), SocketError> {
         let data = msg.into_payload();
         let data_len = data.len();
 
-        match decode_ws_message(data) {
-            Ok(parsed) => {
+        let reassembled =
+            decode_ws_message(data, self.room.max_payload_length).and_then(|parsed| {
+                if parsed.flags & FRAGMENT_FLAGS == 0 {
+                    return Ok(Some(parsed));
+                }
+                // Fragments are copied into the message being put back together
+                let mut reassembly = self.reassembly.lock().unwrap_or_else(|e| e.into_inner());
+                let reassembled =
+                    reassembly.push(parsed.map_payload(|payload| payload.to_vec()))?;
+                Ok(reassembled.map(|msg| msg.map_payload(Bytes::from)))
+            });
+        match reassembled {
+            Ok(None) => debug!("Buffered a fragment of {} bytes", data_len),
+            Ok(Some(parsed)) => {
                 let message_type = parsed.msg_type;
                 debug!(
                     "Decoded binary message: type={}, payload_len={}",
@@ -249,13 +856,114 @@ impl ChannelSender {
                     parsed.payload.len()
                 );
 
-                let payload = WsPayload { parsed };
-                let encoded = payload.handle_payload();
+                if message_type == message_types::AUTH {
+                    return self.authenticate(&parsed.payload);
+                }
+                if is_privileged(message_type) && !self.shared.admin.load(Ordering::Relaxed) {
+                    warn!("Rejecting privileged message type {}", message_type);
+                    return Err(SocketError::Unauthorized(message_type));
+                }
+                // After AUTH, so tokens never end up in a recording
+                if let Some(recorder) = &self.room.recorder {
+                    recorder.record(
+                        &self.connection_id,
+                        &parsed,
+                        self.room.gol.generation_stats().0,
+                    );
+                }
+                if message_type == message_types::KICK_CLIENT {
+                    return self.kick(&parsed.payload);
+                }
+
+                // Subscriptions only concern this connection, nothing is broadcast
+                if matches!(
+                    message_type,
+                    message_types::SUBSCRIBE | message_types::UNSUBSCRIBE
+                ) {
+                    self.update_subscriptions(message_type, &parsed.payload);
+                    return Ok(());
+                }
+                if message_type == message_types::SET_TEXT_PREFERENCES {
+                    self.update_preferences(&parsed.payload);
+                    return Ok(());
+                }
+                if message_type == message_types::SET_NICKNAME {
+                    return self.update_nickname(&parsed.payload, channel_sender);
+                }
+                if message_type == message_types::CHAT {
+                    return self.send_chat(&parsed.payload, channel_sender);
+                }
+                if message_type == message_types::CURSOR_MOVE {
+                    self.move_cursor(&parsed.payload, channel_sender);
+                    return Ok(());
+                }
+                if message_type == message_types::HELLO {
+                    return self.handshake(&parsed.payload);
+                }
+                if message_type == message_types::RESYNC_REQUEST {
+                    debug!("Client missed messages, sending a keyframe");
+                    let keyframe = self.shared.resync_keyframe(&self.room)?;
+                    self.send_direct(BroadcastMessage::system(keyframe));
+                    return Ok(());
+                }
+                if message_type == message_types::SET_VIEWPORT {
+                    return self.set_viewport(&parsed.payload);
+                }
+                if matches!(
+                    message_type,
+                    message_types::ADD_SCHEDULE_ENTRY
+                        | message_types::REMOVE_SCHEDULE_ENTRY
+                        | message_types::LIST_SCHEDULE
+                ) {
+                    return self.update_schedule(message_type, &parsed.payload);
+                }
+                if matches!(
+                    message_type,
+                    message_types::LIST_ROOMS | message_types::CREATE_ROOM
+                ) {
+                    return self.update_lobby(message_type, &parsed.payload);
+                }
+                if message_type == message_types::GET_SERVER_STATS {
+                    let report = self.server_stats.report(self.connections.count());
+                    let message = create_server_stats_message(&report);
+                    self.send_direct(BroadcastMessage::system(message));
+                    return Ok(());
+                }
+
+                if !is_handled(message_type) {
+                    warn!("Unknown message type {} from client", message_type);
+                    return Err(SocketError::UnknownMessageType(message_type));
+                }
+                self.room.modes.admit(message_type)?;
 
-                // Broadcast to all connected clients
-                channel_sender
-                    .send(encoded)
-                    .context("Failed to broadcast message")?;
+                self.room.occupancy.mark_editor(&self.connection_id);
+
+                let payload = WsPayload {
+                    parsed,
+                    room: self.room.clone(),
+                    connection_id: self.connection_id.clone(),
+                };
+
+                // A panicking handler leaves the room's locks poisoned, which the patterns
+                // recover from, so the client is told and the connection carries on
+                let response = panic::catch_unwind(AssertUnwindSafe(|| match &self.room.journal {
+                    Some(journal) => journal.record(&self.connection_id, &payload),
+                    None => payload.handle_payload(),
+                }))
+                .map_err(|_| SocketError::HandlerFailed(message_type))??;
+
+                if let Some(encoded) = response {
+                    match reply_route(message_type) {
+                        ReplyRoute::Sender => self
+                            .direct
+                            .send(encoded)
+                            .await
+                            .map_err(|_| SocketError::ConnectionClosed)?,
+                        ReplyRoute::Room => {
+                            channel_sender.send(encoded)?;
+                        }
+                    }
+                }
 
                 let msg_type_name = match message_type {
                     t if t == message_types::CREATE_NEW_GOL_GENERATION => {
@@ -264,9 +972,15 @@ impl ChannelSender {
                     t if t == message_types::AWAKEN_RANDOM_GOL_CELL => "AWAKEN_RANDOM_GOL_CELL",
                     t if t == message_types::KILL_RANDOM_GOL_CELL => "KILL_RANDOM_GOL_CELL",
                     t if t == message_types::ADVANCE_GOL_GENERATION => "AWAKEN_RANDOM_GOL_CELL",
+                    t if t == message_types::SEED_GOL_FROM_MLP_PAINTING => {
+                        "SEED_GOL_FROM_MLP_PAINTING"
+                    }
 
                     t if t == message_types::CREATE_NEW_MLP_PAINTING => "CREATE_NEW_MLP_PAINTING",
                     t if t == message_types::ADVANCE_MLP_PAINTING => "ADVANCE_MLP_PAINTING",
+                    t if t == message_types::PAINT_MLP_FROM_GOL_GENERATION => {
+                        "PAINT_MLP_FROM_GOL_GENERATION"
+                    }
 
                     _ => "OTHER",
                 };
@@ -286,26 +1000,396 @@ impl ChannelSender {
         Ok(())
     }
 
+    /// Applies a SUBSCRIBE/UNSUBSCRIBE topic mask; an empty payload means every topic
+    fn update_subscriptions(&self, message_type: u8, payload: &[u8]) {
+        let mask = payload.first().copied().unwrap_or(topics::ALL);
+        // A client picking GOL frames back up has missed the regions in between
+        if message_type == message_types::SUBSCRIBE && mask & topics::GOL_FRAMES != 0 {
+            self.room.gol.request_keyframe();
+        }
+        let subscribed = if message_type == message_types::SUBSCRIBE {
+            self.shared.subscriptions.fetch_or(mask, Ordering::Relaxed) | mask
+        } else {
+            self.shared
+                .subscriptions
+                .fetch_and(!mask, Ordering::Relaxed)
+                & !mask
+        };
+        debug!("Subscriptions updated: {:#010b}", subscribed);
+    }
+
+    /// Moves the client's viewport and redraws it, see `Viewport::decode` for the payload
+    fn set_viewport(&self, payload: &[u8]) -> Result<(), SocketError> {
+        let viewport = Viewport::decode(payload)?;
+        debug!("Viewport set to {:?}", viewport);
+        let previous = std::mem::replace(&mut *self.shared.viewport_mut(), viewport);
+        // A client starting on tiles has none yet, panning keeps the ones it has
+        if !previous.is_some_and(|previous| previous.tiles) {
+            let mut tiles = self.shared.tiles.lock().unwrap_or_else(|e| e.into_inner());
+            tiles.clear();
+        }
+        let keyframe = self.shared.keyframe(&self.room)?;
+        self.send_direct(BroadcastMessage::system(keyframe));
+        Ok(())
+    }
+
+    fn update_preferences(&self, payload: &[u8]) {
+        match TextPreferences::decode(payload) {
+            Ok(preferences) => {
+                debug!("Text preferences updated: {:?}", preferences);
+                *self.shared.preferences_mut() = preferences;
+            }
+            Err(e) => warn!("Ignoring invalid text preferences: {}", e),
+        }
+    }
+
+    // SET_NICKNAME payload format:
+    // - N bytes: the nickname (utf8), empty to clear it
+    fn update_nickname(
+        &self,
+        payload: &[u8],
+        channel_sender: &RoomChannel,
+    ) -> Result<(), SocketError> {
+        let nickname = std::str::from_utf8(payload)
+            .map_err(|e| SocketError::DecodeError(anyhow::anyhow!("Nickname isn't utf8: {}", e)))?;
+        self.connections
+            .set_nickname(&self.connection_id, nickname)
+            .map_err(SocketError::DecodeError)?;
+        debug!("Nickname set to {:?}", nickname.trim());
+
+        let members = self.connections.room_members(&self.room.id);
+        channel_sender.send(BroadcastMessage::system(create_presence_list_message(
+            &members,
+        )))?;
+        Ok(())
+    }
+
+    // AUTH payload format:
+    // - N bytes: the admin token (utf8)
+    // A wrong token closes the connection, so tokens can't be guessed one message at a time
+    fn authenticate(&self, payload: &[u8]) -> Result<(), SocketError> {
+        let token = std::str::from_utf8(payload).unwrap_or_default();
+        if !self.admin_access.is_open() && !self.admin_access.verify(token) {
+            warn!("Client sent a wrong admin token");
+            return Err(SocketError::AuthFailed);
+        }
+        self.shared.admin.store(true, Ordering::Relaxed);
+        info!("Client authenticated as admin");
+        self.send_direct(BroadcastMessage::system(create_admin_status_message(true)));
+        Ok(())
+    }
+
+    // KICK_CLIENT payload format:
+    // - N bytes: the id of the connection to close (utf8)
+    fn kick(&self, payload: &[u8]) -> Result<(), SocketError> {
+        let connection_id = std::str::from_utf8(payload).unwrap_or_default();
+        if !self.connections.kick(connection_id) {
+            return Err(SocketError::DecodeError(anyhow::anyhow!(
+                "No connection {:?} to kick",
+                connection_id
+            )));
+        }
+        info!("Kicked connection {}", connection_id);
+        Ok(())
+    }
+
+    // ADD_SCHEDULE_ENTRY payload format:
+    // - N bytes: a schedule line (utf8), see `scheduler`, running in this room unless it
+    //   names another
+    // REMOVE_SCHEDULE_ENTRY payload format:
+    // - 2 bytes: position of the entry in SCHEDULE_LIST (big-endian)
+    // LIST_SCHEDULE has no payload. Each replies with the schedule as it now stands.
+    fn update_schedule(&self, message_type: u8, payload: &[u8]) -> Result<(), SocketError> {
+        if message_type == message_types::ADD_SCHEDULE_ENTRY {
+            let line = std::str::from_utf8(payload).unwrap_or_default();
+            let entry = ScheduleEntry::parse(line, &self.room.id)?;
+            let scheduled = entry.to_string();
+            self.scheduler.add(entry)?;
+            info!("Scheduled {}", scheduled);
+        } else if message_type == message_types::REMOVE_SCHEDULE_ENTRY {
+            let &[high, low] = payload else {
+                return Err(SocketError::DecodeError(anyhow::anyhow!(
+                    "Schedule entry position of {} bytes",
+                    payload.len()
+                )));
+            };
+            let index = u16::from_be_bytes([high, low]) as usize;
+            let Some(entry) = self.scheduler.remove(index) else {
+                return Err(SocketError::DecodeError(anyhow::anyhow!(
+                    "No schedule entry {}",
+                    index
+                )));
+            };
+            info!("Unscheduled {}", entry);
+        }
+
+        let list = create_schedule_list_message(&self.scheduler.entries());
+        self.send_direct(BroadcastMessage::system(list));
+        Ok(())
+    }
+
+    // CREATE_ROOM payload format: see `RoomParams::decode`
+    // LIST_ROOMS has no payload. Both reply with the public rooms, so a room created private
+    // isn't listed, its creator joins it by id.
+    fn update_lobby(&self, message_type: u8, payload: &[u8]) -> Result<(), SocketError> {
+        if message_type == message_types::CREATE_ROOM {
+            let params = RoomParams::decode(payload)?;
+            self.rooms.create(&params)?;
+        }
+
+        let list = create_room_list_message(&self.rooms.summaries());
+        self.send_direct(BroadcastMessage::system(list));
+        Ok(())
+    }
+
+    // CHAT payload format:
+    // - N bytes: the message (utf8), see `decode_chat`
+    fn send_chat(&self, payload: &[u8], channel_sender: &RoomChannel) -> Result<(), SocketError> {
+        let text = decode_chat(payload)?;
+        let Some(sender) = self.connections.presence(&self.connection_id) else {
+            return Err(SocketError::ConnectionClosed);
+        };
+        debug!("Chat message of {} characters", text.chars().count());
+        // Everyone in the room gets it, the sender too so its log shows what was accepted
+        channel_sender.send(BroadcastMessage::system(create_chat_message(&sender, text)))?;
+        Ok(())
+    }
+
+    // CURSOR_MOVE payload format: `CellPayload`, any color is replaced by the connection's
+    // cursor color
+    fn move_cursor(&self, payload: &[u8], channel_sender: &RoomChannel) {
+        let (width, height) = self.room.gol.dimensions();
+        let cell = match CellPayload::decode(payload).and_then(|cell| {
+            cell.validate(width, height)?;
+            Ok(cell)
+        }) {
+            Ok(cell) => cell,
+            Err(e) => {
+                warn!("Dropping cursor move: {}", e);
+                return;
+            }
+        };
+
+        {
+            let mut last_move = self
+                .last_cursor_move
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            if last_move.is_some_and(|last_move| {
+                now.duration_since(last_move) < Duration::from_millis(CURSOR_MIN_INTERVAL_MS)
+            }) {
+                trace!("Dropping cursor move, the last one was too recent");
+                return;
+            }
+            *last_move = Some(now);
+        }
+
+        let message =
+            create_cursor_moved_message(&self.connection_id, cell.x, cell.y, self.cursor_rgb);
+        // Sending only fails with nobody listening, then nobody needs the cursor either
+        let _ = channel_sender.send(BroadcastMessage::system(message).sent_by(&self.connection_id));
+    }
+
+    /// Settles the connection's protocol version, codecs and topics from its HELLO and
+    /// answers with what the room runs. The version stays what the first HELLO agreed on.
+    fn handshake(&self, payload: &[u8]) -> Result<(), SocketError> {
+        let hello = ClientHello::decode(payload)?;
+        let version = hello.negotiate_version()?;
+        let agreed = self.shared.version.load(Ordering::Relaxed);
+        if agreed != 0 && agreed != version {
+            return Err(anyhow!("HELLO changes protocol version {} to {}", agreed, version).into());
+        }
+        self.shared.version.store(version, Ordering::Relaxed);
+
+        let codecs = hello.codecs & SUPPORTED_CODECS;
+        self.shared.codecs.store(codecs, Ordering::Relaxed);
+        if let Some(topics) = hello.topics {
+            self.shared.subscriptions.store(topics, Ordering::Relaxed);
+        }
+        debug!(
+            "Handshake: version {}, codecs {:#04x}, topics {:#010b}",
+            version,
+            codecs,
+            self.shared.subscriptions.load(Ordering::Relaxed)
+        );
+
+        let accepted = create_handshake_message(
+            version,
+            codecs,
+            self.room.gol.dimensions(),
+            self.room.simulation.applied_tick_interval_ms(),
+            &ActivePattern::ALL,
+        );
+        self.send_direct(BroadcastMessage::system(accepted));
+        Ok(())
+    }
+
+    /// Queues a message for this client alone, dropping it if the client isn't reading
+    fn send_direct(&self, message: BroadcastMessage) {
+        if self.direct.try_send(message).is_err() {
+            warn!("Direct channel full or closed, dropping message for this client");
+        }
+    }
+
+    /// Tells the client, and only this client, what went wrong
+    fn report(&self, error: &SocketError) {
+        let message = create_error_message(error.error_code(), &error.to_string());
+        self.send_direct(BroadcastMessage::system(message));
+    }
+
+    /// Handles a JSON text frame like the binary message it translates to. The connection
+    /// gets JSON text frames back from then on.
     #[instrument(skip(self, msg, channel_sender), fields(connection_id = %self.connection_id))]
     async fn handle_text_message(
         &self,
         msg: Message,
-        channel_sender: &broadcast::Sender<Message>,
+        channel_sender: &RoomChannel,
     ) -> Result<(), SocketError> {
         let payload = msg.into_payload();
-        warn!(
-            "Received unsupported text message: {:?}",
-            String::from_utf8_lossy(&payload)
-                .chars()
-                .take(100)
-                .collect::<String>()
+        let parsed = std::str::from_utf8(&payload)
+            .map_err(anyhow::Error::from)
+            .and_then(decode_json_message);
+        match parsed {
+            Ok(parsed) => {
+                if !self.shared.json.swap(true, Ordering::Relaxed) {
+                    info!("Client switched to JSON text frames");
+                }
+                self.handle_binary_message(encode_ws_message(&parsed), channel_sender)
+                    .await
+            }
+            Err(e) => {
+                warn!(
+                    "Received invalid text message {:?}: {:#}",
+                    String::from_utf8_lossy(&payload)
+                        .chars()
+                        .take(100)
+                        .collect::<String>(),
+                    e
+                );
+                self.send_direct(BroadcastMessage::notice(
+                    Notice::InvalidTextMessage,
+                    create_invalid_text_error,
+                ));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Validates a chat message: utf8, up to `MAX_CHAT_LENGTH` characters, no control
+/// characters and not blank. Returns it trimmed.
+fn decode_chat(payload: &[u8]) -> Result<&str> {
+    let text = std::str::from_utf8(payload)?.trim();
+    if text.is_empty() {
+        bail!("Chat message is empty");
+    }
+    if text.chars().count() > MAX_CHAT_LENGTH {
+        bail!("Chat message longer than {} characters", MAX_CHAT_LENGTH);
+    }
+    if text.chars().any(char::is_control) {
+        bail!("Chat message contains control characters");
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{protocol::WsMessage, utils::create_frame_message};
+
+    #[test]
+    fn socket_errors_map_to_codes() {
+        let decode =
+            |data: &[u8]| SocketError::DecodeError(WsMessage::decode(data).unwrap_err().into());
+        assert_eq!(
+            decode(&[2, 1, 0, 0, 0, 0, 0]).error_code(),
+            error_codes::UNSUPPORTED_VERSION
         );
+        assert_eq!(decode(&[1, 1]).error_code(), error_codes::MALFORMED_MESSAGE);
+        assert!(decode(&[1, 1]).is_recoverable());
 
-        let error_msg = Message::text("Only binary messages are supported");
-        channel_sender
-            .send(error_msg)
-            .context("Failed to send error message")?;
+        let timeout = SocketError::Timeout {
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!(timeout.error_code(), error_codes::TIMEOUT);
+        assert!(!timeout.is_recoverable());
+        assert_eq!(
+            SocketError::UnknownMessageType(99).error_code(),
+            error_codes::UNKNOWN_MESSAGE_TYPE
+        );
 
-        Ok(())
+        // A frame that doesn't fit its canvas is reported, the connection carries on
+        let frame = SocketError::from(create_frame_message(2, 2, vec![0; 3]).unwrap_err());
+        assert_eq!(frame.error_code(), error_codes::FRAME_ENCODE_FAILED);
+        assert!(frame.is_recoverable());
+    }
+
+    #[test]
+    fn chat_messages_are_validated() {
+        assert_eq!(decode_chat(b"  hello there \n").unwrap(), "hello there");
+        assert!(decode_chat(b"   ").is_err());
+        assert!(decode_chat(&[0xff, 0xfe]).is_err());
+        assert!(decode_chat(b"bell\x07").is_err());
+        assert!(decode_chat("é".repeat(MAX_CHAT_LENGTH).as_bytes()).is_ok());
+        assert!(decode_chat("é".repeat(MAX_CHAT_LENGTH + 1).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn clients_missing_three_pings_are_dropped() {
+        let shared = ConnectionShared::default();
+        for _ in 0..KEEPALIVE_MAX_MISSED {
+            shared.count_ping().unwrap();
+        }
+        // A pong, or any other message, in time keeps the connection
+        shared.heard_from_client();
+        for _ in 0..KEEPALIVE_MAX_MISSED {
+            shared.count_ping().unwrap();
+        }
+        let dropped = shared.count_ping().unwrap_err();
+        assert_eq!(dropped.error_code(), error_codes::TIMEOUT);
+        assert!(!dropped.is_recoverable());
+    }
+
+    #[test]
+    fn lagging_clients_skip_to_the_newest_frame() {
+        let room = RoomState::for_test();
+        let mut receiver = ChannelReceiver::new(
+            "slow".to_string(),
+            room.clone(),
+            Arc::default(),
+            Arc::default(),
+        );
+        let frame = |n: u8| BroadcastMessage::new(topics::GOL_FRAMES, Message::binary(vec![n]));
+        let pixels = BroadcastMessage::new(topics::PIXEL_EVENTS, Message::binary(vec![0]));
+        let payloads = |sent: Vec<BroadcastMessage>| -> Vec<Vec<u8>> {
+            sent.iter()
+                .map(|broadcast| broadcast.message.as_payload().to_vec())
+                .collect()
+        };
+
+        // Keeping up, every frame goes out
+        assert_eq!(payloads(receiver.pace(frame(1), true, 3).collect()), [[1]]);
+
+        receiver.send_duration = Duration::from_secs(1);
+        assert_eq!(receiver.pace(frame(2), true, 3).count(), 0);
+        assert_eq!(receiver.pace(frame(3), true, 2).count(), 0);
+        // A newer frame replaces the held one, a pixel event goes out after it
+        assert_eq!(receiver.pace(frame(4), true, 0).count(), 1);
+        assert_eq!(receiver.skipped_frames, 2);
+        assert_eq!(receiver.pace(frame(5), true, 1).count(), 0);
+        assert_eq!(
+            payloads(receiver.pace(pixels, true, 0).collect()),
+            [[5], [0]]
+        );
+
+        // The frame after skipped ones goes out as a keyframe
+        let keyframe = receiver.prepare(frame(6));
+        assert_ne!(keyframe.message.as_payload().to_vec(), [6]);
+        assert_eq!(receiver.skipped_frames, 0);
+        assert_eq!(
+            receiver.prepare(frame(7)).message.as_payload().to_vec(),
+            [7]
+        );
     }
 }
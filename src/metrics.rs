@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::constants::{TIMING_BUCKETS_US, message_types};
+
+/// Timings of the whole process, served in the Prometheus text format at /metrics
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// How handling a client message ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    // Something was broadcast or sent back
+    Replied,
+    // Handled without a reply, or rejected
+    Silent,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Replied => "replied",
+            Outcome::Silent => "silent",
+        }
+    }
+}
+
+/// Durations counted by the bucket of `TIMING_BUCKETS_US` they fall in, the last bucket
+/// counting the longer ones
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; TIMING_BUCKETS_US.len() + 1],
+    sum: Duration,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros();
+        let bucket = TIMING_BUCKETS_US
+            .iter()
+            .position(|&bound| micros <= bound as u128)
+            .unwrap_or(TIMING_BUCKETS_US.len());
+        self.buckets[bucket] += 1;
+        self.sum += elapsed;
+        self.count += 1;
+    }
+
+    // Appends the cumulative `_bucket` lines of `name` and its `_sum` and `_count`
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in TIMING_BUCKETS_US.iter().zip(&self.buckets) {
+            cumulative += count;
+            let le = *bound as f64 / 1e6;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, le, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum.as_secs_f64());
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct MessageStats {
+    timing: Histogram,
+    payload_bytes: u64,
+}
+
+/// Handling times of client messages by type and outcome, and of Game of Life ticks
+#[derive(Debug, Default)]
+pub struct Metrics {
+    messages: Mutex<BTreeMap<(u8, Outcome), MessageStats>>,
+    // Stepping a room's board and encoding the frame it broadcasts
+    ticks: Mutex<Histogram>,
+}
+
+impl Metrics {
+    pub fn record_message(
+        &self,
+        msg_type: u8,
+        outcome: Outcome,
+        payload_bytes: usize,
+        elapsed: Duration,
+    ) {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = messages.entry((msg_type, outcome)).or_default();
+        stats.timing.observe(elapsed);
+        stats.payload_bytes += payload_bytes as u64;
+    }
+
+    pub fn record_tick(&self, elapsed: Duration) {
+        self.ticks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .observe(elapsed);
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let messages = self
+            .messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let label = |msg_type: u8, outcome: Outcome| {
+            let name = message_types::name(msg_type).unwrap_or("unknown");
+            format!("type=\"{}\",outcome=\"{}\"", name, outcome.label())
+        };
+
+        let mut out = String::new();
+        out.push_str("# HELP gol_message_duration_seconds Time taken to handle a client message\n");
+        out.push_str("# TYPE gol_message_duration_seconds histogram\n");
+        for (&(msg_type, outcome), stats) in &messages {
+            stats.timing.render(
+                &mut out,
+                "gol_message_duration_seconds",
+                &label(msg_type, outcome),
+            );
+        }
+        out.push_str("# HELP gol_message_payload_bytes_total Payload bytes of client messages\n");
+        out.push_str("# TYPE gol_message_payload_bytes_total counter\n");
+        for (&(msg_type, outcome), stats) in &messages {
+            let _ = writeln!(
+                out,
+                "gol_message_payload_bytes_total{{{}}} {}",
+                label(msg_type, outcome),
+                stats.payload_bytes
+            );
+        }
+        out.push_str(
+            "# HELP gol_tick_duration_seconds Time taken to step a board and encode its frame\n",
+        );
+        out.push_str("# TYPE gol_tick_duration_seconds histogram\n");
+        self.ticks.lock().unwrap_or_else(|e| e.into_inner()).render(
+            &mut out,
+            "gol_tick_duration_seconds",
+            "",
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histograms_count_into_cumulative_buckets() {
+        let metrics = Metrics::default();
+        let advance = message_types::ADVANCE_GOL_GENERATION;
+        metrics.record_message(advance, Outcome::Replied, 0, Duration::from_micros(80));
+        metrics.record_message(advance, Outcome::Replied, 3, Duration::from_millis(2));
+        metrics.record_message(advance, Outcome::Replied, 0, Duration::from_secs(1));
+        metrics.record_tick(Duration::from_micros(40));
+
+        let text = metrics.render();
+        let labels = "type=\"ADVANCE_GOL_GENERATION\",outcome=\"replied\"";
+        for line in [
+            format!(
+                "gol_message_duration_seconds_bucket{{{},le=\"0.00005\"}} 0",
+                labels
+            ),
+            format!(
+                "gol_message_duration_seconds_bucket{{{},le=\"0.0001\"}} 1",
+                labels
+            ),
+            format!(
+                "gol_message_duration_seconds_bucket{{{},le=\"0.0025\"}} 2",
+                labels
+            ),
+            format!(
+                "gol_message_duration_seconds_bucket{{{},le=\"0.25\"}} 2",
+                labels
+            ),
+            format!(
+                "gol_message_duration_seconds_bucket{{{},le=\"+Inf\"}} 3",
+                labels
+            ),
+            format!("gol_message_duration_seconds_count{{{}}} 3", labels),
+            format!("gol_message_payload_bytes_total{{{}}} 3", labels),
+            "gol_tick_duration_seconds_bucket{le=\"0.00005\"} 1".to_string(),
+            "gol_tick_duration_seconds_count{} 1".to_string(),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} missing from\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
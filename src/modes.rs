@@ -0,0 +1,75 @@
+use std::sync::RwLock;
+
+use crate::{constants::message_types, state::ActivePattern};
+
+/// A command for another pattern than the one the room shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "{} is for {} but the room shows {}",
+    message_types::name(*.msg_type).unwrap_or("unknown"),
+    .wanted.name(),
+    .active.name()
+)]
+pub struct WrongMode {
+    pub msg_type: u8,
+    pub wanted: ActivePattern,
+    pub active: ActivePattern,
+}
+
+/// Which pattern subsystem a room shows. Commands only reach that pattern; clients switch
+/// with SWITCH_MODE, the server itself e.g. for a scheduled `mlp`, and every switch is
+/// broadcast with MODE_CHANGED.
+#[derive(Debug, Default)]
+pub struct ModeManager {
+    active: RwLock<ActivePattern>,
+}
+
+impl ModeManager {
+    pub fn active(&self) -> ActivePattern {
+        *self.active.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Makes `pattern` the active one and returns the one it replaced
+    pub fn switch(&self, pattern: ActivePattern) -> ActivePattern {
+        let mut active = self.active.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *active, pattern)
+    }
+
+    /// Fails for a command of another pattern than the active one. Commands that aren't
+    /// any pattern's, like chat or simulation speed, always pass.
+    pub fn admit(&self, msg_type: u8) -> Result<(), WrongMode> {
+        let active = self.active();
+        match ActivePattern::for_message_type(msg_type) {
+            Some(wanted) if wanted != active => Err(WrongMode {
+                msg_type,
+                wanted,
+                active,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_only_reach_the_active_pattern() {
+        let modes = ModeManager::default();
+        assert_eq!(modes.active(), ActivePattern::Gol);
+        assert!(modes.admit(message_types::ADVANCE_GOL_GENERATION).is_ok());
+        assert!(modes.admit(message_types::SET_SIMULATION_SPEED).is_ok());
+        let rejected = modes
+            .admit(message_types::ADVANCE_MLP_PAINTING)
+            .unwrap_err();
+        assert_eq!(
+            rejected.to_string(),
+            "ADVANCE_MLP_PAINTING is for mlp but the room shows gol"
+        );
+
+        assert_eq!(modes.switch(ActivePattern::Mlp), ActivePattern::Gol);
+        assert!(modes.admit(message_types::ADVANCE_MLP_PAINTING).is_ok());
+        assert!(modes.admit(message_types::ADVANCE_GOL_GENERATION).is_err());
+    }
+}
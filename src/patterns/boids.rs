@@ -0,0 +1,300 @@
+use crate::{
+    constants::{
+        BOID_MAX_SPEED, BOID_SEPARATION_RADIUS, BOID_VIEW_RADIUS, DEAD_CELL_R_G_B, MAX_BOIDS,
+    },
+    utils::{FrameError, create_frame_message},
+};
+use axum_tws::Message;
+use game_of_life_core::create_random_rgb;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Boid {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    rgb: [u8; 3],
+}
+
+/// How strongly each rule steers a boid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoidWeights {
+    // Away from boids that come too close
+    pub separation: f32,
+    // Towards the average heading of visible boids
+    pub alignment: f32,
+    // Towards the center of visible boids
+    pub cohesion: f32,
+}
+
+impl Default for BoidWeights {
+    fn default() -> Self {
+        Self {
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+        }
+    }
+}
+
+/// Boids flying over a wrapping grid, steered by separation, alignment and cohesion
+#[derive(Debug, Clone)]
+pub struct Flock {
+    pub width: u16,
+    pub height: u16,
+    boids: Vec<Boid>,
+    pub weights: BoidWeights,
+}
+
+impl Flock {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            boids: Vec::new(),
+            weights: BoidWeights::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.boids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boids.is_empty()
+    }
+
+    /// Adds up to `count` boids heading in random directions around (x, y), stopping at
+    /// `MAX_BOIDS`. Returns how many were added.
+    pub fn spawn(&mut self, x: u16, y: u16, count: usize, rng: &mut impl Rng) -> usize {
+        let count = count.min(MAX_BOIDS - self.boids.len());
+        for _ in 0..count {
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            self.boids.push(Boid {
+                x: (x as f32 + rng.random_range(-2.0..2.0)).rem_euclid(self.width as f32),
+                y: (y as f32 + rng.random_range(-2.0..2.0)).rem_euclid(self.height as f32),
+                vx: angle.cos() * BOID_MAX_SPEED / 2.0,
+                vy: angle.sin() * BOID_MAX_SPEED / 2.0,
+                rgb: create_random_rgb(rng),
+            });
+        }
+        count
+    }
+
+    pub fn clear(&mut self) {
+        self.boids.clear();
+    }
+
+    // Shortest offset from `from` to `to` along an axis that wraps at `size`
+    fn wrapped_delta(from: f32, to: f32, size: f32) -> f32 {
+        let delta = to - from;
+        if delta > size / 2.0 {
+            delta - size
+        } else if delta < -size / 2.0 {
+            delta + size
+        } else {
+            delta
+        }
+    }
+
+    pub fn step(&mut self) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let weights = self.weights;
+
+        let velocities: Vec<(f32, f32)> = self
+            .boids
+            .iter()
+            .enumerate()
+            .map(|(i, boid)| {
+                let (mut separation, mut heading, mut center) =
+                    ((0.0, 0.0), (0.0, 0.0), (0.0, 0.0));
+                let mut visible = 0;
+                for (j, other) in self.boids.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let dx = Self::wrapped_delta(boid.x, other.x, width);
+                    let dy = Self::wrapped_delta(boid.y, other.y, height);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    if distance > BOID_VIEW_RADIUS {
+                        continue;
+                    }
+                    visible += 1;
+                    heading = (heading.0 + other.vx, heading.1 + other.vy);
+                    center = (center.0 + dx, center.1 + dy);
+                    if distance < BOID_SEPARATION_RADIUS && distance > 0.0 {
+                        separation = (separation.0 - dx / distance, separation.1 - dy / distance);
+                    }
+                }
+
+                let (mut vx, mut vy) = (boid.vx, boid.vy);
+                if visible > 0 {
+                    let n = visible as f32;
+                    // Gentle fractions of each rule so a single tick never turns a boid around
+                    vx += weights.separation * separation.0 * 0.05
+                        + weights.alignment * (heading.0 / n - boid.vx) * 0.05
+                        + weights.cohesion * (center.0 / n) * 0.01;
+                    vy += weights.separation * separation.1 * 0.05
+                        + weights.alignment * (heading.1 / n - boid.vy) * 0.05
+                        + weights.cohesion * (center.1 / n) * 0.01;
+                }
+
+                let speed = (vx * vx + vy * vy).sqrt();
+                if speed > BOID_MAX_SPEED {
+                    (vx / speed * BOID_MAX_SPEED, vy / speed * BOID_MAX_SPEED)
+                } else {
+                    (vx, vy)
+                }
+            })
+            .collect();
+
+        for (boid, (vx, vy)) in self.boids.iter_mut().zip(velocities) {
+            boid.vx = vx;
+            boid.vy = vy;
+            boid.x = (boid.x + vx).rem_euclid(width);
+            boid.y = (boid.y + vy).rem_euclid(height);
+        }
+    }
+
+    /// Each boid colors the cell it's in, over a blank canvas
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let mut rgb_data = DEAD_CELL_R_G_B.repeat(width * self.height as usize);
+        for boid in &self.boids {
+            // rem_euclid can round up to the size itself
+            let x = (boid.x as usize).min(width - 1);
+            let y = (boid.y as usize).min(self.height as usize - 1);
+            let offset = (y * width + x) * 3;
+            rgb_data[offset..offset + 3].copy_from_slice(&boid.rgb);
+        }
+        rgb_data
+    }
+}
+
+/// Boids flock of a single room
+pub struct BoidsState {
+    flock: RwLock<Flock>,
+    // Lock after `flock` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl BoidsState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        Self {
+            flock: RwLock::new(Flock::new(width, height)),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn frame(flock: &Flock) -> Result<Message, FrameError> {
+        create_frame_message(flock.width, flock.height, flock.to_rgb_data())
+    }
+
+    /// The flock where it is, without moving it
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        Self::frame(&self.flock.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Restarts the random stream from `seed` and clears the flock
+    pub fn reseed(&self, seed: u64) {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.flock
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    pub fn spawn(&self, x: u16, y: u16, count: usize) -> Result<Message, FrameError> {
+        let mut flock = self.flock.write().unwrap_or_else(|e| e.into_inner());
+        let spawned = flock.spawn(x, y, count, &mut *self.rng());
+        debug!(
+            "Boids: Spawned {} boids at ({}, {}), {} in the flock",
+            spawned,
+            x,
+            y,
+            flock.len()
+        );
+        Self::frame(&flock)
+    }
+
+    pub fn set_weights(&self, weights: BoidWeights) -> Result<Message, FrameError> {
+        let mut flock = self.flock.write().unwrap_or_else(|e| e.into_inner());
+        flock.weights = weights;
+        debug!("Boids: Weights set to {:?}", weights);
+        Self::frame(&flock)
+    }
+
+    pub fn reset(&self) -> Result<Message, FrameError> {
+        let mut flock = self.flock.write().unwrap_or_else(|e| e.into_inner());
+        flock.clear();
+        flock.weights = BoidWeights::default();
+        debug!("Boids: Flock reset");
+        Self::frame(&flock)
+    }
+
+    /// Moves every boid one tick and renders the flock
+    pub fn step(&self) -> Result<Message, FrameError> {
+        let mut flock = self.flock.write().unwrap_or_else(|e| e.into_inner());
+        flock.step();
+        Self::frame(&flock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boid(x: f32, y: f32, vx: f32, vy: f32) -> Boid {
+        Boid {
+            x,
+            y,
+            vx,
+            vy,
+            rgb: [1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn boids_wrap_and_stay_under_max_speed() {
+        let mut flock = Flock::new(20, 20);
+        flock.boids.push(boid(19.5, 0.2, 5.0, -5.0));
+        flock.step();
+
+        let moved = flock.boids[0];
+        assert!((0.0..20.0).contains(&moved.x) && (0.0..20.0).contains(&moved.y));
+        assert!((moved.vx * moved.vx + moved.vy * moved.vy).sqrt() <= BOID_MAX_SPEED + 1e-5);
+        assert_eq!(flock.to_rgb_data().len(), 20 * 20 * 3);
+    }
+
+    #[test]
+    fn cohesion_pulls_boids_together_across_the_edge() {
+        let mut flock = Flock::new(40, 40);
+        flock.weights = BoidWeights {
+            separation: 0.0,
+            alignment: 0.0,
+            cohesion: 1.0,
+        };
+        flock.boids.push(boid(1.0, 20.0, 0.0, 0.0));
+        flock.boids.push(boid(37.0, 20.0, 0.0, 0.0));
+        flock.step();
+
+        // The shortest way between them crosses the left edge
+        assert!(flock.boids[0].vx < 0.0);
+        assert!(flock.boids[1].vx > 0.0);
+    }
+
+    #[test]
+    fn spawning_stops_at_max_boids() {
+        let mut flock = Flock::new(10, 10);
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(flock.spawn(5, 5, MAX_BOIDS + 10, &mut rng), MAX_BOIDS);
+        assert_eq!(flock.spawn(5, 5, 1, &mut rng), 0);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    constants::{BRAIN_DYING_R_G_B, BRAIN_SEED_DENSITY, DEAD_CELL_R_G_B, LIVE_CELL_R_G_B},
+    utils::{FrameError, create_frame_message},
+};
+use axum_tws::Message;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrainCell {
+    Dead,
+    Alive,
+    Dying,
+}
+
+impl BrainCell {
+    fn rgb(&self) -> [u8; 3] {
+        match self {
+            BrainCell::Dead => DEAD_CELL_R_G_B,
+            BrainCell::Alive => LIVE_CELL_R_G_B,
+            BrainCell::Dying => BRAIN_DYING_R_G_B,
+        }
+    }
+}
+
+/// Brian's Brain: dead cells with exactly two alive neighbours fire, alive cells start
+/// dying and dying cells die. Cells beyond the grid are dead.
+#[derive(Debug, Clone)]
+pub struct BriansBrain {
+    pub width: u16,
+    pub height: u16,
+    cells: Vec<BrainCell>,
+    pub generation_count: u64,
+}
+
+impl BriansBrain {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![BrainCell::Dead; width as usize * height as usize],
+            generation_count: 0,
+        }
+    }
+
+    pub fn random(width: u16, height: u16, rng: &mut impl Rng) -> Self {
+        let mut brain = Self::new(width, height);
+        for cell in &mut brain.cells {
+            if rng.random_bool(BRAIN_SEED_DENSITY) {
+                *cell = BrainCell::Alive;
+            }
+        }
+        brain
+    }
+
+    fn alive_neighbours(&self, x: usize, y: usize) -> u8 {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut count = 0;
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                if (nx, ny) != (x, y) && self.cells[ny * width + nx] == BrainCell::Alive {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    pub fn step(&mut self) {
+        let width = self.width as usize;
+        let next = (0..self.cells.len())
+            .map(|i| match self.cells[i] {
+                BrainCell::Alive => BrainCell::Dying,
+                BrainCell::Dying => BrainCell::Dead,
+                BrainCell::Dead if self.alive_neighbours(i % width, i / width) == 2 => {
+                    BrainCell::Alive
+                }
+                BrainCell::Dead => BrainCell::Dead,
+            })
+            .collect();
+        self.cells = next;
+        self.generation_count += 1;
+    }
+
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        self.cells.iter().flat_map(|cell| cell.rgb()).collect()
+    }
+}
+
+/// Brian's Brain state of a single room
+pub struct BrainState {
+    brain: RwLock<BriansBrain>,
+    // Lock after `brain` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl BrainState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self {
+            brain: RwLock::new(BriansBrain::random(width, height, &mut rng)),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn frame(brain: &BriansBrain) -> Result<Message, FrameError> {
+        create_frame_message(brain.width, brain.height, brain.to_rgb_data())
+    }
+
+    /// The grid as it stands, without stepping it
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        Self::frame(&self.brain.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Restarts the random stream from `seed` and starts a fresh grid
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.create_new_generation()
+    }
+
+    pub fn create_new_generation(&self) -> Result<Message, FrameError> {
+        let mut brain = self.brain.write().unwrap_or_else(|e| e.into_inner());
+        *brain = BriansBrain::random(brain.width, brain.height, &mut *self.rng());
+        debug!(
+            "Generated Brian's Brain grid of {}x{} cells",
+            brain.width, brain.height
+        );
+        Self::frame(&brain)
+    }
+
+    pub fn advance_generation(&self) -> Result<Message, FrameError> {
+        let mut brain = self.brain.write().unwrap_or_else(|e| e.into_inner());
+        brain.step();
+        debug!(
+            "Advanced Brian's Brain to generation {}",
+            brain.generation_count
+        );
+        Self::frame(&brain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl BriansBrain {
+        fn get(&self, x: u16, y: u16) -> BrainCell {
+            self.cells[y as usize * self.width as usize + x as usize]
+        }
+
+        fn set(&mut self, x: u16, y: u16, cell: BrainCell) {
+            self.cells[y as usize * self.width as usize + x as usize] = cell;
+        }
+    }
+
+    #[test]
+    fn cells_cycle_through_three_states() {
+        let mut brain = BriansBrain::new(4, 3);
+        brain.set(1, 1, BrainCell::Alive);
+        brain.set(2, 1, BrainCell::Alive);
+
+        brain.step();
+        assert_eq!(brain.get(1, 1), BrainCell::Dying);
+        assert_eq!(brain.get(2, 1), BrainCell::Dying);
+        // Dead cells touching both alive cells fire
+        for x in 1..=2 {
+            assert_eq!(brain.get(x, 0), BrainCell::Alive);
+            assert_eq!(brain.get(x, 2), BrainCell::Alive);
+        }
+        // One alive neighbour isn't enough
+        assert_eq!(brain.get(0, 0), BrainCell::Dead);
+
+        brain.step();
+        assert_eq!(brain.get(1, 1), BrainCell::Dead);
+        assert_eq!(brain.generation_count, 2);
+    }
+
+    #[test]
+    fn frames_map_states_to_colors() {
+        let mut brain = BriansBrain::new(3, 1);
+        brain.set(0, 0, BrainCell::Alive);
+        brain.set(1, 0, BrainCell::Dying);
+        assert_eq!(
+            brain.to_rgb_data(),
+            [LIVE_CELL_R_G_B, BRAIN_DYING_R_G_B, DEAD_CELL_R_G_B].concat()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Result, bail};
+
+use crate::constants::MAX_BRUSH_RADIUS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+    Diamond,
+}
+
+impl BrushShape {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(BrushShape::Square),
+            1 => Some(BrushShape::Circle),
+            2 => Some(BrushShape::Diamond),
+            _ => None,
+        }
+    }
+
+    fn contains(&self, dx: i32, dy: i32, radius: i32) -> bool {
+        match self {
+            BrushShape::Square => true,
+            // The extra radius rounds the circle's rim so small brushes aren't diamonds
+            BrushShape::Circle => dx * dx + dy * dy <= radius * radius + radius,
+            BrushShape::Diamond => dx.abs() + dy.abs() <= radius,
+        }
+    }
+}
+
+/// How cell edits spread around each point of a stroke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brush {
+    pub radius: u8,
+    pub shape: BrushShape,
+    // Chance out of 255 that a covered cell comes alive, 255 fills the whole shape
+    pub density: u8,
+}
+
+impl Brush {
+    pub const LENGTH: usize = 3;
+
+    // Brush payload format:
+    // - 1 byte: radius in cells, 0 covers only the center
+    // - 1 byte: shape (0: square, 1: circle, 2: diamond)
+    // - 1 byte: density, 1 to 255
+    pub fn decode_prefix(data: &[u8]) -> Result<(Self, &[u8])> {
+        if data.len() < Self::LENGTH {
+            bail!("Brush needs {} bytes, got {}", Self::LENGTH, data.len());
+        }
+        let (brush, rest) = data.split_at(Self::LENGTH);
+        let Some(shape) = BrushShape::from_id(brush[1]) else {
+            bail!("Unknown brush shape {}", brush[1]);
+        };
+        if brush[0] > MAX_BRUSH_RADIUS {
+            bail!(
+                "Brush radius {} exceeds the maximum of {}",
+                brush[0],
+                MAX_BRUSH_RADIUS
+            );
+        }
+        if brush[2] == 0 {
+            bail!("Brush density must be at least 1");
+        }
+
+        Ok((
+            Self {
+                radius: brush[0],
+                shape,
+                density: brush[2],
+            },
+            rest,
+        ))
+    }
+
+    /// Cells covered around (x, y), clipped to a width x height grid
+    pub fn footprint(&self, x: u16, y: u16, width: u16, height: u16) -> Vec<(u16, u16)> {
+        let radius = self.radius as i32;
+        let mut cells = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (cx, cy) = (x as i32 + dx, y as i32 + dy);
+                if self.shape.contains(dx, dy, radius)
+                    && (0..width as i32).contains(&cx)
+                    && (0..height as i32).contains(&cy)
+                {
+                    cells.push((cx as u16, cy as u16));
+                }
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brush(radius: u8, shape: u8) -> Brush {
+        Brush::decode_prefix(&[radius, shape, 255]).unwrap().0
+    }
+
+    #[test]
+    fn brush_footprints() {
+        assert_eq!(brush(0, 1).footprint(5, 5, 10, 10), vec![(5, 5)]);
+        assert_eq!(brush(2, 0).footprint(5, 5, 10, 10).len(), 25);
+        assert_eq!(brush(2, 1).footprint(5, 5, 10, 10).len(), 21);
+        assert_eq!(brush(2, 2).footprint(5, 5, 10, 10).len(), 13);
+        // Clipped at the grid corner
+        assert_eq!(brush(2, 0).footprint(0, 0, 10, 10).len(), 9);
+    }
+
+    #[test]
+    fn brush_decoding() {
+        let (brush, rest) = Brush::decode_prefix(&[3, 2, 128, 0, 1, 0, 2]).unwrap();
+        assert_eq!(brush.shape, BrushShape::Diamond);
+        assert_eq!(rest, &[0, 1, 0, 2]);
+
+        assert!(Brush::decode_prefix(&[3, 9, 128]).is_err());
+        assert!(Brush::decode_prefix(&[MAX_BRUSH_RADIUS + 1, 0, 128]).is_err());
+        assert!(Brush::decode_prefix(&[3, 0, 0]).is_err());
+        assert!(Brush::decode_prefix(&[3, 0]).is_err());
+    }
+}
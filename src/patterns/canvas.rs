@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use image::{ImageReader, Limits, imageops::FilterType};
+use std::io::Cursor;
+
+use crate::constants::MAX_UPLOAD_IMAGE_SIDE;
+
+/// Perceived brightness of an rgb value (ITU-R BT.601 weights)
+pub fn luminance([r, g, b]: [u8; 3]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// Thresholds rgb frame data into a cell grid: pixels darker than `threshold` become live cells
+pub fn rgb_to_cells(rgb_data: &[u8], width: usize, height: usize, threshold: u8) -> Vec<Vec<bool>> {
+    let mut cells = vec![vec![false; width]; height];
+
+    for (i, pixel) in rgb_data.chunks_exact(3).take(width * height).enumerate() {
+        let (x, y) = (i % width, i / width);
+        cells[y][x] = luminance([pixel[0], pixel[1], pixel[2]]) < threshold;
+    }
+
+    cells
+}
+
+/// Composites live cells over rgb frame data `width` cells wide: live cells show the pixel
+/// under them, every other cell the `background`
+pub fn reveal_live_cells(
+    live: &[(u16, u16)],
+    width: u16,
+    rgb_data: &[u8],
+    background: [u8; 3],
+) -> Vec<u8> {
+    let mut frame_data = background.repeat(rgb_data.len() / 3);
+    for &(x, y) in live {
+        let i = (y as usize * width as usize + x as usize) * 3;
+        frame_data[i..i + 3].copy_from_slice(&rgb_data[i..i + 3]);
+    }
+    frame_data
+}
+
+/// Collects the coordinates of every live cell, row by row
+pub fn cells_to_points(cells: &[Vec<bool>]) -> Vec<(usize, usize)> {
+    cells
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, alive)| **alive)
+                .map(move |(x, _)| (x, y))
+        })
+        .collect()
+}
+
+/// Decodes a PNG or JPEG and scales it to `width`x`height`, returning its pixels row by row
+pub fn decode_image(data: &[u8], width: usize, height: usize) -> Result<Vec<[u8; 3]>> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .context("Failed to read image")?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_UPLOAD_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_UPLOAD_IMAGE_SIDE);
+    reader.limits(limits);
+    let image = reader.decode().context("Failed to decode image")?;
+
+    let scaled = image
+        .resize_exact(width as u32, height as u32, FilterType::Triangle)
+        .into_rgb8();
+    Ok(scaled.pixels().map(|pixel| pixel.0).collect())
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use game_of_life_core::GolEngine;
+
+type Shape = Vec<(i32, i32)>;
+type Transform = fn((i32, i32)) -> (i32, i32);
+
+/// Object counts of a generation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Census {
+    // Connected groups of live cells, neighbors including diagonals
+    pub objects: usize,
+    pub blocks: usize,
+    pub gliders: usize,
+    pub guns: usize,
+}
+
+// The two glider phases, every other phase is one of these reflected
+const GLIDER_PHASES: [&[(i32, i32)]; 2] = [
+    &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)],
+    &[(0, 0), (2, 0), (1, 1), (2, 1), (1, 2)],
+];
+const BLOCK: &[(i32, i32)] = &[(0, 0), (1, 0), (0, 1), (1, 1)];
+// Offset between the two stator blocks of a Gosper glider gun, which stay put in every phase
+const GOSPER_GUN_BLOCK_OFFSET: (i32, i32) = (34, -2);
+
+/// Counts objects, blocks, gliders and Gosper guns. Objects are classified by shape alone,
+/// in any rotation or reflection; a gun is a pair of blocks at its stator offset.
+pub fn take_census(cells: &[Vec<bool>]) -> Census {
+    let glider_shapes: Vec<Shape> = GLIDER_PHASES
+        .iter()
+        .map(|phase| canonical(phase.to_vec()))
+        .collect();
+    let block_shape = canonical(BLOCK.to_vec());
+
+    let mut census = Census::default();
+    let mut blocks = HashSet::new();
+    for component in components(cells, 1) {
+        census.objects += 1;
+        let origin = component.iter().min().copied().unwrap();
+        let shape = canonical(component);
+        if glider_shapes.contains(&shape) {
+            census.gliders += 1;
+        } else if shape == block_shape {
+            census.blocks += 1;
+            blocks.insert(origin);
+        }
+    }
+
+    let (dx, dy) = GOSPER_GUN_BLOCK_OFFSET;
+    let gun_offsets = [(dx, dy), (dx, -dy), (dy, dx), (-dy, dx)];
+    census.guns = blocks
+        .iter()
+        .map(|&(x, y)| {
+            gun_offsets
+                .iter()
+                .filter(|&&(ox, oy)| blocks.contains(&(x + ox, y + oy)))
+                .count()
+        })
+        .sum();
+    census
+}
+
+/// Smallest period up to `max_period` after which the grid repeats itself, still lifes
+/// having period 1. None if it doesn't repeat that soon.
+pub fn detect_period(game: &dyn GolEngine, max_period: u32) -> Option<u32> {
+    let cells = game.to_packed_bits();
+    let mut sandbox = game.boxed_clone();
+    (1..=max_period).find(|_| {
+        sandbox.step();
+        sandbox.to_packed_bits() == cells
+    })
+}
+
+/// Period of the first object that travels: run on its own, it shows up again somewhere
+/// else within `max_period` generations. Gliders are left out, most soups send some off.
+pub fn find_spaceship(cells: &[Vec<bool>], max_period: u32) -> Option<u32> {
+    let glider_shapes: Vec<Shape> = GLIDER_PHASES
+        .iter()
+        .map(|phase| canonical(phase.to_vec()))
+        .collect();
+
+    // Spaceships like the LWSS have phases with cells a gap away from the rest
+    components(cells, 2)
+        .into_iter()
+        .filter(|component| !glider_shapes.contains(&canonical(component.clone())))
+        .find_map(|component| travel_period(&component, max_period))
+}
+
+// Generations until the object repeats its shape at another position, on an unbounded plane
+fn travel_period(object: &[(i32, i32)], max_period: u32) -> Option<u32> {
+    let start = anchored(object.iter().copied());
+    let mut cells: HashSet<(i32, i32)> = object.iter().copied().collect();
+    (1..=max_period).find(|_| {
+        cells = step_unbounded(&cells);
+        let moved = anchored(cells.iter().copied());
+        moved.0 == start.0 && moved.1 != start.1
+    })
+}
+
+// Shape moved to the origin and where its top-left corner was
+fn anchored(cells: impl Iterator<Item = (i32, i32)>) -> (Shape, (i32, i32)) {
+    let mut cells: Shape = cells.collect();
+    let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    for cell in &mut cells {
+        *cell = (cell.0 - min_x, cell.1 - min_y);
+    }
+    cells.sort_unstable();
+    (cells, (min_x, min_y))
+}
+
+// One B3/S23 generation of a sparse set of live cells
+fn step_unbounded(cells: &HashSet<(i32, i32)>) -> HashSet<(i32, i32)> {
+    let mut neighbours: HashMap<(i32, i32), u8> = HashMap::new();
+    for &(x, y) in cells {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) {
+                    *neighbours.entry((x + dx, y + dy)).or_default() += 1;
+                }
+            }
+        }
+    }
+    neighbours
+        .into_iter()
+        .filter(|&(cell, count)| count == 3 || (count == 2 && cells.contains(&cell)))
+        .map(|(cell, _)| cell)
+        .collect()
+}
+
+// Groups of live cells at most `reach` cells apart in any direction, as (x, y)
+// coordinates. A reach of 1 gives the 8-connected groups.
+fn components(cells: &[Vec<bool>], reach: usize) -> Vec<Shape> {
+    let mut seen: Vec<Vec<bool>> = cells.iter().map(|row| vec![false; row.len()]).collect();
+    let mut components = Vec::new();
+
+    for (y, row) in cells.iter().enumerate() {
+        for (x, &alive) in row.iter().enumerate() {
+            if !alive || seen[y][x] {
+                continue;
+            }
+            seen[y][x] = true;
+            let mut component = Vec::new();
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                component.push((cx as i32, cy as i32));
+                for ny in cy.saturating_sub(reach)..=(cy + reach).min(cells.len() - 1) {
+                    for nx in cx.saturating_sub(reach)..=(cx + reach).min(cells[ny].len() - 1) {
+                        if cells[ny][nx] && !seen[ny][nx] {
+                            seen[ny][nx] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+    }
+    components
+}
+
+// Same cells for every rotation, reflection and position of a shape
+fn canonical(shape: Shape) -> Shape {
+    let transforms: [Transform; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-x, y),
+        |(x, y)| (x, -y),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, x),
+        |(x, y)| (-y, x),
+        |(x, y)| (y, -x),
+        |(x, y)| (-y, -x),
+    ];
+
+    transforms
+        .iter()
+        .map(|transform| {
+            let mut cells: Shape = shape.iter().map(|&cell| transform(cell)).collect();
+            let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+            let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+            for cell in &mut cells {
+                *cell = (cell.0 - min_x, cell.1 - min_y);
+            }
+            cells.sort_unstable();
+            cells
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::library::LibraryPattern;
+    use game_of_life_core::GameOfLifeVecs;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn game_with(stamps: &[(LibraryPattern, u16, u16)]) -> GameOfLifeVecs {
+        let mut game = GameOfLifeVecs::new(64, 64, &mut StdRng::seed_from_u64(0));
+        game.kill_all_cells();
+        for &(pattern, x, y) in stamps {
+            game.stamp_cells(x, y, &pattern.pattern().cells);
+        }
+        game
+    }
+
+    #[test]
+    fn census_counts_gliders_and_guns() {
+        let game = game_with(&[
+            (LibraryPattern::Glider, 2, 2),
+            (LibraryPattern::Glider, 50, 50),
+            (LibraryPattern::GosperGliderGun, 10, 20),
+        ]);
+        let census = take_census(&game.current_generation);
+        assert_eq!(census.gliders, 2);
+        assert_eq!(census.guns, 1);
+        assert_eq!(census.blocks, 2);
+    }
+
+    #[test]
+    fn period_of_oscillators() {
+        let pulsar = game_with(&[(LibraryPattern::Pulsar, 20, 20)]);
+        assert_eq!(detect_period(&pulsar, 8), Some(3));
+
+        let gun = game_with(&[(LibraryPattern::GosperGliderGun, 10, 20)]);
+        assert_eq!(detect_period(&gun, 8), None);
+    }
+
+    #[test]
+    fn spaceships_other_than_gliders_are_found() {
+        let lwss = game_with(&[
+            (LibraryPattern::Glider, 2, 2),
+            (LibraryPattern::Lwss, 30, 30),
+        ]);
+        assert_eq!(find_spaceship(&lwss.current_generation, 8), Some(4));
+
+        let still = game_with(&[
+            (LibraryPattern::Glider, 2, 2),
+            (LibraryPattern::Pulsar, 20, 20),
+        ]);
+        assert_eq!(find_spaceship(&still.current_generation, 8), None);
+    }
+}
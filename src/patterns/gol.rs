@@ -1,146 +1,1725 @@
 use crate::{
-    constants::{CANVAS_HEIGHT, CANVAS_WIDTH, DEAD_CELL_R_G_B},
-    patterns::gol_threads::GameOfLifeVecs,
-    utils::{create_frame_message, create_pixel_message, create_random_rgb},
+    constants::{
+        CROSSOVER_LUMINANCE_THRESHOLD, DEAD_CELL_R_G_B, GRID_DUMP_MAGIC, GRID_DUMP_VERSION,
+        MAX_CANVAS_SIDE, MAX_SPARSE_CANVAS_SIDE, MAX_STABLE_PERIOD, MAX_UNDO_EDITS,
+        POPULATION_HISTORY_LEN, REGION_KEYFRAME_EVERY, REGION_MAX_AREA_PERCENT, TILE_SIDE,
+    },
+    input::{InputEvent, KeyState, input_keys},
+    patterns::{brush::Brush, canvas, mlp::MlpState, rle::RlePattern},
+    protocol::generation_hash,
+    utils::{
+        FrameError, create_frame_message, create_pixel_message, create_pixels_message,
+        create_region_message,
+    },
+    viewport::{Tile, Viewport},
 };
+use anyhow::{Result, bail};
 use axum_tws::Message;
-use once_cell::sync::Lazy;
-use std::sync::RwLock;
-use tracing::debug;
+use game_of_life_core::{ColorScheme, EngineKind, GolEngine, Region};
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use tracing::{debug, warn};
 
-// Global Game of Life state
-static GAME_STATE: Lazy<RwLock<GameOfLifeVecs>> =
-    Lazy::new(|| RwLock::new(GameOfLifeVecs::new(CANVAS_WIDTH, CANVAS_HEIGHT)));
-
-pub fn current_generation() -> Message {
-    let game_state = GAME_STATE.read().unwrap();
-    let frame_data = game_state.to_rgb_data();
+// Cursor steered by keyboard/gamepad input events
+struct InputCursor {
+    x: u16,
+    y: u16,
+    pen_down: bool,
+}
 
-    create_frame_message(frame_data)
+/// Spots a board that stopped changing, or cycles with a period of at most
+/// `MAX_STABLE_PERIOD`, from the hashes of its recent generations
+#[derive(Debug, Default)]
+pub struct StabilityDetector {
+    // Oldest first. Twice the longest period, a cycle counts once it repeated in full so a
+    // single hash collision can't pass for one.
+    hashes: VecDeque<u32>,
+    // Period already reported for the cycle the board is in
+    reported: Option<u8>,
 }
 
-pub fn awaken_random_cell() -> Message {
-    let (x, y) = { GAME_STATE.write().unwrap().awaken_random_cell() };
+impl StabilityDetector {
+    /// Records the hash of the next generation. Returns the period when the board has just
+    /// become stable, 1 for a still life, and None while it stays in the same cycle.
+    pub fn observe(&mut self, hash: u32) -> Option<u8> {
+        if self.hashes.len() == 2 * MAX_STABLE_PERIOD as usize {
+            self.hashes.pop_front();
+        }
+        self.hashes.push_back(hash);
 
-    debug!(
-        "Added a random live cell to current generation, x:{}, y:{}, generation_count:{}",
-        x,
-        y,
-        GAME_STATE.read().unwrap().generation_count
-    );
+        let period = (1..=MAX_STABLE_PERIOD).find(|&period| self.repeats(period as usize));
+        let newly_stable = period.filter(|_| period != self.reported);
+        self.reported = period;
+        newly_stable
+    }
 
-    let [r, g, b] = create_random_rgb();
+    // Whether the last `period` hashes are the `period` before them again
+    fn repeats(&self, period: usize) -> bool {
+        let len = self.hashes.len();
+        len >= 2 * period && (len - period..len).all(|i| self.hashes[i] == self.hashes[i - period])
+    }
+}
 
-    create_pixel_message(x, y, r, g, b)
+/// Longest side of a board `engine` steps. Only the sparse engines, which cost time in the
+/// live cells rather than the area, step boards past `MAX_CANVAS_SIDE`.
+pub fn max_canvas_side(engine: EngineKind) -> u16 {
+    match engine {
+        EngineKind::Sparse | EngineKind::Adaptive => MAX_SPARSE_CANVAS_SIDE,
+        _ => MAX_CANVAS_SIDE,
+    }
 }
 
-pub fn awaken_cell(x: u16, y: u16) -> Message {
-    {
-        GAME_STATE.write().unwrap().awaken_cell_in(x, y)
-    };
+/// Live/dead grid of the cells `game` holds, row by row
+pub fn cell_grid(game: &dyn GolEngine) -> Vec<Vec<bool>> {
+    let (width, height) = game.dimensions();
+    let mut cells = vec![vec![false; width as usize]; height as usize];
+    for (x, y) in game.live_cells() {
+        cells[y as usize][x as usize] = true;
+    }
+    cells
+}
 
-    debug!(
-        "Added a live cell to current generation, x:{}, y:{}, generation_count:{}",
-        x,
-        y,
-        GAME_STATE.read().unwrap().generation_count
-    );
+/// The grid of a room, held by the engine the room selected, which everything reads, edits
+/// and steps. Only the vecs engine keeps a dense grid and the ages cells are colored by.
+struct GolBoard {
+    engine: Box<dyn GolEngine>,
+    // Bumped by every mutable access to the engine and every step that redrew a cell, so it
+    // stays the same as long as the board looks the same
+    version: u64,
+    // Version the last step left as it was, the board is settled while that's still current
+    settled_version: Option<u64>,
+}
 
-    let [r, g, b] = create_random_rgb();
+impl GolBoard {
+    fn new(kind: EngineKind, width: u16, height: u16) -> Self {
+        Self {
+            engine: kind.create(width, height),
+            version: 0,
+            settled_version: None,
+        }
+    }
 
-    create_pixel_message(x, y, r, g, b)
-}
+    fn step(&mut self, scheme: ColorScheme) {
+        self.engine.step();
+        if self.engine.changed_region(scheme).is_some() {
+            self.version += 1;
+        } else {
+            self.settled_version = Some(self.version);
+        }
+    }
 
-pub fn kill_random_cell() -> Message {
-    let (x, y) = { GAME_STATE.write().unwrap().kill_random_cell() };
+    // Nothing redrew a cell since a step that redrew none, so stepping again would too
+    fn is_settled(&self) -> bool {
+        self.settled_version == Some(self.version)
+    }
 
-    debug!(
-        "Killed a random live cell of current generation, x:{}, y:{}, generation_count:{}",
-        x,
-        y,
-        GAME_STATE.read().unwrap().generation_count
-    );
+    // Moves the cells and generation over to an engine of `kind`, cells start over as
+    // newborn unless it's the engine already stepping them
+    fn select_engine(&mut self, kind: EngineKind) {
+        if kind == self.kind() {
+            return;
+        }
+        let (width, height) = self.dimensions();
+        let mut engine = kind.create(width, height);
+        engine.load_packed_bits(&self.to_packed_bits());
+        engine.set_generation_count(self.generation_count());
+        **self = engine;
+    }
 
-    create_pixel_message(
-        x,
-        y,
-        DEAD_CELL_R_G_B[0],
-        DEAD_CELL_R_G_B[1],
-        DEAD_CELL_R_G_B[2],
-    )
-}
+    // Replaces the cells with a random soup at generation 0, drawing from `rng` cell by cell
+    // row by row
+    fn initialize_random(&mut self, rng: &mut dyn RngCore) {
+        let (width, height) = self.dimensions();
+        let cell_count = width as usize * height as usize;
+        let mut bits = vec![0u8; cell_count.div_ceil(8)];
+        for i in 0..cell_count {
+            // 30% chance of a cell being alive initially
+            if rng.random::<f32>() < 0.3 {
+                bits[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        self.load_packed_bits(&bits);
+        self.set_generation_count(0);
+    }
 
-pub fn kill_all_cells() -> Message {
-    {
-        GAME_STATE.write().unwrap().kill_all_cells()
-    };
+    // Replaces the cells with just the in-bounds ones of `live`, at generation 0
+    fn load_live_cells(&mut self, live: impl IntoIterator<Item = (u16, u16)>) {
+        let (width, height) = self.dimensions();
+        self.load_packed_bits(&[]);
+        self.set_generation_count(0);
+        for (x, y) in live {
+            if x < width && y < height {
+                self.set_cell(x, y, true);
+            }
+        }
+    }
+
+    // Sets a cell as part of a user edit, remembering what it was if that changed it
+    fn edit_cell(&mut self, x: u16, y: u16, alive: bool, edit: &mut CellEdit) {
+        let was_alive = self.is_alive(x, y);
+        if was_alive != alive {
+            self.set_cell(x, y, alive);
+            edit.push((x, y, was_alive));
+        }
+    }
 
-    // Convert current state to RGB data
-    let game_state = GAME_STATE.read().unwrap();
-    let frame_data = game_state.to_rgb_data();
+    // Sets the given cell offsets alive relative to (x, y) as part of a user edit, clipping
+    // anything off the grid. Returns the number of cells that landed on the grid.
+    fn stamp_cells(
+        &mut self,
+        x: u16,
+        y: u16,
+        cells: &[(usize, usize)],
+        edit: &mut CellEdit,
+    ) -> usize {
+        let (width, height) = self.dimensions();
+        let mut stamped = 0;
+        for &(dx, dy) in cells {
+            // Offsets past usize are off the grid too
+            let (Some(cx), Some(cy)) = ((x as usize).checked_add(dx), (y as usize).checked_add(dy))
+            else {
+                continue;
+            };
+            if cx < width as usize && cy < height as usize {
+                self.edit_cell(cx as u16, cy as u16, true, edit);
+                stamped += 1;
+            }
+        }
+        stamped
+    }
+}
 
-    debug!(
-        "Killed all cells: current generation {}, {}x{} pixels ({} bytes)",
-        game_state.generation_count,
-        CANVAS_WIDTH,
-        CANVAS_HEIGHT,
-        frame_data.len()
-    );
+impl Deref for GolBoard {
+    type Target = Box<dyn GolEngine>;
 
-    create_frame_message(frame_data)
+    fn deref(&self) -> &Box<dyn GolEngine> {
+        &self.engine
+    }
+}
+
+impl DerefMut for GolBoard {
+    fn deref_mut(&mut self) -> &mut Box<dyn GolEngine> {
+        self.version += 1;
+        &mut self.engine
+    }
 }
 
-pub fn create_new_generation() -> Message {
-    reset_game_of_life_random();
-    let game_state = GAME_STATE.read().unwrap();
-    let frame_data = game_state.to_rgb_data();
+/// Game of Life state of a single room
+pub struct GolState {
+    game: RwLock<GolBoard>,
+    cursor: RwLock<InputCursor>,
+    // Every random choice (seeding, cell picks, colors) draws from here, so a seed
+    // reproduces the same frames. Lock after `game` when holding both.
+    rng: Mutex<StdRng>,
+    // Live cell count of the most recent generations, oldest first, for late joiners.
+    // Updated while `game` is write-locked.
+    population_history: Mutex<VecDeque<u32>>,
+    // How live cells are colored, picked by clients with SET_COLOR_SCHEME
+    color_scheme: RwLock<ColorScheme>,
+    // Painting canvas live cells reveal in hybrid mode, None colors them by `color_scheme`.
+    // Lock after `game` when holding both.
+    painting_layer: RwLock<Option<Vec<u8>>>,
+    // Fed the hash of every generation the broadcaster sends
+    stability: Mutex<StabilityDetector>,
+    // Generations sent as regions since the last full frame
+    regions_since_keyframe: AtomicU32,
+    // User edits UNDO and REDO walk through, locked after `game`
+    edits: Mutex<EditHistory>,
+}
 
-    debug!(
-        "Generated Game of Life frame: generation {}, {}x{} pixels ({} bytes)",
-        game_state.generation_count,
-        CANVAS_WIDTH,
-        CANVAS_HEIGHT,
-        frame_data.len()
-    );
+/// Cells a user edit changed, each with the state it reverts to
+type CellEdit = Vec<(u16, u16, bool)>;
 
-    create_frame_message(frame_data)
+/// User edits of the grid, apart from the generations stepping makes. Undoing an edit
+/// reverts only the cells it changed, whatever the grid went through since.
+#[derive(Default)]
+struct EditHistory {
+    undo: VecDeque<CellEdit>,
+    redo: Vec<CellEdit>,
 }
 
-pub fn advance_generation() -> Message {
-    {
-        // Advance the game by one generation
-        GAME_STATE.write().unwrap().step();
+impl EditHistory {
+    // Remembers the cells an edit changed, dropping what could be redone
+    fn record(&mut self, changed: CellEdit) {
+        if changed.is_empty() {
+            return;
+        }
+        if self.undo.len() == MAX_UNDO_EDITS {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(changed);
+        self.redo.clear();
     }
+}
 
-    // Convert current state to RGB data
-    let game_state = GAME_STATE.read().unwrap();
-    let frame_data = game_state.to_rgb_data();
+/// How live cells are colored for one batch of pixels: by the color scheme, or by the
+/// painting under them in hybrid mode. Holds the layer for as long as it lives.
+struct CellColors<'a> {
+    scheme: ColorScheme,
+    painting: RwLockReadGuard<'a, Option<Vec<u8>>>,
+}
 
-    debug!(
-        "Advanced generation: current generation {}, {}x{} pixels ({} bytes)",
-        game_state.generation_count,
-        CANVAS_WIDTH,
-        CANVAS_HEIGHT,
-        frame_data.len()
-    );
+impl CellColors<'_> {
+    fn cell_rgb(&self, game: &GolBoard, x: u16, y: u16, rng: &mut dyn RngCore) -> [u8; 3] {
+        match self.painting.as_deref() {
+            Some(painting) if game.is_alive(x, y) => {
+                let i = (y as usize * game.dimensions().0 as usize + x as usize) * 3;
+                [painting[i], painting[i + 1], painting[i + 2]]
+            }
+            _ => game.cell_rgb(self.scheme, x, y, rng),
+        }
+    }
 
-    create_frame_message(frame_data)
+    // Color of a viewport pixel covering `zoom` x `zoom` cells from (`left`, `top`): that of
+    // the first live cell in it. Cells past the board's edges are dead.
+    fn block_rgb(
+        &self,
+        game: &GolBoard,
+        (left, top): (usize, usize),
+        zoom: u8,
+        rng: &mut dyn RngCore,
+    ) -> [u8; 3] {
+        let zoom = zoom as usize;
+        let (width, height) = game.dimensions();
+        let live = (top..(top + zoom).min(height as usize))
+            .flat_map(|y| (left..(left + zoom).min(width as usize)).map(move |x| (x, y)))
+            .find(|&(x, y)| game.is_alive(x as u16, y as u16));
+        match live {
+            Some((x, y)) => self.cell_rgb(game, x as u16, y as u16, rng),
+            None => DEAD_CELL_R_G_B,
+        }
+    }
+
+    fn frame_data(&self, game: &GolBoard, rng: &mut dyn RngCore) -> Vec<u8> {
+        match self.painting.as_deref() {
+            Some(painting) => canvas::reveal_live_cells(
+                &game.live_cells(),
+                game.dimensions().0,
+                painting,
+                DEAD_CELL_R_G_B,
+            ),
+            None => game.to_rgb_data(self.scheme, rng),
+        }
+    }
 }
 
-// Utility functions to control Game of Life patterns
-pub fn reset_game_of_life_random() {
-    GAME_STATE.write().unwrap().initialize_random();
-    debug!("Reset Game of Life with random pattern");
+/// What a client joining mid-run needs to render context right away
+pub struct JoinSummary {
+    pub generation: u64,
+    pub populations: Vec<u32>,
+    // Bit-packed keyframe as served by `grid_dump`
+    pub keyframe: Vec<u8>,
 }
 
-#[allow(dead_code)]
-pub fn reset_game_of_life_glider() {
-    GAME_STATE.write().unwrap().initialize_glider();
-    debug!("Reset Game of Life with glider pattern");
+impl GolState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        Self::with_engine(width, height, seed, EngineKind::default())
+    }
+
+    /// A random soup from `seed` stepped by `kind`, which holds the cells from the start so
+    /// a sparse board is never dense in between
+    pub fn with_engine(width: u16, height: u16, seed: u64, kind: EngineKind) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut game = GolBoard::new(kind, width, height);
+        game.initialize_random(&mut rng);
+        let population_history = VecDeque::from([game.population() as u32]);
+        Self {
+            game: RwLock::new(game),
+            rng: Mutex::new(rng),
+            population_history: Mutex::new(population_history),
+            color_scheme: RwLock::new(ColorScheme::default()),
+            painting_layer: RwLock::new(None),
+            stability: Mutex::new(StabilityDetector::default()),
+            regions_since_keyframe: AtomicU32::new(0),
+            edits: Mutex::new(EditHistory::default()),
+            cursor: RwLock::new(InputCursor {
+                x: width / 2,
+                y: height / 2,
+                pen_down: false,
+            }),
+        }
+    }
+
+    fn game(&self) -> RwLockReadGuard<'_, GolBoard> {
+        if self.game.is_poisoned() {
+            self.recover_game();
+        }
+        self.game.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn game_mut(&self) -> RwLockWriteGuard<'_, GolBoard> {
+        if self.game.is_poisoned() {
+            self.recover_game();
+        }
+        self.game.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Takes the grid on as a panicking handler left it, its cells are a valid board even
+    // mid-step
+    fn recover_game(&self) {
+        let game = self.game.write().unwrap_or_else(|e| e.into_inner());
+        self.game.clear_poison();
+        warn!(
+            "Recovered the Game of Life grid at generation {} after a panic",
+            game.generation_count()
+        );
+    }
+
+    // Makes the board unsettled, for changes to how cells are drawn rather than to the cells
+    fn mark_redrawn(&self) {
+        self.game_mut().version += 1;
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Appends the population of a new generation, or restarts the curve when the
+    // grid was replaced wholesale
+    fn record_population(&self, game: &GolBoard, restart: bool) {
+        let mut history = self
+            .population_history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if restart {
+            history.clear();
+        }
+        if history.len() == POPULATION_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(game.population() as u32);
+    }
+
+    /// Restarts the random stream from `seed` and creates a fresh generation from it
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        self.reseed_rng(seed);
+        self.create_new_generation()
+    }
+
+    /// Restarts the random stream from `seed`, leaving the grid as it is
+    pub fn reseed_rng(&self, seed: u64) {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        debug!("Reseeded Game of Life with {}", seed);
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        *self.color_scheme.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Switches how live cells are colored and redraws the whole generation with it
+    pub fn set_color_scheme(&self, scheme: ColorScheme) -> Result<Message, FrameError> {
+        *self.color_scheme.write().unwrap_or_else(|e| e.into_inner()) = scheme;
+        self.mark_redrawn();
+        debug!("Game of Life color scheme set to {:?}", scheme);
+        self.current_generation()
+    }
+
+    pub fn engine(&self) -> EngineKind {
+        self.game().kind()
+    }
+
+    /// Steps the grid with `kind` from now on. Every engine steps the same rule, so the
+    /// board carries on as it would have. Boards too large for `kind` keep their engine,
+    /// returns whether it was selected.
+    pub fn select_engine(&self, kind: EngineKind) -> bool {
+        let mut game = self.game_mut();
+        let (width, height) = game.dimensions();
+        let max_side = max_canvas_side(kind);
+        if width > max_side || height > max_side {
+            warn!(
+                "Keeping the {} engine, {}x{} cells is too large for {}",
+                game.kind(),
+                width,
+                height,
+                kind
+            );
+            return false;
+        }
+        game.select_engine(kind);
+        debug!("Game of Life engine set to {}", kind);
+        true
+    }
+
+    fn cell_colors(&self) -> CellColors<'_> {
+        CellColors {
+            scheme: self.color_scheme(),
+            painting: self
+                .painting_layer
+                .read()
+                .unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    pub fn is_hybrid(&self) -> bool {
+        self.painting_layer
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Turns hybrid mode on with the painting's RGB data as the layer live cells reveal, or
+    /// off with None, and redraws the whole generation
+    pub fn set_painting_layer(&self, painting: Option<Vec<u8>>) -> Result<Message, FrameError> {
+        let painting = painting.filter(|painting| self.fits_grid(painting));
+        *self
+            .painting_layer
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = painting;
+        self.mark_redrawn();
+        debug!(
+            "Game of Life hybrid mode {}",
+            if self.is_hybrid() { "on" } else { "off" }
+        );
+        self.current_generation()
+    }
+
+    /// Replaces the layer of a room in hybrid mode with the painting as it is now. A changed
+    /// painting makes the next generation a full frame, survivors reveal it too.
+    pub fn refresh_painting_layer(&self, painting: Vec<u8>) {
+        if !self.is_hybrid() || !self.fits_grid(&painting) {
+            return;
+        }
+        let mut layer = self
+            .painting_layer
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if layer.as_ref().is_none_or(|current| *current == painting) {
+            return;
+        }
+        *layer = Some(painting);
+        drop(layer);
+        self.mark_redrawn();
+        self.request_keyframe();
+    }
+
+    fn fits_grid(&self, painting: &[u8]) -> bool {
+        let (width, height) = self.dimensions();
+        let fits = painting.len() == width as usize * height as usize * 3;
+        if !fits {
+            warn!(
+                "Dropping {}-byte painting layer for a {}x{} grid",
+                painting.len(),
+                width,
+                height
+            );
+        }
+        fits
+    }
+
+    /// Grid width and height in cells
+    pub fn dimensions(&self) -> (u16, u16) {
+        self.game().dimensions()
+    }
+
+    pub fn current_generation(&self) -> Result<Message, FrameError> {
+        let game_state = self.game();
+        let frame_data = self.cell_colors().frame_data(&game_state, &mut *self.rng());
+
+        let (width, height) = game_state.dimensions();
+        create_frame_message(width, height, frame_data)
+    }
+
+    /// Frame of the cells under `viewport`, each pixel colored like the first live cell it
+    /// covers
+    pub fn viewport_frame(&self, viewport: &Viewport) -> Result<Message, FrameError> {
+        let game = self.game();
+        let colors = self.cell_colors();
+        // Rendered per connection, so it doesn't draw from the seeded stream
+        let mut rng = rand::rng();
+
+        let mut rgb_data =
+            Vec::with_capacity(viewport.width as usize * viewport.height as usize * 3);
+        for row in 0..viewport.height {
+            for column in 0..viewport.width {
+                let cell = viewport.cell_at(column, row);
+                rgb_data.extend(colors.block_rgb(&game, cell, viewport.zoom, &mut rng));
+            }
+        }
+        create_frame_message(viewport.width, viewport.height, rgb_data)
+    }
+
+    /// Every tile `viewport` overlaps, rendered like `viewport_frame`
+    pub fn viewport_tiles(&self, viewport: &Viewport) -> Vec<Tile> {
+        let game = self.game();
+        let colors = self.cell_colors();
+        let mut rng = rand::rng();
+        let zoom = viewport.zoom as usize;
+
+        viewport
+            .visible_tiles(game.dimensions())
+            .into_iter()
+            .map(|(column, row)| {
+                let (left, top) = viewport.tile_origin(column, row);
+                let mut rgb_data = Vec::with_capacity(TILE_SIDE as usize * TILE_SIDE as usize * 3);
+                for y in 0..TILE_SIDE as usize {
+                    for x in 0..TILE_SIDE as usize {
+                        let cell = (left + x * zoom, top + y * zoom);
+                        rgb_data.extend(colors.block_rgb(&game, cell, viewport.zoom, &mut rng));
+                    }
+                }
+                Tile {
+                    column,
+                    row,
+                    rgb_data,
+                }
+            })
+            .collect()
+    }
+
+    pub fn awaken_random_cell(&self) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut rng = self.rng();
+        let (width, height) = game_state.dimensions();
+        let x = rng.random_range(0..width);
+        let y = rng.random_range(0..height);
+        let mut edit = CellEdit::new();
+        game_state.edit_cell(x, y, true, &mut edit);
+        self.record_edit(edit);
+
+        debug!(
+            "Added a random live cell to current generation, x:{}, y:{}, generation_count:{}",
+            x,
+            y,
+            game_state.generation_count()
+        );
+
+        let [r, g, b] = self.cell_colors().cell_rgb(&game_state, x, y, &mut *rng);
+
+        create_pixel_message(x, y, r, g, b)
+    }
+
+    pub fn awaken_cell(&self, x: u16, y: u16) -> Result<Message, FrameError> {
+        self.awaken_cell_colored(x, y, None)
+    }
+
+    /// Awakens a cell drawn in `rgb`, or in the color scheme's color when None
+    pub fn awaken_cell_colored(
+        &self,
+        x: u16,
+        y: u16,
+        rgb: Option<[u8; 3]>,
+    ) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        game_state.edit_cell(x, y, true, &mut edit);
+        self.record_edit(edit);
+
+        debug!(
+            "Added a live cell to current generation, x:{}, y:{}, generation_count:{}",
+            x,
+            y,
+            game_state.generation_count()
+        );
+
+        let [r, g, b] = rgb.unwrap_or_else(|| {
+            self.cell_colors()
+                .cell_rgb(&game_state, x, y, &mut *self.rng())
+        });
+
+        create_pixel_message(x, y, r, g, b)
+    }
+
+    /// Awakens every in-bounds cell of a stroke and returns them as one batched pixel message
+    pub fn awaken_cells(&self, cells: &[(u16, u16)]) -> Message {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let (width, height) = game_state.dimensions();
+        let colors = self.cell_colors();
+        let mut rng = self.rng();
+        let pixels: Vec<_> = cells
+            .iter()
+            .filter(|&&(x, y)| x < width && y < height)
+            .map(|&(x, y)| {
+                game_state.edit_cell(x, y, true, &mut edit);
+                (x, y, colors.cell_rgb(&game_state, x, y, &mut *rng))
+            })
+            .collect();
+        self.record_edit(edit);
+
+        debug!(
+            "Added {} of {} batched live cells to current generation, generation_count:{}",
+            pixels.len(),
+            cells.len(),
+            game_state.generation_count()
+        );
+
+        create_pixels_message(&pixels)
+    }
+
+    /// Awakens the cells a brush covers along a stroke and returns them as batched pixels,
+    /// or as a full frame if too many came alive for one batch
+    pub fn paint_brush(&self, points: &[(u16, u16)], brush: &Brush) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let (width, height) = game_state.dimensions();
+        let colors = self.cell_colors();
+        let mut rng = self.rng();
+        let mut pixels = Vec::new();
+        for &(x, y) in points {
+            for (cx, cy) in brush.footprint(x, y, width, height) {
+                let alive = game_state.is_alive(cx, cy);
+                if !alive && (brush.density == u8::MAX || rng.random::<u8>() < brush.density) {
+                    game_state.edit_cell(cx, cy, true, &mut edit);
+                    pixels.push((cx, cy, colors.cell_rgb(&game_state, cx, cy, &mut *rng)));
+                }
+            }
+        }
+        self.record_edit(edit);
+
+        debug!(
+            "Painted {} live cells along a {}-point brush stroke, generation_count:{}",
+            pixels.len(),
+            points.len(),
+            game_state.generation_count()
+        );
+
+        if pixels.len() > u16::MAX as usize {
+            let frame_data = colors.frame_data(&game_state, &mut *rng);
+            return create_frame_message(width, height, frame_data);
+        }
+        Ok(create_pixels_message(&pixels))
+    }
+
+    pub fn kill_cell(&self, x: u16, y: u16) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        game_state.edit_cell(x, y, false, &mut edit);
+        self.record_edit(edit);
+
+        debug!(
+            "Killed a cell of current generation, x:{}, y:{}, generation_count:{}",
+            x,
+            y,
+            game_state.generation_count()
+        );
+
+        create_pixel_message(
+            x,
+            y,
+            DEAD_CELL_R_G_B[0],
+            DEAD_CELL_R_G_B[1],
+            DEAD_CELL_R_G_B[2],
+        )
+    }
+
+    pub fn kill_random_cell(&self) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let (width, height) = game_state.dimensions();
+        let (x, y) = {
+            let mut rng = self.rng();
+            (rng.random_range(0..width), rng.random_range(0..height))
+        };
+        let mut edit = CellEdit::new();
+        game_state.edit_cell(x, y, false, &mut edit);
+        self.record_edit(edit);
+
+        debug!(
+            "Killed a random live cell of current generation, x:{}, y:{}, generation_count:{}",
+            x,
+            y,
+            game_state.generation_count()
+        );
+
+        create_pixel_message(
+            x,
+            y,
+            DEAD_CELL_R_G_B[0],
+            DEAD_CELL_R_G_B[1],
+            DEAD_CELL_R_G_B[2],
+        )
+    }
+
+    pub fn kill_all_cells(&self) -> Result<Message, FrameError> {
+        {
+            let mut game = self.game_mut();
+            game.load_live_cells([]);
+            self.record_population(&game, true);
+        };
+
+        // Convert current state to RGB data
+        let game_state = self.game();
+        let frame_data = self.cell_colors().frame_data(&game_state, &mut *self.rng());
+        let (width, height) = game_state.dimensions();
+
+        debug!(
+            "Killed all cells: current generation {}, {}x{} pixels ({} bytes)",
+            game_state.generation_count(),
+            width,
+            height,
+            frame_data.len()
+        );
+
+        create_frame_message(width, height, frame_data)
+    }
+
+    pub fn create_new_generation(&self) -> Result<Message, FrameError> {
+        self.reset_game_of_life_random();
+        let game_state = self.game();
+        let frame_data = self.cell_colors().frame_data(&game_state, &mut *self.rng());
+        let (width, height) = game_state.dimensions();
+
+        debug!(
+            "Generated Game of Life frame: generation {}, {}x{} pixels ({} bytes)",
+            game_state.generation_count(),
+            width,
+            height,
+            frame_data.len()
+        );
+
+        create_frame_message(width, height, frame_data)
+    }
+
+    /// Advances one generation like `advance_generation` but returns only the cells that
+    /// were born or died as batched pixels, or the full frame if they don't fit one batch.
+    /// Survivors keep the color they were last drawn with.
+    pub fn advance_generation_delta(&self) -> Result<Message, FrameError> {
+        let scheme = self.color_scheme();
+        let mut game = self.game_mut();
+        game.step(scheme);
+        self.record_population(&game, false);
+
+        let colors = self.cell_colors();
+        let mut rng = self.rng();
+        let pixels: Vec<_> = game
+            .changed_cells()
+            .into_iter()
+            .map(|(x, y)| (x, y, colors.cell_rgb(&game, x, y, &mut *rng)))
+            .collect();
+
+        debug!(
+            "Advanced generation: current generation {}, {} changed cells",
+            game.generation_count(),
+            pixels.len()
+        );
+
+        if pixels.len() > u16::MAX as usize {
+            let frame_data = colors.frame_data(&game, &mut *rng);
+            let (width, height) = game.dimensions();
+            return create_frame_message(width, height, frame_data);
+        }
+        Ok(create_pixels_message(&pixels))
+    }
+
+    /// Makes the next generation go out as a full frame, for clients that may be showing
+    /// something else where the region wouldn't cover it
+    pub fn request_keyframe(&self) {
+        self.regions_since_keyframe
+            .store(REGION_KEYFRAME_EVERY, Ordering::Relaxed);
+    }
+
+    /// Advances one generation, returning the region of the cells it redrew when activity
+    /// is localized, or the full frame
+    pub fn advance_generation(&self) -> Result<Message, FrameError> {
+        {
+            // Advance the game by one generation
+            let scheme = self.color_scheme();
+            let mut game = self.game_mut();
+            game.step(scheme);
+            self.record_population(&game, false);
+        }
+
+        let game_state = self.game();
+        let colors = self.cell_colors();
+        let region = game_state.changed_region(colors.scheme);
+        let (width, height) = game_state.dimensions();
+        let grid_area = width as usize * height as usize;
+        let localized =
+            region.is_none_or(|region| region.area() * 100 <= grid_area * REGION_MAX_AREA_PERCENT);
+        if localized && self.regions_since_keyframe.load(Ordering::Relaxed) < REGION_KEYFRAME_EVERY
+        {
+            self.regions_since_keyframe.fetch_add(1, Ordering::Relaxed);
+            // An empty region still marks the generation for clients
+            let region = region.unwrap_or(Region {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            });
+            debug!(
+                "Advanced generation: current generation {}, {}x{} region at ({}, {})",
+                game_state.generation_count(),
+                region.width,
+                region.height,
+                region.x,
+                region.y
+            );
+            return Ok(self.region_message(&game_state, &colors, region));
+        }
+
+        self.regions_since_keyframe.store(0, Ordering::Relaxed);
+        let frame_data = colors.frame_data(&game_state, &mut *self.rng());
+
+        debug!(
+            "Advanced generation: current generation {}, {}x{} pixels ({} bytes)",
+            game_state.generation_count(),
+            width,
+            height,
+            frame_data.len()
+        );
+
+        create_frame_message(width, height, frame_data)
+    }
+
+    // Region message redrawing the cells of `region` as they are now
+    fn region_message(&self, game: &GolBoard, colors: &CellColors, region: Region) -> Message {
+        let mut rng = self.rng();
+        let rgb_data = (region.y..region.y + region.height)
+            .flat_map(|y| (region.x..region.x + region.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| colors.cell_rgb(game, x, y, &mut *rng))
+            .collect();
+        create_region_message(region, rgb_data)
+    }
+
+    /// Brings a dead cell to life or kills a live one, returning the cell as a region
+    pub fn toggle_cell(&self, x: u16, y: u16) -> Message {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let alive = !game_state.is_alive(x, y);
+        game_state.edit_cell(x, y, alive, &mut edit);
+        self.record_edit(edit);
+
+        debug!(
+            "Toggled a cell of current generation, x:{}, y:{}, generation_count:{}",
+            x,
+            y,
+            game_state.generation_count()
+        );
+
+        self.region_message(&game_state, &self.cell_colors(), Region::cell(x, y))
+    }
+
+    /// Awakens or kills every cell of `region`, clipped to the grid, returning the clipped
+    /// region redrawn
+    pub fn fill_region(&self, region: Region, alive: bool) -> Message {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let (width, height) = game_state.dimensions();
+        let region = Region {
+            width: region.width.min(width.saturating_sub(region.x)),
+            height: region.height.min(height.saturating_sub(region.y)),
+            ..region
+        };
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                game_state.edit_cell(x, y, alive, &mut edit);
+            }
+        }
+        self.record_edit(edit);
+
+        debug!(
+            "{} a {}x{} region at ({}, {}), generation_count:{}",
+            if alive { "Filled" } else { "Cleared" },
+            region.width,
+            region.height,
+            region.x,
+            region.y,
+            game_state.generation_count()
+        );
+
+        self.region_message(&game_state, &self.cell_colors(), region)
+    }
+
+    // Remembers what a user edit changed, for UNDO
+    fn record_edit(&self, edit: CellEdit) {
+        self.edits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(edit);
+    }
+
+    /// Reverts the cells of the newest user edit, returning them redrawn, or None if there's
+    /// nothing to undo
+    pub fn undo(&self) -> Result<Option<Message>, FrameError> {
+        self.revert_edit(true)
+    }
+
+    /// Applies the newest undone edit again, returning its cells redrawn, or None if there's
+    /// nothing to redo
+    pub fn redo(&self) -> Result<Option<Message>, FrameError> {
+        self.revert_edit(false)
+    }
+
+    // Restores the cells of the newest edit on one stack, pushing their current states to
+    // the other so the revert can be reverted in turn
+    fn revert_edit(&self, undo: bool) -> Result<Option<Message>, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut edits = self.edits.lock().unwrap_or_else(|e| e.into_inner());
+        let edit = if undo {
+            edits.undo.pop_back()
+        } else {
+            edits.redo.pop()
+        };
+        let Some(edit) = edit else {
+            return Ok(None);
+        };
+
+        let inverse = edit
+            .iter()
+            .map(|&(x, y, _)| (x, y, game_state.is_alive(x, y)))
+            .collect();
+        for &(x, y, alive) in &edit {
+            game_state.set_cell(x, y, alive);
+        }
+        if undo {
+            edits.redo.push(inverse);
+        } else {
+            edits.undo.push_back(inverse);
+        }
+
+        debug!(
+            "{} an edit of {} cells, generation_count:{}",
+            if undo { "Undid" } else { "Redid" },
+            edit.len(),
+            game_state.generation_count()
+        );
+
+        let colors = self.cell_colors();
+        let mut rng = self.rng();
+        if edit.len() > u16::MAX as usize {
+            let frame_data = colors.frame_data(&game_state, &mut *rng);
+            let (width, height) = game_state.dimensions();
+            return create_frame_message(width, height, frame_data).map(Some);
+        }
+        let pixels: Vec<_> = edit
+            .iter()
+            .map(|&(x, y, _)| (x, y, colors.cell_rgb(&game_state, x, y, &mut *rng)))
+            .collect();
+        Ok(Some(create_pixels_message(&pixels)))
+    }
+
+    pub fn load_pattern(
+        &self,
+        x: u16,
+        y: u16,
+        pattern: &RlePattern,
+    ) -> Result<Message, FrameError> {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let stamped = game_state.stamp_cells(x, y, &pattern.cells, &mut edit);
+        self.record_edit(edit);
+        let frame_data = self.cell_colors().frame_data(&game_state, &mut *self.rng());
+
+        debug!(
+            "Loaded {}x{} pattern at x:{}, y:{} ({} of {} cells on grid), generation_count:{}",
+            pattern.width,
+            pattern.height,
+            x,
+            y,
+            stamped,
+            pattern.cells.len(),
+            game_state.generation_count()
+        );
+
+        let (width, height) = game_state.dimensions();
+        create_frame_message(width, height, frame_data)
+    }
+
+    /// Stamps a pattern without touching the rest of the grid and returns only the
+    /// stamped cells as one batched pixel message
+    pub fn stamp_pattern(&self, x: u16, y: u16, pattern: &RlePattern) -> Message {
+        let mut game_state = self.game_mut();
+        let mut edit = CellEdit::new();
+        let stamped = game_state.stamp_cells(x, y, &pattern.cells, &mut edit);
+        self.record_edit(edit);
+
+        let (width, height) = game_state.dimensions();
+        let (width, height) = (width as usize, height as usize);
+        let colors = self.cell_colors();
+        let mut rng = self.rng();
+        let pixels: Vec<_> = pattern
+            .cells
+            .iter()
+            .map(|&(dx, dy)| (x as usize + dx, y as usize + dy))
+            .filter(|&(cx, cy)| cx < width && cy < height)
+            .map(|(cx, cy)| {
+                let (cx, cy) = (cx as u16, cy as u16);
+                (cx, cy, colors.cell_rgb(&game_state, cx, cy, &mut *rng))
+            })
+            .collect();
+
+        debug!(
+            "Stamped {}x{} pattern at x:{}, y:{} ({} of {} cells on grid), generation_count:{}",
+            pattern.width,
+            pattern.height,
+            x,
+            y,
+            stamped,
+            pattern.cells.len(),
+            game_state.generation_count()
+        );
+
+        create_pixels_message(&pixels)
+    }
+
+    pub fn seed_from_painting(&self, mlp: &MlpState) -> Result<Message, FrameError> {
+        let frame = self.seed_from_rgb(&mlp.painting_rgb_data(), CROSSOVER_LUMINANCE_THRESHOLD);
+        debug!("Seeded Game of Life from painting");
+        frame
+    }
+
+    /// Seeds a new generation from a PNG or JPEG scaled to the grid, pixels darker than
+    /// `threshold` coming alive
+    pub fn seed_from_image(&self, data: &[u8], threshold: u8) -> Result<Message> {
+        let (width, height) = self.dimensions();
+        let pixels = canvas::decode_image(data, width as usize, height as usize)?;
+        let frame = self.seed_from_rgb(pixels.as_flattened(), threshold)?;
+        debug!(
+            "Seeded Game of Life from an uploaded image, luminance threshold {}",
+            threshold
+        );
+        Ok(frame)
+    }
+
+    // Replaces the grid with the pixels of `rgb_data` darker than `threshold` as live cells
+    fn seed_from_rgb(&self, rgb_data: &[u8], threshold: u8) -> Result<Message, FrameError> {
+        let (width, height) = self.dimensions();
+        let cells = canvas::rgb_to_cells(rgb_data, width as usize, height as usize, threshold);
+
+        {
+            let mut game = self.game_mut();
+            game.load_live_cells(
+                canvas::cells_to_points(&cells)
+                    .into_iter()
+                    .map(|(x, y)| (x as u16, y as u16)),
+            );
+            self.record_population(&game, true);
+        }
+
+        let game_state = self.game();
+        let frame_data = self.cell_colors().frame_data(&game_state, &mut *self.rng());
+
+        debug!(
+            "Seeded {}x{} pixels ({} bytes), {} live cells",
+            width,
+            height,
+            frame_data.len(),
+            game_state.population()
+        );
+
+        create_frame_message(width, height, frame_data)
+    }
+
+    /// Input mapping: arrows move the cursor, holding primary draws live cells along
+    /// the way and secondary kills the cell under the cursor
+    pub fn handle_input(&self, event: &InputEvent) -> Result<Option<Message>, FrameError> {
+        let (width, height) = self.dimensions();
+        let (x, y, pen_down) = {
+            let mut cursor = self.cursor.write().unwrap_or_else(|e| e.into_inner());
+            match (event.key, event.state) {
+                (input_keys::UP, KeyState::Pressed) => cursor.y = cursor.y.saturating_sub(1),
+                (input_keys::DOWN, KeyState::Pressed) => cursor.y = (cursor.y + 1).min(height - 1),
+                (input_keys::LEFT, KeyState::Pressed) => cursor.x = cursor.x.saturating_sub(1),
+                (input_keys::RIGHT, KeyState::Pressed) => cursor.x = (cursor.x + 1).min(width - 1),
+                (input_keys::PRIMARY, state) => cursor.pen_down = state == KeyState::Pressed,
+                (input_keys::SECONDARY, KeyState::Pressed) => {
+                    return self.kill_cell(cursor.x, cursor.y).map(Some);
+                }
+                _ => return Ok(None),
+            }
+            (cursor.x, cursor.y, cursor.pen_down)
+        };
+
+        debug!(
+            "GOL input cursor at x:{}, y:{}, pen_down:{}",
+            x, y, pen_down
+        );
+
+        pen_down.then(|| self.awaken_cell(x, y)).transpose()
+    }
+
+    /// Number of cells the engine updates per generation
+    pub fn cell_count(&self) -> usize {
+        let (width, height) = self.dimensions();
+        width as usize * height as usize
+    }
+
+    /// Current generation number and live cell count
+    pub fn generation_stats(&self) -> (u64, usize) {
+        let game_state = self.game();
+        (game_state.generation_count(), game_state.population())
+    }
+
+    /// Current generation number and the hash of its packed bits
+    pub fn generation_hash(&self) -> (u64, u32) {
+        let game_state = self.game();
+        (
+            game_state.generation_count(),
+            generation_hash(&game_state.to_packed_bits()),
+        )
+    }
+
+    /// Whether the last step redrew no cell and nothing changed since, so the next steps
+    /// would only send the same picture again. A still life settles once its colors do.
+    pub fn is_settled(&self) -> bool {
+        self.game().is_settled()
+    }
+
+    /// Checks the hash of the generation just broadcast for a still life or short
+    /// oscillator. Returns its period the first time the board is found stable.
+    pub fn detect_stability(&self, hash: u32) -> Option<u8> {
+        self.stability
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .observe(hash)
+    }
+
+    /// Whether a panic left the grid's lock poisoned, until `try_restore` recovers it
+    pub fn is_poisoned(&self) -> bool {
+        self.game.is_poisoned()
+    }
+
+    /// Copy of the whole engine, waiting for the lock if needed
+    pub fn snapshot(&self) -> Box<dyn GolEngine> {
+        self.game().boxed_clone()
+    }
+
+    /// Copy of the whole engine, or None if the state is currently locked or poisoned
+    pub fn try_snapshot(&self) -> Option<Box<dyn GolEngine>> {
+        self.game.try_read().ok().map(|game| game.boxed_clone())
+    }
+
+    /// Replaces the engine with a snapshot, recovering a poisoned lock.
+    /// Returns false if the lock is held elsewhere.
+    pub fn try_restore(&self, snapshot: Box<dyn GolEngine>) -> bool {
+        let mut game = match self.game.try_write() {
+            Ok(game) => game,
+            Err(TryLockError::Poisoned(poisoned)) => {
+                self.game.clear_poison();
+                poisoned.into_inner()
+            }
+            Err(TryLockError::WouldBlock) => return false,
+        };
+        **game = snapshot;
+        debug!(
+            "Restored Game of Life snapshot at generation {}",
+            game.generation_count()
+        );
+        true
+    }
+
+    /// Bit-packed dump of the current generation:
+    /// [magic "GOLB"][version u8][width u16][height u16][generation u64][bits], big-endian,
+    /// bits as in `GolEngine::to_packed_bits`
+    pub fn grid_dump(&self) -> Vec<u8> {
+        Self::dump_grid(&self.game())
+    }
+
+    fn dump_grid(game_state: &GolBoard) -> Vec<u8> {
+        let bits = game_state.to_packed_bits();
+        let (width, height) = game_state.dimensions();
+
+        let mut buf = Vec::with_capacity(GRID_DUMP_MAGIC.len() + 13 + bits.len());
+        buf.extend(GRID_DUMP_MAGIC);
+        buf.push(GRID_DUMP_VERSION);
+        buf.extend(width.to_be_bytes());
+        buf.extend(height.to_be_bytes());
+        buf.extend(game_state.generation_count().to_be_bytes());
+        buf.extend(bits);
+        buf
+    }
+
+    /// Replaces the grid with a `grid_dump` of a grid with the same dimensions
+    pub fn load_grid_dump(&self, dump: &[u8]) -> Result<()> {
+        let header_length = GRID_DUMP_MAGIC.len() + 13;
+        if dump.len() < header_length || &dump[..4] != GRID_DUMP_MAGIC {
+            bail!("Not a grid dump");
+        }
+        if dump[4] != GRID_DUMP_VERSION {
+            bail!("Unsupported grid dump version {}", dump[4]);
+        }
+        let width = u16::from_be_bytes([dump[5], dump[6]]);
+        let height = u16::from_be_bytes([dump[7], dump[8]]);
+        let generation = u64::from_be_bytes(dump[9..17].try_into()?);
+
+        let mut game = self.game_mut();
+        if (width, height) != game.dimensions() {
+            let (grid_width, grid_height) = game.dimensions();
+            bail!(
+                "Grid dump of {}x{} doesn't fit the {}x{} grid",
+                width,
+                height,
+                grid_width,
+                grid_height
+            );
+        }
+        let bits = &dump[header_length..];
+        let cell_count = width as usize * height as usize;
+        if bits.len() != cell_count.div_ceil(8) {
+            bail!("Grid dump carries {} bytes of cells", bits.len());
+        }
+
+        game.load_packed_bits(bits);
+        game.set_generation_count(generation);
+        self.record_population(&game, true);
+        debug!("Loaded grid dump at generation {}", generation);
+        Ok(())
+    }
+
+    /// Steps without rendering frames until the grid reaches `generation`
+    pub fn fast_forward(&self, generation: u64) {
+        let scheme = self.color_scheme();
+        let mut game = self.game_mut();
+        while game.generation_count() < generation {
+            game.step(scheme);
+            self.record_population(&game, false);
+        }
+    }
+
+    /// Generation, recent population curve and keyframe, taken under one lock so they agree
+    pub fn join_summary(&self) -> JoinSummary {
+        let game_state = self.game();
+        let populations = self
+            .population_history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .copied()
+            .collect();
+
+        JoinSummary {
+            generation: game_state.generation_count(),
+            populations,
+            keyframe: Self::dump_grid(&game_state),
+        }
+    }
+
+    /// Snapshot of the live/dead grid of the current generation
+    pub fn generation_cells(&self) -> Vec<Vec<bool>> {
+        cell_grid(self.game().as_ref())
+    }
+
+    // Utility functions to control Game of Life patterns
+    pub fn reset_game_of_life_random(&self) {
+        let mut game = self.game_mut();
+        game.initialize_random(&mut *self.rng());
+        self.record_population(&game, true);
+        debug!("Reset Game of Life with random pattern");
+    }
+
+    #[allow(dead_code)]
+    pub fn reset_game_of_life_glider(&self) {
+        // Top-left corner
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        self.game_mut().load_live_cells(glider);
+        debug!("Reset Game of Life with glider pattern");
+    }
+
+    #[allow(dead_code)]
+    pub fn reset_game_of_life_blinker(&self) {
+        let mut game = self.game_mut();
+        let (width, height) = game.dimensions();
+        let (center_x, center_y) = (width / 2, height / 2);
+        // Centered, unless the grid is too small for one
+        let blinker = (center_x > 0 && center_y > 0 && center_x < width - 1).then(|| {
+            [
+                (center_x - 1, center_y),
+                (center_x, center_y),
+                (center_x + 1, center_y),
+            ]
+        });
+        game.load_live_cells(blinker.into_iter().flatten());
+        debug!("Reset Game of Life with blinker pattern");
+    }
 }
 
-#[allow(dead_code)]
-pub fn reset_game_of_life_blinker() {
-    GAME_STATE.write().unwrap().initialize_blinker();
-    debug!("Reset Game of Life with blinker pattern");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH, LIVE_CELL_R_G_B, message_types},
+        protocol::WsMessage,
+    };
+
+    #[test]
+    fn crossover_round_trips_cells_through_a_painting() {
+        let gol = GolState::new(8, 8, 0);
+        gol.kill_all_cells().unwrap();
+        gol.awaken_cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2), (7, 7)]);
+
+        let mlp = MlpState::new(8, 8, 1);
+        mlp.paint_from_generation(&gol).unwrap();
+        mlp.fast_forward_painting().unwrap();
+        let rgb = mlp.painting_rgb_data();
+        assert_eq!(rgb[3..6], LIVE_CELL_R_G_B);
+        assert!(canvas::luminance([rgb[0], rgb[1], rgb[2]]) >= CROSSOVER_LUMINANCE_THRESHOLD);
+
+        let seeded = GolState::new(8, 8, 2);
+        seeded.seed_from_painting(&mlp).unwrap();
+        assert_eq!(seeded.generation_cells(), gol.generation_cells());
+    }
+
+    #[test]
+    fn grid_dump_layout() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+        gol.awaken_cell(0, 0).unwrap();
+        gol.awaken_cell(1, 0).unwrap();
+        gol.awaken_cell(DEFAULT_CANVAS_WIDTH - 1, DEFAULT_CANVAS_HEIGHT - 1)
+            .unwrap();
+
+        let dump = gol.grid_dump();
+        assert_eq!(&dump[..4], GRID_DUMP_MAGIC);
+        assert_eq!(dump[4], GRID_DUMP_VERSION);
+        assert_eq!(u16::from_be_bytes([dump[5], dump[6]]), DEFAULT_CANVAS_WIDTH);
+        assert_eq!(
+            u16::from_be_bytes([dump[7], dump[8]]),
+            DEFAULT_CANVAS_HEIGHT
+        );
+
+        let bits = &dump[17..];
+        assert_eq!(
+            bits.len(),
+            (DEFAULT_CANVAS_WIDTH as usize * DEFAULT_CANVAS_HEIGHT as usize).div_ceil(8)
+        );
+        assert_eq!(bits[0], 0b1100_0000);
+        assert_eq!(bits[bits.len() - 1], 0b0000_0001);
+    }
+
+    #[test]
+    fn grid_dump_roundtrip() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 3);
+        gol.fast_forward(4);
+        let dump = gol.grid_dump();
+
+        let restored = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 9);
+        restored.load_grid_dump(&dump).unwrap();
+        assert_eq!(restored.generation_hash(), gol.generation_hash());
+
+        let smaller = GolState::new(10, 10, 0);
+        assert!(smaller.load_grid_dump(&dump).is_err());
+        assert!(restored.load_grid_dump(&dump[..20]).is_err());
+    }
+
+    #[test]
+    fn awaken_cells_batch() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+
+        let message = gol.awaken_cells(&[(0, 0), (5, 7), (DEFAULT_CANVAS_WIDTH, 0)]);
+        let payload = &message.as_payload()[7..];
+        // The out-of-bounds cell is skipped
+        assert_eq!(&payload[..2], &2u16.to_be_bytes());
+        assert_eq!(&payload[2 + 7..2 + 11], &[0, 5, 0, 7]);
+        assert_eq!(gol.generation_stats().1, 2);
+    }
+
+    #[test]
+    fn brush_stroke_paints_footprints_once() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+        let (brush, _) = Brush::decode_prefix(&[1, 0, 255]).unwrap();
+
+        // Overlapping 3x3 squares cover 12 cells
+        let message = gol.paint_brush(&[(10, 10), (11, 10)], &brush).unwrap();
+        let payload = &message.as_payload()[7..];
+        assert_eq!(&payload[..2], &12u16.to_be_bytes());
+        assert_eq!(gol.generation_stats().1, 12);
+    }
+
+    #[test]
+    fn join_summary_tracks_population() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+        // Blinker keeps three cells alive every generation
+        gol.awaken_cell(10, 9).unwrap();
+        gol.awaken_cell(10, 10).unwrap();
+        gol.awaken_cell(10, 11).unwrap();
+        gol.advance_generation().unwrap();
+        gol.advance_generation().unwrap();
+
+        let summary = gol.join_summary();
+        assert_eq!(summary.populations, vec![0, 3, 3]);
+        assert_eq!(summary.keyframe, gol.grid_dump());
+        assert_eq!(summary.generation, gol.generation_stats().0);
+
+        for _ in 0..POPULATION_HISTORY_LEN {
+            gol.advance_generation().unwrap();
+        }
+        assert_eq!(gol.join_summary().populations.len(), POPULATION_HISTORY_LEN);
+    }
+
+    #[test]
+    fn delta_generation_carries_changed_cells() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+        // Blinker: the horizontal bar turns vertical, its ends die and two cells are born
+        for x in 10..13 {
+            gol.awaken_cell(x, 10).unwrap();
+        }
+
+        let message = gol.advance_generation_delta().unwrap();
+        assert_eq!(message.as_payload()[1], message_types::DRAW_PIXELS_BATCH);
+        let payload = &message.as_payload()[7..];
+        assert_eq!(&payload[..2], &4u16.to_be_bytes());
+        assert_eq!(gol.generation_stats().1, 3);
+    }
+
+    #[test]
+    fn localized_generations_send_regions() {
+        let gol = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0);
+        gol.kill_all_cells().unwrap();
+        for x in 10..13 {
+            gol.awaken_cell(x, 10).unwrap();
+        }
+
+        let message = gol.advance_generation().unwrap();
+        assert_eq!(message.as_payload()[1], message_types::DRAW_REGION);
+        let payload = &message.as_payload()[7..];
+        let header: Vec<u8> = [10u16, 9, 3, 3]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        assert_eq!(payload[..8], header);
+        assert_eq!(payload.len(), 8 + 3 * 3 * 3);
+
+        gol.request_keyframe();
+        assert_eq!(
+            gol.advance_generation().unwrap().as_payload()[1],
+            message_types::DRAW_FRAME
+        );
+        assert_eq!(
+            gol.advance_generation().unwrap().as_payload()[1],
+            message_types::DRAW_REGION
+        );
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let a = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 42);
+        let b = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 42);
+
+        assert_eq!(
+            a.current_generation().unwrap().as_payload()[..],
+            b.current_generation().unwrap().as_payload()[..]
+        );
+        assert_eq!(
+            a.awaken_random_cell().unwrap().as_payload()[..],
+            b.awaken_random_cell().unwrap().as_payload()[..]
+        );
+        assert_eq!(
+            a.advance_generation().unwrap().as_payload()[..],
+            b.advance_generation().unwrap().as_payload()[..]
+        );
+
+        // Reseeding a running room matches a room started with that seed
+        let started = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 7);
+        assert_eq!(
+            a.reseed(7).unwrap().as_payload()[..],
+            started.current_generation().unwrap().as_payload()[..]
+        );
+    }
+
+    #[test]
+    fn every_engine_draws_the_same_frames() {
+        let vecs = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 42);
+        let other = GolState::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 42);
+        assert!(other.select_engine(EngineKind::Simd));
+        assert_eq!(other.engine(), EngineKind::Simd);
+        // Only the vecs engine tracks ages, colors that don't depend on them come out the same
+        for gol in [&vecs, &other] {
+            gol.set_color_scheme(ColorScheme::Monochrome).unwrap();
+        }
+
+        for kind in [
+            EngineKind::Bits,
+            EngineKind::BitsParallel,
+            EngineKind::Sparse,
+        ] {
+            for _ in 0..3 {
+                assert_eq!(
+                    vecs.advance_generation().unwrap().as_payload()[..],
+                    other.advance_generation().unwrap().as_payload()[..]
+                );
+            }
+            // Edits between steps reach the selected engine
+            vecs.toggle_cell(5, 5);
+            other.toggle_cell(5, 5);
+            other.select_engine(kind);
+        }
+        vecs.fast_forward(20);
+        other.fast_forward(20);
+        assert_eq!(vecs.generation_hash(), other.generation_hash());
+        assert_eq!(
+            vecs.advance_generation_delta().unwrap().as_payload()[..],
+            other.advance_generation_delta().unwrap().as_payload()[..]
+        );
+    }
+
+    #[test]
+    fn detects_still_lifes_and_oscillators_once() {
+        let gol = GolState::new(10, 10, 0);
+        gol.reset_game_of_life_blinker();
+        let mut periods = Vec::new();
+        for _ in 0..6 {
+            gol.advance_generation().unwrap();
+            let (_, hash) = gol.generation_hash();
+            periods.push(gol.detect_stability(hash));
+        }
+        // The blinker shows twice before its cycle counts
+        assert_eq!(periods, [None, None, None, Some(2), None, None]);
+
+        let mut detector = StabilityDetector::default();
+        assert_eq!(detector.observe(7), None);
+        assert_eq!(detector.observe(7), Some(1));
+        assert_eq!(detector.observe(7), None);
+        assert_eq!(detector.observe(8), None);
+        assert_eq!(detector.observe(8), Some(1));
+    }
+
+    #[test]
+    fn still_lifes_settle_until_something_changes() {
+        let gol = GolState::new(10, 10, 0);
+        gol.kill_all_cells().unwrap();
+        gol.set_color_scheme(ColorScheme::Monochrome).unwrap();
+        gol.awaken_cells(&[(2, 2), (3, 2), (2, 3), (3, 3)]);
+        assert!(!gol.is_settled());
+        gol.advance_generation().unwrap();
+        assert!(gol.is_settled());
+
+        // The lone cell dies on the next step, which is the last one redrawing anything
+        gol.awaken_cell(7, 7).unwrap();
+        assert!(!gol.is_settled());
+        gol.advance_generation().unwrap();
+        assert!(!gol.is_settled());
+        gol.advance_generation().unwrap();
+        assert!(gol.is_settled());
+
+        // Random colors change every frame
+        gol.set_color_scheme(ColorScheme::Random).unwrap();
+        gol.advance_generation().unwrap();
+        assert!(!gol.is_settled());
+    }
+
+    #[test]
+    fn hybrid_cells_reveal_the_painting() {
+        let gol = GolState::new(4, 2, 0);
+        gol.kill_all_cells().unwrap();
+        gol.awaken_cell(1, 0).unwrap();
+        let painting: Vec<u8> = (0..4 * 2 * 3).map(|i| i as u8 + 1).collect();
+        gol.set_painting_layer(Some(painting.clone())).unwrap();
+        assert!(gol.is_hybrid());
+
+        let frame = WsMessage::decode(gol.current_generation().unwrap().as_payload()).unwrap();
+        let rgb = &frame.payload[frame.payload.len() - 4 * 2 * 3..];
+        assert_eq!(rgb[..3], DEAD_CELL_R_G_B);
+        assert_eq!(rgb[3..6], painting[3..6]);
+        let pixel = WsMessage::decode(gol.awaken_cell(2, 1).unwrap().as_payload()).unwrap();
+        assert_eq!(pixel.payload[4..], painting[18..21]);
+
+        // A layer that doesn't cover the grid leaves hybrid mode off
+        gol.set_painting_layer(Some(vec![0; 3])).unwrap();
+        assert!(!gol.is_hybrid());
+    }
+
+    #[test]
+    fn images_seed_dark_pixels_alive() {
+        use image::{ImageFormat, Rgb, RgbImage};
+
+        let image = RgbImage::from_fn(40, 20, |x, _| {
+            if x < 20 {
+                Rgb([10, 10, 10])
+            } else {
+                Rgb([240, 240, 240])
+            }
+        });
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let gol = GolState::new(4, 2, 0);
+        gol.seed_from_image(&png, CROSSOVER_LUMINANCE_THRESHOLD)
+            .unwrap();
+        let alive = [true, true, false, false];
+        assert_eq!(gol.generation_cells(), [alive, alive]);
+        assert_eq!(gol.generation_stats(), (0, 4));
+
+        assert!(gol.seed_from_image(b"not an image", 100).is_err());
+    }
+
+    #[test]
+    fn edits_redraw_only_the_affected_region() {
+        let gol = GolState::new(8, 8, 0);
+        gol.kill_all_cells().unwrap();
+
+        let region = WsMessage::decode(gol.toggle_cell(2, 3).as_payload()).unwrap();
+        assert_eq!(region.msg_type, message_types::DRAW_REGION);
+        assert_eq!(region.payload[..8], [0, 2, 0, 3, 0, 1, 0, 1]);
+        assert_eq!(gol.generation_stats().1, 1);
+        gol.toggle_cell(2, 3);
+        assert_eq!(gol.generation_stats().1, 0);
+
+        // Rects reaching past the grid are clipped to it
+        let fill = Region {
+            x: 6,
+            y: 5,
+            width: 10,
+            height: 2,
+        };
+        let region = WsMessage::decode(gol.fill_region(fill, true).as_payload()).unwrap();
+        assert_eq!(region.payload[..8], [0, 6, 0, 5, 0, 2, 0, 2]);
+        assert_eq!(region.payload.len(), 8 + 2 * 2 * 3);
+        assert_eq!(gol.generation_stats().1, 4);
+        gol.fill_region(Region::cell(7, 6), false);
+        assert_eq!(gol.generation_stats().1, 3);
+    }
+
+    #[test]
+    fn undo_reverts_only_the_edited_cells() {
+        let gol = GolState::new(8, 8, 0);
+        gol.kill_all_cells().unwrap();
+        assert!(gol.undo().unwrap().is_none());
+
+        gol.awaken_cells(&[(1, 1), (2, 1)]);
+        gol.fill_region(
+            Region {
+                x: 4,
+                y: 4,
+                width: 2,
+                height: 2,
+            },
+            true,
+        );
+        gol.kill_cell(2, 1).unwrap();
+        assert_eq!(gol.generation_stats().1, 5);
+
+        let pixels = WsMessage::decode(gol.undo().unwrap().unwrap().as_payload()).unwrap();
+        assert_eq!(pixels.msg_type, message_types::DRAW_PIXELS_BATCH);
+        assert_eq!(gol.generation_stats().1, 6);
+        gol.undo().unwrap();
+        assert_eq!(gol.generation_stats().1, 2);
+        gol.redo().unwrap();
+        assert_eq!(gol.generation_stats().1, 6);
+
+        // Stepping isn't an edit, and a new edit drops what could be redone
+        gol.advance_generation().unwrap();
+        gol.toggle_cell(7, 0);
+        assert!(gol.redo().unwrap().is_none());
+        gol.undo().unwrap();
+        assert!(!gol.generation_cells()[0][7]);
+    }
+
+    #[test]
+    fn a_panic_holding_the_grid_doesnt_break_the_room() {
+        let gol = GolState::new(8, 8, 0);
+        gol.kill_all_cells().unwrap();
+        gol.awaken_cells(&[(1, 1)]);
+        std::thread::scope(|scope| {
+            let panicked = scope.spawn(|| {
+                let _game = gol.game_mut();
+                panic!("handler bug");
+            });
+            assert!(panicked.join().is_err());
+        });
+        assert!(gol.is_poisoned());
+
+        // The cells edited before the panic are kept and the lock works again
+        assert_eq!(gol.generation_stats().1, 1);
+        assert!(!gol.is_poisoned());
+        gol.advance_generation().unwrap();
+        assert_eq!(gol.generation_stats(), (1, 0));
+    }
 }
@@ -0,0 +1,237 @@
+use crate::{
+    constants::{
+        DEFAULT_REACTION_FEED, DEFAULT_REACTION_KILL, REACTION_PARALLEL_MIN_CELLS,
+        REACTION_SEED_SPOTS, REACTION_STEPS_PER_ADVANCE,
+    },
+    utils::{FrameError, create_frame_message},
+};
+use axum_tws::Message;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use tracing::debug;
+
+// Diffusion rates of the two chemicals
+const DIFFUSION_U: f32 = 1.0;
+const DIFFUSION_V: f32 = 0.5;
+
+/// Gray-Scott reaction-diffusion: chemical U is fed in at `feed`, V consumes it to
+/// reproduce and is removed at `kill`. The grid wraps around at the edges.
+#[derive(Debug, Clone)]
+pub struct GrayScott {
+    pub width: u16,
+    pub height: u16,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    next_u: Vec<f32>,
+    next_v: Vec<f32>,
+    pub feed: f32,
+    pub kill: f32,
+}
+
+impl GrayScott {
+    /// All U, with V dropped into a few random square spots
+    pub fn random(width: u16, height: u16, rng: &mut impl Rng) -> Self {
+        let cells = width as usize * height as usize;
+        let mut sim = Self {
+            width,
+            height,
+            u: vec![1.0; cells],
+            v: vec![0.0; cells],
+            next_u: vec![0.0; cells],
+            next_v: vec![0.0; cells],
+            feed: DEFAULT_REACTION_FEED,
+            kill: DEFAULT_REACTION_KILL,
+        };
+
+        for _ in 0..REACTION_SEED_SPOTS {
+            let (cx, cy) = (rng.random_range(0..width), rng.random_range(0..height));
+            for dy in -3..=3 {
+                for dx in -3..=3 {
+                    let i = sim.wrapped_index(cx as usize, cy as usize, dx, dy);
+                    sim.u[i] = 0.5;
+                    sim.v[i] = 0.25;
+                }
+            }
+        }
+        sim
+    }
+
+    fn wrapped_index(&self, x: usize, y: usize, dx: isize, dy: isize) -> usize {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let nx = (x as isize + dx).rem_euclid(width) as usize;
+        let ny = (y as isize + dy).rem_euclid(height) as usize;
+        ny * self.width as usize + nx
+    }
+
+    // Laplacian of `grid` at (x, y): adjacent cells weigh 0.2, diagonal ones 0.05
+    fn laplacian(&self, grid: &[f32], x: usize, y: usize) -> f32 {
+        let at = |dx, dy| grid[self.wrapped_index(x, y, dx, dy)];
+        0.2 * (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1))
+            + 0.05 * (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1))
+            - grid[y * self.width as usize + x]
+    }
+
+    fn step_row(&self, y: usize, next_u: &mut [f32], next_v: &mut [f32]) {
+        for (x, (next_u, next_v)) in next_u.iter_mut().zip(next_v).enumerate() {
+            let i = y * self.width as usize + x;
+            let (u, v) = (self.u[i], self.v[i]);
+            let reaction = u * v * v;
+            *next_u = (u + DIFFUSION_U * self.laplacian(&self.u, x, y) - reaction
+                + self.feed * (1.0 - u))
+                .clamp(0.0, 1.0);
+            *next_v = (v + DIFFUSION_V * self.laplacian(&self.v, x, y) + reaction
+                - (self.kill + self.feed) * v)
+                .clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn step(&mut self) {
+        let width = self.width as usize;
+        let (mut next_u, mut next_v) = (
+            std::mem::take(&mut self.next_u),
+            std::mem::take(&mut self.next_v),
+        );
+        next_u
+            .chunks_mut(width)
+            .zip(next_v.chunks_mut(width))
+            .enumerate()
+            .for_each(|(y, (u, v))| self.step_row(y, u, v));
+        self.next_u = std::mem::replace(&mut self.u, next_u);
+        self.next_v = std::mem::replace(&mut self.v, next_v);
+    }
+
+    /// Same as `step`, with rows computed across the rayon pool
+    pub fn step_parallel(&mut self) {
+        let width = self.width as usize;
+        let (mut next_u, mut next_v) = (
+            std::mem::take(&mut self.next_u),
+            std::mem::take(&mut self.next_v),
+        );
+        next_u
+            .par_chunks_mut(width)
+            .zip(next_v.par_chunks_mut(width))
+            .enumerate()
+            .for_each(|(y, (u, v))| self.step_row(y, u, v));
+        self.next_u = std::mem::replace(&mut self.u, next_u);
+        self.next_v = std::mem::replace(&mut self.v, next_v);
+    }
+
+    /// Shades each cell by how much U outweighs V, V-rich spots come out dark
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        self.u
+            .iter()
+            .zip(&self.v)
+            .flat_map(|(u, v)| {
+                let shade = ((u - v).clamp(0.0, 1.0) * 255.0) as u8;
+                [shade, shade, shade]
+            })
+            .collect()
+    }
+}
+
+/// Reaction-diffusion state of a single room
+pub struct ReactionState {
+    sim: RwLock<GrayScott>,
+    // Lock after `sim` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl ReactionState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self {
+            sim: RwLock::new(GrayScott::random(width, height, &mut rng)),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn frame(sim: &GrayScott) -> Result<Message, FrameError> {
+        create_frame_message(sim.width, sim.height, sim.to_rgb_data())
+    }
+
+    /// The concentrations as they stand, without advancing the reaction
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        Self::frame(&self.sim.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Restarts the random stream from `seed` and seeds fresh spots
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.create_new_reaction()
+    }
+
+    /// Seeds fresh spots, keeping the feed and kill rates
+    pub fn create_new_reaction(&self) -> Result<Message, FrameError> {
+        let mut sim = self.sim.write().unwrap_or_else(|e| e.into_inner());
+        let (feed, kill) = (sim.feed, sim.kill);
+        *sim = GrayScott::random(sim.width, sim.height, &mut *self.rng());
+        sim.feed = feed;
+        sim.kill = kill;
+        debug!(
+            "Seeded reaction-diffusion with feed {} and kill {}",
+            feed, kill
+        );
+        Self::frame(&sim)
+    }
+
+    /// Runs a batch of steps, a single one barely changes the picture
+    pub fn advance(&self) -> Result<Message, FrameError> {
+        let mut sim = self.sim.write().unwrap_or_else(|e| e.into_inner());
+        let parallel = sim.width as usize * sim.height as usize >= REACTION_PARALLEL_MIN_CELLS;
+        for _ in 0..REACTION_STEPS_PER_ADVANCE {
+            if parallel {
+                sim.step_parallel();
+            } else {
+                sim.step();
+            }
+        }
+        debug!(
+            "Advanced reaction-diffusion by {} steps",
+            REACTION_STEPS_PER_ADVANCE
+        );
+        Self::frame(&sim)
+    }
+
+    pub fn set_rates(&self, feed: f32, kill: f32) -> Result<Message, FrameError> {
+        let mut sim = self.sim.write().unwrap_or_else(|e| e.into_inner());
+        sim.feed = feed;
+        sim.kill = kill;
+        debug!(
+            "Reaction-diffusion rates set to feed {} and kill {}",
+            feed, kill
+        );
+        Self::frame(&sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_step_matches_sequential() {
+        let mut sequential = GrayScott::random(40, 30, &mut StdRng::seed_from_u64(7));
+        let mut parallel = sequential.clone();
+        for _ in 0..10 {
+            sequential.step();
+            parallel.step_parallel();
+        }
+        assert_eq!(sequential.u, parallel.u);
+        assert_eq!(sequential.v, parallel.v);
+    }
+
+    #[test]
+    fn uniform_grid_stays_uniform() {
+        let mut sim = GrayScott::random(8, 8, &mut StdRng::seed_from_u64(7));
+        sim.u.fill(1.0);
+        sim.v.fill(0.0);
+        sim.step();
+        assert!(sim.u.iter().all(|&u| u == 1.0));
+        assert_eq!(sim.to_rgb_data()[..3], [255, 255, 255]);
+    }
+}
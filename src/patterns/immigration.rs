@@ -0,0 +1,262 @@
+use crate::{
+    constants::{DEAD_CELL_R_G_B, IMMIGRATION_SEED_DENSITY, IMMIGRATION_TEAM_R_G_B},
+    utils::{FrameError, create_frame_message, create_pixel_message},
+};
+use axum_tws::Message;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+impl Team {
+    pub fn id(&self) -> u8 {
+        match self {
+            Team::Red => 0,
+            Team::Blue => 1,
+        }
+    }
+
+    pub fn rgb(&self) -> [u8; 3] {
+        IMMIGRATION_TEAM_R_G_B[self.id() as usize]
+    }
+}
+
+/// Immigration Game: Conway's rules with live cells belonging to a team. Survivors keep
+/// their team, births take the team most of their three parents belong to. Cells beyond
+/// the grid are dead.
+#[derive(Debug, Clone)]
+pub struct ImmigrationGame {
+    pub width: u16,
+    pub height: u16,
+    cells: Vec<Option<Team>>,
+    pub generation_count: u64,
+}
+
+impl ImmigrationGame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![None; width as usize * height as usize],
+            generation_count: 0,
+        }
+    }
+
+    pub fn random(width: u16, height: u16, rng: &mut impl Rng) -> Self {
+        let mut game = Self::new(width, height);
+        for cell in &mut game.cells {
+            if rng.random_bool(IMMIGRATION_SEED_DENSITY) {
+                *cell = Some(if rng.random_bool(0.5) {
+                    Team::Red
+                } else {
+                    Team::Blue
+                });
+            }
+        }
+        game
+    }
+
+    /// Makes the cell alive for `team`, returning false if it's outside the grid
+    pub fn awaken(&mut self, x: u16, y: u16, team: Team) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        self.cells[y as usize * self.width as usize + x as usize] = Some(team);
+        true
+    }
+
+    // Alive neighbours and how many of them are red
+    fn neighbours(&self, x: usize, y: usize) -> (u8, u8) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (mut alive, mut red) = (0, 0);
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                if (nx, ny) == (x, y) {
+                    continue;
+                }
+                match self.cells[ny * width + nx] {
+                    Some(Team::Red) => {
+                        alive += 1;
+                        red += 1;
+                    }
+                    Some(Team::Blue) => alive += 1,
+                    None => {}
+                }
+            }
+        }
+        (alive, red)
+    }
+
+    pub fn step(&mut self) {
+        let width = self.width as usize;
+        let next = (0..self.cells.len())
+            .map(|i| {
+                let (alive, red) = self.neighbours(i % width, i / width);
+                match (self.cells[i], alive) {
+                    (Some(team), 2 | 3) => Some(team),
+                    (None, 3) if red >= 2 => Some(Team::Red),
+                    (None, 3) => Some(Team::Blue),
+                    _ => None,
+                }
+            })
+            .collect();
+        self.cells = next;
+        self.generation_count += 1;
+    }
+
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        self.cells
+            .iter()
+            .flat_map(|cell| cell.map_or(DEAD_CELL_R_G_B, |team| team.rgb()))
+            .collect()
+    }
+}
+
+/// Immigration Game state of a single room
+pub struct ImmigrationState {
+    game: RwLock<ImmigrationGame>,
+    // Team of each connected client
+    teams: Mutex<HashMap<String, Team>>,
+    // Lock after `game` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl ImmigrationState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self {
+            game: RwLock::new(ImmigrationGame::random(width, height, &mut rng)),
+            teams: Mutex::new(HashMap::new()),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn teams(&self) -> MutexGuard<'_, HashMap<String, Team>> {
+        self.teams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn frame(game: &ImmigrationGame) -> Result<Message, FrameError> {
+        create_frame_message(game.width, game.height, game.to_rgb_data())
+    }
+
+    /// The grid as it stands, without stepping it
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        Self::frame(&self.game.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Restarts the random stream from `seed` and starts a fresh grid
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.create_new_generation()
+    }
+
+    /// Puts the connection on the team with fewer members, keeping a team it already has
+    pub fn assign_team(&self, connection_id: &str) -> Team {
+        let mut teams = self.teams();
+        if let Some(&team) = teams.get(connection_id) {
+            return team;
+        }
+        let reds = teams.values().filter(|&&team| team == Team::Red).count();
+        let team = if reds * 2 <= teams.len() {
+            Team::Red
+        } else {
+            Team::Blue
+        };
+        teams.insert(connection_id.to_string(), team);
+        debug!("Immigration: {} joined team {:?}", connection_id, team);
+        team
+    }
+
+    pub fn forget(&self, connection_id: &str) {
+        self.teams().remove(connection_id);
+    }
+
+    pub fn create_new_generation(&self) -> Result<Message, FrameError> {
+        let mut game = self.game.write().unwrap_or_else(|e| e.into_inner());
+        *game = ImmigrationGame::random(game.width, game.height, &mut *self.rng());
+        debug!(
+            "Generated Immigration Game grid of {}x{} cells",
+            game.width, game.height
+        );
+        Self::frame(&game)
+    }
+
+    pub fn advance_generation(&self) -> Result<Message, FrameError> {
+        let mut game = self.game.write().unwrap_or_else(|e| e.into_inner());
+        game.step();
+        debug!(
+            "Advanced Immigration Game to generation {}",
+            game.generation_count
+        );
+        Self::frame(&game)
+    }
+
+    /// Awakens a cell of the connection's team. None if the connection has no team or the
+    /// cell is outside the grid.
+    pub fn awaken_cell(
+        &self,
+        connection_id: &str,
+        x: u16,
+        y: u16,
+    ) -> Result<Option<Message>, FrameError> {
+        let Some(team) = self.teams().get(connection_id).copied() else {
+            return Ok(None);
+        };
+        if !self
+            .game
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .awaken(x, y, team)
+        {
+            return Ok(None);
+        }
+        let [r, g, b] = team.rgb();
+        create_pixel_message(x, y, r, g, b).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn births_take_the_majority_team() {
+        let mut game = ImmigrationGame::new(5, 5);
+        // Blinker with two red cells and one blue
+        game.awaken(2, 1, Team::Red);
+        game.awaken(2, 2, Team::Blue);
+        game.awaken(2, 3, Team::Red);
+
+        game.step();
+        let at = |game: &ImmigrationGame, x: usize, y: usize| game.cells[y * 5 + x];
+        assert_eq!(at(&game, 1, 2), Some(Team::Red));
+        assert_eq!(at(&game, 3, 2), Some(Team::Red));
+        // Survivors keep their team
+        assert_eq!(at(&game, 2, 2), Some(Team::Blue));
+        assert_eq!(at(&game, 2, 1), None);
+    }
+
+    #[test]
+    fn teams_stay_balanced() {
+        let state = ImmigrationState::new(4, 4, 1);
+        assert_eq!(state.assign_team("a"), Team::Red);
+        assert_eq!(state.assign_team("b"), Team::Blue);
+        assert_eq!(state.assign_team("a"), Team::Red);
+        state.forget("a");
+        assert_eq!(state.assign_team("c"), Team::Red);
+
+        assert!(state.awaken_cell("c", 1, 1).unwrap().is_some());
+        assert!(state.awaken_cell("c", 4, 1).unwrap().is_none());
+        assert!(state.awaken_cell("a", 1, 1).unwrap().is_none());
+    }
+}
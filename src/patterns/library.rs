@@ -0,0 +1,86 @@
+use crate::patterns::rle::{RlePattern, parse_rle};
+
+/// Well-known patterns clients can stamp by id instead of uploading RLE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryPattern {
+    Glider,
+    Lwss,
+    Pulsar,
+    GosperGliderGun,
+    RPentomino,
+}
+
+impl LibraryPattern {
+    pub const ALL: [LibraryPattern; 5] = [
+        LibraryPattern::Glider,
+        LibraryPattern::Lwss,
+        LibraryPattern::Pulsar,
+        LibraryPattern::GosperGliderGun,
+        LibraryPattern::RPentomino,
+    ];
+
+    /// Pattern for a wire id, in the order of `ALL`
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Wire id, the pattern's position in `ALL`
+    pub fn id(&self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|pattern| pattern == self)
+            .unwrap_or(0) as u8
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LibraryPattern::Glider => "glider",
+            LibraryPattern::Lwss => "lwss",
+            LibraryPattern::Pulsar => "pulsar",
+            LibraryPattern::GosperGliderGun => "gosper glider gun",
+            LibraryPattern::RPentomino => "r-pentomino",
+        }
+    }
+
+    fn rle(&self) -> &'static str {
+        match self {
+            LibraryPattern::Glider => "x = 3, y = 3\nbob$2bo$3o!",
+            LibraryPattern::Lwss => "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!",
+            LibraryPattern::Pulsar => {
+                "x = 13, y = 13\n\
+                 2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$\
+                 2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!"
+            }
+            LibraryPattern::GosperGliderGun => {
+                "x = 36, y = 9\n\
+                 24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                 2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"
+            }
+            LibraryPattern::RPentomino => "x = 3, y = 3\nb2o$2o$bo!",
+        }
+    }
+
+    pub fn pattern(&self) -> RlePattern {
+        parse_rle(self.rle()).expect("built-in patterns are valid RLE")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_patterns_parse() {
+        let populations: Vec<usize> = LibraryPattern::ALL
+            .iter()
+            .map(|pattern| pattern.pattern().cells.len())
+            .collect();
+        assert_eq!(populations, vec![5, 9, 48, 36, 5]);
+
+        assert_eq!(
+            LibraryPattern::from_id(3),
+            Some(LibraryPattern::GosperGliderGun)
+        );
+        assert_eq!(LibraryPattern::from_id(5), None);
+    }
+}
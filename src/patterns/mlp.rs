@@ -1,10 +1,17 @@
 use crate::{
-    constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
-    utils::{create_frame_message, create_pixel_message},
+    constants::{
+        COARSE_TO_FINE_BLOCKS, LIVE_CELL_R_G_B, MAX_MLP_PAINTINGS, MLP_REGION_SIDE,
+        PIXEL_PAYLOAD_SIZE,
+    },
+    input::{InputEvent, input_keys},
+    patterns::{canvas, gol::GolState, render_style::RenderStyle},
+    utils::{FrameError, create_frame_message, create_pixel_message, create_pixels_message},
 };
+use anyhow::{Result, bail};
 use axum_tws::Message;
-use once_cell::sync::Lazy;
-use std::sync::RwLock;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tracing::debug;
 
 // Mona Lisa painting state
@@ -17,20 +24,36 @@ pub struct MonaLisaPainting {
     painting_complete: bool,
 }
 
+/// Order a new painting's strokes are revealed in. Strokes on the same pixel keep their
+/// order, so the finished painting looks the same either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StrokeOrder {
+    // Background washes first, then each feature, as the strokes were generated
+    #[default]
+    Generated,
+    // Every 8th pixel first, then every 4th and 2nd, then the rest
+    CoarseToFine,
+    // Square regions one after another, in random order
+    Regions,
+}
+
+impl StrokeOrder {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(StrokeOrder::Generated),
+            1 => Some(StrokeOrder::CoarseToFine),
+            2 => Some(StrokeOrder::Regions),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BrushStroke {
     points: Vec<(usize, usize)>, // (x, y) coordinates
     color: [u8; 3],              // RGB color
 }
 
-// Global Mona Lisa state
-static MONA_LISA_STATE: Lazy<RwLock<MonaLisaPainting>> = Lazy::new(|| {
-    RwLock::new(MonaLisaPainting::new(
-        CANVAS_WIDTH as usize,
-        CANVAS_HEIGHT as usize,
-    ))
-});
-
 impl MonaLisaPainting {
     pub fn new(width: usize, height: usize) -> Self {
         let canvas = vec![vec![[240, 235, 220]; width]; height]; // Cream background
@@ -425,6 +448,91 @@ impl MonaLisaPainting {
         applied_strokes
     }
 
+    /// Paints strokes until `percent` of them are done, returning the pixels that changed
+    pub fn apply_strokes_until(&mut self, percent: usize) -> Vec<(usize, usize, [u8; 3])> {
+        let target = self.brush_strokes.len() * percent.min(100) / 100;
+        let before = self.canvas.clone();
+        self.apply_multiple_strokes(target.saturating_sub(self.current_stroke));
+
+        let mut changed = Vec::new();
+        for (y, (row, old_row)) in self.canvas.iter().zip(&before).enumerate() {
+            for (x, (&rgb, &old)) in row.iter().zip(old_row).enumerate() {
+                if rgb != old {
+                    changed.push((x, y, rgb));
+                }
+            }
+        }
+        changed
+    }
+
+    /// Replaces the stroke source with one stroke per live cell and starts over
+    pub fn load_strokes_from_cells(&mut self, cells: &[Vec<bool>]) {
+        self.brush_strokes = canvas::cells_to_points(cells)
+            .into_iter()
+            .map(|point| BrushStroke {
+                points: vec![point],
+                color: LIVE_CELL_R_G_B,
+            })
+            .collect();
+        self.reset();
+    }
+
+    /// Replaces the stroke source with an image of the canvas size, painted in coarse blocks
+    /// of its average colors first and refined down to single pixels, and starts over
+    pub fn load_strokes_from_image(&mut self, pixels: &[[u8; 3]]) {
+        let (width, height) = (self.canvas[0].len(), self.canvas.len());
+        let mut strokes = Vec::new();
+        for block in COARSE_TO_FINE_BLOCKS {
+            for top in (0..height).step_by(block) {
+                for left in (0..width).step_by(block) {
+                    let points: Vec<(usize, usize)> = (top..(top + block).min(height))
+                        .flat_map(|y| (left..(left + block).min(width)).map(move |x| (x, y)))
+                        .collect();
+                    let mut sum = [0usize; 3];
+                    for &(x, y) in &points {
+                        for (total, channel) in sum.iter_mut().zip(pixels[y * width + x]) {
+                            *total += channel as usize;
+                        }
+                    }
+                    strokes.push(BrushStroke {
+                        color: sum.map(|total| (total / points.len()) as u8),
+                        points,
+                    });
+                }
+            }
+        }
+        self.brush_strokes = strokes;
+        self.reset();
+    }
+
+    /// Reorders the strokes yet to be painted. Sorts are stable and keyed by a stroke's
+    /// first point, which keeps strokes on the same pixel, and blocks within a coarser
+    /// block, in the order they were generated.
+    pub fn order_strokes(&mut self, order: StrokeOrder, rng: &mut StdRng) {
+        let remaining = &mut self.brush_strokes[self.current_stroke..];
+        match order {
+            StrokeOrder::Generated => {}
+            StrokeOrder::CoarseToFine => remaining.sort_by_key(|stroke| {
+                let (x, y) = stroke.points.first().copied().unwrap_or_default();
+                COARSE_TO_FINE_BLOCKS
+                    .iter()
+                    .position(|&block| x % block == 0 && y % block == 0)
+            }),
+            StrokeOrder::Regions => {
+                let columns = self.canvas[0].len().div_ceil(MLP_REGION_SIDE);
+                let rows = self.canvas.len().div_ceil(MLP_REGION_SIDE);
+                let mut rank: Vec<usize> = (0..columns * rows).collect();
+                rank.shuffle(rng);
+                remaining.sort_by_key(|stroke| {
+                    let (x, y) = stroke.points.first().copied().unwrap_or_default();
+                    // Strokes may reach past the canvas, they count to the nearest region
+                    let row = (y / MLP_REGION_SIDE).min(rows - 1);
+                    rank[row * columns + (x / MLP_REGION_SIDE).min(columns - 1)]
+                });
+            }
+        }
+    }
+
     pub fn reset(&mut self) {
         self.canvas = vec![vec![[240, 235, 220]; self.canvas[0].len()]; self.canvas.len()];
         self.current_stroke = 0;
@@ -441,120 +549,446 @@ impl MonaLisaPainting {
     }
 }
 
-// Public API functions
-pub fn start_new_painting() -> Message {
-    {
-        MONA_LISA_STATE.write().unwrap().reset();
-    }
-    let painting_state = MONA_LISA_STATE.read().unwrap();
-    let frame_data = painting_state.to_rgb_data();
-    debug!("Started new Mona Lisa painting");
-    create_frame_message(frame_data)
+/// A room's paintings by id. The one shown is `MlpState::painting`, the others wait here
+/// with their strokes and progress until they're selected.
+#[derive(Debug, Default)]
+struct PaintingRegistry {
+    selected: u16,
+    next_id: u16,
+    parked: BTreeMap<u16, MonaLisaPainting>,
+}
+
+/// Progressive painting state of a single room
+pub struct MlpState {
+    painting: RwLock<MonaLisaPainting>,
+    // Every painting but the shown one, locked after `painting`
+    paintings: Mutex<PaintingRegistry>,
+    width: usize,
+    height: usize,
+    // Lock after `painting` when holding both
+    rng: Mutex<StdRng>,
+    style: RwLock<RenderStyle>,
 }
 
-pub fn apply_single_brush_stroke() -> Message {
-    let stroke_info = { MONA_LISA_STATE.write().unwrap().apply_next_stroke() };
-
-    match stroke_info {
-        Some((x, y, [r, g, b])) => {
-            let painting_state = MONA_LISA_STATE.read().unwrap();
-            debug!(
-                "Applied brush stroke at ({}, {}), progress: {}%",
-                x,
-                y,
-                painting_state.progress_percentage()
+impl MlpState {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self {
+            painting: RwLock::new(MonaLisaPainting::new(width, height)),
+            paintings: Mutex::new(PaintingRegistry {
+                next_id: 1,
+                ..PaintingRegistry::default()
+            }),
+            width,
+            height,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            style: RwLock::new(RenderStyle::default()),
+        }
+    }
+
+    // The painting as it is, also after a handler panicked while painting it
+    fn painting(&self) -> RwLockReadGuard<'_, MonaLisaPainting> {
+        self.painting.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn painting_mut(&self) -> RwLockWriteGuard<'_, MonaLisaPainting> {
+        self.painting.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn style(&self) -> RenderStyle {
+        *self.style.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Frame data of the painting in the room's style
+    fn render(&self, painting: &MonaLisaPainting) -> Vec<u8> {
+        let mut frame_data = painting.to_rgb_data();
+        self.style().apply(&mut frame_data, self.width);
+        frame_data
+    }
+
+    /// Renders the painting in `style` from now on, returning the restyled frame
+    pub fn set_style(&self, style: RenderStyle) -> Result<Message, FrameError> {
+        *self.style.write().unwrap_or_else(|e| e.into_inner()) = style;
+        debug!("Rendering the painting as {:?}", style);
+        self.current_painting_frame()
+    }
+
+    fn paintings(&self) -> MutexGuard<'_, PaintingRegistry> {
+        self.paintings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts another painting and shows it, the one shown so far keeps its progress
+    pub fn add_painting(&self) -> Result<Message> {
+        {
+            let mut painting = self.painting_mut();
+            let mut paintings = self.paintings();
+            if paintings.parked.len() + 1 >= MAX_MLP_PAINTINGS {
+                bail!("Room already has {} paintings", MAX_MLP_PAINTINGS);
+            }
+            // Ids wrap around past ones still in use
+            let mut id = paintings.next_id;
+            while id == paintings.selected || paintings.parked.contains_key(&id) {
+                id = id.wrapping_add(1);
+            }
+            paintings.next_id = id.wrapping_add(1);
+            let shown = std::mem::replace(
+                &mut *painting,
+                MonaLisaPainting::new(self.width, self.height),
             );
-            create_pixel_message(x as u16, y as u16, r, g, b)
+            let previous = std::mem::replace(&mut paintings.selected, id);
+            paintings.parked.insert(previous, shown);
+            debug!("Started painting {}", id);
         }
-        None => {
-            debug!("Mona Lisa painting complete!");
-            current_painting_frame()
+        Ok(self.current_painting_frame()?)
+    }
+
+    /// Shows painting `id` where it left off
+    pub fn select_painting(&self, id: u16) -> Result<Message> {
+        {
+            let mut painting = self.painting_mut();
+            let mut paintings = self.paintings();
+            if paintings.selected != id {
+                let Some(selected) = paintings.parked.remove(&id) else {
+                    bail!("No painting {}", id);
+                };
+                let shown = std::mem::replace(&mut *painting, selected);
+                let previous = std::mem::replace(&mut paintings.selected, id);
+                paintings.parked.insert(previous, shown);
+            }
         }
+        Ok(self.current_painting_frame()?)
     }
-}
 
-pub fn apply_brush_strokes_batch(count: usize) -> Message {
-    {
-        MONA_LISA_STATE
-            .write()
-            .unwrap()
-            .apply_multiple_strokes(count);
-    }
-
-    let painting_state = MONA_LISA_STATE.read().unwrap();
-    let frame_data = painting_state.to_rgb_data();
-    debug!(
-        "Applied {} brush strokes, progress: {}%",
-        count,
-        painting_state.progress_percentage()
-    );
-    create_frame_message(frame_data)
-}
+    /// Drops painting `id`. Deleting the one shown shows the painting with the lowest id,
+    /// the last painting can't be deleted.
+    pub fn delete_painting(&self, id: u16) -> Result<Message> {
+        {
+            let mut painting = self.painting_mut();
+            let mut paintings = self.paintings();
+            if paintings.selected == id {
+                let Some((next, selected)) = paintings.parked.pop_first() else {
+                    bail!("Painting {} is the room's last", id);
+                };
+                *painting = selected;
+                paintings.selected = next;
+            } else if paintings.parked.remove(&id).is_none() {
+                bail!("No painting {}", id);
+            }
+            debug!("Deleted painting {}", id);
+        }
+        Ok(self.current_painting_frame()?)
+    }
 
-pub fn current_painting_frame() -> Message {
-    let painting_state = MONA_LISA_STATE.read().unwrap();
-    let frame_data = painting_state.to_rgb_data();
-    debug!(
-        "Current painting frame: {}% complete",
-        painting_state.progress_percentage()
-    );
-    create_frame_message(frame_data)
-}
+    /// Id of the painting shown, and every painting's id and progress in percent by id
+    pub fn painting_list(&self) -> (u16, Vec<(u16, usize)>) {
+        let painting = self.painting();
+        let paintings = self.paintings();
+        let mut list: Vec<(u16, usize)> = paintings
+            .parked
+            .iter()
+            .map(|(&id, parked)| (id, parked.progress_percentage()))
+            .collect();
+        list.push((paintings.selected, painting.progress_percentage()));
+        list.sort_unstable();
+        (paintings.selected, list)
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Restarts the random stream from `seed` and starts a fresh painting
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.start_new_painting(StrokeOrder::default())
+    }
+
+    pub fn start_new_painting(&self, order: StrokeOrder) -> Result<Message, FrameError> {
+        {
+            let mut painting_state = self.painting_mut();
+            *painting_state = MonaLisaPainting::new(self.width, self.height);
+            painting_state.order_strokes(order, &mut self.rng());
+        }
+        let painting_state = self.painting();
+        let frame_data = self.render(&painting_state);
+        debug!("Started new Mona Lisa painting in {:?} order", order);
+        create_frame_message(self.width as u16, self.height as u16, frame_data)
+    }
 
-pub fn fast_forward_painting() -> Message {
-    let remaining_strokes = {
-        let painting_state = MONA_LISA_STATE.read().unwrap();
-        if painting_state.is_complete() {
-            0
-        } else {
-            painting_state.brush_strokes.len() - painting_state.current_stroke
+    pub fn apply_single_brush_stroke(&self) -> Result<Message, FrameError> {
+        let stroke_info = { self.painting_mut().apply_next_stroke() };
+
+        match stroke_info {
+            Some(_) if self.style() != RenderStyle::Plain => self.current_painting_frame(),
+            Some((x, y, [r, g, b])) => {
+                let painting_state = self.painting();
+                debug!(
+                    "Applied brush stroke at ({}, {}), progress: {}%",
+                    x,
+                    y,
+                    painting_state.progress_percentage()
+                );
+                create_pixel_message(x as u16, y as u16, r, g, b)
+            }
+            None => {
+                debug!("Mona Lisa painting complete!");
+                self.current_painting_frame()
+            }
         }
-    };
+    }
 
-    if remaining_strokes > 0 {
+    pub fn apply_brush_strokes_batch(&self, count: usize) -> Result<Message, FrameError> {
         {
-            MONA_LISA_STATE
-                .write()
-                .unwrap()
-                .apply_multiple_strokes(remaining_strokes);
+            self.painting_mut().apply_multiple_strokes(count);
         }
-        debug!("Fast-forwarded Mona Lisa painting to completion");
+
+        let painting_state = self.painting();
+        let frame_data = self.render(&painting_state);
+        debug!(
+            "Applied {} brush strokes, progress: {}%",
+            count,
+            painting_state.progress_percentage()
+        );
+        create_frame_message(self.width as u16, self.height as u16, frame_data)
     }
 
-    current_painting_frame()
-}
+    /// Advances the painting to `percent` of its strokes. Only the pixels that changed are
+    /// sent, unless a frame would be smaller.
+    pub fn advance_painting_to(&self, percent: usize) -> Result<Message, FrameError> {
+        let changed = self.painting_mut().apply_strokes_until(percent);
+        debug!(
+            "Advanced painting to {}%, {} pixels changed",
+            percent,
+            changed.len()
+        );
+        // A styled pixel depends on its neighbors and the whole palette, so styled
+        // paintings always send frames
+        if self.style() != RenderStyle::Plain
+            || changed.len() > u16::MAX as usize
+            || changed.len() * PIXEL_PAYLOAD_SIZE >= self.width * self.height * 3
+        {
+            return self.current_painting_frame();
+        }
+        let pixels: Vec<(u16, u16, [u8; 3])> = changed
+            .into_iter()
+            .map(|(x, y, rgb)| (x as u16, y as u16, rgb))
+            .collect();
+        Ok(create_pixels_message(&pixels))
+    }
 
-pub fn painting_progress() -> usize {
-    MONA_LISA_STATE.read().unwrap().progress_percentage()
-}
+    /// Applies a random number of strokes, up to one per canvas column
+    pub fn advance_painting(&self) -> Result<Message, FrameError> {
+        let count = self.rng().random_range(0..self.width);
+        self.apply_brush_strokes_batch(count)
+    }
+
+    pub fn paint_from_generation(&self, gol: &GolState) -> Result<Message, FrameError> {
+        let cells = gol.generation_cells();
+
+        {
+            self.painting_mut().load_strokes_from_cells(&cells);
+        }
+
+        let painting_state = self.painting();
+        let frame_data = self.render(&painting_state);
+        debug!(
+            "Started painting from GOL generation with {} strokes",
+            painting_state.brush_strokes.len()
+        );
+        create_frame_message(self.width as u16, self.height as u16, frame_data)
+    }
+
+    /// Starts painting an uploaded PNG or JPEG, scaled to the canvas
+    pub fn paint_image(&self, data: &[u8]) -> Result<Message> {
+        let pixels = canvas::decode_image(data, self.width, self.height)?;
+        {
+            self.painting_mut().load_strokes_from_image(&pixels);
+        }
+        debug!("Started painting an uploaded image");
+        Ok(self.current_painting_frame()?)
+    }
+
+    pub fn painting_rgb_data(&self) -> Vec<u8> {
+        self.painting().to_rgb_data()
+    }
 
-pub fn is_painting_complete() -> bool {
-    MONA_LISA_STATE.read().unwrap().is_complete()
+    pub fn current_painting_frame(&self) -> Result<Message, FrameError> {
+        let painting_state = self.painting();
+        let frame_data = self.render(&painting_state);
+        debug!(
+            "Current painting frame: {}% complete",
+            painting_state.progress_percentage()
+        );
+        create_frame_message(self.width as u16, self.height as u16, frame_data)
+    }
+
+    pub fn fast_forward_painting(&self) -> Result<Message, FrameError> {
+        let remaining_strokes = {
+            let painting_state = self.painting();
+            if painting_state.is_complete() {
+                0
+            } else {
+                painting_state.brush_strokes.len() - painting_state.current_stroke
+            }
+        };
+
+        if remaining_strokes > 0 {
+            {
+                self.painting_mut()
+                    .apply_multiple_strokes(remaining_strokes);
+            }
+            debug!("Fast-forwarded Mona Lisa painting to completion");
+        }
+
+        self.current_painting_frame()
+    }
+
+    /// Input mapping: right paints the next stroke, primary adds a detail stroke and
+    /// secondary fast-forwards to the finished painting
+    pub fn handle_input(&self, event: &InputEvent) -> Result<Option<Message>, FrameError> {
+        if !event.is_pressed() {
+            return Ok(None);
+        }
+
+        match event.key {
+            input_keys::RIGHT => self.apply_single_brush_stroke().map(Some),
+            input_keys::PRIMARY => self.add_random_detail_stroke().map(Some),
+            input_keys::SECONDARY => self.fast_forward_painting().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn painting_progress(&self) -> usize {
+        self.painting().progress_percentage()
+    }
+
+    pub fn is_painting_complete(&self) -> bool {
+        self.painting().is_complete()
+    }
+
+    // Artistic variations
+    pub fn add_random_detail_stroke(&self) -> Result<Message, FrameError> {
+        let (x, y, color) = {
+            let mut painting_state = self.painting_mut();
+            let mut rng = self.rng();
+            let x = rng.random_range(0..painting_state.canvas[0].len());
+            let y = rng.random_range(0..painting_state.canvas.len());
+
+            // Add some artistic variation to existing colors
+            let existing_color = painting_state.canvas[y][x];
+            let variation = rng.random_range(-20i16..=20i16);
+            let new_color = [
+                (existing_color[0] as i16 + variation).clamp(0, 255) as u8,
+                (existing_color[1] as i16 + variation).clamp(0, 255) as u8,
+                (existing_color[2] as i16 + variation).clamp(0, 255) as u8,
+            ];
+
+            painting_state.canvas[y][x] = new_color;
+            (x, y, new_color)
+        };
+
+        debug!("Added random detail stroke at ({}, {})", x, y);
+        if self.style() != RenderStyle::Plain {
+            return self.current_painting_frame();
+        }
+        create_pixel_message(x as u16, y as u16, color[0], color[1], color[2])
+    }
 }
 
-// Artistic variations
-pub fn add_random_detail_stroke() -> Message {
-    use rand::Rng;
-    let mut rng = rand::rng();
-
-    let (x, y, color) = {
-        let mut painting_state = MONA_LISA_STATE.write().unwrap();
-        let x = rng.random_range(0..painting_state.canvas[0].len());
-        let y = rng.random_range(0..painting_state.canvas.len());
-
-        // Add some artistic variation to existing colors
-        let existing_color = painting_state.canvas[y][x];
-        let variation = rng.random_range(-20i16..=20i16);
-        let new_color = [
-            (existing_color[0] as i16 + variation).clamp(0, 255) as u8,
-            (existing_color[1] as i16 + variation).clamp(0, 255) as u8,
-            (existing_color[2] as i16 + variation).clamp(0, 255) as u8,
-        ];
-
-        painting_state.canvas[y][x] = new_color;
-        (x, y, new_color)
-    };
-
-    debug!("Added random detail stroke at ({}, {})", x, y);
-    create_pixel_message(x as u16, y as u16, color[0], color[1], color[2])
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::message_types, protocol::WsMessage};
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn uploaded_images_are_painted_coarse_to_fine() {
+        let mut image = RgbImage::from_pixel(40, 40, Rgb([200, 0, 0]));
+        for x in 20..40 {
+            for y in 0..40 {
+                image.put_pixel(x, y, Rgb([0, 0, 200]));
+            }
+        }
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let mlp = MlpState::new(4, 4, 1);
+        mlp.paint_image(&png).unwrap();
+        // One 8x8 block covers the whole canvas, then 4x4, 2x2 and single pixels
+        let strokes = mlp.painting.read().unwrap().brush_strokes.len();
+        assert_eq!(strokes, 1 + 1 + 4 + 16);
+        mlp.fast_forward_painting().unwrap();
+        let rgb = mlp.painting_rgb_data();
+        assert_eq!(rgb[..3], [200, 0, 0]);
+        assert_eq!(rgb[9..12], [0, 0, 200]);
+
+        assert!(mlp.paint_image(b"not an image").is_err());
+    }
+
+    #[test]
+    fn stroke_orders_reveal_the_same_painting() {
+        let finished = |order| {
+            let mlp = MlpState::new(40, 40, 7);
+            mlp.start_new_painting(order).unwrap();
+            let first = mlp.painting.read().unwrap().brush_strokes[0].points[0];
+            mlp.fast_forward_painting().unwrap();
+            (first, mlp.painting_rgb_data())
+        };
+        let (_, generated) = finished(StrokeOrder::Generated);
+        let (first, coarse) = finished(StrokeOrder::CoarseToFine);
+        assert_eq!(coarse, generated);
+        assert_eq!((first.0 % 8, first.1 % 8), (0, 0));
+        assert_eq!(finished(StrokeOrder::Regions).1, generated);
+    }
+
+    #[test]
+    fn advancing_to_a_percentage_sends_the_changed_pixels() {
+        let mlp = MlpState::new(40, 40, 7);
+        mlp.advance_painting_to(50).unwrap();
+        assert_eq!(mlp.painting.read().unwrap().progress_percentage(), 50);
+        let before = mlp.painting_rgb_data();
+
+        let batch = mlp.advance_painting_to(51).unwrap();
+        let msg = WsMessage::decode(batch.as_payload()).unwrap();
+        assert_eq!(msg.msg_type, message_types::DRAW_PIXELS_BATCH);
+        let count = u16::from_be_bytes([msg.payload[0], msg.payload[1]]) as usize;
+        let after = mlp.painting_rgb_data();
+        let changed = before
+            .chunks(3)
+            .zip(after.chunks(3))
+            .filter(|(a, b)| a != b);
+        assert_eq!(count, changed.count());
+
+        // Painting most of the canvas at once is cheaper as a frame
+        let frame = MlpState::new(40, 40, 7).advance_painting_to(100).unwrap();
+        let msg = WsMessage::decode(frame.as_payload()).unwrap();
+        assert_eq!(msg.msg_type, message_types::DRAW_FRAME);
+    }
+
+    #[test]
+    fn paintings_keep_their_progress_while_another_is_shown() {
+        let mlp = MlpState::new(8, 8, 1);
+        mlp.apply_brush_strokes_batch(10).unwrap();
+        let first = mlp.painting_rgb_data();
+        mlp.add_painting().unwrap();
+        let (selected, paintings) = mlp.painting_list();
+        assert_eq!(selected, 1);
+        assert_eq!(paintings.len(), 2);
+        assert_eq!(paintings[1], (1, 0));
+
+        mlp.select_painting(0).unwrap();
+        assert_eq!(mlp.painting_rgb_data(), first);
+        assert!(mlp.select_painting(7).is_err());
+
+        mlp.delete_painting(0).unwrap();
+        assert_eq!(mlp.painting_list(), (1, vec![(1, 0)]));
+        assert!(mlp.delete_painting(1).is_err());
+
+        for _ in 1..MAX_MLP_PAINTINGS {
+            mlp.add_painting().unwrap();
+        }
+        assert!(mlp.add_painting().is_err());
+    }
 }
@@ -1,4 +1,15 @@
+pub mod boids;
+pub mod brians_brain;
+pub mod brush;
+pub mod canvas;
+pub mod census;
 pub mod gol;
-pub mod gol_simd;
-pub mod gol_threads;
+pub mod gray_scott;
+pub mod immigration;
+pub mod library;
 pub mod mlp;
+pub mod pong;
+pub mod render_style;
+pub mod rle;
+pub mod sand;
+pub mod snake;
@@ -0,0 +1,407 @@
+use crate::{
+    constants::{
+        DEAD_CELL_R_G_B, LIVE_CELL_R_G_B, PONG_BALL_R_G_B, PONG_BALL_SPEED, PONG_PADDLE_HEIGHT,
+    },
+    utils::{
+        FrameError, create_frame_changes_message, create_frame_message, create_pong_score_message,
+    },
+};
+use axum_tws::Message;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::{Mutex, MutexGuard};
+use tracing::{debug, info};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddleSide {
+    Left,
+    Right,
+}
+
+impl PaddleSide {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(PaddleSide::Left),
+            1 => Some(PaddleSide::Right),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            PaddleSide::Left => 0,
+            PaddleSide::Right => 1,
+        }
+    }
+
+    fn index(&self) -> usize {
+        self.id() as usize
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Paddle {
+    // Connection playing this paddle
+    owner: Option<String>,
+    // Row of the paddle's top cell
+    top: i32,
+    // -1 moving up, 1 moving down, 0 resting
+    direction: i8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ball {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+}
+
+/// Two-player Pong: each connection claims a paddle, the ball is in play once both are
+/// taken. A missed ball scores for the other side and is served again from the center.
+#[derive(Debug, Clone)]
+pub struct Pong {
+    pub width: u16,
+    pub height: u16,
+    paddles: [Paddle; 2],
+    ball: Ball,
+    pub score: [u16; 2],
+    // What clients were last sent, None until they get a full frame
+    rendered: Option<Vec<u8>>,
+}
+
+impl Pong {
+    pub fn new(width: u16, height: u16) -> Self {
+        let top = (height as i32 - Self::paddle_height(height)) / 2;
+        Self {
+            width,
+            height,
+            paddles: [
+                Paddle {
+                    top,
+                    ..Paddle::default()
+                },
+                Paddle {
+                    top,
+                    ..Paddle::default()
+                },
+            ],
+            ball: Ball {
+                x: width as f32 / 2.0,
+                y: height as f32 / 2.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            score: [0, 0],
+            rendered: None,
+        }
+    }
+
+    fn paddle_height(height: u16) -> i32 {
+        (PONG_PADDLE_HEIGHT as i32).min(height as i32)
+    }
+
+    // Column each paddle is drawn in
+    fn paddle_x(&self, side: PaddleSide) -> i32 {
+        match side {
+            PaddleSide::Left => 1.min(self.width as i32 - 1),
+            PaddleSide::Right => (self.width as i32 - 2).max(0),
+        }
+    }
+
+    fn side_of(&self, connection_id: &str) -> Option<PaddleSide> {
+        [PaddleSide::Left, PaddleSide::Right]
+            .into_iter()
+            .find(|side| self.paddles[side.index()].owner.as_deref() == Some(connection_id))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.paddles.iter().all(|paddle| paddle.owner.is_some())
+    }
+
+    /// Gives the connection the preferred paddle, or any free one. A connection keeps the
+    /// paddle it already has. Returns the connection's paddle, None when both are taken.
+    pub fn claim(
+        &mut self,
+        connection_id: &str,
+        preferred: Option<PaddleSide>,
+    ) -> Option<PaddleSide> {
+        if let Some(side) = self.side_of(connection_id) {
+            return Some(side);
+        }
+        let side = match preferred {
+            Some(side) => Some(side).filter(|side| self.paddles[side.index()].owner.is_none()),
+            None => [PaddleSide::Left, PaddleSide::Right]
+                .into_iter()
+                .find(|side| self.paddles[side.index()].owner.is_none()),
+        }?;
+        self.paddles[side.index()].owner = Some(connection_id.to_string());
+        Some(side)
+    }
+
+    /// Frees the connection's paddle, stopping play until someone claims it again
+    pub fn release(&mut self, connection_id: &str) -> Option<PaddleSide> {
+        let side = self.side_of(connection_id)?;
+        self.paddles[side.index()] = Paddle {
+            top: self.paddles[side.index()].top,
+            ..Paddle::default()
+        };
+        Some(side)
+    }
+
+    /// Sets the direction the connection's paddle moves in each tick. Returns whether the
+    /// connection owns a paddle.
+    pub fn steer(&mut self, connection_id: &str, direction: i8) -> bool {
+        let Some(side) = self.side_of(connection_id) else {
+            return false;
+        };
+        self.paddles[side.index()].direction = direction.signum();
+        true
+    }
+
+    fn serve(&mut self, towards: PaddleSide, rng: &mut impl Rng) {
+        let vx = match towards {
+            PaddleSide::Left => -PONG_BALL_SPEED,
+            PaddleSide::Right => PONG_BALL_SPEED,
+        };
+        self.ball = Ball {
+            x: self.width as f32 / 2.0,
+            y: self.height as f32 / 2.0,
+            vx,
+            vy: rng.random_range(-0.5..0.5) * PONG_BALL_SPEED,
+        };
+    }
+
+    /// Moves the paddles and, while both are claimed, the ball. Returns whether a point
+    /// was scored.
+    pub fn step(&mut self, rng: &mut impl Rng) -> bool {
+        let max_top = self.height as i32 - Self::paddle_height(self.height);
+        for paddle in &mut self.paddles {
+            paddle.top = (paddle.top + paddle.direction as i32).clamp(0, max_top);
+        }
+
+        if !self.is_playing() {
+            return false;
+        }
+        if self.ball.vx == 0.0 {
+            let towards = if rng.random_bool(0.5) {
+                PaddleSide::Left
+            } else {
+                PaddleSide::Right
+            };
+            self.serve(towards, rng);
+        }
+
+        let ball = &mut self.ball;
+        ball.x += ball.vx;
+        ball.y += ball.vy;
+        let bottom = self.height as f32 - 1.0;
+        if ball.y < 0.0 || ball.y > bottom {
+            ball.vy = -ball.vy;
+            ball.y = ball.y.clamp(0.0, bottom);
+        }
+
+        let (side, scorer) = if ball.vx < 0.0 {
+            (PaddleSide::Left, PaddleSide::Right)
+        } else {
+            (PaddleSide::Right, PaddleSide::Left)
+        };
+        let paddle_x = self.paddle_x(side) as f32;
+        let reached = match side {
+            PaddleSide::Left => self.ball.x <= paddle_x,
+            PaddleSide::Right => self.ball.x >= paddle_x,
+        };
+        if !reached {
+            return false;
+        }
+
+        let paddle = &self.paddles[side.index()];
+        let paddle_height = Self::paddle_height(self.height) as f32;
+        let offset = self.ball.y.round() - paddle.top as f32;
+        if (0.0..paddle_height).contains(&offset) {
+            // Returned, hits off the paddle's ends leave at a steeper angle
+            self.ball.vx = -self.ball.vx;
+            self.ball.x = paddle_x + self.ball.vx.signum();
+            self.ball.vy = (offset / paddle_height - 0.5) * 2.0 * PONG_BALL_SPEED;
+            return false;
+        }
+
+        self.score[scorer.index()] = self.score[scorer.index()].saturating_add(1);
+        info!("Pong: point to {:?}, score {:?}", scorer, self.score);
+        self.serve(side, rng);
+        true
+    }
+
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let mut rgb_data = DEAD_CELL_R_G_B.repeat(width * self.height as usize);
+        let mut paint = |x: i32, y: i32, rgb: [u8; 3]| {
+            if (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y) {
+                let offset = (y as usize * width + x as usize) * 3;
+                rgb_data[offset..offset + 3].copy_from_slice(&rgb);
+            }
+        };
+
+        for side in [PaddleSide::Left, PaddleSide::Right] {
+            let top = self.paddles[side.index()].top;
+            for y in top..top + Self::paddle_height(self.height) {
+                paint(self.paddle_x(side), y, LIVE_CELL_R_G_B);
+            }
+        }
+        if self.is_playing() {
+            paint(
+                self.ball.x.round() as i32,
+                self.ball.y.round() as i32,
+                PONG_BALL_R_G_B,
+            );
+        }
+        rgb_data
+    }
+
+    /// Cells that changed since the last call, or the whole frame if clients need one
+    fn render_changes(&mut self) -> Result<Option<Message>, FrameError> {
+        let frame = self.to_rgb_data();
+        let rendered = self.rendered.replace(frame.clone());
+        create_frame_changes_message(self.width, self.height, rendered.as_deref(), frame)
+    }
+}
+
+/// What one Pong tick sends to the room
+pub struct PongUpdate {
+    pub pixels: Option<Message>,
+    pub score: Option<Message>,
+}
+
+/// Pong game of a single room
+pub struct PongState {
+    game: Mutex<Pong>,
+    // Lock after `game` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl PongState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        Self {
+            game: Mutex::new(Pong::new(width, height)),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    fn game(&self) -> MutexGuard<'_, Pong> {
+        self.game.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The whole court, leaving what the other clients were last sent alone
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        let game = self.game();
+        create_frame_message(game.width, game.height, game.to_rgb_data())
+    }
+
+    /// Restarts the random stream from `seed`, keeping the players
+    pub fn reseed(&self, seed: u64) {
+        *self.rng() = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn claim(&self, connection_id: &str, preferred: Option<PaddleSide>) -> Option<PaddleSide> {
+        let mut game = self.game();
+        let side = game.claim(connection_id, preferred);
+        // Clients may still be showing another pattern
+        game.rendered = None;
+        debug!("Pong: {} claimed {:?}", connection_id, side);
+        side
+    }
+
+    pub fn release(&self, connection_id: &str) {
+        if let Some(side) = self.game().release(connection_id) {
+            info!("Pong: {:?} paddle freed by {}", side, connection_id);
+        }
+    }
+
+    pub fn steer(&self, connection_id: &str, direction: i8) -> bool {
+        self.game().steer(connection_id, direction)
+    }
+
+    /// Advances the game one tick, returning the changed cells and the score if it changed
+    pub fn step(&self) -> Result<PongUpdate, FrameError> {
+        let mut game = self.game();
+        let full_frame = game.rendered.is_none();
+        let scored = game.step(&mut *self.rng());
+        Ok(PongUpdate {
+            pixels: game.render_changes()?,
+            score: (scored || full_frame).then(|| create_pong_score_message(game.score)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::message_types;
+
+    #[test]
+    fn paddles_are_claimed_once() {
+        let mut pong = Pong::new(40, 20);
+        assert_eq!(pong.claim("a", None), Some(PaddleSide::Left));
+        assert_eq!(pong.claim("b", Some(PaddleSide::Left)), None);
+        assert_eq!(pong.claim("b", None), Some(PaddleSide::Right));
+        assert_eq!(
+            pong.claim("a", Some(PaddleSide::Right)),
+            Some(PaddleSide::Left)
+        );
+        assert_eq!(pong.claim("c", None), None);
+        assert!(pong.is_playing());
+
+        assert!(!pong.steer("c", 1));
+        assert_eq!(pong.release("b"), Some(PaddleSide::Right));
+        assert!(!pong.is_playing());
+        assert_eq!(pong.claim("c", None), Some(PaddleSide::Right));
+    }
+
+    #[test]
+    fn ball_bounces_off_paddles_and_misses_score() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut pong = Pong::new(40, 20);
+        pong.claim("a", None);
+        pong.claim("b", None);
+
+        // Straight at the left paddle, which covers the middle rows
+        pong.ball = Ball {
+            x: 2.0,
+            y: 10.0,
+            vx: -PONG_BALL_SPEED,
+            vy: 0.0,
+        };
+        while pong.ball.vx < 0.0 {
+            assert!(!pong.step(&mut rng));
+        }
+
+        // Past the right paddle, moved out of the way
+        pong.paddles[1].top = 0;
+        pong.ball = Ball {
+            x: 37.5,
+            y: 18.0,
+            vx: PONG_BALL_SPEED,
+            vy: 0.0,
+        };
+        while !pong.step(&mut rng) {}
+        assert_eq!(pong.score, [1, 0]);
+    }
+
+    #[test]
+    fn first_render_is_a_full_frame() {
+        let mut pong = Pong::new(10, 10);
+        let frame = pong.render_changes().unwrap().unwrap();
+        assert_eq!(frame.as_payload()[1], message_types::DRAW_FRAME);
+        assert!(pong.render_changes().unwrap().is_none());
+
+        pong.steer("nobody", 1);
+        pong.claim("a", None);
+        pong.steer("a", 1);
+        pong.step(&mut StdRng::seed_from_u64(1));
+        let pixels = pong.render_changes().unwrap().unwrap();
+        assert_eq!(pixels.as_payload()[1], message_types::DRAW_PIXELS_BATCH);
+    }
+}
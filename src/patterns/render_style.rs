@@ -0,0 +1,194 @@
+// Palette sizes a quantized style may ask for
+pub const MIN_STYLE_COLORS: u8 = 2;
+pub const MAX_STYLE_COLORS: u8 = 64;
+
+// 4x4 Bayer threshold map, 0 to 15
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How a painting's canvas is turned into frame data, so the same strokes can render in
+/// different styles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderStyle {
+    // The canvas as painted
+    #[default]
+    Plain,
+    // Every pixel as the nearest of the `colors` that best cover the canvas
+    Quantized {
+        colors: u8,
+    },
+    // Quantized, with a Bayer threshold map breaking up the color bands
+    OrderedDither {
+        colors: u8,
+    },
+    // Quantized, each pixel's error spread to the pixels not yet quantized
+    FloydSteinberg {
+        colors: u8,
+    },
+}
+
+impl RenderStyle {
+    pub fn from_id(id: u8, colors: u8) -> Option<Self> {
+        if id != 0 && !(MIN_STYLE_COLORS..=MAX_STYLE_COLORS).contains(&colors) {
+            return None;
+        }
+        match id {
+            0 => Some(RenderStyle::Plain),
+            1 => Some(RenderStyle::Quantized { colors }),
+            2 => Some(RenderStyle::OrderedDither { colors }),
+            3 => Some(RenderStyle::FloydSteinberg { colors }),
+            _ => None,
+        }
+    }
+
+    /// Renders RGB frame data `width` pixels wide in this style, in place
+    pub fn apply(&self, rgb_data: &mut [u8], width: usize) {
+        let colors = match *self {
+            RenderStyle::Plain => return,
+            RenderStyle::Quantized { colors }
+            | RenderStyle::OrderedDither { colors }
+            | RenderStyle::FloydSteinberg { colors } => colors as usize,
+        };
+        let pixels: Vec<[u8; 3]> = rgb_data
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect();
+        let palette = median_cut_palette(&pixels, colors);
+
+        match self {
+            RenderStyle::Plain => {}
+            RenderStyle::Quantized { .. } => {
+                for rgb in rgb_data.chunks_exact_mut(3) {
+                    rgb.copy_from_slice(&nearest(
+                        &palette,
+                        [rgb[0], rgb[1], rgb[2]].map(i32::from),
+                    ));
+                }
+            }
+            RenderStyle::OrderedDither { .. } => {
+                // Thresholds span about the gap between neighboring palette colors
+                let spread = 255.0 / (colors as f32).cbrt();
+                for (i, rgb) in rgb_data.chunks_exact_mut(3).enumerate() {
+                    let threshold = BAYER_4X4[(i / width) % 4][(i % width) % 4] as f32;
+                    let offset = ((threshold + 0.5) / 16.0 - 0.5) * spread;
+                    let shifted = [rgb[0], rgb[1], rgb[2]].map(|c| (c as f32 + offset) as i32);
+                    rgb.copy_from_slice(&nearest(&palette, shifted));
+                }
+            }
+            RenderStyle::FloydSteinberg { .. } => {
+                let mut work: Vec<[i32; 3]> = pixels.iter().map(|rgb| rgb.map(i32::from)).collect();
+                let height = work.len() / width;
+                for y in 0..height {
+                    for x in 0..width {
+                        let old = work[y * width + x];
+                        let new = nearest(&palette, old);
+                        rgb_data[(y * width + x) * 3..][..3].copy_from_slice(&new);
+                        let error = [0, 1, 2].map(|c| old[c] - new[c] as i32);
+                        for (dx, dy, weight) in [(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)] {
+                            let (nx, ny) = (x as isize + dx, y + dy);
+                            if nx < 0 || nx as usize >= width || ny >= height {
+                                continue;
+                            }
+                            let neighbor = &mut work[ny * width + nx as usize];
+                            for c in 0..3 {
+                                neighbor[c] += error[c] * weight / 16;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Up to `colors` colors covering `pixels`, found by splitting the pixels at the median of
+/// their widest channel until there are enough groups, each group giving its average
+fn median_cut_palette(pixels: &[[u8; 3]], colors: usize) -> Vec<[u8; 3]> {
+    let channel_range = |group: &[[u8; 3]], channel: usize| {
+        let (min, max) = group.iter().fold((u8::MAX, u8::MIN), |(min, max), rgb| {
+            (min.min(rgb[channel]), max.max(rgb[channel]))
+        });
+        max.saturating_sub(min)
+    };
+
+    let mut groups = vec![pixels.to_vec()];
+    while groups.len() < colors {
+        let widest = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| (0..3).map(move |channel| (i, channel, group)))
+            .map(|(i, channel, group)| (channel_range(group, channel), i, channel))
+            .max();
+        let Some((_, i, channel)) = widest.filter(|&(range, _, _)| range > 0) else {
+            break;
+        };
+        let mut group = groups.swap_remove(i);
+        group.sort_unstable_by_key(|rgb| rgb[channel]);
+        let upper = group.split_off(group.len() / 2);
+        groups.push(group);
+        groups.push(upper);
+    }
+
+    groups
+        .iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let sum = group.iter().fold([0usize; 3], |sum, rgb| {
+                [0, 1, 2].map(|c| sum[c] + rgb[c] as usize)
+            });
+            sum.map(|total| (total / group.len()) as u8)
+        })
+        .collect()
+}
+
+fn nearest(palette: &[[u8; 3]], rgb: [i32; 3]) -> [u8; 3] {
+    palette
+        .iter()
+        .copied()
+        .min_by_key(|color| {
+            (0..3)
+                .map(|c| (color[c] as i32 - rgb[c]).pow(2))
+                .sum::<i32>()
+        })
+        .unwrap_or([0, 0, 0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_render_with_the_requested_palette() {
+        // A horizontal gradient from black to white
+        let width = 32;
+        let source: Vec<u8> = (0..width * 8)
+            .flat_map(|i| [(i % width * 8) as u8; 3])
+            .collect();
+        let distinct = |data: &[u8]| {
+            let mut colors: Vec<&[u8]> = data.chunks(3).collect();
+            colors.sort_unstable();
+            colors.dedup();
+            colors.len()
+        };
+
+        let mut plain = source.clone();
+        RenderStyle::Plain.apply(&mut plain, width);
+        assert_eq!(plain, source);
+        for style in [
+            RenderStyle::Quantized { colors: 4 },
+            RenderStyle::OrderedDither { colors: 4 },
+            RenderStyle::FloydSteinberg { colors: 4 },
+        ] {
+            let mut data = source.clone();
+            style.apply(&mut data, width);
+            assert!(distinct(&data) <= 4, "{:?}", style);
+        }
+
+        assert_eq!(
+            RenderStyle::from_id(2, 8),
+            Some(RenderStyle::OrderedDither { colors: 8 })
+        );
+        assert_eq!(RenderStyle::from_id(0, 0), Some(RenderStyle::Plain));
+        assert!(RenderStyle::from_id(1, 1).is_none());
+        assert!(RenderStyle::from_id(4, 8).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result, bail};
+use tracing::debug;
+
+use crate::constants::MAX_CANVAS_SIDE;
+
+/// A pattern decoded from the Life RLE format, as offsets of live cells from its top-left corner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RlePattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Parses a standard `.rle` pattern string.
+///
+/// Comment lines (`#...`) are skipped, the header line (`x = m, y = n[, rule = ...]`)
+/// provides the bounding box and the body is a run-length encoded list of
+/// `b` (dead), `o` (alive), `$` (end of row) and `!` (end of pattern) tokens. Patterns
+/// larger than a canvas may be along either side are refused.
+pub fn parse_rle(input: &str) -> Result<RlePattern> {
+    let mut lines = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let Some(header) = lines.next() else {
+        bail!("Empty RLE pattern");
+    };
+    let (width, height) = parse_header(header)?;
+
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut run_count: Option<usize> = None;
+
+    'body: for line in lines {
+        for token in line.chars() {
+            match token {
+                '0'..='9' => {
+                    let digit = token.to_digit(10).unwrap() as usize;
+                    run_count = run_count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit))
+                        .map(Some)
+                        .context("RLE run count too large")?;
+                    continue;
+                }
+                'b' | '.' => {
+                    x = x
+                        .checked_add(run_count.unwrap_or(1))
+                        .context("RLE row too long")?;
+                }
+                '$' => {
+                    y = y
+                        .checked_add(run_count.unwrap_or(1))
+                        .context("RLE pattern too tall")?;
+                    x = 0;
+                }
+                '!' => break 'body,
+                // Any other state letter (o, A..X) is treated as a live cell
+                'o' | 'A'..='X' => {
+                    for _ in 0..run_count.unwrap_or(1) {
+                        if x >= width || y >= height {
+                            bail!(
+                                "Live cell ({}, {}) outside of declared {}x{} bounds",
+                                x,
+                                y,
+                                width,
+                                height
+                            );
+                        }
+                        cells.push((x, y));
+                        x += 1;
+                    }
+                }
+                c if c.is_whitespace() => {}
+                other => bail!("Unexpected RLE token: {:?}", other),
+            }
+            run_count = None;
+        }
+    }
+
+    debug!(
+        "Parsed RLE pattern: {}x{} with {} live cells",
+        width,
+        height,
+        cells.len()
+    );
+
+    Ok(RlePattern {
+        width,
+        height,
+        cells,
+    })
+}
+
+impl RlePattern {
+    /// Encodes the pattern as `parse_rle` reads it back, with a B3/S23 header and body
+    /// lines of at most 70 characters
+    pub fn to_rle(&self) -> String {
+        let mut rows = vec![vec![false; self.width]; self.height];
+        for &(x, y) in &self.cells {
+            rows[y][x] = true;
+        }
+
+        // (count, tag) runs, dead cells at the end of a row and empty rows at the end
+        // of the pattern are implied
+        let mut runs: Vec<(usize, char)> = Vec::new();
+        let mut push = |count: usize, tag: char| match runs.last_mut() {
+            Some((last, last_tag)) if *last_tag == tag => *last += count,
+            _ => runs.push((count, tag)),
+        };
+        let last_row = rows.iter().rposition(|row| row.contains(&true));
+        for (y, row) in rows
+            .iter()
+            .enumerate()
+            .take(last_row.map_or(0, |row| row + 1))
+        {
+            if y > 0 {
+                push(1, '$');
+            }
+            let end = row.iter().rposition(|&alive| alive).map_or(0, |x| x + 1);
+            for &alive in &row[..end] {
+                push(1, if alive { 'o' } else { 'b' });
+            }
+        }
+
+        let mut rle = format!("x = {}, y = {}, rule = B3/S23\n", self.width, self.height);
+        let mut line_len = 0;
+        for (count, tag) in runs {
+            let token = if count == 1 {
+                tag.to_string()
+            } else {
+                format!("{}{}", count, tag)
+            };
+            if line_len + token.len() > 70 {
+                rle.push('\n');
+                line_len = 0;
+            }
+            line_len += token.len();
+            rle.push_str(&token);
+        }
+        rle.push('!');
+        rle
+    }
+}
+
+fn parse_header(header: &str) -> Result<(usize, usize)> {
+    let mut width = None;
+    let mut height = None;
+
+    for field in header.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            bail!("Malformed RLE header field: {:?}", field);
+        };
+        match key.trim() {
+            "x" => width = Some(value.trim().parse::<usize>()?),
+            "y" => height = Some(value.trim().parse::<usize>()?),
+            // The rule is informational, the engine only runs B3/S23
+            _ => {}
+        }
+    }
+
+    let (Some(width), Some(height)) = (width, height) else {
+        bail!("RLE header missing x or y: {:?}", header);
+    };
+    let max_side = MAX_CANVAS_SIDE as usize;
+    if width > max_side || height > max_side {
+        bail!(
+            "RLE pattern of {}x{} cells larger than {} along a side",
+            width,
+            height,
+            max_side
+        );
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_glider() {
+        let pattern = parse_rle("#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!").unwrap();
+
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn parse_multiline_body_with_blank_rows() {
+        let pattern = parse_rle("x = 2, y = 4\no$\n2$\n2o!").unwrap();
+
+        assert_eq!(pattern.cells, vec![(0, 0), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let glider = parse_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+        assert_eq!(glider.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!");
+
+        let sparse = RlePattern {
+            width: 40,
+            height: 6,
+            cells: (0..40).step_by(2).map(|x| (x, 1)).chain([(3, 4)]).collect(),
+        };
+        let encoded = sparse.to_rle();
+        assert!(encoded.lines().all(|line| line.len() <= 70));
+        assert_eq!(parse_rle(&encoded).unwrap(), sparse);
+    }
+
+    #[test]
+    fn parse_missing_header() {
+        let result = parse_rle("# only a comment");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Empty RLE pattern")
+        );
+    }
+
+    #[test]
+    fn parse_cells_outside_bounds() {
+        let result = parse_rle("x = 2, y = 1\n3o!");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("outside of declared")
+        );
+    }
+
+    #[test]
+    fn parse_overflowing_run_count() {
+        let result = parse_rle("x = 2, y = 1\n99999999999999999999999o!");
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("run count too large")
+        );
+        // A run that fits in usize but pushes x past it
+        let far = format!("x = 2, y = 1\n{}b{}bo!", usize::MAX, usize::MAX);
+        assert!(parse_rle(&far).is_err());
+    }
+
+    #[test]
+    fn parse_oversized_header() {
+        for header in ["x = 1000000000, y = 1", "x = 18446744073709551615, y = 1"] {
+            let result = parse_rle(&format!("{}\n999999999o!", header));
+            assert!(
+                result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("larger than 1000 along a side")
+            );
+        }
+        assert!(parse_rle("x = 1000, y = 1000\no!").is_ok());
+    }
+
+    #[test]
+    fn parse_unexpected_token() {
+        let result = parse_rle("x = 1, y = 1\nz!");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Unexpected RLE token")
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    constants::{
+        DEAD_CELL_R_G_B, FIRE_BURNOUT_CHANCE, FIRE_R_G_B, SAND_R_G_B, WALL_R_G_B, WATER_R_G_B,
+    },
+    utils::{FrameError, create_frame_message, create_pixels_message},
+};
+use axum_tws::Message;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Material {
+    Empty,
+    Sand,
+    Water,
+    Wall,
+    Fire,
+}
+
+impl Material {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Material::Empty),
+            1 => Some(Material::Sand),
+            2 => Some(Material::Water),
+            3 => Some(Material::Wall),
+            4 => Some(Material::Fire),
+            _ => None,
+        }
+    }
+
+    fn rgb(&self) -> [u8; 3] {
+        match self {
+            Material::Empty => DEAD_CELL_R_G_B,
+            Material::Sand => SAND_R_G_B,
+            Material::Water => WATER_R_G_B,
+            Material::Wall => WALL_R_G_B,
+            Material::Fire => FIRE_R_G_B,
+        }
+    }
+
+    // Materials this one sinks through, swapping places with them
+    fn displaces(&self, other: Material) -> bool {
+        match self {
+            Material::Sand => matches!(other, Material::Empty | Material::Water),
+            Material::Water | Material::Fire => other == Material::Empty,
+            Material::Empty | Material::Wall => false,
+        }
+    }
+}
+
+/// Falling-sand grid: sand piles up, water flows sideways, walls stay put and fire rises
+/// until it burns out or touches water. The grid's edges act as walls.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub width: u16,
+    pub height: u16,
+    cells: Vec<Material>,
+}
+
+impl Sandbox {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Material::Empty; width as usize * height as usize],
+        }
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Fills the given cells, skipping those outside the grid. Returns the changed pixels.
+    pub fn spawn(&mut self, material: Material, cells: &[(u16, u16)]) -> Vec<(u16, u16, [u8; 3])> {
+        let mut pixels = Vec::new();
+        for &(x, y) in cells {
+            if x >= self.width || y >= self.height {
+                continue;
+            }
+            let index = self.index(x, y);
+            if self.cells[index] != material {
+                self.cells[index] = material;
+                pixels.push((x, y, material.rgb()));
+            }
+        }
+        pixels
+    }
+
+    // Cell (x + dx, y + dy), if it's on the grid
+    fn offset(&self, x: u16, y: u16, dx: i32, dy: i32) -> Option<usize> {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        ((0..self.width as i32).contains(&nx) && (0..self.height as i32).contains(&ny))
+            .then(|| ny as usize * self.width as usize + nx as usize)
+    }
+
+    // Moves the cell to the first of `moves` it can displace, if any
+    fn try_move(&mut self, x: u16, y: u16, moves: &[(i32, i32)], moved: &mut [bool]) {
+        let from = self.index(x, y);
+        let material = self.cells[from];
+        for &(dx, dy) in moves {
+            if let Some(to) = self.offset(x, y, dx, dy)
+                && material.displaces(self.cells[to])
+            {
+                self.cells.swap(from, to);
+                moved[from] = true;
+                moved[to] = true;
+                return;
+            }
+        }
+    }
+
+    /// Advances the physics by one tick, returning the pixels that changed
+    pub fn step(&mut self, rng: &mut impl Rng) -> Vec<(u16, u16, [u8; 3])> {
+        let before = self.cells.clone();
+        let mut moved = vec![false; self.cells.len()];
+
+        // Bottom-up so a falling column moves as one, alternating sides so piles stay even
+        for y in (0..self.height).rev() {
+            let left_first = rng.random_bool(0.5);
+            for i in 0..self.width {
+                let x = if left_first { i } else { self.width - 1 - i };
+                let index = self.index(x, y);
+                if moved[index] {
+                    continue;
+                }
+
+                let side = if rng.random_bool(0.5) { 1 } else { -1 };
+                match self.cells[index] {
+                    Material::Empty | Material::Wall => {}
+                    Material::Sand => {
+                        self.try_move(x, y, &[(0, 1), (side, 1), (-side, 1)], &mut moved);
+                    }
+                    Material::Water => {
+                        self.try_move(
+                            x,
+                            y,
+                            &[(0, 1), (side, 1), (-side, 1), (side, 0), (-side, 0)],
+                            &mut moved,
+                        );
+                    }
+                    Material::Fire => {
+                        let doused = [(0, -1), (-1, 0), (1, 0), (0, 1)].iter().any(|&(dx, dy)| {
+                            self.offset(x, y, dx, dy)
+                                .is_some_and(|i| self.cells[i] == Material::Water)
+                        });
+                        if doused || rng.random_bool(FIRE_BURNOUT_CHANCE) {
+                            self.cells[index] = Material::Empty;
+                        } else {
+                            self.try_move(x, y, &[(0, -1), (side, -1), (-side, -1)], &mut moved);
+                        }
+                    }
+                }
+            }
+        }
+
+        let width = self.width as usize;
+        before
+            .iter()
+            .zip(&self.cells)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(i, (_, after))| ((i % width) as u16, (i / width) as u16, after.rgb()))
+            .collect()
+    }
+
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        self.cells.iter().flat_map(|cell| cell.rgb()).collect()
+    }
+}
+
+/// Falling-sand state of a single room
+pub struct SandState {
+    sandbox: RwLock<Sandbox>,
+    // Lock after `sandbox` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl SandState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        Self {
+            sandbox: RwLock::new(Sandbox::new(width, height)),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The whole sandbox, without stepping the physics
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        let sandbox = self.sandbox.read().unwrap_or_else(|e| e.into_inner());
+        create_frame_message(sandbox.width, sandbox.height, sandbox.to_rgb_data())
+    }
+
+    /// Restarts the random stream from `seed` and empties the sandbox
+    pub fn reseed(&self, seed: u64) {
+        *self.rng() = StdRng::seed_from_u64(seed);
+        let mut sandbox = self.sandbox.write().unwrap_or_else(|e| e.into_inner());
+        *sandbox = Sandbox::new(sandbox.width, sandbox.height);
+    }
+
+    /// Fills cells with `material` and returns the changed pixels
+    pub fn spawn(&self, material: Material, cells: &[(u16, u16)]) -> Result<Message, FrameError> {
+        let mut sandbox = self.sandbox.write().unwrap_or_else(|e| e.into_inner());
+        let pixels = sandbox.spawn(material, cells);
+        debug!("Sand: Spawned {} cells of {:?}", pixels.len(), material);
+        Ok(create_pixels_message(&pixels))
+    }
+
+    /// Advances the physics by one tick. Returns the changed pixels, None once everything
+    /// has settled.
+    pub fn step(&self) -> Result<Option<Message>, FrameError> {
+        let mut sandbox = self.sandbox.write().unwrap_or_else(|e| e.into_inner());
+        let pixels = sandbox.step(&mut *self.rng());
+        if pixels.is_empty() {
+            return Ok(None);
+        }
+
+        debug!("Sand: Stepped physics, {} changed cells", pixels.len());
+        if pixels.len() > u16::MAX as usize {
+            return create_frame_message(sandbox.width, sandbox.height, sandbox.to_rgb_data())
+                .map(Some);
+        }
+        Ok(Some(create_pixels_message(&pixels)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(sandbox: &Sandbox, x: u16, y: u16) -> Material {
+        sandbox.cells[sandbox.index(x, y)]
+    }
+
+    #[test]
+    fn sand_piles_and_water_sinks_below_it() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sandbox = Sandbox::new(3, 3);
+        sandbox.spawn(Material::Wall, &[(0, 2), (2, 2)]);
+        sandbox.spawn(Material::Water, &[(1, 2)]);
+        sandbox.spawn(Material::Sand, &[(1, 0)]);
+
+        let pixels = sandbox.step(&mut rng);
+        assert_eq!(pixels, vec![(1, 0, DEAD_CELL_R_G_B), (1, 1, SAND_R_G_B)]);
+
+        // Sand swaps with the water underneath, the water has nowhere else to go
+        sandbox.step(&mut rng);
+        assert_eq!(at(&sandbox, 1, 2), Material::Sand);
+        assert_eq!(at(&sandbox, 1, 1), Material::Water);
+        assert_eq!(at(&sandbox, 0, 2), Material::Wall);
+    }
+
+    #[test]
+    fn fire_is_doused_by_water() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sandbox = Sandbox::new(3, 1);
+        sandbox.spawn(Material::Wall, &[(0, 0)]);
+        sandbox.spawn(Material::Fire, &[(1, 0)]);
+        sandbox.spawn(Material::Water, &[(2, 0)]);
+
+        sandbox.step(&mut rng);
+        assert!(!sandbox.cells.contains(&Material::Fire));
+        assert!(sandbox.cells.contains(&Material::Water));
+    }
+}
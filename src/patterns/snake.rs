@@ -0,0 +1,386 @@
+use crate::{
+    constants::{
+        DEAD_CELL_R_G_B, MAX_SNAKES, SNAKE_FOOD_COUNT, SNAKE_FOOD_R_G_B, SNAKE_START_LENGTH,
+    },
+    utils::{FrameError, create_frame_changes_message, create_frame_message},
+};
+use axum_tws::Message;
+use game_of_life_core::create_random_rgb;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use tracing::{debug, info};
+
+// Random cells tried when looking for a free one, the grid is mostly empty
+const FREE_CELL_ATTEMPTS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Direction::Up),
+            1 => Some(Direction::Down),
+            2 => Some(Direction::Left),
+            3 => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    fn is_opposite(&self, other: Direction) -> bool {
+        let ((dx, dy), (ox, oy)) = (self.delta(), other.delta());
+        dx + ox == 0 && dy + oy == 0
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snake {
+    // Connection steering this snake
+    owner: String,
+    // Head first
+    body: VecDeque<(u16, u16)>,
+    direction: Direction,
+    // Applied on the next tick, turns are checked against `direction` so two quick ones
+    // can't reverse the snake into itself
+    next_direction: Direction,
+    // Cells left to grow by, the tail stays put while there are any
+    growth: usize,
+    rgb: [u8; 3],
+}
+
+/// Multiplayer snake: each connection steers its own snake, which grows by eating food
+/// and crashes into the grid's edges and any snake's body. Crashed snakes are removed
+/// until their owner joins again.
+#[derive(Debug, Clone)]
+pub struct SnakeGame {
+    pub width: u16,
+    pub height: u16,
+    snakes: Vec<Snake>,
+    food: Vec<(u16, u16)>,
+    // What clients were last sent, None until they get a full frame
+    rendered: Option<Vec<u8>>,
+}
+
+impl SnakeGame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            snakes: Vec::new(),
+            food: Vec::new(),
+            rendered: None,
+        }
+    }
+
+    fn snake_of(&self, connection_id: &str) -> Option<usize> {
+        self.snakes
+            .iter()
+            .position(|snake| snake.owner == connection_id)
+    }
+
+    fn is_free(&self, cell: (u16, u16)) -> bool {
+        !self.food.contains(&cell) && !self.snakes.iter().any(|s| s.body.contains(&cell))
+    }
+
+    fn random_free_cell(&self, rng: &mut impl Rng) -> Option<(u16, u16)> {
+        (0..FREE_CELL_ATTEMPTS)
+            .map(|_| {
+                (
+                    rng.random_range(0..self.width),
+                    rng.random_range(0..self.height),
+                )
+            })
+            .find(|&cell| self.is_free(cell))
+    }
+
+    /// Spawns a snake for the connection on a free cell. A connection keeps the snake it
+    /// already has. Returns false when the game is full or no free cell turned up.
+    pub fn join(&mut self, connection_id: &str, rng: &mut impl Rng) -> bool {
+        if self.snake_of(connection_id).is_some() {
+            return true;
+        }
+        if self.snakes.len() >= MAX_SNAKES {
+            return false;
+        }
+        let Some(head) = self.random_free_cell(rng) else {
+            return false;
+        };
+
+        let direction = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ][rng.random_range(0..4)];
+        self.snakes.push(Snake {
+            owner: connection_id.to_string(),
+            body: VecDeque::from([head]),
+            direction,
+            next_direction: direction,
+            growth: SNAKE_START_LENGTH - 1,
+            rgb: create_random_rgb(rng),
+        });
+        true
+    }
+
+    /// Removes the connection's snake, returning whether it had one
+    pub fn leave(&mut self, connection_id: &str) -> bool {
+        let Some(index) = self.snake_of(connection_id) else {
+            return false;
+        };
+        self.snakes.remove(index);
+        true
+    }
+
+    /// Turns the connection's snake on the next tick, reversing into itself is ignored.
+    /// Returns whether the connection has a snake.
+    pub fn turn(&mut self, connection_id: &str, direction: Direction) -> bool {
+        let Some(index) = self.snake_of(connection_id) else {
+            return false;
+        };
+        let snake = &mut self.snakes[index];
+        if !direction.is_opposite(snake.direction) {
+            snake.next_direction = direction;
+        }
+        true
+    }
+
+    /// Moves every snake one cell and tops up the food. Returns the owners of the snakes
+    /// that crashed.
+    pub fn step(&mut self, rng: &mut impl Rng) -> Vec<String> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let heads: Vec<Option<(u16, u16)>> = self
+            .snakes
+            .iter_mut()
+            .map(|snake| {
+                snake.direction = snake.next_direction;
+                let (dx, dy) = snake.direction.delta();
+                let (x, y) = (snake.body[0].0 as i32 + dx, snake.body[0].1 as i32 + dy);
+                ((0..width).contains(&x) && (0..height).contains(&y))
+                    .then_some((x as u16, y as u16))
+            })
+            .collect();
+
+        // Tails move first, a head may take the cell a tail just left
+        for snake in &mut self.snakes {
+            if snake.growth > 0 {
+                snake.growth -= 1;
+            } else {
+                snake.body.pop_back();
+            }
+        }
+        let bodies: HashSet<(u16, u16)> = self
+            .snakes
+            .iter()
+            .flat_map(|snake| snake.body.iter().copied())
+            .collect();
+
+        let mut crashed = Vec::new();
+        for (i, snake) in self.snakes.iter_mut().enumerate() {
+            let head = heads[i].filter(|head| {
+                !bodies.contains(head)
+                    && !heads
+                        .iter()
+                        .enumerate()
+                        .any(|(j, other)| j != i && *other == Some(*head))
+            });
+            let Some(head) = head else {
+                crashed.push(snake.owner.clone());
+                continue;
+            };
+
+            snake.body.push_front(head);
+            if let Some(food) = self.food.iter().position(|&food| food == head) {
+                self.food.swap_remove(food);
+                snake.growth += 1;
+            }
+        }
+        self.snakes.retain(|snake| !crashed.contains(&snake.owner));
+
+        while self.food.len() < SNAKE_FOOD_COUNT {
+            let Some(cell) = self.random_free_cell(rng) else {
+                break;
+            };
+            self.food.push(cell);
+        }
+        crashed
+    }
+
+    pub fn to_rgb_data(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let mut rgb_data = DEAD_CELL_R_G_B.repeat(width * self.height as usize);
+        let mut paint = |(x, y): (u16, u16), rgb: [u8; 3]| {
+            let offset = (y as usize * width + x as usize) * 3;
+            rgb_data[offset..offset + 3].copy_from_slice(&rgb);
+        };
+
+        for &food in &self.food {
+            paint(food, SNAKE_FOOD_R_G_B);
+        }
+        for snake in &self.snakes {
+            for &cell in &snake.body {
+                paint(cell, snake.rgb);
+            }
+        }
+        rgb_data
+    }
+
+    // Cells that changed since the last call, or the whole frame if clients need one
+    fn render_changes(&mut self) -> Result<Option<Message>, FrameError> {
+        let frame = self.to_rgb_data();
+        let rendered = self.rendered.replace(frame.clone());
+        create_frame_changes_message(self.width, self.height, rendered.as_deref(), frame)
+    }
+}
+
+/// Snake game of a single room
+pub struct SnakeState {
+    game: Mutex<SnakeGame>,
+    // Lock after `game` when holding both
+    rng: Mutex<StdRng>,
+}
+
+impl SnakeState {
+    pub fn new(width: u16, height: u16, seed: u64) -> Self {
+        Self {
+            game: Mutex::new(SnakeGame::new(width, height)),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    fn game(&self) -> MutexGuard<'_, SnakeGame> {
+        self.game.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The whole grid, leaving what the other clients were last sent alone
+    pub fn current_frame(&self) -> Result<Message, FrameError> {
+        let game = self.game();
+        create_frame_message(game.width, game.height, game.to_rgb_data())
+    }
+
+    /// Restarts the random stream from `seed`, keeping the snakes
+    pub fn reseed(&self, seed: u64) {
+        *self.rng() = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn join(&self, connection_id: &str) -> bool {
+        let mut game = self.game();
+        let joined = game.join(connection_id, &mut *self.rng());
+        // Clients may still be showing another pattern
+        game.rendered = None;
+        debug!(
+            "Snake: {} joined: {}, {} snakes",
+            connection_id,
+            joined,
+            game.snakes.len()
+        );
+        joined
+    }
+
+    pub fn leave(&self, connection_id: &str) {
+        if self.game().leave(connection_id) {
+            info!("Snake: {} left the game", connection_id);
+        }
+    }
+
+    pub fn turn(&self, connection_id: &str, direction: Direction) -> bool {
+        self.game().turn(connection_id, direction)
+    }
+
+    /// Advances the game one tick, returning the changed cells
+    pub fn step(&self) -> Result<Option<Message>, FrameError> {
+        let mut game = self.game();
+        let crashed = game.step(&mut *self.rng());
+        for owner in crashed {
+            info!("Snake: {}'s snake crashed", owner);
+        }
+        game.render_changes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(game: &mut SnakeGame, owner: &str, body: &[(u16, u16)], direction: Direction) {
+        game.snakes.push(Snake {
+            owner: owner.to_string(),
+            body: body.iter().copied().collect(),
+            direction,
+            next_direction: direction,
+            growth: 0,
+            rgb: [1, 2, 3],
+        });
+    }
+
+    #[test]
+    fn snakes_turn_but_never_reverse() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut game = SnakeGame::new(10, 10);
+        place(&mut game, "a", &[(5, 5), (4, 5)], Direction::Right);
+
+        assert!(game.turn("a", Direction::Up));
+        assert!(game.turn("a", Direction::Left));
+        assert!(!game.turn("b", Direction::Up));
+        game.step(&mut rng);
+        assert_eq!(game.snakes[0].body, [(5, 4), (5, 5)]);
+
+        assert!(game.join("b", &mut rng));
+        assert!(game.leave("a"));
+        assert_eq!(game.snakes.len(), 1);
+        assert_eq!(game.snakes[0].body.len(), 1);
+    }
+
+    #[test]
+    fn eating_food_grows_the_snake() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut game = SnakeGame::new(10, 10);
+        place(&mut game, "a", &[(1, 1), (0, 1)], Direction::Right);
+        game.food.push((2, 1));
+
+        game.step(&mut rng);
+        game.step(&mut rng);
+        assert_eq!(game.snakes[0].body, [(3, 1), (2, 1), (1, 1)]);
+        assert_eq!(game.food.len(), SNAKE_FOOD_COUNT);
+    }
+
+    #[test]
+    fn snakes_crash_into_edges_and_each_other() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut game = SnakeGame::new(10, 10);
+        place(&mut game, "edge", &[(9, 0)], Direction::Right);
+        place(&mut game, "left", &[(3, 5)], Direction::Right);
+        place(&mut game, "right", &[(5, 5)], Direction::Left);
+        place(&mut game, "body", &[(7, 2)], Direction::Left);
+        place(
+            &mut game,
+            "wall",
+            &[(6, 3), (6, 2), (6, 1)],
+            Direction::Down,
+        );
+
+        let mut crashed = game.step(&mut rng);
+        crashed.sort();
+        assert_eq!(crashed, ["body", "edge", "left", "right"]);
+        assert_eq!(game.snakes.len(), 1);
+    }
+}
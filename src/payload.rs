@@ -1,14 +1,41 @@
 use crate::{
-    constants::{CANVAS_WIDTH, HELLO_PAYLOAD, message_types},
-    patterns::{gol, mlp},
-    protocol::{PROTOCOL_VERSION, WsMessage, encode_ws_message},
+    bots::{BotKind, start_bot},
+    constants::{
+        HELLO_PAYLOAD, MAX_BOID_SPAWN, MAX_BOID_WEIGHT, MAX_CELL_BATCH, MAX_REACTION_RATE,
+        message_types, topics,
+    },
+    input::{decode_input_event, input_targets},
+    metrics::{METRICS, Outcome},
+    patterns::{
+        boids::BoidWeights, brush::Brush, library::LibraryPattern, mlp::StrokeOrder,
+        pong::PaddleSide, render_style::RenderStyle, rle::parse_rle, sand::Material,
+        snake::Direction,
+    },
+    protocol::{CellPayload, PROTOCOL_VERSION, WsMessage, encode_ws_message},
+    registry::{SERVER, range_of},
+    room::{BroadcastMessage, RoomState},
+    saves::{load_state, save_state},
+    soup::start_soup_search,
+    state::ActivePattern,
+    utils::{
+        FrameError, create_bot_status_message, create_checkpoint_list_message,
+        create_mlp_painting_list_message, create_paddle_assigned_message,
+        create_simulation_status_message, create_state_saved_message,
+    },
 };
+use anyhow::Result;
 use axum_tws::Message;
-use rand::Rng;
-use tracing::{debug, warn};
+use bytes::Bytes;
+use game_of_life_core::{ColorScheme, EngineKind, Region};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug, debug_span, field, warn};
 
 pub struct WsPayload {
-    pub parsed: WsMessage,
+    pub parsed: WsMessage<Bytes>,
+    pub room: Arc<RoomState>,
+    // Connection that sent the message
+    pub connection_id: String,
 }
 
 #[allow(dead_code)]
@@ -22,60 +49,1163 @@ pub fn get_dummy_payload() -> Message {
     encode_ws_message(&response)
 }
 
+/// Whether `handle_payload` acts on a client message type, anything else is reported
+/// back to the client as unknown
+pub fn is_handled(msg_type: u8) -> bool {
+    message_types::name(msg_type).is_some()
+        && !SERVER.contains(msg_type)
+        && !matches!(
+            msg_type,
+            message_types::HELLO
+                | message_types::SUBSCRIBE
+                | message_types::UNSUBSCRIBE
+                | message_types::RESYNC_REQUEST
+                | message_types::SET_TEXT_PREFERENCES
+                | message_types::SET_NICKNAME
+                | message_types::CURSOR_MOVE
+                | message_types::CHAT
+                | message_types::AUTH
+                | message_types::KICK_CLIENT
+                | message_types::GET_SERVER_STATS
+                | message_types::ADD_SCHEDULE_ENTRY
+                | message_types::REMOVE_SCHEDULE_ENTRY
+                | message_types::LIST_SCHEDULE
+                | message_types::LIST_ROOMS
+                | message_types::CREATE_ROOM
+        )
+}
+
+/// Who the response to a client message goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyRoute {
+    // Only the connection that sent the message, e.g. a save confirmation
+    Sender,
+    // Every client in the room, filtered by their subscriptions
+    Room,
+}
+
+pub fn reply_route(msg_type: u8) -> ReplyRoute {
+    match msg_type {
+        message_types::CLAIM_PADDLE
+        | message_types::SAVE_STATE
+        | message_types::LIST_CHECKPOINTS
+        | message_types::LIST_MLP_PAINTINGS => ReplyRoute::Sender,
+        _ => ReplyRoute::Room,
+    }
+}
+
 impl WsPayload {
-    pub fn handle_payload(&self) -> Message {
-        debug!(
-            "Processing payload - Type: {}, Size: {} bytes",
-            self.parsed.msg_type,
-            self.parsed.payload.len()
+    /// Acts on the message in a span naming its type, and times it for /metrics. Fails when
+    /// the pattern's output couldn't be encoded, which the sender is told about.
+    pub fn handle_payload(&self) -> Result<Option<BroadcastMessage>, FrameError> {
+        let msg_type = self.parsed.msg_type;
+        let payload_bytes = self.parsed.payload.len();
+        let span = debug_span!(
+            "handle_payload",
+            message_type = message_types::name(msg_type).unwrap_or("unknown"),
+            payload_bytes,
+            elapsed_us = field::Empty,
+            outcome = field::Empty,
         );
-        match self.parsed.msg_type {
+        let _entered = span.enter();
+
+        let started = Instant::now();
+        let response = self.dispatch();
+        let elapsed = started.elapsed();
+        let outcome = if matches!(response, Ok(Some(_))) {
+            Outcome::Replied
+        } else {
+            Outcome::Silent
+        };
+        span.record("elapsed_us", elapsed.as_micros() as u64);
+        span.record("outcome", outcome.label());
+        debug!("Handled payload in {:?}", elapsed);
+        METRICS.record_message(msg_type, outcome, payload_bytes, elapsed);
+        response
+    }
+
+    fn dispatch(&self) -> Result<Option<BroadcastMessage>, FrameError> {
+        // Commands reach only the pattern the room shows, SWITCH_MODE changes it
+        if let Err(e) = self.room.modes.admit(self.parsed.msg_type) {
+            warn!("Dropping message: {}", e);
+            return Ok(None);
+        }
+        let pattern = ActivePattern::for_message_type(self.parsed.msg_type);
+
+        let response = match self.parsed.msg_type {
             message_types::CREATE_NEW_GOL_GENERATION => {
                 debug!("GOL: Creating a new generation");
-                gol::create_new_generation()
+                self.room.gol.create_new_generation()?
             }
             message_types::AWAKEN_RANDOM_GOL_CELL => {
                 debug!("GOL: Adding a random live cell to current generation");
-                gol::awaken_random_cell()
+                self.room.gol.awaken_random_cell()?
             }
             message_types::KILL_RANDOM_GOL_CELL => {
                 debug!("GOL: Killing a random cell of current generation");
-                gol::kill_random_cell()
+                self.room.gol.kill_random_cell()?
             }
             message_types::ADVANCE_GOL_GENERATION => {
                 debug!("GOL: Advancing to next generation");
-                gol::advance_generation()
+                self.room.gol.advance_generation()?
             }
             message_types::KILL_ALL_GOL_CELLS => {
                 debug!("GOL: Killing all the cells");
-                gol::kill_all_cells()
+                self.room.gol.kill_all_cells()?
+            }
+            message_types::SEED_GOL_FROM_MLP_PAINTING => {
+                debug!("GOL: Seeding a new generation from the painting");
+                self.room.gol.seed_from_painting(&self.room.mlp)?
             }
             message_types::CREATE_NEW_MLP_PAINTING => {
+                let Some(order) = self.stroke_order() else {
+                    return Ok(None);
+                };
                 debug!("MLP: Creating new painting canvas");
-                mlp::start_new_painting()
+                self.room.mlp.start_new_painting(order)?
             }
             message_types::ADVANCE_MLP_PAINTING => {
-                let mut rng = rand::rng();
-                debug!("MLP: Advancing to next stroke");
-                mlp::apply_brush_strokes_batch(rng.random_range(0..CANVAS_WIDTH as usize))
+                return Ok(self
+                    .handle_advance_mlp_painting()?
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Mlp, frame)));
+            }
+            message_types::SET_MLP_STYLE => {
+                return Ok(self
+                    .handle_set_mlp_style()?
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Mlp, frame)));
+            }
+            message_types::ADVANCE_MLP_PAINTING_TO => {
+                return Ok(self
+                    .handle_advance_mlp_painting_to()?
+                    .map(|pixels| BroadcastMessage::from_pattern(ActivePattern::Mlp, pixels)));
+            }
+            message_types::PAINT_MLP_FROM_GOL_GENERATION => {
+                debug!("MLP: Painting the current GOL generation");
+                self.room.mlp.paint_from_generation(&self.room.gol)?
+            }
+            message_types::UPLOAD_MLP_IMAGE => {
+                return Ok(self
+                    .handle_upload_mlp_image()
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Mlp, frame)));
+            }
+            message_types::ADD_MLP_PAINTING => {
+                return Ok(self.painting_frame(self.room.mlp.add_painting()));
+            }
+            message_types::SELECT_MLP_PAINTING => {
+                let Some(id) = self.painting_id() else {
+                    return Ok(None);
+                };
+                return Ok(self.painting_frame(self.room.mlp.select_painting(id)));
+            }
+            message_types::DELETE_MLP_PAINTING => {
+                let Some(id) = self.painting_id() else {
+                    return Ok(None);
+                };
+                return Ok(self.painting_frame(self.room.mlp.delete_painting(id)));
+            }
+            message_types::LIST_MLP_PAINTINGS => {
+                let (selected, paintings) = self.room.mlp.painting_list();
+                return Ok(Some(BroadcastMessage::system(
+                    create_mlp_painting_list_message(selected, &paintings),
+                )));
+            }
+            message_types::CREATE_NEW_REACTION => {
+                debug!("Reaction: Seeding new spots");
+                self.room.reaction.create_new_reaction()?
+            }
+            message_types::ADVANCE_REACTION => {
+                debug!("Reaction: Advancing");
+                self.room.reaction.advance()?
+            }
+            message_types::SET_REACTION_RATES => {
+                return Ok(self
+                    .handle_set_reaction_rates()?
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Reaction, frame)));
+            }
+            message_types::CLAIM_PADDLE => return Ok(self.handle_claim_paddle()),
+            message_types::MOVE_PADDLE => {
+                self.handle_move_paddle();
+                return Ok(None);
+            }
+            message_types::CREATE_NEW_IMMIGRATION_GENERATION => {
+                debug!("Immigration: Creating a new grid");
+                self.room.immigration.create_new_generation()?
+            }
+            message_types::ADVANCE_IMMIGRATION_GENERATION => {
+                debug!("Immigration: Advancing generation");
+                self.room.immigration.advance_generation()?
+            }
+            message_types::AWAKEN_TEAM_CELL => {
+                return Ok(self
+                    .handle_awaken_team_cell()?
+                    .map(|pixel| BroadcastMessage::new(topics::PIXEL_EVENTS, pixel)));
+            }
+            message_types::JOIN_SNAKE => {
+                if !self.room.snake.join(&self.connection_id) {
+                    warn!("Dropping snake join, the game is full");
+                }
+                return Ok(None);
+            }
+            message_types::TURN_SNAKE => {
+                self.handle_turn_snake();
+                return Ok(None);
+            }
+            message_types::SPAWN_BOIDS => {
+                return Ok(self
+                    .handle_spawn_boids()?
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Boids, frame)));
+            }
+            message_types::SET_BOID_WEIGHTS => {
+                return Ok(self
+                    .handle_set_boid_weights()?
+                    .map(|frame| BroadcastMessage::from_pattern(ActivePattern::Boids, frame)));
+            }
+            message_types::RESET_BOIDS => {
+                debug!("Boids: Resetting the flock");
+                self.room.boids.reset()?
+            }
+            message_types::CREATE_NEW_BRAIN_GENERATION => {
+                debug!("Brian's Brain: Creating a new grid");
+                self.room.brain.create_new_generation()?
+            }
+            message_types::ADVANCE_BRAIN_GENERATION => {
+                debug!("Brian's Brain: Advancing generation");
+                self.room.brain.advance_generation()?
             }
             message_types::REQUEST_RANDOM_COLORED_PIXEL => {
-                let x = self.parsed.payload[0];
-                let y = self.parsed.payload[1];
-                debug!("GOL: Adding a live cell to current generation");
-                gol::awaken_cell(x as u16, y as u16)
+                return Ok(self
+                    .handle_request_pixel()?
+                    .map(|pixel| BroadcastMessage::new(topics::PIXEL_EVENTS, pixel)));
             }
-            message_types::HELLO => {
-                debug!("Processing HELLO message");
-                self.create_echo_response()
+            message_types::SET_SIMULATION_SPEED => {
+                return Ok(self
+                    .handle_set_simulation_speed()
+                    .map(BroadcastMessage::system));
+            }
+            message_types::SET_AUTOPLAY => {
+                return Ok(self.handle_set_autoplay().map(BroadcastMessage::system));
+            }
+            message_types::START_SOUP_SEARCH => {
+                if !start_soup_search(&self.room) {
+                    debug!("Soup search already running");
+                }
+                return Ok(None);
+            }
+            message_types::STOP_SOUP_SEARCH => {
+                if !self.room.soup_search.stop() {
+                    debug!("No soup search to stop");
+                }
+                return Ok(None);
+            }
+            message_types::START_BOT | message_types::STOP_BOT => {
+                return Ok(self.handle_bot().map(BroadcastMessage::system));
+            }
+            message_types::SWITCH_MODE => return self.handle_switch_mode(),
+            message_types::SAVE_STATE => {
+                return Ok(self.handle_save_state().map(BroadcastMessage::system));
+            }
+            message_types::LOAD_STATE => {
+                return Ok(self
+                    .handle_load_state()
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::SELECT_ENGINE => {
+                self.handle_select_engine();
+                return Ok(None);
+            }
+            message_types::LIST_CHECKPOINTS => {
+                return Ok(self.handle_list_checkpoints().map(BroadcastMessage::system));
+            }
+            message_types::RESTORE_CHECKPOINT => {
+                return Ok(self
+                    .handle_restore_checkpoint()
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::SET_SEED => {
+                return Ok(self
+                    .handle_set_seed()?
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::PAUSE_SIMULATION => {
+                debug!("Pausing simulation");
+                self.room.simulation.set_paused(true);
+                self.create_simulation_status()
+            }
+            message_types::RESUME_SIMULATION => {
+                debug!("Resuming simulation");
+                self.room.simulation.set_paused(false);
+                self.create_simulation_status()
+            }
+            message_types::LOAD_GOL_PATTERN => {
+                return Ok(self
+                    .handle_load_pattern()?
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::STAMP_GOL_PATTERN => {
+                return Ok(self
+                    .handle_stamp_pattern()
+                    .map(|pixels| BroadcastMessage::new(topics::PIXEL_EVENTS, pixels)));
             }
+            message_types::AWAKEN_CELLS_BATCH => {
+                return Ok(self
+                    .handle_awaken_cells_batch()
+                    .map(|pixels| BroadcastMessage::new(topics::PIXEL_EVENTS, pixels)));
+            }
+            message_types::SET_COLOR_SCHEME => {
+                return Ok(self
+                    .handle_set_color_scheme()?
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::TOGGLE_CELL => {
+                return Ok(self
+                    .handle_toggle_cell()
+                    .map(|region| BroadcastMessage::new(topics::GOL_FRAMES, region)));
+            }
+            message_types::FILL_RECT | message_types::CLEAR_RECT => {
+                let alive = self.parsed.msg_type == message_types::FILL_RECT;
+                return Ok(self
+                    .handle_fill_rect(alive)
+                    .map(|region| BroadcastMessage::new(topics::GOL_FRAMES, region)));
+            }
+            message_types::UNDO | message_types::REDO => {
+                let undo = self.parsed.msg_type == message_types::UNDO;
+                let response = if undo {
+                    self.room.gol.undo()?
+                } else {
+                    self.room.gol.redo()?
+                };
+                if response.is_none() {
+                    debug!("GOL: Nothing to {}", if undo { "undo" } else { "redo" });
+                }
+                return Ok(response
+                    .map(|response| BroadcastMessage::from_pattern(ActivePattern::Gol, response)));
+            }
+            message_types::SEED_GOL_FROM_IMAGE => {
+                return Ok(self
+                    .handle_seed_gol_from_image()
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::SET_GOL_HYBRID => {
+                return Ok(self
+                    .handle_set_gol_hybrid()?
+                    .map(|frame| BroadcastMessage::new(topics::GOL_FRAMES, frame)));
+            }
+            message_types::BRUSH_STROKE => {
+                return Ok(self
+                    .handle_brush_stroke()?
+                    .map(|response| BroadcastMessage::from_pattern(ActivePattern::Gol, response)));
+            }
+            message_types::SPAWN_MATERIAL => {
+                return Ok(self.handle_spawn_material()?.map(|response| {
+                    BroadcastMessage::from_pattern(ActivePattern::Sand, response)
+                }));
+            }
+            message_types::INPUT_EVENT => return self.handle_input_event(),
             unknown_type => {
-                warn!("Unknown message type: {}, echoing back", unknown_type);
+                warn!(
+                    "Unknown message type: {} (range: {}), echoing back",
+                    unknown_type,
+                    range_of(unknown_type).map_or("unregistered", |range| range.owner)
+                );
                 self.create_echo_response()
             }
+        };
+
+        Ok(Some(match pattern {
+            Some(pattern) => BroadcastMessage::from_pattern(pattern, response),
+            None => BroadcastMessage::system(response),
+        }))
+    }
+
+    // Switch mode payload format:
+    // - 1 byte: id of the pattern to show, see `ActivePattern::ALL`
+    // Clients get MODE_CHANGED and then a keyframe of the pattern
+    fn handle_switch_mode(&self) -> Result<Option<BroadcastMessage>, FrameError> {
+        let &[id] = &self.parsed.payload[..] else {
+            warn!(
+                "Invalid switch mode payload length: {}",
+                self.parsed.payload.len()
+            );
+            return Ok(None);
+        };
+        let Some(pattern) = ActivePattern::from_id(id) else {
+            warn!("Dropping switch to unknown mode {}", id);
+            return Ok(None);
+        };
+        if pattern == self.room.active_pattern() {
+            debug!("Room already shows {}", pattern.name());
+            return Ok(None);
+        }
+        self.room.set_active_pattern(pattern);
+        let keyframe = self.room.keyframe()?;
+        Ok(Some(BroadcastMessage::from_pattern(pattern, keyframe)))
+    }
+
+    // Bot payload format:
+    // - 1 byte: bot id, see `BotKind::ALL`, optional for STOP_BOT which then stops every bot
+    fn handle_bot(&self) -> Option<Message> {
+        let start = self.parsed.msg_type == message_types::START_BOT;
+        match (start, &self.parsed.payload[..]) {
+            (false, []) => {
+                let stopped = self.room.bots.stop_all();
+                debug!("Stopped {} bots", stopped);
+            }
+            (_, &[id]) => {
+                let Some(kind) = BotKind::from_id(id) else {
+                    warn!("Dropping bot message for unknown bot {}", id);
+                    return None;
+                };
+                let changed = if start {
+                    start_bot(&self.room, kind)
+                } else {
+                    self.room.bots.stop(kind)
+                };
+                if !changed {
+                    debug!("The {} bot was already in that state", kind.name());
+                    return None;
+                }
+            }
+            _ => {
+                warn!("Invalid bot payload length: {}", self.parsed.payload.len());
+                return None;
+            }
+        }
+        Some(create_bot_status_message(&self.room.bots.running()))
+    }
+
+    // Simulation speed payload format:
+    // - 4 bytes: tick interval in milliseconds (big-endian)
+    fn handle_set_simulation_speed(&self) -> Option<Message> {
+        let Ok(interval_bytes) = <[u8; 4]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Dropping simulation speed message of {} bytes",
+                self.parsed.payload.len()
+            );
+            return None;
+        };
+
+        let requested = u32::from_be_bytes(interval_bytes) as u64;
+        let applied = self.room.simulation.set_tick_interval_ms(requested);
+        debug!(
+            "Simulation speed requested: {}ms, applied: {}ms",
+            requested, applied
+        );
+
+        Some(self.create_simulation_status())
+    }
+
+    // Autoplay payload format:
+    // - 1 byte: 1 to advance generations every tick, 0 to leave stepping to clients
+    fn handle_set_autoplay(&self) -> Option<Message> {
+        let [autoplay @ (0 | 1)] = self.parsed.payload[..] else {
+            warn!(
+                "Dropping invalid autoplay message {:?}",
+                self.parsed.payload
+            );
+            return None;
+        };
+
+        self.room.simulation.set_autoplay(autoplay == 1);
+        Some(self.create_simulation_status())
+    }
+
+    // Select engine payload format:
+    // - 1 byte: engine (0: vecs, 1: bits, 2: simd, 3: bits-parallel, 4: sparse, 5: auto)
+    fn handle_select_engine(&self) {
+        let Some(kind) = <[u8; 1]>::try_from(&self.parsed.payload[..])
+            .ok()
+            .and_then(|[id]| EngineKind::from_id(id))
+        else {
+            warn!(
+                "Dropping invalid engine selection {:?}",
+                self.parsed.payload
+            );
+            return;
+        };
+
+        self.room.gol.select_engine(kind);
+    }
+
+    // Seed payload format:
+    // - 8 bytes: seed (big-endian)
+    fn handle_set_seed(&self) -> Result<Option<Message>, FrameError> {
+        let Ok(seed_bytes) = <[u8; 8]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Dropping set seed message of {} bytes",
+                self.parsed.payload.len()
+            );
+            return Ok(None);
+        };
+
+        let seed = u64::from_be_bytes(seed_bytes);
+        debug!("Reseeding room {:?} with {}", self.room.id, seed);
+        self.room.reseed(seed).map(Some)
+    }
+
+    // New painting payload format:
+    // - 1 byte: stroke order (0: as generated, 1: coarse to fine, 2: random regions),
+    //   optional, as generated when left out
+    fn stroke_order(&self) -> Option<StrokeOrder> {
+        let id = match self.parsed.payload[..] {
+            [] => return Some(StrokeOrder::default()),
+            [id] => id,
+            _ => {
+                warn!(
+                    "Invalid new painting payload length: {}",
+                    self.parsed.payload.len()
+                );
+                return None;
+            }
+        };
+        let order = StrokeOrder::from_id(id);
+        if order.is_none() {
+            warn!("Dropping new painting in unknown stroke order {}", id);
+        }
+        order
+    }
+
+    // Advance painting payload format:
+    // - 2 bytes: strokes to paint (big-endian), optional, a random number when left out
+    fn handle_advance_mlp_painting(&self) -> Result<Option<Message>, FrameError> {
+        match self.parsed.payload[..] {
+            [] => {
+                debug!("MLP: Advancing by a random number of strokes");
+                self.room.mlp.advance_painting().map(Some)
+            }
+            [high, low] => {
+                let count = u16::from_be_bytes([high, low]) as usize;
+                debug!("MLP: Advancing by {} strokes", count);
+                self.room.mlp.apply_brush_strokes_batch(count).map(Some)
+            }
+            _ => {
+                warn!(
+                    "Invalid advance painting payload length: {}",
+                    self.parsed.payload.len()
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    // Advance painting to payload format:
+    // - 1 byte: share of the strokes painted afterwards, in percent (0 to 100)
+    fn handle_advance_mlp_painting_to(&self) -> Result<Option<Message>, FrameError> {
+        let &[percent @ 0..=100] = &self.parsed.payload[..] else {
+            warn!(
+                "Dropping advance painting to {:?}, expected a percentage",
+                self.parsed.payload
+            );
+            return Ok(None);
+        };
+        self.room
+            .mlp
+            .advance_painting_to(percent as usize)
+            .map(Some)
+    }
+
+    // Painting style payload format:
+    // - 1 byte: style (0: plain, 1: quantized, 2: ordered dithering, 3: Floyd-Steinberg)
+    // - 1 byte: palette size, 2 to 64, optional for plain
+    fn handle_set_mlp_style(&self) -> Result<Option<Message>, FrameError> {
+        let (id, colors) = match self.parsed.payload[..] {
+            [id] => (id, 0),
+            [id, colors] => (id, colors),
+            _ => {
+                warn!(
+                    "Invalid painting style payload length: {}",
+                    self.parsed.payload.len()
+                );
+                return Ok(None);
+            }
+        };
+        let Some(style) = RenderStyle::from_id(id, colors) else {
+            warn!("Dropping painting style {} with {} colors", id, colors);
+            return Ok(None);
+        };
+        self.room.mlp.set_style(style).map(Some)
+    }
+
+    // Upload image payload format:
+    // - N bytes: a PNG or JPEG file
+    fn handle_upload_mlp_image(&self) -> Option<Message> {
+        match self.room.mlp.paint_image(&self.parsed.payload) {
+            Ok(frame) => {
+                debug!(
+                    "MLP: Painting an uploaded image of {} bytes",
+                    self.parsed.payload.len()
+                );
+                Some(frame)
+            }
+            Err(e) => {
+                warn!("Dropping uploaded image: {:#}", e);
+                None
+            }
+        }
+    }
+
+    // Select and delete painting payload format:
+    // - 2 bytes: painting id (big-endian)
+    fn painting_id(&self) -> Option<u16> {
+        let Ok(id_bytes) = <[u8; 2]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Dropping painting message of {} bytes",
+                self.parsed.payload.len()
+            );
+            return None;
+        };
+        Some(u16::from_be_bytes(id_bytes))
+    }
+
+    // The painting shown once one was added, selected or deleted
+    fn painting_frame(&self, frame: Result<Message>) -> Option<BroadcastMessage> {
+        match frame {
+            Ok(frame) => Some(BroadcastMessage::from_pattern(ActivePattern::Mlp, frame)),
+            Err(e) => {
+                warn!("MLP: {:#}", e);
+                None
+            }
+        }
+    }
+
+    // Save and load state payload format:
+    // - N bytes: UTF-8 name of the save
+    fn save_name(&self) -> Option<&str> {
+        match std::str::from_utf8(&self.parsed.payload) {
+            Ok(name) => Some(name),
+            Err(e) => {
+                warn!("Dropping save name that isn't UTF-8: {}", e);
+                None
+            }
+        }
+    }
+
+    fn handle_save_state(&self) -> Option<Message> {
+        let name = self.save_name()?;
+        match save_state(&self.room.data_dir, name, &self.room) {
+            Ok(generation) => Some(create_state_saved_message(generation, name)),
+            Err(e) => {
+                warn!("Failed to save room {:?}: {:#}", self.room.id, e);
+                None
+            }
+        }
+    }
+
+    fn handle_load_state(&self) -> Option<Message> {
+        let name = self.save_name()?;
+        match load_state(&self.room.data_dir, name).and_then(|saved| self.room.load_saved(&saved)) {
+            Ok(frame) => {
+                debug!("GOL: Loaded save {:?} into room {:?}", name, self.room.id);
+                Some(frame)
+            }
+            Err(e) => {
+                warn!("Failed to load save {:?}: {:#}", name, e);
+                None
+            }
+        }
+    }
+
+    // Rooms without checkpoints list none
+    fn handle_list_checkpoints(&self) -> Option<Message> {
+        let generations = match &self.room.checkpoints {
+            Some(checkpoints) => match checkpoints.list() {
+                Ok(generations) => generations,
+                Err(e) => {
+                    warn!(
+                        "Failed to list checkpoints of room {:?}: {:#}",
+                        self.room.id, e
+                    );
+                    return None;
+                }
+            },
+            None => Vec::new(),
+        };
+        Some(create_checkpoint_list_message(&generations))
+    }
+
+    // Restore checkpoint payload format:
+    // - 8 bytes: generation of the checkpoint (big-endian)
+    fn handle_restore_checkpoint(&self) -> Option<Message> {
+        let Ok(generation_bytes) = <[u8; 8]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Dropping restore checkpoint message of {} bytes",
+                self.parsed.payload.len()
+            );
+            return None;
+        };
+        let Some(checkpoints) = &self.room.checkpoints else {
+            warn!("Room {:?} takes no checkpoints", self.room.id);
+            return None;
+        };
+
+        let generation = u64::from_be_bytes(generation_bytes);
+        match checkpoints.restore(&self.room, generation) {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                warn!("Failed to restore checkpoint {}: {:#}", generation, e);
+                None
+            }
         }
     }
 
+    fn create_simulation_status(&self) -> Message {
+        create_simulation_status_message(&self.room.simulation)
+    }
+
+    // Load pattern payload format:
+    // - 2 bytes: target x (big-endian)
+    // - 2 bytes: target y (big-endian)
+    // - N bytes: UTF-8 RLE pattern
+    fn handle_load_pattern(&self) -> Result<Option<Message>, FrameError> {
+        let (width, height) = self.room.gol.dimensions();
+        let (origin, rle) =
+            match CellPayload::decode_prefix(&self.parsed.payload).and_then(|(origin, rle)| {
+                origin.validate(width, height)?;
+                Ok((origin, rle))
+            }) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    warn!("Dropping load pattern message: {}", e);
+                    return Ok(None);
+                }
+            };
+        let (x, y) = (origin.x, origin.y);
+        let pattern = match std::str::from_utf8(rle)
+            .map_err(anyhow::Error::from)
+            .and_then(parse_rle)
+        {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                warn!("Dropping invalid RLE pattern: {}", e);
+                return Ok(None);
+            }
+        };
+
+        debug!("GOL: Loading RLE pattern at x:{}, y:{}", x, y);
+        self.room.gol.load_pattern(x, y, &pattern).map(Some)
+    }
+
+    // Stamp pattern payload format:
+    // - 1 byte: library pattern id
+    // - 2 bytes: target x (big-endian)
+    // - 2 bytes: target y (big-endian)
+    fn handle_stamp_pattern(&self) -> Option<Message> {
+        let Some((&id, coords)) = self.parsed.payload.split_first() else {
+            warn!("Dropping empty stamp pattern message");
+            return None;
+        };
+        let Some(pattern) = LibraryPattern::from_id(id) else {
+            warn!("Dropping stamp of unknown library pattern {}", id);
+            return None;
+        };
+        let (width, height) = self.room.gol.dimensions();
+        let origin = match CellPayload::decode(coords).and_then(|cell| {
+            cell.validate(width, height)?;
+            Ok(cell)
+        }) {
+            Ok(origin) => origin,
+            Err(e) => {
+                warn!("Dropping stamp pattern message: {}", e);
+                return None;
+            }
+        };
+
+        debug!(
+            "GOL: Stamping {} at x:{}, y:{}",
+            pattern.name(),
+            origin.x,
+            origin.y
+        );
+        Some(
+            self.room
+                .gol
+                .stamp_pattern(origin.x, origin.y, &pattern.pattern()),
+        )
+    }
+
+    // Pixel request payload format: `CellPayload`, a color replacing the random one
+    fn handle_request_pixel(&self) -> Result<Option<Message>, FrameError> {
+        let (width, height) = self.room.gol.dimensions();
+        let cell = match CellPayload::decode(&self.parsed.payload).and_then(|cell| {
+            cell.validate(width, height)?;
+            Ok(cell)
+        }) {
+            Ok(cell) => cell,
+            Err(e) => {
+                warn!("Dropping pixel request: {}", e);
+                return Ok(None);
+            }
+        };
+
+        debug!("GOL: Adding a live cell to current generation");
+        self.room
+            .gol
+            .awaken_cell_colored(cell.x, cell.y, cell.rgb)
+            .map(Some)
+    }
+
+    // Team cell payload format: `CellPayload`, any color is replaced by the sender's team
+    fn handle_awaken_team_cell(&self) -> Result<Option<Message>, FrameError> {
+        let cell = match CellPayload::decode(&self.parsed.payload) {
+            Ok(cell) => cell,
+            Err(e) => {
+                warn!("Dropping team cell: {}", e);
+                return Ok(None);
+            }
+        };
+
+        let pixel = self
+            .room
+            .immigration
+            .awaken_cell(&self.connection_id, cell.x, cell.y)?;
+        if pixel.is_none() {
+            warn!(
+                "Dropping team cell ({}, {}) from {}",
+                cell.x, cell.y, self.connection_id
+            );
+        }
+        Ok(pixel)
+    }
+
+    // Color scheme payload format:
+    // - 1 byte: scheme (0: random per frame, 1: fire, 2: ocean, 3: grey, 4: rainbow,
+    //   5: monochrome)
+    fn handle_set_color_scheme(&self) -> Result<Option<Message>, FrameError> {
+        let &[id] = &self.parsed.payload[..] else {
+            warn!(
+                "Invalid color scheme payload length: {}",
+                self.parsed.payload.len()
+            );
+            return Ok(None);
+        };
+        let Some(scheme) = ColorScheme::from_id(id) else {
+            warn!("Dropping unknown color scheme {}", id);
+            return Ok(None);
+        };
+        self.room.gol.set_color_scheme(scheme).map(Some)
+    }
+
+    // Toggle cell payload format: `CellPayload` without a color
+    fn handle_toggle_cell(&self) -> Option<Message> {
+        let (width, height) = self.room.gol.dimensions();
+        let cell = match CellPayload::decode(&self.parsed.payload).and_then(|cell| {
+            cell.validate(width, height)?;
+            Ok(cell)
+        }) {
+            Ok(cell) => cell,
+            Err(e) => {
+                warn!("Dropping toggle cell message: {}", e);
+                return None;
+            }
+        };
+
+        debug!("GOL: Toggling the cell at x:{}, y:{}", cell.x, cell.y);
+        Some(self.room.gol.toggle_cell(cell.x, cell.y))
+    }
+
+    // Fill and clear rect payload format:
+    // - 2 bytes: x of the top left cell (big-endian)
+    // - 2 bytes: y of the top left cell (big-endian)
+    // - 2 bytes: width (big-endian)
+    // - 2 bytes: height (big-endian)
+    // The rect is clipped to the grid, its top left cell has to be on it
+    fn handle_fill_rect(&self, alive: bool) -> Option<Message> {
+        let Ok(rect) = <[u8; 8]>::try_from(&self.parsed.payload[..]) else {
+            warn!("Invalid rect payload length: {}", self.parsed.payload.len());
+            return None;
+        };
+        let [x, y, width, height] =
+            [0, 2, 4, 6].map(|i| u16::from_be_bytes([rect[i], rect[i + 1]]));
+        let (grid_width, grid_height) = self.room.gol.dimensions();
+        if x >= grid_width || y >= grid_height || width == 0 || height == 0 {
+            warn!(
+                "Dropping {}x{} rect at ({}, {}) for a {}x{} grid",
+                width, height, x, y, grid_width, grid_height
+            );
+            return None;
+        }
+
+        let region = Region {
+            x,
+            y,
+            width,
+            height,
+        };
+        Some(self.room.gol.fill_region(region, alive))
+    }
+
+    // Seed from image payload format:
+    // - 1 byte: luminance threshold, pixels darker than it come alive
+    // - N bytes: a PNG or JPEG file
+    fn handle_seed_gol_from_image(&self) -> Option<Message> {
+        let Some((&threshold, image)) = self.parsed.payload.split_first() else {
+            warn!("Dropping empty seed from image message");
+            return None;
+        };
+        match self.room.gol.seed_from_image(image, threshold) {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                warn!("Dropping image to seed from: {:#}", e);
+                None
+            }
+        }
+    }
+
+    // Hybrid payload format:
+    // - 1 byte: 1 to have live cells reveal the MLP painting under them, 0 to color them
+    //   by the color scheme again
+    fn handle_set_gol_hybrid(&self) -> Result<Option<Message>, FrameError> {
+        let [hybrid @ (0 | 1)] = self.parsed.payload[..] else {
+            warn!("Dropping invalid hybrid message {:?}", self.parsed.payload);
+            return Ok(None);
+        };
+        let painting = (hybrid == 1).then(|| self.room.mlp.painting_rgb_data());
+        self.room.gol.set_painting_layer(painting).map(Some)
+    }
+
+    // Awaken cells batch payload format:
+    // - per cell: 2 bytes x, 2 bytes y (big-endian)
+    fn handle_awaken_cells_batch(&self) -> Option<Message> {
+        let cells = match CellPayload::decode_list(&self.parsed.payload) {
+            Ok(cells) if !cells.is_empty() && cells.len() <= MAX_CELL_BATCH => cells,
+            Ok(cells) => {
+                warn!("Dropping awaken cells batch of {} cells", cells.len());
+                return None;
+            }
+            Err(e) => {
+                warn!("Dropping awaken cells batch: {}", e);
+                return None;
+            }
+        };
+        // Out-of-bounds cells of a stroke are skipped rather than failing the batch
+        let cells: Vec<(u16, u16)> = cells.iter().map(|cell| (cell.x, cell.y)).collect();
+        debug!("GOL: Awakening a batch of {} cells", cells.len());
+        Some(self.room.gol.awaken_cells(&cells))
+    }
+
+    // Reaction rates payload format:
+    // - 4 bytes: feed rate, f32 (big-endian)
+    // - 4 bytes: kill rate, f32 (big-endian)
+    fn handle_set_reaction_rates(&self) -> Result<Option<Message>, FrameError> {
+        let Ok(rates) = <[u8; 8]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Invalid reaction rates payload length: {}",
+                self.parsed.payload.len()
+            );
+            return Ok(None);
+        };
+        let feed = f32::from_be_bytes([rates[0], rates[1], rates[2], rates[3]]);
+        let kill = f32::from_be_bytes([rates[4], rates[5], rates[6], rates[7]]);
+        if ![feed, kill]
+            .iter()
+            .all(|rate| (0.0..=MAX_REACTION_RATE).contains(rate))
+        {
+            warn!("Dropping reaction rates feed {} and kill {}", feed, kill);
+            return Ok(None);
+        }
+
+        self.room.reaction.set_rates(feed, kill).map(Some)
+    }
+
+    // Claim paddle payload format, empty for any free paddle:
+    // - 1 byte: preferred side (0: left, 1: right)
+    fn handle_claim_paddle(&self) -> Option<BroadcastMessage> {
+        let preferred = match &self.parsed.payload[..] {
+            [] => None,
+            &[id] => match PaddleSide::from_id(id) {
+                Some(side) => Some(side),
+                None => {
+                    warn!("Dropping claim of unknown paddle {}", id);
+                    return None;
+                }
+            },
+            payload => {
+                warn!("Invalid claim paddle payload length: {}", payload.len());
+                return None;
+            }
+        };
+
+        let side = self.room.pong.claim(&self.connection_id, preferred);
+        Some(BroadcastMessage::system(create_paddle_assigned_message(
+            side.map(|side| side.id()),
+        )))
+    }
+
+    // Move paddle payload format:
+    // - 1 byte: direction the paddle keeps moving in, i8 (-1: up, 0: stop, 1: down)
+    fn handle_move_paddle(&self) {
+        let &[direction] = &self.parsed.payload[..] else {
+            warn!(
+                "Invalid move paddle payload length: {}",
+                self.parsed.payload.len()
+            );
+            return;
+        };
+        if !self.room.pong.steer(&self.connection_id, direction as i8) {
+            warn!("Dropping paddle move from a connection without a paddle");
+        }
+    }
+
+    // Turn snake payload format:
+    // - 1 byte: direction (0: up, 1: down, 2: left, 3: right)
+    fn handle_turn_snake(&self) {
+        let &[id] = &self.parsed.payload[..] else {
+            warn!(
+                "Invalid turn snake payload length: {}",
+                self.parsed.payload.len()
+            );
+            return;
+        };
+        let Some(direction) = Direction::from_id(id) else {
+            warn!("Dropping turn in unknown direction {}", id);
+            return;
+        };
+        if !self.room.snake.turn(&self.connection_id, direction) {
+            warn!("Dropping turn from a connection without a snake");
+        }
+    }
+
+    // Spawn boids payload format:
+    // - `CellPayload` without a color, where the boids appear
+    // - 1 byte: number of boids
+    fn handle_spawn_boids(&self) -> Result<Option<Message>, FrameError> {
+        let (width, height) = self.room.gol.dimensions();
+        let (origin, count) = match CellPayload::decode_prefix(&self.parsed.payload) {
+            Ok((origin, &[count])) => (origin, count),
+            Ok((_, rest)) => {
+                warn!("Boid spawn needs 1 count byte, got {}", rest.len());
+                return Ok(None);
+            }
+            Err(e) => {
+                warn!("Dropping boid spawn: {}", e);
+                return Ok(None);
+            }
+        };
+        if let Err(e) = origin.validate(width, height) {
+            warn!("Dropping boid spawn: {}", e);
+            return Ok(None);
+        }
+        if !(1..=MAX_BOID_SPAWN).contains(&count) {
+            warn!("Dropping spawn of {} boids", count);
+            return Ok(None);
+        }
+
+        self.room
+            .boids
+            .spawn(origin.x, origin.y, count as usize)
+            .map(Some)
+    }
+
+    // Boid weights payload format:
+    // - 4 bytes each: separation, alignment and cohesion, f32 (big-endian)
+    fn handle_set_boid_weights(&self) -> Result<Option<Message>, FrameError> {
+        let Ok(payload) = <[u8; 12]>::try_from(&self.parsed.payload[..]) else {
+            warn!(
+                "Invalid boid weights payload length: {}",
+                self.parsed.payload.len()
+            );
+            return Ok(None);
+        };
+        let weight = |i: usize| f32::from_be_bytes(payload[i * 4..i * 4 + 4].try_into().unwrap());
+        let weights = [weight(0), weight(1), weight(2)];
+        if !weights
+            .iter()
+            .all(|weight| (0.0..=MAX_BOID_WEIGHT).contains(weight))
+        {
+            warn!("Dropping boid weights {:?}", weights);
+            return Ok(None);
+        }
+
+        self.room
+            .boids
+            .set_weights(BoidWeights {
+                separation: weights[0],
+                alignment: weights[1],
+                cohesion: weights[2],
+            })
+            .map(Some)
+    }
+
+    // Spawn material payload format:
+    // - 1 byte: material (0: empty, 1: sand, 2: water, 3: wall, 4: fire)
+    // - per cell: 2 bytes x, 2 bytes y (big-endian)
+    fn handle_spawn_material(&self) -> Result<Option<Message>, FrameError> {
+        let Some((&material_id, rest)) = self.parsed.payload.split_first() else {
+            warn!("Dropping empty spawn material message");
+            return Ok(None);
+        };
+        let Some(material) = Material::from_id(material_id) else {
+            warn!("Dropping spawn of unknown material {}", material_id);
+            return Ok(None);
+        };
+        let cells = match CellPayload::decode_list(rest) {
+            Ok(cells) if !cells.is_empty() && cells.len() <= MAX_CELL_BATCH => cells,
+            Ok(cells) => {
+                warn!("Dropping spawn of {} cells", cells.len());
+                return Ok(None);
+            }
+            Err(e) => {
+                warn!("Dropping spawn material message: {}", e);
+                return Ok(None);
+            }
+        };
+        // Out-of-bounds cells are skipped like in strokes
+        let cells: Vec<(u16, u16)> = cells.iter().map(|cell| (cell.x, cell.y)).collect();
+        self.room.sand.spawn(material, &cells).map(Some)
+    }
+
+    // Brush stroke payload format:
+    // - 3 bytes: `Brush`
+    // - per stroke point: 2 bytes x, 2 bytes y (big-endian)
+    fn handle_brush_stroke(&self) -> Result<Option<Message>, FrameError> {
+        let (brush, points) = match Brush::decode_prefix(&self.parsed.payload)
+            .and_then(|(brush, rest)| Ok((brush, CellPayload::decode_list(rest)?)))
+        {
+            Ok((_, points)) if points.is_empty() || points.len() > MAX_CELL_BATCH => {
+                warn!("Dropping brush stroke of {} points", points.len());
+                return Ok(None);
+            }
+            Ok(stroke) => stroke,
+            Err(e) => {
+                warn!("Dropping brush stroke: {}", e);
+                return Ok(None);
+            }
+        };
+        let points: Vec<(u16, u16)> = points.iter().map(|point| (point.x, point.y)).collect();
+        debug!(
+            "GOL: Painting a stroke of {} points with {:?}",
+            points.len(),
+            brush
+        );
+        self.room.gol.paint_brush(&points, &brush).map(Some)
+    }
+
+    fn handle_input_event(&self) -> Result<Option<BroadcastMessage>, FrameError> {
+        let event = match decode_input_event(&self.parsed.payload) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Dropping malformed input event: {}", e);
+                return Ok(None);
+            }
+        };
+
+        Ok(match event.target {
+            input_targets::GOL => self
+                .room
+                .gol
+                .handle_input(&event)?
+                .map(|msg| BroadcastMessage::from_pattern(ActivePattern::Gol, msg)),
+            input_targets::MLP => self
+                .room
+                .mlp
+                .handle_input(&event)?
+                .map(|msg| BroadcastMessage::from_pattern(ActivePattern::Mlp, msg)),
+            unknown_target => {
+                warn!("Input event for unknown target: {}", unknown_target);
+                None
+            }
+        })
+    }
+
     fn create_echo_response(&self) -> Message {
         let response = WsMessage {
             version: PROTOCOL_VERSION,
@@ -86,3 +1216,107 @@ impl WsPayload {
         encode_ws_message(&response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS};
+    use crate::room::RoomSettings;
+
+    // Handles `payload` as a message of `msg_type` the way a connection would
+    fn handle(
+        room: &Arc<RoomState>,
+        msg_type: u8,
+        payload: Vec<u8>,
+    ) -> Result<Option<BroadcastMessage>, FrameError> {
+        WsPayload {
+            parsed: WsMessage {
+                version: PROTOCOL_VERSION,
+                msg_type,
+                flags: 0,
+                payload: Bytes::from(payload),
+            },
+            room: room.clone(),
+            connection_id: "test".to_string(),
+        }
+        .handle_payload()
+    }
+
+    fn load_pattern(x: u16, y: u16, rle: &str) -> Vec<u8> {
+        let mut payload = CellPayload { x, y, rgb: None }.encode();
+        payload.extend(rle.as_bytes());
+        payload
+    }
+
+    // Inputs the handle_payload fuzz target crashed on before run counts, header sizes and
+    // stamp offsets were bounded
+    #[test]
+    fn oversized_rle_patterns_are_dropped() {
+        let settings = RoomSettings {
+            canvas_width: 24,
+            canvas_height: 16,
+            ..RoomSettings::default()
+        };
+        let room = RoomState::for_test_with(&settings);
+        room.gol.kill_all_cells().unwrap();
+
+        for rle in [
+            "x = 3, y = 1\n99999999999999999999999o!",
+            &format!("x = 3, y = 1\n{}b{}bo!", usize::MAX, usize::MAX),
+            &format!("x = {}, y = 1\nbo!", u64::MAX),
+            &format!("x = 1, y = {}\n{}$o!", usize::MAX, usize::MAX),
+        ] {
+            let loaded = handle(
+                &room,
+                message_types::LOAD_GOL_PATTERN,
+                load_pattern(0, 0, rle),
+            );
+            assert!(matches!(loaded, Ok(None)), "{:?} loaded", rle);
+        }
+        assert_eq!(room.gol.generation_stats().1, 0);
+
+        // A pattern hanging off the corner only lands the cells on the grid
+        let glider = load_pattern(22, 14, "x = 3, y = 3\nbo$2bo$3o!");
+        let loaded = handle(&room, message_types::LOAD_GOL_PATTERN, glider).unwrap();
+        assert!(loaded.is_some());
+        assert_eq!(room.gol.generation_stats().1, 1);
+    }
+
+    #[test]
+    fn autoplay_takes_only_on_or_off() {
+        let room = RoomState::for_test();
+        for payload in [vec![], vec![2], vec![u8::MAX], vec![1, 1], vec![0, 1]] {
+            let handled = handle(&room, message_types::SET_AUTOPLAY, payload.clone());
+            assert!(matches!(handled, Ok(None)), "{:?} accepted", payload);
+            assert!(!room.simulation.is_autoplaying());
+        }
+
+        let handled = handle(&room, message_types::SET_AUTOPLAY, vec![1]).unwrap();
+        assert!(handled.is_some());
+        assert!(room.simulation.is_autoplaying());
+        handle(&room, message_types::SET_AUTOPLAY, vec![0]).unwrap();
+        assert!(!room.simulation.is_autoplaying());
+    }
+
+    #[test]
+    fn speed_is_clamped_and_pausing_is_toggled() {
+        let room = RoomState::for_test();
+        let speed = |tick_interval_ms: u32| {
+            let payload = tick_interval_ms.to_be_bytes().to_vec();
+            handle(&room, message_types::SET_SIMULATION_SPEED, payload).unwrap();
+            room.simulation.tick_interval_ms()
+        };
+        assert_eq!(speed(250), 250);
+        assert_eq!(speed(1), MIN_TICK_INTERVAL_MS);
+        assert_eq!(speed(u32::MAX), MAX_TICK_INTERVAL_MS);
+        let short = handle(&room, message_types::SET_SIMULATION_SPEED, vec![0, 0, 100]);
+        assert!(matches!(short, Ok(None)));
+        assert_eq!(room.simulation.tick_interval_ms(), MAX_TICK_INTERVAL_MS);
+
+        let paused = handle(&room, message_types::PAUSE_SIMULATION, vec![]).unwrap();
+        assert!(paused.is_some());
+        assert!(room.simulation.is_paused());
+        handle(&room, message_types::RESUME_SIMULATION, vec![]).unwrap();
+        assert!(!room.simulation.is_paused());
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::Notify;
+
+use crate::{constants::MAX_NICKNAME_LENGTH, room::RoomId};
+
+/// Who a connection is, as announced to the rest of its room
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presence {
+    pub connection_id: String,
+    pub nickname: Option<String>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    room: RoomId,
+    nickname: Option<String>,
+    // Registration order, members are listed oldest first
+    joined: u64,
+    // Notified when an admin kicks the connection
+    kicked: Arc<Notify>,
+}
+
+/// Every open connection of the server, keyed by connection id
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    connections: RwLock<HashMap<String, Connection>>,
+    registrations: AtomicU64,
+}
+
+impl ConnectionRegistry {
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Connection>> {
+        self.connections.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Connection>> {
+        self.connections.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds the connection, returning how it's announced and the signal it's kicked with
+    pub fn register(&self, connection_id: &str, room: &str) -> (Presence, Arc<Notify>) {
+        let kicked = Arc::new(Notify::new());
+        self.write().insert(
+            connection_id.to_string(),
+            Connection {
+                room: room.to_string(),
+                nickname: None,
+                joined: self.registrations.fetch_add(1, Ordering::Relaxed),
+                kicked: kicked.clone(),
+            },
+        );
+        let presence = Presence {
+            connection_id: connection_id.to_string(),
+            nickname: None,
+        };
+        (presence, kicked)
+    }
+
+    /// Removes the connection, returning whether it was registered
+    pub fn unregister(&self, connection_id: &str) -> bool {
+        self.write().remove(connection_id).is_some()
+    }
+
+    /// Tells the connection to close, returning whether it's open
+    pub fn kick(&self, connection_id: &str) -> bool {
+        match self.read().get(connection_id) {
+            Some(connection) => {
+                // Stores a permit if the connection isn't waiting yet
+                connection.kicked.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Names the connection, an empty nickname clears it
+    pub fn set_nickname(&self, connection_id: &str, nickname: &str) -> Result<()> {
+        let nickname = nickname.trim();
+        if nickname.chars().count() > MAX_NICKNAME_LENGTH {
+            bail!(
+                "Nickname longer than {} characters: {:?}",
+                MAX_NICKNAME_LENGTH,
+                nickname
+            );
+        }
+        if nickname.chars().any(char::is_control) {
+            bail!("Nickname contains control characters: {:?}", nickname);
+        }
+
+        let mut connections = self.write();
+        let Some(connection) = connections.get_mut(connection_id) else {
+            bail!("Unknown connection {:?}", connection_id);
+        };
+        connection.nickname = (!nickname.is_empty()).then(|| nickname.to_string());
+        Ok(())
+    }
+
+    pub fn presence(&self, connection_id: &str) -> Option<Presence> {
+        self.read().get(connection_id).map(|connection| Presence {
+            connection_id: connection_id.to_string(),
+            nickname: connection.nickname.clone(),
+        })
+    }
+
+    /// Open connections across every room
+    pub fn count(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Connections in the room, longest connected first
+    pub fn room_members(&self, room: &str) -> Vec<Presence> {
+        let connections = self.read();
+        let mut members: Vec<(&String, &Connection)> = connections
+            .iter()
+            .filter(|(_, connection)| connection.room == room)
+            .collect();
+        members.sort_by_key(|(_, connection)| connection.joined);
+        members
+            .into_iter()
+            .map(|(connection_id, connection)| Presence {
+                connection_id: connection_id.clone(),
+                nickname: connection.nickname.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn registry_tracks_rooms_and_nicknames() {
+        let registry = ConnectionRegistry::default();
+        registry.register("a", "default");
+        registry.register("b", "other");
+        registry.register("c", "default");
+
+        registry.set_nickname("c", "  glider fan ").unwrap();
+        assert!(registry.set_nickname("c", "tab\there").is_err());
+        assert!(
+            registry
+                .set_nickname("c", &"x".repeat(MAX_NICKNAME_LENGTH + 1))
+                .is_err()
+        );
+        assert!(registry.set_nickname("gone", "ghost").is_err());
+
+        let ids = |members: Vec<Presence>| -> Vec<String> {
+            members.into_iter().map(|m| m.connection_id).collect()
+        };
+        let members = registry.room_members("default");
+        assert_eq!(members[1].nickname.as_deref(), Some("glider fan"));
+        assert_eq!(ids(members), ["a", "c"]);
+
+        let (_, kicked) = registry.register("d", "other");
+        assert!(registry.kick("d"));
+        assert!(!registry.kick("gone"));
+        // The permit is kept for a connection that wasn't waiting yet
+        assert!(kicked.notified().now_or_never().is_some());
+
+        assert!(registry.unregister("a"));
+        assert!(!registry.unregister("a"));
+        assert_eq!(ids(registry.room_members("default")), ["c"]);
+    }
+}
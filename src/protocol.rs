@@ -1,93 +1,95 @@
-use anyhow::{Result, bail};
+use anyhow::Result;
 use axum_tws::{Message, Payload};
-use tracing::debug;
-
-pub const PROTOCOL_VERSION: u8 = 1;
-pub const HEADER_LENGTH: u8 = 7;
-
-#[derive(Debug)]
-pub struct WsMessage {
-    pub version: u8,
-    pub msg_type: u8,
-    pub flags: u8,
-    pub payload: Vec<u8>,
+use bytes::Bytes;
+
+use game_of_life_core::compression::deflate;
+pub use game_of_life_core::compression::{
+    CODEC_CRC32, CODEC_DEFLATE, FLAG_DEFLATE, SUPPORTED_CODECS,
+};
+pub use game_of_life_core::protocol::{
+    CHECKSUM_LENGTH, CellPayload, FLAG_CRC32, FLAG_SEQUENCED, FRAGMENT_FLAGS, HASH_FNV1A_32,
+    HEADER_LENGTH, PROTOCOL_VERSION, ProtocolError, Reassembly, SEQUENCE_PREFIX_LENGTH, WsMessage,
+    crc32, generation_hash,
+};
+
+/// Decodes a received message, refusing payloads over `max_payload_length` bytes. The
+/// payload stays a slice of the received frame, nothing is copied.
+pub fn decode_ws_message(data: Payload, max_payload_length: usize) -> Result<WsMessage<Bytes>> {
+    let data = Bytes::from(data);
+    let parsed = WsMessage::parse(&data, max_payload_length)?;
+    let start = HEADER_LENGTH as usize;
+    Ok(parsed.map_payload(|payload| data.slice(start..start + payload.len())))
 }
 
-pub fn decode_ws_message(data: Payload) -> Result<WsMessage> {
-    let data_len = data.len();
-    debug!("Decoding WebSocket message of {} bytes", data_len);
+pub fn encode_ws_message<P: AsRef<[u8]>>(msg: &WsMessage<P>) -> Message {
+    Message::binary(msg.encode())
+}
 
-    if data_len < HEADER_LENGTH as usize {
-        bail!(
-            "Message too short: {} bytes (minimum {} required for header)",
-            data_len,
-            HEADER_LENGTH
-        );
+/// Prefixes the payload of an encoded message with its topic and `sequence` and sets
+/// `FLAG_SEQUENCED`. Anything that isn't a protocol message is returned as is.
+pub fn sequence_ws_message(message: &Message, topic: u8, sequence: u32) -> Message {
+    let data = message.as_payload();
+    let header_length = HEADER_LENGTH as usize;
+    if !message.is_binary() || data.len() < header_length {
+        return message.clone();
     }
 
-    let version = data[0];
-    if version != PROTOCOL_VERSION {
-        bail!(
-            "Unsupported protocol version: {} (expected {})",
-            version,
-            PROTOCOL_VERSION
-        );
-    }
+    // Patches the header in place of decoding, frames are too big to copy twice
+    let payload_length = (data.len() - header_length + SEQUENCE_PREFIX_LENGTH) as u32;
+    let mut sequenced = Vec::with_capacity(data.len() + SEQUENCE_PREFIX_LENGTH);
+    sequenced.extend_from_slice(&[data[0], data[1], data[2] | FLAG_SEQUENCED]);
+    sequenced.extend_from_slice(&payload_length.to_be_bytes());
+    sequenced.push(topic);
+    sequenced.extend_from_slice(&sequence.to_be_bytes());
+    sequenced.extend_from_slice(&data[header_length..]);
+    Message::binary(sequenced)
+}
 
-    let msg_type = data[1];
-    let flags = data[2];
-    let payload_length = u32::from_be_bytes([data[3], data[4], data[5], data[6]]) as usize;
-    let expected_total_length = HEADER_LENGTH as usize + payload_length;
-
-    if data_len != expected_total_length {
-        bail!(
-            "Message length mismatch: got {} bytes, expected {} bytes (header: {}, payload: {})",
-            data_len,
-            expected_total_length,
-            HEADER_LENGTH,
-            payload_length
-        );
+/// Appends the payload's `crc32` to an encoded message and sets `FLAG_CRC32`. Anything
+/// that isn't a protocol message or already has a checksum is returned as is.
+pub fn checksum_ws_message(message: &Message) -> Message {
+    let data = message.as_payload();
+    let header_length = HEADER_LENGTH as usize;
+    if !message.is_binary() || data.len() < header_length || data[2] & FLAG_CRC32 != 0 {
+        return message.clone();
     }
 
-    let payload = data[HEADER_LENGTH as usize..].to_vec();
-
-    debug!(
-        "Successfully decoded message: version={}, type={}, flags={}, payload_len={}",
-        version,
-        msg_type,
-        flags,
-        payload.len()
-    );
-
-    Ok(WsMessage {
-        version,
-        msg_type,
-        flags,
-        payload,
-    })
+    let payload = &data[header_length..];
+    let payload_length = (payload.len() + CHECKSUM_LENGTH) as u32;
+    let mut checksummed = Vec::with_capacity(data.len() + CHECKSUM_LENGTH);
+    checksummed.extend_from_slice(&[data[0], data[1], data[2] | FLAG_CRC32]);
+    checksummed.extend_from_slice(&payload_length.to_be_bytes());
+    checksummed.extend_from_slice(payload);
+    checksummed.extend_from_slice(&crc32(payload).to_be_bytes());
+    Message::binary(checksummed)
 }
 
-pub fn encode_ws_message(msg: &WsMessage) -> Message {
-    let total_size = HEADER_LENGTH as usize + msg.payload.len();
-    let mut buf = Vec::with_capacity(total_size);
-
-    buf.push(msg.version);
-    buf.push(msg.msg_type);
-    buf.push(msg.flags);
-    buf.extend(&(msg.payload.len() as u32).to_be_bytes());
-    buf.extend(&msg.payload);
-
-    debug!(
-        "Encoded message: version={}, type={}, flags={}, total_size={}",
-        msg.version, msg.msg_type, msg.flags, total_size
-    );
-
-    Message::binary(buf)
+/// Deflates the payload of an encoded message and sets `FLAG_DEFLATE`. None when the
+/// message doesn't decode or compressing wouldn't make it smaller. A sequence prefix stays
+/// uncompressed, clients check it before inflating.
+pub fn compress_ws_message(message: &Message) -> Option<Message> {
+    let msg = WsMessage::decode(message.as_payload()).ok()?;
+    let prefix_length = if msg.flags & FLAG_SEQUENCED != 0 {
+        SEQUENCE_PREFIX_LENGTH.min(msg.payload.len())
+    } else {
+        0
+    };
+    let (prefix, body) = msg.payload.split_at(prefix_length);
+    let deflated = deflate(body);
+    if deflated.len() >= body.len() {
+        return None;
+    }
+    Some(encode_ws_message(&WsMessage {
+        flags: msg.flags | FLAG_DEFLATE,
+        payload: [prefix, &deflated].concat(),
+        ..msg
+    }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::{DEFAULT_MAX_PAYLOAD_LENGTH, MAX_REASSEMBLED_PAYLOAD};
     use tracing_test::traced_test;
 
     #[test]
@@ -101,7 +103,7 @@ mod tests {
         };
 
         let buf = encode_ws_message(&msg);
-        let decoded = decode_ws_message(buf.into_payload()).unwrap();
+        let decoded = decode_ws_message(buf.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
         assert_eq!(msg.version, decoded.version);
         assert_eq!(msg.msg_type, decoded.msg_type);
@@ -109,13 +111,87 @@ mod tests {
         assert_eq!(msg.payload, decoded.payload);
     }
 
+    #[test]
+    fn sequenced_messages_carry_their_number() {
+        let msg = WsMessage {
+            version: 1,
+            msg_type: 42,
+            flags: 1,
+            payload: b"frame".to_vec(),
+        };
+
+        let sequenced = sequence_ws_message(&encode_ws_message(&msg), 4, 258);
+        let decoded =
+            decode_ws_message(sequenced.clone().into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH)
+                .unwrap();
+        assert_eq!(decoded.flags, 1 | FLAG_SEQUENCED);
+        assert_eq!(decoded.payload[..], b"\x04\0\0\x01\x02frame"[..]);
+
+        let text = Message::text("hi");
+        assert!(sequence_ws_message(&text, 4, 1).is_text());
+
+        // Deflating leaves the prefix readable
+        let big = WsMessage {
+            payload: vec![0; 1000],
+            ..msg
+        };
+        let sequenced = sequence_ws_message(&encode_ws_message(&big), 4, 258);
+        let deflated = compress_ws_message(&sequenced).unwrap();
+        let decoded =
+            decode_ws_message(deflated.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
+        assert_eq!(
+            decoded.payload[..SEQUENCE_PREFIX_LENGTH],
+            *b"\x04\0\0\x01\x02"
+        );
+        assert_eq!(
+            game_of_life_core::compression::inflate(
+                &decoded.payload[SEQUENCE_PREFIX_LENGTH..],
+                MAX_REASSEMBLED_PAYLOAD
+            )
+            .unwrap(),
+            vec![0; 1000]
+        );
+    }
+
+    #[test]
+    fn checksums_cover_the_payload_as_sent() {
+        let big = WsMessage {
+            version: 1,
+            msg_type: 42,
+            flags: 0,
+            payload: vec![0; 1000],
+        };
+        let sequenced = sequence_ws_message(&encode_ws_message(&big), 4, 258);
+        let deflated = compress_ws_message(&sequenced).unwrap();
+        let checksummed = checksum_ws_message(&deflated);
+        let decoded = decode_ws_message(
+            checksummed.clone().into_payload(),
+            DEFAULT_MAX_PAYLOAD_LENGTH,
+        )
+        .unwrap();
+        assert_eq!(decoded.flags, FLAG_SEQUENCED | FLAG_DEFLATE | FLAG_CRC32);
+        assert_eq!(
+            decoded.payload,
+            deflated.as_payload()[HEADER_LENGTH as usize..]
+        );
+        // Already checksummed
+        assert_eq!(
+            checksum_ws_message(&checksummed).as_payload()[..],
+            checksummed.as_payload()[..]
+        );
+
+        let mut corrupted = checksummed.as_payload().to_vec();
+        corrupted[HEADER_LENGTH as usize + 1] ^= 0x80;
+        assert!(decode_ws_message(corrupted.into(), DEFAULT_MAX_PAYLOAD_LENGTH).is_err());
+    }
+
     #[test]
     #[traced_test]
     fn decode_invalid_version() {
         let mut data = vec![2, 42, 0, 0, 0, 0, 5]; // version 2 (invalid)
         data.extend(b"hello");
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -130,7 +206,7 @@ mod tests {
     fn decode_message_too_short() {
         let data = vec![1, 42, 0, 0, 0, 0]; // Only 6 bytes, need at least 7
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -145,7 +221,7 @@ mod tests {
     fn decode_empty_message() {
         let data = vec![];
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -158,12 +234,14 @@ mod tests {
     #[test]
     #[traced_test]
     fn decode_minimum_valid_message() {
-        let data = vec![1, 100, 255, 0, 0, 0, 0]; // No payload
+        // No payload, every flag but the checksum, which needs one
+        let flags = !FLAG_CRC32;
+        let data = vec![1, 100, flags, 0, 0, 0, 0];
 
-        let decoded = decode_ws_message(data.into()).unwrap();
+        let decoded = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
         assert_eq!(decoded.version, 1);
         assert_eq!(decoded.msg_type, 100);
-        assert_eq!(decoded.flags, 255);
+        assert_eq!(decoded.flags, flags);
         assert_eq!(decoded.payload.len(), 0);
     }
 
@@ -173,7 +251,7 @@ mod tests {
         let mut data = vec![1, 42, 0, 0, 0, 0, 10]; // Claims 10 bytes payload
         data.extend(b"hello"); // Only 5 bytes payload
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -183,13 +261,29 @@ mod tests {
         );
     }
 
+    #[test]
+    #[traced_test]
+    fn decode_payload_over_the_limit() {
+        let mut data = vec![1, 42, 0, 0, 0, 4, 1]; // Claims 1025 bytes payload
+        data.resize(7 + 1025, 0);
+
+        let result = decode_ws_message(data.into(), 1024);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds the 1024-byte limit")
+        );
+    }
+
     #[test]
     #[traced_test]
     fn decode_payload_length_mismatch_too_long() {
         let mut data = vec![1, 42, 0, 0, 0, 0, 3]; // Claims 3 bytes payload
         data.extend(b"hello world"); // 11 bytes payload
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -211,7 +305,8 @@ mod tests {
         };
 
         let encoded = encode_ws_message(&msg);
-        let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+        let decoded =
+            decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
         assert_eq!(decoded.version, 1);
         assert_eq!(decoded.msg_type, 255);
@@ -233,7 +328,8 @@ mod tests {
         };
 
         let encoded = encode_ws_message(&msg);
-        let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+        let decoded =
+            decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
         assert_eq!(decoded.version, 1);
         assert_eq!(decoded.msg_type, 200);
@@ -254,7 +350,8 @@ mod tests {
         };
 
         let encoded = encode_ws_message(&msg);
-        let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+        let decoded =
+            decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
         assert_eq!(decoded.payload, binary_payload);
     }
@@ -271,7 +368,8 @@ mod tests {
             };
 
             let encoded = encode_ws_message(&msg);
-            let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+            let decoded =
+                decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
             assert_eq!(decoded.flags, flags);
             assert_eq!(decoded.payload, vec![flags]);
@@ -290,7 +388,8 @@ mod tests {
             };
 
             let encoded = encode_ws_message(&msg);
-            let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+            let decoded =
+                decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
             assert_eq!(decoded.msg_type, msg_type);
             assert_eq!(decoded.payload, vec![msg_type]);
@@ -302,7 +401,7 @@ mod tests {
     fn decode_invalid_version_zero() {
         let data = vec![0, 42, 0, 0, 0, 0, 0]; // version 0
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -317,7 +416,7 @@ mod tests {
     fn decode_invalid_version_max() {
         let data = vec![255, 42, 0, 0, 0, 0, 0]; // version 255
 
-        let result = decode_ws_message(data.into());
+        let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
         assert!(result.is_err());
         assert!(
             result
@@ -371,10 +470,14 @@ mod tests {
         };
 
         let encoded = encode_ws_message(&msg);
-        let decoded = decode_ws_message(encoded.into_payload()).unwrap();
+        let decoded =
+            decode_ws_message(encoded.into_payload(), DEFAULT_MAX_PAYLOAD_LENGTH).unwrap();
 
         assert_eq!(decoded.payload, utf8_string.as_bytes());
-        assert_eq!(String::from_utf8(decoded.payload).unwrap(), utf8_string);
+        assert_eq!(
+            String::from_utf8(decoded.payload.to_vec()).unwrap(),
+            utf8_string
+        );
     }
 
     #[test]
@@ -383,7 +486,7 @@ mod tests {
         // Test various truncated headers
         for len in 1..7 {
             let data = vec![1; len];
-            let result = decode_ws_message(data.into());
+            let result = decode_ws_message(data.into(), DEFAULT_MAX_PAYLOAD_LENGTH);
             assert!(result.is_err());
             assert!(
                 result
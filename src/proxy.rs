@@ -0,0 +1,449 @@
+//! Protocol debugging proxy: sits between a client and the server, decodes every message
+//! in both directions with the crate's own codec and logs it, optionally recording the
+//! session. A recording can later be replayed to a client without a server.
+//!
+//! ```text
+//! gol-htmx-rust proxy [--listen ADDR] [--upstream ws://HOST:PORT] [--record FILE]
+//! gol-htmx-rust proxy [--listen ADDR] --replay FILE
+//! ```
+//!
+//! Recordings hold one binary message per line: `<ms since connect> <c2s|s2c> <hex bytes>`.
+//! They are meant for one client at a time, concurrent connections interleave.
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    Router,
+    extract::{OriginalUri, State},
+    response::Response,
+    routing::get,
+};
+use axum_tws::{Message, WebSocket, WebSocketUpgrade};
+use futures::{SinkExt, StreamExt};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_websockets::ClientBuilder;
+use tracing::{error, info, warn};
+
+use crate::{constants::message_types, protocol::WsMessage, registry::range_of};
+
+// Bytes of payload shown per logged message
+const PAYLOAD_PREVIEW_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl Direction {
+    fn tag(&self) -> &'static str {
+        match self {
+            Direction::ClientToServer => "c2s",
+            Direction::ServerToClient => "s2c",
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "c2s" => Ok(Direction::ClientToServer),
+            "s2c" => Ok(Direction::ServerToClient),
+            _ => bail!("Unknown direction {:?}", s),
+        }
+    }
+}
+
+/// One line of a recorded session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedMessage {
+    pub elapsed_ms: u64,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+impl fmt::Display for RecordedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.elapsed_ms,
+            self.direction.tag(),
+            to_hex(&self.data)
+        )
+    }
+}
+
+impl FromStr for RecordedMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut fields = line.split_whitespace();
+        let (Some(elapsed_ms), Some(direction), Some(data), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            bail!("Expected `<ms> <c2s|s2c> <hex>`, got {:?}", line);
+        };
+
+        Ok(Self {
+            elapsed_ms: elapsed_ms
+                .parse()
+                .with_context(|| format!("Invalid timestamp {:?}", elapsed_ms))?,
+            direction: direction.parse()?,
+            data: from_hex(data)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyMode {
+    Forward {
+        upstream: String,
+        record: Option<PathBuf>,
+    },
+    Replay(PathBuf),
+}
+
+/// Options of the `proxy` subcommand
+#[derive(Debug, Clone, clap::Args)]
+pub struct ProxyArgs {
+    /// Address clients connect to
+    #[arg(long, default_value = "127.0.0.1:8081")]
+    pub listen: SocketAddr,
+    /// Server messages are forwarded to
+    #[arg(long, default_value = "ws://127.0.0.1:8080")]
+    pub upstream: String,
+    /// Append the session's binary messages to this file
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+    /// Serve a recorded session instead of forwarding to a server
+    #[arg(long, conflicts_with_all = ["record", "upstream"])]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    pub listen: SocketAddr,
+    pub mode: ProxyMode,
+}
+
+impl From<ProxyArgs> for ProxyConfig {
+    fn from(args: ProxyArgs) -> Self {
+        let mode = match args.replay {
+            Some(path) => ProxyMode::Replay(path),
+            None => ProxyMode::Forward {
+                upstream: args.upstream.trim_end_matches('/').to_string(),
+                record: args.record,
+            },
+        };
+        Self {
+            listen: args.listen,
+            mode,
+        }
+    }
+}
+
+struct ProxyState {
+    mode: ProxyMode,
+    recording: Option<Mutex<File>>,
+    session: Vec<RecordedMessage>,
+}
+
+impl ProxyState {
+    fn observe(&self, direction: Direction, message: &Message, started: Instant) {
+        info!("{} {}", direction.tag(), describe(message));
+
+        if let Some(recording) = &self.recording
+            && message.is_binary()
+        {
+            let recorded = RecordedMessage {
+                elapsed_ms: started.elapsed().as_millis() as u64,
+                direction,
+                data: message.as_payload().to_vec(),
+            };
+            if let Err(e) = writeln!(
+                recording.lock().unwrap_or_else(|e| e.into_inner()),
+                "{}",
+                recorded
+            ) {
+                warn!("Failed to record message: {}", e);
+            }
+        }
+    }
+}
+
+/// Human-readable form of a message: type name, owning range, flags and a payload preview
+pub fn describe(message: &Message) -> String {
+    if let Some(text) = message.as_text() {
+        return format!("text {:?}", text);
+    }
+    if !message.is_binary() {
+        return format!("control frame ({} bytes)", message.as_payload().len());
+    }
+
+    match WsMessage::parse(message.as_payload(), usize::MAX) {
+        Ok(decoded) => {
+            let preview_length = decoded.payload.len().min(PAYLOAD_PREVIEW_LENGTH);
+            format!(
+                "{} ({}, {}) flags={:#04x} payload={}B [{}{}]",
+                message_types::name(decoded.msg_type).unwrap_or("UNKNOWN"),
+                decoded.msg_type,
+                range_of(decoded.msg_type).map_or("unregistered", |range| range.owner),
+                decoded.flags,
+                decoded.payload.len(),
+                to_hex(&decoded.payload[..preview_length]),
+                if preview_length < decoded.payload.len() {
+                    "..."
+                } else {
+                    ""
+                }
+            )
+        }
+        Err(e) => format!(
+            "undecodable binary ({} bytes): {}",
+            message.as_payload().len(),
+            e
+        ),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        bail!("Odd number of hex digits");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("Invalid hex byte {:?}", &hex[i..i + 2]))
+        })
+        .collect()
+}
+
+fn load_session(path: &PathBuf) -> Result<Vec<RecordedMessage>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read recording {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            line.parse()
+                .with_context(|| format!("{}:{}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Serves the proxy until the listener fails
+pub async fn run(config: ProxyConfig) -> Result<()> {
+    let state = match &config.mode {
+        ProxyMode::Forward { upstream, record } => {
+            let recording = match record {
+                Some(path) => Some(Mutex::new(File::create(path).with_context(|| {
+                    format!("Failed to create recording {}", path.display())
+                })?)),
+                None => None,
+            };
+            info!("Proxying to {}", upstream);
+            ProxyState {
+                mode: config.mode.clone(),
+                recording,
+                session: Vec::new(),
+            }
+        }
+        ProxyMode::Replay(path) => {
+            let session = load_session(path)?;
+            info!(
+                "Replaying {} recorded messages from {}",
+                session.len(),
+                path.display()
+            );
+            ProxyState {
+                mode: config.mode.clone(),
+                recording: None,
+                session,
+            }
+        }
+    };
+
+    let app = Router::new()
+        .route("/ws", get(proxy_handler))
+        .route("/ws/{room}", get(proxy_handler))
+        .with_state(Arc::new(state));
+
+    let listener = tokio::net::TcpListener::bind(config.listen)
+        .await
+        .with_context(|| format!("Failed to bind proxy to {}", config.listen))?;
+    info!("Protocol proxy listening on {}", config.listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn proxy_handler(
+    ws: WebSocketUpgrade,
+    OriginalUri(uri): OriginalUri,
+    State(state): State<Arc<ProxyState>>,
+) -> Response {
+    ws.on_upgrade(move |client| async move {
+        let result = match &state.mode {
+            ProxyMode::Forward { upstream, .. } => {
+                // Same room and credentials as the client asked the proxy for
+                let path = uri.path_and_query().map_or("/ws", |path| path.as_str());
+                forward(client, format!("{}{}", upstream, path), &state).await
+            }
+            ProxyMode::Replay(_) => replay(client, &state.session).await,
+        };
+
+        match result {
+            Ok(()) => info!("Proxy session closed"),
+            Err(e) => error!("Proxy session failed: {:#}", e),
+        }
+    })
+}
+
+async fn forward(client: WebSocket, upstream_uri: String, state: &ProxyState) -> Result<()> {
+    let (upstream, _) = ClientBuilder::new()
+        .uri(&upstream_uri)?
+        .connect()
+        .await
+        .with_context(|| format!("Failed to connect to {}", upstream_uri))?;
+    info!("Client connected, proxying to {}", upstream_uri);
+
+    let started = Instant::now();
+    let (mut client_sink, mut client_stream) = client.split();
+    let (mut upstream_sink, mut upstream_stream) = upstream.split();
+
+    let to_server = async {
+        while let Some(Ok(message)) = client_stream.next().await {
+            state.observe(Direction::ClientToServer, &message, started);
+            if upstream_sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    };
+    let to_client = async {
+        while let Some(Ok(message)) = upstream_stream.next().await {
+            state.observe(Direction::ServerToClient, &message, started);
+            if client_sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    // Either side hanging up ends the session
+    tokio::select! {
+        _ = to_server => {}
+        _ = to_client => {}
+    }
+    Ok(())
+}
+
+async fn replay(client: WebSocket, session: &[RecordedMessage]) -> Result<()> {
+    let started = Instant::now();
+    let (mut sink, mut stream) = client.split();
+
+    let playback = async {
+        for recorded in session
+            .iter()
+            .filter(|recorded| recorded.direction == Direction::ServerToClient)
+        {
+            tokio::time::sleep_until((started + Duration::from_millis(recorded.elapsed_ms)).into())
+                .await;
+            let message = Message::binary(recorded.data.clone());
+            info!("{} {}", Direction::ServerToClient.tag(), describe(&message));
+            sink.send(message)
+                .await
+                .context("Client went away during replay")?;
+        }
+        info!("Replay finished");
+        anyhow::Ok(())
+    };
+    let incoming = async {
+        while let Some(Ok(message)) = stream.next().await {
+            info!("{} {}", Direction::ClientToServer.tag(), describe(&message));
+        }
+    };
+    tokio::pin!(incoming);
+
+    // Keep logging the client after playback until it disconnects
+    tokio::select! {
+        result = playback => {
+            result?;
+            incoming.await;
+        }
+        _ = &mut incoming => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Cli, Command};
+    use crate::utils::create_occupancy_message;
+    use clap::Parser;
+
+    fn parse(line: &str) -> Result<ProxyConfig, clap::Error> {
+        let cli = Cli::try_parse_from(format!("gol proxy {}", line).split_whitespace())?;
+        match cli.command {
+            Some(Command::Proxy(args)) => Ok(args.into()),
+            None => panic!("proxy subcommand not parsed"),
+        }
+    }
+
+    #[test]
+    fn proxy_options() {
+        let config =
+            parse("--listen 0.0.0.0:9000 --upstream ws://server:8080/ --record session.log")
+                .unwrap();
+        assert_eq!(config.listen, SocketAddr::from(([0, 0, 0, 0], 9000)));
+        assert_eq!(
+            config.mode,
+            ProxyMode::Forward {
+                upstream: "ws://server:8080".to_string(),
+                record: Some(PathBuf::from("session.log")),
+            }
+        );
+
+        assert_eq!(
+            parse("--replay session.log").unwrap().mode,
+            ProxyMode::Replay(PathBuf::from("session.log"))
+        );
+        assert!(parse("--record a --replay b").is_err());
+        assert!(parse("--listen").is_err());
+        assert!(parse("--listen nowhere").is_err());
+        assert!(parse("--bogus 1").is_err());
+    }
+
+    #[test]
+    fn recorded_messages_roundtrip() {
+        let message = create_occupancy_message(3, 1);
+        let recorded = RecordedMessage {
+            elapsed_ms: 1250,
+            direction: Direction::ServerToClient,
+            data: message.as_payload().to_vec(),
+        };
+
+        let line = recorded.to_string();
+        assert_eq!(line, "1250 s2c 0169000000000400030001");
+        assert_eq!(line.parse::<RecordedMessage>().unwrap(), recorded);
+        assert!("12 s2c 0".parse::<RecordedMessage>().is_err());
+
+        assert_eq!(
+            describe(&message),
+            "ROOM_OCCUPANCY (105, server) flags=0x00 payload=4B [00030001]"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result, bail};
+use bytes::Bytes;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::{
+    constants::message_types,
+    payload::{WsPayload, is_handled},
+    protocol::{PROTOCOL_VERSION, WsMessage},
+    room::RoomState,
+};
+
+/// One line of a recording, a JSON object tagged with its `kind`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedLine {
+    // Starts each session, replays start from a fresh room like this one
+    Session(RecordedSession),
+    Command(RecordedCommand),
+}
+
+/// The room a recorded session started in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedSession {
+    pub room: String,
+    pub started: String,
+    pub seed: u64,
+    pub canvas_width: u16,
+    pub canvas_height: u16,
+}
+
+/// A client message as the server decoded it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    // Milliseconds since the session started
+    pub elapsed_ms: u64,
+    pub connection: String,
+    pub msg_type: u8,
+    pub flags: u8,
+    // Generation the message arrived at, replay steps the grid there first
+    pub generation: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Appends every message a room's clients send to `<dir>/<room>.rec`, so the `replay`
+/// binary can feed the session into a fresh room again. A server restart appends a new
+/// session to the same file.
+pub struct SessionRecorder {
+    path: PathBuf,
+    started: Instant,
+    file: Mutex<File>,
+}
+
+impl SessionRecorder {
+    pub fn open(dir: &Path, room: &str, seed: u64, canvas: (u16, u16)) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!("{}.rec", room));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let recorder = Self {
+            path,
+            started: Instant::now(),
+            file: Mutex::new(file),
+        };
+
+        recorder.append(&RecordedLine::Session(RecordedSession {
+            room: room.to_string(),
+            started: Local::now().to_rfc3339(),
+            seed,
+            canvas_width: canvas.0,
+            canvas_height: canvas.1,
+        }))?;
+        info!("Recording room {:?} to {}", room, recorder.path.display());
+        Ok(recorder)
+    }
+
+    pub fn record(&self, connection: &str, parsed: &WsMessage<Bytes>, generation: u64) {
+        let command = RecordedLine::Command(RecordedCommand {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            connection: connection.to_string(),
+            msg_type: parsed.msg_type,
+            flags: parsed.flags,
+            generation,
+            payload: parsed.payload.to_vec(),
+        });
+        if let Err(e) = self.append(&command) {
+            warn!("Failed to record to {}: {:#}", self.path.display(), e);
+        }
+    }
+
+    fn append(&self, line: &RecordedLine) -> Result<()> {
+        let mut bytes = serde_json::to_vec(line)?;
+        bytes.push(b'\n');
+        // One write per line, so lines of concurrent connections don't interleave
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(&bytes)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Sessions of a recording in the order they were recorded, each with its commands
+pub fn read_recording(path: &Path) -> Result<Vec<(RecordedSession, Vec<RecordedCommand>)>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut sessions: Vec<(RecordedSession, Vec<RecordedCommand>)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = serde_json::from_str(line)
+            .with_context(|| format!("Invalid line {} of {}", i + 1, path.display()))?;
+        match line {
+            RecordedLine::Session(session) => sessions.push((session, Vec::new())),
+            RecordedLine::Command(command) => match sessions.last_mut() {
+                Some((_, commands)) => commands.push(command),
+                None => bail!("{} starts without a session", path.display()),
+            },
+        }
+    }
+    Ok(sessions)
+}
+
+/// Feeds recorded commands into `room`, waiting out the time between them divided by
+/// `speed`, or not at all at 0. Messages that only concern their connection, like chat and
+/// subscriptions, are skipped. Returns how many commands were applied.
+pub async fn replay_commands(
+    room: &Arc<RoomState>,
+    commands: &[RecordedCommand],
+    speed: f64,
+) -> usize {
+    let mut applied = 0;
+    let mut previous_ms = 0;
+    for command in commands {
+        if speed > 0.0 && command.elapsed_ms > previous_ms {
+            let wait = (command.elapsed_ms - previous_ms) as f64 / speed;
+            tokio::time::sleep(Duration::from_millis(wait as u64)).await;
+        }
+        previous_ms = command.elapsed_ms;
+
+        let name = message_types::name(command.msg_type).unwrap_or("unknown");
+        if !is_handled(command.msg_type) {
+            debug!("Skipping {} from {}", name, command.connection);
+            continue;
+        }
+        room.gol.fast_forward(command.generation);
+        let payload = WsPayload {
+            parsed: WsMessage {
+                version: PROTOCOL_VERSION,
+                msg_type: command.msg_type,
+                flags: command.flags,
+                payload: Bytes::from(command.payload.clone()),
+            },
+            room: room.clone(),
+            connection_id: command.connection.clone(),
+        };
+        info!(
+            "{:>8}ms {} from {}, {} bytes",
+            command.elapsed_ms,
+            name,
+            command.connection,
+            command.payload.len()
+        );
+        match payload.handle_payload() {
+            // Nobody has to be watching
+            Ok(Some(response)) => {
+                let _ = room.channel.send(response);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Replayed {} without its frame: {}", name, e),
+        }
+        applied += 1;
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{protocol::CellPayload, room::RoomSettings};
+    use futures::FutureExt;
+
+    #[test]
+    fn recorded_sessions_replay_into_the_same_board() {
+        let dir = std::env::temp_dir().join(format!("gol-recording-{}", uuid::Uuid::new_v4()));
+        let settings = RoomSettings {
+            record_dir: Some(dir.clone()),
+            ..RoomSettings::default()
+        };
+        let room = RoomState::for_test_with(&settings);
+        let recorder = room.recorder.as_ref().unwrap();
+
+        let send = |msg_type, payload: Vec<u8>| {
+            let payload = WsPayload {
+                parsed: WsMessage {
+                    version: PROTOCOL_VERSION,
+                    msg_type,
+                    flags: 0,
+                    payload: Bytes::from(payload),
+                },
+                room: room.clone(),
+                connection_id: "client-1".to_string(),
+            };
+            recorder.record("client-1", &payload.parsed, room.gol.generation_stats().0);
+            if is_handled(msg_type) {
+                payload.handle_payload().unwrap();
+            }
+        };
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+            .into_iter()
+            .flat_map(|(x, y)| CellPayload { x, y, rgb: None }.encode())
+            .collect();
+        send(message_types::CREATE_NEW_GOL_GENERATION, vec![]);
+        send(message_types::AWAKEN_CELLS_BATCH, glider);
+        // Autoplay steps between messages are caught up with on replay
+        room.gol.advance_generation().unwrap();
+        send(message_types::CHAT, b"hi".to_vec());
+        send(message_types::ADVANCE_GOL_GENERATION, vec![]);
+        let expected = room.gol.generation_hash();
+
+        let sessions = read_recording(&dir.join("test.rec")).unwrap();
+        let [(session, commands)] = &sessions[..] else {
+            panic!("expected one session, got {}", sessions.len());
+        };
+        assert_eq!(session.seed, room.seed());
+        assert_eq!(commands.len(), 4);
+
+        let replayed = RoomState::for_test();
+        replayed.reseed(session.seed).unwrap();
+        let applied = replay_commands(&replayed, commands, 0.0)
+            .now_or_never()
+            .unwrap();
+        assert_eq!(applied, 3);
+        assert_eq!(replayed.gol.generation_hash(), expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
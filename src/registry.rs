@@ -0,0 +1,135 @@
+use anyhow::{Result, bail};
+use tracing::info;
+
+/// Block of message types owned by one pattern or subsystem, bounds inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageRange {
+    pub owner: &'static str,
+    pub start: u8,
+    pub end: u8,
+}
+
+impl MessageRange {
+    pub const fn new(owner: &'static str, start: u8, end: u8) -> Self {
+        assert!(start <= end, "message range ends before it starts");
+        Self { owner, start, end }
+    }
+
+    /// Message type `offset` places into the range; a constant outside it fails to compile
+    pub const fn at(&self, offset: u8) -> u8 {
+        assert!(
+            offset <= self.end - self.start,
+            "message type outside its range"
+        );
+        self.start + offset
+    }
+
+    pub fn contains(&self, msg_type: u8) -> bool {
+        (self.start..=self.end).contains(&msg_type)
+    }
+
+    fn overlaps(&self, other: &MessageRange) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+pub const HANDSHAKE: MessageRange = MessageRange::new("handshake", 1, 19);
+pub const MLP: MessageRange = MessageRange::new("mlp", 20, 39);
+pub const GOL: MessageRange = MessageRange::new("gol", 40, 59);
+pub const SIMULATION: MessageRange = MessageRange::new("simulation", 60, 69);
+pub const SUBSCRIPTIONS: MessageRange = MessageRange::new("subscriptions", 70, 79);
+pub const BRIANS_BRAIN: MessageRange = MessageRange::new("brians_brain", 80, 89);
+// Switching the pattern a room shows
+pub const MODES: MessageRange = MessageRange::new("modes", 90, 94);
+// Starting and stopping the bots a room hosts
+pub const BOTS: MessageRange = MessageRange::new("bots", 95, 99);
+// Server to client
+pub const SERVER: MessageRange = MessageRange::new("server", 100, 149);
+pub const SAND: MessageRange = MessageRange::new("sand", 150, 169);
+pub const REACTION: MessageRange = MessageRange::new("reaction", 170, 189);
+pub const BOIDS: MessageRange = MessageRange::new("boids", 190, 199);
+pub const CLIENT_INPUT: MessageRange = MessageRange::new("input", 200, 219);
+pub const PONG: MessageRange = MessageRange::new("pong", 220, 229);
+pub const SNAKE: MessageRange = MessageRange::new("snake", 230, 239);
+pub const IMMIGRATION: MessageRange = MessageRange::new("immigration", 240, 249);
+pub const CHECKPOINTS: MessageRange = MessageRange::new("checkpoints", 250, 254);
+
+/// Every registered range, advertised to clients in the capabilities message
+pub const MESSAGE_RANGES: &[MessageRange] = &[
+    HANDSHAKE,
+    MLP,
+    GOL,
+    SIMULATION,
+    SUBSCRIPTIONS,
+    BRIANS_BRAIN,
+    MODES,
+    BOTS,
+    SERVER,
+    SAND,
+    REACTION,
+    BOIDS,
+    CLIENT_INPUT,
+    PONG,
+    SNAKE,
+    IMMIGRATION,
+    CHECKPOINTS,
+];
+
+/// Fails if two ranges share a message type or an owner name
+pub fn validate_message_ranges(ranges: &[MessageRange]) -> Result<()> {
+    for (i, range) in ranges.iter().enumerate() {
+        for other in &ranges[i + 1..] {
+            if range.owner == other.owner {
+                bail!("Message range owner {:?} registered twice", range.owner);
+            }
+            if range.overlaps(other) {
+                bail!(
+                    "Message range {:?} ({}-{}) collides with {:?} ({}-{})",
+                    range.owner,
+                    range.start,
+                    range.end,
+                    other.owner,
+                    other.start,
+                    other.end
+                );
+            }
+        }
+    }
+
+    info!("Validated {} message type ranges", ranges.len());
+    Ok(())
+}
+
+/// Range a message type belongs to, if any
+pub fn range_of(msg_type: u8) -> Option<&'static MessageRange> {
+    MESSAGE_RANGES.iter().find(|range| range.contains(msg_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::message_types;
+
+    #[test]
+    fn registered_ranges_are_disjoint() {
+        assert!(validate_message_ranges(MESSAGE_RANGES).is_ok());
+
+        let clashing = [
+            MessageRange::new("a", 10, 20),
+            MessageRange::new("b", 20, 30),
+        ];
+        assert!(validate_message_ranges(&clashing).is_err());
+
+        let duplicate = [MessageRange::new("a", 1, 2), MessageRange::new("a", 3, 4)];
+        assert!(validate_message_ranges(&duplicate).is_err());
+    }
+
+    #[test]
+    fn message_types_land_in_their_ranges() {
+        assert_eq!(range_of(message_types::HELLO), Some(&HANDSHAKE));
+        assert_eq!(range_of(message_types::LOAD_GOL_PATTERN), Some(&GOL));
+        assert_eq!(range_of(message_types::DRAW_FRAME), Some(&SERVER));
+        assert_eq!(range_of(message_types::INPUT_EVENT), Some(&CLIENT_INPUT));
+        assert_eq!(range_of(255), None);
+    }
+}
@@ -0,0 +1,1283 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum_tws::Message;
+use chrono::Utc;
+use game_of_life_core::{EngineKind, GolEngine};
+use rand::{Rng, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Instant;
+use tokio::sync::{Notify, broadcast};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{
+    admin::constant_time_eq,
+    bots::RoomBots,
+    checkpoints::Checkpoints,
+    constants::{
+        DEADLINE_RECOVERY_TICKS, DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH,
+        DEFAULT_CHANNEL_CAPACITY, DEFAULT_CHECKPOINT_KEEP, DEFAULT_DATA_DIR,
+        DEFAULT_MAX_PAYLOAD_LENGTH, DEFAULT_TICK_INTERVAL_MS, EDITOR_ACTIVITY_WINDOW_MS,
+        INVITE_TOKEN_LENGTH, JOURNAL_COMPACT_EVERY, MAX_ROOM_ID_LENGTH, MAX_ROOM_INVITES,
+        SIMULATION_SEED, WATCHDOG_SNAPSHOT_EVERY_TICKS, message_types, topics,
+    },
+    i18n::{Locale, Notice, TextPreferences},
+    journal::CommandJournal,
+    metrics::METRICS,
+    modes::ModeManager,
+    patterns::{
+        boids::BoidsState, brians_brain::BrainState, gol::GolState, gray_scott::ReactionState,
+        immigration::ImmigrationState, mlp::MlpState, pong::PongState, sand::SandState,
+        snake::SnakeState,
+    },
+    protocol::{
+        CODEC_CRC32, CODEC_DEFLATE, checksum_ws_message, compress_ws_message, sequence_ws_message,
+    },
+    recording::SessionRecorder,
+    saves::SavedState,
+    soup::SoupSearch,
+    state::{ActivePattern, SimulationControl},
+    utils::{
+        FrameError, create_generation_hash_message, create_mode_changed_message,
+        create_simulation_stable_message,
+    },
+};
+
+pub type RoomId = String;
+
+pub const DEFAULT_ROOM: &str = "default";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoomError {
+    #[error("Invalid room id: {0:?}")]
+    InvalidId(String),
+    #[error("Room limit reached: {0} rooms")]
+    TooManyRooms(usize),
+    #[error("Access to room {0:?} denied")]
+    AccessDenied(RoomId),
+    #[error("Room {0:?} has too many outstanding invites")]
+    TooManyInvites(RoomId),
+    #[error("Connection limit reached: {0} connections")]
+    TooManyConnections(usize),
+    #[error("Room {0:?} already exists")]
+    AlreadyExists(RoomId),
+    #[error("Canvas of {0}x{1} cells too large for the {2} engine")]
+    CanvasTooLarge(u16, u16, EngineKind),
+}
+
+impl IntoResponse for RoomError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            RoomError::InvalidId(_) | RoomError::CanvasTooLarge(..) => StatusCode::BAD_REQUEST,
+            RoomError::AccessDenied(_) => StatusCode::FORBIDDEN,
+            RoomError::AlreadyExists(_) => StatusCode::CONFLICT,
+            RoomError::TooManyRooms(_)
+            | RoomError::TooManyInvites(_)
+            | RoomError::TooManyConnections(_) => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Credentials a client presents when joining, taken from the upgrade query string
+#[derive(Debug, Default, Deserialize)]
+pub struct JoinCredentials {
+    pub password: Option<String>,
+    pub invite: Option<String>,
+    // Makes the connection an admin, see `AdminAccess`
+    pub admin_token: Option<String>,
+}
+
+/// Who may join a room: anyone when no password is set, otherwise the password
+/// holder or someone presenting an unused invite token
+#[derive(Debug, Default)]
+pub struct RoomAccess {
+    password: Option<String>,
+    invites: Mutex<HashSet<String>>,
+}
+
+impl RoomAccess {
+    pub fn with_password(password: Option<String>) -> Self {
+        Self {
+            password: password.filter(|p| !p.is_empty()),
+            invites: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// Whether `password` opens the room, compared in constant time like the admin token
+    pub fn has_password(&self, password: Option<&str>) -> bool {
+        match (&self.password, password) {
+            (None, _) => true,
+            (Some(expected), Some(password)) => constant_time_eq(expected, password),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Checks the credentials, consuming the invite token if that is what let the client in
+    pub fn admit(&self, credentials: &JoinCredentials) -> bool {
+        if self.has_password(credentials.password.as_deref()) {
+            return true;
+        }
+        let Some(invite) = &credentials.invite else {
+            return false;
+        };
+        self.invites
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(invite)
+    }
+
+    /// Mints a single-use invite token, or `None` if too many are outstanding
+    pub fn create_invite(&self) -> Option<String> {
+        let mut invites = self.invites.lock().unwrap_or_else(|e| e.into_inner());
+        if invites.len() >= MAX_ROOM_INVITES {
+            return None;
+        }
+
+        let token: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(INVITE_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        invites.insert(token.clone());
+        Some(token)
+    }
+}
+
+/// Validates a client supplied room id: 1-32 ascii alphanumerics, '-' or '_'
+pub fn validate_room_id(id: &str) -> Result<(), RoomError> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ROOM_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(RoomError::InvalidId(id.to_string()))
+    }
+}
+
+/// A message fanned out to a room, tagged with the topic receivers filter on. Receivers
+/// share its encoded buffers, nothing is copied per connection.
+#[derive(Debug, Clone)]
+pub struct BroadcastMessage {
+    pub topic: u8,
+    pub message: Message,
+    // Set for server notices, which receivers re-render in their connection's locale
+    pub notice: Option<LocalizedNotice>,
+    // Number within its topic, given by the room channel. None for messages sent to a
+    // single connection and ones kept from their sender.
+    sequence: Option<u32>,
+    // The message with its sequence number, made by the first receiver
+    sequenced: Arc<OnceLock<Message>>,
+    // Deflated frame, made by the first receiver that takes deflate and shared with the
+    // rest. None inside when compressing doesn't pay off.
+    deflated: Arc<OnceLock<Option<Message>>>,
+    // Connection the message is about, which doesn't get it back
+    sender: Option<Arc<str>>,
+}
+
+/// A room's broadcast channel. Messages are numbered per topic as they're sent, so every
+/// client can spot the ones it missed while sharing a single encoding of each.
+#[derive(Debug, Clone)]
+pub struct RoomChannel {
+    sender: broadcast::Sender<BroadcastMessage>,
+    // Last number given out per topic, held while sending so numbers go out in order
+    sequences: Arc<Mutex<HashMap<u8, u32>>>,
+    // Notified when a receiver subscribes to a channel that had none, which wakes the
+    // room's idle simulation loop
+    first_receiver: Arc<Notify>,
+}
+
+impl RoomChannel {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::Sender::new(capacity),
+            sequences: Arc::default(),
+            first_receiver: Arc::default(),
+        }
+    }
+
+    pub fn send(
+        &self,
+        mut message: BroadcastMessage,
+    ) -> Result<usize, broadcast::error::SendError<BroadcastMessage>> {
+        let mut sequences = self.sequences.lock().unwrap_or_else(|e| e.into_inner());
+        // Its sender never gets it, numbering it would look like a gap there
+        if message.sender.is_none() {
+            let sequence = sequences.entry(message.topic).or_default();
+            *sequence = sequence.wrapping_add(1);
+            message.sequence = Some(*sequence);
+        }
+        self.sender.send(message)
+    }
+
+    /// Whether messages can still be numbered, a panic while numbering poisons the lock
+    pub fn is_functional(&self) -> bool {
+        !self.sequences.is_poisoned()
+    }
+
+    /// Number the last message of `topic` went out with, None before the first
+    pub fn last_sequence(&self, topic: u8) -> Option<u32> {
+        let sequences = self.sequences.lock().unwrap_or_else(|e| e.into_inner());
+        sequences.get(&topic).copied()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BroadcastMessage> {
+        let was_empty = self.sender.receiver_count() == 0;
+        let receiver = self.sender.subscribe();
+        if was_empty {
+            // Kept as a permit if the loop isn't waiting yet, so the wake isn't lost
+            self.first_receiver.notify_one();
+        }
+        receiver
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Notified once the channel goes from no receivers to one. Doesn't keep the
+    /// channel's sender alive.
+    pub fn first_receiver(&self) -> Arc<Notify> {
+        self.first_receiver.clone()
+    }
+}
+
+/// A server notice and how to wrap its rendered text into a message
+#[derive(Debug, Clone, Copy)]
+pub struct LocalizedNotice {
+    pub notice: Notice,
+    pub encode: fn(&str) -> Message,
+}
+
+impl BroadcastMessage {
+    pub fn new(topic: u8, message: Message) -> Self {
+        Self {
+            topic,
+            message,
+            notice: None,
+            sequence: None,
+            sequenced: Arc::default(),
+            deflated: Arc::default(),
+            sender: None,
+        }
+    }
+
+    /// A system notice, carried in English and localized per connection on delivery
+    pub fn notice(notice: Notice, encode: fn(&str) -> Message) -> Self {
+        Self {
+            topic: topics::SYSTEM,
+            message: encode(notice.text(Locale::En)),
+            notice: Some(LocalizedNotice { notice, encode }),
+            sequence: None,
+            sequenced: Arc::default(),
+            deflated: Arc::default(),
+            sender: None,
+        }
+    }
+
+    /// Keeps the message from going back to the connection that sent it
+    pub fn sent_by(mut self, connection_id: &str) -> Self {
+        self.sender = Some(connection_id.into());
+        self
+    }
+
+    pub fn is_from(&self, connection_id: &str) -> bool {
+        self.sender.as_deref() == Some(connection_id)
+    }
+
+    /// Number within its topic, None if it wasn't numbered
+    pub fn sequence(&self) -> Option<u32> {
+        self.sequence
+    }
+
+    /// The same broadcast carrying `message`, rendered for a single connection. Numbered
+    /// like the original, so the connection doesn't see a gap.
+    pub fn replaced_with(&self, message: Message) -> Self {
+        Self {
+            topic: self.topic,
+            message,
+            notice: None,
+            sequence: self.sequence,
+            sequenced: Arc::default(),
+            deflated: Arc::default(),
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// The message as sent to a connection that decodes `codecs`: numbered if it went
+    /// through the room channel, frames and regions deflated when the connection takes it
+    pub fn message_for(&self, codecs: u8) -> Message {
+        let message = match self.sequence {
+            Some(sequence) => self
+                .sequenced
+                .get_or_init(|| sequence_ws_message(&self.message, self.topic, sequence)),
+            None => &self.message,
+        };
+        let is_frame = matches!(
+            self.message.as_payload().get(1),
+            Some(
+                &(message_types::DRAW_FRAME
+                    | message_types::DRAW_REGION
+                    | message_types::DRAW_TILES)
+            )
+        );
+        if is_frame
+            && codecs & CODEC_DEFLATE != 0
+            && let Some(deflated) = self.deflated.get_or_init(|| compress_ws_message(message))
+        {
+            return deflated.clone();
+        }
+        message.clone()
+    }
+
+    /// The message as sent to a connection with `preferences` that decodes `codecs`,
+    /// notices worded for it. Checksummed last, over the payload as sent.
+    pub fn render(&self, preferences: &TextPreferences, codecs: u8) -> Message {
+        let message = match self.notice {
+            Some(localized) => {
+                let text = preferences.render(localized.notice);
+                self.sequence_rendered((localized.encode)(&text))
+            }
+            None => self.message_for(codecs),
+        };
+        if codecs & CODEC_CRC32 != 0 {
+            return checksum_ws_message(&message);
+        }
+        message
+    }
+
+    // Numbers a notice rendered for one connection like the message it was rendered from
+    fn sequence_rendered(&self, rendered: Message) -> Message {
+        match self.sequence {
+            Some(sequence) => sequence_ws_message(&rendered, self.topic, sequence),
+            None => rendered,
+        }
+    }
+
+    /// Tags a pattern's response: pixels are pixel events, frames belong to the pattern's stream
+    pub fn from_pattern(pattern: ActivePattern, message: Message) -> Self {
+        // Byte 1 of an encoded message is its type. Regions are partial frames and travel
+        // with them.
+        let msg_type = message.as_payload().get(1).map(|&msg_type| match msg_type {
+            message_types::DRAW_REGION => message_types::DRAW_FRAME,
+            msg_type => msg_type,
+        });
+        let topic = match msg_type {
+            Some(message_types::DRAW_PIXEL | message_types::DRAW_PIXELS_BATCH) => {
+                topics::PIXEL_EVENTS
+            }
+            Some(message_types::DRAW_FRAME) => pattern.frame_topic(),
+            _ => topics::SYSTEM,
+        };
+        Self::new(topic, message)
+    }
+
+    pub fn system(message: Message) -> Self {
+        Self::new(topics::SYSTEM, message)
+    }
+}
+
+/// Everything owned by a single room: its clients' channel, simulation settings and pattern state
+pub struct RoomState {
+    pub id: RoomId,
+    pub channel: RoomChannel,
+    // Messages the channel holds for each receiver before the oldest are dropped
+    pub channel_capacity: usize,
+    pub simulation: SimulationControl,
+    pub modes: ModeManager,
+    // Seed the room's random streams last started from
+    seed: AtomicU64,
+    pub gol: GolState,
+    pub mlp: MlpState,
+    pub brain: BrainState,
+    pub sand: SandState,
+    pub reaction: ReactionState,
+    pub boids: BoidsState,
+    pub pong: PongState,
+    pub snake: SnakeState,
+    pub immigration: ImmigrationState,
+    pub soup_search: SoupSearch,
+    pub bots: RoomBots,
+    pub health: SimulationHealth,
+    pub access: RoomAccess,
+    pub occupancy: RoomOccupancy,
+    pub journal: Option<CommandJournal>,
+    pub recorder: Option<SessionRecorder>,
+    pub data_dir: PathBuf,
+    pub checkpoints: Option<Checkpoints>,
+    pub max_payload_length: usize,
+}
+
+/// Connections that recently changed the canvas, keyed by connection id
+#[derive(Default)]
+pub struct RoomOccupancy {
+    editors: Mutex<HashMap<String, Instant>>,
+}
+
+impl RoomOccupancy {
+    pub fn mark_editor(&self, connection_id: &str) {
+        self.editors
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(connection_id.to_string(), Instant::now());
+    }
+
+    pub fn forget(&self, connection_id: &str) {
+        self.editors
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(connection_id);
+    }
+
+    /// Connections that edited within `EDITOR_ACTIVITY_WINDOW_MS`, dropping older ones
+    pub fn editor_count(&self) -> usize {
+        let mut editors = self.editors.lock().unwrap_or_else(|e| e.into_inner());
+        editors.retain(|_, last_edit| {
+            last_edit.elapsed().as_millis() < EDITOR_ACTIVITY_WINDOW_MS as u128
+        });
+        editors.len()
+    }
+}
+
+/// What happens when stepping and encoding a generation takes longer than the tick interval
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DeadlineMode {
+    /// No deadline accounting
+    #[default]
+    Off,
+    /// Log and count deadline misses
+    Strict,
+    /// Like strict, and send only changed cells until ticks are on time again
+    Degrade,
+}
+
+/// Liveness bookkeeping of a room's simulation loop, checked by the watchdog
+#[derive(Default)]
+pub struct SimulationHealth {
+    // Unix millis of the last loop iteration
+    heartbeat_ms: AtomicU64,
+    // Bumped to retire a loop; a loop exits once its epoch is stale
+    epoch: AtomicU64,
+    last_good: Mutex<Option<Box<dyn GolEngine>>>,
+    deadline_mode: DeadlineMode,
+    deadline_misses: AtomicU64,
+    // Ticks on time since the last miss, only counted while degraded
+    on_time_ticks: AtomicU32,
+    degraded: AtomicBool,
+    // The loop sleeps until a receiver subscribes, it isn't expected to beat meanwhile
+    idle: AtomicBool,
+}
+
+impl SimulationHealth {
+    pub fn new(deadline_mode: DeadlineMode) -> Self {
+        Self {
+            deadline_mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn deadline_misses(&self) -> u64 {
+        self.deadline_misses.load(Ordering::Relaxed)
+    }
+
+    /// Whether generations go out as changed cells only
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Accounts for a tick whose step and encode took `work`, against a deadline of one
+    /// tick interval. Degrades on a miss and recovers after enough on-time ticks.
+    pub fn record_tick(&self, room: &str, work: std::time::Duration, deadline_ms: u64) {
+        if self.deadline_mode == DeadlineMode::Off {
+            return;
+        }
+
+        if work.as_millis() > deadline_ms as u128 {
+            let misses = self.deadline_misses.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!(
+                "Room {:?} missed its tick deadline: {:?} of {}ms ({} misses)",
+                room, work, deadline_ms, misses
+            );
+            self.on_time_ticks.store(0, Ordering::Relaxed);
+            if self.deadline_mode == DeadlineMode::Degrade
+                && !self.degraded.swap(true, Ordering::Relaxed)
+            {
+                warn!("Room {:?} degraded to changed cells only", room);
+            }
+        } else if self.is_degraded()
+            && self.on_time_ticks.fetch_add(1, Ordering::Relaxed) + 1 >= DEADLINE_RECOVERY_TICKS
+        {
+            self.degraded.store(false, Ordering::Relaxed);
+            info!("Room {:?} back on time, sending full frames again", room);
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    pub fn set_idle(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Relaxed);
+    }
+
+    pub fn beat(&self) {
+        self.heartbeat_ms
+            .store(Utc::now().timestamp_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Milliseconds since the loop last checked in
+    pub fn silence_ms(&self) -> u64 {
+        (Utc::now().timestamp_millis() as u64)
+            .saturating_sub(self.heartbeat_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+
+    /// Retires the current loop and returns the epoch for its replacement
+    pub fn next_epoch(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn store_snapshot(&self, snapshot: Box<dyn GolEngine>) {
+        *self.last_good.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+    }
+
+    pub fn last_good_snapshot(&self) -> Option<Box<dyn GolEngine>> {
+        self.last_good
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// What every new room starts with, from the server config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomSettings {
+    pub channel_cap: usize,
+    pub canvas_width: u16,
+    pub canvas_height: u16,
+    pub tick_interval_ms: u64,
+    pub autoplay: bool,
+    pub reseed_when_stable: bool,
+    pub deadline_mode: DeadlineMode,
+    // Where rooms journal their commands, None runs without a journal
+    pub journal_dir: Option<PathBuf>,
+    // Where rooms record every client message for the replay binary, None records nothing
+    pub record_dir: Option<PathBuf>,
+    // Where SAVE_STATE writes boards and LOAD_STATE reads them
+    pub data_dir: PathBuf,
+    // Generations between automatic checkpoints, None takes none
+    pub checkpoint_every: Option<u64>,
+    pub checkpoint_keep: usize,
+    // Payload bytes a client message may claim, larger ones are refused unread
+    pub max_payload_length: usize,
+    // Engine the Game of Life grid steps with until SELECT_ENGINE picks another
+    pub gol_engine: EngineKind,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            channel_cap: DEFAULT_CHANNEL_CAPACITY,
+            canvas_width: DEFAULT_CANVAS_WIDTH,
+            canvas_height: DEFAULT_CANVAS_HEIGHT,
+            tick_interval_ms: DEFAULT_TICK_INTERVAL_MS,
+            autoplay: false,
+            reseed_when_stable: false,
+            deadline_mode: DeadlineMode::default(),
+            journal_dir: None,
+            record_dir: None,
+            data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+            checkpoint_every: None,
+            checkpoint_keep: DEFAULT_CHECKPOINT_KEEP,
+            max_payload_length: DEFAULT_MAX_PAYLOAD_LENGTH,
+            gol_engine: EngineKind::default(),
+        }
+    }
+}
+
+impl Drop for RoomState {
+    // Wakes a sleeping simulation loop, which then finds the room gone and stops
+    fn drop(&mut self) {
+        self.channel.first_receiver().notify_one();
+    }
+}
+
+impl RoomState {
+    pub fn new(id: RoomId, settings: &RoomSettings, access: RoomAccess) -> Arc<RoomState> {
+        let seed = SIMULATION_SEED.unwrap_or_else(rand::random);
+        let (width, height) = (settings.canvas_width, settings.canvas_height);
+        let journal = settings.journal_dir.as_deref().and_then(|dir| {
+            CommandJournal::open(dir, &id, JOURNAL_COMPACT_EVERY)
+                .inspect_err(|e| error!("Room {:?} runs without a journal: {:#}", id, e))
+                .ok()
+        });
+        let recorder = settings.record_dir.as_deref().and_then(|dir| {
+            SessionRecorder::open(dir, &id, seed, (width, height))
+                .inspect_err(|e| error!("Room {:?} runs without recording: {:#}", id, e))
+                .ok()
+        });
+        let checkpoints = settings.checkpoint_every.map(|every| {
+            Checkpoints::new(&settings.data_dir, &id, every, settings.checkpoint_keep)
+        });
+        let gol = GolState::with_engine(width, height, seed, settings.gol_engine);
+        let room = Arc::new(RoomState {
+            id,
+            channel: RoomChannel::new(settings.channel_cap),
+            channel_capacity: settings.channel_cap,
+            simulation: SimulationControl::new(
+                settings.tick_interval_ms,
+                settings.autoplay,
+                settings.reseed_when_stable,
+            ),
+            modes: ModeManager::default(),
+            seed: AtomicU64::new(seed),
+            gol,
+            mlp: MlpState::new(width as usize, height as usize, seed),
+            brain: BrainState::new(width, height, seed),
+            sand: SandState::new(width, height, seed),
+            reaction: ReactionState::new(width, height, seed),
+            boids: BoidsState::new(width, height, seed),
+            pong: PongState::new(width, height, seed),
+            snake: SnakeState::new(width, height, seed),
+            immigration: ImmigrationState::new(width, height, seed),
+            soup_search: SoupSearch::default(),
+            bots: RoomBots::default(),
+            health: SimulationHealth::new(settings.deadline_mode),
+            access,
+            occupancy: RoomOccupancy::default(),
+            journal,
+            recorder,
+            data_dir: settings.data_dir.clone(),
+            checkpoints,
+            max_payload_length: settings.max_payload_length,
+        });
+
+        info!(
+            "Created {} room {:?} with channel capacity: {}",
+            if room.access.is_private() {
+                "private"
+            } else {
+                "public"
+            },
+            room.id,
+            settings.channel_cap
+        );
+
+        if let Some(journal) = &room.journal {
+            match journal.recover(&room) {
+                Ok(replayed) => info!(
+                    "Recovered room {:?} from its journal, {} commands replayed",
+                    room.id, replayed
+                ),
+                Err(e) => error!(
+                    "Failed to recover room {:?} from its journal: {:#}",
+                    room.id, e
+                ),
+            }
+        }
+        // A journal replays up to the last command, checkpoints only to the last checkpoint
+        if room.journal.is_none()
+            && let Some(checkpoints) = &room.checkpoints
+        {
+            match checkpoints.restore_latest(&room) {
+                Ok(Some(_)) => {}
+                Ok(None) => debug!("No checkpoint to restore room {:?} from", room.id),
+                Err(e) => error!(
+                    "Failed to restore room {:?} from its checkpoints: {:#}",
+                    room.id, e
+                ),
+            }
+        }
+
+        room
+    }
+
+    pub fn active_pattern(&self) -> ActivePattern {
+        self.modes.active()
+    }
+
+    /// Switches the room to `pattern`, telling its clients with MODE_CHANGED if it wasn't
+    /// the active one
+    pub fn set_active_pattern(&self, pattern: ActivePattern) {
+        let previous = self.modes.switch(pattern);
+        if previous != pattern {
+            let _ = self
+                .channel
+                .send(BroadcastMessage::system(create_mode_changed_message(
+                    pattern,
+                )));
+            // Clients still show the previous pattern wherever a region wouldn't redraw
+            if pattern == ActivePattern::Gol {
+                self.gol.request_keyframe();
+            }
+        }
+        if pattern == ActivePattern::Gol {
+            self.sync_painting_layer();
+        }
+    }
+
+    /// Brings the layer hybrid GOL frames reveal up to date with the painting
+    pub fn sync_painting_layer(&self) {
+        if self.gol.is_hybrid() {
+            self.gol
+                .refresh_painting_layer(self.mlp.painting_rgb_data());
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed.load(Ordering::Relaxed)
+    }
+
+    /// Full frame of the active pattern, for clients that missed messages
+    pub fn keyframe(&self) -> Result<Message, FrameError> {
+        match self.active_pattern() {
+            ActivePattern::Gol => self.gol.current_generation(),
+            ActivePattern::Mlp => self.mlp.current_painting_frame(),
+            ActivePattern::BriansBrain => self.brain.current_frame(),
+            ActivePattern::Sand => self.sand.current_frame(),
+            ActivePattern::Reaction => self.reaction.current_frame(),
+            ActivePattern::Boids => self.boids.current_frame(),
+            ActivePattern::Pong => self.pong.current_frame(),
+            ActivePattern::Snake => self.snake.current_frame(),
+            ActivePattern::Immigration => self.immigration.current_frame(),
+        }
+    }
+
+    /// Replaces the grid with a saved board and restarts its random stream from the saved
+    /// seed, returning the restored generation frame
+    pub fn load_saved(&self, saved: &SavedState) -> anyhow::Result<Message> {
+        self.gol.load_grid_dump(&saved.grid_dump)?;
+        self.seed.store(saved.seed, Ordering::Relaxed);
+        self.gol.reseed_rng(saved.seed);
+        self.set_active_pattern(ActivePattern::Gol);
+        Ok(self.gol.current_generation()?)
+    }
+
+    /// Restarts every pattern from `seed` and returns the fresh generation frame
+    pub fn reseed(&self, seed: u64) -> Result<Message, FrameError> {
+        self.seed.store(seed, Ordering::Relaxed);
+        self.mlp.reseed(seed)?;
+        self.brain.reseed(seed)?;
+        self.sand.reseed(seed);
+        self.reaction.reseed(seed)?;
+        self.boids.reseed(seed);
+        self.pong.reseed(seed);
+        self.snake.reseed(seed);
+        self.immigration.reseed(seed)?;
+        self.set_active_pattern(ActivePattern::Gol);
+        self.gol.reseed(seed)
+    }
+}
+
+#[cfg(test)]
+impl RoomState {
+    /// A room with default settings, for tests that don't care which
+    pub fn for_test() -> Arc<RoomState> {
+        Self::for_test_with(&RoomSettings::default())
+    }
+
+    /// A room with `settings`, all rooms made for tests share one id
+    pub fn for_test_with(settings: &RoomSettings) -> Arc<RoomState> {
+        Self::new("test".to_string(), settings, RoomAccess::default())
+    }
+}
+
+/// Periodically advances the room's generation and broadcasts it, until the room is
+/// dropped, the watchdog retires this loop's epoch or the server shuts down
+pub fn spawn_simulation_loop(room: Weak<RoomState>, epoch: u64, shutdown: CancellationToken) {
+    tokio::spawn(async move {
+        info!("Starting periodic message broadcaster (epoch {})", epoch);
+        let Some(mut tick_interval_ms) = room
+            .upgrade()
+            .map(|room| room.simulation.applied_tick_interval_ms())
+        else {
+            return;
+        };
+        let mut interval = tick_interval(tick_interval_ms);
+        let mut consecutive_errors = 0;
+        let mut ticks: u64 = 0;
+        const MAX_CONSECUTIVE_ERRORS: u32 = 10;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    debug!("Server shutting down, stopping broadcaster");
+                    break;
+                }
+                _ = interval.tick() => {}
+            }
+
+            let Some(room) = room.upgrade() else {
+                debug!("Room dropped, stopping broadcaster");
+                break;
+            };
+            if room.health.epoch() != epoch {
+                warn!("Broadcaster epoch {} retired by watchdog, stopping", epoch);
+                break;
+            }
+            room.health.beat();
+
+            // Nobody is watching, so the loop sleeps instead of ticking until someone
+            // subscribes, then catches them up with a keyframe
+            if room.channel.receiver_count() == 0 {
+                let first_receiver = room.channel.first_receiver();
+                room.health.set_idle(true);
+                debug!("No receivers in room {:?}, simulation sleeping", room.id);
+                drop(room);
+                tokio::select! {
+                    _ = shutdown.cancelled() => {
+                        debug!("Server shutting down, stopping broadcaster");
+                        break;
+                    }
+                    _ = first_receiver.notified() => {}
+                }
+                interval.reset();
+                continue;
+            }
+            if room.health.is_idle() {
+                room.health.set_idle(false);
+                debug!("Room {:?} has receivers again, simulation waking", room.id);
+                let pattern = room.active_pattern();
+                match room.keyframe() {
+                    Ok(frame) => {
+                        let _ = room
+                            .channel
+                            .send(BroadcastMessage::new(pattern.frame_topic(), frame));
+                    }
+                    Err(e) => warn!("Failed to draw keyframe after sleeping: {}", e),
+                }
+            }
+
+            // Stepping is CPU bound, so it runs on the blocking pool rather than a worker,
+            // which works on any runtime
+            let stepping = room.clone();
+            let stepped = tokio::task::spawn_blocking(move || {
+                let stepped = step_simulation(&stepping, tick_interval_ms, &mut ticks);
+                // Clients may have stepped the board too, so it's checked every tick
+                if let Some(checkpoints) = &stepping.checkpoints
+                    && let Err(e) = checkpoints.take_if_due(&stepping)
+                {
+                    warn!("Failed to checkpoint room {:?}: {:#}", stepping.id, e);
+                }
+                (stepped, ticks)
+            })
+            .await;
+            let stepped = match stepped {
+                Ok((stepped, stepped_ticks)) => {
+                    ticks = stepped_ticks;
+                    stepped
+                }
+                Err(e) => Err(anyhow::anyhow!("Stepping task failed: {}", e)),
+            };
+            match stepped {
+                Ok(()) => consecutive_errors = 0,
+                Err(e) => {
+                    consecutive_errors += 1;
+                    error!(
+                        "Failed to step and broadcast (attempt {}): {}",
+                        consecutive_errors, e
+                    );
+
+                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                        error!("Too many consecutive broadcast errors, shutting down broadcaster");
+                        break;
+                    }
+                }
+            }
+
+            // A new speed (or budget throttle) starts pacing over from now
+            let applied_ms = room.simulation.applied_tick_interval_ms();
+            if applied_ms != tick_interval_ms {
+                tick_interval_ms = applied_ms;
+                interval = tick_interval(tick_interval_ms);
+                interval.reset();
+            }
+        }
+
+        warn!("Periodic message broadcaster shutting down");
+    });
+}
+
+// Ticks every `tick_interval_ms` from now on, late ticks are skipped rather than bunched up
+fn tick_interval(tick_interval_ms: u64) -> tokio::time::Interval {
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(tick_interval_ms));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    interval
+}
+
+// Advances whichever pattern is active by one tick and broadcasts the result. Fails when
+// the pattern couldn't be drawn or the generation frame couldn't be broadcast.
+fn step_simulation(room: &RoomState, tick_interval_ms: u64, ticks: &mut u64) -> anyhow::Result<()> {
+    let channel = &room.channel;
+    if !room.simulation.is_autoplaying() {
+        trace!("Autoplay off, skipping generation");
+    } else if room.simulation.is_paused() {
+        trace!("Simulation paused, skipping broadcast");
+    } else if channel.receiver_count() == 0 {
+        trace!("No active receivers, skipping broadcast");
+    } else {
+        // Only the pattern the room shows moves, the others keep their state until switched to
+        match room.active_pattern() {
+            ActivePattern::Gol => step_gol(room, tick_interval_ms, ticks)?,
+            ActivePattern::Sand => {
+                // Sand moves in place, only the cells that changed are sent
+                if let Some(pixels) = room.sand.step()? {
+                    let _ = channel.send(BroadcastMessage::new(topics::PIXEL_EVENTS, pixels));
+                }
+            }
+            ActivePattern::Boids => {
+                let frame = room.boids.step()?;
+                let _ = channel.send(BroadcastMessage::new(topics::BOID_FRAMES, frame));
+            }
+            ActivePattern::Pong => {
+                let update = room.pong.step()?;
+                if let Some(pixels) = update.pixels {
+                    let _ = channel.send(BroadcastMessage::new(topics::PIXEL_EVENTS, pixels));
+                }
+                if let Some(score) = update.score {
+                    let _ = channel.send(BroadcastMessage::system(score));
+                }
+            }
+            ActivePattern::Snake => {
+                if let Some(pixels) = room.snake.step()? {
+                    let _ = channel.send(BroadcastMessage::new(topics::PIXEL_EVENTS, pixels));
+                }
+            }
+            ActivePattern::Immigration => {
+                let frame = room.immigration.advance_generation()?;
+                let _ = channel.send(BroadcastMessage::new(topics::GOL_FRAMES, frame));
+            }
+            ActivePattern::BriansBrain => {
+                let frame = room.brain.advance_generation()?;
+                let _ = channel.send(BroadcastMessage::new(topics::BRAIN_FRAMES, frame));
+            }
+            ActivePattern::Reaction => {
+                let frame = room.reaction.advance()?;
+                let _ = channel.send(BroadcastMessage::new(topics::REACTION_FRAMES, frame));
+            }
+            // Paintings only advance when a client asks
+            ActivePattern::Mlp => trace!("Painting shown, skipping generation"),
+        }
+    }
+    Ok(())
+}
+
+// Steps the Game of Life board, broadcasting the frame, its hash and stability
+fn step_gol(room: &RoomState, tick_interval_ms: u64, ticks: &mut u64) -> anyhow::Result<()> {
+    let channel = &room.channel;
+    let started = Instant::now();
+    room.sync_painting_layer();
+    if room.gol.is_settled() {
+        // Clients already show this board, stepping resumes with the next change
+        trace!("Board settled, skipping generation");
+        return Ok(());
+    }
+    let frame = if room.health.is_degraded() {
+        room.gol.advance_generation_delta()?
+    } else {
+        room.gol.advance_generation()?
+    };
+    let elapsed = started.elapsed();
+    room.health.record_tick(&room.id, elapsed, tick_interval_ms);
+    METRICS.record_tick(elapsed);
+    *ticks += 1;
+    if ticks.is_multiple_of(WATCHDOG_SNAPSHOT_EVERY_TICKS)
+        && let Some(snapshot) = room.gol.try_snapshot()
+    {
+        room.health.store_snapshot(snapshot);
+    }
+
+    channel.send(BroadcastMessage::new(topics::GOL_FRAMES, frame))?;
+    // Lets predicting clients check the generation they stepped locally
+    let (generation, hash) = room.gol.generation_hash();
+    let _ = channel.send(BroadcastMessage::new(
+        topics::GOL_FRAMES,
+        create_generation_hash_message(generation, hash),
+    ));
+    if let Some(period) = room.gol.detect_stability(hash) {
+        info!(
+            "Room {:?} stable at generation {} with period {}",
+            room.id, generation, period
+        );
+        let _ = channel.send(BroadcastMessage::new(
+            topics::GOL_FRAMES,
+            create_simulation_stable_message(generation, period),
+        ));
+        if room.simulation.reseeds_when_stable() {
+            let frame = room.gol.create_new_generation()?;
+            let _ = channel.send(BroadcastMessage::new(topics::GOL_FRAMES, frame));
+        }
+    }
+    debug!(
+        "Broadcasted message to {} receivers in room {:?}",
+        channel.receiver_count(),
+        room.id
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MAX_REASSEMBLED_PAYLOAD, MIN_TICK_INTERVAL_MS};
+    use crate::protocol::{FLAG_DEFLATE, FLAG_SEQUENCED, WsMessage};
+
+    #[test]
+    fn validate_room_ids() {
+        assert!(validate_room_id("default").is_ok());
+        assert!(validate_room_id("team-a_2").is_ok());
+        assert!(validate_room_id("").is_err());
+        assert!(validate_room_id("../etc").is_err());
+        assert!(validate_room_id(&"a".repeat(MAX_ROOM_ID_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn frames_are_deflated_for_clients_that_take_it() {
+        let frame = BroadcastMessage::new(
+            topics::GOL_FRAMES,
+            crate::utils::create_frame_message(16, 16, vec![0; 16 * 16 * 3]).unwrap(),
+        );
+        assert_eq!(
+            frame.message_for(0).as_payload()[..],
+            frame.message.as_payload()[..]
+        );
+
+        let deflated = frame.message_for(CODEC_DEFLATE);
+        let msg = WsMessage::decode(deflated.as_payload()).unwrap();
+        assert_ne!(msg.flags & FLAG_DEFLATE, 0);
+        assert!(deflated.as_payload().len() < frame.message.as_payload().len());
+        assert_eq!(
+            game_of_life_core::compression::inflate(&msg.payload, MAX_REASSEMBLED_PAYLOAD).unwrap(),
+            frame.message.as_payload()[7..]
+        );
+    }
+
+    #[test]
+    fn room_channel_numbers_each_topic() {
+        let channel = RoomChannel::new(8);
+        let mut receiver = channel.subscribe();
+        let frame = || {
+            BroadcastMessage::new(
+                topics::GOL_FRAMES,
+                crate::utils::create_frame_message(4, 4, vec![0; 4 * 4 * 3]).unwrap(),
+            )
+        };
+        let status = || BroadcastMessage::system(Message::binary(vec![1, 102, 0, 0, 0, 0, 0]));
+        channel.send(frame()).unwrap();
+        channel.send(status()).unwrap();
+        channel.send(frame()).unwrap();
+        channel.send(status().sent_by("a")).unwrap();
+
+        let mut prefix = || {
+            let message = receiver.try_recv().unwrap();
+            let sent = message.message_for(0);
+            // Every receiver gets the same buffer
+            assert_eq!(
+                sent.as_payload().as_ptr(),
+                message.clone().message_for(0).as_payload().as_ptr()
+            );
+            let msg = WsMessage::decode(sent.as_payload()).unwrap();
+            (msg.flags & FLAG_SEQUENCED != 0).then(|| msg.payload[..5].to_vec())
+        };
+        assert_eq!(prefix(), Some(vec![topics::GOL_FRAMES, 0, 0, 0, 1]));
+        assert_eq!(prefix(), Some(vec![topics::SYSTEM, 0, 0, 0, 1]));
+        assert_eq!(prefix(), Some(vec![topics::GOL_FRAMES, 0, 0, 0, 2]));
+        assert_eq!(prefix(), None);
+    }
+
+    #[test]
+    fn broadcast_topics_follow_message_type() {
+        let encoded = |msg_type| Message::binary(vec![1, msg_type, 0, 0, 0, 0, 0]);
+
+        let pixel =
+            BroadcastMessage::from_pattern(ActivePattern::Mlp, encoded(message_types::DRAW_PIXEL));
+        assert_eq!(pixel.topic, topics::PIXEL_EVENTS);
+
+        let gol =
+            BroadcastMessage::from_pattern(ActivePattern::Gol, encoded(message_types::DRAW_FRAME));
+        assert_eq!(gol.topic, topics::GOL_FRAMES);
+
+        let mlp =
+            BroadcastMessage::from_pattern(ActivePattern::Mlp, encoded(message_types::DRAW_FRAME));
+        assert_eq!(mlp.topic, topics::MLP_FRAMES);
+
+        let brain = BroadcastMessage::from_pattern(
+            ActivePattern::BriansBrain,
+            encoded(message_types::DRAW_FRAME),
+        );
+        assert_eq!(brain.topic, topics::BRAIN_FRAMES);
+
+        let text = BroadcastMessage::from_pattern(ActivePattern::Gol, Message::text("hi"));
+        assert_eq!(text.topic, topics::SYSTEM);
+
+        let cursor = BroadcastMessage::system(crate::utils::create_cursor_moved_message(
+            "a",
+            1,
+            2,
+            [0, 0, 0],
+        ))
+        .sent_by("a");
+        assert!(cursor.is_from("a"));
+        assert!(!cursor.is_from("b"));
+        assert!(!text.is_from("a"));
+    }
+
+    #[test]
+    fn private_room_admission() {
+        let join = |password: Option<&str>, invite: Option<&str>| JoinCredentials {
+            password: password.map(str::to_string),
+            invite: invite.map(str::to_string),
+            ..Default::default()
+        };
+
+        let public = RoomAccess::with_password(None);
+        assert!(public.admit(&join(None, None)));
+
+        let private = RoomAccess::with_password(Some("hunter2".to_string()));
+        assert!(private.admit(&join(Some("hunter2"), None)));
+        assert!(!private.admit(&join(Some("wrong"), None)));
+        assert!(!private.admit(&join(Some("hunter"), None)));
+        assert!(!private.admit(&join(Some("hunter3"), None)));
+        assert!(!private.admit(&join(Some(""), None)));
+        assert!(private.has_password(Some("hunter2")));
+        assert!(!private.has_password(None));
+        assert!(public.has_password(Some("anything")));
+        assert!(!private.admit(&join(None, Some("made-up"))));
+
+        let token = private.create_invite().unwrap();
+        assert!(private.admit(&join(None, Some(&token))));
+        assert!(
+            !private.admit(&join(None, Some(&token))),
+            "invites are single-use"
+        );
+    }
+
+    #[test]
+    fn rooms_without_autoplay_stay_on_their_generation() {
+        let room = RoomState::for_test();
+        assert!(!room.simulation.is_autoplaying());
+        let mut receiver = room.channel.subscribe();
+        let generation = room.gol.generation_stats().0;
+        let mut ticks = 0;
+        for _ in 0..3 {
+            step_simulation(&room, DEFAULT_TICK_INTERVAL_MS, &mut ticks).unwrap();
+        }
+        assert_eq!(room.gol.generation_stats().0, generation);
+        assert!(receiver.try_recv().is_err());
+
+        room.simulation.set_autoplay(true);
+        step_simulation(&room, DEFAULT_TICK_INTERVAL_MS, &mut ticks).unwrap();
+        assert_eq!(room.gol.generation_stats().0, generation + 1);
+    }
+
+    #[test]
+    fn autoplay_steps_only_the_active_pattern() {
+        let room = RoomState::for_test();
+        room.simulation.set_autoplay(true);
+        room.set_active_pattern(ActivePattern::Mlp);
+        let mut receiver = room.channel.subscribe();
+        let generation = room.gol.generation_stats().0;
+        let mut ticks = 0;
+        let mut topics_sent = |room: &RoomState| {
+            for _ in 0..3 {
+                step_simulation(room, DEFAULT_TICK_INTERVAL_MS, &mut ticks).unwrap();
+            }
+            std::iter::from_fn(|| receiver.try_recv().ok())
+                .map(|message| message.topic)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(!topics_sent(&room).contains(&topics::GOL_FRAMES));
+        assert_eq!(room.gol.generation_stats().0, generation);
+
+        room.set_active_pattern(ActivePattern::BriansBrain);
+        let sent = topics_sent(&room);
+        assert!(!sent.contains(&topics::GOL_FRAMES));
+        assert_eq!(
+            sent.iter()
+                .filter(|&&topic| topic == topics::BRAIN_FRAMES)
+                .count(),
+            3
+        );
+        assert_eq!(room.gol.generation_stats().0, generation);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simulation_sleeps_until_someone_watches() {
+        let room = RoomState::for_test();
+        room.simulation.set_autoplay(true);
+        room.simulation.set_tick_interval_ms(MIN_TICK_INTERVAL_MS);
+        let shutdown = CancellationToken::new();
+        spawn_simulation_loop(Arc::downgrade(&room), room.health.epoch(), shutdown.clone());
+
+        let started = Instant::now();
+        while !room.health.is_idle() {
+            assert!(started.elapsed().as_secs() < 5, "loop never went idle");
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let generation = room.gol.generation_stats().0;
+        tokio::time::sleep(std::time::Duration::from_millis(MIN_TICK_INTERVAL_MS * 10)).await;
+        assert_eq!(room.gol.generation_stats().0, generation);
+
+        let mut receiver = room.channel.subscribe();
+        let keyframe = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("A keyframe within 5s")
+            .unwrap();
+        assert_eq!(keyframe.message.as_payload()[1], message_types::DRAW_FRAME);
+        assert!(!room.health.is_idle());
+        shutdown.cancel();
+    }
+
+    // A current-thread runtime, like the one of plain `#[tokio::test]`s, runs the loop too
+    #[tokio::test]
+    async fn simulation_loops_tick_until_shutdown() {
+        let settings = RoomSettings {
+            canvas_width: 16,
+            canvas_height: 16,
+            tick_interval_ms: MIN_TICK_INTERVAL_MS,
+            autoplay: true,
+            ..RoomSettings::default()
+        };
+        let room = RoomState::for_test_with(&settings);
+        let mut receiver = room.channel.subscribe();
+        let shutdown = CancellationToken::new();
+        room.health.beat();
+        spawn_simulation_loop(Arc::downgrade(&room), room.health.epoch(), shutdown.clone());
+
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let message = receiver.recv().await.unwrap();
+                if message.topic == topics::GOL_FRAMES {
+                    break message;
+                }
+            }
+        });
+        frame.await.expect("A generation within 5s");
+        assert!(room.gol.generation_stats().0 > 0);
+
+        shutdown.cancel();
+        // A tick already under way may still land
+        tokio::time::sleep(std::time::Duration::from_millis(MIN_TICK_INTERVAL_MS * 5)).await;
+        let generation = room.gol.generation_stats().0;
+        tokio::time::sleep(std::time::Duration::from_millis(MIN_TICK_INTERVAL_MS * 10)).await;
+        assert_eq!(room.gol.generation_stats().0, generation);
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::{
+    constants::{GOL_RULE, MAX_SAVE_NAME_LENGTH, SAVE_FILE_MAGIC, SAVE_FILE_VERSION},
+    room::RoomState,
+};
+
+/// A board saved with SAVE_STATE, stored as `<data dir>/<name>.gol`. File layout,
+/// big-endian:
+/// - 4 bytes: `SAVE_FILE_MAGIC`
+/// - 1 byte: `SAVE_FILE_VERSION`
+/// - 8 bytes: seed the room's random streams last started from
+/// - 1 byte: rule length, then the rule, e.g. "B3/S23"
+/// - N bytes: grid dump, which carries the dimensions and the generation count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedState {
+    pub seed: u64,
+    pub rule: String,
+    pub grid_dump: Vec<u8>,
+}
+
+impl SavedState {
+    pub fn capture(room: &RoomState) -> Self {
+        Self {
+            seed: room.seed(),
+            rule: GOL_RULE.to_string(),
+            grid_dump: room.gol.grid_dump(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(14 + self.rule.len() + self.grid_dump.len());
+        buf.extend(SAVE_FILE_MAGIC);
+        buf.push(SAVE_FILE_VERSION);
+        buf.extend(self.seed.to_be_bytes());
+        buf.push(self.rule.len() as u8);
+        buf.extend(self.rule.as_bytes());
+        buf.extend(&self.grid_dump);
+        buf
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() < 14 || &data[..4] != SAVE_FILE_MAGIC {
+            bail!("Not a saved board");
+        }
+        if data[4] != SAVE_FILE_VERSION {
+            bail!("Unsupported save file version {}", data[4]);
+        }
+        let seed = u64::from_be_bytes(data[5..13].try_into()?);
+        let rule_end = 14 + data[13] as usize;
+        let Some(rule) = data.get(14..rule_end) else {
+            bail!("Save file ends inside its rule");
+        };
+
+        Ok(Self {
+            seed,
+            rule: String::from_utf8(rule.to_vec()).context("Rule isn't UTF-8")?,
+            grid_dump: data[rule_end..].to_vec(),
+        })
+    }
+}
+
+/// Save names are file names, so only letters, digits, '-' and '_' are allowed
+pub fn validate_save_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_SAVE_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("Invalid save name {:?}", name);
+    }
+    Ok(())
+}
+
+fn save_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.gol", name))
+}
+
+/// Writes the room's board to `path`, replacing the file. Returns the saved generation.
+pub fn write_saved_state(path: &Path, room: &RoomState) -> Result<u64> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let saved = SavedState::capture(room);
+    let (generation, _) = room.gol.generation_stats();
+    // Written aside and moved into place, a crash mid-write keeps the previous file
+    let partial_path = path.with_extension("gol.partial");
+    std::fs::write(&partial_path, saved.encode())
+        .with_context(|| format!("Failed to write {}", partial_path.display()))?;
+    std::fs::rename(&partial_path, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(generation)
+}
+
+/// Reads the board saved at `path`, refusing boards of another rule
+pub fn read_saved_state(path: &Path) -> Result<SavedState> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let saved =
+        SavedState::decode(&data).with_context(|| format!("Invalid save {}", path.display()))?;
+    if saved.rule != GOL_RULE {
+        bail!(
+            "{} runs rule {}, this server runs {}",
+            path.display(),
+            saved.rule,
+            GOL_RULE
+        );
+    }
+    Ok(saved)
+}
+
+/// Writes the room's board to `dir` as `name`, replacing an earlier save of that name.
+/// Returns the saved generation.
+pub fn save_state(dir: &Path, name: &str, room: &RoomState) -> Result<u64> {
+    validate_save_name(name)?;
+    let path = save_path(dir, name);
+    let generation = write_saved_state(&path, room)?;
+    info!(
+        "Saved room {:?} at generation {} to {}",
+        room.id,
+        generation,
+        path.display()
+    );
+    Ok(generation)
+}
+
+/// Reads the board saved in `dir` as `name`
+pub fn load_state(dir: &Path, name: &str) -> Result<SavedState> {
+    validate_save_name(name)?;
+    read_saved_state(&save_path(dir, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_boards_survive_a_new_room() {
+        let dir = std::env::temp_dir().join(format!("gol-saves-{}", uuid::Uuid::new_v4()));
+        let room = RoomState::for_test();
+        room.reseed(42).unwrap();
+        room.gol.fast_forward(5);
+        assert_eq!(save_state(&dir, "glider-party", &room).unwrap(), 5);
+        assert!(save_state(&dir, "../escape", &room).is_err());
+
+        let restored = RoomState::for_test();
+        restored
+            .load_saved(&load_state(&dir, "glider-party").unwrap())
+            .unwrap();
+        assert_eq!(restored.gol.generation_hash(), room.gol.generation_hash());
+        assert_eq!(restored.seed(), 42);
+        assert!(load_state(&dir, "missing").is_err());
+
+        let mut saved = SavedState::capture(&room);
+        saved.rule = "B36/S23".to_string();
+        std::fs::write(save_path(&dir, "highlife"), saved.encode()).unwrap();
+        assert!(load_state(&dir, "highlife").is_err());
+        let mut encoded = saved.encode();
+        encoded[4] = SAVE_FILE_VERSION + 1;
+        assert!(SavedState::decode(&encoded).is_err());
+        assert!(SavedState::decode(&encoded[..10]).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,482 @@
+//! Cron-like automation for unattended deployments.
+//!
+//! Entries are read from `SCHEDULE_FILE` at startup, one per line, and admins add and
+//! remove more with ADD_SCHEDULE_ENTRY and REMOVE_SCHEDULE_ENTRY:
+//!
+//! ```text
+//! # minute hour day-of-month month day-of-week action [room]
+//! */10 * * * *  reseed
+//! 0 * * * *     stamp:gosper-glider-gun
+//! 0 22 * * *    mlp     lobby
+//! 0 * * * *     snapshot
+//! ```
+//!
+//! Fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps (`*/10`, `0-30/5`).
+//! Days of week run 0-6 from Sunday. Times are server local time. Every event that runs is
+//! announced to its room with SCHEDULED_EVENT.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    constants::{MAX_SCHEDULE_ENTRIES, SCHEDULE_FILE, topics},
+    patterns::library::LibraryPattern,
+    room::{BroadcastMessage, DEFAULT_ROOM, RoomId, RoomState},
+    snapshots::save_snapshot,
+    state::{ActivePattern, AppState},
+    utils::{create_scheduled_event_message, create_simulation_status_message},
+};
+
+/// Allowed values of each cron field, one bit per value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    // The five fields as written, for listing the entry
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+}
+
+impl CronSchedule {
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        let is_set = |mask: u64, value: u32| mask & (1 << value) != 0;
+
+        is_set(self.minutes, time.minute())
+            && is_set(self.hours, time.hour())
+            && is_set(self.days_of_month, time.day())
+            && is_set(self.months, time.month())
+            && is_set(self.days_of_week, time.weekday().num_days_from_sunday())
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            bail!("Expected 5 cron fields, got {}", fields.len());
+        };
+
+        Ok(Self {
+            source: fields.join(" "),
+            minutes: parse_field(minute, 0, 59).context("minute")?,
+            hours: parse_field(hour, 0, 23).context("hour")?,
+            days_of_month: parse_field(day_of_month, 1, 31).context("day of month")?,
+            months: parse_field(month, 1, 12).context("month")?,
+            days_of_week: parse_field(day_of_week, 0, 6).context("day of week")?,
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("Step of {:?} must be positive", part);
+        }
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            bail!("{:?} is outside {}-{}", part, min, max);
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledAction {
+    /// Fresh random Game of Life generation
+    Reseed,
+    /// Kill every cell
+    Clear,
+    /// Show the Game of Life canvas
+    SwitchToGol,
+    /// Show the Mona Lisa painting
+    SwitchToMlp,
+    /// Write the bit-packed grid to `SNAPSHOT_DIR`
+    Snapshot,
+    Pause,
+    Resume,
+    /// Stamp a library pattern in the middle of the Game of Life board, written
+    /// `stamp:<name>` with dashes for spaces, e.g. `stamp:gosper-glider-gun`
+    Stamp(LibraryPattern),
+}
+
+impl FromStr for ScheduledAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(name) = s.strip_prefix("stamp:") {
+            let name = name.replace('-', " ");
+            return match LibraryPattern::ALL.iter().find(|p| p.name() == name) {
+                Some(&pattern) => Ok(ScheduledAction::Stamp(pattern)),
+                None => bail!("Unknown library pattern {:?}", name),
+            };
+        }
+
+        Ok(match s {
+            "reseed" => ScheduledAction::Reseed,
+            "clear" => ScheduledAction::Clear,
+            "gol" => ScheduledAction::SwitchToGol,
+            "mlp" => ScheduledAction::SwitchToMlp,
+            "snapshot" => ScheduledAction::Snapshot,
+            "pause" => ScheduledAction::Pause,
+            "resume" => ScheduledAction::Resume,
+            unknown => bail!("Unknown action {:?}", unknown),
+        })
+    }
+}
+
+/// The action as written in a schedule, what SCHEDULED_EVENT announces
+impl fmt::Display for ScheduledAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduledAction::Reseed => f.write_str("reseed"),
+            ScheduledAction::Clear => f.write_str("clear"),
+            ScheduledAction::SwitchToGol => f.write_str("gol"),
+            ScheduledAction::SwitchToMlp => f.write_str("mlp"),
+            ScheduledAction::Snapshot => f.write_str("snapshot"),
+            ScheduledAction::Pause => f.write_str("pause"),
+            ScheduledAction::Resume => f.write_str("resume"),
+            ScheduledAction::Stamp(pattern) => {
+                write!(f, "stamp:{}", pattern.name().replace(' ', "-"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry {
+    pub schedule: CronSchedule,
+    pub action: ScheduledAction,
+    pub room: RoomId,
+}
+
+impl ScheduleEntry {
+    /// Parses one schedule line, running in `default_room` unless it names a room
+    pub fn parse(line: &str, default_room: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if !(6..=7).contains(&fields.len()) {
+            bail!("Expected 5 cron fields, an action and an optional room");
+        }
+
+        let schedule = fields[..5].join(" ").parse().context("invalid schedule")?;
+        let action = fields[5].parse().context("invalid action")?;
+        let room = fields.get(6).copied().unwrap_or(default_room).to_string();
+
+        Ok(Self {
+            schedule,
+            action,
+            room,
+        })
+    }
+}
+
+/// The entry as a schedule line, room included
+impl fmt::Display for ScheduleEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.schedule, self.action, self.room)
+    }
+}
+
+/// Parses a schedule file, skipping blank lines and `#` comments
+pub fn parse_schedule(input: &str) -> Result<Vec<ScheduleEntry>> {
+    let mut entries = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = ScheduleEntry::parse(line, DEFAULT_ROOM)
+            .with_context(|| format!("Line {}", index + 1))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Entries the scheduler runs, shared by the server so admins can change them while it runs
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    entries: Mutex<Vec<ScheduleEntry>>,
+}
+
+impl Scheduler {
+    pub fn entries(&self) -> Vec<ScheduleEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Adds an entry after the others. Fails once there are `MAX_SCHEDULE_ENTRIES`.
+    pub fn add(&self, entry: ScheduleEntry) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= MAX_SCHEDULE_ENTRIES {
+            bail!("Schedule already has {} entries", entries.len());
+        }
+        entries.push(entry);
+        Ok(())
+    }
+
+    /// Removes the entry at `index` in the order of `entries`
+    pub fn remove(&self, index: usize) -> Option<ScheduleEntry> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        (index < entries.len()).then(|| entries.remove(index))
+    }
+
+    /// Entries due at `time`
+    pub fn due(&self, time: &DateTime<Local>) -> Vec<ScheduleEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .filter(|entry| entry.schedule.matches(time))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Loads `SCHEDULE_FILE` into the server's scheduler, if there is one, and runs the due
+/// entries once a minute
+pub fn spawn_scheduler(state: Arc<AppState>) {
+    match std::fs::read_to_string(SCHEDULE_FILE) {
+        Ok(input) => match parse_schedule(&input) {
+            Ok(entries) => {
+                info!("Loaded {} entries from {}", entries.len(), SCHEDULE_FILE);
+                for entry in entries {
+                    if let Err(e) = state.scheduler.add(entry) {
+                        warn!("Skipping the rest of {}: {:#}", SCHEDULE_FILE, e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => error!("Ignoring {}, failed to parse it: {:#}", SCHEDULE_FILE, e),
+        },
+        Err(e) => info!(
+            "No {}, starting with an empty schedule: {}",
+            SCHEDULE_FILE, e
+        ),
+    }
+
+    tokio::spawn(async move {
+        info!("Starting scheduler");
+
+        loop {
+            // Wake just after each minute boundary
+            let now = Local::now();
+            let until_next_minute = 60 - now.second() as u64;
+            tokio::time::sleep(Duration::from_secs(until_next_minute)).await;
+
+            let now = Local::now();
+            for entry in state.scheduler.due(&now) {
+                let Some(room) = state.room(&entry.room) else {
+                    debug!(
+                        "Skipping {:?}, room {:?} does not exist",
+                        entry.action, entry.room
+                    );
+                    continue;
+                };
+                info!("Running scheduled {:?} in room {:?}", entry.action, room.id);
+                if let Err(e) = run_action(&room, entry.action, &now).await {
+                    error!("Scheduled {:?} failed: {:#}", entry.action, e);
+                }
+            }
+        }
+    });
+}
+
+async fn run_action(
+    room: &RoomState,
+    action: ScheduledAction,
+    now: &DateTime<Local>,
+) -> Result<()> {
+    // Announced first, so clients know why the board is about to change
+    let _ = room
+        .channel
+        .send(BroadcastMessage::system(create_scheduled_event_message(
+            &action.to_string(),
+        )));
+
+    let broadcast = match action {
+        ScheduledAction::Reseed => {
+            room.set_active_pattern(ActivePattern::Gol);
+            Some(BroadcastMessage::new(
+                topics::GOL_FRAMES,
+                room.gol.create_new_generation()?,
+            ))
+        }
+        ScheduledAction::Clear => {
+            room.set_active_pattern(ActivePattern::Gol);
+            Some(BroadcastMessage::new(
+                topics::GOL_FRAMES,
+                room.gol.kill_all_cells()?,
+            ))
+        }
+        ScheduledAction::SwitchToGol => {
+            room.set_active_pattern(ActivePattern::Gol);
+            Some(BroadcastMessage::new(
+                topics::GOL_FRAMES,
+                room.gol.current_generation()?,
+            ))
+        }
+        ScheduledAction::SwitchToMlp => {
+            room.set_active_pattern(ActivePattern::Mlp);
+            Some(BroadcastMessage::new(
+                topics::MLP_FRAMES,
+                room.mlp.current_painting_frame()?,
+            ))
+        }
+        ScheduledAction::Snapshot => {
+            save_snapshot(room, now).await?;
+            None
+        }
+        ScheduledAction::Stamp(pattern) => {
+            let pattern = pattern.pattern();
+            let (width, height) = room.gol.dimensions();
+            // Centered, the board wraps whatever doesn't fit
+            let x = width.saturating_sub(pattern.width as u16) / 2;
+            let y = height.saturating_sub(pattern.height as u16) / 2;
+            room.gol.stamp_pattern(x, y, &pattern);
+            room.set_active_pattern(ActivePattern::Gol);
+            // The whole frame, the room may have been showing something else
+            Some(BroadcastMessage::new(
+                topics::GOL_FRAMES,
+                room.gol.current_generation()?,
+            ))
+        }
+        ScheduledAction::Pause | ScheduledAction::Resume => {
+            room.simulation.set_paused(action == ScheduledAction::Pause);
+            Some(BroadcastMessage::system(create_simulation_status_message(
+                &room.simulation,
+            )))
+        }
+    };
+
+    // Nobody watching is fine, the room still changed
+    if let Some(message) = broadcast
+        && room.channel.send(message).is_err()
+    {
+        warn!(
+            "No viewers in room {:?} for scheduled {:?}",
+            room.id, action
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::message_types;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn cron_fields() {
+        let schedule: CronSchedule = "*/15 9-17 * * 1-5".parse().unwrap();
+        let at = |d, h, m| Local.with_ymd_and_hms(2025, 6, d, h, m, 0).unwrap();
+
+        // 2025-06-02 is a Monday, 2025-06-01 a Sunday
+        assert!(schedule.matches(&at(2, 9, 0)));
+        assert!(schedule.matches(&at(2, 17, 45)));
+        assert!(!schedule.matches(&at(2, 9, 10)));
+        assert!(!schedule.matches(&at(2, 18, 0)));
+        assert!(!schedule.matches(&at(1, 9, 0)));
+
+        assert!("0 0 * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+    }
+
+    #[test]
+    fn schedule_file() {
+        let entries = parse_schedule(
+            "# reseed every midnight\n\
+             0 0 * * * reseed\n\
+             \n\
+             0 9 * * * mlp lobby\n",
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, ScheduledAction::Reseed);
+        assert_eq!(entries[0].room, DEFAULT_ROOM);
+        assert_eq!(entries[1].action, ScheduledAction::SwitchToMlp);
+        assert_eq!(entries[1].room, "lobby");
+
+        assert!(parse_schedule("0 0 * * * explode").is_err());
+        assert!(parse_schedule("0 0 * * *").is_err());
+    }
+
+    #[tokio::test]
+    async fn scheduled_events_are_announced_before_they_run() {
+        let scheduler = Scheduler::default();
+        let entry = ScheduleEntry::parse("0  * * * *  stamp:gosper-glider-gun", "lobby").unwrap();
+        assert_eq!(
+            entry.action,
+            ScheduledAction::Stamp(LibraryPattern::GosperGliderGun)
+        );
+        assert_eq!(entry.to_string(), "0 * * * * stamp:gosper-glider-gun lobby");
+        assert!(ScheduleEntry::parse("0 * * * * stamp:glider-factory", "lobby").is_err());
+        scheduler.add(entry.clone()).unwrap();
+        scheduler
+            .add(ScheduleEntry::parse("*/10 * * * * reseed", "lobby").unwrap())
+            .unwrap();
+        let at = |m| Local.with_ymd_and_hms(2025, 6, 2, 9, m, 0).unwrap();
+        assert_eq!(scheduler.due(&at(0)).len(), 2);
+        assert_eq!(scheduler.due(&at(10)).len(), 1);
+        assert!(scheduler.remove(1).is_some());
+        assert!(scheduler.remove(1).is_none());
+        assert!(scheduler.due(&at(10)).is_empty());
+
+        let room = RoomState::for_test();
+        room.gol.kill_all_cells().unwrap();
+        let mut receiver = room.channel.subscribe();
+        run_action(&room, entry.action, &at(0)).await.unwrap();
+
+        let announcement = receiver.recv().await.unwrap();
+        let payload = announcement.message.as_payload();
+        assert_eq!(payload[1], message_types::SCHEDULED_EVENT);
+        assert_eq!(&payload[7..], b"stamp:gosper-glider-gun");
+        let frame = receiver.recv().await.unwrap();
+        assert_eq!(frame.topic, topics::GOL_FRAMES);
+        // The gun's 36 cells
+        assert_eq!(room.gol.generation_stats().1, 36);
+    }
+}
@@ -0,0 +1,374 @@
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+use axum_tws::WebSocketUpgrade;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    api, events,
+    room::{DEFAULT_ROOM, JoinCredentials},
+    socket::handle_socket,
+    state::AppState,
+};
+
+/// Every route of the server, files from `static_dir` answering the rest
+pub fn router(state: Arc<AppState>, static_dir: PathBuf) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/ws/{room}", get(ws_room_handler))
+        .route("/events", get(events::events))
+        .route("/events/{room}", get(events::room_events))
+        .route("/api/stats/live", get(api::live_stats))
+        .route("/api/stats/live/{room}", get(api::live_room_stats))
+        .route("/api/gol/grid.bin", get(api::gol_grid))
+        .route("/api/rooms", get(api::rooms))
+        .route("/api/rooms/{room}/gol/grid.bin", get(api::room_gol_grid))
+        .route("/api/gol/state", get(api::gol_state))
+        .route("/api/gol/step", post(api::gol_step))
+        .route("/api/gol/reset", post(api::gol_reset))
+        .route("/api/gol/cells", post(api::gol_cells))
+        .route("/api/rooms/{room}/gol/state", get(api::room_gol_state))
+        .route("/api/rooms/{room}/gol/step", post(api::room_gol_step))
+        .route("/api/rooms/{room}/gol/reset", post(api::room_gol_reset))
+        .route("/api/rooms/{room}/gol/cells", post(api::room_gol_cells))
+        .route("/api/rooms/{room}/invites", post(api::create_room_invite))
+        .route("/api/rooms/{room}/journal", get(api::room_journal))
+        .route("/api/snapshots", get(api::snapshots))
+        .route("/metrics", get(api::metrics))
+        .route("/healthz", get(api::healthz))
+        .route("/readyz", get(api::readyz))
+        .with_state(state)
+        .fallback_service(axum_static::static_router(static_dir))
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    upgrade_into_room(ws, &state, DEFAULT_ROOM, &credentials)
+}
+
+async fn ws_room_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(credentials): Query<JoinCredentials>,
+) -> Response {
+    upgrade_into_room(ws, &state, &room, &credentials)
+}
+
+fn upgrade_into_room(
+    ws: WebSocketUpgrade,
+    state: &Arc<AppState>,
+    room_id: &str,
+    credentials: &JoinCredentials,
+) -> Response {
+    info!("New WebSocket connection attempt for room {:?}", room_id);
+
+    match state
+        .reserve_connection()
+        .and_then(|slot| Ok((slot, state.join_room(room_id, credentials)?)))
+    {
+        Ok((slot, room)) => {
+            // The connection's id for as long as it stays open, also what the room sees
+            let connection_id = Uuid::new_v4().to_string();
+            let admin = state.admin.admits(credentials.admin_token.as_deref());
+            if admin && !state.admin.is_open() {
+                info!("Connection {} joined as admin", connection_id);
+            }
+            let state = state.clone();
+            ws.on_upgrade(move |socket| async move {
+                handle_socket(socket, room, connection_id, &state, admin).await;
+                // The slot frees up once the connection is done
+                drop(slot);
+            })
+        }
+        Err(e) => {
+            warn!("Rejected WebSocket connection: {}", e);
+            e.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_websockets::{ClientBuilder, MaybeTlsStream, Message, WebSocketStream};
+
+    use crate::client::{ClientMessage, Draw, FrameDecoder};
+    use crate::constants::{CURSOR_MIN_INTERVAL_MS, error_codes, message_types};
+    use crate::protocol::PROTOCOL_VERSION;
+    use crate::room::RoomSettings;
+    use crate::state::ActivePattern;
+    use crate::watchdog::RoomHealth;
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    const WIDTH: u16 = 24;
+    const HEIGHT: u16 = 16;
+
+    // Serves a WIDTH x HEIGHT board on an ephemeral port, returning the state and /ws's uri
+    async fn serve() -> (Arc<AppState>, String) {
+        serve_with_admin_token(None).await
+    }
+
+    async fn serve_with_admin_token(admin_token: Option<&str>) -> (Arc<AppState>, String) {
+        let settings = RoomSettings {
+            canvas_width: WIDTH,
+            canvas_height: HEIGHT,
+            ..RoomSettings::default()
+        };
+        let state = Arc::new(AppState::new(settings, admin_token.map(str::to_string), 8));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(state.clone(), PathBuf::from("static"));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (state, format!("ws://{}/ws", addr))
+    }
+
+    async fn connect(uri: &str) -> Client {
+        let (client, _) = ClientBuilder::new()
+            .uri(uri)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        client
+    }
+
+    async fn send(client: &mut Client, message: ClientMessage) {
+        client.send(message.to_message()).await.unwrap();
+    }
+
+    // Type and payload of the next message of `msg_type`, skipping whatever else the server
+    // sends, and the message as sent
+    async fn receive(client: &mut Client, msg_type: u8) -> (Vec<u8>, Message) {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("Message within 5s")
+                .expect("Connection open")
+                .unwrap();
+            if !message.is_binary() {
+                continue;
+            }
+            let (received_type, payload) = FrameDecoder::default()
+                .unwrap_message(message.as_payload())
+                .unwrap();
+            if received_type == msg_type {
+                return (payload, message);
+            }
+        }
+    }
+
+    // What the next message of `msg_type` draws
+    async fn receive_draw(client: &mut Client, msg_type: u8) -> Draw {
+        let (_, message) = receive(client, msg_type).await;
+        FrameDecoder::default()
+            .decode(message.as_payload())
+            .unwrap()
+            .expect("A drawing message")
+    }
+
+    // The default room's simulation loop still checks in every tick, as it does while
+    // anyone watches, and the room reports healthy
+    async fn assert_room_ticking(state: &AppState) {
+        let room = state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        let tick_ms = room.simulation.applied_tick_interval_ms();
+        tokio::time::sleep(Duration::from_millis(tick_ms * 3)).await;
+        assert!(
+            room.health.silence_ms() <= tick_ms * 2,
+            "Simulation loop silent for {}ms",
+            room.health.silence_ms()
+        );
+        assert!(RoomHealth::check(&room).is_healthy());
+    }
+
+    // Width and height of the next DRAW_FRAME
+    async fn receive_frame(client: &mut Client) -> (u16, u16) {
+        match receive_draw(client, message_types::DRAW_FRAME).await {
+            Draw::Frame(frame) => (frame.width, frame.height),
+            draw => panic!("DRAW_FRAME decoded to {:?}", draw),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clients_get_the_board_and_complete_the_handshake() {
+        let (state, uri) = serve().await;
+        let mut client = connect(&uri).await;
+
+        assert_eq!(receive_frame(&mut client).await, (WIDTH, HEIGHT));
+
+        send(&mut client, ClientMessage::hello()).await;
+        let (accepted, _) = receive(&mut client, message_types::HANDSHAKE_ACCEPTED).await;
+        assert_eq!(accepted[0], PROTOCOL_VERSION);
+        assert_eq!(accepted[2..6], [0, WIDTH as u8, 0, HEIGHT as u8]);
+
+        // The connection outlives a message it doesn't know
+        let unknown = ClientMessage::new(message_types::DRAW_FRAME, Vec::new());
+        send(&mut client, unknown).await;
+        let (error, _) = receive(&mut client, message_types::ERROR).await;
+        assert_eq!(error[..2], error_codes::UNKNOWN_MESSAGE_TYPE.to_be_bytes());
+        send(&mut client, ClientMessage::hello()).await;
+        receive(&mut client, message_types::HANDSHAKE_ACCEPTED).await;
+        assert_room_ticking(&state).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn joining_clients_get_the_pattern_the_room_shows() {
+        let (state, uri) = serve().await;
+        let room = state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        room.set_active_pattern(ActivePattern::Mlp);
+        let painting = FrameDecoder::default()
+            .decode(room.mlp.current_painting_frame().unwrap().as_payload())
+            .unwrap();
+
+        let mut client = connect(&uri).await;
+        let keyframe = receive_draw(&mut client, message_types::DRAW_FRAME).await;
+        assert_eq!(Some(keyframe), painting);
+        assert_room_ticking(&state).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn advancing_a_generation_reaches_everyone_in_the_room() {
+        let (state, uri) = serve().await;
+        let mut sender = connect(&uri).await;
+        let mut watcher = connect(&uri).await;
+        for client in [&mut sender, &mut watcher] {
+            receive_frame(client).await;
+            send(client, ClientMessage::hello()).await;
+            receive(client, message_types::HANDSHAKE_ACCEPTED).await;
+        }
+
+        // A full board dies off nearly everywhere, too much for a region
+        let room = state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        let cells: Vec<(u16, u16)> = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+            .collect();
+        room.gol.awaken_cells(&cells);
+        send(&mut sender, ClientMessage::advance_generation()).await;
+        for client in [&mut sender, &mut watcher] {
+            assert_eq!(receive_frame(client).await, (WIDTH, HEIGHT));
+        }
+
+        // A blinker only changes the cells around it
+        room.gol.kill_all_cells().unwrap();
+        room.gol.awaken_cells(&[(5, 6), (6, 6), (7, 6)]);
+        send(&mut sender, ClientMessage::advance_generation()).await;
+        let Draw::Region { region, .. } =
+            receive_draw(&mut watcher, message_types::DRAW_REGION).await
+        else {
+            panic!("DRAW_REGION decoded to something else");
+        };
+        assert!(region.x + region.width <= WIDTH && region.y + region.height <= HEIGHT);
+        assert!((region.x..region.x + region.width).contains(&6));
+        assert!((region.y..region.y + region.height).contains(&5));
+        assert_room_ticking(&state).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn only_admins_pause_reseed_or_toggle_autoplay() {
+        let (state, uri) = serve_with_admin_token(Some("s3cret")).await;
+        let room = state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        let mut client = connect(&uri).await;
+        receive_frame(&mut client).await;
+
+        for message in [
+            ClientMessage::pause_simulation(),
+            ClientMessage::resume_simulation(),
+            ClientMessage::new(message_types::SET_SEED, 42u64.to_be_bytes().to_vec()),
+            ClientMessage::set_autoplay(false),
+        ] {
+            send(&mut client, message).await;
+            let (error, _) = receive(&mut client, message_types::ERROR).await;
+            assert_eq!(error[..2], error_codes::UNAUTHORIZED.to_be_bytes());
+        }
+        assert!(!room.simulation.is_paused());
+
+        let mut admin = connect(&format!("{}?admin_token=s3cret", uri)).await;
+        receive_frame(&mut admin).await;
+        send(&mut admin, ClientMessage::pause_simulation()).await;
+        receive(&mut admin, message_types::SIMULATION_STATUS).await;
+        assert!(room.simulation.is_paused());
+        // Paused, the loop still checks in
+        assert_room_ticking(&state).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cursors_reach_the_other_clients_at_most_every_interval() {
+        let (state, uri) = serve().await;
+        let mut mover = connect(&uri).await;
+        let mut watcher = connect(&uri).await;
+        for client in [&mut mover, &mut watcher] {
+            receive_frame(client).await;
+        }
+
+        // The second move comes too soon after the first and is dropped
+        send(&mut mover, ClientMessage::move_cursor(3, 4)).await;
+        send(&mut mover, ClientMessage::move_cursor(5, 6)).await;
+        let (first, _) = receive(&mut watcher, message_types::CURSOR_MOVED).await;
+        assert_eq!(first[..4], [0, 3, 0, 4]);
+        tokio::time::sleep(Duration::from_millis(CURSOR_MIN_INTERVAL_MS * 2)).await;
+        send(&mut mover, ClientMessage::move_cursor(7, 8)).await;
+        let (next, _) = receive(&mut watcher, message_types::CURSOR_MOVED).await;
+        assert_eq!(next[..4], [0, 7, 0, 8]);
+        assert_eq!(first[4..], next[4..]);
+
+        // The mover gets none of its own cursors back, its HELLO answer comes first
+        send(&mut mover, ClientMessage::hello()).await;
+        loop {
+            let message = mover.next().await.unwrap().unwrap();
+            if !message.is_binary() {
+                continue;
+            }
+            let (msg_type, _) = FrameDecoder::default()
+                .unwrap_message(message.as_payload())
+                .unwrap();
+            assert_ne!(msg_type, message_types::CURSOR_MOVED);
+            if msg_type == message_types::HANDSHAKE_ACCEPTED {
+                break;
+            }
+        }
+        assert_room_ticking(&state).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn autoplaying_rooms_advance_without_clients_asking() {
+        let (state, uri) = serve().await;
+        let room = state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .unwrap();
+        room.simulation.set_autoplay(true);
+        let mut client = connect(&uri).await;
+        receive_frame(&mut client).await;
+        let generation = room.gol.generation_stats().0;
+
+        // Only the room's own loop steps the board, nothing asks it to
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while room.gol.generation_stats().0 < generation + 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Three generations within 5s");
+        assert_room_ticking(&state).await;
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::{
+    constants::{SNAPSHOT_DIR, SNAPSHOT_MAX_PERIOD},
+    patterns::{
+        census::{detect_period, take_census},
+        gol::cell_grid,
+    },
+    room::{RoomId, RoomState},
+};
+
+/// Properties detected in a saved snapshot, stored next to it as `<snapshot>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotTags {
+    pub file: String,
+    pub room: RoomId,
+    pub taken_at: String,
+    pub generation: u64,
+    pub population: usize,
+    // Smallest period the grid repeats with, None when it doesn't settle that soon
+    pub period: Option<u32>,
+    pub gliders: usize,
+    pub guns: usize,
+    pub tags: Vec<String>,
+}
+
+impl SnapshotTags {
+    fn matches(&self, room: Option<&str>, tag: Option<&str>) -> bool {
+        room.is_none_or(|room| self.room == room)
+            && tag.is_none_or(|tag| self.tags.iter().any(|t| t == tag))
+    }
+}
+
+/// Search terms: "empty", "still-life", "oscillator", "period-N", "gliders", "guns"
+fn derive_tags(population: usize, period: Option<u32>, gliders: usize, guns: usize) -> Vec<String> {
+    let mut tags = Vec::new();
+    match (population, period) {
+        (0, _) => tags.push("empty".to_string()),
+        (_, Some(1)) => tags.push("still-life".to_string()),
+        (_, Some(period)) => {
+            tags.push("oscillator".to_string());
+            tags.push(format!("period-{}", period));
+        }
+        (_, None) => {}
+    }
+    if gliders > 0 {
+        tags.push("gliders".to_string());
+    }
+    if guns > 0 {
+        tags.push("guns".to_string());
+    }
+    tags
+}
+
+/// Writes the room's grid dump to the snapshot directory and tags it
+pub async fn save_snapshot(room: &RoomState, now: &DateTime<Local>) -> Result<SnapshotTags> {
+    tokio::fs::create_dir_all(SNAPSHOT_DIR)
+        .await
+        .with_context(|| format!("Failed to create {}", SNAPSHOT_DIR))?;
+
+    let file = format!("{}-{}.bin", room.id, now.format("%Y%m%d-%H%M"));
+    let game = room.gol.snapshot();
+    let census = take_census(&cell_grid(&*game));
+    let population = game.population();
+    let period = detect_period(&*game, SNAPSHOT_MAX_PERIOD);
+    let tags = SnapshotTags {
+        file: file.clone(),
+        room: room.id.clone(),
+        taken_at: now.to_rfc3339(),
+        generation: game.generation_count(),
+        population,
+        period,
+        gliders: census.gliders,
+        guns: census.guns,
+        tags: derive_tags(population, period, census.gliders, census.guns),
+    };
+
+    let path = Path::new(SNAPSHOT_DIR).join(&file);
+    tokio::fs::write(&path, room.gol.grid_dump())
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    let tags_path = path.with_extension("json");
+    tokio::fs::write(&tags_path, serde_json::to_vec_pretty(&tags)?)
+        .await
+        .with_context(|| format!("Failed to write {}", tags_path.display()))?;
+
+    info!(
+        "Saved snapshot of room {:?} to {} tagged {:?}",
+        room.id,
+        path.display(),
+        tags.tags
+    );
+    Ok(tags)
+}
+
+/// Tags of every saved snapshot, oldest first, optionally narrowed to a room and a tag
+pub async fn list_snapshots(room: Option<&str>, tag: Option<&str>) -> Result<Vec<SnapshotTags>> {
+    let mut entries = match tokio::fs::read_dir(SNAPSHOT_DIR).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {}", SNAPSHOT_DIR)),
+    };
+
+    let mut snapshots = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let tags: SnapshotTags = match tokio::fs::read(&path)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(serde_json::from_slice(&data)?))
+        {
+            Ok(tags) => tags,
+            Err(e) => {
+                warn!(
+                    "Skipping unreadable snapshot tags {}: {}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        if tags.matches(room, tag) {
+            snapshots.push(tags);
+        }
+    }
+
+    snapshots.sort_by(|a, b| a.taken_at.cmp(&b.taken_at));
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_from_detected_properties() {
+        assert_eq!(derive_tags(0, Some(1), 0, 0), vec!["empty"]);
+        assert_eq!(derive_tags(12, Some(1), 0, 0), vec!["still-life"]);
+        assert_eq!(
+            derive_tags(48, Some(3), 0, 0),
+            vec!["oscillator", "period-3"]
+        );
+        assert_eq!(derive_tags(80, None, 4, 1), vec!["gliders", "guns"]);
+    }
+}
@@ -1,21 +1,46 @@
 use axum_tws::WebSocket;
 use futures::StreamExt;
 use std::sync::Arc;
-use tracing::{Span, debug, error, info, instrument};
-use uuid::Uuid;
+use tracing::{debug, error, info, instrument};
 
-use crate::{message::SocketHandler, state::AppState};
+use crate::{message::SocketHandler, room::RoomState, state::AppState};
 
-#[instrument(skip(socket, state), fields(connection_id = %Uuid::new_v4()))]
-pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
-    let connection_id = Span::current().field("connection_id").unwrap();
+#[instrument(skip(socket, room, state), fields(room = %room.id))]
+pub async fn handle_socket(
+    socket: WebSocket,
+    room: Arc<RoomState>,
+    connection_id: String,
+    state: &AppState,
+    admin: bool,
+) {
     info!("New WebSocket connection established");
 
     let (mut sink, stream) = socket.split();
-    let handler = SocketHandler::new(state, connection_id.to_string());
+    let handler = SocketHandler::new(room, connection_id, state, admin);
 
-    // Send stored messages first
-    match handler.send_current_generation(&mut sink).await {
+    // Send capabilities, the client's id, prediction params, the join summary, the team and
+    // stored messages first
+    if let Err(e) = handler.send_capabilities(&mut sink).await {
+        error!("Failed to send capabilities to new connection: {}", e);
+        return;
+    }
+    if let Err(e) = handler.send_identity(&mut sink).await {
+        error!("Failed to send identity to new connection: {}", e);
+        return;
+    }
+    if let Err(e) = handler.send_prediction_params(&mut sink).await {
+        error!("Failed to send prediction params to new connection: {}", e);
+        return;
+    }
+    if let Err(e) = handler.send_join_summary(&mut sink).await {
+        error!("Failed to send join summary to new connection: {}", e);
+        return;
+    }
+    if let Err(e) = handler.send_team_assignment(&mut sink).await {
+        error!("Failed to send team assignment to new connection: {}", e);
+        return;
+    }
+    match handler.send_keyframe(&mut sink).await {
         Ok(_) => {
             debug!("Successfully sent stored messages to new connection");
             // Run the main handler
@@ -0,0 +1,251 @@
+//! Background search for interesting random soups.
+//!
+//! A room's search runs batches of random `SOUP_SIZE` squares, each in the middle of an
+//! empty field with dead edges, on a thread pool that leaves a core to the simulation
+//! loops. Soups that take long to settle (methuselahs) or send off a spaceship other than
+//! a glider are broadcast to the room with their RLE, so clients can load them.
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Weak};
+use std::thread;
+use tracing::{debug, error, info};
+
+use crate::{
+    constants::{
+        SOUP_BATCH_SIZE, SOUP_DENSITY, SOUP_FIELD_SIZE, SOUP_MAX_GENERATIONS,
+        SOUP_MAX_SPACESHIP_PERIOD, SOUP_METHUSELAH_LIFESPAN, SOUP_SIZE, SOUP_SPACESHIP_CHECK_EVERY,
+    },
+    patterns::{census::find_spaceship, gol::StabilityDetector, rle::RlePattern},
+    protocol::generation_hash,
+    room::{BroadcastMessage, RoomState},
+    utils::create_soup_found_message,
+};
+use game_of_life_core::GameOfLifeBits;
+
+// Shared by every room's search, one thread short of the cores so soups don't starve
+// the simulation loops and connections
+static SOUP_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(cores.saturating_sub(1).max(1))
+        .thread_name(|i| format!("soup-search-{}", i))
+        .build()
+        .expect("soup search pool should start")
+});
+
+/// What made a soup interesting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoupFind {
+    /// Generations it took to settle, `SOUP_MAX_GENERATIONS` if it never did
+    Methuselah { lifespan: u64 },
+    /// Sent off an object other than a glider, which travels with this period
+    Spaceship { period: u32 },
+}
+
+impl SoupFind {
+    pub fn id(&self) -> u8 {
+        match self {
+            SoupFind::Methuselah { .. } => 0,
+            SoupFind::Spaceship { .. } => 1,
+        }
+    }
+}
+
+/// An interesting soup and the seed that reproduces it
+#[derive(Debug, Clone)]
+pub struct SoupReport {
+    pub find: SoupFind,
+    pub seed: RlePattern,
+}
+
+/// Whether a room's soup search is running
+#[derive(Debug, Default)]
+pub struct SoupSearch {
+    running: AtomicBool,
+    // Bumped by every start; a search thread exits once its epoch is stale, so one stopped
+    // and started again before finishing its batch doesn't keep searching alongside
+    epoch: AtomicU64,
+}
+
+impl SoupSearch {
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+
+    // Whether the search thread started with `epoch` should keep going
+    fn is_current(&self, epoch: u64) -> bool {
+        self.is_running() && self.epoch() == epoch
+    }
+
+    /// Asks the search to stop once its current batch is done. Returns whether it was
+    /// running.
+    pub fn stop(&self) -> bool {
+        self.running.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Searches soups for the room until it's stopped or the room is gone. Returns false if
+/// the room's search was already running.
+pub fn start_soup_search(room: &Arc<RoomState>) -> bool {
+    if room.soup_search.running.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+
+    let epoch = room.soup_search.epoch.fetch_add(1, Ordering::Relaxed) + 1;
+    let weak_room = Arc::downgrade(room);
+    let spawned = thread::Builder::new()
+        .name(format!("soup-search-{}", room.id))
+        .spawn(move || run_search(weak_room, epoch));
+    if let Err(e) = spawned {
+        error!("Failed to start soup search in room {:?}: {}", room.id, e);
+        room.soup_search.stop();
+        return false;
+    }
+    info!("Soup search started in room {:?}", room.id);
+    true
+}
+
+fn run_search(room: Weak<RoomState>, epoch: u64) {
+    let mut searched = 0u64;
+    loop {
+        let Some(room) = room.upgrade() else {
+            debug!("Room gone, soup search stopped after {} soups", searched);
+            return;
+        };
+        if !room.soup_search.is_current(epoch) {
+            info!(
+                "Soup search in room {:?} stopped after {} soups",
+                room.id, searched
+            );
+            return;
+        }
+
+        let seeds: Vec<u64> = (0..SOUP_BATCH_SIZE).map(|_| rand::random()).collect();
+        let reports: Vec<SoupReport> = SOUP_POOL.install(|| {
+            seeds
+                .into_par_iter()
+                .filter_map(|seed| search_soup(&mut StdRng::seed_from_u64(seed)))
+                .collect()
+        });
+        searched += SOUP_BATCH_SIZE as u64;
+        // Stopped during the batch, its finds would come after the search was stopped
+        if !room.soup_search.is_current(epoch) {
+            continue;
+        }
+
+        for report in reports {
+            info!(
+                "Soup search in room {:?} found {:?} after {} soups",
+                room.id, report.find, searched
+            );
+            let _ = room
+                .channel
+                .send(BroadcastMessage::system(create_soup_found_message(&report)));
+        }
+    }
+}
+
+/// Runs one random soup, reporting it if it lived long or sent off a spaceship
+pub fn search_soup(rng: &mut impl Rng) -> Option<SoupReport> {
+    let cells = (0..SOUP_SIZE)
+        .flat_map(|y| (0..SOUP_SIZE).map(move |x| (x, y)))
+        .filter(|_| rng.random_bool(SOUP_DENSITY))
+        .collect();
+    let seed = RlePattern {
+        width: SOUP_SIZE,
+        height: SOUP_SIZE,
+        cells,
+    };
+    run_soup(&seed).map(|find| SoupReport { find, seed })
+}
+
+// Steps the seed in the middle of the field until it settles
+fn run_soup(seed: &RlePattern) -> Option<SoupFind> {
+    let side = SOUP_FIELD_SIZE as usize;
+    let (offset_x, offset_y) = ((side - seed.width) / 2, (side - seed.height) / 2);
+    let mut bits = vec![0u8; (side * side).div_ceil(8)];
+    for &(x, y) in &seed.cells {
+        let i = (y + offset_y) * side + x + offset_x;
+        bits[i / 8] |= 0x80 >> (i % 8);
+    }
+    let mut field = GameOfLifeBits::empty(SOUP_FIELD_SIZE, SOUP_FIELD_SIZE);
+    field.load_packed_bits(&bits);
+
+    let mut stability = StabilityDetector::default();
+    for generation in 1..=SOUP_MAX_GENERATIONS {
+        field.step();
+        let bits = field.to_packed_bits();
+        if generation.is_multiple_of(SOUP_SPACESHIP_CHECK_EVERY)
+            && let Some(period) = find_spaceship(&unpack(&bits, side), SOUP_MAX_SPACESHIP_PERIOD)
+        {
+            return Some(SoupFind::Spaceship { period });
+        }
+        if let Some(period) = stability.observe(generation_hash(&bits)) {
+            // The cycle has to come around twice before it's recognized
+            let lifespan = generation.saturating_sub(2 * period as u64);
+            return (lifespan >= SOUP_METHUSELAH_LIFESPAN)
+                .then_some(SoupFind::Methuselah { lifespan });
+        }
+    }
+    Some(SoupFind::Methuselah {
+        lifespan: SOUP_MAX_GENERATIONS,
+    })
+}
+
+fn unpack(bits: &[u8], side: usize) -> Vec<Vec<bool>> {
+    (0..side)
+        .map(|y| {
+            (0..side)
+                .map(|x| {
+                    let i = y * side + x;
+                    bits[i / 8] & (0x80 >> (i % 8)) != 0
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::library::LibraryPattern;
+
+    #[test]
+    fn soups_report_spaceships_but_not_debris() {
+        let lwss = run_soup(&LibraryPattern::Lwss.pattern());
+        assert_eq!(lwss, Some(SoupFind::Spaceship { period: 4 }));
+
+        let blinker = RlePattern {
+            width: 3,
+            height: 1,
+            cells: vec![(0, 0), (1, 0), (2, 0)],
+        };
+        assert_eq!(run_soup(&blinker), None);
+        // A lone glider crashes into the edge and settles
+        assert_eq!(run_soup(&LibraryPattern::Glider.pattern()), None);
+    }
+
+    #[test]
+    fn restarting_a_search_retires_the_previous_thread() {
+        let room = RoomState::for_test();
+        assert!(start_soup_search(&room));
+        assert!(!start_soup_search(&room));
+        let first = room.soup_search.epoch();
+
+        assert!(room.soup_search.stop());
+        assert!(start_soup_search(&room));
+        assert!(room.soup_search.is_running());
+        assert_ne!(room.soup_search.epoch(), first);
+        // The first thread, if still in its batch, exits instead of searching alongside
+        run_search(Arc::downgrade(&room), first);
+        assert!(room.soup_search.is_running());
+
+        room.soup_search.stop();
+    }
+}
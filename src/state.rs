@@ -1,18 +1,287 @@
-use axum_tws::Message;
-use tokio::sync::broadcast;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
 use tracing::info;
 
-#[derive(Debug)]
+use crate::{
+    admin::AdminAccess,
+    constants::{MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS, message_types, topics},
+    lobby::RoomRegistry,
+    presence::ConnectionRegistry,
+    room::{DEFAULT_ROOM, JoinCredentials, RoomError, RoomId, RoomSettings, RoomState},
+    scheduler::Scheduler,
+    stats::{LiveStats, ServerStats},
+};
+
 pub struct AppState {
-    pub channel: broadcast::Sender<Message>,
+    pub rooms: Arc<RoomRegistry>,
+    pub live_stats: RwLock<HashMap<RoomId, LiveStats>>,
+    pub server_stats: Arc<ServerStats>,
+    // Open connections of every room, keyed by connection id
+    pub connections: Arc<ConnectionRegistry>,
+    pub admin: Arc<AdminAccess>,
+    // Cron-like entries from the schedule file and admins
+    pub scheduler: Arc<Scheduler>,
+    // Cancelled when the server shuts down, stops the simulation loops
+    pub shutdown: CancellationToken,
+    // A permit per open connection, upgrades beyond `max_connections` are refused
+    connection_slots: Arc<Semaphore>,
+    max_connections: usize,
 }
 
 impl AppState {
-    pub fn new(channel_cap: usize) -> AppState {
-        let channel = broadcast::Sender::<Message>::new(channel_cap);
+    pub fn new(
+        room_settings: RoomSettings,
+        admin_token: Option<String>,
+        max_connections: usize,
+    ) -> AppState {
+        info!("Created AppState with room settings: {:?}", room_settings);
+
+        let shutdown = CancellationToken::new();
+        let state = AppState {
+            rooms: Arc::new(RoomRegistry::new(room_settings, shutdown.clone())),
+            live_stats: RwLock::new(HashMap::new()),
+            server_stats: Arc::default(),
+            connections: Arc::default(),
+            admin: Arc::new(AdminAccess::new(admin_token)),
+            scheduler: Arc::default(),
+            shutdown,
+            connection_slots: Arc::new(Semaphore::new(max_connections)),
+            max_connections,
+        };
+        // The default room always exists so /ws keeps working without a room id
+        state
+            .join_room(DEFAULT_ROOM, &JoinCredentials::default())
+            .expect("default room id is valid");
+        state
+    }
 
-        info!("Created AppState with channel capacity: {}", channel_cap);
+    /// Returns the room with the given id, creating it on first join, see `RoomRegistry::join`
+    pub fn join_room(
+        &self,
+        id: &str,
+        credentials: &JoinCredentials,
+    ) -> Result<Arc<RoomState>, RoomError> {
+        self.rooms.join(id, credentials)
+    }
+
+    /// Takes a connection slot, held until the returned permit is dropped
+    pub fn reserve_connection(&self) -> Result<OwnedSemaphorePermit, RoomError> {
+        self.connection_slots
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| RoomError::TooManyConnections(self.max_connections))
+    }
+
+    /// Connections that may still open before upgrades are refused
+    pub fn free_connection_slots(&self) -> usize {
+        self.connection_slots.available_permits()
+    }
+
+    pub fn room(&self, id: &str) -> Option<Arc<RoomState>> {
+        self.rooms.get(id)
+    }
+
+    pub fn rooms(&self) -> Vec<Arc<RoomState>> {
+        self.rooms.all()
+    }
+}
+
+/// Pattern subsystem a room's canvas shows, see `ModeManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivePattern {
+    #[default]
+    Gol,
+    Mlp,
+    #[serde(rename = "brians_brain")]
+    BriansBrain,
+    Sand,
+    Reaction,
+    Boids,
+    Pong,
+    Snake,
+    Immigration,
+}
+
+impl ActivePattern {
+    pub const ALL: [ActivePattern; 9] = [
+        ActivePattern::Gol,
+        ActivePattern::Mlp,
+        ActivePattern::BriansBrain,
+        ActivePattern::Sand,
+        ActivePattern::Reaction,
+        ActivePattern::Boids,
+        ActivePattern::Pong,
+        ActivePattern::Snake,
+        ActivePattern::Immigration,
+    ];
+
+    /// Id the pattern has on the wire, its position in `ALL`
+    pub fn id(&self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|pattern| pattern == self)
+            .unwrap_or(0) as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Name the pattern is serialized with
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivePattern::Gol => "gol",
+            ActivePattern::Mlp => "mlp",
+            ActivePattern::BriansBrain => "brians_brain",
+            ActivePattern::Sand => "sand",
+            ActivePattern::Reaction => "reaction",
+            ActivePattern::Boids => "boids",
+            ActivePattern::Pong => "pong",
+            ActivePattern::Snake => "snake",
+            ActivePattern::Immigration => "immigration",
+        }
+    }
+
+    /// Topic the pattern's frames are broadcast on
+    pub fn frame_topic(&self) -> u8 {
+        match self {
+            ActivePattern::Gol => topics::GOL_FRAMES,
+            ActivePattern::Mlp => topics::MLP_FRAMES,
+            ActivePattern::BriansBrain => topics::BRAIN_FRAMES,
+            ActivePattern::Sand => topics::SAND_FRAMES,
+            ActivePattern::Reaction => topics::REACTION_FRAMES,
+            ActivePattern::Boids => topics::BOID_FRAMES,
+            // Pong is drawn with pixel events, its keyframes travel with them
+            ActivePattern::Pong | ActivePattern::Snake => topics::PIXEL_EVENTS,
+            // A Game of Life variant, clients following GOL frames get it too
+            ActivePattern::Immigration => topics::GOL_FRAMES,
+        }
+    }
+
+    /// Pattern whose canvas a message type draws, if any
+    pub fn for_message_type(msg_type: u8) -> Option<ActivePattern> {
+        match msg_type {
+            message_types::CREATE_NEW_GOL_GENERATION
+            | message_types::AWAKEN_RANDOM_GOL_CELL
+            | message_types::KILL_RANDOM_GOL_CELL
+            | message_types::ADVANCE_GOL_GENERATION
+            | message_types::KILL_ALL_GOL_CELLS
+            | message_types::SEED_GOL_FROM_MLP_PAINTING
+            | message_types::LOAD_GOL_PATTERN
+            | message_types::STAMP_GOL_PATTERN
+            | message_types::AWAKEN_CELLS_BATCH
+            | message_types::BRUSH_STROKE
+            | message_types::SET_COLOR_SCHEME
+            | message_types::SET_GOL_HYBRID
+            | message_types::SEED_GOL_FROM_IMAGE
+            | message_types::TOGGLE_CELL
+            | message_types::FILL_RECT
+            | message_types::CLEAR_RECT
+            | message_types::UNDO
+            | message_types::REDO
+            | message_types::REQUEST_RANDOM_COLORED_PIXEL => Some(ActivePattern::Gol),
+            message_types::CREATE_NEW_MLP_PAINTING
+            | message_types::ADVANCE_MLP_PAINTING
+            | message_types::PAINT_MLP_FROM_GOL_GENERATION
+            | message_types::UPLOAD_MLP_IMAGE
+            | message_types::ADD_MLP_PAINTING
+            | message_types::SELECT_MLP_PAINTING
+            | message_types::DELETE_MLP_PAINTING
+            | message_types::ADVANCE_MLP_PAINTING_TO
+            | message_types::SET_MLP_STYLE => Some(ActivePattern::Mlp),
+            message_types::CREATE_NEW_BRAIN_GENERATION
+            | message_types::ADVANCE_BRAIN_GENERATION => Some(ActivePattern::BriansBrain),
+            message_types::SPAWN_MATERIAL => Some(ActivePattern::Sand),
+            message_types::CREATE_NEW_REACTION
+            | message_types::ADVANCE_REACTION
+            | message_types::SET_REACTION_RATES => Some(ActivePattern::Reaction),
+            message_types::SPAWN_BOIDS
+            | message_types::SET_BOID_WEIGHTS
+            | message_types::RESET_BOIDS => Some(ActivePattern::Boids),
+            message_types::CLAIM_PADDLE | message_types::MOVE_PADDLE => Some(ActivePattern::Pong),
+            message_types::JOIN_SNAKE | message_types::TURN_SNAKE => Some(ActivePattern::Snake),
+            message_types::CREATE_NEW_IMMIGRATION_GENERATION
+            | message_types::ADVANCE_IMMIGRATION_GENERATION
+            | message_types::AWAKEN_TEAM_CELL => Some(ActivePattern::Immigration),
+            _ => None,
+        }
+    }
+}
+
+/// Tick rate, autoplay and pause flags shared between message handlers and the broadcaster
+#[derive(Debug)]
+pub struct SimulationControl {
+    tick_interval_ms: AtomicU64,
+    // Minimum interval imposed by the compute budget, 0 when unthrottled
+    throttled_interval_ms: AtomicU64,
+    // Whether the broadcaster advances generations on its own, otherwise only clients do
+    autoplay: AtomicBool,
+    // Whether autoplay starts a fresh random generation once the board is stable
+    reseed_when_stable: bool,
+    paused: AtomicBool,
+}
+
+impl SimulationControl {
+    pub fn new(tick_interval_ms: u64, autoplay: bool, reseed_when_stable: bool) -> Self {
+        Self {
+            tick_interval_ms: AtomicU64::new(tick_interval_ms),
+            throttled_interval_ms: AtomicU64::new(0),
+            autoplay: AtomicBool::new(autoplay),
+            reseed_when_stable,
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    pub fn tick_interval_ms(&self) -> u64 {
+        self.tick_interval_ms.load(Ordering::Relaxed)
+    }
+
+    /// Sets the tick interval, clamped to the supported range. Returns the applied value.
+    pub fn set_tick_interval_ms(&self, interval_ms: u64) -> u64 {
+        let interval_ms = interval_ms.clamp(MIN_TICK_INTERVAL_MS, MAX_TICK_INTERVAL_MS);
+        self.tick_interval_ms.store(interval_ms, Ordering::Relaxed);
+        info!("Simulation tick interval set to {}ms", interval_ms);
+        interval_ms
+    }
+
+    /// Interval the simulation actually ticks at: the requested one, stretched when
+    /// the room is throttled
+    pub fn applied_tick_interval_ms(&self) -> u64 {
+        self.tick_interval_ms()
+            .max(self.throttled_interval_ms.load(Ordering::Relaxed))
+    }
+
+    /// Sets the budget's minimum tick interval, 0 lifts the throttle. Returns whether it changed.
+    pub fn set_throttled_interval_ms(&self, interval_ms: u64) -> bool {
+        self.throttled_interval_ms
+            .swap(interval_ms, Ordering::Relaxed)
+            != interval_ms
+    }
+
+    pub fn is_autoplaying(&self) -> bool {
+        self.autoplay.load(Ordering::Relaxed)
+    }
+
+    pub fn set_autoplay(&self, autoplay: bool) {
+        self.autoplay.store(autoplay, Ordering::Relaxed);
+        info!("Autoplay {}", if autoplay { "on" } else { "off" });
+    }
+
+    pub fn reseeds_when_stable(&self) -> bool {
+        self.reseed_when_stable
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
 
-        AppState { channel }
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        info!("Simulation {}", if paused { "paused" } else { "resumed" });
     }
 }
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::{
+    constants::{EMPTY_ROOM_EVICT_MS, STATS_REFRESH_INTERVAL_MS},
+    room::{BroadcastMessage, RoomId, RoomState},
+    state::{ActivePattern, AppState},
+    utils::create_occupancy_message,
+};
+
+/// Read-only snapshot served by the stats endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LiveStats {
+    pub room: RoomId,
+    pub population: usize,
+    pub generation: u64,
+    pub viewers: usize,
+    pub editors: usize,
+    pub tick_interval_ms: u64,
+    pub applied_tick_interval_ms: u64,
+    pub paused: bool,
+    pub deadline_misses: u64,
+    pub degraded: bool,
+    pub active_pattern: ActivePattern,
+    pub seed: u64,
+    pub updated_at: String,
+}
+
+impl LiveStats {
+    pub fn collect(room: &RoomState) -> Self {
+        let (generation, population) = room.gol.generation_stats();
+
+        Self {
+            room: room.id.clone(),
+            population,
+            generation,
+            viewers: room.channel.receiver_count(),
+            editors: room.occupancy.editor_count(),
+            tick_interval_ms: room.simulation.tick_interval_ms(),
+            applied_tick_interval_ms: room.simulation.applied_tick_interval_ms(),
+            paused: room.simulation.is_paused(),
+            deadline_misses: room.health.deadline_misses(),
+            degraded: room.health.is_degraded(),
+            active_pattern: room.active_pattern(),
+            seed: room.seed(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Counters of the whole server, reported to clients that send GET_SERVER_STATS
+#[derive(Debug)]
+pub struct ServerStats {
+    started: Instant,
+    // Times a connection fell a whole channel behind its room
+    lag_events: AtomicU64,
+    // Room messages those connections skipped
+    lagged_messages: AtomicU64,
+    // WebSocket messages sent to clients from their room channels
+    frames_sent: AtomicU64,
+    // Population of every public room as of the last stats refresh, by room id
+    populations: RwLock<Vec<(RoomId, usize)>>,
+}
+
+/// What GET_SERVER_STATS reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerStatsReport {
+    pub connections: usize,
+    pub uptime: Duration,
+    pub lag_events: u64,
+    pub lagged_messages: u64,
+    pub frames_sent: u64,
+    pub populations: Vec<(RoomId, usize)>,
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            lag_events: AtomicU64::new(0),
+            lagged_messages: AtomicU64::new(0),
+            frames_sent: AtomicU64::new(0),
+            populations: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl ServerStats {
+    pub fn record_lag(&self, skipped: u64) {
+        self.lag_events.fetch_add(1, Ordering::Relaxed);
+        self.lagged_messages.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn record_frame_sent(&self) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Replaces the room populations, private rooms are left out so their ids stay unknown
+    pub fn set_populations(&self, stats: &HashMap<RoomId, LiveStats>, private: &[RoomId]) {
+        let mut populations: Vec<(RoomId, usize)> = stats
+            .values()
+            .filter(|stats| !private.contains(&stats.room))
+            .map(|stats| (stats.room.clone(), stats.population))
+            .collect();
+        populations.sort();
+        *self.populations.write().unwrap_or_else(|e| e.into_inner()) = populations;
+    }
+
+    pub fn report(&self, connections: usize) -> ServerStatsReport {
+        ServerStatsReport {
+            connections,
+            uptime: self.started.elapsed(),
+            lag_events: self.lag_events.load(Ordering::Relaxed),
+            lagged_messages: self.lagged_messages.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            populations: self
+                .populations
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
+
+/// Periodically refreshes the cached stats so polling clients never touch the engine locks,
+/// and pushes occupancy to a room's clients whenever its viewer or editor count changes
+pub fn spawn_stats_refresher(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        info!("Starting live stats refresher");
+        let mut interval = tokio::time::interval(Duration::from_millis(STATS_REFRESH_INTERVAL_MS));
+
+        loop {
+            interval.tick().await;
+            // Before collecting, the stats would hold on to the rooms otherwise
+            state
+                .rooms
+                .evict_empty(Duration::from_millis(EMPTY_ROOM_EVICT_MS));
+            let rooms = state.rooms();
+            let stats: HashMap<RoomId, LiveStats> = rooms
+                .iter()
+                .map(|room| (room.id.clone(), LiveStats::collect(room)))
+                .collect();
+
+            {
+                let previous = state.live_stats.read().unwrap_or_else(|e| e.into_inner());
+                for room in &rooms {
+                    let current = &stats[&room.id];
+                    let changed = previous.get(&room.id).is_none_or(|last| {
+                        (last.viewers, last.editors) != (current.viewers, current.editors)
+                    });
+                    if changed && current.viewers > 0 {
+                        let message = create_occupancy_message(current.viewers, current.editors);
+                        // Viewers may have left since, nothing to do then
+                        let _ = room.channel.send(BroadcastMessage::system(message));
+                    }
+                }
+            }
+
+            let private: Vec<RoomId> = rooms
+                .iter()
+                .filter(|room| room.access.is_private())
+                .map(|room| room.id.clone())
+                .collect();
+            state.server_stats.set_populations(&stats, &private);
+
+            debug!("Refreshed live stats for {} rooms", stats.len());
+            *state.live_stats.write().unwrap_or_else(|e| e.into_inner()) = stats;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_server_stats_message;
+
+    #[test]
+    fn server_stats_leave_private_rooms_out() {
+        let server_stats = ServerStats::default();
+        server_stats.record_lag(6);
+        server_stats.record_frame_sent();
+        server_stats.record_frame_sent();
+        let stats: HashMap<RoomId, LiveStats> = [("lobby", 120), ("secret", 7), ("arena", 0)]
+            .into_iter()
+            .map(|(room, population)| {
+                let stats = LiveStats {
+                    room: room.to_string(),
+                    population,
+                    ..LiveStats::default()
+                };
+                (room.to_string(), stats)
+            })
+            .collect();
+        server_stats.set_populations(&stats, &["secret".to_string()]);
+
+        let report = server_stats.report(3);
+        assert_eq!(
+            (
+                report.lag_events,
+                report.lagged_messages,
+                report.frames_sent
+            ),
+            (1, 6, 2)
+        );
+        assert_eq!(
+            report.populations,
+            [("arena".to_string(), 0), ("lobby".to_string(), 120)]
+        );
+
+        let message = create_server_stats_message(&report);
+        let payload = &message.as_payload()[7..];
+        assert_eq!(payload[..4], 3u32.to_be_bytes());
+        assert_eq!(payload[36..38], 2u16.to_be_bytes());
+        assert_eq!(payload[38..44], *b"\x05arena");
+        assert_eq!(payload[48..54], *b"\x05lobby");
+        assert_eq!(payload[54..], 120u32.to_be_bytes());
+    }
+}
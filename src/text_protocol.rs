@@ -0,0 +1,149 @@
+use anyhow::{Context, Result, bail};
+use axum_tws::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::message_types,
+    protocol::{FLAG_SEQUENCED, PROTOCOL_VERSION, SEQUENCE_PREFIX_LENGTH, WsMessage},
+};
+
+/// Message type in a JSON envelope, the constant name from `message_types` or its number
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonType {
+    Name(String),
+    Number(u8),
+}
+
+/// Payload in a JSON envelope, raw bytes or text sent as its UTF-8 bytes
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonPayload {
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+/// A protocol message sent as a text frame, for clients that can't build binary frames:
+/// `{"type": "ADVANCE_GOL_GENERATION"}` or `{"type": "CHAT", "payload": "hi"}`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonRequest {
+    #[serde(rename = "type")]
+    msg_type: JsonType,
+    payload: Option<JsonPayload>,
+}
+
+/// A server message as it's sent to a connection that speaks JSON. Numbered messages carry
+/// their topic and sequence outside the payload.
+#[derive(Debug, Serialize)]
+struct JsonResponse<'a> {
+    #[serde(rename = "type")]
+    msg_type: JsonType,
+    flags: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topic: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence: Option<u32>,
+    payload: &'a [u8],
+}
+
+/// Translates a JSON text frame into the protocol message it stands for
+pub fn decode_json_message(text: &str) -> Result<WsMessage> {
+    let request: JsonRequest = serde_json::from_str(text).context("Invalid JSON message")?;
+    let msg_type = match request.msg_type {
+        JsonType::Name(name) => match message_types::from_name(&name) {
+            Some(msg_type) => msg_type,
+            None => bail!("Unknown message type {:?}", name),
+        },
+        JsonType::Number(msg_type) => msg_type,
+    };
+    let payload = match request.payload {
+        Some(JsonPayload::Bytes(bytes)) => bytes,
+        Some(JsonPayload::Text(text)) => text.into_bytes(),
+        None => Vec::new(),
+    };
+
+    Ok(WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type,
+        flags: 0,
+        payload,
+    })
+}
+
+/// Translates an encoded server message into a JSON text frame. Anything that isn't a
+/// protocol message is returned as is.
+pub fn encode_json_message(message: &Message) -> Message {
+    if !message.is_binary() {
+        return message.clone();
+    }
+    let Ok(msg) = WsMessage::decode(message.as_payload()) else {
+        return message.clone();
+    };
+
+    let (prefix, payload) = if msg.flags & FLAG_SEQUENCED != 0 {
+        msg.payload
+            .split_at(SEQUENCE_PREFIX_LENGTH.min(msg.payload.len()))
+    } else {
+        (&[][..], &msg.payload[..])
+    };
+    let (topic, sequence) = match prefix {
+        [topic, a, b, c, d] => (Some(*topic), Some(u32::from_be_bytes([*a, *b, *c, *d]))),
+        _ => (None, None),
+    };
+    let response = JsonResponse {
+        msg_type: match message_types::name(msg.msg_type) {
+            Some(name) => JsonType::Name(name.to_string()),
+            None => JsonType::Number(msg.msg_type),
+        },
+        flags: msg.flags & !FLAG_SEQUENCED,
+        topic,
+        sequence,
+        payload,
+    };
+    match serde_json::to_string(&response) {
+        Ok(text) => Message::text(text),
+        Err(_) => message.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{encode_ws_message, sequence_ws_message};
+
+    #[test]
+    fn json_messages_translate_both_ways() {
+        let msg = decode_json_message(r#"{"type": "CHAT", "payload": "hi"}"#).unwrap();
+        assert_eq!(msg.msg_type, message_types::CHAT);
+        assert_eq!(msg.payload, b"hi");
+        let msg = decode_json_message(r#"{"type": 40, "payload": [0, 1]}"#).unwrap();
+        assert_eq!(msg.msg_type, message_types::CREATE_NEW_GOL_GENERATION);
+        assert_eq!(msg.payload, [0, 1]);
+        let msg = decode_json_message(r#"{"type": "ADVANCE_GOL_GENERATION"}"#).unwrap();
+        assert!(msg.payload.is_empty());
+        assert!(decode_json_message(r#"{"type": "NOT_A_TYPE"}"#).is_err());
+        assert!(decode_json_message(r#"{"type": "CHAT", "extra": 1}"#).is_err());
+        assert!(decode_json_message("not json").is_err());
+
+        let frame = encode_ws_message(&WsMessage {
+            version: PROTOCOL_VERSION,
+            msg_type: message_types::CHAT_MESSAGE,
+            flags: 1,
+            payload: vec![7, 8],
+        });
+        let sequenced = sequence_ws_message(&frame, 2, 9);
+        let json: serde_json::Value =
+            serde_json::from_slice(&encode_json_message(&sequenced).as_payload()[..]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "CHAT_MESSAGE",
+                "flags": 1,
+                "topic": 2,
+                "sequence": 9,
+                "payload": [7, 8],
+            })
+        );
+    }
+}
@@ -1,57 +1,179 @@
 use axum_tws::Message;
+use game_of_life_core::Region;
 use tracing::debug;
 
 use crate::{
-    constants::{CANVAS_HEIGHT, CANVAS_WIDTH, PIXEL_PAYLOAD_SIZE, message_types},
-    protocol::{PROTOCOL_VERSION, WsMessage, encode_ws_message},
+    bots::BotKind,
+    constants::{
+        EDGE_MODE_DEAD, GOL_RULE, PIXEL_PAYLOAD_SIZE, TILE_SIDE, error_codes, message_types,
+    },
+    lobby::RoomSummary,
+    patterns::gol::JoinSummary,
+    presence::Presence,
+    protocol::{CellPayload, HASH_FNV1A_32, PROTOCOL_VERSION, WsMessage, encode_ws_message},
+    registry::MESSAGE_RANGES,
+    scheduler::ScheduleEntry,
+    soup::{SoupFind, SoupReport},
+    state::{ActivePattern, SimulationControl},
+    stats::ServerStatsReport,
+    viewport::Tile,
 };
 
-/// creates a random rgb value
-pub fn create_random_rgb() -> [u8; 3] {
-    let r = rand::random_range(0..255);
-    let g = rand::random_range(0..255);
-    let b = rand::random_range(0..255);
+/// Why a frame or pixel couldn't be encoded from what a pattern drew
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    #[error("Frame of {width}x{height} pixels needs {expected} RGB bytes, got {got}")]
+    SizeMismatch {
+        width: u16,
+        height: u16,
+        got: usize,
+        expected: usize,
+    },
+    #[error("Pixel payload of {0} bytes, expected {PIXEL_PAYLOAD_SIZE}")]
+    PixelSize(usize),
+}
+
+pub fn create_pixel_message(x: u16, y: u16, r: u8, g: u8, b: u8) -> Result<Message, FrameError> {
+    let payload = CellPayload {
+        x,
+        y,
+        rgb: Some([r, g, b]),
+    }
+    .encode();
+    if payload.len() != PIXEL_PAYLOAD_SIZE {
+        return Err(FrameError::PixelSize(payload.len()));
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::DRAW_PIXEL,
+        flags: 0,
+        payload,
+    };
+    Ok(encode_ws_message(&msg))
+}
+
+pub fn create_region_message(region: Region, rgb_data: Vec<u8>) -> Message {
+    debug_assert_eq!(rgb_data.len(), region.area() * 3);
+
+    // Region payload format:
+    // - 2 bytes: x of the region's top left cell (big-endian)
+    // - 2 bytes: y of the region's top left cell (big-endian)
+    // - 2 bytes: region width (big-endian)
+    // - 2 bytes: region height (big-endian)
+    // - N bytes: RGB pixel data of the region, row by row (width * height * 3 bytes)
+    let mut payload = Vec::with_capacity(8 + rgb_data.len());
+    payload.extend_from_slice(&region.x.to_be_bytes());
+    payload.extend_from_slice(&region.y.to_be_bytes());
+    payload.extend_from_slice(&region.width.to_be_bytes());
+    payload.extend_from_slice(&region.height.to_be_bytes());
+    payload.extend(rgb_data);
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::DRAW_REGION,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_tiles_message(tiles: &[Tile]) -> Message {
+    // Tiles payload format:
+    // - 2 bytes: number of tiles (big-endian)
+    // - per tile:
+    //   - 2 bytes: tile column, 2 bytes: tile row (big-endian), counted in tiles from the
+    //     board's top left at the viewport's zoom
+    //   - N bytes: RGB pixel data, row by row (TILE_SIDE * TILE_SIDE * 3 bytes)
+    let tile_bytes = TILE_SIDE as usize * TILE_SIDE as usize * 3;
+    let mut payload = Vec::with_capacity(2 + tiles.len() * (4 + tile_bytes));
+    payload.extend_from_slice(&(tiles.len() as u16).to_be_bytes());
+    for tile in tiles {
+        debug_assert_eq!(tile.rgb_data.len(), tile_bytes);
+        payload.extend_from_slice(&tile.column.to_be_bytes());
+        payload.extend_from_slice(&tile.row.to_be_bytes());
+        payload.extend_from_slice(&tile.rgb_data);
+    }
 
-    return [r, g, b];
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::DRAW_TILES,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
 }
 
-pub fn create_pixel_message(x: u16, y: u16, r: u8, g: u8, b: u8) -> Message {
-    if x >= CANVAS_WIDTH || y >= CANVAS_HEIGHT {
-        panic!(
-            "Pixel coordinates out of bounds: ({}, {}) max: ({}, {})",
-            x,
-            y,
-            CANVAS_WIDTH - 1,
-            CANVAS_HEIGHT - 1
+pub fn create_pixels_message(pixels: &[(u16, u16, [u8; 3])]) -> Message {
+    // Batched pixels payload format:
+    // - 2 bytes: pixel count (big-endian)
+    // - per pixel: the DRAW_PIXEL payload (x, y big-endian, r, g, b)
+    let mut payload = Vec::with_capacity(2 + pixels.len() * PIXEL_PAYLOAD_SIZE);
+    payload.extend_from_slice(&(pixels.len() as u16).to_be_bytes());
+    for &(x, y, rgb) in pixels {
+        payload.extend(
+            CellPayload {
+                x,
+                y,
+                rgb: Some(rgb),
+            }
+            .encode(),
         );
     }
 
-    let mut payload = Vec::with_capacity(PIXEL_PAYLOAD_SIZE);
-    payload.extend_from_slice(&x.to_be_bytes());
-    payload.extend_from_slice(&y.to_be_bytes());
-    payload.push(r);
-    payload.push(g);
-    payload.push(b);
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::DRAW_PIXELS_BATCH,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_simulation_status_message(simulation: &SimulationControl) -> Message {
+    // Simulation status payload format:
+    // - 1 byte: paused (0 or 1)
+    // - 4 bytes: requested tick interval in milliseconds (big-endian)
+    // - 4 bytes: applied tick interval in milliseconds, larger when throttled (big-endian)
+    // - 1 byte: autoplay (0 or 1)
+    let mut payload = Vec::with_capacity(10);
+    payload.push(simulation.is_paused() as u8);
+    payload.extend_from_slice(&(simulation.tick_interval_ms() as u32).to_be_bytes());
+    payload.extend_from_slice(&(simulation.applied_tick_interval_ms() as u32).to_be_bytes());
+    payload.push(simulation.is_autoplaying() as u8);
 
     let msg = WsMessage {
         version: PROTOCOL_VERSION,
-        msg_type: message_types::DRAW_PIXEL,
+        msg_type: message_types::SIMULATION_STATUS,
         flags: 0,
         payload,
     };
     encode_ws_message(&msg)
 }
 
-pub fn create_frame_message(frame_data: Vec<u8>) -> Message {
-    let expected_size = (CANVAS_WIDTH as usize) * (CANVAS_HEIGHT as usize) * 3;
-    if frame_data.len() != expected_size {
-        panic!(
-            "Frame data size mismatch: got {} bytes, expected {} bytes for {}x{} RGB canvas",
-            frame_data.len(),
-            expected_size,
-            CANVAS_WIDTH,
-            CANVAS_HEIGHT
-        );
+pub fn create_simulation_reset_message(reason: &str) -> Message {
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SIMULATION_RESET,
+        flags: 0,
+        payload: reason.as_bytes().to_vec(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_frame_message(
+    width: u16,
+    height: u16,
+    frame_data: Vec<u8>,
+) -> Result<Message, FrameError> {
+    let expected = (width as usize) * (height as usize) * 3;
+    if frame_data.len() != expected {
+        return Err(FrameError::SizeMismatch {
+            width,
+            height,
+            got: frame_data.len(),
+            expected,
+        });
     }
 
     // Frame payload format:
@@ -59,14 +181,14 @@ pub fn create_frame_message(frame_data: Vec<u8>) -> Message {
     // - 2 bytes: canvas height (big-endian)
     // - N bytes: RGB pixel data (width * height * 3 bytes)
     let mut payload = Vec::with_capacity(4 + frame_data.len());
-    payload.extend_from_slice(&CANVAS_WIDTH.to_be_bytes());
-    payload.extend_from_slice(&CANVAS_HEIGHT.to_be_bytes());
+    payload.extend_from_slice(&width.to_be_bytes());
+    payload.extend_from_slice(&height.to_be_bytes());
     payload.extend_from_slice(&frame_data);
 
     debug!(
         "Created frame message: {}x{} canvas, {} total bytes",
-        CANVAS_WIDTH,
-        CANVAS_HEIGHT,
+        width,
+        height,
         payload.len()
     );
 
@@ -76,5 +198,598 @@ pub fn create_frame_message(frame_data: Vec<u8>) -> Message {
         flags: 0,
         payload,
     };
+    Ok(encode_ws_message(&msg))
+}
+
+/// Pixels of `frame` that differ from `before`, the whole frame when there's nothing to
+/// compare against or too much changed for a batch. None if nothing changed.
+pub fn create_frame_changes_message(
+    width: u16,
+    height: u16,
+    before: Option<&[u8]>,
+    frame: Vec<u8>,
+) -> Result<Option<Message>, FrameError> {
+    let Some(before) = before else {
+        return create_frame_message(width, height, frame).map(Some);
+    };
+
+    let pixels: Vec<(u16, u16, [u8; 3])> = frame
+        .chunks_exact(3)
+        .zip(before.chunks_exact(3))
+        .enumerate()
+        .filter(|(_, (now, before))| now != before)
+        .map(|(i, (now, _))| {
+            (
+                (i % width as usize) as u16,
+                (i / width as usize) as u16,
+                [now[0], now[1], now[2]],
+            )
+        })
+        .collect();
+    if pixels.is_empty() {
+        return Ok(None);
+    }
+    if pixels.len() > u16::MAX as usize {
+        return create_frame_message(width, height, frame).map(Some);
+    }
+    Ok(Some(create_pixels_message(&pixels)))
+}
+
+pub fn create_capabilities_message() -> Message {
+    // Capabilities payload format:
+    // - 1 byte: number of message type ranges
+    // - per range: 1 byte start, 1 byte end (inclusive), 1 byte owner length, N bytes owner
+    let mut payload = vec![MESSAGE_RANGES.len() as u8];
+    for range in MESSAGE_RANGES {
+        payload.push(range.start);
+        payload.push(range.end);
+        payload.push(range.owner.len() as u8);
+        payload.extend_from_slice(range.owner.as_bytes());
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CAPABILITIES,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_handshake_message(
+    version: u8,
+    codecs: u8,
+    (width, height): (u16, u16),
+    tick_interval_ms: u64,
+    engines: &[ActivePattern],
+) -> Message {
+    // Handshake payload format:
+    // - 1 byte: protocol version the connection speaks
+    // - 1 byte: codecs the server may compress with
+    // - 2 bytes: canvas width, 2 bytes: canvas height (big-endian)
+    // - 4 bytes: tick interval in milliseconds (big-endian)
+    // - 1 byte: number of pattern engines
+    // - per engine: 1 byte name length, N bytes name
+    let mut payload = vec![version, codecs];
+    payload.extend_from_slice(&width.to_be_bytes());
+    payload.extend_from_slice(&height.to_be_bytes());
+    payload.extend_from_slice(&(tick_interval_ms.min(u32::MAX as u64) as u32).to_be_bytes());
+    payload.push(engines.len() as u8);
+    for engine in engines {
+        payload.push(engine.name().len() as u8);
+        payload.extend_from_slice(engine.name().as_bytes());
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::HANDSHAKE_ACCEPTED,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_occupancy_message(viewers: usize, editors: usize) -> Message {
+    // Room occupancy payload format:
+    // - 2 bytes: connected viewers (big-endian)
+    // - 2 bytes: viewers that edited recently (big-endian)
+    let mut payload = Vec::with_capacity(4);
+    payload.extend_from_slice(&(viewers.min(u16::MAX as usize) as u16).to_be_bytes());
+    payload.extend_from_slice(&(editors.min(u16::MAX as usize) as u16).to_be_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::ROOM_OCCUPANCY,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_join_summary_message(seed: u64, summary: &JoinSummary) -> Message {
+    // Join summary payload format:
+    // - 8 bytes: room seed (big-endian)
+    // - 8 bytes: generation (big-endian)
+    // - 1 byte rule length, N bytes rule (e.g. "B3/S23")
+    // - 2 bytes population sample count, then 4 bytes per sample (big-endian, oldest first)
+    // - rest: bit-packed keyframe in the grid download format
+    let mut payload = Vec::with_capacity(
+        19 + GOL_RULE.len() + summary.populations.len() * 4 + summary.keyframe.len(),
+    );
+    payload.extend_from_slice(&seed.to_be_bytes());
+    payload.extend_from_slice(&summary.generation.to_be_bytes());
+    payload.push(GOL_RULE.len() as u8);
+    payload.extend_from_slice(GOL_RULE.as_bytes());
+    payload.extend_from_slice(&(summary.populations.len() as u16).to_be_bytes());
+    for population in &summary.populations {
+        payload.extend_from_slice(&population.to_be_bytes());
+    }
+    payload.extend_from_slice(&summary.keyframe);
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::JOIN_SUMMARY,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_prediction_params_message(seed: u64, (width, height): (u16, u16)) -> Message {
+    // Prediction params payload format, everything a client needs to step generations itself:
+    // - 8 bytes: room seed (big-endian), only random actions use it, stepping never does
+    // - 1 byte rule length, N bytes rule (e.g. "B3/S23")
+    // - 2 bytes: grid width, 2 bytes: grid height (big-endian)
+    // - 1 byte: edge mode (0: cells beyond the grid are dead)
+    // - 1 byte: GENERATION_HASH algorithm (1: FNV-1a 32 over the packed grid bits)
+    let mut payload = Vec::with_capacity(15 + GOL_RULE.len());
+    payload.extend_from_slice(&seed.to_be_bytes());
+    payload.push(GOL_RULE.len() as u8);
+    payload.extend_from_slice(GOL_RULE.as_bytes());
+    payload.extend_from_slice(&width.to_be_bytes());
+    payload.extend_from_slice(&height.to_be_bytes());
+    payload.push(EDGE_MODE_DEAD);
+    payload.push(HASH_FNV1A_32);
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::PREDICTION_PARAMS,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_error_message(code: u16, detail: &str) -> Message {
+    // Error payload format:
+    // - 2 bytes: error code, see `error_codes` (big-endian)
+    // - N bytes: UTF-8 detail
+    let mut payload = Vec::with_capacity(2 + detail.len());
+    payload.extend_from_slice(&code.to_be_bytes());
+    payload.extend_from_slice(detail.as_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::ERROR,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_pong_score_message([left, right]: [u16; 2]) -> Message {
+    // Pong score payload format:
+    // - 2 bytes: left paddle's points (big-endian)
+    // - 2 bytes: right paddle's points (big-endian)
+    let mut payload = Vec::with_capacity(4);
+    payload.extend_from_slice(&left.to_be_bytes());
+    payload.extend_from_slice(&right.to_be_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::PONG_SCORE,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_paddle_assigned_message(side: Option<u8>) -> Message {
+    // Paddle assignment payload format:
+    // - 1 byte: paddle side (0: left, 1: right, 255: both taken)
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::PADDLE_ASSIGNED,
+        flags: 0,
+        payload: vec![side.unwrap_or(u8::MAX)],
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_team_assigned_message(team: u8, rgb: [u8; 3]) -> Message {
+    // Team assignment payload format:
+    // - 1 byte: team id (0: red, 1: blue)
+    // - 3 bytes: the team's cell color
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::TEAM_ASSIGNED,
+        flags: 0,
+        payload: [[team].as_slice(), &rgb].concat(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_client_identity_message(connection_id: &str) -> Message {
+    // Client identity payload format:
+    // - N bytes: the receiving connection's id (utf8)
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CLIENT_IDENTITY,
+        flags: 0,
+        payload: connection_id.as_bytes().to_vec(),
+    };
+    encode_ws_message(&msg)
+}
+
+// Presence entry format, shared by CLIENT_JOINED and PRESENCE_LIST:
+// - 1 byte: connection id length, N bytes: connection id (utf8)
+// - 1 byte: nickname length (0 without one), N bytes: nickname (utf8)
+fn encode_presence(presence: &Presence, payload: &mut Vec<u8>) {
+    let nickname = presence.nickname.as_deref().unwrap_or_default();
+    for field in [presence.connection_id.as_bytes(), nickname.as_bytes()] {
+        payload.push(field.len() as u8);
+        payload.extend_from_slice(field);
+    }
+}
+
+pub fn create_client_joined_message(presence: &Presence) -> Message {
+    let mut payload = Vec::new();
+    encode_presence(presence, &mut payload);
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CLIENT_JOINED,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_client_left_message(connection_id: &str) -> Message {
+    // Client left payload format:
+    // - N bytes: the id of the connection that closed (utf8)
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CLIENT_LEFT,
+        flags: 0,
+        payload: connection_id.as_bytes().to_vec(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_cursor_moved_message(connection_id: &str, x: u16, y: u16, rgb: [u8; 3]) -> Message {
+    // Cursor moved payload format:
+    // - 2 bytes: x, 2 bytes: y (big-endian), the cell under the cursor
+    // - 3 bytes: the cursor's color
+    // - N bytes: the id of the connection that moved it (utf8)
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CURSOR_MOVED,
+        flags: 0,
+        payload: [
+            x.to_be_bytes().as_slice(),
+            &y.to_be_bytes(),
+            &rgb,
+            connection_id.as_bytes(),
+        ]
+        .concat(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_chat_message(sender: &Presence, text: &str) -> Message {
+    // Chat message payload format:
+    // - a presence entry of the sender
+    // - N bytes: the message (utf8)
+    let mut payload = Vec::new();
+    encode_presence(sender, &mut payload);
+    payload.extend_from_slice(text.as_bytes());
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CHAT_MESSAGE,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_admin_status_message(admin: bool) -> Message {
+    // Admin status payload format:
+    // - 1 byte: 1 if the connection may send privileged messages, 0 otherwise
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::ADMIN_STATUS,
+        flags: 0,
+        payload: vec![admin as u8],
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_state_saved_message(generation: u64, name: &str) -> Message {
+    // State saved payload format:
+    // - 8 bytes: saved generation (big-endian)
+    // - N bytes: UTF-8 name the board was saved as
+    let mut payload = Vec::with_capacity(8 + name.len());
+    payload.extend_from_slice(&generation.to_be_bytes());
+    payload.extend_from_slice(name.as_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::STATE_SAVED,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_checkpoint_list_message(generations: &[u64]) -> Message {
+    // Checkpoint list payload format:
+    // - 2 bytes: checkpoint count (big-endian)
+    // - per checkpoint: 8 bytes generation (big-endian), newest first
+    let mut payload = Vec::with_capacity(2 + generations.len() * 8);
+    payload.extend_from_slice(&(generations.len() as u16).to_be_bytes());
+    for generation in generations {
+        payload.extend_from_slice(&generation.to_be_bytes());
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::CHECKPOINT_LIST,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_mlp_painting_list_message(selected: u16, paintings: &[(u16, usize)]) -> Message {
+    // MLP painting list payload format:
+    // - 2 bytes: id of the painting shown (big-endian)
+    // - 2 bytes: painting count (big-endian)
+    // - per painting: 2 bytes id (big-endian), 1 byte progress in percent, by id
+    let mut payload = Vec::with_capacity(4 + paintings.len() * 3);
+    payload.extend_from_slice(&selected.to_be_bytes());
+    payload.extend_from_slice(&(paintings.len() as u16).to_be_bytes());
+    for &(id, progress) in paintings {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.push(progress.min(100) as u8);
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::MLP_PAINTING_LIST,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_server_stats_message(report: &ServerStatsReport) -> Message {
+    // Server stats payload format:
+    // - 4 bytes: open connections across every room (big-endian)
+    // - 8 bytes: seconds since the server started (big-endian)
+    // - 8 bytes: times a connection fell a whole channel behind (big-endian)
+    // - 8 bytes: room messages those connections skipped (big-endian)
+    // - 8 bytes: messages sent to clients from their rooms (big-endian)
+    // - 2 bytes: room count (big-endian)
+    // - per public room: 1 byte id length, N bytes UTF-8 id, 4 bytes population (big-endian)
+    let mut payload = Vec::with_capacity(38 + report.populations.len() * 40);
+    payload.extend_from_slice(&(report.connections as u32).to_be_bytes());
+    payload.extend_from_slice(&report.uptime.as_secs().to_be_bytes());
+    payload.extend_from_slice(&report.lag_events.to_be_bytes());
+    payload.extend_from_slice(&report.lagged_messages.to_be_bytes());
+    payload.extend_from_slice(&report.frames_sent.to_be_bytes());
+    payload.extend_from_slice(&(report.populations.len() as u16).to_be_bytes());
+    for (room, population) in &report.populations {
+        payload.push(room.len() as u8);
+        payload.extend_from_slice(room.as_bytes());
+        payload.extend_from_slice(&(*population as u32).to_be_bytes());
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SERVER_STATS,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_presence_list_message(members: &[Presence]) -> Message {
+    // Presence list payload format:
+    // - 2 bytes: member count (big-endian)
+    // - per member: a presence entry, longest connected first
+    let mut payload = (members.len() as u16).to_be_bytes().to_vec();
+    for presence in members {
+        encode_presence(presence, &mut payload);
+    }
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::PRESENCE_LIST,
+        flags: 0,
+        payload,
+    };
     encode_ws_message(&msg)
 }
+
+pub fn create_invalid_text_error(detail: &str) -> Message {
+    create_error_message(error_codes::INVALID_TEXT_MESSAGE, detail)
+}
+
+pub fn create_simulation_stable_message(generation: u64, period: u8) -> Message {
+    // Simulation stable payload format:
+    // - 8 bytes: generation the board was found stable at (big-endian)
+    // - 1 byte: period of the cycle, 1 for a still life
+    let mut payload = Vec::with_capacity(9);
+    payload.extend_from_slice(&generation.to_be_bytes());
+    payload.push(period);
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SIMULATION_STABLE,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_bot_status_message(running: &[BotKind]) -> Message {
+    // Bot status payload format:
+    // - 1 byte: number of running bots
+    // - N bytes: their ids, see `BotKind::ALL`
+    let mut payload = Vec::with_capacity(1 + running.len());
+    payload.push(running.len() as u8);
+    payload.extend(running.iter().map(|kind| kind.id()));
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::BOT_STATUS,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_scheduled_event_message(action: &str) -> Message {
+    // Scheduled event payload format:
+    // - N bytes: the action about to run, as written in the schedule (utf8)
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SCHEDULED_EVENT,
+        flags: 0,
+        payload: action.as_bytes().to_vec(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_schedule_list_message(entries: &[ScheduleEntry]) -> Message {
+    // Schedule list payload format:
+    // - N bytes: one line per entry in the schedule file's syntax (utf8), in the order
+    //   REMOVE_SCHEDULE_ENTRY counts them
+    let lines: Vec<String> = entries.iter().map(ToString::to_string).collect();
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SCHEDULE_LIST,
+        flags: 0,
+        payload: lines.join("\n").into_bytes(),
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_mode_changed_message(pattern: ActivePattern) -> Message {
+    // Mode changed payload format:
+    // - 1 byte: id of the pattern the room shows now, see `ActivePattern::ALL`
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::MODE_CHANGED,
+        flags: 0,
+        payload: vec![pattern.id()],
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_room_list_message(rooms: &[RoomSummary]) -> Message {
+    // Room list payload format:
+    // - 2 bytes: room count (big-endian)
+    // - per public room: 1 byte id length, N bytes UTF-8 id, 1 byte mode id (see
+    //   `ActivePattern::ALL`), 2 bytes clients, 2 bytes canvas width, 2 bytes canvas
+    //   height (big-endian)
+    let mut payload = (rooms.len() as u16).to_be_bytes().to_vec();
+    for room in rooms {
+        payload.push(room.id.len() as u8);
+        payload.extend_from_slice(room.id.as_bytes());
+        payload.push(room.mode.id());
+        payload.extend_from_slice(&(room.clients.min(u16::MAX as usize) as u16).to_be_bytes());
+        payload.extend_from_slice(&room.width.to_be_bytes());
+        payload.extend_from_slice(&room.height.to_be_bytes());
+    }
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::ROOM_LIST,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_soup_found_message(report: &SoupReport) -> Message {
+    // Soup found payload format:
+    // - 1 byte: find (0: methuselah, 1: spaceship)
+    // - 4 bytes: lifespan in generations, or the spaceship's period (big-endian)
+    // - N bytes: UTF-8 RLE of the soup
+    let value = match report.find {
+        SoupFind::Methuselah { lifespan } => lifespan as u32,
+        SoupFind::Spaceship { period } => period,
+    };
+    let rle = report.seed.to_rle();
+    let mut payload = Vec::with_capacity(5 + rle.len());
+    payload.push(report.find.id());
+    payload.extend_from_slice(&value.to_be_bytes());
+    payload.extend_from_slice(rle.as_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::SOUP_FOUND,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+pub fn create_generation_hash_message(generation: u64, hash: u32) -> Message {
+    // Generation hash payload format:
+    // - 8 bytes: generation (big-endian)
+    // - 4 bytes: hash of the generation's packed bits (big-endian)
+    let mut payload = Vec::with_capacity(12);
+    payload.extend_from_slice(&generation.to_be_bytes());
+    payload.extend_from_slice(&hash.to_be_bytes());
+
+    let msg = WsMessage {
+        version: PROTOCOL_VERSION,
+        msg_type: message_types::GENERATION_HASH,
+        flags: 0,
+        payload,
+    };
+    encode_ws_message(&msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_of_the_wrong_size_are_errors() {
+        let frame = create_frame_message(3, 2, vec![7; 18]).unwrap();
+        let parsed = WsMessage::parse(frame.as_payload(), usize::MAX).unwrap();
+        assert_eq!(parsed.msg_type, message_types::DRAW_FRAME);
+        assert_eq!(parsed.payload[..4], [0, 3, 0, 2]);
+        assert_eq!(parsed.payload.len(), 4 + 18);
+
+        for got in [0, 17, 19] {
+            let error = create_frame_message(3, 2, vec![7; got]).unwrap_err();
+            assert!(matches!(
+                error,
+                FrameError::SizeMismatch {
+                    width: 3,
+                    height: 2,
+                    expected: 18,
+                    ..
+                }
+            ));
+            assert!(error.to_string().contains(&format!("got {}", got)));
+        }
+
+        let pixel = create_pixel_message(u16::MAX, 0, 1, 2, 3).unwrap();
+        let parsed = WsMessage::parse(pixel.as_payload(), usize::MAX).unwrap();
+        assert_eq!(parsed.payload, [0xff, 0xff, 0, 0, 1, 2, 3]);
+    }
+}
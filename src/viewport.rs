@@ -0,0 +1,212 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::constants::{
+    DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH, MAX_CANVAS_SIDE, MAX_VIEWPORT_ZOOM, TILE_SIDE,
+};
+
+/// Part of the Game of Life board one connection watches, so a board can be larger than
+/// the canvas it's shown on. Each frame pixel covers `zoom` x `zoom` cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    // Top left cell of the frame
+    pub x: u16,
+    pub y: u16,
+    pub zoom: u8,
+    // Frame size in pixels
+    pub width: u16,
+    pub height: u16,
+    // Whether the client takes DRAW_TILES it keeps for panning rather than whole frames
+    pub tiles: bool,
+}
+
+impl Viewport {
+    // SET_VIEWPORT payload format:
+    // - 2 bytes: x of the top left cell (big-endian)
+    // - 2 bytes: y of the top left cell (big-endian)
+    // - 1 byte: zoom, cells per frame pixel along each side, 0 for the whole board again
+    // - 2 bytes: frame width, 2 bytes: frame height (big-endian), optional, 100x100 without
+    // - 1 byte: flags, bit 0 set for tiles, optional after the frame size
+    // An empty payload also goes back to the whole board
+    pub fn decode(payload: &[u8]) -> Result<Option<Self>> {
+        let (x, y, zoom, rest) = match payload {
+            [] => return Ok(None),
+            [x0, x1, y0, y1, zoom, rest @ ..] => (
+                u16::from_be_bytes([*x0, *x1]),
+                u16::from_be_bytes([*y0, *y1]),
+                *zoom,
+                rest,
+            ),
+            _ => bail!("Viewport payload of {} bytes", payload.len()),
+        };
+        let (width, height, flags) = match rest {
+            [] => (DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT, 0),
+            [w0, w1, h0, h1, flags @ ..] if flags.len() <= 1 => (
+                u16::from_be_bytes([*w0, *w1]),
+                u16::from_be_bytes([*h0, *h1]),
+                flags.first().copied().unwrap_or(0),
+            ),
+            _ => bail!("Viewport payload of {} bytes", payload.len()),
+        };
+        if zoom == 0 {
+            return Ok(None);
+        }
+        if zoom > MAX_VIEWPORT_ZOOM {
+            bail!("Viewport zoom {} above {}", zoom, MAX_VIEWPORT_ZOOM);
+        }
+        if !(1..=MAX_CANVAS_SIDE).contains(&width) || !(1..=MAX_CANVAS_SIDE).contains(&height) {
+            bail!("Viewport frame of {}x{} pixels", width, height);
+        }
+
+        Ok(Some(Self {
+            x,
+            y,
+            zoom,
+            width,
+            height,
+            tiles: flags & 1 != 0,
+        }))
+    }
+
+    /// Top left cell under frame pixel (`column`, `row`), possibly past the board's edges
+    pub fn cell_at(&self, column: u16, row: u16) -> (usize, usize) {
+        let zoom = self.zoom as usize;
+        (
+            self.x as usize + column as usize * zoom,
+            self.y as usize + row as usize * zoom,
+        )
+    }
+
+    /// Column and row of the tiles the viewport overlaps on a board of `board` cells. Tiles
+    /// line up with the board, not the viewport, so they stay valid while it pans.
+    pub fn visible_tiles(&self, (board_width, board_height): (u16, u16)) -> Vec<(u16, u16)> {
+        let zoom = self.zoom as usize;
+        let tile_side = TILE_SIDE as usize;
+        // First and past the last tile along one side, in board pixels at this zoom
+        let span = |start: u16, length: u16, board: u16| {
+            let board = (board as usize).div_ceil(zoom);
+            let first = start as usize / zoom;
+            let end = (first + length as usize).min(board);
+            (first / tile_side..end.div_ceil(tile_side)).map(|tile| tile as u16)
+        };
+        let columns = span(self.x, self.width, board_width);
+        span(self.y, self.height, board_height)
+            .flat_map(|row| columns.clone().map(move |column| (column, row)))
+            .collect()
+    }
+
+    /// Top left cell of tile (`column`, `row`)
+    pub fn tile_origin(&self, column: u16, row: u16) -> (usize, usize) {
+        let side = TILE_SIDE as usize * self.zoom as usize;
+        (column as usize * side, row as usize * side)
+    }
+}
+
+/// A `TILE_SIDE` square of viewport pixels, as sent in DRAW_TILES
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub column: u16,
+    pub row: u16,
+    pub rgb_data: Vec<u8>,
+}
+
+/// Tiles a connection was sent at its current zoom, by the hash of what they showed. The
+/// client keeps them, so panning back doesn't send them again.
+#[derive(Debug, Default)]
+pub struct TileCache {
+    zoom: u8,
+    hashes: HashMap<(u16, u16), u64>,
+}
+
+impl TileCache {
+    /// Forgets every tile, e.g. when the client lost its own
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+
+    /// The tiles the client doesn't have as they are now, which are remembered as sent. A
+    /// new zoom invalidates every tile.
+    pub fn unsent(&mut self, zoom: u8, tiles: Vec<Tile>) -> Vec<Tile> {
+        if zoom != self.zoom {
+            self.zoom = zoom;
+            self.hashes.clear();
+        }
+        tiles
+            .into_iter()
+            .filter(|tile| {
+                let mut hasher = DefaultHasher::new();
+                tile.rgb_data.hash(&mut hasher);
+                let hash = hasher.finish();
+                self.hashes.insert((tile.column, tile.row), hash) != Some(hash)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::DEAD_CELL_R_G_B, patterns::gol::GolState};
+
+    #[test]
+    fn viewport_frames_show_the_cells_under_them() {
+        assert_eq!(Viewport::decode(&[]).unwrap(), None);
+        assert_eq!(Viewport::decode(&[0, 0, 0, 0, 0]).unwrap(), None);
+        assert!(Viewport::decode(&[0, 0, 0, 0, MAX_VIEWPORT_ZOOM + 1]).is_err());
+        assert!(Viewport::decode(&[0, 0, 0, 0, 1, 0, 0, 0, 4]).is_err());
+        assert!(Viewport::decode(&[0, 0, 0]).is_err());
+        let default = Viewport::decode(&[0, 3, 0, 4, 2]).unwrap().unwrap();
+        assert_eq!(
+            (default.width, default.height),
+            (DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT)
+        );
+        assert_eq!(default.cell_at(1, 2), (5, 8));
+
+        let gol = GolState::new(20, 20, 0);
+        gol.kill_all_cells().unwrap();
+        gol.awaken_cells(&[(10, 10), (13, 11)]);
+        // 7x3 pixels of 2x2 cells from (8, 8), the last column past the board's edge
+        let viewport = Viewport::decode(&[0, 8, 0, 8, 2, 0, 7, 0, 3])
+            .unwrap()
+            .unwrap();
+        let frame = gol.viewport_frame(&viewport).unwrap();
+        let payload = &frame.as_payload()[7..];
+        assert_eq!(payload[..4], [0, 7, 0, 3]);
+        let live: Vec<(usize, usize)> = payload[4..]
+            .chunks(3)
+            .enumerate()
+            .filter(|(_, rgb)| *rgb != DEAD_CELL_R_G_B)
+            .map(|(i, _)| (i % 7, i / 7))
+            .collect();
+        assert_eq!(live, [(1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn panning_sends_only_new_and_changed_tiles() {
+        let gol = GolState::new(100, 100, 0);
+        gol.kill_all_cells().unwrap();
+        let mut cache = TileCache::default();
+        let mut viewport = Viewport::decode(&[0, 40, 0, 0, 1, 0, 40, 0, 40, 1])
+            .unwrap()
+            .unwrap();
+        assert!(viewport.tiles);
+        let mut unsent = |viewport: &Viewport| {
+            let tiles = cache.unsent(viewport.zoom, gol.viewport_tiles(viewport));
+            tiles
+                .iter()
+                .map(|tile| (tile.column, tile.row))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(unsent(&viewport), [(1, 0), (2, 0), (1, 1), (2, 1)]);
+        assert_eq!(unsent(&viewport), []);
+        gol.awaken_cells(&[(70, 5)]);
+        assert_eq!(unsent(&viewport), [(2, 0)]);
+        // The board ends in the fourth column of tiles
+        viewport.x = 60;
+        assert_eq!(unsent(&viewport), [(3, 0), (3, 1)]);
+        viewport.zoom = 2;
+        assert_eq!(unsent(&viewport), [(0, 0), (1, 0), (0, 1), (1, 1)]);
+    }
+}
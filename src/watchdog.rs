@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    constants::{SCHEDULER_RUN, WATCHDOG_CHECK_INTERVAL_MS, WATCHDOG_DEADLINE_MS, topics},
+    i18n::Notice,
+    room::{BroadcastMessage, RoomId, RoomState, spawn_simulation_loop},
+    state::AppState,
+    utils::create_simulation_reset_message,
+};
+
+/// Watches every room's simulation loop and replaces loops that stop ticking
+pub fn spawn_watchdog(state: Arc<AppState>) {
+    if !SCHEDULER_RUN {
+        debug!("Simulation loops disabled, watchdog not started");
+        return;
+    }
+
+    tokio::spawn(async move {
+        info!("Starting simulation watchdog");
+        let mut interval = tokio::time::interval(Duration::from_millis(WATCHDOG_CHECK_INTERVAL_MS));
+
+        loop {
+            tokio::select! {
+                // The loops stop on shutdown too, they're not stuck
+                _ = state.shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            for room in state.rooms() {
+                if let Some(silence_ms) = overdue_silence_ms(&room) {
+                    recover_room(&room, silence_ms, &state.shutdown);
+                }
+            }
+        }
+    });
+}
+
+// How long the room's simulation loop has been silent, if longer than it may be. A loop
+// sleeping in a room nobody watches isn't overdue.
+fn overdue_silence_ms(room: &RoomState) -> Option<u64> {
+    if room.health.is_idle() {
+        return None;
+    }
+    let deadline_ms = room.simulation.applied_tick_interval_ms() + WATCHDOG_DEADLINE_MS;
+    let silence_ms = room.health.silence_ms();
+    (silence_ms > deadline_ms).then_some(silence_ms)
+}
+
+/// Whether the parts of a room clients depend on work, as /healthz and /readyz report it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoomHealth {
+    pub room: RoomId,
+    // The simulation loop ticked within the watchdog's deadline
+    pub broadcaster: bool,
+    // Messages can still be numbered and sent to the room's clients
+    pub channel: bool,
+    // The Game of Life grid's lock isn't poisoned
+    pub engine: bool,
+}
+
+impl RoomHealth {
+    pub fn check(room: &RoomState) -> Self {
+        Self {
+            room: room.id.clone(),
+            broadcaster: !SCHEDULER_RUN || overdue_silence_ms(room).is_none(),
+            channel: room.channel.is_functional(),
+            engine: !room.gol.is_poisoned(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.broadcaster && self.channel && self.engine
+    }
+}
+
+fn recover_room(room: &Arc<RoomState>, silence_ms: u64, shutdown: &CancellationToken) {
+    error!(
+        "Simulation in room {:?} stuck for {}ms, restarting it",
+        room.id, silence_ms
+    );
+
+    let epoch = room.health.next_epoch();
+    let restored = match room.health.last_good_snapshot() {
+        Some(snapshot) => {
+            let generation = snapshot.generation_count();
+            if room.gol.try_restore(snapshot) {
+                info!("Restored room {:?} to generation {}", room.id, generation);
+                true
+            } else {
+                warn!(
+                    "Game state of room {:?} still locked, keeping current state",
+                    room.id
+                );
+                false
+            }
+        }
+        None => {
+            warn!("No snapshot available for room {:?}", room.id);
+            false
+        }
+    };
+
+    room.health.beat();
+    spawn_simulation_loop(Arc::downgrade(room), epoch, shutdown.clone());
+
+    let reason = if restored {
+        Notice::SimulationRestored
+    } else {
+        Notice::SimulationRestarted
+    };
+    if room.channel.receiver_count() > 0 {
+        let notice = BroadcastMessage::notice(reason, create_simulation_reset_message);
+        if let Err(e) = room.channel.send(notice) {
+            warn!("Failed to notify clients about simulation reset: {}", e);
+        }
+        if restored {
+            match room.gol.current_generation() {
+                Ok(frame) => {
+                    let frame = BroadcastMessage::new(topics::GOL_FRAMES, frame);
+                    if let Err(e) = room.channel.send(frame) {
+                        warn!("Failed to send restored generation: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to draw restored generation: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rooms_are_unhealthy_until_their_loop_ticks() {
+        let room = RoomState::for_test();
+        let health = RoomHealth::check(&room);
+        assert!(!health.broadcaster);
+        assert!(health.channel && health.engine);
+        assert!(!health.is_healthy());
+
+        room.health.beat();
+        assert!(RoomHealth::check(&room).is_healthy());
+    }
+}
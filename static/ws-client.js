@@ -1,4 +1,27 @@
-const socket = new WebSocket("ws://localhost:8080/ws");
+// Join a specific room with ?room=<id>, otherwise the shared default room.
+// Private rooms also need ?password=<secret> or a single-use ?invite=<token>.
+// ?admin_token=<token> joins as an admin, if the server has one configured.
+const pageParams = new URLSearchParams(window.location.search);
+const room = pageParams.get("room");
+const joinParams = new URLSearchParams();
+for (const key of ["password", "invite", "admin_token"]) {
+  if (pageParams.has(key)) joinParams.set(key, pageParams.get(key));
+}
+const joinQuery = joinParams.size ? `?${joinParams}` : "";
+
+// Codec compiled from game_of_life_core when its wasm build is served from static/pkg
+// (see crates/game_of_life_core/Cargo.toml), otherwise the JS fallbacks below are used
+const wasmCore = await import("./pkg/game_of_life_core.js")
+  .then(async (core) => {
+    await core.default();
+    return core;
+  })
+  .catch(() => null);
+const socket = new WebSocket(
+  room
+    ? `ws://localhost:8080/ws/${encodeURIComponent(room)}${joinQuery}`
+    : `ws://localhost:8080/ws${joinQuery}`,
+);
 socket.binaryType = "arraybuffer";
 
 const logMessage = (prefix, text, className = "") => {
@@ -10,13 +33,50 @@ const logMessage = (prefix, text, className = "") => {
   container.scrollTop = container.scrollHeight;
 };
 
-socket.addEventListener("open", () =>
-  logMessage("✓", "WebSocket connected", "msg-in"),
-);
+// Set once the WebSocket opens, a socket that never does falls back to an event stream
+let socketOpened = false;
+// Receive-only stream of room frames, used when WebSockets can't get through
+let eventStream = null;
 
-socket.addEventListener("close", () =>
-  logMessage("×", "WebSocket closed", "msg-in"),
-);
+socket.addEventListener("open", () => {
+  socketOpened = true;
+  logMessage("✓", `WebSocket connected (room: ${room ?? "default"})`, "msg-in");
+  sendTextPreferences();
+  sendHello();
+});
+
+// Server notices are worded in the browser's language; no length limit, emoji allowed
+function sendTextPreferences() {
+  const locale = new TextEncoder().encode(navigator.language ?? "");
+  const payload = new Uint8Array(3 + locale.length);
+  payload[0] = 0x01;
+  payload.set(locale, 3);
+  sendMessage(MESSAGE_TYPES.SET_TEXT_PREFERENCES, payload);
+}
+
+// The server gives a reason when it drops the client, e.g. for falling too far behind
+socket.addEventListener("close", (event) => {
+  logMessage("×", `WebSocket closed${event.reason ? `: ${event.reason}` : ""}`, "msg-in");
+  if (!socketOpened) openEventStream();
+});
+
+// Server-Sent Events carry the same binary frames, base64 encoded. Input can't be sent back.
+function openEventStream() {
+  eventStream = new EventSource(
+    room
+      ? `http://localhost:8080/events/${encodeURIComponent(room)}${joinQuery}`
+      : `http://localhost:8080/events${joinQuery}`,
+  );
+  eventStream.addEventListener("open", () =>
+    logMessage("✓", `Watching room ${room ?? "default"} over an event stream, input is disabled`, "msg-in"),
+  );
+  eventStream.addEventListener("message", (event) =>
+    receiveFrame(Uint8Array.from(atob(event.data), (c) => c.charCodeAt(0))),
+  );
+  eventStream.addEventListener("error", () =>
+    logMessage("!", "Event stream error", "msg-error"),
+  );
+}
 
 socket.addEventListener("error", () =>
   logMessage("!", "WebSocket error", "msg-error"),
@@ -26,15 +86,41 @@ const canvas = document.getElementById("paint-canvas");
 const ctx = canvas.getContext("2d");
 const CANVAS_WIDTH = 800;
 const CANVAS_HEIGHT = 800;
-const GRID_COLS = 100;
-const GRID_ROWS = 100;
-const CELL_SIZE = CANVAS_WIDTH / GRID_COLS;
+// Grid size is configurable on the server, PREDICTION_PARAMS tells the actual one
+let GRID_COLS = 100;
+let GRID_ROWS = 100;
+let CELL_SIZE = CANVAS_WIDTH / GRID_COLS;
+
+function setGridSize(cols, rows) {
+  GRID_COLS = cols;
+  GRID_ROWS = rows;
+  CELL_SIZE = Math.min(CANVAS_WIDTH / cols, CANVAS_HEIGHT / rows);
+}
+
+// Part of the board the canvas shows, null for the whole board. Frames then cover the
+// viewport, edits still go out in board cells.
+let viewport = null;
+let boardSize = { cols: GRID_COLS, rows: GRID_ROWS };
+// Pixels along each side of a viewport frame
+const VIEWPORT_FRAME_SIZE = 100;
+
+function setBoardSize(cols, rows) {
+  boardSize = { cols, rows };
+  if (!viewport) setGridSize(cols, rows);
+}
+
+// Board cell under a canvas cell
+function boardCell(col, row) {
+  if (!viewport) return { col, row };
+  return { col: viewport.x + col * viewport.zoom, row: viewport.y + row * viewport.zoom };
+}
 
 // Hover and click state
 let hoveredCell = { col: -1, row: -1 };
 let cellColors = new Map(); // Store cell colors: "col,row" -> {r, g, b}
 let isDragging = false;
 let lastDraggedCell = { col: -1, row: -1 };
+let rectStart = null; // Corner cell a rectangle tool started from
 
 // Message types
 const MESSAGE_TYPES = {
@@ -42,22 +128,214 @@ const MESSAGE_TYPES = {
   HELLO: 1,
 
   // received by server
+  SET_TEXT_PREFERENCES: 2,
+  SET_NICKNAME: 3,
+  AUTH: 4,
+  KICK_CLIENT: 5,
+  GET_SERVER_STATS: 6,
+  ADD_SCHEDULE_ENTRY: 7,
+  REMOVE_SCHEDULE_ENTRY: 8,
+  LIST_SCHEDULE: 9,
+  LIST_ROOMS: 10,
+  CREATE_ROOM: 11,
   CREATE_NEW_GENERATION: 40,
   AWAKEN_RANDOM_CELL: 41,
   KILL_RANDOM_CELL: 42,
   STEP_GENERATION: 43,
   KILL_ALL_CELLS: 45,
+  SEED_FROM_PAINTING: 46,
+  LOAD_PATTERN: 47,
+  STAMP_PATTERN: 48,
+  AWAKEN_CELLS_BATCH: 49,
+  BRUSH_STROKE: 50,
+  SET_COLOR_SCHEME: 51,
+  SET_GOL_HYBRID: 52,
+  SEED_FROM_IMAGE: 53,
+  TOGGLE_CELL: 54,
+  FILL_RECT: 55,
+  CLEAR_RECT: 56,
+  UNDO: 57,
+  REDO: 58,
+
+  SET_SIMULATION_SPEED: 60,
+  PAUSE_SIMULATION: 61,
+  RESUME_SIMULATION: 62,
+  SET_AUTOPLAY: 64,
+  SET_SEED: 63,
+  START_SOUP_SEARCH: 65,
+  STOP_SOUP_SEARCH: 66,
+  SAVE_STATE: 67,
+  LOAD_STATE: 68,
+  SELECT_ENGINE: 69,
+
+  SUBSCRIBE: 70,
+  UNSUBSCRIBE: 71,
+  RESYNC_REQUEST: 72,
+  SET_VIEWPORT: 73,
 
   CREATE_NEW_MLP_PAINTING: 20,
   ADVANCE_MLP_PAINTING: 21,
+  PAINT_FROM_GENERATION: 22,
+  UPLOAD_MLP_IMAGE: 23,
+  ADD_MLP_PAINTING: 24,
+  SELECT_MLP_PAINTING: 25,
+  DELETE_MLP_PAINTING: 26,
+  LIST_MLP_PAINTINGS: 27,
+  ADVANCE_MLP_PAINTING_TO: 28,
+  SET_MLP_STYLE: 29,
+
+  CREATE_NEW_BRAIN_GENERATION: 80,
+  ADVANCE_BRAIN_GENERATION: 81,
+
+  SWITCH_MODE: 90,
+
+  START_BOT: 95,
+  STOP_BOT: 96,
+
+  SPAWN_MATERIAL: 150,
+
+  CREATE_NEW_REACTION: 170,
+  ADVANCE_REACTION: 171,
+  SET_REACTION_RATES: 172,
+
+  SPAWN_BOIDS: 190,
+  SET_BOID_WEIGHTS: 191,
+  RESET_BOIDS: 192,
 
   REQUEST_PIXEL: 200,
+  INPUT_EVENT: 201,
+  CURSOR_MOVE: 202,
+  CHAT: 203,
+
+  CLAIM_PADDLE: 220,
+  MOVE_PADDLE: 221,
+
+  JOIN_SNAKE: 230,
+  TURN_SNAKE: 231,
+
+  CREATE_NEW_IMMIGRATION_GENERATION: 240,
+  ADVANCE_IMMIGRATION_GENERATION: 241,
+  AWAKEN_TEAM_CELL: 242,
+  LIST_CHECKPOINTS: 250,
+  RESTORE_CHECKPOINT: 251,
 
   // sent by server
   DRAW_PIXEL: 100,
   DRAW_FRAME: 101,
+  SIMULATION_STATUS: 102,
+  SIMULATION_RESET: 103,
+  CAPABILITIES: 104,
+  ROOM_OCCUPANCY: 105,
+  JOIN_SUMMARY: 106,
+  DRAW_PIXELS_BATCH: 107,
+  PREDICTION_PARAMS: 108,
+  GENERATION_HASH: 109,
+  ERROR: 110,
+  PONG_SCORE: 111,
+  PADDLE_ASSIGNED: 112,
+  TEAM_ASSIGNED: 113,
+  SIMULATION_STABLE: 114,
+  SOUP_FOUND: 115,
+  DRAW_REGION: 116,
+  CLIENT_IDENTITY: 117,
+  CLIENT_JOINED: 118,
+  CLIENT_LEFT: 119,
+  PRESENCE_LIST: 120,
+  CURSOR_MOVED: 121,
+  CHAT_MESSAGE: 122,
+  ADMIN_STATUS: 123,
+  STATE_SAVED: 124,
+  CHECKPOINT_LIST: 125,
+  HANDSHAKE_ACCEPTED: 126,
+  MLP_PAINTING_LIST: 127,
+  DRAW_TILES: 128,
+  SERVER_STATS: 129,
+  BOT_STATUS: 130,
+  SCHEDULED_EVENT: 131,
+  SCHEDULE_LIST: 132,
+  MODE_CHANGED: 133,
+  ROOM_LIST: 134,
+};
+
+// Patterns a room can show by their SWITCH_MODE id, mirrors ActivePattern::ALL
+const MODES = ["gol", "mlp", "brians_brain", "sand", "reaction", "boids", "pong", "snake", "immigration"];
+// Mode of the commands that only reach one pattern, mirrors ActivePattern::for_message_type
+const MESSAGE_MODES = new Map([
+  ...[
+    "CREATE_NEW_GENERATION", "AWAKEN_RANDOM_CELL", "KILL_RANDOM_CELL", "STEP_GENERATION",
+    "KILL_ALL_CELLS", "SEED_FROM_PAINTING", "LOAD_PATTERN", "STAMP_PATTERN",
+    "AWAKEN_CELLS_BATCH", "BRUSH_STROKE", "SET_COLOR_SCHEME", "SET_GOL_HYBRID",
+    "SEED_FROM_IMAGE", "TOGGLE_CELL", "FILL_RECT", "CLEAR_RECT", "UNDO", "REDO", "REQUEST_PIXEL",
+  ].map((name) => [MESSAGE_TYPES[name], "gol"]),
+  ...[
+    "CREATE_NEW_MLP_PAINTING", "ADVANCE_MLP_PAINTING", "PAINT_FROM_GENERATION",
+    "UPLOAD_MLP_IMAGE", "ADD_MLP_PAINTING", "SELECT_MLP_PAINTING", "DELETE_MLP_PAINTING",
+    "ADVANCE_MLP_PAINTING_TO", "SET_MLP_STYLE",
+  ].map((name) => [MESSAGE_TYPES[name], "mlp"]),
+  [MESSAGE_TYPES.CREATE_NEW_BRAIN_GENERATION, "brians_brain"],
+  [MESSAGE_TYPES.ADVANCE_BRAIN_GENERATION, "brians_brain"],
+  [MESSAGE_TYPES.SPAWN_MATERIAL, "sand"],
+  [MESSAGE_TYPES.CREATE_NEW_REACTION, "reaction"],
+  [MESSAGE_TYPES.ADVANCE_REACTION, "reaction"],
+  [MESSAGE_TYPES.SET_REACTION_RATES, "reaction"],
+  [MESSAGE_TYPES.SPAWN_BOIDS, "boids"],
+  [MESSAGE_TYPES.SET_BOID_WEIGHTS, "boids"],
+  [MESSAGE_TYPES.RESET_BOIDS, "boids"],
+  [MESSAGE_TYPES.CLAIM_PADDLE, "pong"],
+  [MESSAGE_TYPES.MOVE_PADDLE, "pong"],
+  [MESSAGE_TYPES.JOIN_SNAKE, "snake"],
+  [MESSAGE_TYPES.TURN_SNAKE, "snake"],
+  [MESSAGE_TYPES.CREATE_NEW_IMMIGRATION_GENERATION, "immigration"],
+  [MESSAGE_TYPES.ADVANCE_IMMIGRATION_GENERATION, "immigration"],
+  [MESSAGE_TYPES.AWAKEN_TEAM_CELL, "immigration"],
+]);
+// Pattern the room shows, from MODE_CHANGED. The server rejects commands for the others.
+let activeMode = "gol";
+
+// Broadcast topic bitmask, mirrors constants::topics on the server
+const TOPICS = {
+  GOL_FRAMES: 1 << 0,
+  MLP_FRAMES: 1 << 1,
+  PIXEL_EVENTS: 1 << 2,
+  BRAIN_FRAMES: 1 << 3,
+  SAND_FRAMES: 1 << 4,
+  REACTION_FRAMES: 1 << 5,
+  BOID_FRAMES: 1 << 6,
+};
+
+const MIN_TICK_INTERVAL_MS = 10;
+const MAX_TICK_INTERVAL_MS = 5000;
+let tickIntervalMs = 100;
+// Mirrors the room's autoplay flag from SIMULATION_STATUS, toggled with O
+let autoplay = false;
+
+// Input event targets and logical keys (keyboard and gamepad)
+const INPUT_TARGETS = {
+  GOL: 0,
+  MLP: 1,
+};
+
+const INPUT_KEYS = {
+  ArrowUp: 1,
+  ArrowDown: 2,
+  ArrowLeft: 3,
+  ArrowRight: 4,
+  " ": 5,
+  Enter: 6,
+};
+
+// Standard gamepad mapping: d-pad buttons 12-15, A = 0, B = 1
+const GAMEPAD_BUTTONS = {
+  12: INPUT_KEYS.ArrowUp,
+  13: INPUT_KEYS.ArrowDown,
+  14: INPUT_KEYS.ArrowLeft,
+  15: INPUT_KEYS.ArrowRight,
+  0: INPUT_KEYS[" "],
+  1: INPUT_KEYS.Enter,
 };
 
+let inputTarget = INPUT_TARGETS.GOL;
+
 // Canvas interaction handlers
 function getCellFromMouseEvent(event) {
   const rect = canvas.getBoundingClientRect();
@@ -113,6 +391,8 @@ function redrawCell(col, row) {
 // Mouse event handlers
 canvas.addEventListener("mousemove", (event) => {
   const { col, row } = getCellFromMouseEvent(event);
+  const cell = boardCell(col, row);
+  sendCursorMove(cell.col, cell.row);
 
   if (col !== hoveredCell.col || row !== hoveredCell.row) {
     // Clear previous hover highlight
@@ -140,7 +420,7 @@ canvas.addEventListener("mousemove", (event) => {
     // Only trigger if we've moved to a different cell
     if (col !== lastDraggedCell.col || row !== lastDraggedCell.row) {
       lastDraggedCell = { col, row };
-      onCellClick(col, row); // Trigger the same callback as click
+      queueStrokeCell(cell.col, cell.row);
     }
   }
 });
@@ -151,6 +431,8 @@ canvas.addEventListener("mouseleave", () => {
     clearHoverHighlight(hoveredCell.col, hoveredCell.row);
   }
   hoveredCell = { col: -1, row: -1 };
+  rectStart = null;
+  flushStroke();
   isDragging = false;
   lastDraggedCell = { col: -1, row: -1 };
 });
@@ -159,13 +441,25 @@ canvas.addEventListener("mousedown", (event) => {
   const { col, row } = getCellFromMouseEvent(event);
 
   if (col >= 0 && col < GRID_COLS && row >= 0 && row < GRID_ROWS) {
+    // Rectangle tools span from here to where the button is released
+    const cell = boardCell(col, row);
+    if (currentTool() === "fill" || currentTool() === "clear") {
+      rectStart = cell;
+      return;
+    }
     isDragging = true;
     lastDraggedCell = { col, row };
-    onCellClick(col, row); // Trigger callback for initial click
+    onCellClick(cell.col, cell.row); // Trigger callback for initial click
   }
 });
 
-canvas.addEventListener("mouseup", () => {
+canvas.addEventListener("mouseup", (event) => {
+  if (rectStart) {
+    const { col, row } = getCellFromMouseEvent(event);
+    sendRect(rectStart, boardCell(col, row));
+    rectStart = null;
+  }
+  flushStroke();
   isDragging = false;
   lastDraggedCell = { col: -1, row: -1 };
 });
@@ -174,37 +468,1047 @@ canvas.addEventListener("mouseup", () => {
 function onCellClick(x, y) {
   logMessage(">>", `Cell clicked: (${x}, ${y})`, "msg-out");
 
+  // A brush or sand material paints the click like any other stroke point
+  if (isBrushActive(currentBrush()) || currentMaterial() !== null) {
+    queueStrokeCell(x, y);
+    return;
+  }
+
+  if (currentTool() === "toggle") {
+    const payload = new Uint8Array(4);
+    const view = new DataView(payload.buffer);
+    view.setUint16(0, x, false); // big-endian
+    view.setUint16(2, y, false);
+    sendMessage(MESSAGE_TYPES.TOGGLE_CELL, payload);
+    logMessage(">>", `GOL: TOGGLE_CELL (${x}, ${y})`, "msg-out");
+    return;
+  }
+
+  // Immigration Game cells take the team color the server assigned
+  if (document.getElementById("team-cells").checked) {
+    const payload = new Uint8Array(4);
+    const view = new DataView(payload.buffer);
+    view.setUint16(0, x, false); // big-endian
+    view.setUint16(2, y, false);
+    sendMessage(MESSAGE_TYPES.AWAKEN_TEAM_CELL, payload);
+    logMessage(">>", `Sent team cell: (${x}, ${y})`, "msg-out");
+    return;
+  }
+
   // Add your custom logic here
   // For example, you could send a message to the server:
-  const payload = new Uint8Array([x, y]);
+  const payload = new Uint8Array(4);
+  const view = new DataView(payload.buffer);
+  view.setUint16(0, x, false); // big-endian
+  view.setUint16(2, y, false);
   sendMessage(MESSAGE_TYPES.REQUEST_PIXEL, payload);
   logMessage(">>", `Sent pixel: (${x}, ${y})`, "msg-out");
 }
 
-socket.addEventListener("message", (event) => {
-  const data = new Uint8Array(event.data);
-  const msg = decodeMessage(data);
+// Click tool picked in the edit tools, "" draws cells
+function currentTool() {
+  return document.getElementById("edit-tool").value;
+}
+
+// Fills or clears the rectangle between two corner cells, clamped to the grid
+function sendRect(start, end) {
+  const clamp = (value, max) => Math.min(Math.max(value, 0), max - 1);
+  const [x0, x1] = [clamp(start.col, boardSize.cols), clamp(end.col, boardSize.cols)];
+  const [y0, y1] = [clamp(start.row, boardSize.rows), clamp(end.row, boardSize.rows)];
+  const rect = [Math.min(x0, x1), Math.min(y0, y1), Math.abs(x1 - x0) + 1, Math.abs(y1 - y0) + 1];
+
+  const payload = new Uint8Array(8);
+  const view = new DataView(payload.buffer);
+  rect.forEach((value, i) => view.setUint16(i * 2, value, false)); // big-endian
+  const fill = currentTool() === "fill";
+  sendMessage(fill ? MESSAGE_TYPES.FILL_RECT : MESSAGE_TYPES.CLEAR_RECT, payload);
+  logMessage(">>", `GOL: ${fill ? "FILL_RECT" : "CLEAR_RECT"} ${rect.join(", ")}`, "msg-out");
+}
+
+// Brush the server applies around each stroke point
+function currentBrush() {
+  return {
+    radius: Number(document.getElementById("brush-radius").value) || 0,
+    shape: Number(document.getElementById("brush-shape").value),
+    density: Number(document.getElementById("brush-density").value) || 255,
+  };
+}
+
+// Sand material strokes spawn, null when strokes paint Game of Life cells
+function currentMaterial() {
+  const value = document.getElementById("sand-material").value;
+  return value === "" ? null : Number(value);
+}
+
+// A radius 0 brush at full density is a plain single-cell stroke
+function isBrushActive(brush) {
+  return brush.radius > 0 || brush.density < 255;
+}
+
+// Cells dragged over since the last flush, sent as one AWAKEN_CELLS_BATCH, BRUSH_STROKE or
+// SPAWN_MATERIAL per frame
+let pendingStroke = [];
+
+function queueStrokeCell(x, y) {
+  if (pendingStroke.length === 0) {
+    requestAnimationFrame(flushStroke);
+  }
+  pendingStroke.push([x, y]);
+}
+
+function flushStroke() {
+  if (pendingStroke.length === 0) return;
+
+  const brush = currentBrush();
+  const material = currentMaterial();
+  const offset = material !== null ? 1 : isBrushActive(brush) ? 3 : 0;
+  const payload = new Uint8Array(offset + pendingStroke.length * 4);
+  const view = new DataView(payload.buffer);
+  pendingStroke.forEach(([x, y], i) => {
+    view.setUint16(offset + i * 4, x, false); // big-endian
+    view.setUint16(offset + i * 4 + 2, y, false);
+  });
+  if (material !== null) {
+    payload[0] = material;
+    sendMessage(MESSAGE_TYPES.SPAWN_MATERIAL, payload);
+    logMessage(">>", `Spawned material on ${pendingStroke.length} cells`, "msg-out");
+  } else if (offset > 0) {
+    payload.set([brush.radius, brush.shape, brush.density]);
+    sendMessage(MESSAGE_TYPES.BRUSH_STROKE, payload);
+    logMessage(">>", `Sent brush stroke of ${pendingStroke.length} points`, "msg-out");
+  } else {
+    sendMessage(MESSAGE_TYPES.AWAKEN_CELLS_BATCH, payload);
+    logMessage(">>", `Sent stroke of ${pendingStroke.length} cells`, "msg-out");
+  }
+  pendingStroke = [];
+}
+
+// Message type ranges the server registered, sent right after connecting
+function handleCapabilities(payload) {
+  const decoder = new TextDecoder();
+  const ranges = [];
+  let offset = 1;
+  for (let i = 0; i < payload[0]; i++) {
+    const [start, end, ownerLength] = payload.slice(offset, offset + 3);
+    const owner = decoder.decode(payload.slice(offset + 3, offset + 3 + ownerLength));
+    ranges.push(`${owner} ${start}-${end}`);
+    offset += 3 + ownerLength;
+  }
+  logMessage("<<", `Server capabilities: ${ranges.join(", ")}`, "msg-in");
+}
+
+// What the server settled on in answer to our HELLO
+function handleHandshake(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const [version, codecs] = payload;
+  const width = view.getUint16(2, false);
+  const height = view.getUint16(4, false);
+  const intervalMs = view.getUint32(6, false);
+  const decoder = new TextDecoder();
+  const engines = [];
+  let offset = 11;
+  for (let i = 0; i < payload[10]; i++) {
+    const length = payload[offset];
+    engines.push(decoder.decode(payload.slice(offset + 1, offset + 1 + length)));
+    offset += 1 + length;
+  }
+  setBoardSize(width, height);
+  logMessage(
+    "<<",
+    `Handshake: protocol v${version}, ${codecs & CODEC_DEFLATE ? "deflate" : "uncompressed"}, ${width}x${height} at ${intervalMs}ms/tick, engines: ${engines.join(", ")}`,
+    "msg-in",
+  );
+}
+
+// Stepping rules sent on connect, lets the client check frames against GENERATION_HASH
+let predictionParams = null;
+let lastFrameBits = null;
+
+function handlePredictionParams(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const ruleLength = payload[8];
+  const offset = 9 + ruleLength;
+  predictionParams = {
+    rule: new TextDecoder().decode(payload.slice(9, offset)),
+    width: view.getUint16(offset, false),
+    height: view.getUint16(offset + 2, false),
+    edgeMode: payload[offset + 4],
+    hashAlgorithm: payload[offset + 5],
+  };
+  setBoardSize(predictionParams.width, predictionParams.height);
+  logMessage(
+    "<<",
+    `Prediction params: ${predictionParams.rule} ${predictionParams.width}x${predictionParams.height}`,
+    "msg-in",
+  );
+}
+
+// FNV-1a 32, matches protocol::generation_hash on the server
+function generationHash(bits) {
+  if (wasmCore) return wasmCore.generationHash(bits);
+  let hash = 0x811c9dc5;
+  for (const byte of bits) {
+    hash = Math.imul(hash ^ byte, 0x01000193) >>> 0;
+  }
+  return hash;
+}
+
+// Packs a DRAW_FRAME payload like GameOfLifeVecs::to_packed_bits, dead cells being white
+function packFrameBits(payload) {
+  const cellCount = (payload.length - 4) / 3;
+  const bits = new Uint8Array(Math.ceil(cellCount / 8));
+  for (let i = 0; i < cellCount; i++) {
+    const [r, g, b] = payload.slice(4 + i * 3, 7 + i * 3);
+    if (r !== 255 || g !== 255 || b !== 255) {
+      bits[i >> 3] |= 0x80 >> (i & 7);
+    }
+  }
+  return bits;
+}
+
+// Keeps the packed bits current when cells arrive as pixels, e.g. delta-only generations
+function updateFrameBit(pixel) {
+  if (!lastFrameBits || pixel.length !== 7) return;
+
+  const view = new DataView(pixel.buffer, pixel.byteOffset);
+  const i = view.getUint16(2, false) * GRID_COLS + view.getUint16(0, false);
+  if (i >= lastFrameBits.length * 8) return;
+  if (pixel[4] !== 255 || pixel[5] !== 255 || pixel[6] !== 255) {
+    lastFrameBits[i >> 3] |= 0x80 >> (i & 7);
+  } else {
+    lastFrameBits[i >> 3] &= ~(0x80 >> (i & 7));
+  }
+}
+
+function handleGenerationHash(payload) {
+  // A viewport frame isn't the board the hash is of
+  if (!predictionParams || !lastFrameBits || viewport) return;
+
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const generation = view.getBigUint64(0, false);
+  const hash = view.getUint32(8, false);
+  if (generationHash(lastFrameBits) !== hash) {
+    logMessage("!", `Frame of generation ${generation} doesn't match its hash`, "msg-error");
+  }
+}
+
+// Context for clients joining mid-run: seed, rule, generation, population curve, keyframe
+function handleJoinSummary(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const seed = view.getBigUint64(0, false);
+  const generation = view.getBigUint64(8, false);
+  const ruleLength = payload[16];
+  const rule = new TextDecoder().decode(payload.slice(17, 17 + ruleLength));
+  let offset = 17 + ruleLength;
+  const count = view.getUint16(offset, false);
+  offset += 2;
+  const populations = [];
+  for (let i = 0; i < count; i++) {
+    populations.push(view.getUint32(offset + i * 4, false));
+  }
+  offset += count * 4;
+
+  document.getElementById("summary").textContent =
+    `Rule ${rule}, seed ${seed}, generation ${generation}`;
+  drawPopulationChart(populations);
+  drawKeyframe(payload.slice(offset));
+}
+
+function drawPopulationChart(populations) {
+  const chart = document.getElementById("population-chart");
+  const chartCtx = chart.getContext("2d");
+  chartCtx.clearRect(0, 0, chart.width, chart.height);
+  if (populations.length < 2) return;
+
+  const max = Math.max(...populations, 1);
+  chartCtx.beginPath();
+  populations.forEach((population, i) => {
+    const x = (i / (populations.length - 1)) * chart.width;
+    const y = chart.height - (population / max) * chart.height;
+    if (i === 0) chartCtx.moveTo(x, y);
+    else chartCtx.lineTo(x, y);
+  });
+  chartCtx.stroke();
+}
+
+// Expands the bit-packed grid dump ("GOLB" header) into a frame for drawFrame
+function drawKeyframe(keyframe) {
+  const view = new DataView(keyframe.buffer, keyframe.byteOffset);
+  const width = view.getUint16(5, false);
+  const height = view.getUint16(7, false);
+  const bits = keyframe.slice(17);
+
+  const frame = new Uint8Array(4 + width * height * 3);
+  const frameView = new DataView(frame.buffer);
+  frameView.setUint16(0, width, false);
+  frameView.setUint16(2, height, false);
+  for (let i = 0; i < width * height; i++) {
+    const alive = (bits[i >> 3] >> (7 - (i & 7))) & 1;
+    frame.fill(alive ? 0 : 255, 4 + i * 3, 4 + i * 3 + 3);
+  }
+  drawFrame(frame);
+}
+
+// Header flag of payloads the server deflated, sent once HELLO says we can decode them
+const FLAG_DEFLATE = 0x02;
+const CODEC_DEFLATE = 0x01;
+
+async function inflate(payload) {
+  const stream = new Blob([payload]).stream().pipeThrough(new DecompressionStream("deflate"));
+  return new Uint8Array(await new Response(stream).arrayBuffer());
+}
+
+// Messages are handled in arrival order, a deflated frame holds back the ones after it
+let pendingMessages = Promise.resolve();
+
+// Header flag of broadcast messages whose payload starts with their topic and sequence number
+const FLAG_SEQUENCED = 0x04;
+// Last sequence number seen per topic, each topic is numbered on its own
+const lastSequences = new Map();
+
+// The server skips frames for clients that can't keep up, sending the next one whole
+const KEYFRAME_TYPES = new Set([MESSAGE_TYPES.DRAW_FRAME, MESSAGE_TYPES.DRAW_TILES]);
+
+// Strips the sequence number, asking for a keyframe when messages went missing
+function checkSequence(msg) {
+  if (!(msg.flags & FLAG_SEQUENCED)) {
+    // A keyframe sent to this client alone covers whatever it missed before
+    if (KEYFRAME_TYPES.has(msg.msg_type)) lastSequences.clear();
+    return;
+  }
+  const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+  const topic = view.getUint8(0);
+  const sequence = view.getUint32(1, false);
+  msg.payload = msg.payload.slice(5);
+  const lastSequence = lastSequences.get(topic);
+  const gap = lastSequence !== undefined && sequence !== ((lastSequence + 1) >>> 0);
+  if (gap && !KEYFRAME_TYPES.has(msg.msg_type)) {
+    const missed = (sequence - lastSequence - 1) >>> 0;
+    logMessage("!", `Missed ${missed} messages, resynchronizing`, "msg-error");
+    sendMessage(MESSAGE_TYPES.RESYNC_REQUEST, new Uint8Array());
+  }
+  lastSequences.set(topic, sequence);
+}
+
+socket.addEventListener("message", (event) => receiveFrame(new Uint8Array(event.data)));
+
+function receiveFrame(frame) {
+  const msg = decodeMessage(frame);
+  checkSequence(msg);
+  pendingMessages = pendingMessages
+    .then(async () => {
+      if (msg.flags & FLAG_DEFLATE) msg.payload = await inflate(msg.payload);
+      handleMessage(msg);
+    })
+    .catch((e) => logMessage("!", `Failed to handle message: ${e}`, "msg-error"));
+}
 
+function handleMessage(msg) {
   if (msg.msg_type === MESSAGE_TYPES.DRAW_PIXEL) {
     logMessage("<<", `Received pixel (${msg.payload.length} bytes)`, "msg-in");
     drawCell(msg.payload);
+    updateFrameBit(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.DRAW_PIXELS_BATCH) {
+    const count = new DataView(msg.payload.buffer, msg.payload.byteOffset).getUint16(0, false);
+    logMessage("<<", `Received ${count} pixels`, "msg-in");
+    for (let i = 0; i < count; i++) {
+      const pixel = msg.payload.slice(2 + i * 7, 9 + i * 7);
+      drawCell(pixel);
+      updateFrameBit(pixel);
+    }
   } else if (msg.msg_type === MESSAGE_TYPES.DRAW_FRAME) {
     logMessage("<<", `Received frame (${msg.payload.length} bytes)`, "msg-in");
     drawFrame(msg.payload);
+    lastFrameBits = packFrameBits(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.DRAW_REGION) {
+    drawRegion(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.DRAW_TILES) {
+    drawTiles(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.PREDICTION_PARAMS) {
+    handlePredictionParams(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.GENERATION_HASH) {
+    handleGenerationHash(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.SIMULATION_STATUS) {
+    handleSimulationStatus(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.ROOM_OCCUPANCY) {
+    const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+    const viewers = view.getUint16(0, false);
+    const editors = view.getUint16(2, false);
+    document.getElementById("occupancy").textContent =
+      `${viewers} watching, ${editors} editing`;
+  } else if (msg.msg_type === MESSAGE_TYPES.CAPABILITIES) {
+    handleCapabilities(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.HANDSHAKE_ACCEPTED) {
+    handleHandshake(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.JOIN_SUMMARY) {
+    handleJoinSummary(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.SIMULATION_RESET) {
+    const reason = new TextDecoder().decode(msg.payload);
+    logMessage("!", `Simulation reset: ${reason}`, "msg-error");
+  } else if (msg.msg_type === MESSAGE_TYPES.ERROR) {
+    const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+    const code = view.getUint16(0, false);
+    const detail = new TextDecoder().decode(msg.payload.subarray(2));
+    logMessage("!", `Error ${code}: ${detail}`, "msg-error");
+  } else if (msg.msg_type === MESSAGE_TYPES.PADDLE_ASSIGNED) {
+    // 255 when both paddles are taken
+    paddleSide = msg.payload[0] === 255 ? null : msg.payload[0];
+    const text =
+      paddleSide === null
+        ? "Both paddles are taken"
+        : `Playing the ${paddleSide === 0 ? "left" : "right"} paddle`;
+    logMessage("<<", text, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.CLIENT_IDENTITY) {
+    myConnectionId = new TextDecoder().decode(msg.payload);
+    renderPresence();
+  } else if (msg.msg_type === MESSAGE_TYPES.CLIENT_JOINED) {
+    const [member] = decodePresence(msg.payload, 0);
+    presence.set(member.id, member.nickname);
+    renderPresence();
+  } else if (msg.msg_type === MESSAGE_TYPES.CLIENT_LEFT) {
+    const id = new TextDecoder().decode(msg.payload);
+    presence.delete(id);
+    removeRemoteCursor(id);
+    renderPresence();
+  } else if (msg.msg_type === MESSAGE_TYPES.ADMIN_STATUS) {
+    isAdmin = msg.payload[0] === 1;
+    document.getElementById("auth-form").hidden = isAdmin;
+    document.getElementById("kick-form").hidden = !isAdmin;
+    document.getElementById("save-form").hidden = !isAdmin;
+    document.getElementById("schedule-form").hidden = !isAdmin;
+    document.getElementById("gol-engine").hidden = !isAdmin;
+    document.getElementById("restore-checkpoint").hidden = !isAdmin;
+    renderPresence();
+  } else if (msg.msg_type === MESSAGE_TYPES.CHAT_MESSAGE) {
+    const [sender, offset] = decodePresence(msg.payload, 0);
+    const text = new TextDecoder().decode(msg.payload.subarray(offset));
+    const name = sender.nickname || `guest-${sender.id.slice(0, 4)}`;
+    const style = sender.id === myConnectionId ? "msg-out" : "msg-in";
+    logMessage("💬", `${name}: ${text}`, style);
+  } else if (msg.msg_type === MESSAGE_TYPES.STATE_SAVED) {
+    const generation = new DataView(msg.payload.buffer, msg.payload.byteOffset).getBigUint64(0, false);
+    const name = new TextDecoder().decode(msg.payload.subarray(8));
+    logMessage("<<", `Saved generation ${generation} as ${name}`, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.SCHEDULED_EVENT) {
+    const action = new TextDecoder().decode(msg.payload);
+    logMessage("⏰", `Scheduled event: ${action}`, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.SCHEDULE_LIST) {
+    handleScheduleList(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.MODE_CHANGED) {
+    const mode = MODES[msg.payload[0]];
+    if (mode && mode !== activeMode) {
+      activeMode = mode;
+      logMessage("<<", `The room shows ${mode}`, "msg-in");
+    }
+  } else if (msg.msg_type === MESSAGE_TYPES.ROOM_LIST) {
+    handleRoomList(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.CHECKPOINT_LIST) {
+    handleCheckpointList(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.SERVER_STATS) {
+    handleServerStats(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.MLP_PAINTING_LIST) {
+    handlePaintingList(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.CURSOR_MOVED) {
+    drawRemoteCursor(msg.payload);
+  } else if (msg.msg_type === MESSAGE_TYPES.PRESENCE_LIST) {
+    const count = new DataView(msg.payload.buffer, msg.payload.byteOffset).getUint16(0, false);
+    presence.clear();
+    let offset = 2;
+    for (let i = 0; i < count; i++) {
+      const [member, next] = decodePresence(msg.payload, offset);
+      presence.set(member.id, member.nickname);
+      offset = next;
+    }
+    renderPresence();
+  } else if (msg.msg_type === MESSAGE_TYPES.TEAM_ASSIGNED) {
+    const [team, r, g, b] = msg.payload;
+    const teamLabel = document.getElementById("team");
+    teamLabel.textContent = team === 0 ? "Team red" : "Team blue";
+    teamLabel.style.color = `rgb(${r}, ${g}, ${b})`;
+    logMessage("<<", `Immigration Game: ${teamLabel.textContent}`, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.SIMULATION_STABLE) {
+    const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+    const generation = Number(view.getBigUint64(0, false));
+    const period = msg.payload[8];
+    const text =
+      period === 1
+        ? `Still life at generation ${generation}`
+        : `Period ${period} oscillator at generation ${generation}`;
+    logMessage("<<", text, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.SOUP_FOUND) {
+    const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+    const value = view.getUint32(1, false);
+    const rle = new TextDecoder().decode(msg.payload.subarray(5));
+    const text =
+      msg.payload[0] === 0
+        ? `Soup search: methuselah lasting ${value} generations`
+        : `Soup search: period ${value} spaceship`;
+    logMessage("<<", text, "msg-in");
+    logMessage("<<", rle, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.BOT_STATUS) {
+    runningBots = new Set(msg.payload.subarray(1, 1 + msg.payload[0]));
+    const names = [...runningBots].map((id) => BOT_NAMES[id] ?? `bot ${id}`);
+    const text = names.length ? names.join(", ") : "none";
+    logMessage("<<", `Bots running: ${text}`, "msg-in");
+  } else if (msg.msg_type === MESSAGE_TYPES.PONG_SCORE) {
+    const view = new DataView(msg.payload.buffer, msg.payload.byteOffset);
+    const left = view.getUint16(0, false);
+    const right = view.getUint16(2, false);
+    logMessage("<<", `Pong score: ${left} - ${right}`, "msg-in");
   } else {
     const text = new TextDecoder().decode(msg.payload);
     logMessage("<<", text, "msg-in");
   }
-});
+}
 
 document.getElementById("msg-form").addEventListener("submit", (e) => {
   e.preventDefault();
   const input = document.getElementById("msg-input");
-  const text = input.value;
-  const payload = new TextEncoder().encode(text);
-  sendMessage(MESSAGE_TYPES.HELLO, payload);
-  logMessage(">>", text, "msg-out");
+  const text = input.value.trim();
+  if (!text) return;
+  // Logged when the server broadcasts it back, attributed like everyone else's
+  sendMessage(MESSAGE_TYPES.CHAT, new TextEncoder().encode(text));
+  input.value = "";
+});
+
+document.getElementById("rle-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const x = Number(document.getElementById("rle-x").value) || 0;
+  const y = Number(document.getElementById("rle-y").value) || 0;
+  const rle = new TextEncoder().encode(document.getElementById("rle-input").value);
+
+  const payload = new Uint8Array(4 + rle.length);
+  const view = new DataView(payload.buffer);
+  view.setUint16(0, x, false); // big-endian
+  view.setUint16(2, y, false);
+  payload.set(rle, 4);
+
+  sendMessage(MESSAGE_TYPES.LOAD_PATTERN, payload);
+  logMessage(">>", `GOL: LOAD_PATTERN at (${x}, ${y})`, "msg-out");
+});
+
+document.getElementById("stamp-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const select = document.getElementById("stamp-pattern");
+  const x = Number(document.getElementById("stamp-x").value) || 0;
+  const y = Number(document.getElementById("stamp-y").value) || 0;
+
+  const payload = new Uint8Array(5);
+  const view = new DataView(payload.buffer);
+  payload[0] = Number(select.value);
+  view.setUint16(1, x, false); // big-endian
+  view.setUint16(3, y, false);
+
+  sendMessage(MESSAGE_TYPES.STAMP_PATTERN, payload);
+  logMessage(">>", `GOL: STAMP_PATTERN ${select.selectedOptions[0].text} at (${x}, ${y})`, "msg-out");
+});
+
+// Zoom 0 goes back to the whole board
+document.getElementById("viewport-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const [x, y, zoom] = ["viewport-x", "viewport-y", "viewport-zoom"].map(
+    (id) => Number(document.getElementById(id).value) || 0,
+  );
+  const tiles = document.getElementById("viewport-tiles").checked;
+
+  const payload = new Uint8Array(10);
+  const view = new DataView(payload.buffer);
+  view.setUint16(0, x, false); // big-endian
+  view.setUint16(2, y, false);
+  payload[4] = zoom;
+  view.setUint16(5, VIEWPORT_FRAME_SIZE, false);
+  view.setUint16(7, VIEWPORT_FRAME_SIZE, false);
+  payload[9] = tiles ? 1 : 0;
+
+  // The server forgets the tiles it sent on a new zoom, or when tiles start
+  const previous = viewport;
+  if (!previous || !previous.tiles || previous.zoom !== zoom) tileStore.clear();
+  viewport = zoom > 0 ? { x, y, zoom, tiles } : null;
+  if (viewport) {
+    setGridSize(VIEWPORT_FRAME_SIZE, VIEWPORT_FRAME_SIZE);
+  } else {
+    setGridSize(boardSize.cols, boardSize.rows);
+  }
+  // Tiles already here fill the panned view right away
+  if (viewport && tiles) {
+    ctx.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
+    cellColors.clear();
+    for (const key of tileStore.keys()) {
+      const [column, row] = key.split(",").map(Number);
+      drawTile(column, row);
+    }
+  }
+  sendMessage(MESSAGE_TYPES.SET_VIEWPORT, payload);
+  logMessage(
+    ">>",
+    viewport ? `SET_VIEWPORT (${x}, ${y}) zoom ${zoom}${tiles ? " in tiles" : ""}` : "SET_VIEWPORT whole board",
+    "msg-out",
+  );
+});
+
+// Who is connected to the room, connection id to nickname (empty without one)
+const presence = new Map();
+let myConnectionId = null;
+
+// Presence entry: id and nickname, each prefixed with its byte length. Returns the member
+// and the offset after it.
+function decodePresence(payload, offset) {
+  const decoder = new TextDecoder();
+  const idLength = payload[offset];
+  const id = decoder.decode(payload.slice(offset + 1, offset + 1 + idLength));
+  offset += 1 + idLength;
+  const nicknameLength = payload[offset];
+  const nickname = decoder.decode(payload.slice(offset + 1, offset + 1 + nicknameLength));
+  return [{ id, nickname }, offset + 1 + nicknameLength];
+}
+
+function renderPresence() {
+  const names = [...presence].map(([id, nickname]) => {
+    const name = nickname || `guest-${id.slice(0, 4)}`;
+    return id === myConnectionId ? `${name} (you)` : name;
+  });
+  document.getElementById("presence").textContent = `Connected: ${names.join(", ")}`;
+
+  const kickSelect = document.getElementById("kick-select");
+  kickSelect.replaceChildren(
+    ...[...presence]
+      .filter(([id]) => id !== myConnectionId)
+      .map(([id, nickname]) => new Option(nickname || `guest-${id.slice(0, 4)}`, id)),
+  );
+}
+
+// Whether this connection may kill all cells, change speeds, kick clients and save boards
+let isAdmin = false;
+
+// A wrong token closes the connection, reload to try again
+document.getElementById("auth-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const input = document.getElementById("auth-input");
+  sendMessage(MESSAGE_TYPES.AUTH, new TextEncoder().encode(input.value));
   input.value = "";
+  logMessage(">>", "AUTH", "msg-out");
+});
+
+document.getElementById("kick-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const id = document.getElementById("kick-select").value;
+  if (!id) return;
+  sendMessage(MESSAGE_TYPES.KICK_CLIENT, new TextEncoder().encode(id));
+  logMessage(">>", `KICK_CLIENT ${id}`, "msg-out");
+});
+
+document.getElementById("gol-engine").addEventListener("change", (e) => {
+  const select = e.target;
+  sendMessage(MESSAGE_TYPES.SELECT_ENGINE, new Uint8Array([Number(select.value)]));
+  logMessage(">>", `SELECT_ENGINE ${select.selectedOptions[0].text}`, "msg-out");
+});
+
+// The submit button that was clicked picks SAVE_STATE or LOAD_STATE
+document.getElementById("save-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const name = document.getElementById("save-name").value.trim();
+  if (!name) return;
+  const type = e.submitter?.id === "load-state" ? "LOAD_STATE" : "SAVE_STATE";
+  sendMessage(MESSAGE_TYPES[type], new TextEncoder().encode(name));
+  logMessage(">>", `${type} ${name}`, "msg-out");
+});
+
+// The submit button that was clicked picks ADD_SCHEDULE_ENTRY, REMOVE_SCHEDULE_ENTRY or
+// LIST_SCHEDULE
+document.getElementById("schedule-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  if (e.submitter?.id === "add-schedule-entry") {
+    const line = document.getElementById("schedule-line").value.trim();
+    if (!line) return;
+    sendMessage(MESSAGE_TYPES.ADD_SCHEDULE_ENTRY, new TextEncoder().encode(line));
+    logMessage(">>", `ADD_SCHEDULE_ENTRY ${line}`, "msg-out");
+  } else if (e.submitter?.id === "remove-schedule-entry") {
+    const select = document.getElementById("schedule-select");
+    if (select.selectedIndex < 0) return;
+    const payload = new Uint8Array(2);
+    new DataView(payload.buffer).setUint16(0, select.selectedIndex, false); // big-endian
+    sendMessage(MESSAGE_TYPES.REMOVE_SCHEDULE_ENTRY, payload);
+    logMessage(">>", `REMOVE_SCHEDULE_ENTRY ${select.value}`, "msg-out");
+  } else {
+    sendMessage(MESSAGE_TYPES.LIST_SCHEDULE, new Uint8Array());
+    logMessage(">>", "LIST_SCHEDULE", "msg-out");
+  }
+});
+
+// The server's schedule, one entry per line in the schedule file's syntax
+function handleScheduleList(payload) {
+  const text = new TextDecoder().decode(payload);
+  const entries = text ? text.split("\n") : [];
+  const select = document.getElementById("schedule-select");
+  select.replaceChildren(
+    ...entries.map((entry) => {
+      const option = document.createElement("option");
+      option.textContent = entry;
+      return option;
+    }),
+  );
+  logMessage("<<", `Schedule: ${entries.length ? entries.join("; ") : "empty"}`, "msg-in");
+}
+
+document.getElementById("new-room-mode").replaceChildren(
+  ...MODES.map((mode, id) => {
+    const option = document.createElement("option");
+    option.value = id;
+    option.textContent = mode;
+    return option;
+  }),
+);
+
+// The submit button that was clicked picks CREATE_ROOM or LIST_ROOMS, both answered with
+// ROOM_LIST
+document.getElementById("lobby-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  if (e.submitter?.id !== "create-room") {
+    sendMessage(MESSAGE_TYPES.LIST_ROOMS, new Uint8Array());
+    logMessage(">>", "LIST_ROOMS", "msg-out");
+    return;
+  }
+  const id = new TextEncoder().encode(document.getElementById("new-room-id").value.trim());
+  if (!id.length) return;
+  const password = new TextEncoder().encode(document.getElementById("new-room-password").value);
+  // Id, 2 bytes width, 2 bytes height, 4 bytes tick interval, flags, mode, password. Zero
+  // sizes and interval take the server's.
+  const payload = new Uint8Array(1 + id.length + 10 + password.length);
+  const view = new DataView(payload.buffer);
+  payload[0] = id.length;
+  payload.set(id, 1);
+  const offset = 1 + id.length;
+  view.setUint16(offset, Number(document.getElementById("new-room-width").value) || 0, false);
+  view.setUint16(offset + 2, Number(document.getElementById("new-room-height").value) || 0, false);
+  view.setUint32(offset + 4, 0, false);
+  payload[offset + 8] = document.getElementById("new-room-autoplay").checked ? 1 : 0;
+  payload[offset + 9] = Number(document.getElementById("new-room-mode").value);
+  payload.set(password, offset + 10);
+  sendMessage(MESSAGE_TYPES.CREATE_ROOM, payload);
+  logMessage(">>", `CREATE_ROOM ${new TextDecoder().decode(id)}`, "msg-out");
+});
+
+// Public rooms with their mode, clients and canvas size, each a link that joins it
+function handleRoomList(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const count = view.getUint16(0, false);
+  const items = [];
+  let offset = 2;
+  for (let i = 0; i < count; i++) {
+    const length = payload[offset];
+    const id = new TextDecoder().decode(payload.subarray(offset + 1, offset + 1 + length));
+    offset += 1 + length;
+    const mode = MODES[payload[offset]] ?? "unknown";
+    const clients = view.getUint16(offset + 1, false);
+    const width = view.getUint16(offset + 3, false);
+    const height = view.getUint16(offset + 5, false);
+    offset += 7;
+
+    const link = document.createElement("a");
+    link.href = `?room=${encodeURIComponent(id)}`;
+    link.textContent = id;
+    const item = document.createElement("li");
+    item.append(link, ` ${mode}, ${clients} clients, ${width}x${height}`);
+    items.push(item);
+  }
+  document.getElementById("room-list").replaceChildren(...items);
+  logMessage("<<", `${count} public rooms`, "msg-in");
+}
+
+// Generations the room has checkpoints of, newest first
+function handleCheckpointList(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const count = view.getUint16(0, false);
+  const generations = [];
+  for (let i = 0; i < count; i++) {
+    generations.push(view.getBigUint64(2 + i * 8, false));
+  }
+  document.getElementById("checkpoint-select").replaceChildren(
+    ...generations.map((generation) => {
+      const option = document.createElement("option");
+      option.value = generation.toString();
+      option.textContent = `Generation ${generation}`;
+      return option;
+    }),
+  );
+  logMessage("<<", `${count} checkpoints`, "msg-in");
+}
+
+// Connections, uptime, lag and messages sent across the server, with public room populations
+function handleServerStats(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const connections = view.getUint32(0, false);
+  const uptime = view.getBigUint64(4, false);
+  const lagEvents = view.getBigUint64(12, false);
+  const laggedMessages = view.getBigUint64(20, false);
+  const framesSent = view.getBigUint64(28, false);
+  const roomCount = view.getUint16(36, false);
+  const rooms = [];
+  let offset = 38;
+  for (let i = 0; i < roomCount; i++) {
+    const length = payload[offset];
+    const room = new TextDecoder().decode(payload.subarray(offset + 1, offset + 1 + length));
+    offset += 1 + length;
+    rooms.push(`${room}: ${view.getUint32(offset, false)}`);
+    offset += 4;
+  }
+  logMessage(
+    "<<",
+    `Server: ${connections} connections, up ${uptime}s, ${framesSent} messages sent, ` +
+      `${lagEvents} lags skipping ${laggedMessages} messages. Populations: ${rooms.join(", ")}`,
+    "msg-in",
+  );
+}
+
+document.getElementById("get-server-stats").addEventListener("click", () => {
+  sendMessage(MESSAGE_TYPES.GET_SERVER_STATS, new Uint8Array());
+  logMessage(">>", "GET_SERVER_STATS", "msg-out");
+});
+
+document.getElementById("list-checkpoints").addEventListener("click", () => {
+  sendMessage(MESSAGE_TYPES.LIST_CHECKPOINTS, new Uint8Array());
+  logMessage(">>", "LIST_CHECKPOINTS", "msg-out");
+});
+
+document.getElementById("checkpoint-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const generation = document.getElementById("checkpoint-select").value;
+  if (!generation) return;
+  const payload = new Uint8Array(8);
+  new DataView(payload.buffer).setBigUint64(0, BigInt(generation), false);
+  sendMessage(MESSAGE_TYPES.RESTORE_CHECKPOINT, payload);
+  logMessage(">>", `RESTORE_CHECKPOINT ${generation}`, "msg-out");
+});
+
+// The room's paintings and their progress, the one shown selected
+function handlePaintingList(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const selected = view.getUint16(0, false);
+  const count = view.getUint16(2, false);
+  const options = [];
+  for (let i = 0; i < count; i++) {
+    const id = view.getUint16(4 + i * 3, false);
+    const option = document.createElement("option");
+    option.value = id.toString();
+    option.textContent = `Painting ${id} (${payload[6 + i * 3]}%)`;
+    option.selected = id === selected;
+    options.push(option);
+  }
+  document.getElementById("painting-select").replaceChildren(...options);
+  logMessage("<<", `${count} paintings, showing ${selected}`, "msg-in");
+}
+
+function listPaintings() {
+  sendMessage(MESSAGE_TYPES.LIST_MLP_PAINTINGS, new Uint8Array());
+  logMessage(">>", "LIST_MLP_PAINTINGS", "msg-out");
+}
+
+document.getElementById("list-paintings").addEventListener("click", listPaintings);
+
+document.getElementById("add-painting").addEventListener("click", () => {
+  sendMessage(MESSAGE_TYPES.ADD_MLP_PAINTING, new Uint8Array());
+  logMessage(">>", "ADD_MLP_PAINTING", "msg-out");
+  listPaintings();
+});
+
+// The submit button that was clicked picks SELECT_MLP_PAINTING or DELETE_MLP_PAINTING
+document.getElementById("painting-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const id = document.getElementById("painting-select").value;
+  if (!id) return;
+  const type = e.submitter?.id === "delete-painting" ? "DELETE_MLP_PAINTING" : "SELECT_MLP_PAINTING";
+  const payload = new Uint8Array(2);
+  new DataView(payload.buffer).setUint16(0, Number(id), false);
+  sendMessage(MESSAGE_TYPES[type], payload);
+  logMessage(">>", `${type} ${id}`, "msg-out");
+  listPaintings();
+});
+
+// Other clients' cursors over the canvas, connection id to its marker
+const remoteCursors = new Map();
+// The server drops cursor moves closer together than this, so don't send them
+const CURSOR_MIN_INTERVAL_MS = 50;
+// A cursor that stopped moving fades out, its client may have left the canvas
+const CURSOR_IDLE_MS = 5000;
+let lastCursorSent = { col: -1, row: -1, at: 0 };
+
+function sendCursorMove(col, row) {
+  if (col < 0 || col >= GRID_COLS || row < 0 || row >= GRID_ROWS) return;
+  const now = performance.now();
+  if (col === lastCursorSent.col && row === lastCursorSent.row) return;
+  if (now - lastCursorSent.at < CURSOR_MIN_INTERVAL_MS) return;
+  lastCursorSent = { col, row, at: now };
+
+  const payload = new Uint8Array(4);
+  const view = new DataView(payload.buffer);
+  view.setUint16(0, col, false);
+  view.setUint16(2, row, false);
+  sendMessage(MESSAGE_TYPES.CURSOR_MOVE, payload);
+}
+
+// Cursor moved payload: x, y (u16 each), r, g, b, then the mover's connection id
+function drawRemoteCursor(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const col = view.getUint16(0, false);
+  const row = view.getUint16(2, false);
+  const [r, g, b] = payload.subarray(4, 7);
+  const id = new TextDecoder().decode(payload.subarray(7));
+
+  let cursor = remoteCursors.get(id);
+  if (!cursor) {
+    const marker = document.createElement("div");
+    marker.className = "remote-cursor";
+    document.getElementById("cursors").appendChild(marker);
+    cursor = { marker, idleTimer: null };
+    remoteCursors.set(id, cursor);
+  }
+  cursor.marker.style.left = `${((col + 0.5) * CELL_SIZE * 100) / CANVAS_WIDTH}%`;
+  cursor.marker.style.top = `${((row + 0.5) * CELL_SIZE * 100) / CANVAS_HEIGHT}%`;
+  cursor.marker.style.borderColor = `rgb(${r}, ${g}, ${b})`;
+  cursor.marker.style.color = `rgb(${r}, ${g}, ${b})`;
+  cursor.marker.textContent = presence.get(id) || `guest-${id.slice(0, 4)}`;
+  cursor.marker.hidden = false;
+  clearTimeout(cursor.idleTimer);
+  cursor.idleTimer = setTimeout(() => (cursor.marker.hidden = true), CURSOR_IDLE_MS);
+}
+
+function removeRemoteCursor(id) {
+  const cursor = remoteCursors.get(id);
+  if (!cursor) return;
+  clearTimeout(cursor.idleTimer);
+  cursor.marker.remove();
+  remoteCursors.delete(id);
+}
+
+document.getElementById("nickname-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const nickname = document.getElementById("nickname-input").value;
+  sendMessage(MESSAGE_TYPES.SET_NICKNAME, new TextEncoder().encode(nickname));
+  logMessage(">>", `SET_NICKNAME ${nickname}`, "msg-out");
+});
+
+document.getElementById("seed-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const seed = BigInt(document.getElementById("seed-input").value || 0);
+
+  const payload = new Uint8Array(8);
+  new DataView(payload.buffer).setBigUint64(0, seed, false); // big-endian
+  sendMessage(MESSAGE_TYPES.SET_SEED, payload);
+  logMessage(">>", `SET_SEED ${seed}`, "msg-out");
+});
+
+// The submit button that was clicked paints a number of strokes or up to a percentage
+document.getElementById("mlp-advance-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  if (e.submitter?.id === "advance-percent") {
+    const percent = Number(document.getElementById("mlp-percent").value) || 0;
+    sendMessage(MESSAGE_TYPES.ADVANCE_MLP_PAINTING_TO, new Uint8Array([percent]));
+    logMessage(">>", `MLP: ADVANCE_MLP_PAINTING_TO ${percent}%`, "msg-out");
+    return;
+  }
+  const strokes = Number(document.getElementById("mlp-strokes").value) || 1;
+  const payload = new Uint8Array(2);
+  new DataView(payload.buffer).setUint16(0, strokes, false); // big-endian
+  sendMessage(MESSAGE_TYPES.ADVANCE_MLP_PAINTING, payload);
+  logMessage(">>", `MLP: ADVANCE_MLP_PAINTING ${strokes} strokes`, "msg-out");
+});
+
+// Style: 0 plain, 1 quantized, 2 ordered dithering, 3 Floyd-Steinberg, with a palette size
+document.getElementById("mlp-style-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const style = Number(document.getElementById("mlp-style").value);
+  const colors = Number(document.getElementById("mlp-colors").value) || 2;
+  sendMessage(MESSAGE_TYPES.SET_MLP_STYLE, new Uint8Array([style, colors]));
+  logMessage(">>", `MLP: SET_MLP_STYLE ${style} with ${colors} colors`, "msg-out");
+});
+
+// The server scales the PNG or JPEG to the canvas and paints it stroke by stroke
+document.getElementById("image-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const file = document.getElementById("image-file").files[0];
+  if (!file) return;
+  const payload = new Uint8Array(await file.arrayBuffer());
+  sendMessage(MESSAGE_TYPES.UPLOAD_MLP_IMAGE, payload);
+  logMessage(">>", `UPLOAD_MLP_IMAGE ${file.name} (${payload.length} bytes)`, "msg-out");
+});
+
+document.getElementById("gol-image-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const file = document.getElementById("gol-image-file").files[0];
+  if (!file) return;
+  const threshold = Number(document.getElementById("gol-image-threshold").value);
+  const image = new Uint8Array(await file.arrayBuffer());
+  const payload = new Uint8Array(1 + image.length);
+  payload[0] = threshold;
+  payload.set(image, 1);
+  sendMessage(MESSAGE_TYPES.SEED_FROM_IMAGE, payload);
+  logMessage(">>", `GOL: SEED_GOL_FROM_IMAGE ${file.name} (threshold ${threshold})`, "msg-out");
+});
+
+document.getElementById("reaction-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const feed = Number(document.getElementById("reaction-feed").value);
+  const kill = Number(document.getElementById("reaction-kill").value);
+
+  const payload = new Uint8Array(8);
+  const view = new DataView(payload.buffer);
+  view.setFloat32(0, feed, false); // big-endian
+  view.setFloat32(4, kill, false);
+  sendMessage(MESSAGE_TYPES.SET_REACTION_RATES, payload);
+  logMessage(">>", `SET_REACTION_RATES feed ${feed}, kill ${kill}`, "msg-out");
+});
+
+document.getElementById("boids-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const x = Number(document.getElementById("boids-x").value) || 0;
+  const y = Number(document.getElementById("boids-y").value) || 0;
+  const count = Number(document.getElementById("boids-count").value) || 1;
+
+  const payload = new Uint8Array(5);
+  const view = new DataView(payload.buffer);
+  view.setUint16(0, x, false); // big-endian
+  view.setUint16(2, y, false);
+  payload[4] = count;
+  sendMessage(MESSAGE_TYPES.SPAWN_BOIDS, payload);
+  logMessage(">>", `SPAWN_BOIDS ${count} at (${x}, ${y})`, "msg-out");
+});
+
+document.getElementById("boid-weights-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const weights = ["boid-separation", "boid-alignment", "boid-cohesion"].map((id) =>
+    Number(document.getElementById(id).value),
+  );
+
+  const payload = new Uint8Array(12);
+  const view = new DataView(payload.buffer);
+  weights.forEach((weight, i) => view.setFloat32(i * 4, weight, false)); // big-endian
+  sendMessage(MESSAGE_TYPES.SET_BOID_WEIGHTS, payload);
+  logMessage(">>", `SET_BOID_WEIGHTS ${weights.join(", ")}`, "msg-out");
+});
+
+document.getElementById("color-scheme").addEventListener("change", (e) => {
+  const select = e.target;
+  sendMessage(MESSAGE_TYPES.SET_COLOR_SCHEME, new Uint8Array([Number(select.value)]));
+  logMessage(">>", `GOL: SET_COLOR_SCHEME ${select.selectedOptions[0].text}`, "msg-out");
+});
+
+document.getElementById("gol-hybrid").addEventListener("change", (e) => {
+  const hybrid = e.target.checked;
+  sendMessage(MESSAGE_TYPES.SET_GOL_HYBRID, new Uint8Array([hybrid ? 1 : 0]));
+  logMessage(">>", `GOL: SET_GOL_HYBRID ${hybrid ? "on" : "off"}`, "msg-out");
+});
+
+document.querySelectorAll("#subscriptions input[data-topic]").forEach((checkbox) => {
+  checkbox.addEventListener("change", () => {
+    const topic = TOPICS[checkbox.dataset.topic];
+    const msgType = checkbox.checked ? MESSAGE_TYPES.SUBSCRIBE : MESSAGE_TYPES.UNSUBSCRIBE;
+    sendMessage(msgType, new Uint8Array([topic]));
+    logMessage(
+      ">>",
+      `${checkbox.checked ? "SUBSCRIBE" : "UNSUBSCRIBE"} ${checkbox.dataset.topic}`,
+      "msg-out"
+    );
+  });
 });
 
 const gol = {
@@ -232,18 +1536,202 @@ const gol = {
     sendMessage(MESSAGE_TYPES.STEP_GENERATION, new Uint8Array());
     logMessage(">>", "GOL: STEP_GENERATION", "msg-out");
   },
+
+  seed_from_painting: () => {
+    sendMessage(MESSAGE_TYPES.SEED_FROM_PAINTING, new Uint8Array());
+    logMessage(">>", "GOL: SEED_FROM_PAINTING", "msg-out");
+  },
+
+  undo: () => {
+    sendMessage(MESSAGE_TYPES.UNDO, new Uint8Array());
+    logMessage(">>", "GOL: UNDO", "msg-out");
+  },
+
+  redo: () => {
+    sendMessage(MESSAGE_TYPES.REDO, new Uint8Array());
+    logMessage(">>", "GOL: REDO", "msg-out");
+  },
+};
+
+// Whether this client last started the room's soup search or stopped it
+let soupSearching = false;
+
+// Bot ids, mirrors BotKind::ALL on the server
+const BOT_NAMES = ["chaos", "gardener"];
+// Ids of the bots running in the room, from the last BOT_STATUS
+let runningBots = new Set();
+
+function toggleBot(id) {
+  const running = runningBots.has(id);
+  const msgType = running ? MESSAGE_TYPES.STOP_BOT : MESSAGE_TYPES.START_BOT;
+  sendMessage(msgType, new Uint8Array([id]));
+  logMessage(
+    ">>",
+    `SIM: ${running ? "STOP_BOT" : "START_BOT"} ${BOT_NAMES[id]}`,
+    "msg-out",
+  );
+}
+
+const simulation = {
+  pause: () => {
+    sendMessage(MESSAGE_TYPES.PAUSE_SIMULATION, new Uint8Array());
+    logMessage(">>", "SIM: PAUSE_SIMULATION", "msg-out");
+  },
+
+  resume: () => {
+    sendMessage(MESSAGE_TYPES.RESUME_SIMULATION, new Uint8Array());
+    logMessage(">>", "SIM: RESUME_SIMULATION", "msg-out");
+  },
+
+  set_speed: (intervalMs) => {
+    const clamped = Math.min(
+      MAX_TICK_INTERVAL_MS,
+      Math.max(MIN_TICK_INTERVAL_MS, Math.round(intervalMs)),
+    );
+    const payload = new Uint8Array(4);
+    new DataView(payload.buffer).setUint32(0, clamped, false); // big-endian
+    sendMessage(MESSAGE_TYPES.SET_SIMULATION_SPEED, payload);
+    logMessage(">>", `SIM: SET_SIMULATION_SPEED ${clamped}ms`, "msg-out");
+  },
+
+  toggle_autoplay: () => {
+    sendMessage(MESSAGE_TYPES.SET_AUTOPLAY, new Uint8Array([autoplay ? 0 : 1]));
+    logMessage(">>", `SIM: SET_AUTOPLAY ${autoplay ? "off" : "on"}`, "msg-out");
+  },
+
+  toggle_soup_search: () => {
+    soupSearching = !soupSearching;
+    const msgType = soupSearching
+      ? MESSAGE_TYPES.START_SOUP_SEARCH
+      : MESSAGE_TYPES.STOP_SOUP_SEARCH;
+    sendMessage(msgType, new Uint8Array());
+    logMessage(
+      ">>",
+      `SIM: ${soupSearching ? "START_SOUP_SEARCH" : "STOP_SOUP_SEARCH"}`,
+      "msg-out",
+    );
+  },
+
+  toggle_chaos_bot: () => toggleBot(0),
+  toggle_gardener_bot: () => toggleBot(1),
+
+  faster: () => simulation.set_speed(tickIntervalMs / 2),
+  slower: () => simulation.set_speed(tickIntervalMs * 2),
 };
 
+function handleSimulationStatus(payload) {
+  if (payload.length !== 10) {
+    logMessage(
+      "!",
+      `Invalid simulation status size: ${payload.length}`,
+      "msg-error",
+    );
+    return;
+  }
+
+  const paused = payload[0] === 1;
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  tickIntervalMs = view.getUint32(1, false);
+  const appliedIntervalMs = view.getUint32(5, false);
+  autoplay = payload[9] === 1;
+  const throttled =
+    appliedIntervalMs > tickIntervalMs
+      ? ` (throttled to ${appliedIntervalMs}ms)`
+      : "";
+  logMessage(
+    "<<",
+    `Simulation ${paused ? "paused" : "running"}, autoplay ${autoplay ? "on" : "off"} at ${tickIntervalMs}ms/tick${throttled}`,
+    "msg-in",
+  );
+}
+
 const mlp = {
+  // Stroke order: 0 as generated, 1 coarse to fine, 2 random regions
   create_new_mlp: () => {
-    sendMessage(MESSAGE_TYPES.CREATE_NEW_MLP_PAINTING, new Uint8Array());
-    logMessage(">>", "MLP: CREATE_NEW_MLP_PAINTING", "msg-out");
+    const order = Number(document.getElementById("stroke-order").value);
+    sendMessage(MESSAGE_TYPES.CREATE_NEW_MLP_PAINTING, new Uint8Array([order]));
+    logMessage(">>", `MLP: CREATE_NEW_MLP_PAINTING order ${order}`, "msg-out");
   },
 
   advance_mlp: () => {
     sendMessage(MESSAGE_TYPES.ADVANCE_MLP_PAINTING, new Uint8Array());
     logMessage(">>", "MLP: ADVANCE_MLP_PAINTING", "msg-out");
   },
+
+  paint_from_generation: () => {
+    sendMessage(MESSAGE_TYPES.PAINT_FROM_GENERATION, new Uint8Array());
+    logMessage(">>", "MLP: PAINT_FROM_GENERATION", "msg-out");
+  },
+};
+
+const brain = {
+  create_new_brain: () => {
+    sendMessage(MESSAGE_TYPES.CREATE_NEW_BRAIN_GENERATION, new Uint8Array());
+    logMessage(">>", "BRAIN: CREATE_NEW_BRAIN_GENERATION", "msg-out");
+  },
+
+  advance_brain: () => {
+    sendMessage(MESSAGE_TYPES.ADVANCE_BRAIN_GENERATION, new Uint8Array());
+    logMessage(">>", "BRAIN: ADVANCE_BRAIN_GENERATION", "msg-out");
+  },
+};
+
+const immigration = {
+  create_new_immigration: () => {
+    sendMessage(MESSAGE_TYPES.CREATE_NEW_IMMIGRATION_GENERATION, new Uint8Array());
+    logMessage(">>", "IMMIGRATION: CREATE_NEW_IMMIGRATION_GENERATION", "msg-out");
+  },
+
+  advance_immigration: () => {
+    sendMessage(MESSAGE_TYPES.ADVANCE_IMMIGRATION_GENERATION, new Uint8Array());
+    logMessage(">>", "IMMIGRATION: ADVANCE_IMMIGRATION_GENERATION", "msg-out");
+  },
+};
+
+const reaction = {
+  create_new_reaction: () => {
+    sendMessage(MESSAGE_TYPES.CREATE_NEW_REACTION, new Uint8Array());
+    logMessage(">>", "REACTION: CREATE_NEW_REACTION", "msg-out");
+  },
+
+  advance_reaction: () => {
+    sendMessage(MESSAGE_TYPES.ADVANCE_REACTION, new Uint8Array());
+    logMessage(">>", "REACTION: ADVANCE_REACTION", "msg-out");
+  },
+};
+
+const boids = {
+  reset_boids: () => {
+    sendMessage(MESSAGE_TYPES.RESET_BOIDS, new Uint8Array());
+    logMessage(">>", "BOIDS: RESET_BOIDS", "msg-out");
+  },
+};
+
+// Paddle claimed with CLAIM_PADDLE, the arrow keys steer it while set
+let paddleSide = null;
+const PADDLE_DIRECTIONS = { ArrowUp: -1, ArrowDown: 1 };
+
+const pong = {
+  claim_paddle: () => {
+    sendMessage(MESSAGE_TYPES.CLAIM_PADDLE, new Uint8Array());
+    logMessage(">>", "PONG: CLAIM_PADDLE", "msg-out");
+  },
+};
+
+function sendPaddleMove(direction) {
+  sendMessage(MESSAGE_TYPES.MOVE_PADDLE, new Uint8Array([direction & 0xff]));
+}
+
+// Set once JOIN_SNAKE is sent, the arrow keys steer the snake while no paddle is claimed
+let steeringSnake = false;
+const SNAKE_DIRECTIONS = { ArrowUp: 0, ArrowDown: 1, ArrowLeft: 2, ArrowRight: 3 };
+
+const snake = {
+  join_snake: () => {
+    sendMessage(MESSAGE_TYPES.JOIN_SNAKE, new Uint8Array());
+    logMessage(">>", "SNAKE: JOIN_SNAKE", "msg-out");
+    steeringSnake = true;
+  },
 };
 
 const mapper = {
@@ -252,13 +1740,58 @@ const mapper = {
   k: gol.kill_random_cell,
   e: gol.kill_all_cells,
   s: gol.step_generation,
+  g: gol.seed_from_painting,
+  Z: gol.undo,
+  Y: gol.redo,
 
   m: mlp.create_new_mlp,
   b: mlp.advance_mlp,
+  p: mlp.paint_from_generation,
+
+  w: brain.create_new_brain,
+  v: brain.advance_brain,
+
+  h: immigration.create_new_immigration,
+  u: immigration.advance_immigration,
+
+  d: reaction.create_new_reaction,
+  f: reaction.advance_reaction,
+
+  j: boids.reset_boids,
+
+  l: pong.claim_paddle,
+
+  i: snake.join_snake,
+
+  z: simulation.pause,
+  r: simulation.resume,
+  o: simulation.toggle_autoplay,
+  q: simulation.toggle_soup_search,
+  C: simulation.toggle_chaos_bot,
+  G: simulation.toggle_gardener_bot,
+  "+": simulation.faster,
+  "-": simulation.slower,
 
   c: clearCanvas,
+  t: toggleInputTarget,
 };
 
+function toggleInputTarget() {
+  inputTarget =
+    inputTarget === INPUT_TARGETS.GOL ? INPUT_TARGETS.MLP : INPUT_TARGETS.GOL;
+  const name = inputTarget === INPUT_TARGETS.GOL ? "GOL" : "MLP";
+  logMessage(">>", `Input target: ${name}`, "msg-out");
+}
+
+function sendInputEvent(key, pressed) {
+  const payload = new Uint8Array(4);
+  const view = new DataView(payload.buffer);
+  payload[0] = inputTarget;
+  view.setUint16(1, key, false); // big-endian
+  payload[3] = pressed ? 1 : 0;
+  sendMessage(MESSAGE_TYPES.INPUT_EVENT, payload);
+}
+
 function clearCanvas() {
   ctx.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
   cellColors.clear();
@@ -282,9 +1815,64 @@ window.addEventListener("keydown", (e) => {
   if (isTypingInInput()) {
     return;
   }
+  if (paddleSide !== null && PADDLE_DIRECTIONS[e.key] !== undefined) {
+    e.preventDefault();
+    if (!e.repeat) {
+      sendPaddleMove(PADDLE_DIRECTIONS[e.key]);
+    }
+    return;
+  }
+  if (steeringSnake && SNAKE_DIRECTIONS[e.key] !== undefined) {
+    e.preventDefault();
+    sendMessage(MESSAGE_TYPES.TURN_SNAKE, new Uint8Array([SNAKE_DIRECTIONS[e.key]]));
+    return;
+  }
+  const inputKey = INPUT_KEYS[e.key];
+  if (inputKey !== undefined) {
+    e.preventDefault();
+    sendInputEvent(inputKey, true);
+    return;
+  }
   mapper[e.key]?.();
 });
 
+window.addEventListener("keyup", (e) => {
+  if (isTypingInInput()) {
+    return;
+  }
+  if (paddleSide !== null && PADDLE_DIRECTIONS[e.key] !== undefined) {
+    sendPaddleMove(0);
+    return;
+  }
+  if (steeringSnake && SNAKE_DIRECTIONS[e.key] !== undefined) {
+    return;
+  }
+  const inputKey = INPUT_KEYS[e.key];
+  if (inputKey !== undefined) {
+    sendInputEvent(inputKey, false);
+  }
+});
+
+// Poll connected gamepads and forward button transitions as input events
+const gamepadButtonState = new Map();
+
+function pollGamepads() {
+  for (const pad of navigator.getGamepads?.() ?? []) {
+    if (!pad) continue;
+    for (const [index, key] of Object.entries(GAMEPAD_BUTTONS)) {
+      const pressed = pad.buttons[index]?.pressed ?? false;
+      const stateKey = `${pad.index}:${index}`;
+      if ((gamepadButtonState.get(stateKey) ?? false) !== pressed) {
+        gamepadButtonState.set(stateKey, pressed);
+        sendInputEvent(key, pressed);
+      }
+    }
+  }
+  requestAnimationFrame(pollGamepads);
+}
+
+requestAnimationFrame(pollGamepads);
+
 window.addEventListener("click", (e) => {
   const id = e.target.id;
   mapper[id]?.();
@@ -382,6 +1970,73 @@ function drawFrame(payload) {
   logMessage("<<", `Drew frame: ${frameWidth}x${frameHeight}`, "msg-in");
 }
 
+// Redraws the cells of a region, the rest of the canvas keeps the previous frame
+function drawRegion(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const [x, y, width, height] = [0, 2, 4, 6].map((offset) => view.getUint16(offset, false));
+  if (payload.length !== 8 + width * height * 3) {
+    logMessage("!", `Invalid region payload size: ${payload.length}`, "msg-error");
+    return;
+  }
+
+  for (let row = 0; row < height; row++) {
+    for (let col = 0; col < width; col++) {
+      const offset = 8 + (row * width + col) * 3;
+      const pixel = new Uint8Array([
+        (x + col) >> 8, (x + col) & 0xff, (y + row) >> 8, (y + row) & 0xff,
+        ...payload.slice(offset, offset + 3),
+      ]);
+      drawCell(pixel);
+      updateFrameBit(pixel);
+    }
+  }
+  logMessage("<<", `Drew region: ${width}x${height} at (${x}, ${y})`, "msg-in");
+}
+
+// Viewport tiles received at the current zoom, "column,row" to RGB data. Kept so panning
+// redraws them without the server sending them again.
+const tileStore = new Map();
+const TILE_SIDE = 32;
+
+function drawTiles(payload) {
+  const view = new DataView(payload.buffer, payload.byteOffset);
+  const count = view.getUint16(0, false);
+  const tileBytes = TILE_SIDE * TILE_SIDE * 3;
+  if (payload.length !== 2 + count * (4 + tileBytes)) {
+    logMessage("!", `Invalid tiles payload size: ${payload.length}`, "msg-error");
+    return;
+  }
+
+  for (let i = 0; i < count; i++) {
+    const offset = 2 + i * (4 + tileBytes);
+    const column = view.getUint16(offset, false);
+    const row = view.getUint16(offset + 2, false);
+    tileStore.set(`${column},${row}`, payload.slice(offset + 4, offset + 4 + tileBytes));
+    drawTile(column, row);
+  }
+  if (count > 0) logMessage("<<", `Drew ${count} tiles`, "msg-in");
+}
+
+// Draws the part of a stored tile inside the viewport
+function drawTile(column, row) {
+  const rgbData = tileStore.get(`${column},${row}`);
+  if (!rgbData || !viewport) return;
+  // Tiles line up with the board, the viewport starts at a whole pixel
+  const left = column * TILE_SIDE - Math.floor(viewport.x / viewport.zoom);
+  const top = row * TILE_SIDE - Math.floor(viewport.y / viewport.zoom);
+  for (let y = 0; y < TILE_SIDE; y++) {
+    for (let x = 0; x < TILE_SIDE; x++) {
+      const [col, canvasRow] = [left + x, top + y];
+      if (col < 0 || col >= GRID_COLS || canvasRow < 0 || canvasRow >= GRID_ROWS) continue;
+      const offset = (y * TILE_SIDE + x) * 3;
+      drawCell(new Uint8Array([
+        col >> 8, col & 0xff, canvasRow >> 8, canvasRow & 0xff,
+        ...rgbData.slice(offset, offset + 3),
+      ]));
+    }
+  }
+}
+
 function drawGridLines() {
   return;
   // ctx.strokeStyle = "#eee";
@@ -407,6 +2062,7 @@ drawGridLines();
 
 // === Protocol encoding/decoding ===
 function encodeMessage(msgType, flags, payload) {
+  if (wasmCore) return wasmCore.encodeMessage(msgType, flags, payload);
   const version = 1;
   const length = payload.length;
   const buffer = new Uint8Array(7 + length);
@@ -420,6 +2076,21 @@ function encodeMessage(msgType, flags, payload) {
 }
 
 function decodeMessage(data) {
+  if (wasmCore) {
+    try {
+      const decoded = wasmCore.decodeMessage(data);
+      const msg = {
+        version: decoded.version,
+        msg_type: decoded.msgType,
+        flags: decoded.flags,
+        payload: decoded.payload,
+      };
+      decoded.free();
+      return msg;
+    } catch {
+      return {};
+    }
+  }
   if (data.length < 7) return {};
   const version = data[0];
   const msgType = data[1];
@@ -429,8 +2100,34 @@ function decodeMessage(data) {
   return { version, msg_type: msgType, flags, payload };
 }
 
+// Protocol versions this client speaks, offered in its HELLO
+const PROTOCOL_VERSIONS = [1];
+
+// HELLO offering the compression this browser decodes, the protocol versions it speaks and
+// the topics ticked in the subscriptions panel
+function sendHello() {
+  const codecs = "DecompressionStream" in window ? CODEC_DEFLATE : 0;
+  let topics = 0;
+  document.querySelectorAll("#subscriptions input[data-topic]").forEach((checkbox) => {
+    if (checkbox.checked) topics |= TOPICS[checkbox.dataset.topic];
+  });
+  const hello = new TextEncoder().encode("hello");
+  sendMessage(
+    MESSAGE_TYPES.HELLO,
+    new Uint8Array([...hello, codecs, PROTOCOL_VERSIONS.length, ...PROTOCOL_VERSIONS, topics]),
+  );
+}
+
 function sendMessage(msgType, payload) {
-  const flags = 0x01 | 0x04; // FLAG_START | FLAG_END
+  if (eventStream) return;
+  // Switches the room over first, like pressing a pattern's key always did
+  const mode = MESSAGE_MODES.get(msgType);
+  if (mode && mode !== activeMode) {
+    activeMode = mode;
+    socket.send(encodeMessage(MESSAGE_TYPES.SWITCH_MODE, 0, new Uint8Array([MODES.indexOf(mode)])));
+  }
+  // Sent whole, messages too big for one frame go in parts with the FLAG_FRAGMENT_* bits
+  const flags = 0;
   const msg = encodeMessage(msgType, flags, payload);
   socket.send(msg);
 }